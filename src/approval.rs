@@ -0,0 +1,416 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+
+use crate::backend::KingClient;
+use crate::diff;
+use crate::plan::UpdatePlan;
+use crate::state::state_dir;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Longest question text sent to the chat.
+const MAX_QUESTION_CHARS: usize = 800;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A reviewer's verdict on a held plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// Deliver the whole plan.
+    Approve,
+    /// Deliver the plan to the listed repos only.
+    Modify,
+    /// Deliver nothing and do not ask about these files again.
+    Reject,
+}
+
+/// The structured answer to a question, carried by a later pipeline event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub question_id: String,
+    pub decision: Decision,
+    /// Repos to deliver to, for [`Decision::Modify`].
+    pub repos: Vec<String>,
+}
+
+/// A question registered with the king's chat about a held plan.  Its id is
+/// the id of the stored plan.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Question {
+    pub question_id: String,
+    pub text: String,
+    pub repos: Vec<String>,
+    /// `{ "crate", "version" }` of every bump in the plan.
+    pub bumps: Vec<Value>,
+    /// `{ "repo", "file", "diff", "truncated" }` of every file, with the
+    /// diff cut to a snippet.
+    pub files: Vec<Value>,
+}
+
+/// A file of a held plan, by content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeldFile {
+    pub repo: String,
+    pub file: String,
+    pub sha256: String,
+}
+
+/// Questions waiting for an answer and the files a reviewer rejected, so a
+/// held update is asked about once rather than on every run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalStore {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Question id → files it covers.
+    pub open: Vec<(String, Vec<HeldFile>)>,
+    pub rejected: Vec<HeldFile>,
+}
+
+// ─── Answers ──────────────────────────────────────────────────────────────────
+
+impl Answer {
+    /// Reads `question_id`, `decision` and (for `modify`) `repos` from
+    /// pipeline metadata.
+    pub fn from_metadata(metadata: &Value) -> Result<Self> {
+        let question_id = metadata
+            .get("question_id")
+            .and_then(Value::as_str)
+            .context("answer needs metadata.question_id")?
+            .to_string();
+        let decision =
+            serde_json::from_value(metadata.get("decision").cloned().unwrap_or_default())
+                .context("answer needs metadata.decision: approve, modify or reject")?;
+        let repos: Vec<String> = metadata
+            .get("repos")
+            .and_then(Value::as_array)
+            .map(|repos| {
+                repos
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        anyhow::ensure!(
+            decision != Decision::Modify || !repos.is_empty(),
+            "a modify answer needs metadata.repos"
+        );
+        Ok(Self {
+            question_id,
+            decision,
+            repos,
+        })
+    }
+
+    /// The part of `plan` the answer approves, with its metadata targeted at
+    /// the approved repos.
+    pub fn select(&self, mut plan: UpdatePlan) -> Result<UpdatePlan> {
+        match self.decision {
+            Decision::Reject => anyhow::bail!("question {} was rejected", self.question_id),
+            Decision::Approve => {}
+            Decision::Modify => {
+                for repo in &self.repos {
+                    anyhow::ensure!(
+                        plan.changes.iter().any(|c| c.repo == *repo),
+                        "{repo} is not part of plan {}",
+                        plan.plan_id
+                    );
+                }
+                plan.changes.retain(|c| self.repos.contains(&c.repo));
+            }
+        }
+        plan.metadata["repos"] = json!(repos(&plan));
+        Ok(plan)
+    }
+}
+
+// ─── Questions ────────────────────────────────────────────────────────────────
+
+impl Question {
+    /// "Apply evo-agent-sdk 0.3.0 to evo-king, evo-runner? Breaking changes:
+    /// …" for the held `plan`; `risk` is the analysis summary.
+    pub fn for_plan(plan: &UpdatePlan, risk: Option<&str>) -> Self {
+        let mut bumps: Vec<Value> = Vec::new();
+        let mut breaking: Vec<String> = Vec::new();
+        for change in &plan.changes {
+            for report in &change.versions {
+                let bump = json!({ "crate": report.crate_name, "version": report.latest });
+                if !bumps.contains(&bump) {
+                    bumps.push(bump);
+                }
+            }
+            for note in &change.migration_notes {
+                let line = format!("{} {}: {}", note.crate_name, note.version, note.heading);
+                if !breaking.contains(&line) {
+                    breaking.push(line);
+                }
+            }
+        }
+        let repos = repos(plan);
+        let names: Vec<String> = bumps
+            .iter()
+            .map(|b| {
+                format!(
+                    "{} {}",
+                    b["crate"].as_str().unwrap_or(""),
+                    b["version"].as_str().unwrap_or("")
+                )
+            })
+            .collect();
+        let mut text = format!("Apply {} to {}?", names.join(", "), repos.join(", "));
+        if !breaking.is_empty() {
+            text.push_str(&format!(" Breaking changes: {}.", breaking.join("; ")));
+        }
+        if let Some(risk) = risk.filter(|r| !r.is_empty()) {
+            text.push_str(&format!(" Risk: {risk}"));
+        }
+        if text.chars().count() > MAX_QUESTION_CHARS {
+            text = text.chars().take(MAX_QUESTION_CHARS - 1).collect();
+            text.push('…');
+        }
+        let files = plan
+            .changes
+            .iter()
+            .map(|change| {
+                let diff =
+                    diff::snippet(&change.diff, diff::SNIPPET_LINES, diff::SNIPPET_LINE_CHARS);
+                json!({
+                    "repo": change.repo,
+                    "file": change.file,
+                    "truncated": diff != change.diff,
+                    "diff": diff,
+                })
+            })
+            .collect();
+        Self {
+            question_id: plan.plan_id.clone(),
+            text,
+            repos,
+            bumps,
+            files,
+        }
+    }
+}
+
+/// POSTs `question` to the king's `/admin/chat/questions` endpoint, with the
+/// shape of the answer event the king should start once the human replies.
+pub async fn ask(king: &dyn KingClient, question: &Question) -> Result<()> {
+    king.post_ok(
+        "/admin/chat/questions",
+        &json!({
+            "source": "update-agent",
+            "question": question,
+            "choices": ["approve", "modify", "reject"],
+            "answer_event": {
+                "trigger": "chat_answer",
+                "metadata": {
+                    "action": "answer",
+                    "question_id": question.question_id,
+                    "decision": "approve | modify | reject",
+                    "repos": "for modify: the repos to update",
+                },
+            },
+        }),
+    )
+    .await
+    .map(drop)
+}
+
+// ─── Approval store ───────────────────────────────────────────────────────────
+
+impl ApprovalStore {
+    /// Default location inside [`state_dir`].
+    pub fn default_path() -> PathBuf {
+        state_dir().join("approvals.json")
+    }
+
+    /// The store at `path`; empty when the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut store: Self = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parse approvals {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        store.path = path.to_path_buf();
+        Ok(store)
+    }
+
+    /// Whether `file` is covered by an open question or was rejected.
+    pub fn asked(&self, file: &HeldFile) -> bool {
+        self.rejected.contains(file) || self.open.iter().any(|(_, files)| files.contains(file))
+    }
+
+    /// Whether question `question_id` still waits for its answer.
+    pub fn is_open(&self, question_id: &str) -> bool {
+        self.open.iter().any(|(id, _)| id == question_id)
+    }
+
+    /// Records question `question_id` about `files` as asked.
+    pub fn open(&mut self, question_id: &str, files: Vec<HeldFile>) {
+        self.open.push((question_id.to_string(), files));
+    }
+
+    /// Closes question `question_id`; with [`Decision::Reject`] its files are
+    /// not asked about again.
+    pub fn answer(&mut self, question_id: &str, decision: Decision) {
+        let Some(index) = self.open.iter().position(|(id, _)| id == question_id) else {
+            return;
+        };
+        let (_, files) = self.open.remove(index);
+        if decision == Decision::Reject {
+            self.rejected.extend(files);
+        }
+    }
+
+    /// Writes the store back to disk, creating the state dir if needed.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text = serde_json::to_string_pretty(self).context("serialize approvals")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write approvals {}", self.path.display()))
+    }
+}
+
+/// Repos of `plan`, in plan order.
+fn repos(plan: &UpdatePlan) -> Vec<String> {
+    let mut repos: Vec<String> = Vec::new();
+    for change in &plan.changes {
+        if !repos.contains(&change.repo) {
+            repos.push(change.repo.clone());
+        }
+    }
+    repos
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changelog::MigrationNote;
+    use crate::plan::PlannedChange;
+    use crate::versions::VersionReport;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn change(repo: &str) -> PlannedChange {
+        PlannedChange {
+            repo: repo.to_string(),
+            file: "Cargo.toml".to_string(),
+            base_sha256: "aa".to_string(),
+            sha256: format!("{repo}-bb"),
+            diff: diff::unified_diff(
+                "Cargo.toml",
+                "evo-agent-sdk = \"0.2\"\n",
+                "evo-agent-sdk = \"0.3.0\"\n",
+            ),
+            original: String::new(),
+            content: String::new(),
+            commit_message: "chore(deps): bump evo-agent-sdk to 0.3.0".to_string(),
+            versions: vec![VersionReport::new("evo-agent-sdk", "0.2", "0.3.0")],
+            migration_notes: vec![MigrationNote {
+                crate_name: "evo-agent-sdk".to_string(),
+                version: "0.3.0".to_string(),
+                heading: "BREAKING: AgentHandler::on_pipeline takes ctx by value".to_string(),
+                text: String::new(),
+            }],
+            requires_pr: false,
+            verified_sha256: None,
+            part_of: None,
+        }
+    }
+
+    fn plan() -> UpdatePlan {
+        UpdatePlan {
+            plan_id: "run-7".to_string(),
+            created_at: 1,
+            config_version: "builtin".to_string(),
+            metadata: json!({ "commit_mode": "pr" }),
+            versions: BTreeMap::new(),
+            changes: vec![change("evo-king"), change("evo-runner")],
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_question_text() {
+        let question = Question::for_plan(&plan(), Some("High risk."));
+        assert_eq!(question.question_id, "run-7");
+        assert_eq!(question.repos, vec!["evo-king", "evo-runner"]);
+        assert_eq!(
+            question.text,
+            "Apply evo-agent-sdk 0.3.0 to evo-king, evo-runner? Breaking changes: \
+             evo-agent-sdk 0.3.0: BREAKING: AgentHandler::on_pipeline takes ctx by value. \
+             Risk: High risk."
+        );
+        assert_eq!(question.files.len(), 2);
+        assert_eq!(question.files[0]["truncated"], false);
+        assert!(
+            question.files[0]["diff"]
+                .as_str()
+                .unwrap()
+                .contains("+evo-agent-sdk = \"0.3.0\"\n")
+        );
+    }
+
+    #[test]
+    fn test_answers_select_the_plan() {
+        let modify = Answer::from_metadata(&json!({
+            "action": "answer",
+            "question_id": "run-7",
+            "decision": "modify",
+            "repos": ["evo-runner"],
+        }))
+        .unwrap();
+        let selected = modify.select(plan()).unwrap();
+        assert_eq!(selected.changes.len(), 1);
+        assert_eq!(selected.metadata["repos"], json!(["evo-runner"]));
+
+        let approve =
+            Answer::from_metadata(&json!({ "question_id": "run-7", "decision": "approve" }))
+                .unwrap();
+        assert_eq!(approve.select(plan()).unwrap().changes.len(), 2);
+
+        let unknown = Answer {
+            repos: vec!["evo-ghost".to_string()],
+            ..modify
+        };
+        assert!(unknown.select(plan()).is_err());
+        assert!(
+            Answer::from_metadata(&json!({ "question_id": "run-7", "decision": "modify" }))
+                .is_err()
+        );
+        assert!(
+            Answer::from_metadata(&json!({ "question_id": "run-7", "decision": "maybe" })).is_err()
+        );
+    }
+
+    #[test]
+    fn test_store_asks_once() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("approvals.json");
+        let file = HeldFile {
+            repo: "evo-king".to_string(),
+            file: "Cargo.toml".to_string(),
+            sha256: "bb".to_string(),
+        };
+        let mut store = ApprovalStore::load(&path).unwrap();
+        assert!(!store.asked(&file));
+        store.open("run-7", vec![file.clone()]);
+        store.save().unwrap();
+
+        let mut store = ApprovalStore::load(&path).unwrap();
+        assert!(store.is_open("run-7"));
+        assert!(store.asked(&file));
+        store.answer("run-7", Decision::Reject);
+        assert!(!store.is_open("run-7"));
+        assert!(store.asked(&file));
+    }
+}
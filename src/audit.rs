@@ -0,0 +1,287 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+use crate::net::apply_to_command;
+use crate::validate::{scratch_manifest_dir, uses_workspace_inheritance};
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A RustSec advisory matched against a locked package.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Advisory {
+    /// Advisory id, e.g. `"RUSTSEC-2024-0001"`.
+    pub id: String,
+    /// Affected package name.
+    pub package: String,
+    /// Locked version of the affected package.
+    pub version: String,
+    /// One-line advisory title.
+    pub title: String,
+}
+
+/// `cargo audit` results before and after a manifest update.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    /// Advisories present in the repo's current `Cargo.lock`.
+    pub baseline: Vec<Advisory>,
+    /// Advisories present once the patched manifest is resolved.
+    pub patched: Vec<Advisory>,
+    /// Advisories the update would newly introduce.
+    pub introduced: Vec<Advisory>,
+    /// Advisories against an updated crate that the update does not fix.
+    pub unfixed: Vec<Advisory>,
+    /// Baseline advisories the update resolves.
+    pub fixed: Vec<Advisory>,
+}
+
+impl AuditReport {
+    /// Compares `baseline` and `patched`; `updated` are the crates being bumped.
+    pub fn compare(baseline: Vec<Advisory>, patched: Vec<Advisory>, updated: &[&str]) -> Self {
+        let same = |a: &Advisory, b: &Advisory| a.id == b.id && a.package == b.package;
+        let introduced = patched
+            .iter()
+            .filter(|p| !baseline.iter().any(|b| same(b, p)))
+            .cloned()
+            .collect();
+        let unfixed = patched
+            .iter()
+            .filter(|p| updated.contains(&p.package.as_str()))
+            .filter(|p| baseline.iter().any(|b| same(b, p)))
+            .cloned()
+            .collect();
+        let fixed = baseline
+            .iter()
+            .filter(|b| !patched.iter().any(|p| same(b, p)))
+            .cloned()
+            .collect();
+        Self {
+            baseline,
+            patched,
+            introduced,
+            unfixed,
+            fixed,
+        }
+    }
+
+    /// `true` when the update must be held for review instead of committed.
+    pub fn hold_for_review(&self) -> bool {
+        !self.introduced.is_empty() || !self.unfixed.is_empty()
+    }
+}
+
+// ─── cargo-audit output ───────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct AuditOutput {
+    vulnerabilities: Vulnerabilities,
+}
+
+#[derive(Debug, Deserialize)]
+struct Vulnerabilities {
+    #[serde(default)]
+    list: Vec<Vulnerability>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Vulnerability {
+    advisory: AdvisoryMeta,
+    package: PackageMeta,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvisoryMeta {
+    id: String,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageMeta {
+    name: String,
+    version: String,
+}
+
+/// Parses `cargo audit --json` output into advisories.
+fn parse_audit_json(stdout: &str) -> Result<Vec<Advisory>> {
+    let out: AuditOutput = serde_json::from_str(stdout).context("parse cargo audit JSON")?;
+    Ok(out
+        .vulnerabilities
+        .list
+        .into_iter()
+        .map(|v| Advisory {
+            id: v.advisory.id,
+            package: v.package.name,
+            version: v.package.version,
+            title: v.advisory.title,
+        })
+        .collect())
+}
+
+// ─── Sandbox audit ────────────────────────────────────────────────────────────
+
+/// Whether the `cargo audit` subcommand is installed (checked once).
+pub fn cargo_audit_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("cargo")
+            .args(["audit", "--version"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Audits a patched manifest against the repo's current lockfile.
+///
+/// The patched manifest is written to a scratch dir (with a stub `src/lib.rs`)
+/// next to a copy of `original_lock`; `cargo metadata` then re-resolves the
+/// lock minimally, exactly as a developer's next build would.  Both lockfiles
+/// are run through `cargo audit` and compared.
+///
+/// Returns `Ok(None)` when the audit cannot run: `cargo audit` is missing or
+/// the manifest inherits from a workspace that is not available here.
+pub fn audit_update(
+    original_lock: Option<&Path>,
+    patched_manifest: &str,
+    updated: &[&str],
+) -> Result<Option<AuditReport>> {
+    if !cargo_audit_available() {
+        return Ok(None);
+    }
+    if uses_workspace_inheritance(patched_manifest) {
+        debug!("manifest uses workspace inheritance — skipping cargo audit");
+        return Ok(None);
+    }
+
+    let dir = scratch_manifest_dir(patched_manifest, original_lock)?;
+    let manifest = dir.path().join("Cargo.toml");
+    let lock = dir.path().join("Cargo.lock");
+
+    let resolve =
+        apply_to_command(Command::new("cargo").args(["metadata", "--format-version", "1"]))
+            .arg("--manifest-path")
+            .arg(&manifest)
+            .output()
+            .context("run cargo metadata in audit sandbox")?;
+    if !resolve.status.success() {
+        anyhow::bail!(
+            "cannot resolve patched manifest: {}",
+            String::from_utf8_lossy(&resolve.stderr).trim()
+        );
+    }
+
+    let baseline = match original_lock {
+        Some(path) => run_cargo_audit(path)?,
+        None => Vec::new(),
+    };
+    let patched = run_cargo_audit(&lock)?;
+    Ok(Some(AuditReport::compare(baseline, patched, updated)))
+}
+
+/// Runs `cargo audit --json` on `lockfile`.  Exit code 1 only means
+/// vulnerabilities were found, so the JSON on stdout is parsed regardless.
+fn run_cargo_audit(lockfile: &Path) -> Result<Vec<Advisory>> {
+    let output = apply_to_command(Command::new("cargo").args(["audit", "--json", "--file"]))
+        .arg(lockfile)
+        .output()
+        .context("run cargo audit")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_audit_json(&stdout).inspect_err(|_| {
+        warn!(
+            lockfile = %lockfile.display(),
+            stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+            "cargo audit produced no report"
+        );
+    })
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(id: &str, package: &str) -> Advisory {
+        Advisory {
+            id: id.to_string(),
+            package: package.to_string(),
+            version: "1.0.0".to_string(),
+            title: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_audit_json() {
+        let json = r#"{
+            "database": {},
+            "vulnerabilities": {
+                "found": true,
+                "count": 1,
+                "list": [{
+                    "advisory": { "id": "RUSTSEC-2024-0001", "package": "evo-common", "title": "Bad thing" },
+                    "package": { "name": "evo-common", "version": "0.3.0" }
+                }]
+            },
+            "warnings": {}
+        }"#;
+        let found = parse_audit_json(json).unwrap();
+        assert_eq!(
+            found,
+            vec![Advisory {
+                id: "RUSTSEC-2024-0001".to_string(),
+                package: "evo-common".to_string(),
+                version: "0.3.0".to_string(),
+                title: "Bad thing".to_string(),
+            }]
+        );
+        assert!(parse_audit_json("error: no lockfile").is_err());
+    }
+
+    #[test]
+    fn test_compare_detects_introduced() {
+        let report = AuditReport::compare(
+            vec![],
+            vec![advisory("RUSTSEC-1", "tokio")],
+            &["evo-common"],
+        );
+        assert_eq!(report.introduced.len(), 1);
+        assert!(report.unfixed.is_empty());
+        assert!(report.hold_for_review());
+    }
+
+    #[test]
+    fn test_compare_detects_unfixed_updated_crate() {
+        let report = AuditReport::compare(
+            vec![
+                advisory("RUSTSEC-2", "evo-common"),
+                advisory("RUSTSEC-3", "time"),
+            ],
+            vec![
+                advisory("RUSTSEC-2", "evo-common"),
+                advisory("RUSTSEC-3", "time"),
+            ],
+            &["evo-common"],
+        );
+        assert!(report.introduced.is_empty());
+        assert_eq!(report.unfixed, vec![advisory("RUSTSEC-2", "evo-common")]);
+        assert!(report.hold_for_review());
+    }
+
+    #[test]
+    fn test_compare_clean_update_passes() {
+        let report = AuditReport::compare(
+            vec![
+                advisory("RUSTSEC-2", "evo-common"),
+                advisory("RUSTSEC-3", "time"),
+            ],
+            vec![advisory("RUSTSEC-3", "time")],
+            &["evo-common"],
+        );
+        assert!(!report.hold_for_review());
+        assert_eq!(report.fixed, vec![advisory("RUSTSEC-2", "evo-common")]);
+    }
+}
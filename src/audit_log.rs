@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::backend::KingClient;
+use crate::state::state_dir;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// One applied dependency change, as recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix seconds when the change was applied.
+    pub timestamp: u64,
+    pub run_id: String,
+    pub repo: String,
+    pub file: String,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub from: String,
+    pub to: String,
+    /// Commit SHA (or local push description) for direct commits.
+    pub sha: Option<String>,
+    /// PR URL when the change was delivered as a pull request.
+    pub pr_url: Option<String>,
+    /// `GhCli`, `LocalGit`, `RestApi` or `PullRequest`.
+    pub strategy: String,
+}
+
+// ─── Audit log ────────────────────────────────────────────────────────────────
+
+/// Append-only JSONL record of every change the agent applied, kept apart
+/// from the per-run summary for compliance review.
+///
+/// Entries are only ever appended; the agent never rewrites or truncates the
+/// file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// A log at `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The log at `UPDATE_AUDIT_LOG` (default `$UPDATE_STATE_DIR/audit-log.jsonl`).
+    pub fn from_env() -> Self {
+        let path = std::env::var("UPDATE_AUDIT_LOG")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| state_dir().join("audit-log.jsonl"));
+        Self::new(&path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `entries`, one JSON object per line, creating the file (and
+    /// its directory) on first use.
+    pub fn append(&self, entries: &[AuditEntry]) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create audit log dir {}", parent.display()))?;
+        }
+        let mut lines = String::new();
+        for entry in entries {
+            lines.push_str(&serde_json::to_string(entry).context("serialize audit entry")?);
+            lines.push('\n');
+        }
+        // One write per run keeps a run's entries contiguous.
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(lines.as_bytes()))
+            .with_context(|| format!("append to audit log {}", self.path.display()))
+    }
+
+    /// Every entry recorded so far, oldest first (empty when the log does
+    /// not exist yet).
+    pub fn read(&self) -> Result<Vec<AuditEntry>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("read audit log {}", self.path.display()));
+            }
+        };
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("parse {} line {}", self.path.display(), idx + 1))
+            })
+            .collect()
+    }
+}
+
+/// Whether entries should also be sent to the king (`AUDIT_LOG_TO_KING`).
+pub fn ship_to_king_enabled() -> bool {
+    std::env::var("AUDIT_LOG_TO_KING").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// POSTs `entries` to the king's `/admin/update-audit` endpoint.
+pub async fn ship_to_king(king: &dyn KingClient, entries: &[AuditEntry]) -> Result<()> {
+    king.post_ok("/admin/update-audit", &json!(entries))
+        .await
+        .map(drop)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(to: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: 1_700_000_000,
+            run_id: "run-1".into(),
+            repo: "evo-king".into(),
+            file: "Cargo.toml".into(),
+            crate_name: "evo-common".into(),
+            from: "0.3".into(),
+            to: to.into(),
+            sha: Some("a1b2c3d".into()),
+            pr_url: None,
+            strategy: "GhCli".into(),
+        }
+    }
+
+    #[test]
+    fn test_append_only() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::new(&dir.path().join("logs/audit.jsonl"));
+        log.append(&[entry("0.4.0")]).unwrap();
+        log.append(&[]).unwrap();
+        log.append(&[entry("0.4.1"), entry("0.4.2")]).unwrap();
+
+        let text = std::fs::read_to_string(log.path()).unwrap();
+        let entries = log.read().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], entry("0.4.0"));
+        assert_eq!(entries[2].to, "0.4.2");
+        assert!(text.contains("\"crate\":\"evo-common\""));
+    }
+
+    #[test]
+    fn test_read_missing_log_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let log = AuditLog::new(&dir.path().join("none.jsonl"));
+        assert!(log.read().unwrap().is_empty());
+    }
+}
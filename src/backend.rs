@@ -0,0 +1,644 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::checksum::{ChecksumPins, sha256_hex, verify_crate};
+use crate::diff::diff_stats;
+use crate::fixtures::{Fixtures, RegistryHttp};
+use crate::git::{
+    CommitResult, CommitStrategy, FileChange, PushTarget, commit_changes, read_remote_file,
+};
+use crate::gomod;
+use crate::metadata::{self, CrateLinks};
+use crate::osv::{self, Vulnerability};
+use crate::ownership::{self, CrateOwners};
+use crate::package_json;
+use crate::publish_wait;
+use crate::pyproject;
+use crate::registry::Registry;
+use crate::toolchain;
+use crate::versions::{Lookup, latest_crate_version_with_retry};
+
+// ─── Traits ───────────────────────────────────────────────────────────────────
+
+/// Where latest versions (and their checksums) come from.
+#[async_trait]
+pub trait RegistryClient: Send + Sync {
+    /// Latest stable version of `crate_name` on `registry`, with retries.
+    async fn latest_version(&self, registry: &Registry, crate_name: &str) -> Lookup;
+
+    /// Checks the `.crate` of `crate_name@version` against the registry
+    /// index and `pins`; returns its hex checksum.
+    async fn verify_checksum(
+        &self,
+        registry: &Registry,
+        crate_name: &str,
+        version: &str,
+        pins: &ChecksumPins,
+    ) -> Result<String>;
+
+    /// `Ok` once cargo can resolve `crate_name@version` from `registry`.
+    async fn resolvable(&self, registry: &Registry, crate_name: &str, version: &str) -> Result<()>;
+
+    /// Version of the current stable Rust release.
+    async fn latest_toolchain(&self) -> Result<String>;
+
+    /// Known vulnerabilities of `crate_name@version` (OSV).
+    async fn vulnerabilities(&self, crate_name: &str, version: &str) -> Result<Vec<Vulnerability>>;
+
+    /// Repository, homepage and documentation links of `crate_name`.
+    async fn crate_links(&self, registry: &Registry, crate_name: &str) -> Result<CrateLinks>;
+
+    /// Owners and publishers of `crate_name`.
+    async fn owners(&self, registry: &Registry, crate_name: &str) -> Result<CrateOwners>;
+
+    /// Account that published `crate_name@version`, if recorded.
+    async fn publisher(
+        &self,
+        registry: &Registry,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<String>>;
+
+    /// Latest stable version of the Python package `package` on PyPI.
+    async fn latest_pypi_version(&self, package: &str) -> Result<String>;
+
+    /// Latest stable version of the npm package `package`.
+    async fn latest_npm_version(&self, package: &str) -> Result<String>;
+
+    /// Latest stable version (without `v`) of the Go module `module`.
+    async fn latest_go_version(&self, module: &str) -> Result<String>;
+}
+
+/// How changed files reach a repo.
+#[async_trait]
+pub trait VcsBackend: Send + Sync {
+    /// Commits every file of `changes` to `{org}/{repo}` as one commit; see
+    /// [`commit_changes`].
+    #[allow(clippy::too_many_arguments)]
+    async fn commit(
+        &self,
+        org: &str,
+        repo: &str,
+        changes: &[FileChange<'_>],
+        message: &str,
+        local_base: Option<&Path>,
+        push: &PushTarget,
+        strategies: &[CommitStrategy],
+    ) -> Result<Vec<CommitResult>>;
+
+    /// Content of `file` on the default branch of `{org}/{repo}`, or `None`
+    /// when it does not exist; see [`read_remote_file`].
+    async fn read_file(&self, org: &str, repo: &str, file: &str) -> Result<Option<String>>;
+}
+
+/// The king's HTTP endpoints.
+#[async_trait]
+pub trait KingClient: Send + Sync {
+    /// Full URL of `path` (e.g. `/admin/badges`), for logs and summaries.
+    fn url(&self, path: &str) -> String;
+
+    /// POSTs `body` to `path` (no body when `Null`); returns the response
+    /// status and text.
+    async fn post(&self, path: &str, body: &Value) -> Result<(StatusCode, String)>;
+
+    /// [`KingClient::post`], failing unless the king answers `2xx`.
+    async fn post_ok(&self, path: &str, body: &Value) -> Result<String> {
+        let (status, text) = self.post(path, body).await?;
+        anyhow::ensure!(status.is_success(), "{} returned {status}", self.url(path));
+        Ok(text)
+    }
+}
+
+/// The backends a run talks to.
+#[derive(Clone)]
+pub struct Backends {
+    pub registry: Arc<dyn RegistryClient>,
+    pub vcs: Arc<dyn VcsBackend>,
+    pub king: Arc<dyn KingClient>,
+}
+
+impl Backends {
+    /// The real backends: registries (recorded or replayed per
+    /// `REGISTRY_FIXTURES`) and the king (at `KING_ADDRESS`) over `http`,
+    /// commits through gh / git / the REST API.
+    pub fn from_env(http: &reqwest::Client) -> Self {
+        let king_addr =
+            std::env::var("KING_ADDRESS").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        Self {
+            registry: Arc::new(HttpRegistry::new(http.clone(), Fixtures::from_env())),
+            vcs: Arc::new(GitVcs),
+            king: Arc::new(HttpKing::new(http.clone(), king_addr)),
+        }
+    }
+}
+
+// ─── Real backends ────────────────────────────────────────────────────────────
+
+/// Registry APIs over HTTP.
+pub struct HttpRegistry {
+    http: RegistryHttp,
+}
+
+impl HttpRegistry {
+    /// Requests go through `fixtures` when given (see [`Fixtures`]).
+    pub fn new(http: reqwest::Client, fixtures: Option<Fixtures>) -> Self {
+        Self {
+            http: RegistryHttp::new(http, fixtures),
+        }
+    }
+}
+
+#[async_trait]
+impl RegistryClient for HttpRegistry {
+    async fn latest_version(&self, registry: &Registry, crate_name: &str) -> Lookup {
+        latest_crate_version_with_retry(&self.http, registry, crate_name).await
+    }
+
+    async fn verify_checksum(
+        &self,
+        registry: &Registry,
+        crate_name: &str,
+        version: &str,
+        pins: &ChecksumPins,
+    ) -> Result<String> {
+        verify_crate(&self.http, registry, crate_name, version, pins).await
+    }
+
+    async fn resolvable(&self, registry: &Registry, crate_name: &str, version: &str) -> Result<()> {
+        publish_wait::resolvable(&self.http, registry, crate_name, version).await
+    }
+
+    async fn latest_toolchain(&self) -> Result<String> {
+        toolchain::latest_stable(&self.http).await
+    }
+
+    async fn vulnerabilities(&self, crate_name: &str, version: &str) -> Result<Vec<Vulnerability>> {
+        osv::query(&self.http, &osv::api_url(), crate_name, version).await
+    }
+
+    async fn crate_links(&self, registry: &Registry, crate_name: &str) -> Result<CrateLinks> {
+        metadata::fetch(&self.http, registry, crate_name).await
+    }
+
+    async fn owners(&self, registry: &Registry, crate_name: &str) -> Result<CrateOwners> {
+        ownership::fetch(&self.http, registry, crate_name).await
+    }
+
+    async fn publisher(
+        &self,
+        registry: &Registry,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<String>> {
+        ownership::publisher(&self.http, registry, crate_name, version).await
+    }
+
+    async fn latest_pypi_version(&self, package: &str) -> Result<String> {
+        pyproject::latest_version(&self.http, &pyproject::index_url(), package).await
+    }
+
+    async fn latest_npm_version(&self, package: &str) -> Result<String> {
+        package_json::latest_version(&self.http, &package_json::registry_url(), package).await
+    }
+
+    async fn latest_go_version(&self, module: &str) -> Result<String> {
+        gomod::latest_version(&self.http, &gomod::proxy_url(), module).await
+    }
+}
+
+/// Commits through gh, local git or the REST API.
+pub struct GitVcs;
+
+#[async_trait]
+impl VcsBackend for GitVcs {
+    async fn commit(
+        &self,
+        org: &str,
+        repo: &str,
+        changes: &[FileChange<'_>],
+        message: &str,
+        local_base: Option<&Path>,
+        push: &PushTarget,
+        strategies: &[CommitStrategy],
+    ) -> Result<Vec<CommitResult>> {
+        commit_changes(org, repo, changes, message, local_base, push, strategies).await
+    }
+
+    async fn read_file(&self, org: &str, repo: &str, file: &str) -> Result<Option<String>> {
+        read_remote_file(org, repo, file).await
+    }
+}
+
+/// The king at `addr`, over HTTP.
+pub struct HttpKing {
+    http: reqwest::Client,
+    addr: String,
+}
+
+impl HttpKing {
+    pub fn new(http: reqwest::Client, addr: String) -> Self {
+        Self { http, addr }
+    }
+}
+
+#[async_trait]
+impl KingClient for HttpKing {
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.addr)
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<(StatusCode, String)> {
+        let url = self.url(path);
+        let request = match body {
+            Value::Null => self.http.post(&url),
+            body => self.http.post(&url).json(body),
+        };
+        let resp = request
+            .send()
+            .await
+            .with_context(|| format!("POST {url}"))?;
+        let status = resp.status();
+        Ok((status, resp.text().await.unwrap_or_default()))
+    }
+}
+
+// ─── Fakes ────────────────────────────────────────────────────────────────────
+
+/// An in-memory registry for tests: every crate is served from one map.
+#[derive(Default)]
+pub struct FakeRegistry {
+    versions: Mutex<BTreeMap<String, String>>,
+    toolchain: Option<String>,
+    vulnerabilities: BTreeMap<(String, String), Vec<Vulnerability>>,
+    links: BTreeMap<String, CrateLinks>,
+    owners: BTreeMap<String, CrateOwners>,
+    publishers: BTreeMap<(String, String), String>,
+    pypi: BTreeMap<String, String>,
+    npm: BTreeMap<String, String>,
+    go: BTreeMap<String, String>,
+}
+
+impl FakeRegistry {
+    /// Serves `version` as the latest `crate_name`.
+    pub fn with_version(self, crate_name: &str, version: &str) -> Self {
+        self.set_version(crate_name, version);
+        self
+    }
+
+    /// Serves `version` as the latest stable Rust.
+    pub fn with_toolchain(mut self, version: &str) -> Self {
+        self.toolchain = Some(version.to_string());
+        self
+    }
+
+    /// Lists `vulnerability` as affecting `crate_name@version`.
+    pub fn with_vulnerability(
+        mut self,
+        crate_name: &str,
+        version: &str,
+        vulnerability: Vulnerability,
+    ) -> Self {
+        self.vulnerabilities
+            .entry((crate_name.to_string(), version.to_string()))
+            .or_default()
+            .push(vulnerability);
+        self
+    }
+
+    /// Serves `links` as the registry links of `crate_name`.
+    pub fn with_links(mut self, crate_name: &str, links: CrateLinks) -> Self {
+        self.links.insert(crate_name.to_string(), links);
+        self
+    }
+
+    /// Serves `owners` as the owners and publishers of `crate_name`.
+    pub fn with_owners(mut self, crate_name: &str, owners: CrateOwners) -> Self {
+        self.owners.insert(crate_name.to_string(), owners);
+        self
+    }
+
+    /// Records `login` as the publisher of `crate_name@version`.
+    pub fn with_publisher(mut self, crate_name: &str, version: &str, login: &str) -> Self {
+        self.publishers.insert(
+            (crate_name.to_string(), version.to_string()),
+            login.to_string(),
+        );
+        self
+    }
+
+    /// Serves `version` as the latest `package` on PyPI.
+    pub fn with_pypi_version(mut self, package: &str, version: &str) -> Self {
+        self.pypi.insert(package.to_string(), version.to_string());
+        self
+    }
+
+    /// Serves `version` as the latest npm `package`.
+    pub fn with_npm_version(mut self, package: &str, version: &str) -> Self {
+        self.npm.insert(package.to_string(), version.to_string());
+        self
+    }
+
+    /// Serves `version` (without `v`) as the latest Go `module`.
+    pub fn with_go_version(mut self, module: &str, version: &str) -> Self {
+        self.go.insert(module.to_string(), version.to_string());
+        self
+    }
+
+    /// Publishes `version` of `crate_name`.
+    pub fn set_version(&self, crate_name: &str, version: &str) {
+        self.versions
+            .lock()
+            .unwrap()
+            .insert(crate_name.to_string(), version.to_string());
+    }
+}
+
+#[async_trait]
+impl RegistryClient for FakeRegistry {
+    async fn latest_version(&self, _registry: &Registry, crate_name: &str) -> Lookup {
+        let version = self.versions.lock().unwrap().get(crate_name).cloned();
+        Lookup {
+            result: version.with_context(|| format!("crate {crate_name} not found")),
+            attempts: 1,
+        }
+    }
+
+    async fn verify_checksum(
+        &self,
+        _registry: &Registry,
+        crate_name: &str,
+        version: &str,
+        _pins: &ChecksumPins,
+    ) -> Result<String> {
+        Ok(sha256_hex(format!("{crate_name}@{version}").as_bytes()))
+    }
+
+    async fn resolvable(
+        &self,
+        _registry: &Registry,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<()> {
+        let served = self.versions.lock().unwrap().get(crate_name).cloned();
+        anyhow::ensure!(
+            served.as_deref() == Some(version),
+            "not in the sparse index yet"
+        );
+        Ok(())
+    }
+
+    async fn latest_toolchain(&self) -> Result<String> {
+        self.toolchain.clone().context("no stable Rust configured")
+    }
+
+    async fn vulnerabilities(&self, crate_name: &str, version: &str) -> Result<Vec<Vulnerability>> {
+        Ok(self
+            .vulnerabilities
+            .get(&(crate_name.to_string(), version.to_string()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn crate_links(&self, _registry: &Registry, crate_name: &str) -> Result<CrateLinks> {
+        Ok(self.links.get(crate_name).cloned().unwrap_or_default())
+    }
+
+    async fn owners(&self, _registry: &Registry, crate_name: &str) -> Result<CrateOwners> {
+        Ok(self.owners.get(crate_name).cloned().unwrap_or_default())
+    }
+
+    async fn publisher(
+        &self,
+        _registry: &Registry,
+        crate_name: &str,
+        version: &str,
+    ) -> Result<Option<String>> {
+        Ok(self
+            .publishers
+            .get(&(crate_name.to_string(), version.to_string()))
+            .cloned())
+    }
+
+    async fn latest_pypi_version(&self, package: &str) -> Result<String> {
+        self.pypi
+            .get(package)
+            .cloned()
+            .with_context(|| format!("package {package} not found on PyPI"))
+    }
+
+    async fn latest_npm_version(&self, package: &str) -> Result<String> {
+        self.npm
+            .get(package)
+            .cloned()
+            .with_context(|| format!("package {package} not found on npm"))
+    }
+
+    async fn latest_go_version(&self, module: &str) -> Result<String> {
+        self.go
+            .get(module)
+            .cloned()
+            .with_context(|| format!("module {module} not found on the module proxy"))
+    }
+}
+
+/// A commit recorded by [`FakeVcs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeCommit {
+    pub repo: String,
+    pub message: String,
+    /// `(path, content)` of every file.
+    pub files: Vec<(String, String)>,
+}
+
+/// Records commits in memory instead of pushing them, and serves the
+/// files it was given or committed.
+#[derive(Default)]
+pub struct FakeVcs {
+    commits: Mutex<Vec<FakeCommit>>,
+    /// `(repo, path)` → content on the default branch.
+    files: Mutex<BTreeMap<(String, String), String>>,
+}
+
+impl FakeVcs {
+    /// Every commit made so far, oldest first.
+    pub fn commits(&self) -> Vec<FakeCommit> {
+        self.commits.lock().unwrap().clone()
+    }
+
+    /// Serves `content` as `repo`'s `path`.
+    pub fn set_file(&self, repo: &str, path: &str, content: &str) {
+        self.files
+            .lock()
+            .unwrap()
+            .insert((repo.to_string(), path.to_string()), content.to_string());
+    }
+}
+
+#[async_trait]
+impl VcsBackend for FakeVcs {
+    async fn commit(
+        &self,
+        org: &str,
+        repo: &str,
+        changes: &[FileChange<'_>],
+        message: &str,
+        _local_base: Option<&Path>,
+        _push: &PushTarget,
+        _strategies: &[CommitStrategy],
+    ) -> Result<Vec<CommitResult>> {
+        anyhow::ensure!(!changes.is_empty(), "no files to commit");
+        let mut commits = self.commits.lock().unwrap();
+        let sha =
+            sha256_hex(format!("{}:{repo}:{message}", commits.len()).as_bytes())[..40].to_string();
+        let files: Vec<(String, String)> = changes
+            .iter()
+            .map(|c| {
+                let content = String::from_utf8_lossy(c.content).into_owned();
+                (c.path.to_string(), content)
+            })
+            .collect();
+        let mut served = self.files.lock().unwrap();
+        for (path, content) in &files {
+            served.insert((repo.to_string(), path.clone()), content.clone());
+        }
+        commits.push(FakeCommit {
+            repo: repo.to_string(),
+            message: message.to_string(),
+            files,
+        });
+        Ok(changes
+            .iter()
+            .map(|c| CommitResult {
+                repo: format!("{org}/{repo}"),
+                file_path: c.path.to_string(),
+                strategy: CommitStrategy::LocalGit,
+                sha: sha.clone(),
+                stats: diff_stats(c.previous, c.content),
+            })
+            .collect())
+    }
+
+    async fn read_file(&self, _org: &str, repo: &str, file: &str) -> Result<Option<String>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .get(&(repo.to_string(), file.to_string()))
+            .cloned())
+    }
+}
+
+/// A king that records every request and answers `200 {}` unless told
+/// otherwise.
+#[derive(Default)]
+pub struct FakeKing {
+    requests: Mutex<Vec<(String, Value)>>,
+    responses: Mutex<BTreeMap<String, (StatusCode, String)>>,
+}
+
+impl FakeKing {
+    /// Answers requests to `path` with `status` and `body`.
+    pub fn respond(&self, path: &str, status: StatusCode, body: &str) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), (status, body.to_string()));
+    }
+
+    /// `(path, body)` of every request so far, oldest first.
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl KingClient for FakeKing {
+    fn url(&self, path: &str) -> String {
+        format!("http://king.test{path}")
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<(StatusCode, String)> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push((path.to_string(), body.clone()));
+        Ok(self
+            .responses
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .unwrap_or((StatusCode::OK, "{}".to_string())))
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_fake_king_records_and_answers() {
+        let king = FakeKing::default();
+        king.respond("/admin/badges", StatusCode::BAD_GATEWAY, "");
+        assert!(
+            king.post_ok("/admin/config-sync", &Value::Null)
+                .await
+                .is_ok()
+        );
+        let err = king
+            .post_ok("/admin/badges", &json!({ "run_id": "r1" }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "http://king.test/admin/badges returned 502 Bad Gateway"
+        );
+        assert_eq!(
+            king.requests(),
+            vec![
+                ("/admin/config-sync".to_string(), Value::Null),
+                ("/admin/badges".to_string(), json!({ "run_id": "r1" })),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_vcs_records_commits() {
+        let vcs = FakeVcs::default();
+        let changes = [FileChange {
+            path: "Cargo.toml",
+            previous: b"a = \"1\"\n",
+            content: b"a = \"2\"\n",
+        }];
+        let results = vcs
+            .commit(
+                "o",
+                "evo-king",
+                &changes,
+                "bump a",
+                None,
+                &PushTarget::default(),
+                CommitStrategy::ALL,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].repo, "o/evo-king");
+        assert_eq!(results[0].sha.len(), 40);
+        assert_eq!(
+            vcs.commits(),
+            vec![FakeCommit {
+                repo: "evo-king".to_string(),
+                message: "bump a".to_string(),
+                files: vec![("Cargo.toml".to_string(), "a = \"2\"\n".to_string())],
+            }]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::backend::KingClient;
+use crate::git::gh_api_with_body;
+use crate::repo_config::UpdateType;
+use crate::state::{date_from_unix, state_dir};
+use crate::versions::needs_update;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Left-hand text of every badge.
+const LABEL: &str = "dependencies";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Freshness of a repo's tracked dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeState {
+    UpToDate,
+    Outdated,
+    /// None of the repo's tracked crates could be compared.
+    Unknown,
+}
+
+/// A tracked crate the repo still requires at an older version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutdatedCrate {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub current: String,
+    pub latest: String,
+    pub update_type: UpdateType,
+}
+
+/// Dependency status of one managed repo, as published for its README badge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Badge {
+    pub repo: String,
+    pub state: BadgeState,
+    pub outdated: Vec<OutdatedCrate>,
+    /// Unix seconds of the agent's last direct commit to the repo.
+    pub last_updated: Option<u64>,
+    /// Run that produced this status.
+    pub run_id: String,
+}
+
+impl Badge {
+    /// Status of `repo` from its requirements (`crate → requirement`) and
+    /// the `latest` released versions.
+    pub fn build(
+        repo: &str,
+        requirements: &BTreeMap<String, String>,
+        latest: &BTreeMap<String, String>,
+        last_updated: Option<u64>,
+        run_id: &str,
+    ) -> Self {
+        let mut compared = 0;
+        let mut outdated: Vec<OutdatedCrate> = Vec::new();
+        for (crate_name, current) in requirements {
+            let Some(latest) = latest.get(crate_name) else {
+                continue;
+            };
+            compared += 1;
+            if needs_update(current, latest) {
+                outdated.push(OutdatedCrate {
+                    crate_name: crate_name.clone(),
+                    current: current.clone(),
+                    latest: latest.clone(),
+                    update_type: UpdateType::of(current, latest),
+                });
+            }
+        }
+        let state = match (compared, outdated.is_empty()) {
+            (0, _) => BadgeState::Unknown,
+            (_, true) => BadgeState::UpToDate,
+            (_, false) => BadgeState::Outdated,
+        };
+        Self {
+            repo: repo.to_string(),
+            state,
+            outdated,
+            last_updated,
+            run_id: run_id.to_string(),
+        }
+    }
+
+    /// Right-hand text, e.g. `up to date | 2026-03-01` or `2 outdated`.
+    pub fn message(&self) -> String {
+        let status = match self.state {
+            BadgeState::UpToDate => "up to date".to_string(),
+            BadgeState::Outdated => format!("{} outdated", self.outdated.len()),
+            BadgeState::Unknown => "unknown".to_string(),
+        };
+        match self.last_updated {
+            Some(secs) => format!("{status} | {}", date_from_unix(secs)),
+            None => status,
+        }
+    }
+
+    /// `(shields.io color name, hex)`; red when a major bump is pending.
+    pub fn color(&self) -> (&'static str, &'static str) {
+        match self.state {
+            BadgeState::UpToDate => ("brightgreen", "#4c1"),
+            BadgeState::Outdated
+                if self
+                    .outdated
+                    .iter()
+                    .any(|c| c.update_type == UpdateType::Major) =>
+            {
+                ("red", "#e05d44")
+            }
+            BadgeState::Outdated => ("yellow", "#dfb317"),
+            BadgeState::Unknown => ("lightgrey", "#9f9f9f"),
+        }
+    }
+
+    /// shields.io endpoint document (`https://img.shields.io/endpoint?url=…`).
+    pub fn shields_endpoint(&self) -> Value {
+        json!({
+            "schemaVersion": 1,
+            "label": LABEL,
+            "message": self.message(),
+            "color": self.color().0,
+        })
+    }
+
+    /// Self-contained flat SVG badge.
+    pub fn svg(&self) -> String {
+        let message = self.message();
+        let left = text_width(LABEL);
+        let right = text_width(&message);
+        let width = left + right;
+        let color = self.color().1;
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{LABEL}: {message}">
+<title>{LABEL}: {message}</title>
+<rect width="{left}" height="20" fill="#555"/>
+<rect x="{left}" width="{right}" height="20" fill="{color}"/>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{lx}" y="14">{LABEL}</text>
+<text x="{rx}" y="14">{message}</text>
+</g>
+</svg>
+"##,
+            lx = left / 2,
+            rx = left + right / 2,
+        )
+    }
+}
+
+/// Approximate rendered width of `text` in 11px Verdana, plus padding.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+// ─── Storage ──────────────────────────────────────────────────────────────────
+
+/// Badges kept under `state_dir/badges`: `{repo}.json` (status),
+/// `{repo}-shield.json` (shields.io endpoint) and `{repo}.svg`.
+#[derive(Debug, Clone)]
+pub struct BadgeStore {
+    dir: PathBuf,
+}
+
+impl BadgeStore {
+    /// A store in `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The store under [`state_dir`].
+    pub fn default_location() -> Self {
+        Self::new(&state_dir().join("badges"))
+    }
+
+    /// The badge last stored for `repo`, if any.
+    pub fn load(&self, repo: &str) -> Option<Badge> {
+        let text = std::fs::read_to_string(self.dir.join(format!("{repo}.json"))).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Writes the three files of `badge`; returns the status file path.
+    pub fn save(&self, badge: &Badge) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create badge dir {}", self.dir.display()))?;
+        for (name, content) in files(badge)? {
+            let path = self.dir.join(&name);
+            std::fs::write(&path, content).with_context(|| format!("write {}", path.display()))?;
+        }
+        Ok(self.dir.join(format!("{}.json", badge.repo)))
+    }
+}
+
+/// `(file name, content)` of every file published for `badge`.
+fn files(badge: &Badge) -> Result<Vec<(String, String)>> {
+    Ok(vec![
+        (
+            format!("{}.json", badge.repo),
+            serde_json::to_string_pretty(badge).context("serialize badge")?,
+        ),
+        (
+            format!("{}-shield.json", badge.repo),
+            badge.shields_endpoint().to_string(),
+        ),
+        (format!("{}.svg", badge.repo), badge.svg()),
+    ])
+}
+
+// ─── Publishing ───────────────────────────────────────────────────────────────
+
+/// POSTs every badge to the king's `/admin/badges` endpoint.
+pub async fn publish_to_king(king: &dyn KingClient, run_id: &str, badges: &[Badge]) -> Result<()> {
+    let body = json!({
+        "run_id": run_id,
+        "badges": badges
+            .iter()
+            .map(|b| json!({ "status": b, "shield": b.shields_endpoint(), "svg": b.svg() }))
+            .collect::<Vec<_>>(),
+    });
+    king.post_ok("/admin/badges", &body).await.map(drop)
+}
+
+/// Writes every badge into the public gist `BADGE_GIST_ID`, or creates one
+/// when it is unset.  Returns the gist URL.
+///
+/// Updating one gist in place keeps the raw URLs embedded in READMEs stable.
+pub fn publish_gist(badges: &[Badge]) -> Result<String> {
+    let mut gist_files = serde_json::Map::new();
+    for badge in badges {
+        for (name, content) in files(badge)? {
+            gist_files.insert(name, json!({ "content": content }));
+        }
+    }
+    let body = json!({
+        "description": "evo-kernel-agent-update dependency badges",
+        "public": true,
+        "files": gist_files,
+    });
+    match std::env::var("BADGE_GIST_ID")
+        .ok()
+        .filter(|id| !id.is_empty())
+    {
+        Some(id) => gh_api_with_body(
+            &[
+                "--method",
+                "PATCH",
+                &format!("gists/{id}"),
+                "--jq",
+                ".html_url",
+            ],
+            &body,
+        )
+        .context("update badge gist"),
+        None => gh_api_with_body(&["--method", "POST", "gists", "--jq", ".html_url"], &body)
+            .context("create badge gist"),
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_build_and_render() {
+        let latest = map(&[("evo-common", "0.4.0"), ("evo-agent-sdk", "0.3.2")]);
+
+        let fresh = Badge::build(
+            "evo-king",
+            &map(&[("evo-common", "0.4.0")]),
+            &latest,
+            Some(1_772_323_200),
+            "run-1",
+        );
+        assert_eq!(fresh.state, BadgeState::UpToDate);
+        assert_eq!(fresh.message(), "up to date | 2026-03-01");
+        assert_eq!(fresh.shields_endpoint()["color"], "brightgreen");
+
+        let stale = Badge::build(
+            "evo-runner",
+            &map(&[("evo-common", "0.3"), ("evo-agent-sdk", "0.3.1")]),
+            &latest,
+            None,
+            "run-1",
+        );
+        assert_eq!(stale.state, BadgeState::Outdated);
+        assert_eq!(stale.message(), "2 outdated");
+        assert_eq!(stale.color().0, "red");
+        assert!(stale.svg().contains("dependencies: 2 outdated"));
+
+        let unknown = Badge::build("evo-x", &BTreeMap::new(), &latest, None, "run-1");
+        assert_eq!(unknown.state, BadgeState::Unknown);
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = BadgeStore::new(dir.path());
+        let badge = Badge::build("evo-king", &BTreeMap::new(), &BTreeMap::new(), Some(1), "r");
+        store.save(&badge).unwrap();
+        assert_eq!(store.load("evo-king"), Some(badge));
+        assert!(dir.path().join("evo-king.svg").is_file());
+        assert!(dir.path().join("evo-king-shield.json").is_file());
+        assert_eq!(store.load("evo-runner"), None);
+    }
+}
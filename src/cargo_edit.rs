@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::net::apply_to_command;
+use crate::tokens::redact;
+use crate::updater::{ChangedEntry, PatchOutcome};
+use crate::versions::dep_requirements;
+
+// ─── cargo add backend ────────────────────────────────────────────────────────
+
+/// Updates `dep_name` to `new_version` by running `cargo add` against the
+/// manifest at `manifest_path` in the local checkout.
+///
+/// `content` (the manifest with any earlier patches of this run) is written
+/// to the checkout first; the manifest and `lockfile` are restored
+/// afterwards whatever happens, so the checkout is left as it was and only
+/// the returned content carries the change.  Cargo rewrites the entry in its
+/// own way, so unlike [`crate::updater::patch_cargo_toml_multi`] no formatting
+/// guard is applied; the caller validates the result as usual.
+pub fn patch_with_cargo_add(
+    manifest_path: &Path,
+    lockfile: Option<&Path>,
+    content: &str,
+    dep_name: &str,
+    new_version: &str,
+    offline: bool,
+) -> Result<PatchOutcome> {
+    let from = dependencies_version(content, dep_name)
+        .with_context(|| format!("dependency {dep_name} has no version in [dependencies]"))?;
+    let args = add_args(content, dep_name, new_version, manifest_path, offline)?;
+
+    let saved_manifest = std::fs::read(manifest_path)
+        .with_context(|| format!("read {}", manifest_path.display()))?;
+    let saved_lock = lockfile.and_then(|p| std::fs::read(p).ok().map(|bytes| (p, bytes)));
+    let restore = || -> Result<()> {
+        std::fs::write(manifest_path, &saved_manifest)
+            .with_context(|| format!("restore {}", manifest_path.display()))?;
+        if let Some((path, bytes)) = &saved_lock {
+            std::fs::write(path, bytes).with_context(|| format!("restore {}", path.display()))?;
+        }
+        Ok(())
+    };
+
+    let result = std::fs::write(manifest_path, content)
+        .with_context(|| format!("write {}", manifest_path.display()))
+        .and_then(|()| {
+            apply_to_command(&mut Command::new("cargo"))
+                .args(&args)
+                .output()
+                .context("run cargo add")
+        })
+        .and_then(|output| {
+            anyhow::ensure!(
+                output.status.success(),
+                "cargo add {dep_name}@{new_version} failed: {}",
+                redact(String::from_utf8_lossy(&output.stderr).trim())
+            );
+            std::fs::read_to_string(manifest_path)
+                .with_context(|| format!("read {}", manifest_path.display()))
+        });
+    restore()?;
+    let patched = result?;
+
+    let to = dependencies_version(&patched, dep_name)
+        .with_context(|| format!("cargo add left {dep_name} without a version"))?;
+    let changed_entries: Vec<ChangedEntry> = (from != to)
+        .then(|| ChangedEntry {
+            section: "dependencies".to_string(),
+            dep: dep_name.to_string(),
+            from,
+            to,
+        })
+        .into_iter()
+        .collect();
+    let sections_touched = changed_entries.iter().map(|e| e.section.clone()).collect();
+    Ok(PatchOutcome {
+        content: patched,
+        changed_entries,
+        sections_touched,
+        failed: Vec::new(),
+    })
+}
+
+/// Returns `lockfile` as cargo resolves it for the manifest at
+/// `manifest_path` with `content`.
+///
+/// Runs `cargo update --workspace`, which keeps every locked package that
+/// still satisfies its requirement and only moves what the new requirements
+/// force.  Like [`patch_with_cargo_add`], the checkout's manifest and lockfile
+/// are restored afterwards.
+pub fn regenerate_lockfile(
+    manifest_path: &Path,
+    lockfile: &Path,
+    content: &str,
+    offline: bool,
+) -> Result<String> {
+    let saved_manifest = std::fs::read(manifest_path)
+        .with_context(|| format!("read {}", manifest_path.display()))?;
+    let saved_lock =
+        std::fs::read(lockfile).with_context(|| format!("read {}", lockfile.display()))?;
+
+    let mut args = vec![
+        "update".to_string(),
+        "--workspace".to_string(),
+        "--manifest-path".to_string(),
+        manifest_path.display().to_string(),
+        "--quiet".to_string(),
+    ];
+    if offline {
+        args.push("--offline".to_string());
+    }
+    let result = std::fs::write(manifest_path, content)
+        .with_context(|| format!("write {}", manifest_path.display()))
+        .and_then(|()| {
+            apply_to_command(&mut Command::new("cargo"))
+                .args(&args)
+                .output()
+                .context("run cargo update")
+        })
+        .and_then(|output| {
+            anyhow::ensure!(
+                output.status.success(),
+                "cargo update --workspace failed: {}",
+                redact(String::from_utf8_lossy(&output.stderr).trim())
+            );
+            std::fs::read_to_string(lockfile)
+                .with_context(|| format!("read {}", lockfile.display()))
+        });
+    std::fs::write(manifest_path, &saved_manifest)
+        .with_context(|| format!("restore {}", manifest_path.display()))?;
+    std::fs::write(lockfile, &saved_lock)
+        .with_context(|| format!("restore {}", lockfile.display()))?;
+    result
+}
+
+/// `cargo add` arguments; a renamed dependency (`package = …`) is added
+/// under its package name with `--rename`.
+/// `dep_name`'s requirement in `[dependencies]`, the only table `cargo add`
+/// edits here.
+fn dependencies_version(content: &str, dep_name: &str) -> Option<String> {
+    dep_requirements(content, dep_name)
+        .into_iter()
+        .find(|(section, _)| section == "dependencies")
+        .map(|(_, requirement)| requirement)
+}
+
+fn add_args(
+    content: &str,
+    dep_name: &str,
+    new_version: &str,
+    manifest_path: &Path,
+    offline: bool,
+) -> Result<Vec<String>> {
+    let doc: toml_edit::DocumentMut = content
+        .parse()
+        .with_context(|| format!("parse Cargo.toml to patch {dep_name}"))?;
+    let package = doc
+        .get("dependencies")
+        .and_then(|deps| deps.get(dep_name))
+        .and_then(|dep| dep.get("package"))
+        .and_then(|p| p.as_str());
+
+    let mut args = vec!["add".to_string()];
+    match package {
+        Some(package) => args.extend([
+            format!("{package}@{new_version}"),
+            "--rename".to_string(),
+            dep_name.to_string(),
+        ]),
+        None => args.push(format!("{dep_name}@{new_version}")),
+    }
+    args.extend([
+        "--manifest-path".to_string(),
+        manifest_path.display().to_string(),
+        "--quiet".to_string(),
+    ]);
+    if offline {
+        args.push("--offline".to_string());
+    }
+    Ok(args)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_args() {
+        let manifest = Path::new("/repo/Cargo.toml");
+        assert_eq!(
+            add_args(
+                "[dependencies]\nevo-common = \"0.3\"\n",
+                "evo-common",
+                "0.4.0",
+                manifest,
+                false
+            )
+            .unwrap(),
+            vec![
+                "add",
+                "evo-common@0.4.0",
+                "--manifest-path",
+                "/repo/Cargo.toml",
+                "--quiet"
+            ]
+        );
+        let renamed = add_args(
+            "[dependencies]\ncommon = { package = \"evo-common\", version = \"0.3\" }\n",
+            "common",
+            "0.4.0",
+            manifest,
+            true,
+        )
+        .unwrap();
+        assert_eq!(&renamed[1..4], ["evo-common@0.4.0", "--rename", "common"]);
+        assert_eq!(renamed.last().map(String::as_str), Some("--offline"));
+    }
+
+    #[test]
+    fn test_failed_lockfile_regeneration_restores_the_checkout() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        let lockfile = dir.path().join("Cargo.lock");
+        std::fs::write(&manifest, "[package]\n").unwrap();
+        std::fs::write(&lockfile, "version = 4\n").unwrap();
+        assert!(regenerate_lockfile(&manifest, &lockfile, "not [valid", true).is_err());
+        assert_eq!(std::fs::read_to_string(&manifest).unwrap(), "[package]\n");
+        assert_eq!(std::fs::read_to_string(&lockfile).unwrap(), "version = 4\n");
+    }
+
+    #[test]
+    fn test_missing_dep_is_an_error() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        std::fs::write(&manifest, "[dependencies]\n").unwrap();
+        assert!(
+            patch_with_cargo_add(
+                &manifest,
+                None,
+                "[dependencies]\n",
+                "evo-common",
+                "0.4.0",
+                true
+            )
+            .is_err()
+        );
+        assert_eq!(
+            std::fs::read_to_string(&manifest).unwrap(),
+            "[dependencies]\n"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::debug;
+
+use crate::git::gh_api;
+use crate::versions::needs_update;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A BREAKING / Migration passage from a tracked crate's changelog, kept
+/// verbatim so breaking steps are not lost in the LLM summary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationNote {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Release the passage belongs to.
+    pub version: String,
+    /// Heading (or marker) the passage was found under.
+    pub heading: String,
+    pub text: String,
+}
+
+// ─── Fetching ─────────────────────────────────────────────────────────────────
+
+/// Reads `crate_name`'s `CHANGELOG.md`, from the local checkout at
+/// `base_dir/{crate_name}` or, when online, from its source `repo`
+/// (`owner/name`) on GitHub.
+pub fn fetch(base_dir: &Path, repo: &str, crate_name: &str, offline: bool) -> Option<String> {
+    if let Ok(text) = std::fs::read_to_string(base_dir.join(crate_name).join("CHANGELOG.md")) {
+        return Some(text);
+    }
+    if offline {
+        return None;
+    }
+    let endpoint = format!("repos/{repo}/contents/CHANGELOG.md");
+    match gh_api(&[&endpoint, "-H", "Accept: application/vnd.github.raw"]) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            debug!(crate = crate_name, error = %e, "no changelog available");
+            None
+        }
+    }
+}
+
+// ─── Extraction ───────────────────────────────────────────────────────────────
+
+/// The BREAKING / Migration passages of every release after `from` up to
+/// and including `to`, oldest release first.
+///
+/// Releases are `##` headings carrying a version (`## [0.4.0] - 2026-01-02`,
+/// `## v0.4.0`).  Inside a release, a sub-heading whose title mentions
+/// "breaking" or "migration" is captured with everything under it; so is a
+/// single bullet starting with a `BREAKING` marker.
+pub fn migration_notes(
+    changelog: &str,
+    crate_name: &str,
+    from: &str,
+    to: &str,
+) -> Vec<MigrationNote> {
+    let mut notes: Vec<MigrationNote> = Vec::new();
+    let mut release: Option<String> = None;
+    // (heading level, heading, captured lines) of the section being copied.
+    let mut capture: Option<(usize, String, Vec<&str>)> = None;
+
+    let mut flush = |capture: &mut Option<(usize, String, Vec<&str>)>, release: &Option<String>| {
+        if let (Some((_, heading, lines)), Some(version)) = (capture.take(), release) {
+            let text = lines.join("\n").trim().to_string();
+            if !text.is_empty() {
+                notes.push(MigrationNote {
+                    crate_name: crate_name.to_string(),
+                    version: version.clone(),
+                    heading,
+                    text,
+                });
+            }
+        }
+    };
+
+    let mut in_fence = false;
+    for line in changelog.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && let Some((level, title)) = heading(line) {
+            if capture.as_ref().is_some_and(|(l, _, _)| level <= *l) {
+                flush(&mut capture, &release);
+            }
+            if level <= 2 {
+                release = release_version(title)
+                    .filter(|v| needs_update(from, v) && !needs_update(to, v));
+            } else if release.is_some() && capture.is_none() && is_migration(title) {
+                capture = Some((level, title.to_string(), Vec::new()));
+            }
+            continue;
+        }
+        match &mut capture {
+            Some((_, _, lines)) => lines.push(line),
+            None if release.is_some() && is_breaking_bullet(line) => {
+                let mut bullet = Some((usize::MAX, "BREAKING".to_string(), vec![line]));
+                flush(&mut bullet, &release);
+            }
+            None => {}
+        }
+    }
+    flush(&mut capture, &release);
+    notes.sort_by_key(|n| version_key(&n.version));
+    notes
+}
+
+/// Markdown `## Migration notes` section for a PR description; empty when
+/// there are no notes.
+pub fn render_markdown(notes: &[MigrationNote]) -> String {
+    if notes.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("## Migration notes\n\n");
+    for note in notes {
+        out.push_str(&format!(
+            "### `{}` {} — {}\n\n{}\n\n",
+            note.crate_name, note.version, note.heading, note.text
+        ));
+    }
+    out
+}
+
+/// Plain-text commit-message body carrying the notes; empty when there are
+/// none.
+pub fn render_commit_body(notes: &[MigrationNote]) -> String {
+    notes
+        .iter()
+        .map(|note| {
+            format!(
+                "{} {} ({}):\n{}",
+                note.crate_name, note.version, note.heading, note.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// `(level, title)` of a Markdown ATX heading.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line.get(level..)?;
+    (level > 0 && title.starts_with(' ')).then(|| (level, title.trim()))
+}
+
+/// The version a release heading names, e.g. `0.4.0` from `[0.4.0] - 2026-01-02`.
+fn release_version(title: &str) -> Option<String> {
+    title
+        .split(|c: char| c.is_whitespace() || matches!(c, '[' | ']' | '(' | ')'))
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| {
+            word.split('.').count() >= 2
+                && word
+                    .split(['-', '+'])
+                    .next()
+                    .is_some_and(|core| core.split('.').all(|p| p.parse::<u64>().is_ok()))
+        })
+        .map(str::to_string)
+}
+
+fn is_migration(title: &str) -> bool {
+    let title = title.to_ascii_lowercase();
+    title.contains("breaking") || title.contains("migration")
+}
+
+fn is_breaking_bullet(line: &str) -> bool {
+    let item = line.trim_start().trim_start_matches(['-', '*', ' ']);
+    item.trim_start_matches("**").starts_with("BREAKING")
+}
+
+/// Sort key ordering releases by version.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|p| p.parse().unwrap_or(0))
+        .collect()
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "\
+# Changelog
+
+## [Unreleased]
+
+### Breaking changes
+- Not released yet.
+
+## [0.5.0] - 2026-03-01
+
+### Added
+- **BREAKING**: `Config::load` now returns `Result`.
+- A new helper.
+
+## [0.4.0] - 2026-02-01
+
+### Migration
+Rename `AgentConfig` to `Config`:
+
+```sh
+# not a heading
+cargo update -p evo-common
+```
+
+### Fixed
+- Something unrelated.
+
+## [0.3.0] - 2026-01-01
+
+### Breaking changes
+- Already applied.
+";
+
+    #[test]
+    fn test_extracts_notes_between_versions() {
+        let notes = migration_notes(CHANGELOG, "evo-common", "0.3", "0.5.0");
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].version, "0.4.0");
+        assert_eq!(notes[0].heading, "Migration");
+        assert!(notes[0].text.starts_with("Rename `AgentConfig`"));
+        assert!(
+            notes[0]
+                .text
+                .contains("# not a heading\ncargo update -p evo-common")
+        );
+        assert!(!notes[0].text.contains("unrelated"));
+        assert_eq!(notes[1].version, "0.5.0");
+        assert_eq!(
+            notes[1].text,
+            "- **BREAKING**: `Config::load` now returns `Result`."
+        );
+    }
+
+    #[test]
+    fn test_target_bounds_the_range() {
+        let notes = migration_notes(CHANGELOG, "evo-common", "0.3.0", "0.4.0");
+        assert_eq!(notes.len(), 1);
+        assert!(migration_notes(CHANGELOG, "evo-common", "0.5.0", "0.5.0").is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let notes = migration_notes(CHANGELOG, "evo-common", "0.3", "0.4.0");
+        assert!(
+            render_markdown(&notes)
+                .starts_with("## Migration notes\n\n### `evo-common` 0.4.0 — Migration\n\n")
+        );
+        assert!(render_commit_body(&notes).starts_with("evo-common 0.4.0 (Migration):\nRename"));
+        assert_eq!(render_markdown(&[]), "");
+    }
+}
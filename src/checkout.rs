@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::config::RepoConfig;
+use crate::git::{self, PushTarget};
+use crate::gomod;
+use crate::repo_config;
+use crate::workflow_sync;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A checkout cloned because it was missing at the start of a run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bootstrap {
+    pub repo: String,
+    /// Where the checkout was created.
+    pub path: String,
+    /// Blob-less clone with only the agent's files checked out.
+    pub sparse: bool,
+    pub ms: u64,
+    /// Why the clone failed; the repo is then skipped as before.
+    pub error: Option<String>,
+}
+
+// ─── Bootstrapping ────────────────────────────────────────────────────────────
+
+/// Clones `spec`'s repo into `base_dir/local` when no checkout exists there.
+///
+/// Repos with `sparse_checkout` get a blob-less (`--filter=blob:none`) clone
+/// with only [`sparse_patterns`] checked out.  Returns `None` when the
+/// checkout already exists.
+pub fn bootstrap(
+    org: &str,
+    spec: &RepoConfig,
+    shared: &[String],
+    base_dir: &Path,
+) -> Option<Bootstrap> {
+    let dest = base_dir.join(&spec.local);
+    if dest.exists() {
+        return None;
+    }
+    let push = spec.push_target();
+    let url = remote_url(org, &spec.repo, &push);
+    let sparse = spec.sparse_checkout.then(|| sparse_patterns(spec, shared));
+    info!(repo = %spec.repo, url, sparse = sparse.is_some(), "cloning missing checkout");
+
+    let started = Instant::now();
+    let result = git::clone_checkout(&url, &dest, sparse.as_deref(), &push);
+    let ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    if let Err(e) = &result {
+        warn!(repo = %spec.repo, error = %e, "cloning checkout failed");
+    }
+    Some(Bootstrap {
+        repo: spec.repo.clone(),
+        path: dest.display().to_string(),
+        sparse: sparse.is_some(),
+        ms,
+        error: result.err().map(|e| format!("{e:#}")),
+    })
+}
+
+/// Paths a sparse checkout needs (non-cone `sparse-checkout` patterns): the
+/// configured manifests, workflows (listed or rendered) and schema
+/// directory, the `shared` files of the `[[file_sync]]` campaigns, every
+/// `Cargo.toml` / `Cargo.lock`
+/// (workspace members, SBOMs) and the repo-local rules file.
+pub fn sparse_patterns(spec: &RepoConfig, shared: &[String]) -> Vec<String> {
+    let mut patterns: Vec<String> = spec
+        .cargo_files
+        .iter()
+        .chain(&spec.workflow_files)
+        .chain(&spec.python_files)
+        .chain(&spec.npm_files)
+        .chain(&spec.go_files)
+        .chain(shared)
+        .map(|file| format!("/{}", file.trim_matches('/')))
+        .collect();
+    for name in &spec.workflow_templates {
+        let target = format!("/{}", workflow_sync::target_path(name));
+        if !patterns.contains(&target) {
+            patterns.push(target);
+        }
+    }
+    if let Some(dir) = &spec.schema_dir {
+        patterns.push(format!("/{}/", dir.trim_matches('/')));
+    }
+    // `go mod tidy` rewrites the go.sum next to each go.mod.
+    for go_mod in &spec.go_files {
+        let go_sum = format!("/{}", gomod::go_sum_of(go_mod.trim_start_matches('/')));
+        if !patterns.contains(&go_sum) {
+            patterns.push(go_sum);
+        }
+    }
+    for extra in [
+        "Cargo.toml".to_string(),
+        "Cargo.lock".to_string(),
+        format!("/{}", repo_config::FILE_NAME),
+    ] {
+        if !patterns.contains(&extra) {
+            patterns.push(extra);
+        }
+    }
+    patterns
+}
+
+/// Clone URL of `{org}/{repo}`: SSH when the repo pushes with a deploy key,
+/// HTTPS otherwise.
+fn remote_url(org: &str, repo: &str, push: &PushTarget) -> String {
+    if push.deploy_key_env.is_some() {
+        format!("git@github.com:{org}/{repo}.git")
+    } else {
+        format!("https://github.com/{org}/{repo}.git")
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_patterns() {
+        let spec = RepoConfig {
+            cargo_files: vec!["Cargo.toml".into()],
+            workflow_files: vec![".github/workflows/release.yml".into()],
+            workflow_templates: vec!["ci.yml".into(), "release.yml".into()],
+            schema_dir: Some("proto/".into()),
+            ..RepoConfig::default()
+        };
+        assert_eq!(
+            sparse_patterns(&spec, &["deny.toml".into()]),
+            vec![
+                "/Cargo.toml",
+                "/.github/workflows/release.yml",
+                "/deny.toml",
+                "/.github/workflows/ci.yml",
+                "/proto/",
+                "Cargo.toml",
+                "Cargo.lock",
+                "/.evo-update.toml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remote_url() {
+        let push = PushTarget {
+            deploy_key_env: Some("KEY".into()),
+            ..PushTarget::default()
+        };
+        assert_eq!(
+            remote_url("ai-evo-agents", "evo-king", &push),
+            "git@github.com:ai-evo-agents/evo-king.git"
+        );
+        assert_eq!(
+            remote_url("ai-evo-agents", "evo-king", &PushTarget::default()),
+            "https://github.com/ai-evo-agents/evo-king.git"
+        );
+    }
+
+    #[test]
+    fn test_existing_checkout_is_left_alone() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("evo-king")).unwrap();
+        let spec = RepoConfig {
+            repo: "evo-king".into(),
+            local: "evo-king".into(),
+            ..RepoConfig::default()
+        };
+        assert_eq!(bootstrap("ai-evo-agents", &spec, &[], dir.path()), None);
+    }
+}
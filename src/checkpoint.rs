@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::provenance::ChangedFile;
+use crate::state::{now_secs, state_dir};
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A file Phase 4 set out to deliver.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub repo: String,
+    pub file: String,
+}
+
+/// Phase 4 progress of one run, saved after every delivered file so a run
+/// that stops half-way can be resumed without committing anything twice.
+/// Kept as one JSON file under [`state_dir`] and removed once Phase 4
+/// completes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Run the progress belongs to (the first run, when resumed).
+    pub run_id: String,
+    pub started_at: u64,
+    /// Metadata that run was started with.
+    pub metadata: Value,
+    pub planned: Vec<PlannedFile>,
+    /// Files delivered so far, by this run and the runs it resumed.
+    pub delivered: Vec<ChangedFile>,
+}
+
+// ─── Checkpoint ───────────────────────────────────────────────────────────────
+
+impl Checkpoint {
+    /// File name of the checkpoint inside the state dir.
+    pub const FILE_NAME: &'static str = "checkpoint.json";
+
+    /// Default location inside [`state_dir`].
+    pub fn default_path() -> PathBuf {
+        state_dir().join(Self::FILE_NAME)
+    }
+
+    /// A checkpoint at `path` for Phase 4 of run `run_id`, started with
+    /// `metadata`.  `delivered` carries over what an unfinished checkpoint
+    /// recorded.
+    pub fn start(
+        path: &Path,
+        run_id: &str,
+        metadata: &Value,
+        planned: Vec<PlannedFile>,
+        delivered: Vec<ChangedFile>,
+    ) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            run_id: run_id.to_string(),
+            started_at: now_secs(),
+            metadata: metadata.clone(),
+            planned,
+            delivered,
+        }
+    }
+
+    /// The unfinished checkpoint at `path`, if any.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        let mut checkpoint: Self = serde_json::from_str(&text)
+            .with_context(|| format!("parse checkpoint {}", path.display()))?;
+        checkpoint.path = path.to_path_buf();
+        Ok(Some(checkpoint))
+    }
+
+    /// Whether `repo`'s `file` was already delivered with content hashing to
+    /// `sha256`.
+    pub fn delivered(&self, repo: &str, file: &str, sha256: &str) -> bool {
+        self.delivered
+            .iter()
+            .any(|d| d.repo == repo && d.file == file && d.post_sha256 == sha256)
+    }
+
+    /// Planned files not delivered yet, in plan order.
+    pub fn remaining(&self) -> Vec<&PlannedFile> {
+        self.planned
+            .iter()
+            .filter(|planned| {
+                !self
+                    .delivered
+                    .iter()
+                    .any(|d| d.repo == planned.repo && d.file == planned.file)
+            })
+            .collect()
+    }
+
+    /// Planned repos with files not delivered yet, in plan order.
+    pub fn remaining_repos(&self) -> Vec<&str> {
+        let mut repos: Vec<&str> = Vec::new();
+        for planned in self.remaining() {
+            if !repos.contains(&planned.repo.as_str()) {
+                repos.push(&planned.repo);
+            }
+        }
+        repos
+    }
+
+    /// Adds the files `previous` (an unfinished run this one does not
+    /// resume) still had to deliver to this checkpoint's plan, so a run
+    /// targeted at other repos does not drop them.
+    pub fn carry_over(&mut self, previous: &Checkpoint) {
+        for planned in previous.remaining() {
+            if !self.planned.contains(planned) {
+                self.planned.push(planned.clone());
+            }
+        }
+    }
+
+    /// Records `file` as delivered and saves the checkpoint.
+    pub fn record(&mut self, file: &ChangedFile) -> Result<()> {
+        self.delivered.push(file.clone());
+        self.save()
+    }
+
+    /// Writes the checkpoint to disk, creating the state dir if needed.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text = serde_json::to_string_pretty(self).context("serialize checkpoint")?;
+        // Written aside and renamed, so a crash mid-write keeps the last one.
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, text).with_context(|| format!("write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("replace checkpoint {}", self.path.display()))
+    }
+
+    /// Ends the checkpoint once Phase 4 has completed.  Files `previous`
+    /// planned and nobody has delivered yet hand the checkpoint back to that
+    /// run, restricted to them, so `resume` can still finish it; otherwise
+    /// the checkpoint is removed.
+    pub fn complete(self, previous: Option<&Checkpoint>) -> Result<()> {
+        let left: Vec<PlannedFile> = previous
+            .map(|previous| {
+                self.remaining()
+                    .into_iter()
+                    .filter(|planned| previous.planned.contains(planned))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        match previous {
+            Some(previous) if !left.is_empty() => Self::start(
+                &self.path,
+                &previous.run_id,
+                &previous.metadata,
+                left,
+                self.delivered,
+            )
+            .save(),
+            _ => self.finish(),
+        }
+    }
+
+    /// Removes the checkpoint once Phase 4 has completed.
+    pub fn finish(self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("remove checkpoint {}", self.path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn planned(repo: &str, file: &str) -> PlannedFile {
+        PlannedFile {
+            repo: repo.to_string(),
+            file: file.to_string(),
+        }
+    }
+
+    fn changed(repo: &str, file: &str, sha256: &str) -> ChangedFile {
+        ChangedFile {
+            repo: repo.to_string(),
+            file: file.to_string(),
+            pre_sha256: "0".repeat(64),
+            post_sha256: sha256.to_string(),
+            commit: Some("a1b2c3d".to_string()),
+            pr_url: None,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_survives_restart_and_is_carried_over() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(Checkpoint::FILE_NAME);
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let metadata = json!({ "commit_mode": "direct" });
+        let plan = vec![
+            planned("evo-agents", "evo-agent-sdk/Cargo.toml"),
+            planned("evo-king", "Cargo.toml"),
+            planned("evo-king", ".github/workflows/ci.yml"),
+        ];
+        let mut checkpoint = Checkpoint::start(&path, "run-1", &metadata, plan.clone(), Vec::new());
+        checkpoint
+            .record(&changed("evo-agents", "evo-agent-sdk/Cargo.toml", "aa"))
+            .unwrap();
+        checkpoint
+            .record(&changed("evo-king", "Cargo.toml", "bb"))
+            .unwrap();
+
+        let loaded = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.remaining_repos(), vec!["evo-king"]);
+        assert!(loaded.delivered("evo-king", "Cargo.toml", "bb"));
+        assert!(!loaded.delivered("evo-king", "Cargo.toml", "cc"));
+
+        let next = Checkpoint::start(&path, "run-2", &json!({}), plan, loaded.delivered);
+        assert!(next.delivered("evo-agents", "evo-agent-sdk/Cargo.toml", "aa"));
+        next.save().unwrap();
+        next.finish().unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_targeted_run_keeps_the_unfinished_runs_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(Checkpoint::FILE_NAME);
+        let mut crashed = Checkpoint::start(
+            &path,
+            "run-1",
+            &json!({ "commit_mode": "direct" }),
+            vec![
+                planned("evo-king", "Cargo.toml"),
+                planned("evo-runner", "Cargo.toml"),
+                planned("evo-agents", "Cargo.toml"),
+            ],
+            Vec::new(),
+        );
+        crashed
+            .record(&changed("evo-king", "Cargo.toml", "aa"))
+            .unwrap();
+
+        // A webhook run limited to evo-agents starts, and crashes too.
+        let mut targeted = Checkpoint::start(
+            &path,
+            "run-2",
+            &json!({ "repos": ["evo-agents"] }),
+            vec![planned("evo-agents", "Cargo.toml")],
+            crashed.delivered.clone(),
+        );
+        targeted.carry_over(&crashed);
+        assert_eq!(targeted.remaining_repos(), vec!["evo-agents", "evo-runner"]);
+
+        // Completed, it hands the checkpoint back with what run-1 still owes.
+        targeted
+            .record(&changed("evo-agents", "Cargo.toml", "bb"))
+            .unwrap();
+        targeted.complete(Some(&crashed)).unwrap();
+        let left = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(left.run_id, "run-1");
+        assert_eq!(left.metadata, json!({ "commit_mode": "direct" }));
+        assert_eq!(left.remaining_repos(), vec!["evo-runner"]);
+        assert!(left.delivered("evo-agents", "Cargo.toml", "bb"));
+
+        left.complete(None).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+use crate::git::{gh_api, gh_api_with_body};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Name of the check-run (and context of the commit status) on each commit.
+pub const CHECK_NAME: &str = "evo-update verification";
+
+/// Set once GitHub refuses a check-run: only GitHub Apps may create them,
+/// so every later commit of the run goes straight to a commit status.
+static CHECK_RUNS_REFUSED: AtomicBool = AtomicBool::new(false);
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// How the verification result was attached to a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    CheckRun,
+    /// Commit status, for tokens that cannot create check-runs.
+    Status,
+}
+
+/// Outcome of publishing the verification result of one commit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckResult {
+    pub repo: String,
+    /// Full SHA of the commit, once resolved.
+    pub sha: String,
+    /// `None` when nothing could be posted.
+    pub kind: Option<CheckKind>,
+    /// `success` or `failure`.
+    pub conclusion: &'static str,
+    pub error: Option<String>,
+}
+
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+/// Title and Markdown summary of a check-run for the sandbox `commands`
+/// (the `commands` of a `sandbox` summary entry).
+pub fn render(success: bool, commands: &[Value]) -> (String, String) {
+    let title = if success {
+        "Verified in a clean sandbox clone"
+    } else if commands.iter().all(|c| c["success"] == true) {
+        "Committed content differs from the verified content"
+    } else {
+        "Sandbox verification failed"
+    };
+    let mut summary =
+        String::from("`evo-kernel-agent-update` applied this update in a clean clone and ran:\n\n");
+    for command in commands {
+        let mark = if command["success"] == true {
+            "✅"
+        } else {
+            "❌"
+        };
+        summary.push_str(&format!(
+            "- {mark} `{}`\n",
+            command["command"].as_str().unwrap_or_default()
+        ));
+    }
+    if let Some(failed) = commands.iter().find(|c| c["success"] == false) {
+        summary.push_str(&format!(
+            "\n```\n{}\n```\n",
+            failed["output"].as_str().unwrap_or_default().trim_end()
+        ));
+    }
+    (title.to_string(), summary)
+}
+
+// ─── Publishing ───────────────────────────────────────────────────────────────
+
+/// Attaches the verification result to `rev` (a SHA, short SHA or branch)
+/// of `org/repo`: a completed check-run named [`CHECK_NAME`], or a commit
+/// status with that context when check-runs are refused.
+pub fn publish(org: &str, repo: &str, rev: &str, success: bool, commands: &[Value]) -> CheckResult {
+    let conclusion = if success { "success" } else { "failure" };
+    let mut result = CheckResult {
+        repo: repo.to_string(),
+        sha: rev.to_string(),
+        kind: None,
+        conclusion,
+        error: None,
+    };
+    let outcome = resolve_sha(org, repo, rev).and_then(|sha| {
+        result.sha = sha;
+        let (title, summary) = render(success, commands);
+        post(org, repo, &result.sha, conclusion, &title, &summary)
+    });
+    match outcome {
+        Ok(kind) => {
+            info!(repo, sha = %result.sha, ?kind, conclusion, "verification check published");
+            result.kind = Some(kind);
+        }
+        Err(e) => {
+            warn!(repo, rev, error = %e, "publishing the verification check failed");
+            result.error = Some(format!("{e:#}"));
+        }
+    }
+    result
+}
+
+/// The full SHA of `rev`; the check-runs and statuses APIs take no other.
+fn resolve_sha(org: &str, repo: &str, rev: &str) -> Result<String> {
+    if rev.len() == 40 && rev.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(rev.to_string());
+    }
+    gh_api(&[&format!("repos/{org}/{repo}/commits/{rev}"), "--jq", ".sha"])
+        .with_context(|| format!("resolve {rev} in {org}/{repo}"))
+}
+
+fn post(
+    org: &str,
+    repo: &str,
+    sha: &str,
+    conclusion: &str,
+    title: &str,
+    summary: &str,
+) -> Result<CheckKind> {
+    if !CHECK_RUNS_REFUSED.load(Ordering::Relaxed) {
+        let check_run = gh_api_with_body(
+            &[
+                "--method",
+                "POST",
+                &format!("repos/{org}/{repo}/check-runs"),
+            ],
+            &json!({
+                "name": CHECK_NAME,
+                "head_sha": sha,
+                "status": "completed",
+                "conclusion": conclusion,
+                "output": { "title": title, "summary": summary },
+            }),
+        );
+        match check_run {
+            Ok(_) => return Ok(CheckKind::CheckRun),
+            Err(e) if e.to_string().contains("403") || e.to_string().contains("GitHub App") => {
+                info!("check-runs need a GitHub App; falling back to commit statuses");
+                CHECK_RUNS_REFUSED.store(true, Ordering::Relaxed);
+            }
+            Err(e) => return Err(e.context("create check-run")),
+        }
+    }
+    gh_api_with_body(
+        &[
+            "--method",
+            "POST",
+            &format!("repos/{org}/{repo}/statuses/{sha}"),
+        ],
+        &json!({
+            "state": conclusion,
+            "context": CHECK_NAME,
+            "description": title,
+        }),
+    )
+    .context("create commit status")?;
+    Ok(CheckKind::Status)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_lists_commands_and_failure_output() {
+        let commands = vec![
+            json!({ "command": "cargo check", "success": true, "output": "" }),
+            json!({ "command": "cargo test --no-run", "success": false, "output": "error[E0425]: x\n" }),
+        ];
+        let (title, summary) = render(false, &commands);
+        assert_eq!(title, "Sandbox verification failed");
+        assert!(summary.contains("- ✅ `cargo check`\n- ❌ `cargo test --no-run`\n"));
+        assert!(summary.ends_with("```\nerror[E0425]: x\n```\n"));
+
+        let (title, summary) = render(true, &commands[..1]);
+        assert_eq!(title, "Verified in a clean sandbox clone");
+        assert!(!summary.contains("```"));
+        assert_eq!(
+            render(false, &commands[..1]).0,
+            "Committed content differs from the verified content"
+        );
+    }
+}
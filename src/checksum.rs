@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::fixtures::RegistryHttp;
+use crate::registry::{IndexEntry, Registry, fetch_index};
+
+// ─── Pinned checksums ─────────────────────────────────────────────────────────
+
+/// Operator-maintained allowlist of known-good checksums:
+/// `{ "crate": { "version": "sha256-hex" } }`.
+///
+/// A version listed here must match its pin exactly; unlisted versions are
+/// verified against the index only.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct ChecksumPins(BTreeMap<String, BTreeMap<String, String>>);
+
+impl ChecksumPins {
+    /// Loads pins from `path`; a missing file yields no pins.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parse checksum pins {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("read checksum pins {}", path.display())),
+        }
+    }
+
+    /// Pinned checksum for `crate_name@version`, if any.
+    pub fn get(&self, crate_name: &str, version: &str) -> Option<&str> {
+        self.0.get(crate_name)?.get(version).map(String::as_str)
+    }
+}
+
+// ─── Verification ─────────────────────────────────────────────────────────────
+
+/// Downloads `crate_name@version` from `registry` and checks its sha256
+/// against the registry's sparse index checksum (and against `pins`, when
+/// pinned).
+///
+/// Returns the verified hex checksum.  Any mismatch is an error, so a
+/// tampered registry response cannot be propagated across the fleet.
+pub async fn verify_crate(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+    version: &str,
+    pins: &ChecksumPins,
+) -> Result<String> {
+    let expected = index_checksum(client, registry, crate_name, version).await?;
+
+    if let Some(pinned) = pins.get(crate_name, version)
+        && !pinned.eq_ignore_ascii_case(&expected)
+    {
+        anyhow::bail!(
+            "{crate_name}@{version}: index checksum {expected} does not match pinned {pinned}"
+        );
+    }
+
+    let url = registry.download_url(crate_name, version);
+    let download = client.get(&url).await?.ensure_success(&url)?;
+    let actual = sha256_hex(&download.body);
+
+    if !actual.eq_ignore_ascii_case(&expected) {
+        anyhow::bail!(
+            "{crate_name}@{version}: downloaded .crate sha256 {actual} does not match index {expected}"
+        );
+    }
+    Ok(actual)
+}
+
+/// Looks up the checksum of `crate_name@version` in the sparse index.
+async fn index_checksum(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+    version: &str,
+) -> Result<String> {
+    let index = fetch_index(client, registry, crate_name, false).await?;
+    find_checksum(&index, version)
+        .with_context(|| format!("{crate_name}@{version} not found in sparse index"))
+}
+
+/// Finds the checksum of `version` among a crate's index entries.
+fn find_checksum(index: &[IndexEntry], version: &str) -> Option<String> {
+    index
+        .iter()
+        .find(|e| e.vers == version)
+        .map(|e| e.cksum.clone())
+}
+
+/// Lower-case hex sha256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::parse_index;
+
+    #[test]
+    fn test_find_checksum() {
+        let index = concat!(
+            r#"{"name":"evo-common","vers":"0.3.0","deps":[],"cksum":"aaa","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"evo-common","vers":"0.4.0","deps":[],"cksum":"bbb","features":{},"yanked":false}"#,
+            "\n"
+        );
+        let index = parse_index(index);
+        assert_eq!(find_checksum(&index, "0.4.0"), Some("bbb".to_string()));
+        assert_eq!(find_checksum(&index, "9.9.9"), None);
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_checksum_pins() {
+        let pins: ChecksumPins = serde_json::from_str(r#"{"evo-common":{"0.4.0":"abc"}}"#).unwrap();
+        assert_eq!(pins.get("evo-common", "0.4.0"), Some("abc"));
+        assert_eq!(pins.get("evo-common", "0.3.0"), None);
+        assert_eq!(pins.get("other", "0.4.0"), None);
+    }
+}
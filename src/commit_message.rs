@@ -0,0 +1,300 @@
+use regex::Regex;
+
+use crate::versions::VersionReport;
+
+// ─── Default templates ────────────────────────────────────────────────────────
+
+/// Default message for commits that bump tracked deps in a Cargo.toml.
+pub const DEFAULT_CARGO_TEMPLATE: &str =
+    "chore(deps): update dependencies in {file} [run_id={run_id}]";
+
+/// Default message for commits that bump a workflow `sed` pattern.
+pub const DEFAULT_WORKFLOW_TEMPLATE: &str =
+    "ci: bump {crate} to {to} in sed pattern [run_id={run_id}]";
+
+/// Subject of one commit carrying the updates of several files.
+pub const REPO_SUBJECT_TEMPLATE: &str = "chore(deps): update dependencies [run_id={run_id}]";
+
+/// Title of the grouped PR carrying a repo's updates.
+pub const PR_TITLE_TEMPLATE: &str = "chore(deps): update evo dependencies [run_id={run_id}]";
+
+// ─── Templates ────────────────────────────────────────────────────────────────
+
+/// The pair of commit-message templates in effect for one repo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitTemplates {
+    /// Template for Cargo.toml dependency bumps.
+    pub cargo: String,
+    /// Template for workflow `sed` pattern bumps.
+    pub workflow: String,
+}
+
+impl CommitTemplates {
+    /// Global templates: `COMMIT_TEMPLATE_CARGO` / `COMMIT_TEMPLATE_WORKFLOW`
+    /// env vars, falling back to the built-in defaults.
+    pub fn from_env() -> Self {
+        Self {
+            cargo: std::env::var("COMMIT_TEMPLATE_CARGO")
+                .unwrap_or_else(|_| DEFAULT_CARGO_TEMPLATE.to_string()),
+            workflow: std::env::var("COMMIT_TEMPLATE_WORKFLOW")
+                .unwrap_or_else(|_| DEFAULT_WORKFLOW_TEMPLATE.to_string()),
+        }
+    }
+
+    /// Returns a copy with any per-repo overrides applied on top.
+    pub fn with_overrides(&self, cargo: Option<&str>, workflow: Option<&str>) -> Self {
+        Self {
+            cargo: cargo.unwrap_or(&self.cargo).to_string(),
+            workflow: workflow.unwrap_or(&self.workflow).to_string(),
+        }
+    }
+}
+
+/// Values substituted into a commit-message template.
+#[derive(Debug)]
+pub struct MessageVars<'a> {
+    /// Repo slug (without org).
+    pub repo: &'a str,
+    /// Path of the committed file inside the repo.
+    pub file: &'a str,
+    /// Pipeline run id.
+    pub run_id: &'a str,
+    /// Version bumps carried by the commit.
+    pub versions: &'a [VersionReport],
+}
+
+/// Renders `template`, replacing the supported placeholders:
+///
+/// | Placeholder | Value |
+/// |-------------|-------|
+/// | `{repo}` | repo slug |
+/// | `{file}` | committed file path |
+/// | `{run_id}` | pipeline run id |
+/// | `{crate}` | comma-separated crate names |
+/// | `{from}` / `{to}` | old / new version (comma-separated when several) |
+/// | `{versions}` | `crate from → to` for every bump, comma-separated |
+/// | `{bump}` | bump kind (`major`, `minor`, `patch`, `prerelease`, `downgrade`), comma-separated |
+///
+/// Unknown placeholders are left as-is.
+pub fn render(template: &str, vars: &MessageVars<'_>) -> String {
+    let join = |f: fn(&VersionReport) -> String| {
+        vars.versions.iter().map(f).collect::<Vec<_>>().join(", ")
+    };
+
+    template
+        .replace("{repo}", vars.repo)
+        .replace("{file}", vars.file)
+        .replace("{run_id}", vars.run_id)
+        .replace("{crate}", &join(|r| r.crate_name.clone()))
+        .replace("{from}", &join(|r| r.current.clone()))
+        .replace("{to}", &join(|r| r.latest.clone()))
+        .replace("{bump}", &join(|r| r.bump_kind.to_string()))
+        .replace(
+            "{versions}",
+            &join(|r| format!("{} {} → {}", r.crate_name, r.current, r.latest)),
+        )
+}
+
+// ─── Self-update trailer ──────────────────────────────────────────────────────
+
+/// Trailer key marking commits the agent pushed to its own repo.
+pub const SELF_UPDATE_TRAILER: &str = "Evo-Update-Run";
+
+/// `message` with a `Evo-Update-Run: <run_id>` trailer, so automation
+/// reacting to pushes of the agent's repo can recognise its own commits.
+pub fn with_self_update_trailer(message: &str, run_id: &str) -> String {
+    format!(
+        "{}\n\n{SELF_UPDATE_TRAILER}: {run_id}\n",
+        message.trim_end()
+    )
+}
+
+/// Whether `message` carries the [`SELF_UPDATE_TRAILER`].
+pub fn is_self_update(message: &str) -> bool {
+    message
+        .lines()
+        .any(|line| line.starts_with(&format!("{SELF_UPDATE_TRAILER}: ")))
+}
+
+// ─── Conventional-commit header ───────────────────────────────────────────────
+
+/// Message of one commit carrying several files' updates: `subject`, then
+/// the first line of each file's own message as a body bullet.
+pub fn combine(subject: &str, messages: &[&str]) -> String {
+    let mut message = format!("{subject}\n");
+    if !messages.is_empty() {
+        message.push('\n');
+    }
+    for line in messages.iter().filter_map(|m| m.lines().next()) {
+        message.push_str(&format!("- {line}\n"));
+    }
+    message
+}
+
+/// Rewrites the conventional-commit header (`type(scope)!: `) of `message`.
+///
+/// `commit_type` replaces the type and `scope` replaces (or adds) the scope;
+/// `None` keeps the existing part.  An empty `scope` removes the scope.
+/// Messages without a conventional header get one prepended when a type is
+/// given, and are returned unchanged otherwise.
+pub fn apply_type_scope(message: &str, commit_type: Option<&str>, scope: Option<&str>) -> String {
+    if commit_type.is_none() && scope.is_none() {
+        return message.to_string();
+    }
+
+    // SAFETY: the pattern is a literal known to be valid.
+    let re = Regex::new(r"^([A-Za-z]+)(?:\(([^)]*)\))?(!)?: ").expect("header regex is valid");
+
+    let (old_type, old_scope, breaking, subject) = match re.captures(message) {
+        Some(caps) => (
+            Some(caps[1].to_string()),
+            caps.get(2).map(|m| m.as_str().to_string()),
+            caps.get(3).is_some(),
+            &message[caps[0].len()..],
+        ),
+        None => (None, None, false, message),
+    };
+
+    let Some(new_type) = commit_type.map(str::to_string).or(old_type) else {
+        return message.to_string();
+    };
+    let new_scope = match scope {
+        Some(s) => Some(s.to_string()),
+        None => old_scope,
+    };
+
+    let mut header = new_type;
+    if let Some(s) = new_scope.filter(|s| !s.is_empty()) {
+        header.push_str(&format!("({s})"));
+    }
+    if breaking {
+        header.push('!');
+    }
+    format!("{header}: {subject}")
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(name: &str, from: &str, to: &str) -> VersionReport {
+        VersionReport::new(name, from, to)
+    }
+
+    #[test]
+    fn test_render_default_templates_match_legacy_messages() {
+        let versions = [report("evo-agent-sdk", "0.1", "0.2")];
+        let vars = MessageVars {
+            repo: "evo-king",
+            file: "Cargo.toml",
+            run_id: "r1",
+            versions: &versions,
+        };
+        assert_eq!(
+            render(DEFAULT_CARGO_TEMPLATE, &vars),
+            "chore(deps): update dependencies in Cargo.toml [run_id=r1]"
+        );
+        assert_eq!(
+            render(DEFAULT_WORKFLOW_TEMPLATE, &vars),
+            "ci: bump evo-agent-sdk to 0.2 in sed pattern [run_id=r1]"
+        );
+    }
+
+    #[test]
+    fn test_render_multiple_versions() {
+        let versions = [
+            report("evo-common", "0.2", "0.3"),
+            report("evo-agent-sdk", "0.1", "0.2"),
+        ];
+        let vars = MessageVars {
+            repo: "evo-king",
+            file: "Cargo.toml",
+            run_id: "r1",
+            versions: &versions,
+        };
+        assert_eq!(
+            render("build(deps): {versions} in {repo}", &vars),
+            "build(deps): evo-common 0.2 → 0.3, evo-agent-sdk 0.1 → 0.2 in evo-king"
+        );
+        assert_eq!(
+            render("chore(deps): {bump} bumps", &vars),
+            "chore(deps): major, major bumps"
+        );
+        assert_eq!(render("{unknown}", &vars), "{unknown}");
+    }
+
+    #[test]
+    fn test_apply_type_scope() {
+        let msg = "chore(deps): update dependencies in Cargo.toml";
+        assert_eq!(apply_type_scope(msg, None, None), msg);
+        assert_eq!(
+            apply_type_scope(msg, Some("build"), None),
+            "build(deps): update dependencies in Cargo.toml"
+        );
+        assert_eq!(
+            apply_type_scope(msg, None, Some("sdk")),
+            "chore(sdk): update dependencies in Cargo.toml"
+        );
+        assert_eq!(
+            apply_type_scope("ci: bump sdk", Some("build"), Some("ci")),
+            "build(ci): bump sdk"
+        );
+        assert_eq!(
+            apply_type_scope(msg, None, Some("")),
+            "chore: update dependencies in Cargo.toml"
+        );
+    }
+
+    #[test]
+    fn test_apply_type_scope_without_header() {
+        assert_eq!(apply_type_scope("bump sdk", None, Some("deps")), "bump sdk");
+        assert_eq!(
+            apply_type_scope("bump sdk", Some("chore"), Some("deps")),
+            "chore(deps): bump sdk"
+        );
+        assert_eq!(
+            apply_type_scope("feat(x)!: drop api", None, Some("y")),
+            "feat(y)!: drop api"
+        );
+    }
+
+    #[test]
+    fn test_combine() {
+        assert_eq!(
+            combine(
+                "chore(deps): update dependencies [run_id=r1]",
+                &[
+                    "chore(deps): update dependencies in Cargo.toml [run_id=r1]\n\nbody",
+                    "ci: bump evo-agent-sdk to 0.2 in sed pattern [run_id=r1]",
+                ]
+            ),
+            "chore(deps): update dependencies [run_id=r1]\n\n\
+             - chore(deps): update dependencies in Cargo.toml [run_id=r1]\n\
+             - ci: bump evo-agent-sdk to 0.2 in sed pattern [run_id=r1]\n"
+        );
+    }
+
+    #[test]
+    fn test_with_overrides() {
+        let global = CommitTemplates {
+            cargo: "a".to_string(),
+            workflow: "b".to_string(),
+        };
+        let repo = global.with_overrides(Some("c"), None);
+        assert_eq!(repo.cargo, "c");
+        assert_eq!(repo.workflow, "b");
+    }
+
+    #[test]
+    fn test_self_update_trailer() {
+        let message = with_self_update_trailer("chore(deps): bump evo-common\n", "r1");
+        assert_eq!(
+            message,
+            "chore(deps): bump evo-common\n\nEvo-Update-Run: r1\n"
+        );
+        assert!(is_self_update(&message));
+        assert!(!is_self_update("chore(deps): bump evo-common"));
+    }
+}
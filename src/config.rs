@@ -0,0 +1,669 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+use crate::checksum::sha256_hex;
+use crate::file_sync::FileSyncCampaign;
+use crate::fleet_tag::FleetTagConfig;
+use crate::git::PushTarget;
+use crate::license::LicensePolicy;
+use crate::llm::LlmConfig;
+use crate::policy::AutoApply;
+use crate::registry::{Registries, Registry};
+use crate::release::{ReleaseBump, ReleaseConfig};
+use crate::risk::MajorBumpGate;
+use crate::rollout::{self, RolloutConfig};
+use crate::sandbox::SandboxConfig;
+use crate::schemas::SchemaConfig;
+use crate::template::TemplateConfig;
+use crate::updater::PatchBackend;
+use crate::workflow_sync::WorkflowSyncConfig;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Default config file location (relative to the working directory).
+const DEFAULT_CONFIG_PATH: &str = "update-agent.toml";
+
+/// `config_version` reported when no config file is in use.
+pub const BUILTIN_VERSION: &str = "builtin";
+
+// ─── Config types ─────────────────────────────────────────────────────────────
+
+/// One managed repository (see `RepoSpec` in `main.rs` for field docs).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub repo: String,
+    /// Defaults to `repo` when empty.
+    pub local: String,
+    pub cargo_files: Vec<String>,
+    pub workflow_files: Vec<String>,
+    pub python_files: Vec<String>,
+    pub npm_files: Vec<String>,
+    pub go_files: Vec<String>,
+    pub cargo_commit_template: Option<String>,
+    pub workflow_commit_template: Option<String>,
+    pub commit_type: Option<String>,
+    pub commit_scope: Option<String>,
+    pub extra_crates: Vec<String>,
+    pub excluded_crates: Vec<String>,
+    pub frozen: bool,
+    pub frozen_until: Option<String>,
+    pub deny_crates: Vec<String>,
+    pub allow_crates: Option<Vec<String>>,
+    pub commit_strategy: Option<RepoCommitStrategy>,
+    pub commit_granularity: Option<CommitGranularity>,
+    pub push_remote: Option<String>,
+    pub push_refspec: Option<String>,
+    pub deploy_key_env: Option<String>,
+    pub sparse_checkout: bool,
+    pub verify: Option<Vec<String>>,
+    pub patcher: Option<PatchBackend>,
+    pub release_bump: Option<ReleaseBump>,
+    pub release_workflow: Option<String>,
+    pub depends_on: Vec<String>,
+    pub schema_dir: Option<String>,
+    pub file_sync_opt_out: Vec<String>,
+    pub from_template: bool,
+    pub workflow_templates: Vec<String>,
+    pub workflow_params: BTreeMap<String, String>,
+    pub license_exempt: bool,
+}
+
+/// How a repo's updates must be delivered, overriding the run-wide
+/// commit mode and the gh → local git → REST fallback order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoCommitStrategy {
+    /// Commit through gh CLI only (a grouped PR when the run asks for one).
+    GhCli,
+    /// Commit and push from the local checkout only; never a PR.
+    LocalGit,
+    /// Always open a PR, whatever the run's commit mode; never push directly.
+    PrOnly,
+}
+
+/// How a repo's pending updates map to commits and PRs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitGranularity {
+    /// One commit per changed file.
+    #[default]
+    File,
+    /// One commit carrying all of the repo's changed files.
+    Repo,
+    /// Every update on the run's `evo-update/<run_id>` branch, in one PR.
+    SingleRunBranch,
+}
+
+impl RepoConfig {
+    /// Where local git commits of this repo are pushed.
+    pub fn push_target(&self) -> PushTarget {
+        PushTarget {
+            remote: self.push_remote.clone(),
+            refspec: self.push_refspec.clone(),
+            deploy_key_env: self.deploy_key_env.clone(),
+        }
+    }
+
+    /// Crates kept current in this repo: `global` minus `excluded_crates`,
+    /// plus `extra_crates`.
+    pub fn tracked_crates<'a>(&'a self, global: &'a [String]) -> Vec<&'a str> {
+        let mut crates: Vec<&str> = global
+            .iter()
+            .filter(|c| !self.excluded_crates.contains(c))
+            .map(String::as_str)
+            .collect();
+        for extra in &self.extra_crates {
+            if !crates.contains(&extra.as_str()) {
+                crates.push(extra);
+            }
+        }
+        crates
+    }
+}
+
+/// Global crate allow/deny lists from the config file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    pub deny_crates: Vec<String>,
+    pub allow_crates: Option<Vec<String>>,
+    /// Handling of major bumps the changelog analysis flags as risky.
+    pub major_bumps: MajorBumpGate,
+    /// `security_only` holds every update that fixes no advisory.
+    pub auto_apply: AutoApply,
+}
+
+/// The effective configuration for one run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpdateConfig {
+    /// Crates checked on crates.io and propagated to every repo.
+    pub tracked_crates: Vec<String>,
+    /// Python packages checked on PyPI and kept current in every repo's
+    /// `python_files`.
+    pub tracked_python_packages: Vec<String>,
+    /// npm packages checked on the npm registry and kept current in every
+    /// repo's `npm_files`.
+    pub tracked_npm_packages: Vec<String>,
+    /// Go modules checked on the module proxy and kept current in every
+    /// repo's `go_files`.
+    pub tracked_go_modules: Vec<String>,
+    /// Global crate policy (environment variables take precedence).
+    pub policy: PolicyConfig,
+    /// Clean-clone verification before committing.
+    pub sandbox: SandboxConfig,
+    /// Model parameters and prompt of the gateway calls.
+    pub llm: LlmConfig,
+    /// Tagging of the fleet after a green update wave.
+    pub fleet_tag: FleetTagConfig,
+    /// GitHub release summarizing each wave.
+    pub release: ReleaseConfig,
+    /// Registries each tracked crate's latest version is looked up in.
+    pub registries: Registries,
+    /// Source repo of each tracked crate not living in a repo of its name.
+    pub crate_repos: BTreeMap<String, String>,
+    /// Tier-by-tier application along the repos' `depends_on` graph.
+    pub rollout: RolloutConfig,
+    /// Canonical schema files copied into each repo's `schema_dir`.
+    pub schemas: SchemaConfig,
+    /// Shared files kept identical across the fleet.
+    pub file_sync: Vec<FileSyncCampaign>,
+    /// Template the `from_template` repos are compared with.
+    pub template: TemplateConfig,
+    /// Canonical CI workflows rendered into each repo's `workflow_templates`.
+    pub workflow_sync: WorkflowSyncConfig,
+    /// SPDX headers and license texts required in every repo.
+    pub license: LicensePolicy,
+    /// Managed repositories, in processing order.
+    pub repos: Vec<RepoConfig>,
+}
+
+impl UpdateConfig {
+    /// Every crate whose latest version Phase 1 must resolve:
+    /// `tracked_crates` plus each repo's `extra_crates`.
+    pub fn all_tracked_crates(&self) -> Vec<&str> {
+        let mut crates: Vec<&str> = self.tracked_crates.iter().map(String::as_str).collect();
+        for extra in self.repos.iter().flat_map(|r| &r.extra_crates) {
+            if !crates.contains(&extra.as_str()) {
+                crates.push(extra);
+            }
+        }
+        crates
+    }
+
+    /// The repo `crate_name` is developed and tagged in.
+    pub fn crate_repo<'a>(&'a self, crate_name: &'a str) -> &'a str {
+        self.crate_repos
+            .get(crate_name)
+            .map_or(crate_name, String::as_str)
+    }
+
+    /// Parses a config file, taking any section it omits from `builtin`.
+    pub fn parse(text: &str, builtin: &UpdateConfig) -> Result<Self> {
+        let file: ConfigFile = toml_edit::de::from_str(text).context("parse config file")?;
+        let mut repos = file.repos.unwrap_or_else(|| builtin.repos.clone());
+        for (idx, repo) in repos.iter_mut().enumerate() {
+            anyhow::ensure!(!repo.repo.is_empty(), "repos[{idx}] has no `repo` name");
+            if repo.local.is_empty() {
+                repo.local = repo.repo.clone();
+            }
+        }
+        rollout::tiers(&repos)?;
+        let file_sync = file.file_sync.unwrap_or_else(|| builtin.file_sync.clone());
+        for (idx, campaign) in file_sync.iter().enumerate() {
+            anyhow::ensure!(!campaign.name.is_empty(), "file_sync[{idx}] has no `name`");
+            anyhow::ensure!(
+                file_sync[..idx].iter().all(|c| c.name != campaign.name),
+                "file_sync campaign {} is defined twice",
+                campaign.name
+            );
+        }
+        let rollout = file.rollout.unwrap_or_else(|| builtin.rollout.clone());
+        if let Some(canary) = &rollout.canary {
+            anyhow::ensure!(
+                repos.iter().any(|r| r.repo == *canary),
+                "rollout.canary names unknown repo {canary}"
+            );
+        }
+        let registries = Registries {
+            named: file
+                .registries
+                .unwrap_or_else(|| builtin.registries.named.clone()),
+            order: file
+                .crate_registries
+                .unwrap_or_else(|| builtin.registries.order.clone()),
+        };
+        let undefined = registries.undefined();
+        anyhow::ensure!(
+            undefined.is_empty(),
+            "crate_registries names undefined registries: {}",
+            undefined.join(", ")
+        );
+        Ok(Self {
+            tracked_crates: file
+                .tracked_crates
+                .unwrap_or_else(|| builtin.tracked_crates.clone()),
+            tracked_python_packages: file
+                .tracked_python_packages
+                .unwrap_or_else(|| builtin.tracked_python_packages.clone()),
+            tracked_npm_packages: file
+                .tracked_npm_packages
+                .unwrap_or_else(|| builtin.tracked_npm_packages.clone()),
+            tracked_go_modules: file
+                .tracked_go_modules
+                .unwrap_or_else(|| builtin.tracked_go_modules.clone()),
+            policy: file.policy.unwrap_or_else(|| builtin.policy.clone()),
+            sandbox: file.sandbox.unwrap_or_else(|| builtin.sandbox.clone()),
+            llm: file.llm.unwrap_or_else(|| builtin.llm.clone()),
+            fleet_tag: file.fleet_tag.unwrap_or_else(|| builtin.fleet_tag.clone()),
+            release: file.release.unwrap_or_else(|| builtin.release.clone()),
+            registries,
+            crate_repos: file
+                .crate_repos
+                .unwrap_or_else(|| builtin.crate_repos.clone()),
+            rollout,
+            schemas: file.schemas.unwrap_or_else(|| builtin.schemas.clone()),
+            file_sync,
+            template: file.template.unwrap_or_else(|| builtin.template.clone()),
+            workflow_sync: file
+                .workflow_sync
+                .unwrap_or_else(|| builtin.workflow_sync.clone()),
+            license: file.license.unwrap_or_else(|| builtin.license.clone()),
+            repos,
+        })
+    }
+}
+
+/// On-disk layout of the config file; every section is optional.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    tracked_crates: Option<Vec<String>>,
+    tracked_python_packages: Option<Vec<String>>,
+    tracked_npm_packages: Option<Vec<String>>,
+    tracked_go_modules: Option<Vec<String>>,
+    policy: Option<PolicyConfig>,
+    sandbox: Option<SandboxConfig>,
+    llm: Option<LlmConfig>,
+    fleet_tag: Option<FleetTagConfig>,
+    release: Option<ReleaseConfig>,
+    registries: Option<BTreeMap<String, Registry>>,
+    crate_registries: Option<BTreeMap<String, Vec<String>>>,
+    crate_repos: Option<BTreeMap<String, String>>,
+    rollout: Option<RolloutConfig>,
+    schemas: Option<SchemaConfig>,
+    file_sync: Option<Vec<FileSyncCampaign>>,
+    template: Option<TemplateConfig>,
+    workflow_sync: Option<WorkflowSyncConfig>,
+    license: Option<LicensePolicy>,
+    repos: Option<Vec<RepoConfig>>,
+}
+
+// ─── Hot-reloading store ──────────────────────────────────────────────────────
+
+/// The configuration in effect for a run, with its provenance.
+#[derive(Debug, Clone)]
+pub struct ConfigSnapshot {
+    pub config: Arc<UpdateConfig>,
+    /// `sha256:<12 hex>` of the file content, or [`BUILTIN_VERSION`].
+    pub version: String,
+    /// Why the latest file could not be used, if it was rejected.
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Loaded {
+    /// `(mtime, len)` of the file when it was last read.
+    stamp: (SystemTime, u64),
+    snapshot: ConfigSnapshot,
+}
+
+/// Serves the current configuration, re-reading the config file whenever it
+/// changes on disk so edits take effect on the next run without a restart.
+///
+/// A file that fails to parse is reported and ignored; the last good
+/// configuration (or the built-in one) stays in effect.
+#[derive(Debug)]
+pub struct ConfigStore {
+    path: PathBuf,
+    builtin: Arc<UpdateConfig>,
+    loaded: Mutex<Option<Loaded>>,
+}
+
+impl ConfigStore {
+    /// A store for the file at `path`, falling back to `builtin`.
+    pub fn new(path: &Path, builtin: UpdateConfig) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            builtin: Arc::new(builtin),
+            loaded: Mutex::new(None),
+        }
+    }
+
+    /// A store for `UPDATE_CONFIG` (default `update-agent.toml`).
+    pub fn from_env(builtin: UpdateConfig) -> Self {
+        let path = std::env::var("UPDATE_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.into());
+        Self::new(Path::new(&path), builtin)
+    }
+
+    /// The configuration to use for the run starting now.
+    pub fn current(&self) -> ConfigSnapshot {
+        let mut loaded = self.loaded.lock().expect("config store lock");
+
+        let stamp = match std::fs::metadata(&self.path) {
+            Ok(meta) => (
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                meta.len(),
+            ),
+            Err(_) => {
+                if loaded.take().is_some() {
+                    info!(path = %self.path.display(), "config file removed — using built-in configuration");
+                }
+                return self.builtin_snapshot(None);
+            }
+        };
+        if let Some(ref l) = *loaded
+            && l.stamp == stamp
+        {
+            return l.snapshot.clone();
+        }
+
+        let previous = loaded.as_ref().map(|l| l.snapshot.clone());
+        let result = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("read {}", self.path.display()))
+            .and_then(|text| {
+                let version = format!("sha256:{}", &sha256_hex(text.as_bytes())[..12]);
+                UpdateConfig::parse(&text, &self.builtin).map(|c| (c, version))
+            });
+        match result {
+            Ok((config, version)) => {
+                if previous.as_ref().map(|p| &p.version) != Some(&version) {
+                    info!(path = %self.path.display(), version = %version, "configuration loaded");
+                }
+                let snapshot = ConfigSnapshot {
+                    config: Arc::new(config),
+                    version,
+                    error: None,
+                };
+                *loaded = Some(Loaded {
+                    stamp,
+                    snapshot: snapshot.clone(),
+                });
+                snapshot
+            }
+            Err(e) => {
+                warn!(path = %self.path.display(), error = %e, "config file rejected — keeping previous configuration");
+                let error = Some(format!("{e:#}"));
+                match previous {
+                    Some(prev) => ConfigSnapshot { error, ..prev },
+                    None => self.builtin_snapshot(error),
+                }
+            }
+        }
+    }
+
+    fn builtin_snapshot(&self, error: Option<String>) -> ConfigSnapshot {
+        ConfigSnapshot {
+            config: Arc::clone(&self.builtin),
+            version: BUILTIN_VERSION.to_string(),
+            error,
+        }
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_sync::SyncMode;
+    use crate::rollout::TierGate;
+    use tempfile::TempDir;
+
+    fn builtin() -> UpdateConfig {
+        UpdateConfig {
+            tracked_crates: vec!["evo-common".into(), "evo-agent-sdk".into()],
+            tracked_python_packages: vec!["evo-agent-sdk-py".into()],
+            tracked_npm_packages: vec!["@evo/agent-client".into()],
+            tracked_go_modules: Vec::new(),
+            policy: PolicyConfig::default(),
+            sandbox: SandboxConfig::default(),
+            llm: LlmConfig::default(),
+            fleet_tag: FleetTagConfig::default(),
+            release: ReleaseConfig::default(),
+            registries: Registries::default(),
+            crate_repos: BTreeMap::from([("evo-agent-sdk".into(), "evo-agents".into())]),
+            rollout: RolloutConfig::default(),
+            schemas: SchemaConfig::default(),
+            file_sync: Vec::new(),
+            template: TemplateConfig::default(),
+            workflow_sync: WorkflowSyncConfig::default(),
+            license: LicensePolicy::default(),
+            repos: vec![RepoConfig {
+                repo: "evo-king".into(),
+                local: "evo-king".into(),
+                cargo_files: vec!["Cargo.toml".into()],
+                ..RepoConfig::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_parse_overrides_and_defaults() {
+        let text = r#"
+tracked_python_packages = ["evo-agent-sdk-py", "evo-tools"]
+tracked_go_modules = ["github.com/ai-evo-agents/evo-agent-sdk-go"]
+
+[policy]
+deny_crates = ["evo-common"]
+
+[[repos]]
+repo = "evo-new"
+cargo_files = ["Cargo.toml"]
+python_files = ["agent/pyproject.toml"]
+npm_files = ["web/package.json"]
+go_files = ["go.mod"]
+extra_crates = ["evo-extra"]
+commit_strategy = "pr_only"
+commit_granularity = "single-run-branch"
+push_remote = "fork"
+verify = ["cargo test --no-run"]
+patcher = "cargo_edit"
+release_bump = "minor"
+release_workflow = ".github/workflows/release.yml"
+schema_dir = "proto"
+file_sync_opt_out = ["issue-templates"]
+from_template = true
+workflow_templates = ["ci.yml"]
+workflow_params = { features = "full" }
+license_exempt = true
+
+[sandbox]
+enabled = true
+
+[fleet_tag]
+name = "sdk-{evo-agent-sdk:minor}-rollout"
+
+[release]
+repo = "evo-ops"
+
+[registries.mirror]
+api = "https://mirror.example/api/v1"
+index = "https://mirror.example/index"
+download = "https://mirror.example/dl/{crate}/{version}"
+
+[crate_registries]
+evo-agent-sdk = ["crates-io", "mirror"]
+
+[crate_repos]
+evo-common = "evo-core"
+
+[rollout]
+tier_gate = "checks"
+canary = "evo-new"
+
+[schemas]
+source_dir = "proto"
+
+[[file_sync]]
+name = "lint-config"
+files = ["rustfmt.toml", "deny.toml"]
+mode = "check"
+
+[template]
+open_prs = true
+
+[workflow_sync]
+source_dir = "ci"
+
+[license]
+expression = "MIT OR Apache-2.0"
+mode = "check"
+"#;
+        let config = UpdateConfig::parse(text, &builtin()).unwrap();
+        assert_eq!(config.tracked_crates, builtin().tracked_crates);
+        assert_eq!(
+            config.tracked_python_packages,
+            vec!["evo-agent-sdk-py", "evo-tools"]
+        );
+        assert_eq!(config.policy.deny_crates, vec!["evo-common"]);
+        assert_eq!(config.repos.len(), 1);
+        assert_eq!(config.repos[0].python_files, vec!["agent/pyproject.toml"]);
+        assert_eq!(config.repos[0].npm_files, vec!["web/package.json"]);
+        assert_eq!(config.repos[0].go_files, vec!["go.mod"]);
+        assert_eq!(
+            config.tracked_go_modules,
+            vec!["github.com/ai-evo-agents/evo-agent-sdk-go"]
+        );
+        assert_eq!(config.tracked_npm_packages, builtin().tracked_npm_packages);
+        assert_eq!(config.repos[0].local, "evo-new");
+        assert_eq!(
+            config.repos[0].commit_strategy,
+            Some(RepoCommitStrategy::PrOnly)
+        );
+        assert_eq!(
+            config.repos[0].commit_granularity,
+            Some(CommitGranularity::SingleRunBranch)
+        );
+        assert_eq!(config.repos[0].push_target().to_string(), "fork HEAD");
+        assert_eq!(
+            config.repos[0].verify.as_deref(),
+            Some(&["cargo test --no-run".to_string()][..])
+        );
+        assert_eq!(config.repos[0].patcher, Some(PatchBackend::CargoEdit));
+        assert_eq!(config.repos[0].release_bump, Some(ReleaseBump::Minor));
+        assert_eq!(config.crate_repo("evo-common"), "evo-core");
+        assert_eq!(config.crate_repo("evo-agent-sdk"), "evo-agent-sdk");
+        assert!(config.sandbox.enabled);
+        assert_eq!(config.sandbox.verify, SandboxConfig::default().verify);
+        assert_eq!(
+            config.fleet_tag.name.as_deref(),
+            Some("sdk-{evo-agent-sdk:minor}-rollout")
+        );
+        assert_eq!(config.fleet_tag.ops_repo, None);
+        assert_eq!(config.release.repo.as_deref(), Some("evo-ops"));
+        assert_eq!(config.release.tag, ReleaseConfig::default().tag);
+        assert_eq!(config.rollout.tier_gate, TierGate::Checks);
+        assert_eq!(config.rollout.canary.as_deref(), Some("evo-new"));
+        assert_eq!(config.repos[0].schema_dir.as_deref(), Some("proto"));
+        assert_eq!(config.schemas.source_repo, "evo-common");
+        assert_eq!(config.schemas.source_dir, "proto");
+        assert_eq!(config.repos[0].file_sync_opt_out, vec!["issue-templates"]);
+        assert_eq!(config.file_sync[0].source_repo, "evo-common");
+        assert_eq!(config.file_sync[0].mode, SyncMode::Check);
+        assert!(config.repos[0].from_template);
+        assert!(config.template.open_prs);
+        assert_eq!(config.template.repo, "evo-user-agent-template");
+        assert_eq!(config.repos[0].workflow_templates, vec!["ci.yml"]);
+        assert_eq!(config.repos[0].workflow_params["features"], "full");
+        assert_eq!(config.workflow_sync.source_repo, "evo-common");
+        assert_eq!(config.workflow_sync.source_dir, "ci");
+        assert!(config.repos[0].license_exempt);
+        assert_eq!(
+            config.license.expression.as_deref(),
+            Some("MIT OR Apache-2.0")
+        );
+        assert_eq!(config.license.files, vec!["LICENSE"]);
+        assert_eq!(config.license.mode, SyncMode::Check);
+        assert_eq!(
+            config.rollout.timeout_secs,
+            RolloutConfig::default().timeout_secs
+        );
+        let sdk: Vec<String> = config
+            .registries
+            .for_crate("evo-agent-sdk")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(sdk, vec!["crates-io", "mirror"]);
+        assert_eq!(
+            config.all_tracked_crates(),
+            vec!["evo-common", "evo-agent-sdk", "evo-extra"]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys_and_nameless_repos() {
+        assert!(UpdateConfig::parse("tracked = []", &builtin()).is_err());
+        assert!(UpdateConfig::parse("[[repos]]\nlocal = \"x\"\n", &builtin()).is_err());
+        assert!(
+            UpdateConfig::parse("[crate_registries]\n\"*\" = [\"corp\"]\n", &builtin()).is_err()
+        );
+        assert!(UpdateConfig::parse("[rollout]\ncanary = \"evo-new\"\n", &builtin()).is_err());
+        assert!(
+            UpdateConfig::parse(
+                "[[repos]]\nrepo = \"a\"\ndepends_on = [\"evo-king\"]\n",
+                &builtin()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_repo_tracked_crates() {
+        let global = vec!["evo-common".to_string(), "evo-agent-sdk".to_string()];
+        let repo = RepoConfig {
+            excluded_crates: vec!["evo-common".into()],
+            extra_crates: vec!["evo-extra".into(), "evo-agent-sdk".into()],
+            ..RepoConfig::default()
+        };
+        assert_eq!(
+            repo.tracked_crates(&global),
+            vec!["evo-agent-sdk", "evo-extra"]
+        );
+    }
+
+    #[test]
+    fn test_store_reloads_on_change_and_keeps_last_good() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("update-agent.toml");
+        let store = ConfigStore::new(&path, builtin());
+
+        let snap = store.current();
+        assert_eq!(snap.version, BUILTIN_VERSION);
+
+        std::fs::write(&path, "tracked_crates = [\"evo-common\"]\n").unwrap();
+        let first = store.current();
+        assert!(first.version.starts_with("sha256:"));
+        assert_eq!(first.config.tracked_crates, vec!["evo-common"]);
+
+        std::fs::write(&path, "tracked_crates = [\"evo-common\", \"evo-x\"]\n").unwrap();
+        let second = store.current();
+        assert_ne!(second.version, first.version);
+        assert_eq!(second.config.tracked_crates.len(), 2);
+
+        std::fs::write(&path, "not = [valid").unwrap();
+        let broken = store.current();
+        assert_eq!(broken.version, second.version);
+        assert!(broken.error.is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(store.current().version, BUILTIN_VERSION);
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use crate::net::apply_to_command;
+use crate::validate::{scratch_manifest_dir, uses_workspace_inheritance};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// `cargo deny` checks enforced after an update.  Advisories are covered by
+/// `cargo audit` (see `audit.rs`).
+const CHECKS: &[&str] = &["bans", "licenses", "sources"];
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Outcome of `cargo deny check` on a patched manifest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DenyReport {
+    /// Checks that reported at least one error (e.g. `["licenses"]`).
+    pub failed_checks: Vec<String>,
+    /// Error-level diagnostic messages, in output order.
+    pub errors: Vec<String>,
+}
+
+impl DenyReport {
+    /// `true` when the update violates the repo's policy.
+    pub fn is_violation(&self) -> bool {
+        !self.failed_checks.is_empty()
+    }
+}
+
+// ─── Policy check ─────────────────────────────────────────────────────────────
+
+/// Whether the `cargo deny` subcommand is installed (checked once).
+pub fn cargo_deny_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("cargo")
+            .args(["deny", "--version"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Finds the repo's `deny.toml`: next to the manifest, otherwise at the
+/// repo root.
+pub fn find_deny_config(repo_base: &Path, manifest_path: &Path) -> Option<PathBuf> {
+    manifest_path
+        .parent()
+        .map(|dir| dir.join("deny.toml"))
+        .into_iter()
+        .chain(std::iter::once(repo_base.join("deny.toml")))
+        .find(|p| p.is_file())
+}
+
+/// Runs `cargo deny check bans licenses sources` with the repo's own
+/// `config` against the patched manifest in a scratch dir (seeded with the
+/// repo's `lockfile`).
+///
+/// Returns `Ok(None)` when the check cannot run: `cargo deny` is missing or
+/// the manifest inherits from a workspace that is not available here.
+pub fn deny_check(
+    config: &Path,
+    lockfile: Option<&Path>,
+    patched_manifest: &str,
+) -> Result<Option<DenyReport>> {
+    if !cargo_deny_available() {
+        return Ok(None);
+    }
+    if uses_workspace_inheritance(patched_manifest) {
+        debug!("manifest uses workspace inheritance — skipping cargo deny");
+        return Ok(None);
+    }
+
+    let dir = scratch_manifest_dir(patched_manifest, lockfile)?;
+    let output = apply_to_command(Command::new("cargo").args(["deny", "--format", "json"]))
+        .arg("--manifest-path")
+        .arg(dir.path().join("Cargo.toml"))
+        .arg("check")
+        .arg("--config")
+        .arg(config)
+        .args(CHECKS)
+        .output()
+        .context("run cargo deny")?;
+
+    // cargo-deny writes its JSON diagnostics to stderr, one object per line.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let report = parse_deny_output(&stderr);
+    if !output.status.success() && !report.is_violation() {
+        anyhow::bail!("cargo deny failed: {}", stderr.trim());
+    }
+    Ok(Some(report))
+}
+
+/// Parses cargo-deny's JSON-lines output: failed checks come from the
+/// `summary` record, messages from error-level `diagnostic` records.
+fn parse_deny_output(output: &str) -> DenyReport {
+    let mut report = DenyReport::default();
+    for record in output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+    {
+        let fields = &record["fields"];
+        match record["type"].as_str() {
+            Some("diagnostic") if fields["severity"] == "error" => {
+                if let Some(msg) = fields["message"].as_str() {
+                    report.errors.push(msg.to_string());
+                }
+            }
+            Some("summary") => {
+                for &check in CHECKS {
+                    if fields[check]["errors"].as_u64().unwrap_or(0) > 0 {
+                        report.failed_checks.push(check.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deny_output_reports_failed_checks() {
+        let output = concat!(
+            r#"{"type":"diagnostic","fields":{"severity":"error","message":"failed to satisfy license requirements","code":"rejected"}}"#,
+            "\n",
+            r#"{"type":"diagnostic","fields":{"severity":"warning","message":"duplicate entries","code":"duplicate"}}"#,
+            "\n",
+            r#"{"type":"summary","fields":{"bans":{"errors":0,"warnings":1},"licenses":{"errors":1,"warnings":0},"sources":{"errors":0,"warnings":0}}}"#,
+            "\n"
+        );
+        let report = parse_deny_output(output);
+        assert_eq!(report.failed_checks, vec!["licenses"]);
+        assert_eq!(
+            report.errors,
+            vec!["failed to satisfy license requirements"]
+        );
+        assert!(report.is_violation());
+    }
+
+    #[test]
+    fn test_parse_deny_output_clean() {
+        let output = r#"{"type":"summary","fields":{"bans":{"errors":0},"licenses":{"errors":0},"sources":{"errors":0}}}"#;
+        let report = parse_deny_output(output);
+        assert!(!report.is_violation());
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn test_find_deny_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("sdk/Cargo.toml");
+        assert_eq!(find_deny_config(dir.path(), &manifest), None);
+
+        std::fs::write(dir.path().join("deny.toml"), "").unwrap();
+        assert_eq!(
+            find_deny_config(dir.path(), &manifest),
+            Some(dir.path().join("deny.toml"))
+        );
+    }
+}
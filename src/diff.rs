@@ -0,0 +1,281 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::checksum::sha256_hex;
+
+// ─── Line diffing ─────────────────────────────────────────────────────────────
+
+/// Number of unchanged lines shown around each change in a unified diff.
+const CONTEXT_LINES: usize = 3;
+
+/// Diff lines shown per file in an approval question or a notification.
+pub const SNIPPET_LINES: usize = 20;
+
+/// Longest diff line shown in a [`snippet`] of a question or notification.
+pub const SNIPPET_LINE_CHARS: usize = 160;
+
+/// A single line-level edit produced by [`diff_lines`].
+#[derive(Debug, Clone, PartialEq)]
+enum Edit<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+}
+
+/// Computes a line-level edit script turning `old` into `new` using a
+/// longest-common-subsequence table.
+///
+/// Manifests and workflow files are small, so the quadratic table is fine.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            edits.push(Edit::Keep(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            edits.push(Edit::Remove(old[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Add(new[j]));
+            j += 1;
+        }
+    }
+    edits.extend(old[i..].iter().map(|l| Edit::Remove(l)));
+    edits.extend(new[j..].iter().map(|l| Edit::Add(l)));
+    edits
+}
+
+/// Renders a unified diff of `old` → `new` for `path`.
+///
+/// Returns an empty string when the contents are identical.
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    if edits.iter().all(|e| matches!(e, Edit::Keep(_))) {
+        return String::new();
+    }
+
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+
+    // Group changed edits into hunks padded with CONTEXT_LINES of context.
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Keep(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changed {
+        let start = idx.saturating_sub(CONTEXT_LINES);
+        let end = (idx + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        // Line numbers (1-based) at the start of the hunk in each file.
+        let old_start = 1 + edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Add(_)))
+            .count();
+        let new_start = 1 + edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Remove(_)))
+            .count();
+        let slice = &edits[start..end];
+        let old_len = slice.iter().filter(|e| !matches!(e, Edit::Add(_))).count();
+        let new_len = slice
+            .iter()
+            .filter(|e| !matches!(e, Edit::Remove(_)))
+            .count();
+
+        out.push_str(&format!(
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@\n"
+        ));
+        for edit in slice {
+            match edit {
+                Edit::Keep(l) => out.push_str(&format!(" {l}\n")),
+                Edit::Remove(l) => out.push_str(&format!("-{l}\n")),
+                Edit::Add(l) => out.push_str(&format!("+{l}\n")),
+            }
+        }
+    }
+
+    out
+}
+
+/// The first `max_lines` lines of a unified `diff`, for messages read
+/// without a diff viewer.  Lines are cut at `max_line_chars` and a final
+/// `… N more lines` marks what was left out; the text stays plain so any
+/// chat renders it.
+pub fn snippet(diff: &str, max_lines: usize, max_line_chars: usize) -> String {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut out = String::new();
+    for line in lines.iter().take(max_lines) {
+        if line.chars().count() > max_line_chars {
+            out.extend(line.chars().take(max_line_chars));
+            out.push_str("…\n");
+        } else {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    if lines.len() > max_lines {
+        out.push_str(&format!("… {} more lines\n", lines.len() - max_lines));
+    }
+    out
+}
+
+/// Lines of `new` that are not in `old`, with their 1-based line numbers
+/// in `new`.
+pub fn added_lines<'a>(old: &'a str, new: &'a str) -> Vec<(usize, &'a str)> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let mut line = 0;
+    let mut added = Vec::new();
+    for edit in diff_lines(&old_lines, &new_lines) {
+        match edit {
+            Edit::Keep(_) => line += 1,
+            Edit::Add(text) => {
+                line += 1;
+                added.push((line, text));
+            }
+            Edit::Remove(_) => {}
+        }
+    }
+    added
+}
+
+// ─── Change statistics ────────────────────────────────────────────────────────
+
+/// Size of a file change, for sanity-checking that a version bump touched
+/// only the lines it should have.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct DiffStats {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub bytes_before: usize,
+    pub bytes_after: usize,
+    /// sha256 (hex) of the content before the change.
+    pub pre_sha256: String,
+    /// sha256 (hex) of the content after the change.
+    pub post_sha256: String,
+}
+
+/// Line and byte counts plus content hashes for `old` → `new`.
+///
+/// Lines are compared as (lossy) UTF-8, so binary content still yields a
+/// rough count.
+pub fn diff_stats(old: &[u8], new: &[u8]) -> DiffStats {
+    let (old_text, new_text) = (String::from_utf8_lossy(old), String::from_utf8_lossy(new));
+    let old_lines: Vec<&str> = old_text.lines().collect();
+    let new_lines: Vec<&str> = new_text.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    DiffStats {
+        lines_added: edits.iter().filter(|e| matches!(e, Edit::Add(_))).count(),
+        lines_removed: edits
+            .iter()
+            .filter(|e| matches!(e, Edit::Remove(_)))
+            .count(),
+        bytes_before: old.len(),
+        bytes_after: new.len(),
+        pre_sha256: sha256_hex(old),
+        post_sha256: sha256_hex(new),
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_identical_is_empty() {
+        let text = "a\nb\nc\n";
+        assert_eq!(unified_diff("Cargo.toml", text, text), "");
+    }
+
+    #[test]
+    fn test_unified_diff_single_line_change() {
+        let old = "[dependencies]\nevo-common = \"0.2\"\nserde = \"1\"\n";
+        let new = "[dependencies]\nevo-common = \"0.3\"\nserde = \"1\"\n";
+        let diff = unified_diff("Cargo.toml", old, new);
+        assert!(diff.starts_with("--- a/Cargo.toml\n+++ b/Cargo.toml\n"));
+        assert!(diff.contains("@@ -1,3 +1,3 @@"));
+        assert!(diff.contains("-evo-common = \"0.2\"\n+evo-common = \"0.3\"\n"));
+        assert!(diff.contains(" serde = \"1\"\n"));
+    }
+
+    #[test]
+    fn test_added_lines_numbers_new_file() {
+        let old = "a\nb\nc\n";
+        let new = "a\nb2\nc\nd\n";
+        assert_eq!(added_lines(old, new), vec![(2, "b2"), (4, "d")]);
+        assert!(added_lines(old, old).is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks() {
+        let old: String = (1..=20).map(|i| format!("line{i}\n")).collect();
+        let new = old
+            .replace("line2\n", "line2b\n")
+            .replace("line18\n", "line18b\n");
+        let diff = unified_diff("f", &old, &new);
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(!diff.contains(" line10\n"));
+    }
+
+    #[test]
+    fn test_snippet_truncates() {
+        let old: String = (0..30).map(|i| format!("line{i}\n")).collect();
+        let new: String = (0..30)
+            .map(|i| format!("LINE{i} {}\n", "x".repeat(40)))
+            .collect();
+        let diff = unified_diff("Cargo.toml", &old, &new);
+        let cut = snippet(&diff, 5, 20);
+        let lines: Vec<&str> = cut.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "--- a/Cargo.toml");
+        assert_eq!(lines[3], "-line0");
+        assert_eq!(lines[4], "-line1");
+        assert_eq!(
+            lines[5],
+            format!("… {} more lines", diff.lines().count() - 5)
+        );
+
+        let short = unified_diff("Cargo.toml", "a\n", &format!("{}\n", "b".repeat(30)));
+        assert!(snippet(&short, 10, 20).contains(&format!("+{}…\n", "b".repeat(19))));
+        assert_eq!(snippet("", 10, 20), "");
+    }
+
+    #[test]
+    fn test_diff_stats() {
+        let old = b"[dependencies]\nevo-common = \"0.2\"\nserde = \"1\"\n";
+        let new = b"[dependencies]\nevo-common = \"0.3\"\nserde = \"1\"\n";
+        let stats = diff_stats(old, new);
+        assert_eq!((stats.lines_added, stats.lines_removed), (1, 1));
+        assert_eq!(stats.bytes_before, stats.bytes_after);
+        assert_ne!(stats.pre_sha256, stats.post_sha256);
+        assert_eq!(diff_stats(old, old).lines_added, 0);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::checksum::sha256_hex;
+use crate::repo_config;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// What a campaign does with a repo's drifted copies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Replace the copies with the canonical files.
+    #[default]
+    Sync,
+    /// Only report the drift.
+    Check,
+}
+
+/// One `[[file_sync]]` campaign: files of a source-of-truth repo kept
+/// identical in every managed repo.
+///
+/// ```toml
+/// [[file_sync]]
+/// name = "lint-config"
+/// source_repo = "evo-common"
+/// files = ["rustfmt.toml", "deny.toml", ".editorconfig", ".github/ISSUE_TEMPLATE"]
+/// mode = "check"                      # "sync" (default) | "check"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileSyncCampaign {
+    /// Name reported with the campaign's findings and used in opt-outs.
+    pub name: String,
+    /// Repo holding the canonical files.
+    #[serde(default = "default_source_repo")]
+    pub source_repo: String,
+    /// Files, or directories synced file by file, relative to the repo root.
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub mode: SyncMode,
+}
+
+fn default_source_repo() -> String {
+    "evo-common".to_string()
+}
+
+impl FileSyncCampaign {
+    /// Whether `opt_outs` (campaign names or file paths) exclude `file`
+    /// of this campaign.
+    pub fn opted_out(&self, opt_outs: &[String], file: &str) -> bool {
+        opt_outs.iter().any(|o| {
+            let o = o.trim_end_matches('/');
+            o == self.name || o == file || file.starts_with(&format!("{o}/"))
+        })
+    }
+}
+
+// ─── Canonical files ──────────────────────────────────────────────────────────
+
+/// A campaign's canonical files, keyed by repo-relative path.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CanonicalFiles {
+    pub files: BTreeMap<String, String>,
+}
+
+impl CanonicalFiles {
+    /// Reads `campaign.files` from the source checkout at `source_root`;
+    /// directories contribute every file below them.
+    pub fn load(source_root: &Path, campaign: &FileSyncCampaign) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for file in &campaign.files {
+            let file = file.trim_matches('/');
+            anyhow::ensure!(
+                repo_config::is_repo_relative(file),
+                "file_sync {}: {file:?} is not a path inside the repo",
+                campaign.name
+            );
+            let path = source_root.join(file);
+            if path.is_dir() {
+                collect(&path, file, &mut files)?;
+            } else {
+                let content = std::fs::read_to_string(&path).with_context(|| {
+                    format!(
+                        "{} has no {file} ({})",
+                        campaign.source_repo,
+                        path.display()
+                    )
+                })?;
+                files.insert(file.to_string(), content);
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// sha256 over every path and content, so a repo's scan fingerprint
+    /// changes whenever a canonical file does.
+    pub fn digest(&self) -> String {
+        let mut all = String::new();
+        for (path, content) in &self.files {
+            all.push_str(path);
+            all.push('\0');
+            all.push_str(&sha256_hex(content.as_bytes()));
+            all.push('\n');
+        }
+        sha256_hex(all.as_bytes())
+    }
+
+    /// The files of the checkout at `repo_base` that are missing or differ
+    /// from the canonical ones, as `(path, current, canonical)`.
+    pub fn drift(
+        &self,
+        repo_base: &Path,
+        include: impl Fn(&str) -> bool,
+    ) -> Vec<(String, String, String)> {
+        self.files
+            .iter()
+            .filter(|(path, _)| include(path))
+            .filter_map(|(path, canonical)| {
+                let current = std::fs::read_to_string(repo_base.join(path)).unwrap_or_default();
+                (current != *canonical).then(|| (path.clone(), current, canonical.clone()))
+            })
+            .collect()
+    }
+}
+
+fn collect(dir: &Path, prefix: &str, files: &mut BTreeMap<String, String>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("read {}", dir.display()))?;
+        let path = entry.path();
+        let relative = format!("{prefix}/{}", entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            collect(&path, &relative, files)?;
+        } else {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("read {}", path.display()))?;
+            files.insert(relative, content);
+        }
+    }
+    Ok(())
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign(files: &[&str]) -> FileSyncCampaign {
+        FileSyncCampaign {
+            name: "lint-config".into(),
+            source_repo: "evo-common".into(),
+            files: files.iter().map(ToString::to_string).collect(),
+            mode: SyncMode::Check,
+        }
+    }
+
+    #[test]
+    fn test_load_and_drift() {
+        let source = tempfile::tempdir().unwrap();
+        let templates = source.path().join(".github/ISSUE_TEMPLATE");
+        std::fs::create_dir_all(&templates).unwrap();
+        std::fs::write(source.path().join("rustfmt.toml"), "edition = \"2024\"\n").unwrap();
+        std::fs::write(templates.join("bug.md"), "# Bug\n").unwrap();
+
+        let lint = campaign(&["rustfmt.toml", ".github/ISSUE_TEMPLATE/"]);
+        let canonical = CanonicalFiles::load(source.path(), &lint).unwrap();
+        assert_eq!(
+            canonical.files.keys().collect::<Vec<_>>(),
+            vec![".github/ISSUE_TEMPLATE/bug.md", "rustfmt.toml"]
+        );
+
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::write(repo.path().join("rustfmt.toml"), "edition = \"2021\"\n").unwrap();
+        let drift = canonical.drift(repo.path(), |_| true);
+        assert_eq!(drift.len(), 2);
+        assert_eq!(drift[1].1, "edition = \"2021\"\n");
+        let drift = canonical.drift(repo.path(), |file| {
+            !lint.opted_out(&[".github/ISSUE_TEMPLATE".into()], file)
+        });
+        assert_eq!(drift.len(), 1);
+        assert_eq!(drift[0].0, "rustfmt.toml");
+
+        let err = CanonicalFiles::load(source.path(), &campaign(&["deny.toml"])).unwrap_err();
+        assert!(err.to_string().contains("evo-common has no deny.toml"));
+        assert!(CanonicalFiles::load(source.path(), &campaign(&["../x"])).is_err());
+    }
+
+    #[test]
+    fn test_opt_outs() {
+        let campaign = campaign(&["rustfmt.toml"]);
+        assert!(campaign.opted_out(&["lint-config".into()], "rustfmt.toml"));
+        assert!(campaign.opted_out(&["rustfmt.toml".into()], "rustfmt.toml"));
+        assert!(!campaign.opted_out(&["deny.toml".into()], "rustfmt.toml"));
+        assert!(!campaign.opted_out(&[".github".into()], ".githubx/a.md"));
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::checksum::sha256_hex;
+use crate::state::state_dir;
+use crate::versions::USER_AGENT;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// What happens to registry responses (`REGISTRY_FIXTURES`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Make real requests and write each response to the fixture directory.
+    Record,
+    /// Serve responses from the fixture directory without touching the network.
+    Replay,
+}
+
+/// Recorded registry responses in `REGISTRY_FIXTURES_DIR` (default
+/// `<state dir>/fixtures`), one JSON file per request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixtures {
+    pub mode: FixtureMode,
+    pub dir: PathBuf,
+}
+
+/// A response as stored on disk.  Bodies that are not UTF-8 (`.crate`
+/// downloads) are kept base64-encoded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recorded {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+/// Status and body of a registry response, live or replayed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+}
+
+/// HTTP client for registry requests that records or replays responses
+/// when fixtures are on.
+#[derive(Debug, Clone)]
+pub struct RegistryHttp {
+    client: reqwest::Client,
+    fixtures: Option<Fixtures>,
+}
+
+// ─── Fixtures ─────────────────────────────────────────────────────────────────
+
+impl Fixtures {
+    /// Reads `REGISTRY_FIXTURES` (`record` or `replay`) and
+    /// `REGISTRY_FIXTURES_DIR`; `None` when fixtures are off.
+    pub fn from_env() -> Option<Self> {
+        let mode = match std::env::var("REGISTRY_FIXTURES").ok()?.trim() {
+            "" | "off" => return None,
+            "record" => FixtureMode::Record,
+            "replay" => FixtureMode::Replay,
+            other => {
+                warn!(
+                    value = other,
+                    "unknown REGISTRY_FIXTURES — expected record or replay"
+                );
+                return None;
+            }
+        };
+        let dir = std::env::var("REGISTRY_FIXTURES_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| state_dir().join("fixtures"));
+        Some(Self { mode, dir })
+    }
+
+    pub fn replay(dir: &Path) -> Self {
+        Self {
+            mode: FixtureMode::Replay,
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// File holding the response to `method url`, e.g.
+    /// `GET_crates.io_api_v1_crates_serde.json`.
+    pub fn path(&self, method: &Method, url: &str) -> PathBuf {
+        let name: String = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{method}_{name}.json"))
+    }
+
+    /// The recorded response to `method url`.
+    pub fn load(&self, method: &Method, url: &str) -> Result<Response> {
+        let path = self.path(method, url);
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("no fixture for {method} {url} ({})", path.display()))?;
+        let recorded: Recorded = serde_json::from_str(&text)
+            .with_context(|| format!("parse fixture {}", path.display()))?;
+        let body = match (recorded.body, recorded.body_base64) {
+            (_, Some(encoded)) => BASE64
+                .decode(encoded.trim())
+                .with_context(|| format!("decode body of {}", path.display()))?,
+            (Some(text), None) => text.into_bytes(),
+            (None, None) => Vec::new(),
+        };
+        let status = StatusCode::from_u16(recorded.status)
+            .with_context(|| format!("status of {}", path.display()))?;
+        Ok(Response { status, body })
+    }
+
+    /// Writes `response` as the recording of `method url`.
+    pub fn save(&self, method: &Method, url: &str, response: &Response) -> Result<()> {
+        let (body, body_base64) = match std::str::from_utf8(&response.body) {
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (None, Some(BASE64.encode(&response.body))),
+        };
+        let recorded = Recorded {
+            method: method.to_string(),
+            url: url.to_string(),
+            status: response.status.as_u16(),
+            body,
+            body_base64,
+        };
+        let path = self.path(method, url);
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create {}", self.dir.display()))?;
+        std::fs::write(&path, serde_json::to_string_pretty(&recorded)? + "\n")
+            .with_context(|| format!("write {}", path.display()))
+    }
+}
+
+// ─── Responses ────────────────────────────────────────────────────────────────
+
+impl Response {
+    /// The response, or an error naming `url` unless it is a 2xx.
+    pub fn ensure_success(self, url: &str) -> Result<Self> {
+        anyhow::ensure!(self.status.is_success(), "{url} returned {}", self.status);
+        Ok(self)
+    }
+
+    pub fn text(&self) -> Result<&str> {
+        std::str::from_utf8(&self.body).context("response body is not UTF-8")
+    }
+}
+
+// ─── Client ───────────────────────────────────────────────────────────────────
+
+impl RegistryHttp {
+    pub fn new(client: reqwest::Client, fixtures: Option<Fixtures>) -> Self {
+        Self { client, fixtures }
+    }
+
+    /// GETs `url` with the crates.io `User-Agent`.
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        self.send(Method::GET, url, false).await
+    }
+
+    /// GETs `url` past any caching proxy or CDN.
+    pub async fn get_uncached(&self, url: &str) -> Result<Response> {
+        self.send(Method::GET, url, true).await
+    }
+
+    /// HEADs `url`; the body is always empty.
+    pub async fn head(&self, url: &str) -> Result<Response> {
+        self.send(Method::HEAD, url, false).await
+    }
+
+    /// POSTs `body` as JSON to `url`.  Its recording is keyed by the URL and
+    /// a digest of the body (`{url}#{sha256 prefix}`).
+    pub async fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<Response> {
+        let body = serde_json::to_vec(body)?;
+        let key = format!("{url}#{}", &sha256_hex(&body)[..12]);
+        self.request(Method::POST, url, &key, Some(body), false)
+            .await
+    }
+
+    async fn send(&self, method: Method, url: &str, no_cache: bool) -> Result<Response> {
+        self.request(method, url, url, None, no_cache).await
+    }
+
+    /// Makes (or replays) one request; `key` names its recording.
+    async fn request(
+        &self,
+        method: Method,
+        url: &str,
+        key: &str,
+        body: Option<Vec<u8>>,
+        no_cache: bool,
+    ) -> Result<Response> {
+        if let Some(fixtures) = &self.fixtures
+            && fixtures.mode == FixtureMode::Replay
+        {
+            debug!(%method, url = key, "replaying registry fixture");
+            return fixtures.load(&method, key);
+        }
+
+        let mut request = self
+            .client
+            .request(method.clone(), url)
+            .header("User-Agent", USER_AGENT);
+        if no_cache {
+            request = request.header("Cache-Control", "no-cache");
+        }
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+        let resp = request
+            .send()
+            .await
+            .with_context(|| format!("HTTP request to {url}"))?;
+        let response = Response {
+            status: resp.status(),
+            body: resp
+                .bytes()
+                .await
+                .with_context(|| format!("read {url}"))?
+                .to_vec(),
+        };
+
+        if let Some(fixtures) = &self.fixtures
+            && let Err(e) = fixtures.save(&method, key, &response)
+        {
+            warn!(%method, url = key, error = %e, "could not record registry fixture");
+        }
+        Ok(response)
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_path() {
+        let fixtures = Fixtures::replay(Path::new("fx"));
+        assert_eq!(
+            fixtures.path(&Method::GET, "https://crates.io/api/v1/crates/evo-common"),
+            Path::new("fx/GET_crates.io_api_v1_crates_evo-common.json")
+        );
+        assert_eq!(
+            fixtures.path(
+                &Method::HEAD,
+                "https://static.crates.io/crates/a/a-1.0.0.crate"
+            ),
+            Path::new("fx/HEAD_static.crates.io_crates_a_a-1.0.0.crate.json")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let fixtures = Fixtures::replay(dir.path());
+        let text = Response {
+            status: StatusCode::OK,
+            body: b"{\"crate\":{}}".to_vec(),
+        };
+        let binary = Response {
+            status: StatusCode::OK,
+            body: vec![0x1f, 0x8b, 0xff, 0x00],
+        };
+        fixtures
+            .save(&Method::GET, "https://a.test/text", &text)
+            .unwrap();
+        fixtures
+            .save(&Method::GET, "https://a.test/bin", &binary)
+            .unwrap();
+
+        assert_eq!(
+            fixtures.load(&Method::GET, "https://a.test/text").unwrap(),
+            text
+        );
+        assert_eq!(
+            fixtures.load(&Method::GET, "https://a.test/bin").unwrap(),
+            binary
+        );
+        let raw =
+            std::fs::read_to_string(fixtures.path(&Method::GET, "https://a.test/bin")).unwrap();
+        assert!(raw.contains("body_base64"));
+        assert!(fixtures.load(&Method::HEAD, "https://a.test/text").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_makes_no_requests() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let fixtures = Fixtures::replay(dir.path());
+        let url = "https://unreachable.invalid/crates/x";
+        fixtures
+            .save(
+                &Method::GET,
+                url,
+                &Response {
+                    status: StatusCode::NOT_FOUND,
+                    body: Vec::new(),
+                },
+            )
+            .unwrap();
+        let http = RegistryHttp::new(reqwest::Client::new(), Some(fixtures));
+        let resp = http.get(url).await.unwrap();
+        assert_eq!(resp.status, StatusCode::NOT_FOUND);
+        assert!(resp.ensure_success(url).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::runs::VersionMatrix;
+use crate::versions::needs_update;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A tracked crate the fleet requires at more than one version.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Divergence {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Newest requirement in the fleet.
+    pub newest: String,
+    /// Requirement → repos requiring it.
+    pub versions: BTreeMap<String, Vec<String>>,
+    /// Repos behind `newest`, with why this run left them there.
+    pub lagging: Vec<Lagging>,
+}
+
+/// A repo requiring an older version than the rest of the fleet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Lagging {
+    pub repo: String,
+    pub version: String,
+    /// Run findings naming the repo (`held_for_review`, `errors`, …); empty
+    /// when the run reported nothing about it.
+    pub reasons: Vec<String>,
+}
+
+// ─── Audit ────────────────────────────────────────────────────────────────────
+
+/// Compares every repo's requirement of each of `crates` in `matrix`.
+/// Requirements naming the same release (`0.4` and `0.4.0`) agree; repos
+/// not depending on a crate are ignored.  `findings` are the run's labelled
+/// finding lists, searched for entries of each lagging repo.
+pub fn audit(
+    matrix: &VersionMatrix,
+    crates: &[&str],
+    findings: &[(&str, &[Value])],
+) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    for &crate_name in crates {
+        let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (repo, row) in matrix {
+            let Some(version) = row.get(crate_name) else {
+                continue;
+            };
+            let key = versions
+                .keys()
+                .find(|known| same_release(known, version))
+                .cloned()
+                .unwrap_or_else(|| version.clone());
+            versions.entry(key).or_default().push(repo.clone());
+        }
+        if versions.len() < 2 {
+            continue;
+        }
+        let newest = versions
+            .keys()
+            .cloned()
+            .reduce(|best, v| if needs_update(&best, &v) { v } else { best })
+            .unwrap_or_default();
+        let lagging = versions
+            .iter()
+            .filter(|(version, _)| **version != newest)
+            .flat_map(|(version, repos)| {
+                repos.iter().map(move |repo| Lagging {
+                    repo: repo.clone(),
+                    version: version.clone(),
+                    reasons: reasons(repo, findings),
+                })
+            })
+            .collect();
+        divergences.push(Divergence {
+            crate_name: crate_name.to_string(),
+            newest,
+            versions,
+            lagging,
+        });
+    }
+    divergences
+}
+
+/// Whether two requirements name the same release.
+fn same_release(a: &str, b: &str) -> bool {
+    !needs_update(a, b) && !needs_update(b, a)
+}
+
+/// Labels of the finding lists with an entry for `repo`.
+fn reasons(repo: &str, findings: &[(&str, &[Value])]) -> Vec<String> {
+    findings
+        .iter()
+        .filter(|(_, entries)| entries.iter().any(|e| e["repo"] == repo))
+        .map(|(label, _)| label.to_string())
+        .collect()
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(c, v)| (c.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_audit_reports_partial_rollouts() {
+        let matrix = VersionMatrix::from([
+            (
+                "evo-king".to_string(),
+                row(&[("evo-common", "0.4.0"), ("evo-agent-sdk", "0.3")]),
+            ),
+            (
+                "evo-runner".to_string(),
+                row(&[("evo-common", "0.3"), ("evo-agent-sdk", "0.3.0")]),
+            ),
+            ("evo-agents".to_string(), row(&[("evo-common", "0.4")])),
+        ]);
+        let held =
+            vec![json!({ "repo": "evo-runner", "file": "Cargo.toml", "reason": "llm_risk" })];
+        let errors = vec![json!({ "repo": "evo-king", "error": "push rejected" })];
+        let divergences = audit(
+            &matrix,
+            &["evo-common", "evo-agent-sdk"],
+            &[("held_for_review", &held), ("errors", &errors)],
+        );
+        assert_eq!(divergences.len(), 1);
+        let common = &divergences[0];
+        assert_eq!(common.crate_name, "evo-common");
+        assert_eq!(common.newest, "0.4");
+        assert_eq!(common.versions["0.4"], vec!["evo-agents", "evo-king"]);
+        assert_eq!(
+            common.lagging,
+            vec![Lagging {
+                repo: "evo-runner".to_string(),
+                version: "0.3".to_string(),
+                reasons: vec!["held_for_review".to_string()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::git::{PushTarget, create_tag_via_gh, create_tag_via_local_git, gh_api};
+use crate::runs::VersionMatrix;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[fleet_tag]` section of the config file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FleetTagConfig {
+    /// Tag name template; fleet tagging is off while unset (`FLEET_TAG`
+    /// takes precedence).
+    pub name: Option<String>,
+    /// Repo receiving one tag that records the whole fleet, instead of a
+    /// tag in every updated repo (`FLEET_TAG_OPS_REPO` takes precedence).
+    pub ops_repo: Option<String>,
+}
+
+impl FleetTagConfig {
+    /// The tag name template in effect, if tagging is on.
+    pub fn template(&self) -> Option<String> {
+        env_or("FLEET_TAG", &self.name)
+    }
+
+    /// The ops repo in effect, if any.
+    pub fn ops_repo(&self) -> Option<String> {
+        env_or("FLEET_TAG_OPS_REPO", &self.ops_repo)
+    }
+}
+
+fn env_or(var: &str, configured: &Option<String>) -> Option<String> {
+    std::env::var(var)
+        .ok()
+        .or_else(|| configured.clone())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+// ─── Tag names and messages ───────────────────────────────────────────────────
+
+/// Expands `template` into a tag name.
+///
+/// Placeholders: `{run_id}`, `{date}` (`YYYY-MM-DD`), `{<crate>}` (the
+/// crate's latest version, e.g. `0.3.2`) and `{<crate>:minor}` (its
+/// `major.minor`, e.g. `0.3`).  Unknown placeholders and names git would
+/// reject are errors.
+pub fn render_name(
+    template: &str,
+    run_id: &str,
+    date: &str,
+    latest: &BTreeMap<String, String>,
+) -> Result<String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .with_context(|| format!("unclosed placeholder in fleet tag {template:?}"))?;
+        let key = &rest[open + 1..open + close];
+        let value = match key {
+            "run_id" => run_id.to_string(),
+            "date" => date.to_string(),
+            _ => {
+                let (crate_name, minor) = match key.strip_suffix(":minor") {
+                    Some(crate_name) => (crate_name, true),
+                    None => (key, false),
+                };
+                let version = latest.get(crate_name).with_context(|| {
+                    format!("unknown placeholder {{{key}}} in fleet tag {template:?}")
+                })?;
+                if minor {
+                    version.split('.').take(2).collect::<Vec<_>>().join(".")
+                } else {
+                    version.clone()
+                }
+            }
+        };
+        name.push_str(&value);
+        rest = &rest[open + close + 1..];
+    }
+    name.push_str(rest);
+    anyhow::ensure!(is_valid_tag_name(&name), "{name:?} is not a valid tag name");
+    Ok(name)
+}
+
+/// The subset of `git check-ref-format` rules a rendered name can break.
+fn is_valid_tag_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['.', '/', '-'])
+        && !name.ends_with(['.', '/'])
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("@{")
+        && !name.contains("//")
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "~^:?*[\\".contains(c))
+}
+
+/// Annotated tag message recording the fleet state: the latest crate
+/// versions, every repo's requirements and the commits the run made.
+pub fn message(
+    name: &str,
+    run_id: &str,
+    latest: &BTreeMap<String, String>,
+    matrix: &VersionMatrix,
+    commits: &BTreeMap<String, String>,
+) -> String {
+    let mut text = format!("evo fleet rollout {name}\n\nrun_id: {run_id}\n\nCrates:\n");
+    for (crate_name, version) in latest {
+        text.push_str(&format!("  {crate_name} {version}\n"));
+    }
+    text.push_str("\nRepos:\n");
+    for (repo, requirements) in matrix {
+        let versions: Vec<String> = requirements
+            .iter()
+            .map(|(crate_name, requirement)| format!("{crate_name} {requirement}"))
+            .collect();
+        let commit = commits
+            .get(repo)
+            .map(|sha| format!(" @ {sha}"))
+            .unwrap_or_default();
+        text.push_str(&format!("  {repo}{commit}: {}\n", versions.join(", ")));
+    }
+    text
+}
+
+// ─── Tagging ──────────────────────────────────────────────────────────────────
+
+/// A repo to tag at the last commit the run made in it.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub repo: String,
+    pub sha: String,
+    /// Checkout to tag in when the commit was made with local git;
+    /// otherwise the tag is created through the GitHub API.
+    pub local_base: Option<PathBuf>,
+    pub push: PushTarget,
+}
+
+/// Outcome of tagging one repo.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagResult {
+    pub repo: String,
+    pub tag: String,
+    /// Tagged commit.
+    pub commit: String,
+    pub error: Option<String>,
+}
+
+/// Tags every target's commit with `name`.  Failures are recorded per repo.
+pub fn tag_repos(org: &str, name: &str, message: &str, targets: &[Target]) -> Vec<TagResult> {
+    targets
+        .iter()
+        .map(|target| {
+            let outcome = match &target.local_base {
+                Some(base) => {
+                    create_tag_via_local_git(base, name, &target.sha, message, &target.push)
+                }
+                None => create_tag_via_gh(org, &target.repo, name, &target.sha, message).map(drop),
+            };
+            result(&target.repo, name, &target.sha, outcome)
+        })
+        .collect()
+}
+
+/// Tags the head of `ops_repo`'s default branch with `name`.
+pub fn tag_ops_repo(org: &str, ops_repo: &str, name: &str, message: &str) -> TagResult {
+    let head = gh_api(&[
+        &format!("repos/{org}/{ops_repo}/commits/HEAD"),
+        "--jq",
+        ".sha",
+    ])
+    .with_context(|| format!("resolve the head of {org}/{ops_repo}"));
+    match head {
+        Ok(sha) => {
+            let outcome = create_tag_via_gh(org, ops_repo, name, &sha, message).map(drop);
+            result(ops_repo, name, &sha, outcome)
+        }
+        Err(e) => result(ops_repo, name, "", Err(e)),
+    }
+}
+
+fn result(repo: &str, tag: &str, commit: &str, outcome: Result<()>) -> TagResult {
+    match &outcome {
+        Ok(()) => info!(repo, tag, commit, "fleet tag created"),
+        Err(e) => warn!(repo, tag, error = %e, "fleet tagging failed"),
+    }
+    TagResult {
+        repo: repo.to_string(),
+        tag: tag.to_string(),
+        commit: commit.to_string(),
+        error: outcome.err().map(|e| format!("{e:#}")),
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latest() -> BTreeMap<String, String> {
+        [("evo-agent-sdk", "0.3.2"), ("evo-common", "0.4.0")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_name() {
+        assert_eq!(
+            render_name(
+                "sdk-{evo-agent-sdk:minor}-rollout",
+                "r1",
+                "2026-10-17",
+                &latest()
+            )
+            .unwrap(),
+            "sdk-0.3-rollout"
+        );
+        assert_eq!(
+            render_name("fleet/{date}-{run_id}", "r1", "2026-10-17", &latest()).unwrap(),
+            "fleet/2026-10-17-r1"
+        );
+        assert_eq!(
+            render_name("common-{evo-common}", "r1", "d", &latest()).unwrap(),
+            "common-0.4.0"
+        );
+        assert!(render_name("x-{evo-missing}", "r1", "d", &latest()).is_err());
+        assert!(render_name("x-{run_id", "r1", "d", &latest()).is_err());
+        assert!(render_name("rollout {run_id}", "r1", "d", &latest()).is_err());
+        assert!(render_name("{run_id}.lock", "r1", "d", &latest()).is_err());
+    }
+
+    #[test]
+    fn test_message_records_fleet_state() {
+        let mut matrix = VersionMatrix::new();
+        matrix
+            .entry("evo-king".to_string())
+            .or_default()
+            .insert("evo-common".to_string(), "0.4.0".to_string());
+        matrix
+            .entry("evo-runner".to_string())
+            .or_default()
+            .insert("evo-common".to_string(), "0.3".to_string());
+        let commits = BTreeMap::from([("evo-king".to_string(), "abc1234".to_string())]);
+        let text = message("sdk-0.3-rollout", "r1", &latest(), &matrix, &commits);
+        assert!(text.starts_with("evo fleet rollout sdk-0.3-rollout\n"));
+        assert!(text.contains("  evo-common 0.4.0\n"));
+        assert!(text.contains("  evo-king @ abc1234: evo-common 0.4.0\n"));
+        assert!(text.contains("  evo-runner: evo-common 0.3\n"));
+    }
+}
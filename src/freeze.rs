@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+// ─── Freeze overrides ─────────────────────────────────────────────────────────
+
+/// Runtime freeze list from `FROZEN_REPOS`, e.g.
+/// `evo-king,evo-agents:2026-03-31` (a date means "frozen until then").
+///
+/// Complements the compile-time `frozen` / `frozen_until` fields of a
+/// `RepoSpec`, so a repo can be frozen for a refactor without a release.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FreezeList(HashMap<String, Option<String>>);
+
+impl FreezeList {
+    /// Parses a comma-separated `repo[:YYYY-MM-DD]` list.
+    pub fn parse(value: &str) -> Self {
+        Self(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|e| !e.is_empty())
+                .map(|entry| match entry.split_once(':') {
+                    Some((repo, until)) => {
+                        (repo.trim().to_string(), Some(until.trim().to_string()))
+                    }
+                    None => (entry.to_string(), None),
+                })
+                .collect(),
+        )
+    }
+
+    /// Reads `FROZEN_REPOS` (empty when unset).
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var("FROZEN_REPOS").unwrap_or_default())
+    }
+}
+
+// ─── Freeze status ────────────────────────────────────────────────────────────
+
+/// Why a repo is currently excluded from automated updates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Freeze {
+    /// Last frozen day (`YYYY-MM-DD`, inclusive); `None` = until unfrozen.
+    pub until: Option<String>,
+}
+
+/// Resolves whether `repo` is frozen on `today` (`YYYY-MM-DD`).
+///
+/// A `FROZEN_REPOS` entry takes precedence over the `RepoSpec` fields.
+/// `frozen_until` alone implies a freeze; once its date has passed the repo
+/// is updated again even if `frozen` is still set.
+pub fn freeze_status(
+    repo: &str,
+    frozen: bool,
+    frozen_until: Option<&str>,
+    overrides: &FreezeList,
+    today: &str,
+) -> Option<Freeze> {
+    let until = match overrides.0.get(repo) {
+        Some(until) => until.clone(),
+        None if frozen || frozen_until.is_some() => frozen_until.map(str::to_string),
+        None => return None,
+    };
+    // ISO dates compare correctly as strings.
+    match until {
+        Some(ref date) if date.as_str() < today => None,
+        _ => Some(Freeze { until }),
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freeze_list_parse() {
+        let list = FreezeList::parse(" evo-king , evo-agents:2026-03-31,,");
+        assert_eq!(list.0.len(), 2);
+        assert_eq!(list.0["evo-king"], None);
+        assert_eq!(list.0["evo-agents"].as_deref(), Some("2026-03-31"));
+    }
+
+    #[test]
+    fn test_freeze_status_from_spec() {
+        let none = FreezeList::default();
+        assert_eq!(freeze_status("r", false, None, &none, "2026-01-01"), None);
+        assert_eq!(
+            freeze_status("r", true, None, &none, "2026-01-01"),
+            Some(Freeze { until: None })
+        );
+        assert_eq!(
+            freeze_status("r", false, Some("2026-01-01"), &none, "2026-01-01"),
+            Some(Freeze {
+                until: Some("2026-01-01".to_string())
+            })
+        );
+        assert_eq!(
+            freeze_status("r", true, Some("2025-12-31"), &none, "2026-01-01"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_freeze_status_env_overrides_spec() {
+        let list = FreezeList::parse("r:2026-02-01");
+        assert_eq!(
+            freeze_status("r", false, None, &list, "2026-01-01"),
+            Some(Freeze {
+                until: Some("2026-02-01".to_string())
+            })
+        );
+        assert_eq!(freeze_status("r", true, None, &list, "2026-03-01"), None);
+    }
+}
@@ -1,14 +1,27 @@
 use anyhow::{Context, Result};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use std::path::Path;
-use std::process::Command;
+use serde_json::{Value, json};
+use std::fmt;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
 use tracing::{debug, info, warn};
 
+use crate::diff::{DiffStats, diff_stats};
+use crate::github::{GithubRest, decode_content};
+use crate::net::apply_to_command;
+use crate::tokens::{TokenPool, redact};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Output lines of a failed subprocess kept in its error.
+const ERROR_OUTPUT_LINES: usize = 20;
+
 // ─── Public types ─────────────────────────────────────────────────────────────
 
 /// Outcome of a single file commit operation.
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // fields are used by callers via serde_json::json!
 pub struct CommitResult {
     /// Repository slug, e.g. `"my-org/evo-king"`.
     pub repo: String,
@@ -18,175 +31,922 @@ pub struct CommitResult {
     pub strategy: CommitStrategy,
     /// Commit SHA or a brief description of the local push.
     pub sha: String,
+    /// Line/byte counts and content hashes of the committed change.
+    pub stats: DiffStats,
 }
 
 /// Which commit mechanism was used.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CommitStrategy {
     /// GitHub CLI (`gh api`) — remote commit, no local checkout needed.
     GhCli,
     /// Local `git add / commit / push` — used as fallback when gh CLI fails.
     LocalGit,
+    /// Direct GitHub REST calls with `GITHUB_TOKEN` — used when neither of
+    /// the above is possible (e.g. no gh binary and no local checkout).
+    RestApi,
+}
+
+impl CommitStrategy {
+    /// Every strategy, in the order [`commit_bytes`] attempts them.
+    pub const ALL: &'static [CommitStrategy] = &[
+        CommitStrategy::GhCli,
+        CommitStrategy::LocalGit,
+        CommitStrategy::RestApi,
+    ];
+}
+
+/// Where the local git strategy pushes its commits.
+///
+/// The default is a plain `git push`, i.e. the current branch's upstream,
+/// with the ambient git credentials.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PushTarget {
+    /// Remote name or URL; `origin` when only a refspec is given.
+    pub remote: Option<String>,
+    /// Refspec, e.g. `HEAD:refs/heads/evo-update`; `HEAD` when only a
+    /// remote is given.
+    pub refspec: Option<String>,
+    /// Environment variable holding an SSH deploy key used for this
+    /// target's pushes (and the pull before a revert).
+    pub deploy_key_env: Option<String>,
+}
+
+impl PushTarget {
+    /// Credentials for talking to the remote: the deploy key when one is
+    /// configured, otherwise the current GitHub token for HTTPS remotes.
+    fn credentials(&self) -> Result<Credentials> {
+        let Some(var) = &self.deploy_key_env else {
+            return match TokenPool::global().current() {
+                Some((_, token)) => Credentials::from_token(&token),
+                None => Ok(Credentials::Ambient),
+            };
+        };
+        let key = std::env::var(var)
+            .ok()
+            .filter(|k| !k.trim().is_empty())
+            .with_context(|| format!("deploy key variable {var} is not set"))?;
+        Credentials::from_key(&key)
+    }
+
+    /// Arguments of the `git push` invocation.
+    fn args(&self) -> Vec<&str> {
+        match (&self.remote, &self.refspec) {
+            (None, None) => vec!["push"],
+            (remote, refspec) => vec![
+                "push",
+                remote.as_deref().unwrap_or("origin"),
+                refspec.as_deref().unwrap_or("HEAD"),
+            ],
+        }
+    }
+
+    /// Whether commits go somewhere other than the branch's upstream.
+    pub fn is_custom(&self) -> bool {
+        self.remote.is_some() || self.refspec.is_some()
+    }
+}
+
+impl fmt::Display for PushTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.args().as_slice() {
+            [_, remote, refspec] => write!(f, "{remote} {refspec}"),
+            _ => f.write_str("upstream"),
+        }
+    }
+}
+
+/// `GIT_ASKPASS` helper answering GitHub's HTTPS prompts with the token in
+/// `EVO_GIT_TOKEN`.  The prompt must name exactly `https://github.com` (with
+/// an optional user); prompts for any other host, including lookalikes such
+/// as `github.com.example`, are refused.
+const ASKPASS_SCRIPT: &str = r#"#!/bin/sh
+case "$1" in
+  "Username for 'https://github.com'"*) echo x-access-token ;;
+  "Password for 'https://github.com'"* | "Password for 'https://"*"@github.com'"*)
+    printf '%s\n' "$EVO_GIT_TOKEN" ;;
+  *) exit 1 ;;
+esac
+"#;
+
+/// Git credentials held for the duration of a push.
+///
+/// Temp files are deleted when this is dropped.
+enum Credentials {
+    /// Whatever the host's git is configured with.
+    Ambient,
+    /// An SSH deploy key, written to a file only the current user can read.
+    DeployKey(tempfile::TempPath),
+    /// A GitHub token handed to HTTPS pushes through an askpass helper; the
+    /// host's credential helpers are bypassed.
+    Token {
+        askpass: tempfile::TempPath,
+        token: String,
+    },
+}
+
+impl Credentials {
+    /// Writes `key` to a temp file readable only by the current user.
+    fn from_key(key: &str) -> Result<Self> {
+        let content = format!("{}\n", key.trim_end());
+        Ok(Self::DeployKey(
+            private_file(&content, 0o600).context("write deploy key file")?,
+        ))
+    }
+
+    /// Writes the askpass helper serving `token`.
+    fn from_token(token: &str) -> Result<Self> {
+        Ok(Self::Token {
+            askpass: private_file(ASKPASS_SCRIPT, 0o700).context("write askpass helper")?,
+            token: token.to_string(),
+        })
+    }
+
+    /// Variables for the git invocation.
+    fn env(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Ambient => Vec::new(),
+            Self::DeployKey(path) => vec![(
+                "GIT_SSH_COMMAND",
+                format!(
+                    "ssh -i '{}' -o IdentitiesOnly=yes -o BatchMode=yes",
+                    path.display()
+                ),
+            )],
+            Self::Token { askpass, token } => vec![
+                ("GIT_ASKPASS", askpass.display().to_string()),
+                ("EVO_GIT_TOKEN", token.clone()),
+                ("GIT_TERMINAL_PROMPT", "0".to_string()),
+                // An empty `credential.helper` clears the configured helpers,
+                // so cached host credentials are neither used nor updated.
+                ("GIT_CONFIG_COUNT", "1".to_string()),
+                ("GIT_CONFIG_KEY_0", "credential.helper".to_string()),
+                ("GIT_CONFIG_VALUE_0", String::new()),
+            ],
+        }
+    }
+}
+
+/// Writes `content` to a closed temp file with permissions `mode`.
+fn private_file(content: &str, mode: u32) -> Result<tempfile::TempPath> {
+    let mut file = tempfile::NamedTempFile::new().context("create temp file")?;
+    file.write_all(content.as_bytes())
+        .context("write temp file")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(mode))
+            .context("restrict temp file")?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    // Closed, so an executable helper can be run without ETXTBSY.
+    Ok(file.into_temp_path())
+}
+
+/// One file of a multi-file commit (see [`commit_changes`]).
+#[derive(Debug, Clone, Copy)]
+pub struct FileChange<'a> {
+    /// Path inside the repo, e.g. `"crates/a/Cargo.toml"`.
+    pub path: &'a str,
+    /// Content the change was computed from (feeds the [`DiffStats`]).
+    pub previous: &'a [u8],
+    /// New content.
+    pub content: &'a [u8],
+}
+
+// ─── gh CLI detection ─────────────────────────────────────────────────────────
+
+/// Whether the `gh` binary can be executed on this host.
+///
+/// Probed once (`gh --version`) and cached for the process lifetime so a
+/// missing binary does not produce a failed attempt per file.
+pub fn gh_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        apply_to_command(&mut Command::new("gh"))
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Refusal to write outside a repo checkout in the local git strategy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSecurityError {
+    /// `file_path` is absolute (or carries a drive prefix).
+    Absolute { file_path: String },
+    /// `file_path` contains a `..` component.
+    ParentTraversal { file_path: String },
+    /// The base directory itself cannot be resolved.
+    BaseUnavailable { base: PathBuf, reason: String },
+    /// The resolved path (after following symlinks) lies outside the base.
+    EscapesBase {
+        file_path: String,
+        resolved: PathBuf,
+    },
+}
+
+impl fmt::Display for PathSecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Absolute { file_path } => {
+                write!(f, "refusing absolute path {file_path:?}")
+            }
+            Self::ParentTraversal { file_path } => {
+                write!(f, "refusing path with `..` component {file_path:?}")
+            }
+            Self::BaseUnavailable { base, reason } => {
+                write!(f, "cannot resolve repo base {}: {reason}", base.display())
+            }
+            Self::EscapesBase {
+                file_path,
+                resolved,
+            } => write!(
+                f,
+                "path {file_path:?} resolves outside the repo to {}",
+                resolved.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathSecurityError {}
+
+/// A subprocess that exited unsuccessfully.
+///
+/// Carries the last [`ERROR_OUTPUT_LINES`] lines of its combined stdout and
+/// stderr, with tokens redacted, so summaries say why a command failed
+/// rather than just its exit status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandError {
+    /// The command line, e.g. `git push origin HEAD`.
+    pub command: String,
+    /// Exit status as printed by the OS, e.g. `exit status: 128`.
+    pub status: String,
+    /// Redacted tail of the output; empty when the command printed nothing.
+    pub output: String,
+}
+
+impl CommandError {
+    /// Error for `command` from its captured `output`.
+    pub fn from_output(command: impl Into<String>, output: &std::process::Output) -> Self {
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Self {
+            command: command.into(),
+            status: output.status.to_string(),
+            output: redact(&tail_lines(&text, ERROR_OUTPUT_LINES)),
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` exited with {}", self.command, self.status)?;
+        if !self.output.is_empty() {
+            write!(f, ": {}", self.output)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// The last `n` non-empty lines of `text`, trimmed.
+fn tail_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
 }
 
 // ─── Main commit entry-point ──────────────────────────────────────────────────
 
-/// Commits `content` to `file_path` in `{org}/{repo}` with `message`.
+/// Commits raw `content` bytes to `file_path` in `{org}/{repo}` with `message`.
+///
+/// Content is treated as opaque bytes, so lockfiles, binaries and non-UTF-8
+/// text round-trip unchanged.  The file mode of an existing file is kept:
+/// the contents API retains the mode of the blob it replaces, and the local
+/// strategy re-applies the previous permissions after writing.
 ///
 /// Strategy order:
 /// 1. **`gh` CLI** — uses the GitHub API via `gh api` to create/update the file
-///    entirely in-memory; no local clone required.
+///    entirely in-memory; no local clone required.  Skipped when the binary
+///    is absent (see [`gh_available`]).
 /// 2. **Local git** — writes the file to `local_base/file_path`, then runs
-///    `git add`, `git commit`, and `git push`.  Only attempted when
-///    `local_base` is `Some(_)` and the gh CLI attempt fails (or when
-///    `GITHUB_TOKEN` is not set).
+///    `git add`, `git commit`, and `git push` to `push`.  Only attempted
+///    when `local_base` is `Some(_)`.
+/// 3. **REST API** — contents GET/PUT via `reqwest`, only attempted when
+///    `GITHUB_TOKEN` is set.
+///
+/// Only strategies listed in `strategies` are attempted (pass
+/// [`CommitStrategy::ALL`] for the default behaviour).
+///
+/// `previous` is the content the change was computed from; it only feeds the
+/// [`DiffStats`] of the result.
 ///
-/// Returns `Err` only if *both* strategies fail.
-pub async fn commit_file(
+/// Returns `Err` only if *every* applicable strategy fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn commit_bytes(
     org: &str,
     repo: &str,
     file_path: &str,
-    content: &str,
+    previous: &[u8],
+    content: &[u8],
     message: &str,
     local_base: Option<&Path>,
+    push: &PushTarget,
+    strategies: &[CommitStrategy],
 ) -> Result<CommitResult> {
     let slug = format!("{org}/{repo}");
+    let stats = diff_stats(previous, content);
+    let mut failures: Vec<String> = Vec::new();
 
     // ── Attempt 1: gh CLI ──────────────────────────────────────────────────
-    match commit_via_gh_cli(&slug, file_path, content, message) {
-        Ok(sha) => {
-            info!(repo = %slug, file = file_path, sha = %sha, "committed via gh CLI");
-            return Ok(CommitResult {
-                repo: slug,
-                file_path: file_path.to_string(),
-                strategy: CommitStrategy::GhCli,
-                sha,
-            });
-        }
-        Err(e) => {
-            warn!(
-                repo = %slug,
-                file = file_path,
-                error = %e,
-                "gh CLI commit failed — will try local git fallback"
-            );
+    if !strategies.contains(&CommitStrategy::GhCli) {
+        failures.push("gh CLI: disabled".to_string());
+    } else if gh_available() {
+        match commit_via_gh_cli(&slug, file_path, content, message, None) {
+            Ok(sha) => {
+                info!(repo = %slug, file = file_path, sha = %sha, "committed via gh CLI");
+                return Ok(CommitResult {
+                    repo: slug,
+                    file_path: file_path.to_string(),
+                    strategy: CommitStrategy::GhCli,
+                    sha,
+                    stats,
+                });
+            }
+            Err(e) => {
+                warn!(
+                    repo = %slug,
+                    file = file_path,
+                    error = %e,
+                    "gh CLI commit failed — will try fallbacks"
+                );
+                failures.push(format!("gh CLI: {e:#}"));
+            }
         }
+    } else {
+        failures.push("gh CLI: not installed".to_string());
     }
 
     // ── Attempt 2: local git ───────────────────────────────────────────────
-    let base = local_base.with_context(|| {
-        format!("gh CLI failed and no local_base provided for {slug}/{file_path}")
-    })?;
+    if !strategies.contains(&CommitStrategy::LocalGit) {
+        failures.push("local git: disabled".to_string());
+    } else if let Some(base) = local_base {
+        match commit_files_via_local_git(base, &[(file_path, content)], message, push) {
+            Ok(sha) => {
+                info!(repo = %slug, file = file_path, "committed via local git");
+                return Ok(CommitResult {
+                    repo: slug,
+                    file_path: file_path.to_string(),
+                    strategy: CommitStrategy::LocalGit,
+                    sha,
+                    stats,
+                });
+            }
+            Err(e) => {
+                // Security refusals must not be papered over by a remote write.
+                if e.downcast_ref::<PathSecurityError>().is_some() {
+                    return Err(
+                        e.context(format!("local git commit refused for {slug}/{file_path}"))
+                    );
+                }
+                warn!(repo = %slug, file = file_path, error = %e, "local git commit failed");
+                failures.push(format!("local git: {e:#}"));
+            }
+        }
+    } else {
+        failures.push("local git: no local_base provided".to_string());
+    }
 
-    let sha = commit_via_local_git(base, file_path, content, message)
-        .with_context(|| format!("local git commit failed for {slug}/{file_path}"))?;
+    // ── Attempt 3: REST API ────────────────────────────────────────────────
+    if !strategies.contains(&CommitStrategy::RestApi) {
+        failures.push("REST API: disabled".to_string());
+    } else if let Some(rest) = GithubRest::from_env() {
+        match rest
+            .commit_file(&slug, file_path, content, message, None)
+            .await
+        {
+            Ok(sha) => {
+                info!(repo = %slug, file = file_path, sha = %sha, "committed via REST API");
+                return Ok(CommitResult {
+                    repo: slug,
+                    file_path: file_path.to_string(),
+                    strategy: CommitStrategy::RestApi,
+                    sha,
+                    stats,
+                });
+            }
+            Err(e) => failures.push(format!("REST API: {e:#}")),
+        }
+    } else {
+        failures.push("REST API: GITHUB_TOKEN not set".to_string());
+    }
 
-    info!(repo = %slug, file = file_path, "committed via local git");
-    Ok(CommitResult {
-        repo: slug,
-        file_path: file_path.to_string(),
-        strategy: CommitStrategy::LocalGit,
-        sha,
-    })
+    anyhow::bail!(
+        "all commit strategies failed for {slug}/{file_path}: {}",
+        failures.join("; ")
+    )
+}
+
+/// Commits every file of `changes` to `{org}/{repo}` as a single commit.
+///
+/// A single change goes through [`commit_bytes`] with its full fallback
+/// order.  Several changes need a strategy that can write more than one file
+/// per commit: gh CLI (git data API) or local git; the contents-based REST
+/// strategy is skipped.  Returns one [`CommitResult`] per file, all carrying
+/// the same commit SHA.
+#[allow(clippy::too_many_arguments)]
+pub async fn commit_changes(
+    org: &str,
+    repo: &str,
+    changes: &[FileChange<'_>],
+    message: &str,
+    local_base: Option<&Path>,
+    push: &PushTarget,
+    strategies: &[CommitStrategy],
+) -> Result<Vec<CommitResult>> {
+    if let [change] = changes {
+        return commit_bytes(
+            org,
+            repo,
+            change.path,
+            change.previous,
+            change.content,
+            message,
+            local_base,
+            push,
+            strategies,
+        )
+        .await
+        .map(|result| vec![result]);
+    }
+    anyhow::ensure!(!changes.is_empty(), "no files to commit");
+
+    let slug = format!("{org}/{repo}");
+    let files: Vec<(&str, &[u8])> = changes.iter().map(|c| (c.path, c.content)).collect();
+    let results = |strategy: CommitStrategy, sha: String| {
+        changes
+            .iter()
+            .map(|c| CommitResult {
+                repo: slug.clone(),
+                file_path: c.path.to_string(),
+                strategy,
+                sha: sha.clone(),
+                stats: diff_stats(c.previous, c.content),
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut failures: Vec<String> = Vec::new();
+
+    if !strategies.contains(&CommitStrategy::GhCli) {
+        failures.push("gh CLI: disabled".to_string());
+    } else if gh_available() {
+        match commit_tree_via_gh(&slug, &files, message, None) {
+            Ok(sha) => {
+                info!(repo = %slug, files = files.len(), sha = %sha, "committed via gh CLI");
+                return Ok(results(CommitStrategy::GhCli, sha));
+            }
+            Err(e) => {
+                warn!(repo = %slug, error = %e, "gh CLI commit failed — will try fallbacks");
+                failures.push(format!("gh CLI: {e:#}"));
+            }
+        }
+    } else {
+        failures.push("gh CLI: not installed".to_string());
+    }
+
+    if !strategies.contains(&CommitStrategy::LocalGit) {
+        failures.push("local git: disabled".to_string());
+    } else if let Some(base) = local_base {
+        match commit_files_via_local_git(base, &files, message, push) {
+            Ok(sha) => {
+                info!(repo = %slug, files = files.len(), "committed via local git");
+                return Ok(results(CommitStrategy::LocalGit, sha));
+            }
+            Err(e) => {
+                if e.downcast_ref::<PathSecurityError>().is_some() {
+                    return Err(e.context(format!("local git commit refused for {slug}")));
+                }
+                warn!(repo = %slug, error = %e, "local git commit failed");
+                failures.push(format!("local git: {e:#}"));
+            }
+        }
+    } else {
+        failures.push("local git: no local_base provided".to_string());
+    }
+
+    failures.push("REST API: cannot commit several files at once".to_string());
+    anyhow::bail!(
+        "all commit strategies failed for {slug} ({} files): {}",
+        changes.len(),
+        failures.join("; ")
+    )
+}
+
+// ─── Reading ──────────────────────────────────────────────────────────────────
+
+/// Content of `file_path` on the default branch of `{org}/{repo}`, read
+/// through the gh CLI, else the REST API; `None` when the file does not
+/// exist.
+pub async fn read_remote_file(org: &str, repo: &str, file_path: &str) -> Result<Option<String>> {
+    let slug = format!("{org}/{repo}");
+    let mut failures: Vec<String> = Vec::new();
+    if gh_available() {
+        match gh_api(&[
+            &format!("repos/{slug}/contents/{file_path}"),
+            "--jq",
+            ".content",
+        ]) {
+            Ok(encoded) => {
+                return decode_content(&encoded)
+                    .with_context(|| format!("decode {file_path} of {slug}"))
+                    .map(Some);
+            }
+            Err(e) if e.to_string().contains("Not Found") => return Ok(None),
+            Err(e) => {
+                warn!(repo = %slug, file = file_path, error = %e, "gh CLI read failed — trying the REST API");
+                failures.push(format!("gh CLI: {e:#}"));
+            }
+        }
+    } else {
+        failures.push("gh CLI: not installed".to_string());
+    }
+    match GithubRest::from_env() {
+        Some(rest) => rest.read_file(&slug, file_path).await,
+        None => {
+            failures.push("REST API: GITHUB_TOKEN not set".to_string());
+            anyhow::bail!("cannot read {slug}/{file_path}: {}", failures.join("; "))
+        }
+    }
 }
 
 // ─── gh CLI strategy ──────────────────────────────────────────────────────────
 
+/// Payloads larger than this (raw bytes) are committed through the git data
+/// API with the request body streamed over stdin.
+///
+/// The contents API needs the whole base64 payload as a single argv entry,
+/// and Linux caps a single argument at 128 KiB; base64 inflates by 4/3.
+const LARGE_FILE_THRESHOLD: usize = 64 * 1024;
+
+/// Default mode for files that do not exist yet in the target tree.
+const DEFAULT_FILE_MODE: &str = "100644";
+
 /// Commits `content` to `file_path` in `repo` (e.g. `"org/name"`) using the
 /// GitHub REST API via `gh api`.
 ///
 /// Uses `gh api repos/{repo}/contents/{file_path}` (PUT).  The current file
 /// SHA is fetched first so GitHub can confirm we're updating the right blob.
-fn commit_via_gh_cli(repo: &str, file_path: &str, content: &str, message: &str) -> Result<String> {
-    // ── Fetch current blob SHA ──
-    let sha_output = Command::new("gh")
-        .args([
-            "api",
-            &format!("repos/{repo}/contents/{file_path}"),
-            "--jq",
-            ".sha",
-        ])
-        .output()
-        .context("gh CLI not found or failed to run")?;
-
-    if !sha_output.status.success() {
-        let stderr = String::from_utf8_lossy(&sha_output.stderr);
-        anyhow::bail!("gh api GET failed: {stderr}");
+/// When `branch` is `Some(_)` the commit lands on that branch instead of the
+/// repository's default branch.
+///
+/// Payloads above `LARGE_FILE_THRESHOLD` transparently switch to
+/// [`commit_via_gh_blob`].
+pub fn commit_via_gh_cli(
+    repo: &str,
+    file_path: &str,
+    content: &[u8],
+    message: &str,
+    branch: Option<&str>,
+) -> Result<String> {
+    if content.len() > LARGE_FILE_THRESHOLD {
+        debug!(
+            file = file_path,
+            bytes = content.len(),
+            "large payload — using blob upload"
+        );
+        return commit_via_gh_blob(repo, file_path, content, message, branch);
     }
 
-    let blob_sha = String::from_utf8_lossy(&sha_output.stdout)
-        .trim()
-        .trim_matches('"')
-        .to_string();
+    let contents_path = format!("repos/{repo}/contents/{file_path}");
+
+    // ── Fetch current blob SHA ──
+    let get_path = match branch {
+        Some(b) => format!("{contents_path}?ref={b}"),
+        None => contents_path.clone(),
+    };
+    let blob_sha = gh_api(&[&get_path, "--jq", ".sha"]).context("gh api GET failed")?;
 
     debug!(file = file_path, blob_sha = %blob_sha, "fetched current blob SHA");
 
     // ── PUT updated content ──
-    let encoded = BASE64.encode(content.as_bytes());
+    let encoded = BASE64.encode(content);
+
+    let message_field = format!("message={message}");
+    let content_field = format!("content={encoded}");
+    let sha_field = format!("sha={blob_sha}");
+    let mut args = vec![
+        "--method",
+        "PUT",
+        &contents_path,
+        "--field",
+        &message_field,
+        "--field",
+        &content_field,
+        "--field",
+        &sha_field,
+    ];
+    let branch_field = branch.map(|b| format!("branch={b}"));
+    if let Some(ref field) = branch_field {
+        args.extend(["--field", field]);
+    }
+    args.extend(["--jq", ".commit.sha"]);
 
-    let put_output = Command::new("gh")
-        .args([
-            "api",
+    gh_api(&args).context("gh api PUT failed")
+}
+
+/// Commits `content` through the git data API (blob → tree → commit → ref).
+fn commit_via_gh_blob(
+    repo: &str,
+    file_path: &str,
+    content: &[u8],
+    message: &str,
+    branch: Option<&str>,
+) -> Result<String> {
+    commit_tree_via_gh(repo, &[(file_path, content)], message, branch)
+}
+
+/// Commits every `(path, content)` of `files` as one commit through the git
+/// data API (blobs → tree → commit → ref).
+///
+/// Every request body is piped to `gh api --input -`, so payload size is
+/// bounded only by GitHub's 100 MB blob limit rather than argv limits.  The
+/// base tree is fetched once and the existing file modes are carried over
+/// from it (see [`file_modes`]); a tree that cannot be read fails the commit
+/// rather than dropping an executable bit.
+/// When `branch` is `None` the repository's default branch is updated.
+pub fn commit_tree_via_gh(
+    repo: &str,
+    files: &[(&str, &[u8])],
+    message: &str,
+    branch: Option<&str>,
+) -> Result<String> {
+    let branch = match branch {
+        Some(b) => b.to_string(),
+        None => gh_api(&[&format!("repos/{repo}"), "--jq", ".default_branch"])
+            .context("resolve default branch")?,
+    };
+
+    let head_sha = gh_api(&[
+        &format!("repos/{repo}/git/ref/heads/{branch}"),
+        "--jq",
+        ".object.sha",
+    ])
+    .context("resolve branch head")?;
+    let base_tree = gh_api(&[
+        &format!("repos/{repo}/git/commits/{head_sha}"),
+        "--jq",
+        ".tree.sha",
+    ])
+    .context("resolve base tree")?;
+
+    let tree = gh_api(&[&format!("repos/{repo}/git/trees/{base_tree}?recursive=1")])
+        .context("fetch base tree")?;
+    let tree: Value = serde_json::from_str(&tree).context("parse base tree")?;
+    let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+    let modes = file_modes(&tree, &paths)?;
+
+    let mut entries: Vec<(String, String, String)> = Vec::new();
+    for ((file_path, content), mode) in files.iter().zip(modes) {
+        let blob_sha = gh_api_with_body(
+            &[
+                "--method",
+                "POST",
+                &format!("repos/{repo}/git/blobs"),
+                "--jq",
+                ".sha",
+            ],
+            &json!({ "content": BASE64.encode(content), "encoding": "base64" }),
+        )
+        .with_context(|| format!("create blob for {file_path}"))?;
+        debug!(file = file_path, blob_sha = %blob_sha, mode = %mode, "uploaded blob");
+        entries.push((file_path.to_string(), mode, blob_sha));
+    }
+
+    let tree_sha = gh_api_with_body(
+        &[
             "--method",
-            "PUT",
-            &format!("repos/{repo}/contents/{file_path}"),
-            "--field",
-            &format!("message={message}"),
-            "--field",
-            &format!("content={encoded}"),
-            "--field",
-            &format!("sha={blob_sha}"),
+            "POST",
+            &format!("repos/{repo}/git/trees"),
             "--jq",
-            ".commit.sha",
-        ])
-        .output()
-        .context("gh api PUT failed")?;
+            ".sha",
+        ],
+        &tree_request(&base_tree, &entries),
+    )
+    .context("create tree")?;
 
-    if !put_output.status.success() {
-        let stderr = String::from_utf8_lossy(&put_output.stderr);
-        anyhow::bail!("gh api PUT returned non-zero: {stderr}");
-    }
+    let commit_sha = gh_api_with_body(
+        &[
+            "--method",
+            "POST",
+            &format!("repos/{repo}/git/commits"),
+            "--jq",
+            ".sha",
+        ],
+        &json!({ "message": message, "tree": tree_sha, "parents": [head_sha] }),
+    )
+    .context("create commit")?;
 
-    let commit_sha = String::from_utf8_lossy(&put_output.stdout)
-        .trim()
-        .trim_matches('"')
-        .to_string();
+    gh_api_with_body(
+        &[
+            "--method",
+            "PATCH",
+            &format!("repos/{repo}/git/refs/heads/{branch}"),
+        ],
+        &json!({ "sha": commit_sha }),
+    )
+    .context("update branch ref")?;
 
     Ok(commit_sha)
 }
 
+/// Mode of each of `paths` in a recursive `GET git/trees` response: the
+/// existing blob's mode, or [`DEFAULT_FILE_MODE`] for a new file.  A
+/// truncated listing cannot tell a new file from an unlisted one, so a path
+/// missing from it is an error, as is a response without a `tree`.
+fn file_modes(tree: &Value, paths: &[&str]) -> Result<Vec<String>> {
+    let entries = tree["tree"]
+        .as_array()
+        .context("base tree response has no entries")?;
+    let truncated = tree["truncated"].as_bool().unwrap_or(false);
+    paths
+        .iter()
+        .map(|path| {
+            let entry = entries
+                .iter()
+                .find(|e| e["path"] == *path && e["type"] == "blob");
+            match entry {
+                Some(entry) => entry["mode"]
+                    .as_str()
+                    .map(str::to_string)
+                    .with_context(|| format!("base tree entry {path} has no mode")),
+                None if truncated => anyhow::bail!(
+                    "base tree listing is truncated and does not include {path} — cannot tell its mode"
+                ),
+                None => Ok(DEFAULT_FILE_MODE.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Request body for `POST git/trees` replacing each `(path, mode, blob sha)`
+/// of `entries` on `base_tree`.
+fn tree_request(base_tree: &str, entries: &[(String, String, String)]) -> Value {
+    json!({
+        "base_tree": base_tree,
+        "tree": entries
+            .iter()
+            .map(|(path, mode, sha)| json!({ "path": path, "mode": mode, "type": "blob", "sha": sha }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Like [`gh_api`], but streams `body` as the JSON request body via
+/// `--input -` instead of passing fields on the command line.
+pub fn gh_api_with_body(args: &[&str], body: &Value) -> Result<String> {
+    let mut full_args = args.to_vec();
+    full_args.extend(["--input", "-"]);
+    run_gh_api(&full_args, Some(body.to_string().as_bytes()))
+}
+
+/// Runs `gh api` with `args` and returns its trimmed stdout.
+///
+/// Surrounding quotes are stripped so `--jq` string results can be used
+/// directly.  Returns `Err` if the binary is missing or exits non-zero.
+pub fn gh_api(args: &[&str]) -> Result<String> {
+    run_gh_api(args, None)
+}
+
+/// Shared `gh api` runner.
+///
+/// When the [`TokenPool`] has tokens, the current one is passed as
+/// `GH_TOKEN`; a rate-limit failure rotates to the next token and retries.
+fn run_gh_api(args: &[&str], stdin: Option<&[u8]>) -> Result<String> {
+    let pool = TokenPool::global();
+    let attempts = pool.len().max(1);
+
+    for _ in 0..attempts {
+        let token = pool.current();
+        let mut cmd = Command::new("gh");
+        apply_to_command(&mut cmd)
+            .arg("api")
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some((_, ref t)) = token {
+            cmd.env("GH_TOKEN", t);
+        }
+
+        let mut child = cmd.spawn().context("gh CLI not found or failed to run")?;
+        if let Some(body) = stdin {
+            child
+                .stdin
+                .take()
+                .context("gh stdin unavailable")?
+                .write_all(body)
+                .context("write request body to gh")?;
+        }
+        let output = child.wait_with_output().context("wait for gh")?;
+
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .trim_matches('"')
+                .to_string());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.to_ascii_lowercase().contains("rate limit")
+            && let Some((idx, _)) = token
+            && pool.rotate_from(idx)
+        {
+            continue;
+        }
+        anyhow::bail!("gh api returned non-zero: {}", redact(stderr.trim()));
+    }
+    anyhow::bail!("gh api: every configured GitHub token is rate limited")
+}
+
 // ─── Local git strategy ───────────────────────────────────────────────────────
 
 /// Writes `content` to `base/file_path`, then runs `git add`, `git commit`,
 /// and `git push` in `base`.
+///
+/// If the file already exists its permissions (e.g. the executable bit) are
+/// restored after writing.
+#[cfg(test)]
 fn commit_via_local_git(
     base: &Path,
     file_path: &str,
-    content: &str,
+    content: &[u8],
     message: &str,
 ) -> Result<String> {
-    let full_path = base.join(file_path);
+    commit_files_via_local_git(
+        base,
+        &[(file_path, content)],
+        message,
+        &PushTarget::default(),
+    )
+}
 
-    // Ensure parent directory exists
-    if let Some(parent) = full_path.parent() {
-        std::fs::create_dir_all(parent)
-            .with_context(|| format!("create parent dirs for {}", full_path.display()))?;
-    }
+/// Writes every `(path, content)` of `files` under `base`, commits them as
+/// one commit and pushes it to `push`.
+///
+/// Every path is checked before anything is written, so a refused path
+/// leaves the checkout untouched.  Existing files keep their permissions
+/// (e.g. the executable bit).
+fn commit_files_via_local_git(
+    base: &Path,
+    files: &[(&str, &[u8])],
+    message: &str,
+    push: &PushTarget,
+) -> Result<String> {
+    let full_paths = files
+        .iter()
+        .map(|(file_path, _)| resolve_inside(base, file_path))
+        .collect::<std::result::Result<Vec<PathBuf>, PathSecurityError>>()?;
+
+    for (full_path, (file_path, content)) in full_paths.iter().zip(files) {
+        // Ensure parent directory exists
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create parent dirs for {}", full_path.display()))?;
+        }
+
+        let previous_permissions = std::fs::metadata(full_path).ok().map(|m| m.permissions());
 
-    std::fs::write(&full_path, content)
-        .with_context(|| format!("write {}", full_path.display()))?;
+        std::fs::write(full_path, content)
+            .with_context(|| format!("write {}", full_path.display()))?;
 
-    // git add
-    run_git(base, &["add", file_path]).with_context(|| format!("git add {file_path}"))?;
+        if let Some(permissions) = previous_permissions {
+            std::fs::set_permissions(full_path, permissions)
+                .with_context(|| format!("restore permissions of {}", full_path.display()))?;
+        }
+
+        // git add
+        run_git(base, &["add", file_path]).with_context(|| format!("git add {file_path}"))?;
+    }
 
     // git commit
     run_git(base, &["commit", "-m", message]).with_context(|| "git commit")?;
 
     // git push
-    run_git(base, &["push"]).with_context(|| "git push")?;
+    let credentials = push.credentials()?;
+    run_git_env(base, &push.args(), &credentials.env())
+        .with_context(|| format!("git push to {push}"))?;
 
     // Return short SHA of HEAD
-    let output = Command::new("git")
+    let output = apply_to_command(&mut Command::new("git"))
         .args(["rev-parse", "--short", "HEAD"])
         .current_dir(base)
         .output()
@@ -196,16 +956,210 @@ fn commit_via_local_git(
     Ok(sha)
 }
 
+// ─── Cloning ──────────────────────────────────────────────────────────────────
+
+/// Clones `url` into `dest` with `push`'s credentials.
+///
+/// With `sparse` patterns the clone is blob-less (`--filter=blob:none`) and
+/// only paths matching the non-cone `sparse-checkout` patterns are checked
+/// out; other blobs are fetched on demand if ever needed.  A failed clone
+/// leaves no directory behind.
+pub fn clone_checkout(
+    url: &str,
+    dest: &Path,
+    sparse: Option<&[String]>,
+    push: &PushTarget,
+) -> Result<()> {
+    let parent = dest
+        .parent()
+        .with_context(|| format!("{} has no parent directory", dest.display()))?;
+    std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    let credentials = push.credentials()?;
+    let dest_arg = dest.to_string_lossy();
+
+    let mut args = vec!["clone", "--quiet"];
+    if sparse.is_some() {
+        args.extend(["--filter=blob:none", "--sparse"]);
+    }
+    args.extend([url, dest_arg.as_ref()]);
+    let result = run_git_env(parent, &args, &credentials.env()).and_then(|()| {
+        let Some(patterns) = sparse else {
+            return Ok(());
+        };
+        let mut args = vec!["sparse-checkout", "set", "--no-cone"];
+        args.extend(patterns.iter().map(String::as_str));
+        run_git_env(dest, &args, &credentials.env())
+    });
+    if result.is_err() && dest.exists() {
+        let _ = std::fs::remove_dir_all(dest);
+    }
+    result
+}
+
+// ─── Reverts ──────────────────────────────────────────────────────────────────
+
+/// Reverts `shas` (given oldest first) in the checkout at `base` and pushes
+/// to `push`.
+///
+/// The checkout is fast-forwarded first so commits made remotely (gh CLI,
+/// REST) are present.  Commits are reverted newest first, one revert commit
+/// each; if any revert conflicts the whole operation is aborted and nothing
+/// is pushed.  Returns the short SHA of the last revert commit.
+pub fn revert_commits(base: &Path, shas: &[&str], push: &PushTarget) -> Result<String> {
+    anyhow::ensure!(!shas.is_empty(), "no commits to revert");
+    let credentials = push.credentials()?;
+    run_git_env(base, &["pull", "--ff-only"], &credentials.env()).context("git pull --ff-only")?;
+
+    let mut args = vec!["revert", "--no-edit"];
+    args.extend(shas.iter().rev());
+    if let Err(e) = run_git(base, &args) {
+        // Leave the checkout as it was; a failed abort just means nothing started.
+        let _ = run_git(base, &["revert", "--abort"]);
+        return Err(e.context(format!("git revert {}", shas.join(" "))));
+    }
+    run_git_env(base, &push.args(), &credentials.env())
+        .with_context(|| format!("git push to {push}"))?;
+
+    let output = apply_to_command(&mut Command::new("git"))
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(base)
+        .output()
+        .context("git rev-parse HEAD")?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// ─── Tags ─────────────────────────────────────────────────────────────────────
+
+/// Creates the annotated tag `tag` on commit `sha` of `org/repo` through the
+/// GitHub API (a tag object, then its `refs/tags/` ref).  Returns the tag
+/// object's SHA.
+pub fn create_tag_via_gh(
+    org: &str,
+    repo: &str,
+    tag: &str,
+    sha: &str,
+    message: &str,
+) -> Result<String> {
+    let tag_sha = gh_api_with_body(
+        &[
+            "--method",
+            "POST",
+            &format!("repos/{org}/{repo}/git/tags"),
+            "--jq",
+            ".sha",
+        ],
+        &json!({ "tag": tag, "message": message, "object": sha, "type": "commit" }),
+    )
+    .with_context(|| format!("create tag object {tag} in {org}/{repo}"))?;
+    gh_api_with_body(
+        &["--method", "POST", &format!("repos/{org}/{repo}/git/refs")],
+        &json!({ "ref": format!("refs/tags/{tag}"), "sha": tag_sha }),
+    )
+    .with_context(|| format!("create ref refs/tags/{tag} in {org}/{repo}"))?;
+    Ok(tag_sha)
+}
+
+/// Creates the annotated tag `tag` on `sha` in the checkout at `base` and
+/// pushes it to `push`'s remote (`origin` by default).
+///
+/// The local tag is deleted again when the push fails, so a retry starts
+/// clean.
+pub fn create_tag_via_local_git(
+    base: &Path,
+    tag: &str,
+    sha: &str,
+    message: &str,
+    push: &PushTarget,
+) -> Result<()> {
+    let credentials = push.credentials()?;
+    run_git(base, &["tag", "--annotate", "--message", message, tag, sha])
+        .with_context(|| format!("git tag {tag}"))?;
+    let remote = push.remote.as_deref().unwrap_or("origin");
+    let refspec = format!("refs/tags/{tag}");
+    if let Err(e) = run_git_env(base, &["push", remote, &refspec], &credentials.env()) {
+        let _ = run_git(base, &["tag", "--delete", tag]);
+        return Err(e.context(format!("git push {remote} {refspec}")));
+    }
+    Ok(())
+}
+
+/// Resolves `file_path` against `base`, guaranteeing the result stays inside
+/// `base` once symlinks are followed.
+///
+/// `file_path` must be relative and free of `..`.  The deepest existing
+/// ancestor of the target (or the target itself) is canonicalized and must
+/// still live under the canonical base — this catches symlinked directories
+/// or files pointing out of the checkout, including dangling ones.
+pub(crate) fn resolve_inside(
+    base: &Path,
+    file_path: &str,
+) -> std::result::Result<PathBuf, PathSecurityError> {
+    for component in Path::new(file_path).components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            Component::ParentDir => {
+                return Err(PathSecurityError::ParentTraversal {
+                    file_path: file_path.to_string(),
+                });
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(PathSecurityError::Absolute {
+                    file_path: file_path.to_string(),
+                });
+            }
+        }
+    }
+
+    let canonical_base = base
+        .canonicalize()
+        .map_err(|e| PathSecurityError::BaseUnavailable {
+            base: base.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+    let full_path = canonical_base.join(file_path);
+
+    // Find the deepest path component that exists on disk (a dangling
+    // symlink counts as existing) and resolve it.
+    let mut probe = full_path.as_path();
+    while std::fs::symlink_metadata(probe).is_err() {
+        probe = match probe.parent() {
+            Some(parent) => parent,
+            None => break,
+        };
+    }
+    let escapes = |resolved: PathBuf| PathSecurityError::EscapesBase {
+        file_path: file_path.to_string(),
+        resolved,
+    };
+    let resolved = probe
+        .canonicalize()
+        .map_err(|_| escapes(std::fs::read_link(probe).unwrap_or_else(|_| probe.to_path_buf())))?;
+    if !resolved.starts_with(&canonical_base) {
+        return Err(escapes(resolved));
+    }
+
+    Ok(full_path)
+}
+
 /// Runs a git subcommand in `dir`, returns `Err` if it exits non-zero.
 fn run_git(dir: &Path, args: &[&str]) -> Result<()> {
-    let status = Command::new("git")
+    run_git_env(dir, args, &[])
+}
+
+/// [`run_git`] with extra environment variables.
+///
+/// Output is captured; a failure becomes a [`CommandError`].
+fn run_git_env(dir: &Path, args: &[&str], env: &[(&str, String)]) -> Result<()> {
+    let output = apply_to_command(&mut Command::new("git"))
         .args(args)
+        .envs(env.iter().map(|(k, v)| (k, v)))
         .current_dir(dir)
-        .status()
+        .stdin(Stdio::null())
+        .output()
         .with_context(|| format!("spawn git {:?}", args))?;
 
-    if !status.success() {
-        anyhow::bail!("git {:?} exited with {status}", args);
+    if !output.status.success() {
+        return Err(CommandError::from_output(format!("git {}", args.join(" ")), &output).into());
     }
     Ok(())
 }
@@ -250,13 +1204,58 @@ mod tests {
         (repo, bare)
     }
 
+    #[test]
+    fn test_tree_request_shape() {
+        let body = tree_request(
+            "base",
+            &[(
+                "Cargo.lock".to_string(),
+                "100755".to_string(),
+                "blob".to_string(),
+            )],
+        );
+        assert_eq!(body["base_tree"], "base");
+        assert_eq!(body["tree"][0]["path"], "Cargo.lock");
+        assert_eq!(body["tree"][0]["mode"], "100755");
+        assert_eq!(body["tree"][0]["type"], "blob");
+        assert_eq!(body["tree"][0]["sha"], "blob");
+    }
+
+    #[test]
+    fn test_file_modes_from_base_tree() {
+        let tree = json!({
+            "tree": [
+                { "path": "scripts/release.sh", "mode": "100755", "type": "blob" },
+                { "path": "we\"ird\\name", "mode": "100644", "type": "blob" },
+                { "path": "scripts", "mode": "040000", "type": "tree" },
+            ],
+            "truncated": false,
+        });
+        assert_eq!(
+            file_modes(&tree, &["scripts/release.sh", "we\"ird\\name", "new.toml"]).unwrap(),
+            ["100755", "100644", DEFAULT_FILE_MODE]
+        );
+
+        let truncated = json!({ "tree": [], "truncated": true });
+        assert!(file_modes(&truncated, &["new.toml"]).is_err());
+        assert!(file_modes(&json!({ "message": "Not Found" }), &["Cargo.toml"]).is_err());
+    }
+
+    #[test]
+    fn test_large_file_threshold_fits_argv_limit() {
+        // base64 of a threshold-sized payload must stay under Linux's 128 KiB
+        // MAX_ARG_STRLEN, including the `content=` prefix.
+        let encoded = BASE64.encode(vec![0u8; LARGE_FILE_THRESHOLD]);
+        assert!(encoded.len() + "content=".len() < 128 * 1024);
+    }
+
     #[test]
     fn test_local_git_commit_new_file() {
         let (repo, _bare) = make_git_repo_with_remote();
         let result = commit_via_local_git(
             repo.path(),
             "Cargo.toml",
-            "[package]\nname=\"x\"\n",
+            b"[package]\nname=\"x\"\n",
             "chore: update Cargo.toml",
         );
         assert!(
@@ -272,15 +1271,344 @@ mod tests {
         let (repo, _bare) = make_git_repo_with_remote();
 
         // Write initial version
-        commit_via_local_git(repo.path(), "Cargo.toml", "version = \"0.1\"", "init Cargo").unwrap();
+        commit_via_local_git(
+            repo.path(),
+            "Cargo.toml",
+            b"version = \"0.1\"",
+            "init Cargo",
+        )
+        .unwrap();
 
         // Update it
         let result = commit_via_local_git(
             repo.path(),
             "Cargo.toml",
-            "version = \"0.2\"",
+            b"version = \"0.2\"",
             "bump version",
         );
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_local_git_commit_binary_content() {
+        let (repo, _bare) = make_git_repo_with_remote();
+        let bytes: &[u8] = &[0x00, 0xff, 0xfe, b'\n', 0x80];
+        commit_via_local_git(repo.path(), "artifact.bin", bytes, "add artifact").unwrap();
+        assert_eq!(fs::read(repo.path().join("artifact.bin")).unwrap(), bytes);
+    }
+
+    #[tokio::test]
+    async fn test_commit_changes_lands_files_in_one_commit() {
+        let (repo, _bare) = make_git_repo_with_remote();
+        let changes = [
+            FileChange {
+                path: "Cargo.toml",
+                previous: b"",
+                content: b"v = \"0.2\"\n",
+            },
+            FileChange {
+                path: "crates/a/Cargo.toml",
+                previous: b"",
+                content: b"v = \"0.2\"\n",
+            },
+        ];
+        let results = commit_changes(
+            "org",
+            "repo",
+            &changes,
+            "chore(deps): bump",
+            Some(repo.path()),
+            &PushTarget::default(),
+            &[CommitStrategy::LocalGit],
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].sha, results[1].sha);
+        assert_eq!(results[1].file_path, "crates/a/Cargo.toml");
+        let output = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "2");
+    }
+
+    #[test]
+    fn test_local_git_pushes_to_custom_target() {
+        let (repo, _bare) = make_git_repo_with_remote();
+        let fork = TempDir::new().unwrap();
+        run_git(fork.path(), &["init", "--bare"]).unwrap();
+        run_git(
+            repo.path(),
+            &["remote", "add", "fork", fork.path().to_str().unwrap()],
+        )
+        .unwrap();
+
+        let push = PushTarget {
+            remote: Some("fork".to_string()),
+            refspec: Some("HEAD:refs/heads/evo-update".to_string()),
+            ..PushTarget::default()
+        };
+        assert_eq!(push.to_string(), "fork HEAD:refs/heads/evo-update");
+        commit_files_via_local_git(repo.path(), &[("Cargo.toml", b"v = 1")], "bump", &push)
+            .unwrap();
+        run_git(
+            fork.path(),
+            &["rev-parse", "--verify", "refs/heads/evo-update"],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_run_git_failure_carries_output() {
+        let dir = TempDir::new().unwrap();
+        let err = run_git(dir.path(), &["rev-parse", "--verify", "HEAD"]).unwrap_err();
+        let command = err.downcast_ref::<CommandError>().expect("a CommandError");
+        assert_eq!(command.command, "git rev-parse --verify HEAD");
+        assert!(command.output.contains("fatal"), "{}", command.output);
+        assert!(err.to_string().contains("fatal"));
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\n\nb\nc\n", 2), "b\nc");
+        assert_eq!(tail_lines("", 5), "");
+    }
+
+    #[test]
+    fn test_sparse_clone_checks_out_only_patterns() {
+        let (repo, _bare) = make_git_repo_with_remote();
+        commit_files_via_local_git(
+            repo.path(),
+            &[
+                ("Cargo.toml", b"[workspace]"),
+                ("crates/a/Cargo.toml", b"[package]"),
+                ("crates/a/src/lib.rs", b"// big source"),
+            ],
+            "add files",
+            &PushTarget::default(),
+        )
+        .unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let dest = dir.path().join("agents/evo-king");
+        let url = format!("file://{}", repo.path().display());
+        clone_checkout(
+            &url,
+            &dest,
+            Some(&["/Cargo.toml".to_string(), "Cargo.toml".to_string()]),
+            &PushTarget::default(),
+        )
+        .unwrap();
+        assert!(dest.join("Cargo.toml").is_file());
+        assert!(dest.join("crates/a/Cargo.toml").is_file());
+        assert!(!dest.join("crates/a/src/lib.rs").exists());
+        assert!(!dest.join("README.md").exists());
+
+        let missing = dir.path().join("missing");
+        assert!(
+            clone_checkout(
+                "file:///nonexistent/repo",
+                &missing,
+                None,
+                &PushTarget::default()
+            )
+            .is_err()
+        );
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    fn test_push_target_defaults() {
+        assert_eq!(PushTarget::default().args(), vec!["push"]);
+        assert!(!PushTarget::default().is_custom());
+        let remote_only = PushTarget {
+            remote: Some("fork".to_string()),
+            ..PushTarget::default()
+        };
+        assert_eq!(remote_only.args(), vec!["push", "fork", "HEAD"]);
+        let refspec_only = PushTarget {
+            refspec: Some("HEAD:refs/heads/x".to_string()),
+            ..PushTarget::default()
+        };
+        assert_eq!(
+            refspec_only.args(),
+            vec!["push", "origin", "HEAD:refs/heads/x"]
+        );
+    }
+
+    #[test]
+    fn test_deploy_key_file_is_private_and_removed() {
+        let credentials = Credentials::from_key("-----BEGIN KEY-----\nabc\n").unwrap();
+        let Credentials::DeployKey(ref key) = credentials else {
+            panic!("expected a deploy key");
+        };
+        let path = key.to_path_buf();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "-----BEGIN KEY-----\nabc\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(
+                fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+                0o600
+            );
+        }
+        let env = credentials.env();
+        assert_eq!(env[0].0, "GIT_SSH_COMMAND");
+        assert!(env[0].1.contains(&path.display().to_string()));
+        drop(credentials);
+        assert!(!path.exists());
+
+        let missing = PushTarget {
+            deploy_key_env: Some("EVO_TEST_DEPLOY_KEY_THAT_IS_NOT_SET".to_string()),
+            ..PushTarget::default()
+        };
+        assert!(missing.credentials().is_err());
+    }
+
+    #[test]
+    fn test_askpass_answers_github_prompts_only() {
+        let credentials = Credentials::from_token("ghp_secret").unwrap();
+        let env = credentials.env();
+        let askpass = |prompt: &str| {
+            let output = Command::new(&env[0].1)
+                .arg(prompt)
+                .envs(env.iter().map(|(k, v)| (k, v)))
+                .output()
+                .unwrap();
+            (
+                output.status.success(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            )
+        };
+        assert_eq!(
+            askpass("Username for 'https://github.com': "),
+            (true, "x-access-token\n".to_string())
+        );
+        assert_eq!(
+            askpass("Password for 'https://x-access-token@github.com': "),
+            (true, "ghp_secret\n".to_string())
+        );
+        assert!(!askpass("Password for 'https://gitlab.com': ").0);
+        for lookalike in [
+            "Username for 'https://github.com.evil.example': ",
+            "Password for 'https://x-access-token@github.com.evil.example': ",
+            "Password for 'https://evil.example/github.com': ",
+            "Password for 'https://x@github.com@evil.example': ",
+        ] {
+            assert_eq!(askpass(lookalike), (false, String::new()), "{lookalike}");
+        }
+        assert!(env.contains(&("GIT_CONFIG_KEY_0", "credential.helper".to_string())));
+    }
+
+    #[test]
+    fn test_revert_commits_restores_previous_content() {
+        let (repo, _bare) = make_git_repo_with_remote();
+        commit_via_local_git(repo.path(), "Cargo.toml", b"v = \"0.1\"", "init").unwrap();
+        let first =
+            commit_via_local_git(repo.path(), "Cargo.toml", b"v = \"0.2\"", "bump").unwrap();
+        let second = commit_via_local_git(repo.path(), "ci.yml", b"v: 0.2", "bump ci").unwrap();
+
+        revert_commits(repo.path(), &[&first, &second], &PushTarget::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(repo.path().join("Cargo.toml")).unwrap(),
+            "v = \"0.1\""
+        );
+        assert!(!repo.path().join("ci.yml").exists());
+    }
+
+    #[test]
+    fn test_local_tag_is_annotated_and_pushed() {
+        let (repo, bare) = make_git_repo_with_remote();
+        let sha = commit_via_local_git(repo.path(), "Cargo.toml", b"v = 1", "bump").unwrap();
+        create_tag_via_local_git(
+            repo.path(),
+            "sdk-0.3-rollout",
+            &sha,
+            "fleet state",
+            &PushTarget::default(),
+        )
+        .unwrap();
+        run_git(
+            bare.path(),
+            &["rev-parse", "--verify", "refs/tags/sdk-0.3-rollout^{tag}"],
+        )
+        .unwrap();
+
+        // A tag that already exists remotely fails and is not kept locally.
+        run_git(repo.path(), &["tag", "--delete", "sdk-0.3-rollout"]).unwrap();
+        assert!(
+            create_tag_via_local_git(
+                repo.path(),
+                "sdk-0.3-rollout",
+                "HEAD~1",
+                "again",
+                &PushTarget::default()
+            )
+            .is_err()
+        );
+        assert!(
+            run_git(
+                repo.path(),
+                &["rev-parse", "--verify", "refs/tags/sdk-0.3-rollout"]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_resolve_inside_rejects_traversal_and_absolute() {
+        let dir = TempDir::new().unwrap();
+        assert!(matches!(
+            resolve_inside(dir.path(), "../../etc/passwd"),
+            Err(PathSecurityError::ParentTraversal { .. })
+        ));
+        assert!(matches!(
+            resolve_inside(dir.path(), "/etc/passwd"),
+            Err(PathSecurityError::Absolute { .. })
+        ));
+        let ok = resolve_inside(dir.path(), "new/dir/Cargo.toml").unwrap();
+        assert!(ok.ends_with("new/dir/Cargo.toml"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_inside_rejects_symlink_escape() {
+        let outside = TempDir::new().unwrap();
+        let dir = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink("/nonexistent/target", dir.path().join("dangling")).unwrap();
+
+        assert!(matches!(
+            resolve_inside(dir.path(), "linked/Cargo.toml"),
+            Err(PathSecurityError::EscapesBase { .. })
+        ));
+        assert!(matches!(
+            resolve_inside(dir.path(), "dangling"),
+            Err(PathSecurityError::EscapesBase { .. })
+        ));
+
+        // The structured error survives the anyhow wrapping in the commit path.
+        let err = commit_via_local_git(dir.path(), "linked/x", b"x", "m").unwrap_err();
+        assert!(err.downcast_ref::<PathSecurityError>().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_local_git_commit_preserves_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (repo, _bare) = make_git_repo_with_remote();
+        let script = repo.path().join("build.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        commit_via_local_git(repo.path(), "build.sh", b"#!/bin/sh\necho hi\n", "update").unwrap();
+        let mode = fs::metadata(&script).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
 }
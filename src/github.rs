@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+use crate::tokens::{TokenPool, is_rate_limited};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Default GitHub REST API root; overridable via `GITHUB_API_URL` (GHES).
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// `User-Agent` sent with every GitHub request (required by the API).
+const USER_AGENT: &str = "evo-kernel-agent-update (github.com/ai-evo-agents)";
+
+// ─── API response types ───────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct ContentsGet {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentsFile {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentsPut {
+    commit: CommitRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitRef {
+    sha: String,
+}
+
+// ─── Client ───────────────────────────────────────────────────────────────────
+
+/// Minimal GitHub REST client used when the `gh` CLI is not installed.
+///
+/// Only the contents API is implemented — enough to read, create or update
+/// a single file remotely without a local checkout.  Requests draw their token
+/// from the shared [`TokenPool`] and rotate on rate limits.
+#[derive(Debug, Clone)]
+pub struct GithubRest {
+    client: reqwest::Client,
+    api_url: String,
+    pool: &'static TokenPool,
+}
+
+impl GithubRest {
+    /// Builds a client from the global token pool (`GITHUB_TOKENS` /
+    /// `GITHUB_TOKEN`) and `GITHUB_API_URL` (optional).  Returns `None` when
+    /// no token is configured.
+    pub fn from_env() -> Option<Self> {
+        let pool = TokenPool::global();
+        if pool.is_empty() {
+            return None;
+        }
+        let api_url = std::env::var("GITHUB_API_URL")
+            .unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let client = match crate::net::http_client() {
+            Ok(c) => c,
+            Err(e) => {
+                warn!(error = %e, "cannot build GitHub HTTP client — REST fallback disabled");
+                return None;
+            }
+        };
+        Some(Self {
+            client,
+            api_url,
+            pool,
+        })
+    }
+
+    /// Creates or updates `file_path` in `repo` (e.g. `"org/name"`) through
+    /// `PUT /repos/{repo}/contents/{file_path}` and returns the commit SHA.
+    ///
+    /// The current blob SHA is fetched first; a 404 means the file is new.
+    pub async fn commit_file(
+        &self,
+        repo: &str,
+        file_path: &str,
+        content: &[u8],
+        message: &str,
+        branch: Option<&str>,
+    ) -> Result<String> {
+        let url = contents_url(&self.api_url, repo, file_path);
+
+        // ── Fetch current blob SHA ──
+        let resp = self
+            .send(|| {
+                let get = self.client.get(&url);
+                match branch {
+                    Some(b) => get.query(&[("ref", b)]),
+                    None => get,
+                }
+            })
+            .await
+            .with_context(|| format!("GET {url}"))?;
+        let blob_sha = match resp.status() {
+            s if s.is_success() => Some(
+                resp.json::<ContentsGet>()
+                    .await
+                    .context("parse contents GET response")?
+                    .sha,
+            ),
+            reqwest::StatusCode::NOT_FOUND => None,
+            s => anyhow::bail!("GitHub contents GET returned {s} for {repo}/{file_path}"),
+        };
+        debug!(file = file_path, blob_sha = ?blob_sha, "fetched current blob SHA via REST");
+
+        // ── PUT updated content ──
+        let body = put_body(content, message, blob_sha.as_deref(), branch);
+        let resp = self
+            .send(|| self.client.put(&url).json(&body))
+            .await
+            .with_context(|| format!("PUT {url}"))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            anyhow::bail!("GitHub contents PUT returned {status}: {text}");
+        }
+
+        Ok(resp
+            .json::<ContentsPut>()
+            .await
+            .context("parse contents PUT response")?
+            .commit
+            .sha)
+    }
+
+    /// Content of `file_path` on the default branch of `repo`, or `None`
+    /// when the file does not exist.
+    pub async fn read_file(&self, repo: &str, file_path: &str) -> Result<Option<String>> {
+        let url = contents_url(&self.api_url, repo, file_path);
+        let resp = self
+            .send(|| self.client.get(&url))
+            .await
+            .with_context(|| format!("GET {url}"))?;
+        match resp.status() {
+            s if s.is_success() => {
+                let file = resp
+                    .json::<ContentsFile>()
+                    .await
+                    .context("parse contents GET response")?;
+                decode_content(&file.content)
+                    .with_context(|| format!("decode {file_path} of {repo}"))
+                    .map(Some)
+            }
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            s => anyhow::bail!("GitHub contents GET returned {s} for {repo}/{file_path}"),
+        }
+    }
+
+    /// Sends the request produced by `build` with the pool's current token,
+    /// recording quota headers and retrying with the next token when GitHub
+    /// answers with a rate limit.
+    async fn send(&self, build: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        for _ in 0..self.pool.len() {
+            let (idx, token) = self.pool.current().context("no GitHub token configured")?;
+            let resp = build()
+                .bearer_auth(&token)
+                .header("Accept", "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28")
+                .header("User-Agent", USER_AGENT)
+                .send()
+                .await?;
+            self.pool.record(idx, resp.headers());
+
+            let status = resp.status();
+            if status == reqwest::StatusCode::FORBIDDEN
+                || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            {
+                let headers = resp.headers().clone();
+                let text = resp.text().await.unwrap_or_default();
+                if is_rate_limited(status, &headers, &text) && self.pool.rotate_from(idx) {
+                    continue;
+                }
+                anyhow::bail!("GitHub returned {status}: {text}");
+            }
+            return Ok(resp);
+        }
+        anyhow::bail!("every configured GitHub token is rate limited")
+    }
+}
+
+/// The text of a contents API `content` field: base64, wrapped over lines.
+pub fn decode_content(encoded: &str) -> Result<String> {
+    let compact: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = BASE64.decode(compact).context("invalid base64 content")?;
+    String::from_utf8(bytes).context("content is not UTF-8")
+}
+
+/// `{api_url}/repos/{repo}/contents/{file_path}`
+fn contents_url(api_url: &str, repo: &str, file_path: &str) -> String {
+    format!("{api_url}/repos/{repo}/contents/{file_path}")
+}
+
+/// JSON body for the contents PUT; `sha` is omitted when creating a file.
+fn put_body(content: &[u8], message: &str, sha: Option<&str>, branch: Option<&str>) -> Value {
+    let mut body = json!({
+        "message": message,
+        "content": BASE64.encode(content),
+    });
+    if let Some(sha) = sha {
+        body["sha"] = json!(sha);
+    }
+    if let Some(branch) = branch {
+        body["branch"] = json!(branch);
+    }
+    body
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contents_url() {
+        assert_eq!(
+            contents_url(
+                "https://api.github.com",
+                "org/repo",
+                ".github/workflows/ci.yml"
+            ),
+            "https://api.github.com/repos/org/repo/contents/.github/workflows/ci.yml"
+        );
+    }
+
+    #[test]
+    fn test_put_body_update() {
+        let body = put_body(b"hi", "msg", Some("abc"), Some("feature"));
+        assert_eq!(body["message"], "msg");
+        assert_eq!(body["content"], "aGk=");
+        assert_eq!(body["sha"], "abc");
+        assert_eq!(body["branch"], "feature");
+    }
+
+    #[test]
+    fn test_decode_content() {
+        assert_eq!(
+            decode_content("W2RlcGVuZGVuY2llc10K\nZXZvLWNvbW1vbiA9ICIwLjMi\n").unwrap(),
+            "[dependencies]\nevo-common = \"0.3\""
+        );
+        assert!(decode_content("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_put_body_create_omits_sha() {
+        let body = put_body(b"hi", "msg", None, None);
+        assert!(body.get("sha").is_none());
+        assert!(body.get("branch").is_none());
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::ops::Range;
+
+use crate::fixtures::RegistryHttp;
+use crate::versions::{is_prerelease, needs_update};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Default Go module proxy.
+const DEFAULT_PROXY: &str = "https://proxy.golang.org";
+
+/// File name of a Go module's manifest.
+pub const GO_MOD: &str = "go.mod";
+
+/// Checksums `go mod tidy` keeps next to a `go.mod`.
+pub const GO_SUM: &str = "go.sum";
+
+// ─── Module proxy ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct LatestInfo {
+    #[serde(rename = "Version")]
+    version: String,
+}
+
+/// The first HTTP(S) proxy of `GOPROXY` (a comma- or pipe-separated list
+/// that may also hold `direct` and `off`), default [`DEFAULT_PROXY`].
+pub fn proxy_url() -> String {
+    std::env::var("GOPROXY")
+        .ok()
+        .and_then(|list| {
+            list.split([',', '|'])
+                .map(str::trim)
+                .find(|p| p.starts_with("https://") || p.starts_with("http://"))
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| DEFAULT_PROXY.to_string())
+}
+
+/// A module path in the proxy protocol's case encoding: every uppercase
+/// letter becomes `!` and its lowercase (`github.com/BurntSushi/toml` →
+/// `github.com/!burnt!sushi/toml`).
+pub fn escape_path(module: &str) -> String {
+    let mut out = String::with_capacity(module.len());
+    for c in module.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('!');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Latest stable tagged version of `module` on the proxy at `proxy`,
+/// without its `v` prefix.
+pub async fn latest_version(client: &RegistryHttp, proxy: &str, module: &str) -> Result<String> {
+    let root = format!("{}/{}", proxy.trim_end_matches('/'), escape_path(module));
+    let list_url = format!("{root}/@v/list");
+    let list = client.get(&list_url).await?.ensure_success(&list_url)?;
+    if let Some(latest) = resolve_latest(list.text().context("read module version list")?) {
+        return Ok(latest);
+    }
+    // Modules without tags only have `@latest` (usually a pseudo-version).
+    let latest_url = format!("{root}/@latest");
+    let resp = client.get(&latest_url).await?.ensure_success(&latest_url)?;
+    let info: LatestInfo = serde_json::from_slice(&resp.body)
+        .with_context(|| format!("parse module proxy response for {module}"))?;
+    stable(&info.version)
+        .map(str::to_string)
+        .with_context(|| format!("{module} has no stable release on the module proxy"))
+}
+
+/// The highest stable version of a `@v/list` response.
+fn resolve_latest(list: &str) -> Option<String> {
+    list.lines()
+        .filter_map(|line| stable(line.trim()))
+        .fold(None, |best: Option<&str>, v| match best {
+            Some(b) if !needs_update(b, v) => Some(b),
+            _ => Some(v),
+        })
+        .map(str::to_string)
+}
+
+/// `version` without its `v` when it is a stable release; pre-releases,
+/// pseudo-versions and `+incompatible` versions yield `None`.
+fn stable(version: &str) -> Option<&str> {
+    let bare = version.strip_prefix('v')?;
+    (!bare.is_empty() && !is_prerelease(bare) && !bare.contains('+')).then_some(bare)
+}
+
+// ─── go.mod ───────────────────────────────────────────────────────────────────
+
+/// Whether `path` is a Go module manifest.
+pub fn is_go_mod(path: &str) -> bool {
+    path == GO_MOD || path.ends_with(&format!("/{GO_MOD}"))
+}
+
+/// The `go.sum` next to the `go.mod` at `go_mod`.
+pub fn go_sum_of(go_mod: &str) -> String {
+    match go_mod.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/{GO_SUM}"),
+        None => GO_SUM.to_string(),
+    }
+}
+
+/// One directive line of a go.mod: its verb, its arguments and where the
+/// line's code (comments excluded) starts.
+struct Directive<'a> {
+    verb: &'a str,
+    args: Vec<&'a str>,
+    code: &'a str,
+    offset: usize,
+}
+
+/// The directives of `content`, with `require ( … )`-style blocks
+/// flattened into one directive per entry.
+fn directives(content: &str) -> Vec<Directive<'_>> {
+    let mut found = Vec::new();
+    let mut block: Option<&str> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let code = line.split("//").next().unwrap_or_default();
+        let tokens: Vec<&str> = code.split_whitespace().collect();
+        let Some(&first) = tokens.first() else {
+            continue;
+        };
+        match block {
+            Some(_) if first == ")" => block = None,
+            Some(verb) => found.push(Directive {
+                verb,
+                args: tokens,
+                code,
+                offset: start,
+            }),
+            None if tokens.get(1) == Some(&"(") => block = Some(first),
+            None => found.push(Directive {
+                verb: first,
+                args: tokens[1..].to_vec(),
+                code,
+                offset: start,
+            }),
+        }
+    }
+    found
+}
+
+/// Byte ranges of the versions `content` requires `module` at, `v` included.
+fn required_versions(content: &str, module: &str) -> Vec<Range<usize>> {
+    directives(content)
+        .into_iter()
+        .filter(|d| d.verb == "require" && d.args.len() >= 2)
+        .filter(|d| d.args[0].trim_matches('"') == module)
+        .filter_map(|d| {
+            let at = d.code.rfind(d.args[1])?;
+            Some(d.offset + at..d.offset + at + d.args[1].len())
+        })
+        .collect()
+}
+
+/// Whether a `replace` directive redirects `module` (its required version
+/// is then not what builds use).
+fn is_replaced(content: &str, module: &str) -> bool {
+    directives(content)
+        .iter()
+        .any(|d| d.verb == "replace" && d.args.first().map(|m| m.trim_matches('"')) == Some(module))
+}
+
+/// The version (without `v`) `content` requires `module` at; `None` when
+/// the module is not required or is replaced.
+pub fn current_version(content: &str, module: &str) -> Option<String> {
+    if is_replaced(content, module) {
+        return None;
+    }
+    let range = required_versions(content, module).into_iter().next()?;
+    content[range].strip_prefix('v').map(str::to_string)
+}
+
+/// Moves every `require` of `module` older than `latest` (no `v`) to
+/// `v{latest}`, leaving comments such as `// indirect` and the rest of the
+/// file alone.  Replaced modules are not touched.
+pub fn patch_require(content: &str, module: &str, latest: &str) -> String {
+    if is_replaced(content, module) {
+        return content.to_string();
+    }
+    let mut patched = content.to_string();
+    for range in required_versions(content, module).into_iter().rev() {
+        let current = content[range.clone()].trim_start_matches('v');
+        if needs_update(current, latest) {
+            patched.replace_range(range, &format!("v{latest}"));
+        }
+    }
+    patched
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GO_MOD_FILE: &str = "module github.com/ai-evo-agents/evo-go-agent
+
+go 1.22
+
+require github.com/ai-evo-agents/evo-agent-sdk-go v0.3.1
+
+require (
+\tgithub.com/BurntSushi/toml v1.3.2 // indirect
+\tgolang.org/x/sync v0.6.0
+)
+";
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(
+            escape_path("github.com/BurntSushi/toml"),
+            "github.com/!burnt!sushi/toml"
+        );
+        assert_eq!(
+            resolve_latest("v0.9.0\nv0.10.1\nv1.0.0-rc.1\nv2.0.0+incompatible\n").as_deref(),
+            Some("0.10.1")
+        );
+        assert_eq!(resolve_latest(""), None);
+        assert_eq!(stable("v0.0.0-20240101000000-abcdefabcdef"), None);
+        assert_eq!(go_sum_of("go.mod"), "go.sum");
+        assert_eq!(go_sum_of("tools/go.mod"), "tools/go.sum");
+        assert!(is_go_mod("tools/go.mod"));
+        assert!(!is_go_mod("tools/notgo.mod"));
+    }
+
+    #[test]
+    fn test_patch_require() {
+        assert_eq!(
+            current_version(GO_MOD_FILE, "github.com/ai-evo-agents/evo-agent-sdk-go").as_deref(),
+            Some("0.3.1")
+        );
+        assert_eq!(
+            current_version(GO_MOD_FILE, "github.com/BurntSushi/toml").as_deref(),
+            Some("1.3.2")
+        );
+        assert_eq!(current_version(GO_MOD_FILE, "golang.org/x/net"), None);
+
+        let patched = patch_require(GO_MOD_FILE, "github.com/BurntSushi/toml", "1.4.0");
+        assert_eq!(
+            patched,
+            GO_MOD_FILE.replace("toml v1.3.2 // indirect", "toml v1.4.0 // indirect")
+        );
+        let patched = patch_require(
+            GO_MOD_FILE,
+            "github.com/ai-evo-agents/evo-agent-sdk-go",
+            "0.5.0",
+        );
+        assert!(patched.contains("require github.com/ai-evo-agents/evo-agent-sdk-go v0.5.0\n"));
+        assert_eq!(
+            patch_require(GO_MOD_FILE, "golang.org/x/sync", "0.5.0"),
+            GO_MOD_FILE
+        );
+    }
+
+    #[test]
+    fn test_replaced_modules_are_left_alone() {
+        let content = format!(
+            "{GO_MOD_FILE}\nreplace github.com/ai-evo-agents/evo-agent-sdk-go => ../evo-agent-sdk-go\n"
+        );
+        let module = "github.com/ai-evo-agents/evo-agent-sdk-go";
+        assert_eq!(current_version(&content, module), None);
+        assert_eq!(patch_require(&content, module, "0.5.0"), content);
+    }
+}
@@ -0,0 +1,1600 @@
+use async_trait::async_trait;
+use evo_agent_sdk::prelude::*;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+use crate::approval::{Answer, ApprovalStore, Decision, Question};
+use crate::audit_log::{AuditEntry, AuditLog};
+use crate::backend::{Backends, RegistryClient};
+use crate::changelog::MigrationNote;
+use crate::checkpoint::Checkpoint;
+use crate::checksum::{ChecksumPins, sha256_hex};
+use crate::config::{
+    CommitGranularity, ConfigSnapshot, ConfigStore, PolicyConfig, RepoCommitStrategy, RepoConfig,
+    UpdateConfig,
+};
+use crate::fleet_tag::FleetTagConfig;
+use crate::git::CommitStrategy;
+use crate::license::LicensePolicy;
+use crate::llm::{Llm, LlmConfig};
+use crate::osv::OsvFinding;
+use crate::ownership::OwnershipState;
+use crate::plan::{PlanStore, PlannedChange, SigningKey, UpdatePlan};
+use crate::policy::AutoApply;
+use crate::preflight::PreflightEnv;
+use crate::release::{ReleaseBump, ReleaseConfig};
+use crate::repo_config::RepoLocalConfig;
+use crate::retry_queue::{QueuedCommit, RetryQueue};
+use crate::rollout::RolloutConfig;
+use crate::run_lock::RunLock;
+use crate::runs::RunHistory;
+use crate::sandbox::SandboxConfig;
+use crate::schemas::SchemaConfig;
+use crate::state::{VersionCache, now_secs, state_dir, today_utc};
+use crate::summary::HoldReason;
+use crate::template::TemplateConfig;
+use crate::timing::Timings;
+use crate::updater::PatchBackend;
+use crate::upstream::UpstreamChanges;
+use crate::versions::{BumpKind, VersionReport};
+use crate::workflow_sync::WorkflowSyncConfig;
+use crate::{
+    checkout, diff, freeze, git, gomod, net, outdated, package_json, plan, policy, preflight,
+    pyproject, registry, runs, summary, workspace,
+};
+
+mod analysis;
+mod delivery;
+mod lookup;
+mod report;
+mod scan;
+
+use self::analysis::Verification;
+use self::lookup::Versions;
+use self::scan::Scan;
+
+// ─── Crates we track on crates.io ────────────────────────────────────────────
+
+/// Crates whose versions are checked on crates.io and propagated to all repos.
+const TRACKED_CRATES: &[&str] = &["evo-common", "evo-agent-sdk"];
+
+/// Python packages whose versions are checked on PyPI and propagated to
+/// every repo's `python_files`.
+const TRACKED_PYTHON_PACKAGES: &[&str] = &["evo-agent-sdk-py"];
+
+/// npm packages whose versions are checked on the npm registry and
+/// propagated to every repo's `npm_files`.
+const TRACKED_NPM_PACKAGES: &[&str] = &["@evo/agent-client"];
+
+/// Go modules whose versions are checked on the module proxy and propagated
+/// to every repo's `go_files` (none built in).
+const TRACKED_GO_MODULES: &[&str] = &[];
+
+/// Tracked crates developed in a repo not named like the crate.
+const CRATE_REPOS: &[(&str, &str)] = &[("evo-agent-sdk", "evo-agents")];
+
+// ─── Managed repo table ───────────────────────────────────────────────────────
+
+/// Configuration for a single managed repository.
+struct RepoSpec {
+    /// GitHub repo slug (without org prefix).
+    repo: &'static str,
+    /// Local folder name relative to the kernel-agents base dir.
+    local: &'static str,
+    /// Cargo.toml paths inside the repo that may contain tracked deps.
+    cargo_files: &'static [&'static str],
+    /// CI workflow files that contain `sed` version substitution patterns.
+    /// Every tracked crate referenced by such a pattern is kept current.
+    workflow_files: &'static [&'static str],
+    /// PEP 621 `pyproject.toml` files whose `[project]` dependencies on
+    /// tracked Python packages are kept current.
+    python_files: &'static [&'static str],
+    /// `package.json` files whose dependencies on tracked npm packages are
+    /// kept current.
+    npm_files: &'static [&'static str],
+    /// `go.mod` files whose requirements of tracked Go modules are kept
+    /// current (`go mod tidy` updates their `go.sum` in the sandbox).
+    go_files: &'static [&'static str],
+    /// Overrides the global Cargo.toml commit-message template for this repo.
+    cargo_commit_template: Option<&'static str>,
+    /// Overrides the global workflow commit-message template for this repo.
+    workflow_commit_template: Option<&'static str>,
+    /// Conventional-commit type forced onto this repo's commits (e.g. `"build"`).
+    commit_type: Option<&'static str>,
+    /// Conventional-commit scope forced onto this repo's commits (e.g. `"deps"`);
+    /// `Some("")` drops the scope entirely.
+    commit_scope: Option<&'static str>,
+    /// Crates tracked for this repo in addition to `TRACKED_CRATES`.
+    extra_crates: &'static [&'static str],
+    /// Entries of `TRACKED_CRATES` never updated in this repo.
+    excluded_crates: &'static [&'static str],
+    /// Excludes the repo from automated updates (still scanned and reported).
+    frozen: bool,
+    /// Last frozen day (`YYYY-MM-DD`); implies `frozen` until that date passes.
+    frozen_until: Option<&'static str>,
+    /// Crates never updated automatically in this repo (reported as skipped).
+    deny_crates: &'static [&'static str],
+    /// When set, only these crates are updated automatically in this repo.
+    allow_crates: Option<&'static [&'static str]>,
+    /// Forces how this repo's updates are delivered (see `RepoCommitStrategy`).
+    commit_strategy: Option<RepoCommitStrategy>,
+    /// How this repo's updates map to commits and PRs (run-wide default otherwise).
+    commit_granularity: Option<CommitGranularity>,
+    /// Remote (name or URL) the local git fallback pushes to instead of the
+    /// branch's upstream.
+    push_remote: Option<&'static str>,
+    /// Refspec the local git fallback pushes (e.g. `HEAD:refs/heads/evo-update`).
+    push_refspec: Option<&'static str>,
+    /// Environment variable holding an SSH deploy key for this repo's local
+    /// git pushes.
+    deploy_key_env: Option<&'static str>,
+    /// Clone a missing checkout blob-less with only the agent's files.
+    sparse_checkout: bool,
+    /// Sandbox verification commands replacing `[sandbox].verify` for this repo.
+    verify: Option<&'static [&'static str]>,
+    /// Manifest patcher for this repo (default `toml_edit`).
+    patcher: Option<PatchBackend>,
+    /// Bumps the repo's own version whenever its dependencies are updated.
+    release_bump: Option<ReleaseBump>,
+    /// Release workflow whose version references follow the bump and which is
+    /// dispatched once the bump is committed.
+    release_workflow: Option<&'static str>,
+    /// Managed repos updated (and gated) before this one; see `[rollout]`.
+    depends_on: &'static [&'static str],
+    /// Directory receiving the canonical schema files of `[schemas]`.
+    schema_dir: Option<&'static str>,
+    /// `[[file_sync]]` campaigns (or single files of them) not applied here.
+    file_sync_opt_out: &'static [&'static str],
+    /// Generated from `[template]`: its structure is checked for drift.
+    from_template: bool,
+    /// `[workflow_sync]` templates rendered into `.github/workflows/`.
+    workflow_templates: &'static [&'static str],
+    /// Values of the templates' repo-specific `{{evo.<key>}}` placeholders.
+    workflow_params: &'static [(&'static str, &'static str)],
+    /// Left out of the `[license]` campaign (e.g. a differently licensed repo).
+    license_exempt: bool,
+}
+
+impl RepoSpec {
+    /// Field defaults for `MANAGED_REPOS` entries (`..RepoSpec::DEFAULTS`).
+    const DEFAULTS: RepoSpec = RepoSpec {
+        repo: "",
+        local: "",
+        cargo_files: &[],
+        workflow_files: &[],
+        python_files: &[],
+        npm_files: &[],
+        go_files: &[],
+        cargo_commit_template: None,
+        workflow_commit_template: None,
+        commit_type: None,
+        commit_scope: None,
+        extra_crates: &[],
+        excluded_crates: &[],
+        frozen: false,
+        frozen_until: None,
+        deny_crates: &[],
+        allow_crates: None,
+        commit_strategy: None,
+        commit_granularity: None,
+        push_remote: None,
+        push_refspec: None,
+        deploy_key_env: None,
+        sparse_checkout: false,
+        verify: None,
+        patcher: None,
+        release_bump: None,
+        release_workflow: None,
+        depends_on: &[],
+        schema_dir: None,
+        file_sync_opt_out: &[],
+        from_template: false,
+        workflow_templates: &[],
+        workflow_params: &[],
+        license_exempt: false,
+    };
+}
+
+/// Built-in configuration: `TRACKED_CRATES` and `MANAGED_REPOS`, used when
+/// no config file is present (and for any section the file omits).
+pub fn builtin_config() -> UpdateConfig {
+    UpdateConfig {
+        tracked_crates: TRACKED_CRATES.iter().map(ToString::to_string).collect(),
+        tracked_python_packages: TRACKED_PYTHON_PACKAGES
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        tracked_npm_packages: TRACKED_NPM_PACKAGES
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        tracked_go_modules: TRACKED_GO_MODULES.iter().map(ToString::to_string).collect(),
+        policy: PolicyConfig::default(),
+        sandbox: SandboxConfig::default(),
+        llm: LlmConfig::default(),
+        fleet_tag: FleetTagConfig::default(),
+        release: ReleaseConfig::default(),
+        registries: registry::Registries::default(),
+        crate_repos: CRATE_REPOS
+            .iter()
+            .map(|(c, r)| (c.to_string(), r.to_string()))
+            .collect(),
+        rollout: RolloutConfig::default(),
+        schemas: SchemaConfig::default(),
+        file_sync: Vec::new(),
+        template: TemplateConfig::default(),
+        workflow_sync: WorkflowSyncConfig::default(),
+        license: LicensePolicy::default(),
+        repos: MANAGED_REPOS.iter().map(RepoConfig::from).collect(),
+    }
+}
+
+impl From<&RepoSpec> for RepoConfig {
+    fn from(spec: &RepoSpec) -> Self {
+        let strings = |list: &[&str]| list.iter().map(ToString::to_string).collect();
+        RepoConfig {
+            repo: spec.repo.to_string(),
+            local: spec.local.to_string(),
+            cargo_files: strings(spec.cargo_files),
+            workflow_files: strings(spec.workflow_files),
+            python_files: strings(spec.python_files),
+            npm_files: strings(spec.npm_files),
+            go_files: strings(spec.go_files),
+            cargo_commit_template: spec.cargo_commit_template.map(str::to_string),
+            workflow_commit_template: spec.workflow_commit_template.map(str::to_string),
+            commit_type: spec.commit_type.map(str::to_string),
+            commit_scope: spec.commit_scope.map(str::to_string),
+            extra_crates: strings(spec.extra_crates),
+            excluded_crates: strings(spec.excluded_crates),
+            frozen: spec.frozen,
+            frozen_until: spec.frozen_until.map(str::to_string),
+            deny_crates: strings(spec.deny_crates),
+            allow_crates: spec.allow_crates.map(strings),
+            commit_strategy: spec.commit_strategy,
+            commit_granularity: spec.commit_granularity,
+            push_remote: spec.push_remote.map(str::to_string),
+            push_refspec: spec.push_refspec.map(str::to_string),
+            deploy_key_env: spec.deploy_key_env.map(str::to_string),
+            sparse_checkout: spec.sparse_checkout,
+            verify: spec.verify.map(strings),
+            patcher: spec.patcher,
+            release_bump: spec.release_bump,
+            release_workflow: spec.release_workflow.map(str::to_string),
+            depends_on: strings(spec.depends_on),
+            schema_dir: spec.schema_dir.map(str::to_string),
+            file_sync_opt_out: strings(spec.file_sync_opt_out),
+            from_template: spec.from_template,
+            workflow_templates: strings(spec.workflow_templates),
+            workflow_params: spec
+                .workflow_params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            license_exempt: spec.license_exempt,
+        }
+    }
+}
+
+/// All repos managed by this agent.
+///
+/// To add a new repo, append a `RepoSpec` entry here.  No other changes are
+/// required.  Optional fields fall back to `RepoSpec::DEFAULTS`.
+const MANAGED_REPOS: &[RepoSpec] = &[
+    RepoSpec {
+        repo: "evo-king",
+        local: "evo-king",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[],
+        depends_on: &["evo-agents"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-agents",
+        local: "evo-agents",
+        cargo_files: &["evo-agent-sdk/Cargo.toml"],
+        workflow_files: &[],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-kernel-agent-learning",
+        local: "evo-kernel-agent-learning",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        depends_on: &["evo-king"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-kernel-agent-building",
+        local: "evo-kernel-agent-building",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        depends_on: &["evo-king"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-kernel-agent-pre-load",
+        local: "evo-kernel-agent-pre-load",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        depends_on: &["evo-king"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-kernel-agent-evaluation",
+        local: "evo-kernel-agent-evaluation",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        depends_on: &["evo-king"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-kernel-agent-skill-manage",
+        local: "evo-kernel-agent-skill-manage",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        depends_on: &["evo-king"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-kernel-agent-update",
+        local: "evo-kernel-agent-update",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        depends_on: &["evo-king"],
+        ..RepoSpec::DEFAULTS
+    },
+    RepoSpec {
+        repo: "evo-user-agent-template",
+        local: "evo-user-agent-template",
+        cargo_files: &["Cargo.toml"],
+        workflow_files: &[".github/workflows/ci.yml", ".github/workflows/release.yml"],
+        // Template users pull evo-common transitively through the SDK.
+        excluded_crates: &["evo-common"],
+        // Users fork the template; changes land through review only.
+        commit_strategy: Some(RepoCommitStrategy::PrOnly),
+        depends_on: &["evo-agents"],
+        ..RepoSpec::DEFAULTS
+    },
+];
+
+/// This agent's own repo.  Its manifest bumps carry a regenerated
+/// `Cargo.lock` (the release build uses `--locked`), and it is updated last so
+/// the redeploy its commit sets off cannot cut a wave short.
+const SELF_REPO: &str = env!("CARGO_PKG_NAME");
+
+// ─── Internal tracking types ──────────────────────────────────────────────────
+
+/// A single pending file update, discovered in Phase 2.
+#[derive(Debug)]
+struct PendingUpdate {
+    repo: String,
+    local_base: PathBuf,
+    file_path: String,
+    original_content: String,
+    patched_content: String,
+    commit_message: String,
+    /// Tracked-crate bumps applied to this file.
+    versions: Vec<VersionReport>,
+    /// BREAKING / Migration passages from the bumped crates' changelogs.
+    migration_notes: Vec<MigrationNote>,
+    /// What changed upstream in the bumped crates, from their commit logs.
+    upstream: Vec<UpstreamChanges>,
+    /// OSV vulnerabilities the bumps fix or leave in place.
+    vulnerabilities: Vec<OsvFinding>,
+    /// Source rewritten by the LLM migration loop: only ever delivered
+    /// through a PR, never committed directly.
+    requires_pr: bool,
+    /// sha256 of `patched_content` as verified in the sandbox.
+    verified_sha256: Option<String>,
+    /// File whose commit this update joins: a regenerated lockfile, a
+    /// tidied go.sum or a release-version edit lands with its manifest.
+    part_of: Option<String>,
+}
+
+impl PendingUpdate {
+    /// An update of `file_path` from `original_content` to
+    /// `patched_content`, with no bump details, delivered like any other.
+    fn new(
+        repo: String,
+        local_base: PathBuf,
+        file_path: String,
+        original_content: String,
+        patched_content: String,
+        commit_message: String,
+    ) -> Self {
+        Self {
+            repo,
+            local_base,
+            file_path,
+            original_content,
+            patched_content,
+            commit_message,
+            versions: Vec::new(),
+            migration_notes: Vec::new(),
+            upstream: Vec::new(),
+            vulnerabilities: Vec::new(),
+            requires_pr: false,
+            verified_sha256: None,
+            part_of: None,
+        }
+    }
+}
+
+/// A non-Cargo manifest kind whose dependencies on tracked packages are
+/// kept current, with the registry those packages are resolved from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PackageManifest {
+    /// PEP 621 `pyproject.toml` (`python_files`), packages from PyPI.
+    Pyproject,
+    /// `package.json` (`npm_files`), packages from the npm registry.
+    PackageJson,
+    /// `go.mod` (`go_files`), modules from the Go module proxy.
+    GoMod,
+}
+
+impl PackageManifest {
+    const ALL: [PackageManifest; 3] = [
+        PackageManifest::Pyproject,
+        PackageManifest::PackageJson,
+        PackageManifest::GoMod,
+    ];
+
+    /// Ecosystem name for logs.
+    fn ecosystem(self) -> &'static str {
+        match self {
+            PackageManifest::Pyproject => "python",
+            PackageManifest::PackageJson => "npm",
+            PackageManifest::GoMod => "go",
+        }
+    }
+
+    /// `version_freshness` source of a freshly resolved version.
+    fn source(self) -> &'static str {
+        match self {
+            PackageManifest::Pyproject => "pypi.org",
+            PackageManifest::PackageJson => "registry.npmjs.org",
+            PackageManifest::GoMod => "proxy.golang.org",
+        }
+    }
+
+    /// Packages kept current in manifests of this kind.
+    fn packages(self, config: &UpdateConfig) -> &[String] {
+        match self {
+            PackageManifest::Pyproject => &config.tracked_python_packages,
+            PackageManifest::PackageJson => &config.tracked_npm_packages,
+            PackageManifest::GoMod => &config.tracked_go_modules,
+        }
+    }
+
+    /// The manifests of this kind `spec` lists.
+    fn files(self, spec: &RepoConfig) -> &[String] {
+        match self {
+            PackageManifest::Pyproject => &spec.python_files,
+            PackageManifest::PackageJson => &spec.npm_files,
+            PackageManifest::GoMod => &spec.go_files,
+        }
+    }
+
+    /// The repo-local list replacing [`PackageManifest::files`], if any.
+    fn local_files(self, local: &RepoLocalConfig) -> Option<&[String]> {
+        match self {
+            PackageManifest::Pyproject => local.python_files.as_deref(),
+            PackageManifest::PackageJson => local.npm_files.as_deref(),
+            PackageManifest::GoMod => local.go_files.as_deref(),
+        }
+    }
+
+    async fn latest_version(
+        self,
+        registry: &dyn RegistryClient,
+        package: &str,
+    ) -> anyhow::Result<String> {
+        match self {
+            PackageManifest::Pyproject => registry.latest_pypi_version(package).await,
+            PackageManifest::PackageJson => registry.latest_npm_version(package).await,
+            PackageManifest::GoMod => registry.latest_go_version(package).await,
+        }
+    }
+
+    /// The version `content` currently requires `package` at.
+    fn current_version(self, content: &str, package: &str) -> Option<String> {
+        match self {
+            PackageManifest::Pyproject => pyproject::current_requirement(content, package)
+                .and_then(|requirement| pyproject::floor(&requirement)),
+            PackageManifest::PackageJson => package_json::current_range(content, package)
+                .and_then(|range| package_json::range_version(&range)),
+            PackageManifest::GoMod => gomod::current_version(content, package),
+        }
+    }
+
+    /// `content` with its requirements on `package` moved to `latest`.
+    fn patch(self, content: &str, package: &str, latest: &str) -> anyhow::Result<String> {
+        match self {
+            PackageManifest::Pyproject => pyproject::patch_dependency(content, package, latest),
+            PackageManifest::PackageJson => {
+                package_json::patch_dependency(content, package, latest)
+            }
+            PackageManifest::GoMod => Ok(gomod::patch_require(content, package, latest)),
+        }
+    }
+}
+
+/// How pending updates are delivered to each repo in Phase 4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CommitMode {
+    /// Commit each file directly to the default branch (the original behaviour).
+    Direct,
+    /// Bundle all of a repo's updates onto one branch and open a single PR.
+    PullRequest,
+}
+
+impl CommitMode {
+    /// Reads `metadata.commit_mode` (`"direct"` | `"pr"`), defaulting to `Direct`.
+    fn from_metadata(metadata: &Value) -> Self {
+        match metadata.get("commit_mode").and_then(Value::as_str) {
+            Some("pr") => CommitMode::PullRequest,
+            _ => CommitMode::Direct,
+        }
+    }
+}
+
+/// Reads `metadata.commit_granularity` (`"file"` | `"repo"` |
+/// `"single-run-branch"`), the default for repos without their own setting.
+fn granularity_from_metadata(metadata: &Value) -> CommitGranularity {
+    match metadata.get("commit_granularity") {
+        None => CommitGranularity::default(),
+        Some(value) => serde_json::from_value(value.clone()).unwrap_or_else(|_| {
+            warn!(value = %value, "unknown commit_granularity — committing per file");
+            CommitGranularity::default()
+        }),
+    }
+}
+
+/// The configuration limited to the repos in `metadata.repos`, for runs
+/// targeted at the repos a release affects; `None` when the run is not
+/// targeted.
+fn target_repos(config: &UpdateConfig, metadata: &Value) -> Option<UpdateConfig> {
+    let names: Vec<&str> = metadata
+        .get("repos")?
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    for name in &names {
+        if !config.repos.iter().any(|spec| spec.repo == *name) {
+            warn!(repo = name, "targeted repo is not managed — ignoring it");
+        }
+    }
+    info!(repos = ?names, "run targeted at selected repos");
+    Some(UpdateConfig {
+        repos: config
+            .repos
+            .iter()
+            .filter(|spec| names.contains(&spec.repo.as_str()))
+            .cloned()
+            .collect(),
+        ..config.clone()
+    })
+}
+
+/// Why Phase 3 is skipped: `"requested"` by `metadata.skip_llm: true`, or
+/// `"patch_only"` when every bump is patch-level and `skip_llm` is not
+/// `false`.
+fn llm_skip_reason(metadata: &Value, updates: &[PendingUpdate]) -> Option<&'static str> {
+    match metadata.get("skip_llm").and_then(Value::as_bool) {
+        Some(true) => Some("requested"),
+        Some(false) => None,
+        None => {
+            let mut bumps = updates.iter().flat_map(|u| &u.versions).peekable();
+            let patch_only =
+                bumps.peek().is_some() && bumps.all(|r| r.bump_kind == BumpKind::Patch);
+            patch_only.then_some("patch_only")
+        }
+    }
+}
+
+/// Whether `metadata.refresh_versions` asks to bypass recently cached
+/// versions and query the registries again.
+fn refresh_versions(metadata: &Value) -> bool {
+    metadata.get("refresh_versions").and_then(Value::as_bool) == Some(true)
+}
+
+/// Checksum pins from `CHECKSUM_PINS_FILE` (default
+/// `state_dir/pinned-checksums.json`); none when unreadable.
+fn load_checksum_pins() -> ChecksumPins {
+    let pins_path = std::env::var("CHECKSUM_PINS_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| state_dir().join("pinned-checksums.json"));
+    ChecksumPins::load(&pins_path).unwrap_or_else(|e| {
+        warn!(error = %e, "checksum pins unreadable — verifying against the index only");
+        ChecksumPins::default()
+    })
+}
+
+/// How one repo's updates are delivered in Phase 4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Delivery {
+    /// One grouped PR for the repo.
+    PullRequest,
+    /// Per-file commits, trying only these strategies.
+    Direct(&'static [CommitStrategy]),
+}
+
+impl Delivery {
+    /// Combines the run's commit mode with a repo's `commit_strategy` and
+    /// commit granularity; `single-run-branch` always delivers through a PR.
+    ///
+    /// Returns the reason when the repo cannot be updated at all this run
+    /// (e.g. a PR-only repo during an offline run).
+    fn resolve(
+        mode: CommitMode,
+        offline: bool,
+        strategy: Option<RepoCommitStrategy>,
+        granularity: CommitGranularity,
+    ) -> Result<Self, &'static str> {
+        if granularity == CommitGranularity::SingleRunBranch {
+            return match strategy {
+                Some(RepoCommitStrategy::LocalGit) => {
+                    Err("repo is local_git but single-run-branch delivery needs a PR")
+                }
+                _ if offline => {
+                    Err("single-run-branch delivery needs the GitHub API, unavailable offline")
+                }
+                _ => Ok(Delivery::PullRequest),
+            };
+        }
+        match (strategy, mode) {
+            (Some(RepoCommitStrategy::PrOnly), _) if offline => {
+                Err("repo is pr_only and PRs need the GitHub API, unavailable offline")
+            }
+            (Some(RepoCommitStrategy::GhCli), _) if offline => {
+                Err("repo is gh_cli only and offline runs commit through local git")
+            }
+            (Some(RepoCommitStrategy::PrOnly), _) => Ok(Delivery::PullRequest),
+            (Some(RepoCommitStrategy::LocalGit), _) => {
+                Ok(Delivery::Direct(&[CommitStrategy::LocalGit]))
+            }
+            (_, CommitMode::PullRequest) => Ok(Delivery::PullRequest),
+            (Some(RepoCommitStrategy::GhCli), CommitMode::Direct) => {
+                Ok(Delivery::Direct(&[CommitStrategy::GhCli]))
+            }
+            (None, CommitMode::Direct) if offline => {
+                Ok(Delivery::Direct(&[CommitStrategy::LocalGit]))
+            }
+            (None, CommitMode::Direct) => Ok(Delivery::Direct(CommitStrategy::ALL)),
+        }
+    }
+
+    /// Short label for the summary (`"PullRequest"` / `"Direct"`).
+    fn label(&self) -> &'static str {
+        match self {
+            Delivery::PullRequest => "PullRequest",
+            Delivery::Direct(_) => "Direct",
+        }
+    }
+}
+
+// ─── Runs ─────────────────────────────────────────────────────────────────────
+
+/// One patching run as its phases see it: the event, the configuration in
+/// effect, the backends and what the run's action and metadata asked for.
+struct Run<'a> {
+    ctx: &'a PipelineContext<'a>,
+    snapshot: &'a ConfigSnapshot,
+    config: &'a UpdateConfig,
+    /// Whether `metadata.repos` limited the run to some repos.
+    targeted: bool,
+    backends: &'a Backends,
+    llm: Llm<'a>,
+    http: reqwest::Client,
+    org: String,
+    base_dir: PathBuf,
+    dry_run: bool,
+    offline: bool,
+    /// A `plan` run: the changes are stored, not delivered.
+    planning: bool,
+    commit_mode: CommitMode,
+    granularity: CommitGranularity,
+    plans: PlanStore,
+    /// The verified plan an `apply` or `answer` run delivers.
+    applying: Option<UpdatePlan>,
+    answer: Option<Answer>,
+    /// Checkpoint of a run that stopped during Phase 4.
+    unfinished: Option<Checkpoint>,
+    /// Id of the run a `resume` run continues.
+    resumed_from: Option<String>,
+    checkpoint_path: PathBuf,
+    retry_queue: RetryQueue,
+}
+
+/// What an update passes before it is delivered: repo freezes, the crate
+/// policy and the auto-apply policy.  Fresh updates meet them in Phase 2,
+/// queued commits before they are retried.
+struct Gates {
+    freeze_list: freeze::FreezeList,
+    today: String,
+    crate_policy: policy::CratePolicy,
+    auto_apply: AutoApply,
+}
+
+impl Gates {
+    /// The gates of `config`; crates in `held` are held for review.
+    fn new(config: &UpdateConfig, held: Vec<String>) -> Self {
+        Self {
+            freeze_list: freeze::FreezeList::from_env(),
+            today: today_utc(),
+            crate_policy: policy::CratePolicy::from_env_or(&config.policy).with_held(held),
+            auto_apply: AutoApply::from_env_or(&config.policy),
+        }
+    }
+
+    /// Why `item` is not retried for `spec` this run, as fields of its
+    /// `retried` entry, and whether it stays queued.
+    fn check(&self, spec: &RepoConfig, item: &QueuedCommit) -> Option<(Value, bool)> {
+        if let Some(freeze) = freeze::freeze_status(
+            &spec.repo,
+            spec.frozen,
+            spec.frozen_until.as_deref(),
+            &self.freeze_list,
+            &self.today,
+        ) {
+            return Some((
+                json!({ "outcome": "held", "reason": "frozen", "until": freeze.until }),
+                true,
+            ));
+        }
+        for crate_name in &item.crates {
+            if let Some(block) =
+                self.crate_policy
+                    .check(crate_name, &spec.deny_crates, spec.allow_crates.as_deref())
+            {
+                return Some((
+                    json!({ "outcome": "skipped_by_policy", "crate": crate_name, "policy": block }),
+                    false,
+                ));
+            }
+        }
+        if self.auto_apply == AutoApply::SecurityOnly && !item.security_fix {
+            return Some((
+                json!({ "outcome": "held", "reason": HoldReason::SecurityOnly }),
+                true,
+            ));
+        }
+        None
+    }
+}
+
+/// The changes of the verified `plan`, as the updates to deliver.  Fails
+/// when a file changed on the repo's default branch (in the checkout when
+/// offline) since the plan was made.
+async fn planned_updates(run: &Run<'_>, plan: &UpdatePlan) -> anyhow::Result<Vec<PendingUpdate>> {
+    let Run {
+        config,
+        offline,
+        ref org,
+        ref base_dir,
+        ..
+    } = *run;
+    let vcs = &run.backends.vcs;
+    let drifted = plan::drift(plan, |repo, file| async move {
+        if offline {
+            let spec = config
+                .repos
+                .iter()
+                .find(|spec| spec.repo == repo)
+                .ok_or_else(|| anyhow::anyhow!("{repo} is not a managed repo"))?;
+            std::fs::read_to_string(base_dir.join(&spec.local).join(file))
+                .map_err(|e| anyhow::anyhow!("read {file}: {e}"))
+        } else {
+            vcs.read_file(org, repo, file)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("{file} does not exist in {org}/{repo}"))
+        }
+    })
+    .await;
+    if !drifted.is_empty() {
+        let files: Vec<String> = drifted
+            .iter()
+            .map(|d| format!("{}/{} ({})", d.repo, d.file, d.reason))
+            .collect();
+        anyhow::bail!(
+            "plan {} is out of date, re-plan: {}",
+            plan.plan_id,
+            files.join(", ")
+        );
+    }
+    plan.changes
+        .iter()
+        .map(|change| {
+            let spec = config
+                .repos
+                .iter()
+                .find(|spec| spec.repo == change.repo)
+                .ok_or_else(|| anyhow::anyhow!("{} is not a managed repo", change.repo))?;
+            Ok(PendingUpdate {
+                versions: change.versions.clone(),
+                migration_notes: change.migration_notes.clone(),
+                requires_pr: change.requires_pr,
+                verified_sha256: change.verified_sha256.clone(),
+                part_of: change.part_of.clone(),
+                ..PendingUpdate::new(
+                    change.repo.clone(),
+                    base_dir.join(&spec.local),
+                    change.file.clone(),
+                    change.original.clone(),
+                    change.content.clone(),
+                    change.commit_message.clone(),
+                )
+            })
+        })
+        .collect()
+}
+
+// ─── UpdateHandler ────────────────────────────────────────────────────────────
+
+/// Handles the `pipeline:next` event for the `update` role.
+///
+/// Phases:
+/// 1. Check crates.io for latest stable versions of tracked crates.
+/// 2. Scan every managed repo's Cargo.toml and workflow files for stale deps.
+/// 3. Ask the LLM gateway for a brief changelog-risk analysis.
+/// 4. Apply all patches and commit (skipped in dry-run mode).
+/// 5. Notify king's `/admin/config-sync` endpoint.
+/// 6. Return a structured JSON summary.
+///
+/// The configuration is re-read from its file at the start of every run.
+pub struct UpdateHandler {
+    config: Arc<ConfigStore>,
+    backends: Backends,
+}
+
+impl UpdateHandler {
+    pub fn new(config: Arc<ConfigStore>, backends: Backends) -> Self {
+        Self { config, backends }
+    }
+}
+
+#[async_trait]
+impl AgentHandler for UpdateHandler {
+    async fn on_pipeline(&self, mut ctx: PipelineContext<'_>) -> anyhow::Result<Value> {
+        // ── `schema` action: the JSON Schema of the run summary ──
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("schema") {
+            return Ok(json!(summary::schema()));
+        }
+
+        // ── `resume` action: continue a run that stopped during Phase 4 ──
+        let checkpoint_path = Checkpoint::default_path();
+        let unfinished = Checkpoint::load(&checkpoint_path).unwrap_or_else(|e| {
+            warn!(error = %e, "unreadable checkpoint — ignoring it");
+            None
+        });
+        let resumed_from = if ctx.metadata.get("action").and_then(Value::as_str) == Some("resume") {
+            let Some(checkpoint) = &unfinished else {
+                anyhow::bail!("no unfinished run to resume");
+            };
+            let remaining = checkpoint.remaining_repos();
+            info!(run = %checkpoint.run_id, repos = ?remaining, "resuming run");
+            if remaining.is_empty() {
+                if let Err(e) = checkpoint.clone().finish() {
+                    warn!(error = %e, "failed to remove the checkpoint");
+                }
+                return Ok(json!({
+                    "run_id": ctx.run_id,
+                    "action": "resume",
+                    "resumed_from": checkpoint.run_id,
+                    "remaining_repos": remaining,
+                }));
+            }
+            // The stopped run again, restricted to the repos it did not finish.
+            let mut metadata = checkpoint.metadata.clone();
+            metadata["repos"] = json!(remaining);
+            ctx.metadata = metadata;
+            Some(checkpoint.run_id.clone())
+        } else {
+            None
+        };
+
+        // ── `plan` / `apply` actions: store a run's changes, deliver them later ──
+        // `answer` applies a plan held for approval, as the reviewer decided.
+        let plans = PlanStore::default_location();
+        let action = ctx.metadata.get("action").and_then(Value::as_str);
+        let planning = action == Some("plan");
+        let answer = match action {
+            Some("answer") => Some(Answer::from_metadata(&ctx.metadata)?),
+            _ => None,
+        };
+        let applying = if let Some(plan_id) = match (action, &answer) {
+            (_, Some(answer)) => Some(answer.question_id.as_str()),
+            (Some("apply"), _) => Some(
+                ctx.metadata
+                    .get("plan_id")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("apply needs metadata.plan_id"))?,
+            ),
+            _ => None,
+        } {
+            let mut plan = plans.load(plan_id)?;
+            plan.verify(
+                SigningKey::from_env().as_ref(),
+                plan::unsigned_allowed_from_env(),
+            )?;
+            if plan.signature.is_none() {
+                warn!(plan = %plan.plan_id, "ALLOW_UNSIGNED_PLANS set — applying an unsigned plan");
+            }
+            if let Some(answer) = &answer {
+                let mut approvals = ApprovalStore::load(&ApprovalStore::default_path())?;
+                anyhow::ensure!(
+                    approvals.is_open(&answer.question_id),
+                    "question {} is not waiting for an answer",
+                    answer.question_id
+                );
+                info!(question = %answer.question_id, decision = ?answer.decision, repos = ?answer.repos, "reviewer answered");
+                // An approval closes the question only once its plan is
+                // delivered (see `close_answered`); a rejection delivers nothing.
+                if answer.decision == Decision::Reject {
+                    approvals.answer(&answer.question_id, answer.decision);
+                    approvals.save()?;
+                    return Ok(json!({
+                        "run_id": ctx.run_id,
+                        "action": "answer",
+                        "question_id": answer.question_id,
+                        "decision": answer.decision,
+                        "repos": Question::for_plan(&plan, None).repos,
+                    }));
+                }
+                plan = answer.select(plan)?;
+            }
+            info!(plan = %plan.plan_id, changes = plan.changes.len(), "applying stored plan");
+            // The planning run again, this time delivering.
+            ctx.metadata = plan.metadata.clone();
+            Some(plan)
+        } else {
+            None
+        };
+
+        let dry_run = planning
+            || ctx
+                .metadata
+                .get("dry_run")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+        if dry_run {
+            info!("running in DRY-RUN mode — no files will be committed");
+        }
+        let offline =
+            std::env::var("OFFLINE").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        let mut commit_mode = CommitMode::from_metadata(&ctx.metadata);
+        if offline {
+            info!("running in OFFLINE mode — cached versions, local git commits only");
+            if commit_mode == CommitMode::PullRequest {
+                warn!("PR mode needs the GitHub API — falling back to direct commits offline");
+                commit_mode = CommitMode::Direct;
+            }
+        }
+        let run_granularity = granularity_from_metadata(&ctx.metadata);
+
+        let org = std::env::var("GITHUB_ORG").unwrap_or_else(|_| "ai-evo-agents".to_string());
+        let Backends {
+            registry: registry_client,
+            vcs,
+            king,
+        } = &self.backends;
+        let base_dir: PathBuf = std::env::var("KERNEL_AGENTS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(".."));
+        let config_snapshot = self.config.current();
+        let targeted = target_repos(&config_snapshot.config, &ctx.metadata);
+        let config = targeted.as_ref().unwrap_or(&*config_snapshot.config);
+        info!(version = %config_snapshot.version, repos = config.repos.len(), "configuration in effect");
+
+        // ── `diff_runs` action: compare the fleet matrix of two runs ──
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("diff_runs") {
+            return diff_runs(&ctx);
+        }
+
+        // ── `validate` action: report preflight findings, change nothing ──
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("validate") {
+            let env = PreflightEnv::from_env();
+            let findings = preflight::run(&config_snapshot, &env, &net::http_client()?).await;
+            let error_count = preflight::log_findings(&findings);
+            info!(
+                findings = findings.len(),
+                errors = error_count,
+                "validation complete"
+            );
+            return Ok(json!({
+                "run_id": ctx.run_id,
+                "action": "validate",
+                "ok": error_count == 0,
+                "config_version": config_snapshot.version,
+                "findings": findings,
+            }));
+        }
+
+        // ── `report` action: fleet-wide outdated matrix, change nothing ──
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("report") {
+            return outdated_report(
+                &ctx,
+                &config_snapshot,
+                &**registry_client,
+                &base_dir,
+                offline,
+            )
+            .await;
+        }
+
+        // ── Run lock: one patching run at a time ────────────────────────────
+        let lock_path = RunLock::path_from_env();
+        let Some(_run_lock) =
+            RunLock::acquire(&lock_path, &ctx.run_id, RunLock::wait_from_env()).await?
+        else {
+            warn!(lock = %lock_path.display(), "another run holds the run lock — skipping");
+            return Ok(json!({
+                "run_id": ctx.run_id,
+                "skipped": "concurrent_run_in_progress",
+                "lock_path": lock_path.display().to_string(),
+            }));
+        };
+
+        // ── `revert` action: undo one repo's commits from an earlier run ──
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("revert") {
+            return revert_repo_run(&ctx, config, &base_dir);
+        }
+
+        // ── `retry` action: only retry the queued commits of earlier runs ──
+        let retry_queue = RetryQueue::default_location();
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("retry") {
+            return delivery::retry_only(
+                &ctx,
+                &config_snapshot.config,
+                config,
+                &**vcs,
+                king,
+                &org,
+                &base_dir,
+                offline,
+                &retry_queue,
+            )
+            .await;
+        }
+
+        // ── `acknowledge_owners` action: accept ownership changes ──
+        if ctx.metadata.get("action").and_then(Value::as_str) == Some("acknowledge_owners") {
+            return acknowledge_owners(&ctx);
+        }
+
+        // ── Phase 0: Environment preflight ──────────────────────────────────
+        let mut timings = Timings::start();
+        let llm = Llm::new(
+            ctx.gateway,
+            LlmConfig::resolve(&config.llm, &ctx.soul.behavior, &ctx.metadata),
+        );
+        let http = net::http_client()?;
+        info!("Phase 0: checking the environment");
+        let environment = preflight::check_environment(&PreflightEnv::from_env(), &http).await;
+        preflight::log_findings(&environment.findings);
+        if environment.is_fatal() {
+            let diagnosis = environment.diagnosis();
+            error!(%diagnosis, "Phase 0: environment unusable — aborting run");
+            return Ok(json!({
+                "run_id": ctx.run_id,
+                "aborted": "environment_preflight_failed",
+                "diagnosis": diagnosis,
+                "environment": environment,
+            }));
+        }
+        timings.end_phase("environment");
+
+        // ── Missing checkouts are cloned (sparse where configured) ──
+        let checkouts: Vec<checkout::Bootstrap> = if offline {
+            Vec::new()
+        } else {
+            let shared: Vec<String> = config
+                .file_sync
+                .iter()
+                .flat_map(|campaign| campaign.files.iter().cloned())
+                .chain(
+                    config
+                        .license
+                        .expression
+                        .iter()
+                        .flat_map(|_| config.license.files.iter().cloned()),
+                )
+                .collect();
+            config
+                .repos
+                .iter()
+                .filter_map(|spec| checkout::bootstrap(&org, spec, &shared, &base_dir))
+                .collect()
+        };
+        timings.end_phase("checkouts");
+
+        let run = Run {
+            ctx: &ctx,
+            snapshot: &config_snapshot,
+            config,
+            targeted: targeted.is_some(),
+            backends: &self.backends,
+            llm,
+            http,
+            org,
+            base_dir,
+            dry_run,
+            offline,
+            planning,
+            commit_mode,
+            granularity: run_granularity,
+            plans,
+            applying,
+            answer,
+            unfinished,
+            resumed_from,
+            checkpoint_path,
+            retry_queue,
+        };
+
+        // A plan was scanned and verified when it was made: applying it
+        // delivers exactly its changes once their files are found unchanged.
+        let (versions, gates, mut scan) = match &run.applying {
+            Some(plan) => {
+                let pending_updates = planned_updates(&run, plan).await?;
+                let version_reports = pending_updates
+                    .iter()
+                    .flat_map(|u| u.versions.iter().cloned())
+                    .collect();
+                let scan = Scan {
+                    pending_updates,
+                    version_reports,
+                    ..Scan::default()
+                };
+                (
+                    Versions::planned(plan),
+                    Gates::new(config, Vec::new()),
+                    scan,
+                )
+            }
+            None => {
+                let versions = lookup::check_versions(&run, &mut timings).await;
+                let gates = Gates::new(
+                    config,
+                    versions
+                        .ownership_changes
+                        .iter()
+                        .map(|c| c.crate_name.clone())
+                        .collect(),
+                );
+                let scan = scan::scan(&run, &versions, &gates, &mut timings).await;
+                (versions, gates, scan)
+            }
+        };
+        let analysis = analysis::analyze(&run, &versions, &mut scan, &mut timings).await;
+        let verification = match run.applying {
+            Some(_) => Verification::default(),
+            None => analysis::verify(&run, &mut scan, &mut timings).await,
+        };
+        let delivered =
+            delivery::deliver(&run, &versions, &gates, &mut scan, &analysis, &mut timings).await?;
+        if let (Some(answer), Some(plan)) = (&run.answer, &run.applying) {
+            close_answered(answer, plan, &delivered)?;
+        }
+        report::report(
+            &run,
+            versions,
+            &gates,
+            scan,
+            analysis,
+            verification,
+            delivered,
+            environment,
+            checkouts,
+            timings,
+        )
+        .await
+    }
+}
+
+/// Closes the question `answer` approved once every change of `plan` (the
+/// part the answer selected) was committed.  When delivery fell short — the
+/// lease was refused, a commit failed, a hold stopped the rollout — the
+/// question stays open so the reviewer can answer it again.
+fn close_answered(
+    answer: &Answer,
+    plan: &UpdatePlan,
+    delivered: &delivery::Delivered,
+) -> anyhow::Result<()> {
+    let undelivered: Vec<String> = plan
+        .changes
+        .iter()
+        .filter(|change| {
+            !delivered.committed.iter().any(|c| {
+                c.repo == change.repo
+                    && !c.dry_run
+                    && (c.file.as_deref() == Some(change.file.as_str())
+                        || c.files.contains(&change.file))
+            })
+        })
+        .map(|change| format!("{}/{}", change.repo, change.file))
+        .collect();
+    if !undelivered.is_empty() {
+        warn!(question = %answer.question_id, undelivered = ?undelivered, "answered plan not fully delivered — question stays open");
+        return Ok(());
+    }
+    let mut approvals = ApprovalStore::load(&ApprovalStore::default_path())?;
+    approvals.answer(&answer.question_id, answer.decision);
+    approvals.save()
+}
+
+/// Groups pending updates by repo, preserving the configured repo order
+/// except that [`SELF_REPO`] always comes last.
+fn group_by_repo(updates: &[PendingUpdate]) -> Vec<(&str, Vec<&PendingUpdate>)> {
+    let mut groups: Vec<(&str, Vec<&PendingUpdate>)> = Vec::new();
+    for update in updates {
+        match groups.iter_mut().find(|(repo, _)| *repo == update.repo) {
+            Some((_, group)) => group.push(update),
+            None => groups.push((&update.repo, vec![update])),
+        }
+    }
+    groups.sort_by_key(|(repo, _)| *repo == SELF_REPO);
+    groups
+}
+
+/// An update plan `plan_id` of `updates`, to be applied with `metadata`
+/// (less `action` and `dry_run`), signed when `PLAN_SIGNING_KEY` is set.
+fn build_plan<'a>(
+    plan_id: &str,
+    metadata: &Value,
+    config_version: &str,
+    latest_versions: &HashMap<&str, String>,
+    updates: impl Iterator<Item = &'a PendingUpdate>,
+) -> anyhow::Result<UpdatePlan> {
+    let mut metadata = metadata.clone();
+    if let Some(fields) = metadata.as_object_mut() {
+        fields.remove("action");
+        fields.remove("dry_run");
+    }
+    let mut plan = UpdatePlan {
+        plan_id: plan_id.to_string(),
+        created_at: now_secs(),
+        config_version: config_version.to_string(),
+        metadata,
+        versions: latest_versions
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.clone()))
+            .collect(),
+        changes: updates
+            .map(|u| PlannedChange {
+                repo: u.repo.clone(),
+                file: u.file_path.clone(),
+                base_sha256: sha256_hex(u.original_content.as_bytes()),
+                sha256: sha256_hex(u.patched_content.as_bytes()),
+                diff: diff::unified_diff(&u.file_path, &u.original_content, &u.patched_content),
+                original: u.original_content.clone(),
+                content: u.patched_content.clone(),
+                commit_message: u.commit_message.clone(),
+                versions: u.versions.clone(),
+                migration_notes: u.migration_notes.clone(),
+                requires_pr: u.requires_pr,
+                verified_sha256: u.verified_sha256.clone(),
+                part_of: u.part_of.clone(),
+            })
+            .collect(),
+        signature: None,
+    };
+    match SigningKey::from_env() {
+        Some(key) => plan.sign(&key)?,
+        None => warn!(plan = %plan.plan_id, "PLAN_SIGNING_KEY not set — update plan is unsigned"),
+    }
+    Ok(plan)
+}
+
+/// Manifests the agent scans in a repo: the repo-local list when set,
+/// otherwise the configured files plus every member of the workspaces rooted
+/// at the repo's `Cargo.toml` or at any configured manifest.
+fn repo_cargo_files(
+    spec: &RepoConfig,
+    repo_base: &Path,
+    repo_local: &RepoLocalConfig,
+) -> Vec<String> {
+    if let Some(files) = &repo_local.cargo_files {
+        return files.clone();
+    }
+    let mut files: Vec<String> = spec.cargo_files.clone();
+    let mut roots: Vec<String> = vec!["Cargo.toml".to_string()];
+    roots.extend(spec.cargo_files.iter().cloned());
+    roots.dedup();
+    for root in &roots {
+        let Ok(content) = std::fs::read_to_string(repo_base.join(root)) else {
+            continue;
+        };
+        for member in workspace::member_manifests(repo_base, root, &content) {
+            if !files.contains(&member) {
+                files.push(member);
+            }
+        }
+    }
+    files
+}
+
+/// `cargo outdated` for the whole fleet: every crates.io dependency of every
+/// managed repo (not only the tracked crates) against its latest stable
+/// version.  Nothing is patched or committed.
+async fn outdated_report(
+    ctx: &PipelineContext<'_>,
+    snapshot: &ConfigSnapshot,
+    registry_client: &dyn RegistryClient,
+    base_dir: &Path,
+    offline: bool,
+) -> anyhow::Result<Value> {
+    let config = &*snapshot.config;
+    let mut matrix: BTreeMap<String, outdated::RepoRow> = BTreeMap::new();
+    for spec in &config.repos {
+        let repo_base = base_dir.join(&spec.local);
+        let repo_local = RepoLocalConfig::load(&repo_base).unwrap_or_else(|e| {
+            warn!(repo = %spec.repo, error = %e, "invalid repo-local update config — using central manifest list");
+            RepoLocalConfig::default()
+        });
+        let manifests: Vec<(String, String)> = repo_cargo_files(spec, &repo_base, &repo_local)
+            .into_iter()
+            .filter_map(|file| {
+                let content = std::fs::read_to_string(repo_base.join(&file)).ok()?;
+                Some((file, content))
+            })
+            .collect();
+        matrix.insert(spec.repo.clone(), outdated::scan_repo(&manifests));
+    }
+
+    let mut names: Vec<&str> = matrix
+        .values()
+        .flat_map(|row| row.keys().map(String::as_str))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    info!(
+        repos = matrix.len(),
+        crates = names.len(),
+        offline,
+        "resolving latest versions for outdated report"
+    );
+
+    let mut latest: BTreeMap<String, String> = BTreeMap::new();
+    let mut errors: Vec<Value> = Vec::new();
+    let mut cached: Vec<String> = Vec::new();
+    let mut cache = VersionCache::load(&VersionCache::default_path()).unwrap_or_else(|e| {
+        warn!(error = %e, "version cache unreadable — starting empty");
+        VersionCache::new(&VersionCache::default_path())
+    });
+    if offline {
+        for name in &names {
+            if let Some(cached) = cache.get(name) {
+                latest.insert(name.to_string(), cached.version.clone());
+            }
+        }
+    } else {
+        let now = now_secs();
+        let ttl = if refresh_versions(&ctx.metadata) {
+            0
+        } else {
+            VersionCache::ttl_from_env()
+        };
+        let tracked = config.all_tracked_crates();
+        let checksum_pins = load_checksum_pins();
+        for name in &names {
+            if let Some(entry) = cache.fresh(name, now, ttl) {
+                latest.insert(name.to_string(), entry.version.clone());
+                cached.push(name.to_string());
+                continue;
+            }
+            let mut last_error = None;
+            for (_, registry) in config.registries.for_crate(name) {
+                match registry_client.latest_version(&registry, name).await.result {
+                    Ok(version) => {
+                        // Tracked crates are cached like in Phase 1, so an
+                        // apply right after this report uses the same versions.
+                        if tracked.contains(name)
+                            && registry_client
+                                .verify_checksum(&registry, name, &version, &checksum_pins)
+                                .await
+                                .is_ok()
+                        {
+                            cache.insert(name, &version, now);
+                        }
+                        latest.insert(name.to_string(), version);
+                        last_error = None;
+                        break;
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            if let Some(e) = last_error {
+                debug!(crate = name, error = %e, "no latest version");
+                errors.push(json!({ "crate": name, "error": format!("{e:#}") }));
+            }
+        }
+        if let Err(e) = cache.save() {
+            warn!(error = %e, "failed to persist version cache");
+        }
+    }
+
+    let mut outdated_count = 0;
+    for row in matrix.values_mut() {
+        outdated::resolve(row, &latest);
+        outdated_count += row
+            .values()
+            .filter(|cell| cell.status == outdated::Status::Outdated)
+            .count();
+    }
+    info!(outdated = outdated_count, "outdated report complete");
+    Ok(json!({
+        "run_id": ctx.run_id,
+        "action": "report",
+        "offline": offline,
+        "config_version": snapshot.version,
+        "latest": latest,
+        "cached": cached,
+        "outdated": outdated_count,
+        "matrix": matrix,
+        "errors": errors,
+    }))
+}
+
+/// Compares the fleet version matrix of run `metadata.run_id` (default: the
+/// latest recorded run) with run `metadata.base_run_id` (default: the run
+/// recorded before it).
+fn diff_runs(ctx: &PipelineContext<'_>) -> anyhow::Result<Value> {
+    let field = |key: &str| ctx.metadata.get(key).and_then(Value::as_str);
+    let history = RunHistory::default_location().list();
+    let find = |id: &str| history.iter().position(|r| r.run_id == id);
+
+    let run_idx = match field("run_id") {
+        Some(id) => {
+            find(id).ok_or_else(|| anyhow::anyhow!("run {id} is not in the run history"))?
+        }
+        None => history
+            .len()
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::anyhow!("run history is empty"))?,
+    };
+    let base_idx = match field("base_run_id") {
+        Some(id) => {
+            find(id).ok_or_else(|| anyhow::anyhow!("run {id} is not in the run history"))?
+        }
+        None => run_idx
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::anyhow!("no run recorded before {}", history[run_idx].run_id))?,
+    };
+
+    let delta = runs::diff(&history[base_idx], &history[run_idx]);
+    info!(
+        base = %delta.base_run_id,
+        run = %delta.run_id,
+        moved = delta.moved.len(),
+        regressed = delta.regressed.len(),
+        pending = delta.pending.len(),
+        "run delta computed"
+    );
+    Ok(json!({
+        "run_id": ctx.run_id,
+        "action": "diff_runs",
+        "delta": delta,
+    }))
+}
+
+/// Accepts the pending ownership change of each crate in `metadata.crates`
+/// (or `metadata.crate`) as its new baseline, releasing the hold on its
+/// updates.
+fn acknowledge_owners(ctx: &PipelineContext<'_>) -> anyhow::Result<Value> {
+    let crates: Vec<String> = match ctx.metadata.get("crates") {
+        Some(list) => serde_json::from_value(list.clone())
+            .map_err(|e| anyhow::anyhow!("metadata.crates must be a list of names: {e}"))?,
+        None => vec![
+            ctx.metadata
+                .get("crate")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow::anyhow!("acknowledge_owners needs metadata.crate"))?
+                .to_string(),
+        ],
+    };
+    let mut state = OwnershipState::load(&OwnershipState::default_path())?;
+    let (acknowledged, unknown): (Vec<String>, Vec<String>) =
+        crates.into_iter().partition(|c| state.acknowledge(c));
+    state.save()?;
+    info!(?acknowledged, ?unknown, "ownership changes acknowledged");
+    Ok(json!({
+        "run_id": ctx.run_id,
+        "action": "acknowledge_owners",
+        "acknowledged": acknowledged,
+        "not_pending": unknown,
+    }))
+}
+
+/// Reverts the direct commits that run `metadata.run_id` made in
+/// `metadata.repo`, as recorded in the audit log, and logs the reverts.
+///
+/// Changes that went out as PRs cannot be reverted this way; their URLs are
+/// returned so the PRs can be closed or reverted on GitHub.
+fn revert_repo_run(
+    ctx: &PipelineContext<'_>,
+    config: &UpdateConfig,
+    base_dir: &Path,
+) -> anyhow::Result<Value> {
+    let field = |key: &str| ctx.metadata.get(key).and_then(Value::as_str);
+    let (Some(repo), Some(target_run)) = (field("repo"), field("run_id")) else {
+        anyhow::bail!("revert needs `repo` and `run_id` metadata");
+    };
+    let Some(spec) = config.repos.iter().find(|s| s.repo == repo) else {
+        anyhow::bail!("{repo} is not a managed repo");
+    };
+
+    let audit_log = AuditLog::from_env();
+    let entries: Vec<AuditEntry> = audit_log
+        .read()?
+        .into_iter()
+        .filter(|e| e.run_id == target_run && e.repo == repo && e.strategy != "Revert")
+        .collect();
+    anyhow::ensure!(
+        !entries.is_empty(),
+        "audit log has no changes to {repo} from run {target_run}"
+    );
+
+    let mut shas: Vec<&str> = Vec::new();
+    let mut pull_requests: Vec<&str> = Vec::new();
+    for entry in &entries {
+        for (value, list) in [(&entry.sha, &mut shas), (&entry.pr_url, &mut pull_requests)] {
+            if let Some(v) = value.as_deref()
+                && !list.contains(&v)
+            {
+                list.push(v);
+            }
+        }
+    }
+    info!(
+        repo,
+        run = target_run,
+        commits = shas.len(),
+        "reverting run"
+    );
+
+    let revert_sha = if shas.is_empty() {
+        None
+    } else {
+        let sha = git::revert_commits(&base_dir.join(&spec.local), &shas, &spec.push_target())?;
+        let reverts = revert_entries(&entries, &ctx.run_id, &sha);
+        if let Err(e) = audit_log.append(&reverts) {
+            error!(error = %e, "failed to write audit log");
+        }
+        Some(sha)
+    };
+
+    Ok(json!({
+        "run_id": ctx.run_id,
+        "action": "revert",
+        "repo": repo,
+        "target_run_id": target_run,
+        "reverted": shas,
+        "revert_sha": revert_sha,
+        "pull_requests": pull_requests,
+    }))
+}
+
+/// Audit-log entries recording run `run_id`'s revert commit `sha` of the
+/// direct commits among `entries`.
+fn revert_entries(entries: &[AuditEntry], run_id: &str, sha: &str) -> Vec<AuditEntry> {
+    let now = now_secs();
+    entries
+        .iter()
+        .filter(|e| e.sha.is_some())
+        .map(|e| AuditEntry {
+            timestamp: now,
+            run_id: run_id.to_string(),
+            from: e.to.clone(),
+            to: e.from.clone(),
+            sha: Some(sha.to_string()),
+            pr_url: None,
+            strategy: "Revert".to_string(),
+            ..e.clone()
+        })
+        .collect()
+}
@@ -0,0 +1,640 @@
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::changelog::MigrationNote;
+use crate::checksum::sha256_hex;
+use crate::llm::Purpose;
+use crate::migrate::{MigrateConfig, Outcome};
+use crate::risk::RiskAssessment;
+use crate::sandbox::Sandbox;
+use crate::sbom::NewDependency;
+use crate::summary::{HeldUpdate, HoldReason};
+use crate::timing::{Stage, Timings};
+use crate::upstream::UpstreamChanges;
+use crate::vet::VetReport;
+use crate::{changelog, gomod, metadata, migrate, osv, risk, sandbox, sbom, upstream, vet};
+
+use super::lookup::Versions;
+use super::scan::Scan;
+use super::{PendingUpdate, Run, group_by_repo, llm_skip_reason};
+
+// ─── Analysis: changelogs, upstream logs and Phase 3 ─────────────────────────
+
+/// What the run learned about its updates before delivering them.
+#[derive(Debug)]
+pub(super) struct Analysis {
+    pub migration_notes: Vec<MigrationNote>,
+    pub upstream_changes: Vec<UpstreamChanges>,
+    /// Why the LLM was not asked, if it was not.
+    pub llm_skipped: Option<&'static str>,
+    /// The LLM's risk analysis, or why there is none.
+    pub summary: String,
+    pub risk_assessment: Option<RiskAssessment>,
+}
+
+/// Annotates the scan's updates with the migration notes of their
+/// changelogs and the upstream commits of their bumps, then asks the LLM
+/// how risky the bumps are (Phase 3).  An applied plan's changes carry
+/// their notes and commit messages as planned, so only the risk is asked.
+pub(super) async fn analyze(
+    run: &Run<'_>,
+    versions: &Versions<'_>,
+    scan: &mut Scan<'_>,
+    timings: &mut Timings,
+) -> Analysis {
+    let migration_notes = if run.applying.is_some() {
+        let mut notes: Vec<MigrationNote> = Vec::new();
+        for note in scan.pending_updates.iter().flat_map(|u| &u.migration_notes) {
+            if !notes.contains(note) {
+                notes.push(note.clone());
+            }
+        }
+        notes
+    } else {
+        let notes = migration_notes(run, versions, &mut scan.pending_updates);
+        timings.end_phase("changelogs");
+        notes
+    };
+
+    let llm_skipped = llm_skip_reason(&run.ctx.metadata, &scan.pending_updates);
+    if let Some(reason) = llm_skipped {
+        info!(reason, "skipping the LLM analysis");
+    }
+    let upstream_changes = if run.applying.is_some() {
+        Vec::new()
+    } else {
+        let changes = upstream_logs(run, versions, &mut scan.pending_updates, llm_skipped).await;
+        timings.end_phase("upstream_logs");
+        changes
+    };
+
+    let (summary, risk_assessment) = assess_risk(run, scan, &upstream_changes, llm_skipped).await;
+    timings.end_phase("analysis");
+
+    Analysis {
+        migration_notes,
+        upstream_changes,
+        llm_skipped,
+        summary,
+        risk_assessment,
+    }
+}
+
+/// Migration notes from the bumped crates' changelogs, added to each
+/// update's commit message.  Returns every note once.
+fn migration_notes(
+    run: &Run<'_>,
+    versions: &Versions<'_>,
+    pending_updates: &mut [PendingUpdate],
+) -> Vec<MigrationNote> {
+    let Run {
+        offline,
+        ref org,
+        ref base_dir,
+        ..
+    } = *run;
+    let crate_links = &versions.crate_links;
+    let mut changelogs: HashMap<String, Option<String>> = HashMap::new();
+    let mut migration_notes: Vec<MigrationNote> = Vec::new();
+    for update in pending_updates.iter_mut() {
+        for report in &update.versions {
+            let changelog = changelogs
+                .entry(report.crate_name.clone())
+                .or_insert_with(|| {
+                    let repo = metadata::source_repo(
+                        crate_links.get(&report.crate_name),
+                        org,
+                        &report.crate_name,
+                    );
+                    changelog::fetch(base_dir, &repo, &report.crate_name, offline)
+                });
+            let Some(changelog) = changelog else {
+                continue;
+            };
+            for note in changelog::migration_notes(
+                changelog,
+                &report.crate_name,
+                &report.current,
+                &report.latest,
+            ) {
+                if !update.migration_notes.contains(&note) {
+                    update.migration_notes.push(note);
+                }
+            }
+        }
+        if !update.migration_notes.is_empty() {
+            update.commit_message = format!(
+                "{}\n\n{}",
+                update.commit_message,
+                changelog::render_commit_body(&update.migration_notes)
+            );
+            for note in &update.migration_notes {
+                if !migration_notes.contains(note) {
+                    migration_notes.push(note.clone());
+                }
+            }
+        }
+    }
+    migration_notes
+}
+
+/// Upstream commit logs of the bumped crates, summarized by the LLM unless
+/// it is skipped, and added to each update's commit message.
+async fn upstream_logs(
+    run: &Run<'_>,
+    versions: &Versions<'_>,
+    pending_updates: &mut [PendingUpdate],
+    llm_skipped: Option<&'static str>,
+) -> Vec<UpstreamChanges> {
+    let Run {
+        ctx,
+        offline,
+        ref org,
+        ref base_dir,
+        ref llm,
+        ..
+    } = *run;
+    let crate_links = &versions.crate_links;
+    let mut upstream_changes: Vec<UpstreamChanges> = Vec::new();
+    if upstream::enabled() {
+        let mut missing: Vec<(String, String, String)> = Vec::new();
+        for update in pending_updates.iter_mut() {
+            let lock = sbom::find_lockfile(&update.local_base, Path::new(&update.file_path))
+                .and_then(|lock| std::fs::read_to_string(lock).ok());
+            for report in &update.versions {
+                let from = lock
+                    .as_deref()
+                    .and_then(|lock| sbom::locked_version(lock, &report.crate_name))
+                    .unwrap_or_else(|| osv::requirement_floor(&report.current));
+                let key = (
+                    report.crate_name.clone(),
+                    from.clone(),
+                    report.latest.clone(),
+                );
+                let known = upstream_changes.iter().find(|c| {
+                    c.crate_name == report.crate_name && c.from == from && c.to == report.latest
+                });
+                let changes = match known {
+                    Some(changes) => changes.clone(),
+                    None if missing.contains(&key) => continue,
+                    None => {
+                        let repo = metadata::source_repo(
+                            crate_links.get(&report.crate_name),
+                            org,
+                            &report.crate_name,
+                        );
+                        let Some((range, commits)) = upstream::fetch(
+                            base_dir,
+                            &repo,
+                            &report.crate_name,
+                            &from,
+                            &report.latest,
+                            offline,
+                        ) else {
+                            missing.push(key);
+                            continue;
+                        };
+                        let changes = upstream::summarize(
+                            Some(llm).filter(|_| llm_skipped.is_none()),
+                            &ctx.soul.behavior,
+                            &report.crate_name,
+                            &from,
+                            &report.latest,
+                            range,
+                            &commits,
+                        )
+                        .await;
+                        upstream_changes.push(changes.clone());
+                        changes
+                    }
+                };
+                if !update.upstream.contains(&changes) {
+                    update.upstream.push(changes);
+                }
+            }
+            if !update.upstream.is_empty() {
+                update.commit_message = format!(
+                    "{}\n\n{}",
+                    update.commit_message,
+                    upstream::render_commit_body(&update.upstream)
+                );
+            }
+        }
+    }
+    upstream_changes
+}
+
+/// Phase 3: the LLM's changelog-risk analysis of the scan's bumps, and its
+/// structured assessment when it gave one.
+async fn assess_risk(
+    run: &Run<'_>,
+    scan: &Scan<'_>,
+    upstream_changes: &[UpstreamChanges],
+    llm_skipped: Option<&'static str>,
+) -> (String, Option<RiskAssessment>) {
+    let Run { ctx, ref llm, .. } = *run;
+    let Scan {
+        pending_updates,
+        version_reports,
+        ..
+    } = scan;
+    info!("Phase 3: LLM changelog risk analysis");
+    let (analysis_summary, risk_assessment) = if pending_updates.is_empty() {
+        (
+            "No dependency updates required — all repos are up to date.".to_string(),
+            None,
+        )
+    } else if let Some(reason) = llm_skipped {
+        (format!("LLM analysis skipped ({reason})."), None)
+    } else {
+        let update_list: Vec<String> = version_reports
+            .iter()
+            .map(|r| {
+                let line = format!(
+                    "{}: {} → {} ({})",
+                    r.crate_name, r.current, r.latest, r.bump_kind
+                );
+                match upstream_changes
+                    .iter()
+                    .find(|c| c.crate_name == r.crate_name && c.to == r.latest)
+                {
+                    Some(c) => format!(
+                        "{line}\n  What changed upstream ({} commits):\n  {}",
+                        c.commits,
+                        c.summary.replace('\n', "\n  ")
+                    ),
+                    None => line,
+                }
+            })
+            .collect();
+
+        let prompt = risk::prompt(llm.config().analysis_prompt.as_deref(), &update_list);
+        match llm
+            .complete(Purpose::RiskAnalysis, &ctx.soul.behavior, &prompt)
+            .await
+        {
+            Ok(response) => match RiskAssessment::parse(&response) {
+                Some(assessment) => (assessment.summary.clone(), Some(assessment)),
+                None => {
+                    warn!("LLM analysis is not structured — major-bump gate disabled this run");
+                    (response, None)
+                }
+            },
+            Err(e) => {
+                warn!(error = %e, "LLM analysis failed — continuing without it");
+                (format!("Analysis unavailable (gateway error: {e})"), None)
+            }
+        }
+    };
+
+    info!(analysis = %analysis_summary, "LLM analysis complete");
+    (analysis_summary, risk_assessment)
+}
+
+// ─── Sandbox: apply, migrate and verify in a clean clone ─────────────────────
+
+/// What verifying the updates in sandboxes found.
+#[derive(Debug, Default)]
+pub(super) struct Verification {
+    pub migrations: Vec<Value>,
+    pub sandboxes: Vec<Value>,
+    pub new_dependencies: Vec<NewDependency>,
+}
+
+/// Applies each repo's updates in a clean clone, migrates code the bumps
+/// break and runs the repo's verification commands; repos that fail are
+/// held.  The content Phase 4 commits is read back from the sandbox, so
+/// what was verified is exactly what gets pushed.
+pub(super) async fn verify(
+    run: &Run<'_>,
+    scan: &mut Scan<'_>,
+    timings: &mut Timings,
+) -> Verification {
+    let Run {
+        ctx,
+        config,
+        offline,
+        ref llm,
+        ..
+    } = *run;
+    let registry_client = &run.backends.registry;
+    let Scan {
+        pending_updates,
+        held_for_review,
+        ..
+    } = scan;
+    let mut migrations: Vec<Value> = Vec::new();
+    let mut sandboxes: Vec<Value> = Vec::new();
+    let mut new_dependencies: Vec<NewDependency> = Vec::new();
+    let sandbox_enabled = config.sandbox.is_enabled();
+    let migrate_config = MigrateConfig::from_env().filter(|_| !offline);
+    let repos: Vec<(String, PathBuf)> = group_by_repo(pending_updates)
+        .into_iter()
+        .filter(|(_, updates)| {
+            sandbox_enabled
+                || (migrate_config.is_some()
+                    && updates.iter().any(|u| u.file_path.ends_with("Cargo.toml")))
+        })
+        .map(|(repo, updates)| (repo.to_string(), updates[0].local_base.clone()))
+        .collect();
+    for (repo, local_base) in repos {
+        let is_repo = |u: &PendingUpdate| u.repo == repo;
+        let clone_started = Instant::now();
+        let prepared = Sandbox::clone_from(&local_base).and_then(|sandbox| {
+            for update in pending_updates.iter().filter(|u| is_repo(u)) {
+                sandbox.write(&update.file_path, &update.patched_content)?;
+            }
+            Ok(sandbox)
+        });
+        timings.repo(&repo, Stage::Verify, clone_started.elapsed());
+        let sandbox = match prepared {
+            Ok(sandbox) => sandbox,
+            Err(e) if sandbox_enabled => {
+                warn!(repo = %repo, error = %e, "sandbox could not be prepared — holding repo updates");
+                for update in pending_updates.extract_if(.., |u| is_repo(u)) {
+                    held_for_review.push(HeldUpdate::new(
+                        &repo,
+                        &update.file_path,
+                        HoldReason::SandboxVerificationFailed,
+                    ));
+                }
+                sandboxes
+                    .push(json!({ "repo": repo, "verified": false, "error": format!("{e:#}") }));
+                continue;
+            }
+            Err(e) => {
+                warn!(repo = %repo, error = %e, "verification build could not run — continuing without it");
+                migrations
+                    .push(json!({ "repo": repo, "status": "skipped", "error": format!("{e:#}") }));
+                continue;
+            }
+        };
+
+        let manifests: Vec<String> = pending_updates
+            .iter()
+            .filter(|u| is_repo(u) && u.file_path.ends_with("Cargo.toml"))
+            .map(|u| u.file_path.clone())
+            .collect();
+        if let Some(migrate_config) = &migrate_config
+            && !manifests.is_empty()
+        {
+            info!(repo = %repo, manifests = manifests.len(), "verifying patched manifests with cargo check");
+            let manifest_refs: Vec<&str> = manifests.iter().map(String::as_str).collect();
+            let migrate_started = Instant::now();
+            let outcome = migrate::verify_and_migrate(
+                llm,
+                &ctx.soul.behavior,
+                &sandbox,
+                &manifest_refs,
+                migrate_config,
+            )
+            .await;
+            timings.repo(&repo, Stage::Verify, migrate_started.elapsed());
+            match outcome {
+                Ok(Outcome::Passed) => {
+                    migrations.push(json!({ "repo": repo, "status": "passed", "attempts": 0 }));
+                }
+                Ok(Outcome::Healed { attempts, files }) => {
+                    info!(repo = %repo, attempts, files = files.len(), "LLM migration fixed the build — delivering as PR");
+                    migrations.push(json!({
+                        "repo": repo,
+                        "status": "healed",
+                        "attempts": attempts,
+                        "files": files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+                    }));
+                    for file in files {
+                        let message = format!(
+                            "fix: adapt {} to updated dependencies [run_id={}]",
+                            file.path, ctx.run_id
+                        );
+                        pending_updates.push(PendingUpdate {
+                            requires_pr: true,
+                            ..PendingUpdate::new(
+                                repo.clone(),
+                                local_base.clone(),
+                                file.path,
+                                file.original,
+                                file.content,
+                                message,
+                            )
+                        });
+                    }
+                }
+                Ok(Outcome::Failed { attempts, errors }) => {
+                    warn!(repo = %repo, attempts, "cargo check still fails — holding manifest updates for review");
+                    // With the sandbox on, nothing of the repo is verified.
+                    let held: Vec<String> = pending_updates
+                        .extract_if(.., |u| {
+                            is_repo(u) && (sandbox_enabled || u.file_path.ends_with("Cargo.toml"))
+                        })
+                        .map(|u| u.file_path)
+                        .collect();
+                    for file in &held {
+                        held_for_review.push(HeldUpdate::new(
+                            &repo,
+                            file,
+                            HoldReason::CargoCheckFailed,
+                        ));
+                    }
+                    migrations.push(json!({
+                        "repo": repo,
+                        "status": "failed",
+                        "attempts": attempts,
+                        "held_files": held,
+                        "errors": errors,
+                    }));
+                    continue;
+                }
+                Err(e) => {
+                    warn!(repo = %repo, error = %e, "verification build could not run — continuing without it");
+                    migrations.push(json!({
+                        "repo": repo,
+                        "status": "skipped",
+                        "error": format!("{e:#}"),
+                    }));
+                }
+            }
+        }
+        if !sandbox_enabled {
+            continue;
+        }
+
+        let commands = config
+            .repos
+            .iter()
+            .find(|spec| spec.repo == repo)
+            .and_then(|spec| spec.verify.as_ref())
+            .unwrap_or(&config.sandbox.verify);
+        let verify_started = Instant::now();
+        let command_error = |e: anyhow::Error| sandbox::CommandResult {
+            command: String::new(),
+            success: false,
+            exit_code: None,
+            output: format!("{e:#}"),
+        };
+        // `go mod tidy` settles the go.sum of every patched go.mod before
+        // the repo's own commands run.
+        let go_mods: Vec<(String, String)> = pending_updates
+            .iter()
+            .filter(|u| is_repo(u) && gomod::is_go_mod(&u.file_path))
+            .map(|u| (u.file_path.clone(), u.commit_message.clone()))
+            .collect();
+        let mut results: Vec<sandbox::CommandResult> = Vec::new();
+        for (go_mod, commit_message) in &go_mods {
+            let tidy = sandbox.go_mod_tidy(go_mod).unwrap_or_else(command_error);
+            let tidied = tidy.success;
+            results.push(tidy);
+            if !tidied {
+                break;
+            }
+            let go_sum = gomod::go_sum_of(go_mod);
+            let Ok(content) = sandbox.read(&go_sum) else {
+                continue;
+            };
+            let original = std::fs::read_to_string(local_base.join(&go_sum)).unwrap_or_default();
+            if content != original {
+                pending_updates.push(PendingUpdate {
+                    part_of: Some(go_mod.clone()),
+                    ..PendingUpdate::new(
+                        repo.clone(),
+                        local_base.clone(),
+                        go_sum,
+                        original,
+                        content,
+                        commit_message.clone(),
+                    )
+                });
+            }
+        }
+        if results.iter().all(|r| r.success) {
+            match sandbox.verify(commands) {
+                Ok(verified) => results.extend(verified),
+                Err(e) => results.push(command_error(e)),
+            }
+        }
+        timings.repo(&repo, Stage::Verify, verify_started.elapsed());
+        let verified = results.iter().all(|r| r.success);
+
+        // cargo-vet: crates the update brings in must be audited.
+        let mut vet_report: Option<VetReport> = None;
+        let mut vet_error: Option<String> = None;
+        if verified && vet::has_config(sandbox.root()) {
+            if vet::cargo_vet_available() {
+                let previous = std::fs::read_to_string(local_base.join("Cargo.lock")).ok();
+                let vet_started = Instant::now();
+                match vet::check(sandbox.root(), previous.as_deref()) {
+                    Ok(report) => vet_report = Some(report),
+                    Err(e) => {
+                        warn!(repo = %repo, error = %e, "cargo vet failed — continuing without it");
+                        vet_error = Some(format!("{e:#}"));
+                    }
+                }
+                timings.repo(&repo, Stage::Verify, vet_started.elapsed());
+            } else {
+                warn!(repo = %repo, "repo uses cargo-vet but it is not installed — skipping vetting");
+                vet_error = Some("cargo vet not installed".to_string());
+            }
+        }
+        let vetted = !vet_report.as_ref().is_some_and(VetReport::is_violation);
+
+        if verified && vetted {
+            // Hand the verified tree to Phase 4.
+            for update in pending_updates.iter_mut().filter(|u| u.repo == repo) {
+                match sandbox.read(&update.file_path) {
+                    Ok(content) => {
+                        update.verified_sha256 = Some(sha256_hex(content.as_bytes()));
+                        update.patched_content = content;
+                    }
+                    Err(e) => {
+                        warn!(repo = %repo, file = %update.file_path, error = %e, "cannot read verified file back from sandbox");
+                    }
+                }
+            }
+            info!(repo = %repo, commands = results.len(), "sandbox verification passed");
+
+            // Crates the resolved lockfiles pull in for the first time.
+            let lockfiles: BTreeSet<String> = manifests
+                .iter()
+                .filter_map(|m| sbom::find_lockfile(&local_base, &local_base.join(m)))
+                .filter_map(|p| {
+                    Some(
+                        p.strip_prefix(&local_base)
+                            .ok()?
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                })
+                .collect();
+            for lockfile in lockfiles {
+                let added = std::fs::read_to_string(local_base.join(&lockfile))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|before| sbom::new_packages(&before, &sandbox.read(&lockfile)?));
+                let added = match added {
+                    Ok(added) => added,
+                    Err(e) => {
+                        warn!(repo = %repo, file = %lockfile, error = %e, "cannot diff resolved lockfile");
+                        continue;
+                    }
+                };
+                for (crate_name, version) in added {
+                    let mut publisher = None;
+                    if !offline
+                        && let Some((_, registry)) =
+                            config.registries.for_crate(&crate_name).into_iter().next()
+                    {
+                        publisher = registry_client
+                            .publisher(&registry, &crate_name, &version)
+                            .await
+                            .unwrap_or_else(|e| {
+                                debug!(crate = %crate_name, error = %e, "publisher lookup failed");
+                                None
+                            });
+                    }
+                    warn!(repo = %repo, file = %lockfile, crate = %crate_name, version = %version, publisher = ?publisher, "new transitive dependency");
+                    new_dependencies.push(NewDependency {
+                        repo: repo.clone(),
+                        lockfile: lockfile.clone(),
+                        crate_name,
+                        version,
+                        publisher,
+                    });
+                }
+            }
+        } else if verified {
+            let unvetted = vet_report.as_ref().map(|r| &r.unvetted);
+            warn!(repo = %repo, unvetted = ?unvetted, "cargo vet rejected the update — holding repo updates");
+            for update in pending_updates.extract_if(.., |u| is_repo(u)) {
+                held_for_review.push(
+                    HeldUpdate::new(&repo, &update.file_path, HoldReason::UnvettedCrates)
+                        .with("unvetted", unvetted),
+                );
+            }
+        } else {
+            warn!(repo = %repo, "sandbox verification failed — holding repo updates");
+            for update in pending_updates.extract_if(.., |u| is_repo(u)) {
+                held_for_review.push(HeldUpdate::new(
+                    &repo,
+                    &update.file_path,
+                    HoldReason::SandboxVerificationFailed,
+                ));
+            }
+        }
+        let mut entry = json!({ "repo": repo, "verified": verified, "commands": results });
+        if let Some(report) = &vet_report {
+            entry["vet"] = json!(report);
+        } else if let Some(error) = vet_error {
+            entry["vet"] = json!({ "error": error });
+        }
+        sandboxes.push(entry);
+    }
+
+    timings.end_phase("verification");
+    Verification {
+        migrations,
+        sandboxes,
+        new_dependencies,
+    }
+}
@@ -0,0 +1,1758 @@
+use evo_agent_sdk::prelude::*;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::approval::{ApprovalStore, HeldFile, Question};
+use crate::audit_log::{AuditEntry, AuditLog};
+use crate::backend::{Backends, KingClient, VcsBackend};
+use crate::changelog::MigrationNote;
+use crate::checkpoint::{Checkpoint, PlannedFile};
+use crate::checksum::sha256_hex;
+use crate::commit_message::{MessageVars, apply_type_scope};
+use crate::config::{CommitGranularity, RepoConfig, UpdateConfig};
+use crate::diff::diff_stats;
+use crate::git::{CommitStrategy, FileChange};
+use crate::lease::{Lease, LeaseConfig, LeaseStatus};
+use crate::metadata::CrateLinks;
+use crate::osv::OsvFinding;
+use crate::plan::{PlanStore, PlannedChange, SigningKey, UpdatePlan};
+use crate::policy::AutoApply;
+use crate::pr::{
+    PrFile, branch_exists, branch_name, open_grouped_pr, post_review, render_pr_body,
+    version_comments,
+};
+use crate::provenance::ChangedFile;
+use crate::retry_queue::{Freshness, QueuedCommit, QueuedFile, RetryQueue};
+use crate::risk::GateAction;
+use crate::rollout::TierGate;
+use crate::state::now_secs;
+use crate::summary::{CommitDiffStats, CommitRecord, HeldUpdate, HoldReason};
+use crate::template::Drift;
+use crate::timing::{Stage, Timings};
+use crate::upstream::UpstreamChanges;
+use crate::versions::VersionReport;
+use crate::{
+    approval, commit_message, git, plan, release, retry_queue, rollout, template, watcher,
+};
+
+use super::analysis::Analysis;
+use super::lookup::Versions;
+use super::scan::Scan;
+use super::{
+    CommitMode, Delivery, Gates, PendingUpdate, Run, SELF_REPO, build_plan, group_by_repo,
+    revert_entries,
+};
+
+// ─── Phase 4: Apply updates ───────────────────────────────────────────────────
+
+/// What Phase 4 delivered, held back or retried.
+#[derive(Debug, Default)]
+pub(super) struct Delivered {
+    /// Summary of the plan a `plan` run stored.
+    pub stored_plan: Option<Value>,
+    pub committed: Vec<CommitRecord>,
+    pub errors: Vec<Value>,
+    /// File contents committed directly this run, keyed by (repo, file).
+    pub applied: HashMap<(String, String), String>,
+    pub audit_entries: Vec<AuditEntry>,
+    pub changed_files: Vec<ChangedFile>,
+    /// The question asked about the updates the major-bump gate held.
+    pub approval_question: Option<Value>,
+    pub rollout_tiers: Vec<rollout::TierResult>,
+    pub canary: Option<rollout::CanaryResult>,
+    pub lease: Option<LeaseStatus>,
+    pub retried: Vec<Value>,
+    /// `(repo, file, sha256)` of every file a retried commit delivered.
+    pub retried_files: Vec<(String, String, String)>,
+    /// Commits left in the retry queue.
+    pub queued: usize,
+    pub template_prs: Vec<Value>,
+}
+
+/// Phase 4: delivers the updates repo by repo under the run lease, the
+/// canary first and the other repos tier by tier, retrying each repo's
+/// queued commits at its turn.  Release workflows and template PRs follow
+/// once the repos are done.
+pub(super) async fn deliver(
+    run: &Run<'_>,
+    versions: &Versions<'_>,
+    gates: &Gates,
+    scan: &mut Scan<'_>,
+    analysis: &Analysis,
+    timings: &mut Timings,
+) -> anyhow::Result<Delivered> {
+    let Run {
+        ctx,
+        config,
+        dry_run,
+        offline,
+        planning,
+        commit_mode,
+        ref org,
+        ref base_dir,
+        ref plans,
+        ref unfinished,
+        ref resumed_from,
+        ref checkpoint_path,
+        ref retry_queue,
+        ..
+    } = *run;
+    let Backends { vcs, king, .. } = run.backends;
+    let latest_versions = &versions.latest;
+    let Scan {
+        pending_updates,
+        held_for_review,
+        release_bumps,
+        template_proposals,
+        frozen_repos,
+        ..
+    } = scan;
+
+    // What a run that stopped half-way already delivered is skipped.
+    if let Some(checkpoint) = &unfinished {
+        let before = pending_updates.len();
+        pending_updates.retain(|u| {
+            !checkpoint.delivered(
+                &u.repo,
+                &u.file_path,
+                &sha256_hex(u.patched_content.as_bytes()),
+            )
+        });
+        if pending_updates.len() < before {
+            info!(
+                skipped = before - pending_updates.len(),
+                run = %checkpoint.run_id,
+                "skipping files an unfinished run already delivered"
+            );
+        }
+    }
+    let stored_plan = if planning {
+        let plan = build_plan(
+            &ctx.run_id,
+            &ctx.metadata,
+            &run.snapshot.version,
+            latest_versions,
+            pending_updates.iter(),
+        )?;
+        let path = plans.save(&plan)?;
+        info!(plan = %plan.plan_id, changes = plan.changes.len(), path = %path.display(), "stored update plan");
+        Some(plan::summary(&plan, &path))
+    } else {
+        None
+    };
+    info!(
+        count = pending_updates.len(),
+        dry_run,
+        mode = ?commit_mode,
+        "Phase 4: applying updates"
+    );
+
+    // ── Retry queue: commits that failed in earlier runs ──
+    // Each is retried at its repo's turn below, behind the same lease,
+    // tier and canary holds as the repo's fresh updates.
+    let mut queue = retry_queue.load().unwrap_or_else(|e| {
+        error!(error = %e, "retry queue unreadable — starting empty");
+        Vec::new()
+    });
+    let mut retried = if dry_run {
+        Vec::new()
+    } else {
+        drop_unmanaged(&run.snapshot.config, &mut queue)
+    };
+    let mut retried_files: Vec<(String, String, String)> = Vec::new();
+
+    // ── Run lease: only one replica commits at a time ──
+    let mut lease = None;
+    let mut lease_status = None;
+    if let Some(lease_config) = LeaseConfig::from_env()
+        && !dry_run
+        && !(pending_updates.is_empty() && queue.is_empty())
+    {
+        let (granted, status) = Lease::acquire(king, &ctx.run_id, &lease_config).await;
+        if granted.is_none() {
+            warn!(
+                holder = ?status.holder,
+                expires_at = ?status.expires_at,
+                error = ?status.error,
+                "run lease not granted — deferring {} update(s) to a later run",
+                pending_updates.len()
+            );
+        }
+        lease = granted;
+        lease_status = Some(status);
+    }
+    let deliverable: &[PendingUpdate] = match &lease_status {
+        Some(status) if !status.acquired => &[],
+        _ => pending_updates,
+    };
+
+    // ── Checkpoint: progress saved after every delivered file ──
+    let leased = lease_status.as_ref().is_none_or(|status| status.acquired);
+    let checkpoint = (!dry_run && leased).then(|| {
+        let (run_id, metadata) = match (&resumed_from, &unfinished) {
+            (Some(run_id), Some(previous)) => (run_id.as_str(), &previous.metadata),
+            _ => (ctx.run_id.as_str(), &ctx.metadata),
+        };
+        let planned = deliverable
+            .iter()
+            .map(|u| PlannedFile {
+                repo: u.repo.clone(),
+                file: u.file_path.clone(),
+            })
+            .collect();
+        let delivered = unfinished
+            .as_ref()
+            .map(|previous| previous.delivered.clone())
+            .unwrap_or_default();
+        let mut checkpoint =
+            Checkpoint::start(checkpoint_path, run_id, metadata, planned, delivered);
+        // A run that does not resume the unfinished one still keeps its
+        // remaining files planned, so `resume` can finish them later.
+        if let (None, Some(previous)) = (&resumed_from, &unfinished) {
+            checkpoint.carry_over(previous);
+        }
+        if let Err(e) = checkpoint.save() {
+            warn!(error = %e, "failed to save the checkpoint");
+        }
+        checkpoint
+    });
+
+    // ── Tiers: repos after the repos they depend on, gated in between ──
+    let tier_of = tier_index(config);
+    // Dry runs commit nothing and offline runs cannot see CI, so only
+    // failed updates gate them.
+    let tier_gate = match config.rollout.tier_gate {
+        TierGate::Checks if dry_run || offline => TierGate::Errors,
+        tier_gate => tier_gate,
+    };
+    // The canary goes first, ahead of its tier.
+    let canary = config.rollout.canary.as_deref();
+    // Updates the major-bump gate holds, asked about once Phase 4 is done.
+    let mut awaiting_approval: Vec<&PendingUpdate> = Vec::new();
+    let mut groups = group_by_repo(deliverable);
+    // Repos with only queued commits take their turn too.
+    if !dry_run && leased {
+        for spec in &config.repos {
+            if queue.iter().any(|item| item.repo == spec.repo)
+                && !groups.iter().any(|(repo, _)| *repo == spec.repo)
+            {
+                groups.push((&spec.repo, Vec::new()));
+            }
+        }
+    }
+    groups.sort_by_key(|(repo, _)| {
+        (
+            Some(*repo) != canary,
+            tier_of.get(*repo).copied().unwrap_or_default(),
+        )
+    });
+    let mut rollout_tiers: Vec<rollout::TierResult> = Vec::new();
+    let mut canary_result: Option<rollout::CanaryResult> = None;
+    let mut current_tier: Option<usize> = None;
+    let mut tier_repos: Vec<String> = Vec::new();
+    let (mut tier_errors, mut tier_committed) = (0, 0);
+    // Why every remaining repo is held: reason and detail.
+    let mut hold: Option<(HoldReason, String)> = None;
+
+    let mut wave = Wave {
+        run,
+        analysis,
+        crate_links: &versions.crate_links,
+        auto_apply: gates.auto_apply,
+        checkpoint,
+        queue,
+        committed: Vec::new(),
+        errors: Vec::new(),
+        applied: HashMap::new(),
+        audit_entries: Vec::new(),
+        changed_files: Vec::new(),
+    };
+    // `None` closes the loop, so a canary updated alone is still verified.
+    for group in groups.into_iter().map(Some).chain([None]) {
+        // ── Canary: verified before any other repo is touched ──
+        if let Some(canary) = canary
+            && canary_result.is_none()
+            && group.as_ref().is_none_or(|(repo, _)| *repo != canary)
+        {
+            let (result, canary_hold) = wave.check_canary(canary).await;
+            if canary_hold.is_some() {
+                hold = canary_hold;
+            }
+            canary_result = Some(result);
+        }
+        let Some((repo, mut updates)) = group else {
+            break;
+        };
+
+        let repo_tier = tier_of.get(repo).copied().unwrap_or_default();
+        if Some(repo) != canary && current_tier != Some(repo_tier) {
+            if let Some(done) = current_tier
+                && hold.is_none()
+            {
+                let result = rollout::gate(
+                    &config.rollout,
+                    tier_gate,
+                    org,
+                    done,
+                    std::mem::take(&mut tier_repos),
+                    &wave.errors[tier_errors..],
+                    &wave.committed[tier_committed..],
+                )
+                .await;
+                hold = result
+                    .reason
+                    .clone()
+                    .map(|detail| (HoldReason::UpstreamTierFailed, detail));
+                rollout_tiers.push(result);
+            }
+            current_tier = Some(repo_tier);
+            (tier_errors, tier_committed) = (wave.errors.len(), wave.committed.len());
+        }
+        // ── Lease: a replica that lost it must not commit any further ──
+        if hold.is_none()
+            && let Some(lease) = &lease
+            && !lease.is_valid()
+        {
+            warn!(repo, "run lease lost — holding the remaining repos");
+            if let Some(status) = lease_status.as_mut() {
+                status.lost = true;
+            }
+            hold = Some((
+                HoldReason::LeaseLost,
+                "the run lease was taken by another replica or expired".to_string(),
+            ));
+        }
+        // ── Retry queue: the repo's commits from earlier runs go first ──
+        let spec = config.repos.iter().find(|spec| spec.repo == repo);
+        if !dry_run
+            && let Some(spec) = spec
+            && wave.queue.iter().any(|item| item.repo == repo)
+        {
+            let (entries, files) = retry_queued_commits(
+                &**vcs,
+                org,
+                spec,
+                base_dir,
+                offline,
+                gates,
+                hold.as_ref(),
+                &mut wave.queue,
+            )
+            .await;
+            retried.extend(entries);
+            // A retried commit already carries what the scan proposed
+            // again from the unchanged checkout.
+            updates.retain(|u| {
+                !files.iter().any(|(_, file, sha)| {
+                    *file == u.file_path && *sha == sha256_hex(u.patched_content.as_bytes())
+                })
+            });
+            retried_files.extend(files);
+        }
+        if let Some((reason, detail)) = &hold {
+            warn!(repo, tier = repo_tier, reason = ?reason, "holding the repo");
+            for update in updates {
+                held_for_review.push(
+                    HeldUpdate::new(repo, &update.file_path, *reason)
+                        .with("tier", repo_tier)
+                        .with("detail", detail),
+                );
+            }
+            continue;
+        }
+        if Some(repo) != canary {
+            tier_repos.push(repo.to_string());
+        }
+
+        wave.deliver_repo(
+            repo,
+            updates,
+            held_for_review,
+            &mut awaiting_approval,
+            timings,
+        )
+        .await;
+    }
+
+    let previous = unfinished.as_ref().filter(|_| resumed_from.is_none());
+    if let Some(checkpoint) = wave.checkpoint.take()
+        && let Err(e) = checkpoint.complete(previous)
+    {
+        warn!(error = %e, "failed to remove the checkpoint");
+    }
+    dispatch_release_workflows(run, release_bumps, &wave.applied).await;
+    let template_prs = propose_template_prs(run, template_proposals, frozen_repos);
+
+    if let Some(lease) = lease {
+        lease.release().await;
+    }
+    if !dry_run && let Err(e) = retry_queue.save(&wave.queue) {
+        error!(error = %e, "failed to update the retry queue");
+    }
+    timings.end_phase("apply");
+
+    // ── Ask a human about the updates the major-bump gate held ──────────
+    let approval_question = if dry_run || offline || awaiting_approval.is_empty() {
+        None
+    } else {
+        ask_approval(
+            &**king,
+            plans,
+            build_plan(
+                &format!("{}-approval", ctx.run_id),
+                &ctx.metadata,
+                &run.snapshot.version,
+                latest_versions,
+                awaiting_approval.into_iter(),
+            )?,
+            analysis
+                .risk_assessment
+                .as_ref()
+                .map(|a| a.summary.as_str()),
+        )
+        .await
+    };
+
+    let Wave {
+        committed,
+        errors,
+        applied,
+        audit_entries,
+        changed_files,
+        queue,
+        ..
+    } = wave;
+    Ok(Delivered {
+        stored_plan,
+        committed,
+        errors,
+        applied,
+        audit_entries,
+        changed_files,
+        approval_question,
+        rollout_tiers,
+        canary: canary_result,
+        lease: lease_status,
+        retried,
+        retried_files,
+        queued: queue.len(),
+        template_prs,
+    })
+}
+
+/// Phase 4 under way: what the repos' turns share and what they delivered
+/// so far.
+struct Wave<'s, 'a> {
+    run: &'s Run<'a>,
+    analysis: &'s Analysis,
+    crate_links: &'s BTreeMap<String, CrateLinks>,
+    auto_apply: AutoApply,
+    checkpoint: Option<Checkpoint>,
+    queue: Vec<QueuedCommit>,
+    committed: Vec<CommitRecord>,
+    errors: Vec<Value>,
+    applied: HashMap<(String, String), String>,
+    audit_entries: Vec<AuditEntry>,
+    changed_files: Vec<ChangedFile>,
+}
+
+impl<'s> Wave<'s, '_> {
+    /// Checks the canary's CI before any other repo is touched.  A failed
+    /// canary is reverted; returns its result, and why the remaining repos
+    /// are held when it did not pass.
+    async fn check_canary(
+        &mut self,
+        canary: &str,
+    ) -> (rollout::CanaryResult, Option<(HoldReason, String)>) {
+        let Run {
+            ctx,
+            config,
+            dry_run,
+            offline,
+            ref org,
+            ref base_dir,
+            ..
+        } = *self.run;
+        let king = &self.run.backends.king;
+        let Wave {
+            committed,
+            errors,
+            applied,
+            audit_entries,
+            ..
+        } = self;
+        let mut hold = None;
+        let handed_over = rollout::handed_over(&ctx.metadata, canary);
+        let follow_up = !handed_over.is_empty();
+        let mut commits = handed_over;
+        commits.extend(
+            committed
+                .iter()
+                .filter(|c| c.repo == canary)
+                .filter_map(|c| c.sha.clone()),
+        );
+        let mut result = rollout::canary_gate(
+            &config.rollout,
+            if dry_run || offline {
+                TierGate::Errors
+            } else {
+                TierGate::Checks
+            },
+            org,
+            canary,
+            errors,
+            commits,
+            follow_up,
+        )
+        .await;
+        let reason = result.reason.clone().unwrap_or_default();
+        match result.status {
+            rollout::CanaryStatus::Passed => {}
+            rollout::CanaryStatus::Failed => {
+                if !errors.iter().any(|e| e["repo"] == canary) {
+                    errors.push(json!({ "repo": canary, "error": reason }));
+                }
+                if !result.commits.is_empty() {
+                    let reverted = revert_canary(
+                        config,
+                        base_dir,
+                        &ctx.run_id,
+                        canary,
+                        &result.commits,
+                        audit_entries,
+                    );
+                    if reverted.get("sha").is_some() {
+                        applied.retain(|(repo, _), _| *repo != canary);
+                        for entry in committed.iter_mut().filter(|c| c.repo == canary) {
+                            entry.reverted = true;
+                        }
+                    }
+                    result.reverted = Some(reverted);
+                }
+                hold = Some((HoldReason::CanaryFailed, reason));
+            }
+            rollout::CanaryStatus::Pending => {
+                let metadata = rollout::follow_up_metadata(canary, &result.commits);
+                result.follow_up = Some(
+                    match watcher::request_run(&**king, "canary_follow_up", metadata).await {
+                        Ok(()) => json!("requested"),
+                        Err(e) => {
+                            warn!(repo = canary, error = %e, "requesting the canary follow-up run failed");
+                            json!({ "error": format!("{e:#}") })
+                        }
+                    },
+                );
+                hold = Some((HoldReason::CanaryPending, reason));
+            }
+        }
+        (result, hold)
+    }
+
+    /// Delivers `repo`'s `updates`: risky major bumps go to a draft PR or
+    /// wait for approval, the rest is committed or proposed as the repo's
+    /// delivery mode says.
+    async fn deliver_repo<'p>(
+        &mut self,
+        repo: &str,
+        updates: Vec<&'p PendingUpdate>,
+        held_for_review: &mut Vec<HeldUpdate>,
+        awaiting_approval: &mut Vec<&'p PendingUpdate>,
+        timings: &mut Timings,
+    ) {
+        let Run {
+            ctx,
+            config,
+            dry_run,
+            offline,
+            commit_mode,
+            granularity: run_granularity,
+            ref org,
+            ref answer,
+            ..
+        } = *self.run;
+        let vcs = &self.run.backends.vcs;
+        let Wave {
+            analysis,
+            crate_links,
+            auto_apply,
+            checkpoint,
+            queue,
+            committed,
+            errors,
+            applied,
+            audit_entries,
+            changed_files,
+            ..
+        } = self;
+        let granularity_for = |repo: &str| {
+            config
+                .repos
+                .iter()
+                .find(|spec| spec.repo == repo)
+                .and_then(|spec| spec.commit_granularity)
+                .unwrap_or(run_granularity)
+        };
+        let delivery_for = |repo: &str| {
+            let strategy = config
+                .repos
+                .iter()
+                .find(|spec| spec.repo == repo)
+                .and_then(|spec| spec.commit_strategy);
+            Delivery::resolve(commit_mode, offline, strategy, granularity_for(repo))
+        };
+        let gate = &config.policy.major_bumps;
+
+        let granularity = granularity_for(repo);
+        let push = config
+            .repos
+            .iter()
+            .find(|spec| spec.repo == repo)
+            .map(RepoConfig::push_target)
+            .unwrap_or_default();
+        let delivery = match delivery_for(repo) {
+            Ok(delivery) => delivery,
+            Err(reason) => {
+                warn!(repo, reason, "repo cannot be updated this run");
+                for update in updates {
+                    errors.push(json!({
+                        "repo": repo,
+                        "file": update.file_path,
+                        "error": reason,
+                    }));
+                }
+                return;
+            }
+        };
+
+        // ── Major-bump gate: risky major bumps go to a draft PR or wait ──
+        let can_draft = match delivery {
+            Delivery::PullRequest => true,
+            Delivery::Direct(strategies) => {
+                strategies.contains(&CommitStrategy::GhCli) && git::gh_available()
+            }
+        };
+        let llm_source = updates.iter().any(|u| u.requires_pr);
+        let mut held_any = false;
+        let mut direct: Vec<&PendingUpdate> = Vec::new();
+        let mut drafted: Vec<&PendingUpdate> = Vec::new();
+        for update in updates {
+            let flagged = analysis
+                .risk_assessment
+                .as_ref()
+                .map(|a| gate.flagged(&update.versions, a))
+                .unwrap_or_default();
+            if flagged.is_empty() || answer.is_some() {
+                direct.push(update);
+            } else if gate.action == GateAction::DraftPr && can_draft {
+                info!(repo, file = %update.file_path, "risky major bump — delivering as draft PR");
+                drafted.push(update);
+            } else {
+                warn!(repo, file = %update.file_path, "risky major bump — holding for review");
+                held_for_review.push(
+                    HeldUpdate::new(repo, &update.file_path, HoldReason::LlmRisk)
+                        .with("bumps", &flagged),
+                );
+                awaiting_approval.push(update);
+                held_any = true;
+            }
+        }
+
+        // LLM-migrated source needs every manifest bump it was written
+        // against, and may only land through a PR.
+        if llm_source && (held_any || !can_draft) {
+            warn!(
+                repo,
+                "LLM-migrated source cannot be delivered as a PR with its bumps — holding for review"
+            );
+            for update in direct.drain(..).chain(drafted.drain(..)) {
+                held_for_review.push(HeldUpdate::new(
+                    repo,
+                    &update.file_path,
+                    HoldReason::LlmMigrationNeedsPr,
+                ));
+            }
+            return;
+        }
+
+        if dry_run {
+            // In dry-run, list what would have been committed
+            let label = if llm_source {
+                "PullRequest"
+            } else {
+                delivery.label()
+            };
+            for (update, mode) in direct
+                .iter()
+                .map(|u| (u, label))
+                .chain(drafted.iter().map(|u| (u, "DraftPullRequest")))
+            {
+                committed.push(CommitRecord {
+                    repo: update.repo.clone(),
+                    file: Some(update.file_path.clone()),
+                    bumps: update.versions.clone(),
+                    dry_run: true,
+                    commit_mode: Some(mode.to_string()),
+                    commit_granularity: Some(granularity),
+                    commit_message: Some(update.commit_message.clone()),
+                    ..Default::default()
+                });
+            }
+            return;
+        }
+
+        // Under PR delivery the repo's single PR carries every update and
+        // is drafted when any of them is gated; otherwise gated bumps get
+        // a draft PR of their own and the rest is committed directly.
+        let (pr_updates, draft, direct, strategies): (Vec<&PendingUpdate>, bool, _, _) =
+            match delivery {
+                Delivery::PullRequest => {
+                    let draft = !drafted.is_empty();
+                    direct.append(&mut drafted);
+                    (direct, draft, Vec::new(), &[][..])
+                }
+                // LLM-migrated source goes into one PR with its bumps.
+                Delivery::Direct(strategies) if llm_source => {
+                    let draft = !drafted.is_empty();
+                    direct.append(&mut drafted);
+                    (direct, draft, Vec::new(), strategies)
+                }
+                Delivery::Direct(strategies) => (drafted, true, direct, strategies),
+            };
+
+        if !pr_updates.is_empty() {
+            let pr_started = Instant::now();
+            let single_commit = (granularity == CommitGranularity::Repo)
+                .then(|| repo_commit_message(config, repo, &ctx.run_id, &pr_updates));
+            let opened = open_update_pr(
+                org,
+                repo,
+                &ctx.run_id,
+                &repo_pr_title(config, repo, &ctx.run_id),
+                &analysis.summary,
+                crate_links,
+                &pr_updates,
+                draft,
+                single_commit.as_deref(),
+            );
+            timings.repo(repo, Stage::Commit, pr_started.elapsed());
+            match opened {
+                Ok(mut entry) => {
+                    if pr_updates.iter().any(|u| u.verified_sha256.is_some()) {
+                        entry.verified = Some(pr_updates.iter().all(|u| {
+                            u.verified_sha256.as_deref()
+                                == Some(sha256_hex(u.patched_content.as_bytes()).as_str())
+                        }));
+                    }
+                    for update in &pr_updates {
+                        let stats = diff_stats(
+                            update.original_content.as_bytes(),
+                            update.patched_content.as_bytes(),
+                        );
+                        record_delivery(
+                            changed_files,
+                            checkpoint,
+                            ChangedFile {
+                                repo: update.repo.clone(),
+                                file: update.file_path.clone(),
+                                pre_sha256: stats.pre_sha256,
+                                post_sha256: stats.post_sha256,
+                                commit: None,
+                                pr_url: entry.pr_url.clone(),
+                            },
+                        );
+                        audit_entries.extend(audit_entries_for(
+                            &ctx.run_id,
+                            update,
+                            None,
+                            entry.pr_url.as_deref(),
+                            "PullRequest",
+                        ));
+                    }
+                    committed.push(entry);
+                }
+                Err(e) => {
+                    warn!(repo, error = %e, "grouped PR failed");
+                    errors.push(json!({
+                        "repo": repo,
+                        "files": pr_updates.iter().map(|u| u.file_path.as_str()).collect::<Vec<_>>(),
+                        "error": e.to_string(),
+                    }));
+                }
+            }
+        }
+
+        // Per-file granularity commits each file on its own; `repo`
+        // lands all of them in one commit.
+        // Lockfiles and release-version edits always join their
+        // manifest's commit.
+        let batches: Vec<Vec<&PendingUpdate>> = match granularity {
+            CommitGranularity::Repo if !direct.is_empty() => vec![direct],
+            _ => {
+                let mut batches: Vec<Vec<&PendingUpdate>> = Vec::new();
+                for update in direct {
+                    let manifest_batch = update.part_of.as_ref().and_then(|manifest| {
+                        batches.iter_mut().find(|b| b[0].file_path == *manifest)
+                    });
+                    match manifest_batch {
+                        Some(batch) => batch.push(update),
+                        None => batches.push(vec![update]),
+                    }
+                }
+                batches
+            }
+        };
+        for batch in batches {
+            let changes: Vec<FileChange> = batch
+                .iter()
+                .map(|u| FileChange {
+                    path: &u.file_path,
+                    previous: u.original_content.as_bytes(),
+                    content: u.patched_content.as_bytes(),
+                })
+                .collect();
+            let message = match batch.as_slice() {
+                [update] => update.commit_message.clone(),
+                [update, rest @ ..] if rest.iter().all(|u| u.part_of.is_some()) => {
+                    update.commit_message.clone()
+                }
+                _ => repo_commit_message(config, repo, &ctx.run_id, &batch),
+            };
+            let message = if repo == SELF_REPO {
+                commit_message::with_self_update_trailer(&message, &ctx.run_id)
+            } else {
+                message
+            };
+            let commit_started = Instant::now();
+            let outcome = vcs
+                .commit(
+                    org,
+                    repo,
+                    &changes,
+                    &message,
+                    Some(Path::new(&batch[0].local_base)),
+                    &push,
+                    strategies,
+                )
+                .await;
+            timings.repo(repo, Stage::Commit, commit_started.elapsed());
+            let results = match outcome {
+                Ok(results) => results,
+                Err(e) => {
+                    for update in &batch {
+                        warn!(repo = %update.repo, file = %update.file_path, error = %e, "commit failed");
+                        errors.push(json!({
+                            "repo": update.repo,
+                            "file": update.file_path,
+                            "error": e.to_string(),
+                            "queued_for_retry": e.downcast_ref::<git::PathSecurityError>().is_none(),
+                        }));
+                    }
+                    // Refused paths fail the same way every time.
+                    if e.downcast_ref::<git::PathSecurityError>().is_none() {
+                        queue.push(QueuedCommit {
+                            repo: repo.to_string(),
+                            files: batch
+                                .iter()
+                                .map(|u| QueuedFile {
+                                    path: u.file_path.clone(),
+                                    original: u.original_content.clone(),
+                                    content: u.patched_content.clone(),
+                                })
+                                .collect(),
+                            message,
+                            run_id: ctx.run_id.clone(),
+                            attempts: 1,
+                            last_error: format!("{e:#}"),
+                            queued_at: now_secs(),
+                            crates: queued_crates(&batch),
+                            // Under security-only, only fixes reach Phase 4.
+                            security_fix: *auto_apply == AutoApply::SecurityOnly
+                                || batch
+                                    .iter()
+                                    .any(|u| u.vulnerabilities.iter().any(|f| !f.fixed.is_empty())),
+                        });
+                    }
+                    continue;
+                }
+            };
+            for (update, result) in batch.into_iter().zip(results) {
+                info!(
+                    repo = %update.repo,
+                    file = %update.file_path,
+                    sha = %result.sha,
+                    strategy = ?result.strategy,
+                    "committed"
+                );
+                applied.insert(
+                    (update.repo.clone(), update.file_path.clone()),
+                    update.patched_content.clone(),
+                );
+                audit_entries.extend(audit_entries_for(
+                    &ctx.run_id,
+                    update,
+                    Some(&result.sha),
+                    None,
+                    &format!("{:?}", result.strategy),
+                ));
+                record_delivery(
+                    changed_files,
+                    checkpoint,
+                    ChangedFile {
+                        repo: update.repo.clone(),
+                        file: update.file_path.clone(),
+                        pre_sha256: result.stats.pre_sha256.clone(),
+                        post_sha256: result.stats.post_sha256.clone(),
+                        commit: Some(result.sha.clone()),
+                        pr_url: None,
+                    },
+                );
+                committed.push(CommitRecord {
+                    repo: update.repo.clone(),
+                    file: Some(update.file_path.clone()),
+                    strategy: Some(format!("{:?}", result.strategy)),
+                    sha: Some(result.sha.clone()),
+                    // The sandbox checked exactly these bytes.
+                    verified: update
+                        .verified_sha256
+                        .as_ref()
+                        .map(|verified| *verified == result.stats.post_sha256),
+                    pushed_to: (result.strategy == CommitStrategy::LocalGit && push.is_custom())
+                        .then(|| push.to_string()),
+                    diff_stats: Some(CommitDiffStats::File(result.stats)),
+                    bumps: update.versions.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+/// Dispatches the release workflow of every repo whose version bump
+/// landed, recording the outcome in its `release_bumps` entry.
+async fn dispatch_release_workflows(
+    run: &Run<'_>,
+    release_bumps: &mut [Value],
+    applied: &HashMap<(String, String), String>,
+) {
+    let Run {
+        config,
+        dry_run,
+        offline,
+        ref org,
+        ..
+    } = *run;
+    let king = &run.backends.king;
+    for bump in release_bumps.iter_mut() {
+        let Some(repo) = bump["repo"].as_str().map(str::to_string) else {
+            continue;
+        };
+        if bump.get("error").is_some() {
+            continue;
+        }
+        let landed = bump["files"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .all(|file| applied.contains_key(&(repo.clone(), file.to_string())));
+        let spec = config.repos.iter().find(|spec| spec.repo == repo);
+        bump["dispatch"] = match spec.and_then(|spec| spec.release_workflow.as_deref()) {
+            None => json!("no_workflow"),
+            Some(_) if dry_run => json!("dry_run"),
+            Some(_) if !landed => json!("not_committed"),
+            Some(_) if offline => json!("offline"),
+            Some(_) if spec.is_some_and(|spec| spec.push_target().is_custom()) => {
+                json!("custom_push_target")
+            }
+            Some(workflow) => match release::dispatch_workflow(org, &repo, workflow) {
+                Ok(branch) => {
+                    info!(repo = %repo, workflow, branch = %branch, "release workflow dispatched");
+                    let mut dispatch = json!({ "workflow": workflow, "ref": branch });
+                    // A tracked crate's release starts the next stage: a
+                    // run that waits until the new version is resolvable.
+                    if let Some(package) = bump["package"].as_str()
+                        && config.all_tracked_crates().contains(&package)
+                    {
+                        let released = [(
+                            package.to_string(),
+                            bump["to"].as_str().unwrap_or_default().to_string(),
+                        )];
+                        let metadata = json!({
+                            "refresh_versions": true,
+                            "released": watcher::released_metadata(&released),
+                        });
+                        dispatch["next_stage"] = match watcher::request_run(
+                            &**king,
+                            "release_rollout",
+                            metadata,
+                        )
+                        .await
+                        {
+                            Ok(()) => json!("requested"),
+                            Err(e) => {
+                                warn!(repo = %repo, error = %e, "requesting the next rollout stage failed");
+                                json!({ "error": format!("{e:#}") })
+                            }
+                        };
+                    }
+                    dispatch
+                }
+                Err(e) => {
+                    warn!(repo = %repo, workflow, error = %e, "release workflow dispatch failed");
+                    json!({ "workflow": workflow, "error": format!("{e:#}") })
+                }
+            },
+        };
+    }
+}
+
+/// Opens a PR proposing the template's structure to every repo that
+/// drifted from it.  Returns one `template_prs` entry per repo.
+fn propose_template_prs(
+    run: &Run<'_>,
+    proposals: &[(String, Vec<Drift>)],
+    frozen_repos: &[Value],
+) -> Vec<Value> {
+    let Run {
+        ctx,
+        config,
+        dry_run,
+        offline,
+        ref org,
+        ..
+    } = *run;
+    let mut template_prs: Vec<Value> = Vec::new();
+    for (repo, drift) in proposals {
+        let branch = template::branch_name(drift);
+        let files: Vec<PrFile> = drift
+            .iter()
+            .filter_map(|d| {
+                Some(PrFile {
+                    path: d.file.clone(),
+                    original: d.original.clone(),
+                    content: d.proposed.clone()?,
+                    commit_message: format!(
+                        "chore: apply {} to {} [run_id={}]",
+                        config.template.repo, d.file, ctx.run_id
+                    ),
+                })
+            })
+            .collect();
+        let frozen = frozen_repos.iter().any(|f| f["repo"] == repo.as_str());
+        let pr = if dry_run {
+            json!("dry_run")
+        } else if offline {
+            json!("offline")
+        } else if frozen {
+            json!("frozen")
+        } else if branch_exists(org, repo, &branch) {
+            json!("already_proposed")
+        } else {
+            match open_grouped_pr(
+                org,
+                repo,
+                &branch,
+                &format!("chore: apply {} updates", config.template.repo),
+                &template::pr_body(&config.template.repo, drift),
+                &files,
+                false,
+                None,
+            ) {
+                Ok(pr) => {
+                    info!(repo = %repo, url = %pr.url, "opened template PR");
+                    json!({ "number": pr.number, "url": pr.url })
+                }
+                Err(e) => {
+                    warn!(repo = %repo, error = %e, "opening the template PR failed");
+                    json!({ "error": format!("{e:#}") })
+                }
+            }
+        };
+        template_prs.push(json!({
+            "repo": repo,
+            "branch": branch,
+            "files": files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+            "pr": pr,
+        }));
+    }
+    template_prs
+}
+
+/// Tier of every repo in `config`, after the repos it depends on; empty
+/// (config order) when the dependency graph is rejected.
+fn tier_index(config: &UpdateConfig) -> HashMap<String, usize> {
+    match rollout::tiers(&config.repos) {
+        Ok(tiers) => tiers
+            .into_iter()
+            .enumerate()
+            .flat_map(|(tier, repos)| repos.into_iter().map(move |repo| (repo, tier)))
+            .collect(),
+        Err(e) => {
+            warn!(error = %e, "repo dependency graph rejected — applying in config order");
+            HashMap::new()
+        }
+    }
+}
+
+/// The `retry` action: the queued commits of earlier runs, without a scan.
+///
+/// They are retried under the run lease and through the gates of a full
+/// run: freeze, crate policy and security-only per commit, then the canary
+/// first and the other repos tier by tier, each verified like in Phase 4.
+/// A failed canary is not reverted here; the next full run sees its CI.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn retry_only(
+    ctx: &PipelineContext<'_>,
+    managed: &UpdateConfig,
+    config: &UpdateConfig,
+    vcs: &dyn VcsBackend,
+    king: &Arc<dyn KingClient>,
+    org: &str,
+    base_dir: &Path,
+    offline: bool,
+    retry_queue: &RetryQueue,
+) -> anyhow::Result<Value> {
+    let mut queue = retry_queue.load()?;
+    let mut retried = drop_unmanaged(managed, &mut queue);
+
+    let mut lease = None;
+    let mut lease_status = None;
+    if let Some(lease_config) = LeaseConfig::from_env()
+        && !queue.is_empty()
+    {
+        let (granted, status) = Lease::acquire(king, &ctx.run_id, &lease_config).await;
+        if granted.is_none() {
+            warn!(holder = ?status.holder, error = ?status.error, "run lease not granted — keeping the queued commits");
+        }
+        lease = granted;
+        lease_status = Some(status);
+    }
+    if lease_status.as_ref().is_some_and(|status| !status.acquired) {
+        retry_queue.save(&queue)?;
+        return Ok(json!({
+            "run_id": ctx.run_id,
+            "action": "retry",
+            "retried": retried,
+            "queued": queue.len(),
+            "lease": lease_status,
+        }));
+    }
+
+    let gates = Gates::new(config, Vec::new());
+    let ci_gate = |gate: TierGate| match gate {
+        TierGate::Checks if offline => TierGate::Errors,
+        gate => gate,
+    };
+    let tier_of = tier_index(config);
+    let canary = config.rollout.canary.as_deref();
+    let mut repos: Vec<&RepoConfig> = config
+        .repos
+        .iter()
+        .filter(|spec| queue.iter().any(|item| item.repo == spec.repo))
+        .collect();
+    repos.sort_by_key(|spec| {
+        (
+            Some(spec.repo.as_str()) != canary,
+            tier_of.get(&spec.repo).copied().unwrap_or_default(),
+        )
+    });
+
+    let mut errors: Vec<Value> = Vec::new();
+    let mut committed: Vec<CommitRecord> = Vec::new();
+    let mut rollout_tiers: Vec<rollout::TierResult> = Vec::new();
+    let mut canary_result: Option<rollout::CanaryResult> = None;
+    let mut current_tier: Option<usize> = None;
+    let mut tier_repos: Vec<String> = Vec::new();
+    let (mut tier_errors, mut tier_committed) = (0, 0);
+    let mut hold: Option<(HoldReason, String)> = None;
+    for spec in repos {
+        let repo = spec.repo.as_str();
+        let repo_tier = tier_of.get(repo).copied().unwrap_or_default();
+        if Some(repo) != canary && current_tier != Some(repo_tier) {
+            if let Some(done) = current_tier
+                && hold.is_none()
+            {
+                let result = rollout::gate(
+                    &config.rollout,
+                    ci_gate(config.rollout.tier_gate),
+                    org,
+                    done,
+                    std::mem::take(&mut tier_repos),
+                    &errors[tier_errors..],
+                    &committed[tier_committed..],
+                )
+                .await;
+                hold = result
+                    .reason
+                    .clone()
+                    .map(|detail| (HoldReason::UpstreamTierFailed, detail));
+                rollout_tiers.push(result);
+            }
+            current_tier = Some(repo_tier);
+            (tier_errors, tier_committed) = (errors.len(), committed.len());
+        }
+        if hold.is_none()
+            && let Some(lease) = &lease
+            && !lease.is_valid()
+        {
+            warn!(
+                repo,
+                "run lease lost — keeping the remaining queued commits"
+            );
+            if let Some(status) = lease_status.as_mut() {
+                status.lost = true;
+            }
+            hold = Some((
+                HoldReason::LeaseLost,
+                "the run lease was taken by another replica or expired".to_string(),
+            ));
+        }
+        if Some(repo) != canary && hold.is_none() {
+            tier_repos.push(repo.to_string());
+        }
+
+        let (entries, _) = retry_queued_commits(
+            vcs,
+            org,
+            spec,
+            base_dir,
+            offline,
+            &gates,
+            hold.as_ref(),
+            &mut queue,
+        )
+        .await;
+        for entry in &entries {
+            if let Some(error) = entry.get("error").filter(|_| entry["outcome"] != "held") {
+                errors.push(json!({ "repo": repo, "error": error }));
+            }
+            if let Some(sha) = entry["sha"].as_str() {
+                committed.push(CommitRecord {
+                    repo: repo.to_string(),
+                    sha: Some(sha.to_string()),
+                    ..Default::default()
+                });
+            }
+        }
+        retried.extend(entries);
+
+        if Some(repo) == canary && hold.is_none() {
+            let commits = committed.iter().filter_map(|c| c.sha.clone()).collect();
+            let result = rollout::canary_gate(
+                &config.rollout,
+                ci_gate(TierGate::Checks),
+                org,
+                repo,
+                &errors,
+                commits,
+                false,
+            )
+            .await;
+            hold = match result.status {
+                rollout::CanaryStatus::Passed => None,
+                rollout::CanaryStatus::Failed => Some(HoldReason::CanaryFailed),
+                rollout::CanaryStatus::Pending => Some(HoldReason::CanaryPending),
+            }
+            .map(|reason| (reason, result.reason.clone().unwrap_or_default()));
+            canary_result = Some(result);
+        }
+    }
+
+    if let Some(lease) = lease {
+        lease.release().await;
+    }
+    retry_queue.save(&queue)?;
+    Ok(json!({
+        "run_id": ctx.run_id,
+        "action": "retry",
+        "retried": retried,
+        "queued": queue.len(),
+        "lease": lease_status,
+        "canary": canary_result,
+        "rollout_tiers": rollout_tiers,
+    }))
+}
+
+/// Tracked crates `batch` bumps, for its retry queue entry.
+fn queued_crates(batch: &[&PendingUpdate]) -> Vec<String> {
+    let mut crates: Vec<String> = batch
+        .iter()
+        .flat_map(|u| u.versions.iter().map(|v| v.crate_name.clone()))
+        .collect();
+    crates.sort();
+    crates.dedup();
+    crates
+}
+
+/// The `retried` entry of `item`, before its outcome.
+fn queued_entry(item: &QueuedCommit) -> Value {
+    json!({
+        "repo": item.repo,
+        "files": item.files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+        "run_id": item.run_id,
+    })
+}
+
+/// Drops the queued commits of repos the configuration no longer manages.
+/// Returns their `retried` entries.
+fn drop_unmanaged(config: &UpdateConfig, queue: &mut Vec<QueuedCommit>) -> Vec<Value> {
+    let mut results = Vec::new();
+    queue.retain(|item| {
+        let managed = config.repos.iter().any(|spec| spec.repo == item.repo);
+        if !managed {
+            let mut entry = queued_entry(item);
+            entry["outcome"] = json!("dropped");
+            entry["reason"] = json!("repo is no longer managed");
+            results.push(entry);
+        }
+        managed
+    });
+    results
+}
+
+/// Retries the commits queued for `spec`'s repo by earlier runs, oldest
+/// first, with the repo's current commit strategies and push target.
+///
+/// The whole repo stays queued under `hold` (the lease, tier and canary
+/// holds of Phase 4); each commit must also pass `gates`.  A commit whose
+/// files already hold the queued content, or changed in some other way (the
+/// next scan recomputes it), is dropped unretried; files are compared with
+/// the checkout, or with the default branch through `vcs` when the repo has
+/// none.  `queue` keeps the commits that failed again, until
+/// [`retry_queue::MAX_ATTEMPTS`].  Returns one summary entry per queued
+/// commit, and `(repo, file, sha256)` of every file committed.
+#[allow(clippy::too_many_arguments)]
+async fn retry_queued_commits(
+    vcs: &dyn VcsBackend,
+    org: &str,
+    spec: &RepoConfig,
+    base_dir: &Path,
+    offline: bool,
+    gates: &Gates,
+    hold: Option<&(HoldReason, String)>,
+    queue: &mut Vec<QueuedCommit>,
+) -> (Vec<Value>, Vec<(String, String, String)>) {
+    let mut results: Vec<Value> = Vec::new();
+    let mut committed: Vec<(String, String, String)> = Vec::new();
+    let (items, rest): (Vec<QueuedCommit>, Vec<QueuedCommit>) = std::mem::take(queue)
+        .into_iter()
+        .partition(|item| item.repo == spec.repo);
+    *queue = rest;
+    let local_base = base_dir.join(&spec.local);
+    let checkout = local_base.join(".git").exists();
+    for mut item in items {
+        let mut entry = queued_entry(&item);
+        let gated = match hold {
+            Some((reason, detail)) => Some((
+                json!({ "outcome": "held", "reason": reason, "detail": detail }),
+                true,
+            )),
+            None => gates.check(spec, &item),
+        };
+        if let Some((fields, keep)) = gated {
+            info!(repo = %item.repo, outcome = %fields["outcome"], reason = %fields["reason"], "queued commit not retried");
+            for (key, value) in fields.as_object().into_iter().flatten() {
+                entry[key] = value.clone();
+            }
+            results.push(entry);
+            if keep {
+                queue.push(item);
+            }
+            continue;
+        }
+
+        let mut current: HashMap<String, Option<String>> = HashMap::new();
+        let mut unreadable: Option<String> = None;
+        for file in &item.files {
+            let content = if checkout {
+                std::fs::read_to_string(local_base.join(&file.path)).ok()
+            } else {
+                match vcs.read_file(org, &item.repo, &file.path).await {
+                    Ok(content) => content,
+                    Err(e) => {
+                        unreadable = Some(format!("{e:#}"));
+                        break;
+                    }
+                }
+            };
+            current.insert(file.path.clone(), content);
+        }
+        if let Some(error) = unreadable {
+            warn!(repo = %item.repo, error = %error, "cannot read the queued files — keeping the commit queued");
+            entry["outcome"] = json!("held");
+            entry["reason"] = json!("unreadable");
+            entry["error"] = json!(error);
+            results.push(entry);
+            queue.push(item);
+            continue;
+        }
+        let strategies = match item.freshness(|path| current.get(path).cloned().flatten()) {
+            Freshness::Applied => Err("already_applied"),
+            Freshness::Stale => Err("stale"),
+            Freshness::Pending => match Delivery::resolve(
+                CommitMode::Direct,
+                offline,
+                spec.commit_strategy,
+                CommitGranularity::File,
+            ) {
+                Ok(Delivery::Direct(strategies)) => Ok(strategies),
+                _ => Err("dropped"),
+            },
+        };
+        let strategies = match strategies {
+            Ok(strategies) => strategies,
+            Err(outcome) => {
+                info!(repo = %item.repo, outcome, "queued commit not retried");
+                entry["outcome"] = json!(outcome);
+                results.push(entry);
+                continue;
+            }
+        };
+
+        let changes: Vec<FileChange> = item
+            .files
+            .iter()
+            .map(|f| FileChange {
+                path: &f.path,
+                previous: f.original.as_bytes(),
+                content: f.content.as_bytes(),
+            })
+            .collect();
+        let outcome = vcs
+            .commit(
+                org,
+                &item.repo,
+                &changes,
+                &item.message,
+                checkout.then_some(local_base.as_path()),
+                &spec.push_target(),
+                strategies,
+            )
+            .await;
+        item.attempts += 1;
+        entry["attempts"] = json!(item.attempts);
+        match outcome {
+            Ok(commits) => {
+                info!(repo = %item.repo, sha = %commits[0].sha, attempts = item.attempts, "queued commit retried");
+                entry["outcome"] = json!("committed");
+                entry["sha"] = json!(commits[0].sha);
+                entry["strategy"] = json!(format!("{:?}", commits[0].strategy));
+                committed.extend(
+                    commits
+                        .into_iter()
+                        .map(|c| (item.repo.clone(), c.file_path, c.stats.post_sha256)),
+                );
+            }
+            Err(e) => {
+                warn!(repo = %item.repo, attempts = item.attempts, error = %e, "queued commit failed again");
+                entry["error"] = json!(format!("{e:#}"));
+                item.last_error = format!("{e:#}");
+                if item.attempts >= retry_queue::MAX_ATTEMPTS {
+                    entry["outcome"] = json!("gave_up");
+                } else {
+                    entry["outcome"] = json!("failed");
+                    queue.push(item);
+                }
+            }
+        }
+        results.push(entry);
+    }
+    (results, committed)
+}
+
+/// Stores the held `plan` and asks the king's chat whether to apply it.
+/// Files already asked about (or rejected) are left out; `None` when none
+/// remain.  Returns `{ question_id, text, repos }` or `{ error }`.
+async fn ask_approval(
+    king: &dyn KingClient,
+    plans: &PlanStore,
+    mut plan: UpdatePlan,
+    risk: Option<&str>,
+) -> Option<Value> {
+    let result = async {
+        let mut approvals = ApprovalStore::load(&ApprovalStore::default_path())?;
+        let held = |c: &PlannedChange| HeldFile {
+            repo: c.repo.clone(),
+            file: c.file.clone(),
+            sha256: c.sha256.clone(),
+        };
+        plan.changes.retain(|c| !approvals.asked(&held(c)));
+        if plan.changes.is_empty() {
+            return Ok(None);
+        }
+        if let Some(key) = SigningKey::from_env() {
+            plan.sign(&key)?;
+        }
+        plans.save(&plan)?;
+        let question = Question::for_plan(&plan, risk);
+        approval::ask(king, &question).await?;
+        approvals.open(
+            &question.question_id,
+            plan.changes.iter().map(held).collect(),
+        );
+        approvals.save()?;
+        info!(question = %question.question_id, repos = ?question.repos, "asked for approval of held updates");
+        anyhow::Ok(Some(json!({
+            "question_id": question.question_id,
+            "text": question.text,
+            "repos": question.repos,
+        })))
+    };
+    result.await.unwrap_or_else(|e| {
+        warn!(error = %e, "asking for approval failed — the updates stay held");
+        Some(json!({ "error": format!("{e:#}") }))
+    })
+}
+
+/// Message of one commit carrying several of `repo`'s updates, with the
+/// repo's conventional-commit type and scope applied.
+fn repo_commit_message(
+    config: &UpdateConfig,
+    repo: &str,
+    run_id: &str,
+    updates: &[&PendingUpdate],
+) -> String {
+    let spec = config.repos.iter().find(|spec| spec.repo == repo);
+    let versions: Vec<VersionReport> = updates.iter().flat_map(|u| u.versions.clone()).collect();
+    let subject = apply_type_scope(
+        &commit_message::render(
+            commit_message::REPO_SUBJECT_TEMPLATE,
+            &MessageVars {
+                repo,
+                file: "",
+                run_id,
+                versions: &versions,
+            },
+        ),
+        spec.and_then(|s| s.commit_type.as_deref()),
+        spec.and_then(|s| s.commit_scope.as_deref()),
+    );
+    let messages: Vec<&str> = updates.iter().map(|u| u.commit_message.as_str()).collect();
+    commit_message::combine(&subject, &messages)
+}
+
+/// Title of `repo`'s grouped PR, with the repo's `commit_type` / `commit_scope`
+/// applied like its commit subjects.
+fn repo_pr_title(config: &UpdateConfig, repo: &str, run_id: &str) -> String {
+    let spec = config.repos.iter().find(|spec| spec.repo == repo);
+    apply_type_scope(
+        &commit_message::render(
+            commit_message::PR_TITLE_TEMPLATE,
+            &MessageVars {
+                repo,
+                file: "",
+                run_id,
+                versions: &[],
+            },
+        ),
+        spec.and_then(|s| s.commit_type.as_deref()),
+        spec.and_then(|s| s.commit_scope.as_deref()),
+    )
+}
+
+/// Adds `file` to the run's changed files and records it in the checkpoint.
+fn record_delivery(
+    changed_files: &mut Vec<ChangedFile>,
+    checkpoint: &mut Option<Checkpoint>,
+    file: ChangedFile,
+) {
+    if let Some(checkpoint) = checkpoint
+        && let Err(e) = checkpoint.record(&file)
+    {
+        warn!(repo = %file.repo, file = %file.file, error = %e, "failed to save the checkpoint");
+    }
+    changed_files.push(file);
+}
+
+/// Reverts the canary's `commits` (oldest first) after a failed
+/// verification and adds the reverts to the run's `audit_entries`.  Commits
+/// of an earlier run are looked up in the audit log.  Returns `{ "sha" }` or
+/// `{ "error" }`.
+fn revert_canary(
+    config: &UpdateConfig,
+    base_dir: &Path,
+    run_id: &str,
+    canary: &str,
+    commits: &[String],
+    audit_entries: &mut Vec<AuditEntry>,
+) -> Value {
+    let Some(spec) = config.repos.iter().find(|spec| spec.repo == canary) else {
+        return json!({ "error": format!("{canary} is not a managed repo") });
+    };
+    let shas: Vec<&str> = commits.iter().map(String::as_str).collect();
+    match git::revert_commits(&base_dir.join(&spec.local), &shas, &spec.push_target()) {
+        Ok(sha) => {
+            info!(repo = canary, sha = %sha, "canary reverted");
+            let is_canary_commit = |e: &AuditEntry| {
+                e.repo == canary && e.sha.as_ref().is_some_and(|sha| commits.contains(sha))
+            };
+            let mut reverted: Vec<AuditEntry> = audit_entries
+                .iter()
+                .filter(|e| is_canary_commit(e))
+                .cloned()
+                .collect();
+            match AuditLog::from_env().read() {
+                Ok(logged) => reverted.extend(logged.into_iter().filter(is_canary_commit)),
+                Err(e) => warn!(error = %e, "failed to read the audit log"),
+            }
+            audit_entries.extend(revert_entries(&reverted, run_id, &sha));
+            json!({ "sha": sha })
+        }
+        Err(e) => {
+            error!(repo = canary, error = %e, "reverting the canary failed");
+            json!({ "error": format!("{e:#}") })
+        }
+    }
+}
+
+/// Audit-log entries for every bump applied by `update`.
+fn audit_entries_for(
+    run_id: &str,
+    update: &PendingUpdate,
+    sha: Option<&str>,
+    pr_url: Option<&str>,
+    strategy: &str,
+) -> Vec<AuditEntry> {
+    let timestamp = now_secs();
+    update
+        .versions
+        .iter()
+        .map(|v| AuditEntry {
+            timestamp,
+            run_id: run_id.to_string(),
+            repo: update.repo.clone(),
+            file: update.file_path.clone(),
+            crate_name: v.crate_name.clone(),
+            from: v.current.clone(),
+            to: v.latest.clone(),
+            sha: sha.map(str::to_string),
+            pr_url: pr_url.map(str::to_string),
+            strategy: strategy.to_string(),
+        })
+        .collect()
+}
+
+/// Opens one grouped PR with `updates` for `repo` and returns its `committed`
+/// summary entry.  A `draft` PR is flagged for review in its body.
+#[allow(clippy::too_many_arguments)]
+fn open_update_pr(
+    org: &str,
+    repo: &str,
+    run_id: &str,
+    title: &str,
+    analysis: &str,
+    links: &BTreeMap<String, CrateLinks>,
+    updates: &[&PendingUpdate],
+    draft: bool,
+    single_commit: Option<&str>,
+) -> anyhow::Result<CommitRecord> {
+    let files: Vec<PrFile> = updates
+        .iter()
+        .map(|u| PrFile {
+            path: u.file_path.clone(),
+            original: u.original_content.clone(),
+            content: u.patched_content.clone(),
+            commit_message: u.commit_message.clone(),
+        })
+        .collect();
+    let versions: Vec<VersionReport> = updates.iter().flat_map(|u| u.versions.clone()).collect();
+    let mut notes: Vec<MigrationNote> = Vec::new();
+    for note in updates.iter().flat_map(|u| &u.migration_notes) {
+        if !notes.contains(note) {
+            notes.push(note.clone());
+        }
+    }
+    let mut upstream: Vec<UpstreamChanges> = Vec::new();
+    for changes in updates.iter().flat_map(|u| &u.upstream) {
+        if !upstream.contains(changes) {
+            upstream.push(changes.clone());
+        }
+    }
+    let vulnerabilities: Vec<OsvFinding> = updates
+        .iter()
+        .flat_map(|u| u.vulnerabilities.clone())
+        .collect();
+    let mut body = render_pr_body(
+        &versions,
+        &notes,
+        &upstream,
+        &vulnerabilities,
+        &files,
+        analysis,
+        links,
+    );
+    let migrated: Vec<&str> = updates
+        .iter()
+        .filter(|u| u.requires_pr)
+        .map(|u| u.file_path.as_str())
+        .collect();
+    if !migrated.is_empty() {
+        body.insert_str(
+            0,
+            &format!(
+                "> [!CAUTION]\n> `cargo check` failed after the bump; {} rewritten by \
+                 LLM-assisted migration.  Review the source changes carefully.\n\n",
+                migrated
+                    .iter()
+                    .map(|f| format!("`{f}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    + if migrated.len() == 1 { " was" } else { " were" }
+            ),
+        );
+    }
+    if draft {
+        body.insert_str(
+            0,
+            "> [!WARNING]\n> Draft: the changelog analysis flagged a major version bump \
+             as risky.  Review before marking ready.\n\n",
+        );
+    }
+
+    let pr = open_grouped_pr(
+        org,
+        repo,
+        &branch_name(run_id),
+        title,
+        &body,
+        &files,
+        draft,
+        single_commit,
+    )?;
+
+    // A failed review leaves the PR as it is.
+    let comments = version_comments(org, links, &files, &versions);
+    let annotations = match post_review(org, repo, pr.number, &comments) {
+        _ if comments.is_empty() => json!(0),
+        Ok(()) => json!(comments.len()),
+        Err(e) => {
+            warn!(repo, number = pr.number, error = %e, "annotating the PR failed");
+            json!({ "error": format!("{e:#}") })
+        }
+    };
+    Ok(CommitRecord {
+        repo: repo.to_string(),
+        files: files.iter().map(|f| f.path.clone()).collect(),
+        strategy: Some("PullRequest".to_string()),
+        pr_number: Some(pr.number),
+        pr_url: Some(pr.url),
+        branch: Some(pr.branch),
+        draft: Some(draft),
+        annotations: Some(annotations),
+        diff_stats: Some(CommitDiffStats::Files(
+            files
+                .iter()
+                .map(|f| {
+                    let stats = diff_stats(f.original.as_bytes(), f.content.as_bytes());
+                    (f.path.clone(), stats)
+                })
+                .collect(),
+        )),
+        bumps: versions,
+        ..Default::default()
+    })
+}
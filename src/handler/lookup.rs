@@ -0,0 +1,481 @@
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::backend::Backends;
+use crate::metadata::{CrateLinks, LinkCache};
+use crate::ownership::{OwnershipChange, OwnershipState};
+use crate::plan::UpdatePlan;
+use crate::state::{VersionCache, now_secs};
+use crate::timing::Timings;
+use crate::versions::needs_update;
+use crate::{ownership, publish_lag, publish_wait, registry, toolchain};
+
+use super::{PackageManifest, Run, load_checksum_pins, refresh_versions};
+
+// ─── Phase 1: Check crates.io ─────────────────────────────────────────────────
+
+/// What Phase 1 learned about the tracked crates and packages.
+#[derive(Debug, Default)]
+pub(super) struct Versions<'a> {
+    /// Latest version of every tracked crate, of `rust` (the stable
+    /// toolchain) and of the tracked packages of other ecosystems.
+    pub latest: HashMap<&'a str, String>,
+    /// Where each latest version came from and how old it is.
+    pub freshness: serde_json::Map<String, Value>,
+    /// How each registry lookup went.
+    pub lookups: serde_json::Map<String, Value>,
+    pub checksum_failures: Vec<Value>,
+    pub publish_waits: Vec<Value>,
+    pub crate_links: BTreeMap<String, CrateLinks>,
+    /// Crates whose owners or publishers changed: their updates are held.
+    pub ownership_changes: Vec<OwnershipChange>,
+    pub publish_stuck: Vec<publish_lag::StuckPublish>,
+}
+
+impl<'a> Versions<'a> {
+    /// The versions `plan` was made with, for the run applying it.
+    pub fn planned(plan: &'a UpdatePlan) -> Self {
+        Self {
+            latest: plan
+                .versions
+                .iter()
+                .map(|(name, version)| (name.as_str(), version.clone()))
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Phase 1: resolves the latest versions (from the version cache offline or
+/// while fresh), then the links, owners and publish lag of the tracked
+/// crates.
+pub(super) async fn check_versions<'a>(run: &Run<'a>, timings: &mut Timings) -> Versions<'a> {
+    let Run {
+        ctx,
+        config,
+        offline,
+        ref org,
+        ..
+    } = *run;
+    let Backends {
+        registry: registry_client,
+        king,
+        ..
+    } = run.backends;
+    info!(offline, "Phase 1: checking crates.io for latest versions");
+    let mut latest_versions: HashMap<&str, String> = HashMap::new();
+    let mut version_freshness = serde_json::Map::new();
+    let mut version_lookups = serde_json::Map::new();
+    let cache_path = VersionCache::default_path();
+    let mut version_cache = VersionCache::load(&cache_path).unwrap_or_else(|e| {
+        warn!(error = %e, "version cache unreadable — starting empty");
+        VersionCache::new(&cache_path)
+    });
+    let now = now_secs();
+    let version_ttl = if refresh_versions(&ctx.metadata) {
+        0
+    } else {
+        VersionCache::ttl_from_env()
+    };
+    let checksum_pins = load_checksum_pins();
+    let mut checksum_failures: Vec<Value> = Vec::new();
+    // A run started for a release must not patch to a version cargo
+    // cannot resolve yet.
+    let released = publish_wait::released_versions(&ctx.metadata);
+    let publish_wait = publish_wait::PublishWait::from_env();
+    let mut publish_waits: Vec<Value> = Vec::new();
+
+    for crate_name in config.all_tracked_crates() {
+        if offline {
+            match version_cache.get(crate_name) {
+                Some(cached) => {
+                    info!(crate = crate_name, latest = %cached.version, fetched_at = cached.fetched_at, "using cached version");
+                    version_freshness.insert(
+                        crate_name.to_string(),
+                        json!({
+                            "source": "cache",
+                            "fetched_at": cached.fetched_at,
+                            "age_secs": now.saturating_sub(cached.fetched_at),
+                        }),
+                    );
+                    latest_versions.insert(crate_name, cached.version.clone());
+                }
+                None => {
+                    warn!(
+                        crate = crate_name,
+                        "no cached version available offline — skipping"
+                    );
+                }
+            }
+            continue;
+        }
+
+        // A version resolved moments ago (e.g. by the check that preceded
+        // this apply) is reused, so both act on the same snapshot.
+        if let Some(cached) = version_cache.fresh(crate_name, now, version_ttl) {
+            info!(crate = crate_name, latest = %cached.version, fetched_at = cached.fetched_at, "reusing recently resolved version");
+            version_lookups.insert(
+                crate_name.to_string(),
+                json!({ "outcome": "cached", "attempts": 0 }),
+            );
+            version_freshness.insert(
+                crate_name.to_string(),
+                json!({
+                    "source": "cache",
+                    "fetched_at": cached.fetched_at,
+                    "age_secs": now.saturating_sub(cached.fetched_at),
+                }),
+            );
+            latest_versions.insert(crate_name, cached.version.clone());
+            continue;
+        }
+
+        // Registries are tried in order; the first that answers is used
+        // for the checksum check too.
+        let lookup_started = Instant::now();
+        let mut fallbacks: Vec<Value> = Vec::new();
+        let mut answered = None;
+        let mut last_error = None;
+        for (name, registry) in config.registries.for_crate(crate_name) {
+            let lookup = registry_client.latest_version(&registry, crate_name).await;
+            match lookup.result {
+                Ok(latest) => {
+                    answered = Some((name, registry, latest, lookup.attempts));
+                    break;
+                }
+                Err(e) => {
+                    warn!(crate = crate_name, registry = %name, error = %e, "registry lookup failed — trying the next registry");
+                    fallbacks.push(json!({
+                        "registry": name,
+                        "attempts": lookup.attempts,
+                        "error": format!("{e:#}"),
+                    }));
+                    last_error = Some(e);
+                }
+            }
+        }
+        timings.crates_io(crate_name, lookup_started.elapsed());
+        version_lookups.insert(
+            crate_name.to_string(),
+            match &answered {
+                Some((name, _, _, attempts)) => json!({
+                    "outcome": if !fallbacks.is_empty() {
+                        "fallback"
+                    } else if *attempts == 1 {
+                        "ok"
+                    } else {
+                        "retried"
+                    },
+                    "attempts": attempts,
+                    "registry": name,
+                    "fallbacks": fallbacks,
+                }),
+                None => json!({
+                    "outcome": "gave_up",
+                    "attempts": fallbacks.iter().filter_map(|f| f["attempts"].as_u64()).sum::<u64>(),
+                    "error": last_error.map(|e| format!("{e:#}")).unwrap_or_else(|| "no registry configured".to_string()),
+                    "fallbacks": fallbacks,
+                }),
+            },
+        );
+        match answered {
+            Some((registry_name, registry, mut latest, _)) => {
+                // The registry API may not list the announced release yet.
+                if let Some(announced) = released.get(crate_name)
+                    && needs_update(&latest, announced)
+                {
+                    latest = announced.clone();
+                }
+                info!(crate = crate_name, latest = %latest, registry = %registry_name, "fetched latest version");
+                if let Some(wait) = &publish_wait {
+                    match publish_wait::wait_for_version(
+                        &**registry_client,
+                        &registry,
+                        crate_name,
+                        &latest,
+                        wait,
+                    )
+                    .await
+                    {
+                        Ok(waited) if waited.polls > 1 => publish_waits.push(json!({
+                            "crate": crate_name,
+                            "version": latest,
+                            "polls": waited.polls,
+                            "waited_secs": waited.elapsed.as_secs(),
+                            "resolvable": true,
+                        })),
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!(crate = crate_name, latest = %latest, error = %e, "version not resolvable yet — not propagating");
+                            publish_waits.push(json!({
+                                "crate": crate_name,
+                                "version": latest,
+                                "resolvable": false,
+                                "error": format!("{e:#}"),
+                            }));
+                            continue;
+                        }
+                    }
+                }
+                // Only checksum-verified versions are recommended or cached.
+                match registry_client
+                    .verify_checksum(&registry, crate_name, &latest, &checksum_pins)
+                    .await
+                {
+                    Ok(sum) => debug!(crate = crate_name, sha256 = %sum, "checksum verified"),
+                    Err(e) => {
+                        warn!(crate = crate_name, latest = %latest, error = %e, "checksum verification failed — not propagating");
+                        checksum_failures.push(json!({
+                            "crate": crate_name,
+                            "version": latest,
+                            "error": e.to_string(),
+                        }));
+                        continue;
+                    }
+                }
+                version_cache.insert(crate_name, &latest, now);
+                let source = if registry_name == registry::CRATES_IO {
+                    "crates.io"
+                } else {
+                    registry_name.as_str()
+                };
+                version_freshness.insert(
+                    crate_name.to_string(),
+                    json!({ "source": source, "fetched_at": now, "age_secs": 0 }),
+                );
+                latest_versions.insert(crate_name, latest);
+            }
+            None => {
+                warn!(crate = crate_name, "every registry failed — skipping");
+            }
+        }
+    }
+
+    // The current stable Rust, for toolchain pins in CI workflows.  It is
+    // tracked like a crate named `rust`.
+    let toolchain_lookup = if offline {
+        version_cache
+            .get(toolchain::RUST)
+            .map(|cached| (cached.version.clone(), "cache", cached.fetched_at))
+    } else if let Some(cached) = version_cache.fresh(toolchain::RUST, now, version_ttl) {
+        Some((cached.version.clone(), "cache", cached.fetched_at))
+    } else {
+        match registry_client.latest_toolchain().await {
+            Ok(latest) => {
+                version_cache.insert(toolchain::RUST, &latest, now);
+                Some((latest, "static.rust-lang.org", now))
+            }
+            Err(e) => {
+                warn!(error = %e, "stable Rust lookup failed — leaving toolchain pins alone");
+                None
+            }
+        }
+    };
+    if let Some((latest, source, fetched_at)) = toolchain_lookup {
+        info!(latest = %latest, source, "latest stable Rust");
+        version_freshness.insert(
+            toolchain::RUST.to_string(),
+            json!({
+                "source": source,
+                "fetched_at": fetched_at,
+                "age_secs": now.saturating_sub(fetched_at),
+            }),
+        );
+        latest_versions.insert(toolchain::RUST, latest);
+    }
+
+    // Tracked packages of other ecosystems, for repos listing manifests
+    // of their kind.
+    for kind in PackageManifest::ALL {
+        if !config.repos.iter().any(|spec| !kind.files(spec).is_empty()) {
+            continue;
+        }
+        for package in kind.packages(config) {
+            let lookup = if offline {
+                version_cache
+                    .get(package)
+                    .map(|cached| (cached.version.clone(), "cache", cached.fetched_at))
+            } else if let Some(cached) = version_cache.fresh(package, now, version_ttl) {
+                Some((cached.version.clone(), "cache", cached.fetched_at))
+            } else {
+                match kind.latest_version(&**registry_client, package).await {
+                    Ok(latest) => {
+                        version_cache.insert(package, &latest, now);
+                        Some((latest, kind.source(), now))
+                    }
+                    Err(e) => {
+                        warn!(ecosystem = kind.ecosystem(), package = %package, error = %e, "package lookup failed — leaving its requirements alone");
+                        None
+                    }
+                }
+            };
+            if let Some((latest, source, fetched_at)) = lookup {
+                info!(ecosystem = kind.ecosystem(), package = %package, latest = %latest, source, "latest package version");
+                version_freshness.insert(
+                    package.clone(),
+                    json!({
+                        "source": source,
+                        "fetched_at": fetched_at,
+                        "age_secs": now.saturating_sub(fetched_at),
+                    }),
+                );
+                latest_versions.insert(package, latest);
+            }
+        }
+    }
+
+    if !offline && let Err(e) = version_cache.save() {
+        warn!(error = %e, "failed to persist version cache");
+    }
+
+    // ── Repository / homepage / documentation links of tracked crates ──
+    let mut link_cache = LinkCache::load(&LinkCache::default_path());
+    let links_ttl = LinkCache::ttl_from_env();
+    let mut crate_links: BTreeMap<String, CrateLinks> = BTreeMap::new();
+    for crate_name in config.all_tracked_crates() {
+        if !latest_versions.contains_key(crate_name) {
+            continue;
+        }
+        let cached = if offline {
+            link_cache.get(crate_name)
+        } else {
+            link_cache.fresh(crate_name, now, links_ttl)
+        };
+        if let Some(cached) = cached {
+            crate_links.insert(crate_name.to_string(), cached.links.clone());
+            continue;
+        }
+        let Some((_, registry)) = config.registries.for_crate(crate_name).into_iter().next() else {
+            continue;
+        };
+        match registry_client.crate_links(&registry, crate_name).await {
+            Ok(links) => {
+                link_cache.insert(crate_name, links.clone(), now);
+                crate_links.insert(crate_name.to_string(), links);
+            }
+            Err(e) => {
+                debug!(crate = crate_name, error = %e, "crate links unavailable");
+                if let Some(stale) = link_cache.get(crate_name) {
+                    crate_links.insert(crate_name.to_string(), stale.links.clone());
+                }
+            }
+        }
+    }
+    if !offline && let Err(e) = link_cache.save() {
+        warn!(error = %e, "failed to persist crate-link cache");
+    }
+
+    // ── Owners and publishers: hold crates that changed hands ──
+    let mut ownership_changes: Vec<OwnershipChange> = Vec::new();
+    if ownership::enabled() {
+        let owners_path = OwnershipState::default_path();
+        let mut owners_state = OwnershipState::load(&owners_path).unwrap_or_else(|e| {
+            warn!(error = %e, "ownership state unreadable — recording new baselines");
+            OwnershipState::new(&owners_path)
+        });
+        if !offline {
+            for crate_name in config.all_tracked_crates() {
+                if !latest_versions.contains_key(crate_name) {
+                    continue;
+                }
+                let Some((_, registry)) =
+                    config.registries.for_crate(crate_name).into_iter().next()
+                else {
+                    continue;
+                };
+                match registry_client.owners(&registry, crate_name).await {
+                    Ok(seen) => owners_state.observe(crate_name, seen, now),
+                    Err(e) => {
+                        debug!(crate = crate_name, error = %e, "owner lookup failed — ownership unchecked")
+                    }
+                }
+            }
+        }
+        let fresh: Vec<OwnershipChange> = owners_state
+            .pending()
+            .into_iter()
+            .filter(|c| !c.notified)
+            .cloned()
+            .collect();
+        for change in &fresh {
+            warn!(crate = %change.crate_name, added = ?change.added_owners, removed = ?change.removed_owners, publishers = ?change.new_publishers, "ownership changed — holding the crate's updates");
+        }
+        if !fresh.is_empty() && !offline {
+            match ownership::alert_king(&**king, &ctx.run_id, &fresh).await {
+                Ok(()) => {
+                    for change in &fresh {
+                        owners_state.mark_notified(&change.crate_name);
+                    }
+                }
+                Err(e) => warn!(error = %e, "alerting the king about ownership changes failed"),
+            }
+        }
+        ownership_changes = owners_state.pending().into_iter().cloned().collect();
+        if !offline && let Err(e) = owners_state.save() {
+            warn!(error = %e, "failed to persist ownership state");
+        }
+    }
+
+    // ── Registry lag: tagged upstream but never published ──
+    let mut publish_stuck: Vec<publish_lag::StuckPublish> = Vec::new();
+    if let Some(threshold) = publish_lag::threshold_from_env().filter(|_| !offline) {
+        let alerted_path = publish_lag::AlertedVersions::default_path();
+        let mut alerted = publish_lag::AlertedVersions::load(&alerted_path);
+        for crate_name in config.all_tracked_crates() {
+            let Some(latest) = latest_versions.get(crate_name) else {
+                continue;
+            };
+            let repo = config.crate_repo(crate_name);
+            match publish_lag::check(org, repo, crate_name, latest, threshold, now) {
+                Ok(Some(mut stuck)) => {
+                    stuck.source_url = crate_links
+                        .get(crate_name)
+                        .and_then(CrateLinks::source_url)
+                        .map(str::to_string);
+                    warn!(crate = crate_name, repo, tag = %stuck.tag, registry = %latest, lag_secs = stuck.lag_secs, "publish appears stuck");
+                    publish_stuck.push(stuck);
+                }
+                Ok(None) => alerted.clear(crate_name),
+                Err(e) => {
+                    debug!(crate = crate_name, repo, error = %e, "tag lookup failed — lag unknown")
+                }
+            }
+        }
+        let new: Vec<usize> = (0..publish_stuck.len())
+            .filter(|&i| alerted.record(&publish_stuck[i]))
+            .collect();
+        if !new.is_empty() {
+            let fresh: Vec<_> = new.iter().map(|&i| publish_stuck[i].clone()).collect();
+            match publish_lag::alert_king(&**king, &ctx.run_id, &fresh).await {
+                Ok(()) => {
+                    for i in new {
+                        publish_stuck[i].notified = true;
+                    }
+                }
+                Err(e) => {
+                    warn!(error = %e, "alerting the king about stuck publishes failed");
+                    for stuck in &fresh {
+                        alerted.clear(&stuck.crate_name);
+                    }
+                }
+            }
+        }
+        if let Err(e) = alerted.save() {
+            warn!(error = %e, "failed to persist publish-lag state");
+        }
+    }
+    timings.end_phase("check_versions");
+
+    Versions {
+        latest: latest_versions,
+        freshness: version_freshness,
+        lookups: version_lookups,
+        checksum_failures,
+        publish_waits,
+        crate_links,
+        ownership_changes,
+        publish_stuck,
+    }
+}
@@ -0,0 +1,688 @@
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+use crate::audit_log::AuditLog;
+use crate::badges::{Badge, BadgeStore};
+use crate::config::{RepoConfig, UpdateConfig};
+use crate::fleet_tag::render_name;
+use crate::llm::UsageReport;
+use crate::notify::{Notification, NotifiedFile, Notifiers};
+use crate::provenance::PublishTarget;
+use crate::runs::{RunHistory, RunRecord, VersionMatrix};
+use crate::state::{now_secs, today_utc};
+use crate::summary::{CommitRecord, HeldUpdate, HoldReason, RepoResult, RunSummary};
+use crate::timing::Timings;
+use crate::versions::{current_dep_version, needs_update};
+use crate::{
+    audit_log, badges, checkout, checks, fleet_audit, fleet_tag, preflight, provenance, release,
+    sbom, tokens,
+};
+
+use super::analysis::{Analysis, Verification};
+use super::delivery::Delivered;
+use super::lookup::Versions;
+use super::scan::Scan;
+use super::{Gates, Run};
+
+// ─── Record and report ────────────────────────────────────────────────────────
+
+/// Records what the run did (audit log, provenance, SBOMs, run history,
+/// badges, checks, notifications, fleet tag and release), asks king to
+/// sync its config (Phase 5) and returns the run summary (Phase 6).
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn report(
+    run: &Run<'_>,
+    versions: Versions<'_>,
+    gates: &Gates,
+    scan: Scan<'_>,
+    analysis: Analysis,
+    verification: Verification,
+    delivered: Delivered,
+    environment: preflight::Environment,
+    checkouts: Vec<checkout::Bootstrap>,
+    mut timings: Timings,
+) -> anyhow::Result<Value> {
+    let Run {
+        ctx,
+        config,
+        targeted,
+        dry_run,
+        offline,
+        commit_mode,
+        granularity: run_granularity,
+        ref llm,
+        ref http,
+        ref org,
+        ref base_dir,
+        ref applying,
+        ref resumed_from,
+        ref retry_queue,
+        ..
+    } = *run;
+    let king = &run.backends.king;
+    let auto_apply = gates.auto_apply;
+    let Versions {
+        latest: latest_versions,
+        freshness: version_freshness,
+        lookups: version_lookups,
+        checksum_failures,
+        publish_waits,
+        crate_links,
+        ownership_changes,
+        publish_stuck,
+    } = versions;
+    let Scan {
+        pending_updates,
+        rejected,
+        unverified_manifests,
+        audits,
+        held_for_review,
+        workspace_conflicts,
+        patch_states,
+        workflow_patterns,
+        repo_tracked,
+        osv_report,
+        skipped_by_policy,
+        frozen_repos,
+        suppressed_by_repo,
+        release_bumps,
+        schema_sync,
+        file_sync,
+        file_drift,
+        template_drift,
+        mut workflow_sync,
+        workflow_failures,
+        license,
+        license_drift,
+        full_scan,
+        unchanged_repos,
+        ..
+    } = scan;
+    let Analysis {
+        migration_notes,
+        upstream_changes,
+        llm_skipped,
+        summary: analysis_summary,
+        risk_assessment,
+    } = analysis;
+    let Verification {
+        migrations,
+        sandboxes,
+        new_dependencies,
+    } = verification;
+    let Delivered {
+        stored_plan,
+        committed,
+        errors,
+        applied,
+        audit_entries,
+        changed_files,
+        approval_question,
+        rollout_tiers,
+        canary: canary_result,
+        lease: lease_status,
+        retried,
+        retried_files,
+        queued,
+        template_prs,
+    } = delivered;
+
+    // ── Audit log of applied changes ────────────────────────────────────
+    let audit_log = AuditLog::from_env();
+    if let Err(e) = audit_log.append(&audit_entries) {
+        error!(error = %e, "failed to write audit log");
+    }
+    let audit_log_shipped = if audit_log::ship_to_king_enabled() && !audit_entries.is_empty() {
+        match audit_log::ship_to_king(&**king, &audit_entries).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(error = %e, "shipping audit log entries to king failed");
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    // ── Provenance of the update wave ───────────────────────────────────
+    let mut provenance_info = Value::Null;
+    if !changed_files.is_empty() {
+        let input_versions = latest_versions
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.clone()))
+            .collect();
+        let key = match provenance::ProvenanceKey::from_env() {
+            Ok(Some(key)) => Some(key),
+            Ok(None) => {
+                warn!("PROVENANCE_KEY not set — provenance statement is unsigned");
+                None
+            }
+            Err(e) => {
+                warn!(error = %e, "PROVENANCE_KEY unusable — provenance statement is unsigned");
+                None
+            }
+        };
+        let statement = provenance::statement(&ctx.run_id, org, &input_versions, &changed_files);
+        let envelope = provenance::envelope(&statement, key.as_ref());
+        if let Some(key) = &key
+            && let Err(e) = provenance::store_public_key(key)
+        {
+            warn!(error = %e, "failed to store the provenance public key");
+        }
+        let mut published: Vec<Value> = Vec::new();
+        for target in PublishTarget::from_env() {
+            let outcome = match target {
+                PublishTarget::King => provenance::publish_to_king(&**king, &envelope)
+                    .await
+                    .map(|()| king.url("/admin/provenance")),
+                PublishTarget::Gist => provenance::publish_gist(&ctx.run_id, &envelope),
+            };
+            match outcome {
+                Ok(location) => published.push(json!(location)),
+                Err(e) => warn!(target = ?target, error = %e, "publishing provenance failed"),
+            }
+        }
+        let path = provenance::store(&ctx.run_id, &envelope)
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|e| {
+                error!(error = %e, "failed to store provenance statement");
+                String::new()
+            });
+        provenance_info = json!({
+            "path": path,
+            "signed": key.is_some(),
+            "key_id": key.as_ref().map(|k| k.id.as_str()),
+            "public_key": key.as_ref().map(provenance::ProvenanceKey::public_key),
+            "diffs_sha256": statement["predicate"]["diffs_sha256"],
+            "published": published,
+        });
+    }
+
+    // ── SBOM inventory ──────────────────────────────────────────────────
+    info!("generating per-repo SBOMs");
+    let mut sboms: Vec<Value> = Vec::new();
+    for spec in &config.repos {
+        let Some(cargo_file) = spec.cargo_files.first() else {
+            continue;
+        };
+        let repo_base = base_dir.join(&spec.local);
+        let manifest_path = repo_base.join(cargo_file);
+        let manifest = match applied.get(&(spec.repo.clone(), cargo_file.clone())) {
+            Some(content) => content.to_string(),
+            None => match std::fs::read_to_string(&manifest_path) {
+                Ok(c) => c,
+                Err(_) => continue,
+            },
+        };
+        let lockfile = sbom::find_lockfile(&repo_base, &manifest_path)
+            .and_then(|p| std::fs::read_to_string(p).ok());
+        match sbom::generate(&spec.repo, &manifest, lockfile.as_deref(), &ctx.run_id)
+            .and_then(|bom| sbom::store(&spec.repo, &bom).map(|path| (bom, path)))
+        {
+            Ok((bom, path)) => sboms.push(json!({
+                "repo": spec.repo,
+                "path": path.display().to_string(),
+                "components": bom["components"].as_array().map_or(0, Vec::len),
+                "from_lockfile": lockfile.is_some(),
+            })),
+            Err(e) => warn!(repo = %spec.repo, error = %e, "SBOM generation failed"),
+        }
+    }
+
+    // ── Run history (fleet version matrix) ──────────────────────────────
+    let mut matrix = VersionMatrix::new();
+    for spec in &config.repos {
+        let repo_base = base_dir.join(&spec.local);
+        let row = matrix.entry(spec.repo.clone()).or_default();
+        for cargo_file in &spec.cargo_files {
+            let content = match applied.get(&(spec.repo.clone(), cargo_file.clone())) {
+                Some(content) => content.to_string(),
+                None => match std::fs::read_to_string(repo_base.join(cargo_file)) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                },
+            };
+            for crate_name in spec.tracked_crates(&config.tracked_crates) {
+                let Some(version) = current_dep_version(&content, crate_name) else {
+                    continue;
+                };
+                // The oldest requirement is what still needs updating.
+                match row.get(crate_name) {
+                    Some(seen) if !needs_update(&version, seen) => {}
+                    _ => {
+                        row.insert(crate_name.to_string(), version);
+                    }
+                }
+            }
+        }
+    }
+    let latest: BTreeMap<String, String> = latest_versions
+        .iter()
+        .map(|(name, version)| (name.to_string(), version.clone()))
+        .collect();
+
+    // ── Fleet consistency ───────────────────────────────────────────────
+    // Partial rollouts, where part of the fleet moved to a new version and
+    // the rest did not, are flagged on their own.
+    let open_prs: Vec<Value> = committed
+        .iter()
+        .filter(|c| c.pr_url.is_some())
+        .map(|c| json!(c))
+        .collect();
+    let held: Vec<Value> = held_for_review.iter().map(|h| json!(h)).collect();
+    let unchanged: Vec<Value> = unchanged_repos
+        .iter()
+        .map(|repo| json!({ "repo": repo }))
+        .collect();
+    let fleet_crates: Vec<&str> = config.tracked_crates.iter().map(String::as_str).collect();
+    let fleet_divergence = fleet_audit::audit(
+        &matrix,
+        &fleet_crates,
+        &[
+            ("errors", &errors),
+            ("rejected", &rejected),
+            ("checksum_failures", &checksum_failures),
+            ("held_for_review", &held),
+            ("workspace_conflicts", &workspace_conflicts),
+            ("frozen", &frozen_repos),
+            ("skipped_by_policy", &skipped_by_policy),
+            ("suppressed_by_repo", &suppressed_by_repo),
+            ("pr_open", &open_prs),
+            ("unchanged_repos", &unchanged),
+        ],
+    );
+    for divergence in &fleet_divergence {
+        warn!(
+            crate = %divergence.crate_name,
+            newest = %divergence.newest,
+            lagging = ?divergence.lagging.iter().map(|l| l.repo.as_str()).collect::<Vec<_>>(),
+            "fleet is inconsistent"
+        );
+    }
+
+    // ── Per-repo status badges ──────────────────────────────────────────
+    let badge_store = BadgeStore::default_location();
+    let mut badges: Vec<Badge> = Vec::new();
+    for (repo, requirements) in &matrix {
+        let committed_now = changed_files
+            .iter()
+            .any(|f| f.repo == *repo && f.commit.is_some());
+        let last_updated = if committed_now && !dry_run {
+            Some(now_secs())
+        } else {
+            badge_store.load(repo).and_then(|b| b.last_updated)
+        };
+        let badge = Badge::build(repo, requirements, &latest, last_updated, &ctx.run_id);
+        if let Err(e) = badge_store.save(&badge) {
+            warn!(repo = %repo, error = %e, "failed to store badge");
+        }
+        badges.push(badge);
+    }
+    let mut badges_published: Vec<Value> = Vec::new();
+    if !dry_run && !offline {
+        for target in PublishTarget::from_env_var("BADGE_PUBLISH") {
+            let outcome = match target {
+                PublishTarget::King => badges::publish_to_king(&**king, &ctx.run_id, &badges)
+                    .await
+                    .map(|()| king.url("/admin/badges")),
+                PublishTarget::Gist => badges::publish_gist(&badges),
+            };
+            match outcome {
+                Ok(location) => badges_published.push(json!(location)),
+                Err(e) => warn!(target = ?target, error = %e, "publishing badges failed"),
+            }
+        }
+    }
+
+    // ── Verification checks on the run's commits and PRs ────────────────
+    let mut checks: Vec<checks::CheckResult> = Vec::new();
+    if !dry_run && !offline && config.sandbox.publish_checks {
+        for entry in &committed {
+            let repo = entry.repo.as_str();
+            let Some(verified) = entry.verified else {
+                continue;
+            };
+            let Some(rev) = entry.sha.as_deref().or(entry.branch.as_deref()) else {
+                continue;
+            };
+            let commands = sandboxes
+                .iter()
+                .find(|s| s["repo"] == repo)
+                .and_then(|s| s["commands"].as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            checks.push(checks::publish(org, repo, rev, verified, commands));
+        }
+    }
+
+    // ── Notifications: the delivered changes, with diff snippets ────────
+    let notifiers = Notifiers::from_env();
+    let mut notifications: Vec<Value> = Vec::new();
+    if !dry_run && !offline && !notifiers.is_empty() {
+        let files: Vec<NotifiedFile> = committed
+            .iter()
+            .filter(|entry| !entry.reverted)
+            .flat_map(|entry| {
+                let link = entry.pr_url.clone().or_else(|| {
+                    entry
+                        .sha
+                        .as_ref()
+                        .map(|sha| format!("https://github.com/{org}/{}/commit/{sha}", entry.repo))
+                });
+                let pending = &pending_updates;
+                entry
+                    .file
+                    .iter()
+                    .chain(&entry.files)
+                    .filter_map(move |file| {
+                        let update = pending
+                            .iter()
+                            .find(|u| u.repo == entry.repo && u.file_path == *file)?;
+                        Some(NotifiedFile::new(
+                            &entry.repo,
+                            file,
+                            &update.original_content,
+                            &update.patched_content,
+                            link.clone(),
+                        ))
+                    })
+            })
+            .collect();
+        if !files.is_empty() {
+            let notification = Notification {
+                run_id: ctx.run_id.clone(),
+                files,
+            };
+            notifications = notifiers.send(http, &notification).await;
+        }
+    }
+
+    // ── Fleet tag: mark the fleet state after a green wave ──────────────
+    let mut fleet_tag_info = Value::Null;
+    if let Some(template) = config.fleet_tag.template() {
+        let blocker = if dry_run {
+            Some("dry_run")
+        } else if offline {
+            Some("offline")
+        } else {
+            wave_blocker(&committed, &errors, &held_for_review)
+        };
+        let ops_repo = config.fleet_tag.ops_repo();
+        fleet_tag_info = match blocker {
+            Some(reason) => {
+                info!(reason, "fleet tag skipped");
+                json!({ "skipped": reason })
+            }
+            None => match render_name(&template, &ctx.run_id, &today_utc(), &latest) {
+                Ok(name) => {
+                    let targets = fleet_tag_targets(config, base_dir, &committed);
+                    let commits: BTreeMap<String, String> = targets
+                        .iter()
+                        .map(|t| (t.repo.clone(), t.sha.clone()))
+                        .collect();
+                    let message =
+                        fleet_tag::message(&name, &ctx.run_id, &latest, &matrix, &commits);
+                    let tags = match &ops_repo {
+                        Some(ops_repo) => {
+                            vec![fleet_tag::tag_ops_repo(org, ops_repo, &name, &message)]
+                        }
+                        None => fleet_tag::tag_repos(org, &name, &message, &targets),
+                    };
+                    json!({ "name": name, "ops_repo": ops_repo, "tags": tags })
+                }
+                Err(e) => {
+                    warn!(error = %e, "fleet tag name cannot be rendered");
+                    json!({ "error": format!("{e:#}") })
+                }
+            },
+        };
+    }
+
+    // ── GitHub release summarizing the wave ─────────────────────────────
+    let mut release_info = Value::Null;
+    if let Some(release_repo) = config.release.repo()
+        && !dry_run
+        && !offline
+        && !committed.is_empty()
+    {
+        let report = release::render_report(&release::Wave {
+            org,
+            run_id: &ctx.run_id,
+            latest: &latest,
+            committed: &committed,
+            held_for_review: &held_for_review,
+            errors: &errors,
+            new_dependencies: &new_dependencies,
+            fleet_tag: fleet_tag_info["tags"]
+                .as_array()
+                .is_some_and(|tags| tags.iter().any(|t| t["error"].is_null()))
+                .then(|| fleet_tag_info["name"].as_str())
+                .flatten(),
+        });
+        let outcome = render_name(&config.release.tag, &ctx.run_id, &today_utc(), &latest)
+            .and_then(|tag| {
+                release::publish(org, &release_repo, &tag, &report, config.release.draft)
+                    .map(|url| (tag, url))
+            });
+        release_info = match outcome {
+            Ok((tag, url)) => {
+                info!(repo = %release_repo, tag = %tag, url = %url, "release published");
+                json!({ "repo": release_repo, "tag": tag, "url": url })
+            }
+            Err(e) => {
+                warn!(repo = %release_repo, error = %e, "publishing the release failed");
+                json!({ "repo": release_repo, "error": format!("{e:#}") })
+            }
+        };
+    }
+
+    let run_record = RunRecord {
+        run_id: ctx.run_id.clone(),
+        timestamp: now_secs(),
+        dry_run,
+        latest,
+        matrix,
+    };
+    if let Err(e) = RunHistory::default_location().save(&run_record) {
+        warn!(error = %e, "failed to record run history");
+    }
+
+    timings.end_phase("record");
+
+    // ── Phase 5: Config sync ────────────────────────────────────────────
+    info!("Phase 5: requesting config sync from king");
+    let config_synced = if !dry_run && (!committed.is_empty() || !retried_files.is_empty()) {
+        match king.post("/admin/config-sync", &Value::Null).await {
+            Ok((status, _)) if status.is_success() => {
+                info!("config-sync accepted by king");
+                true
+            }
+            Ok((status, _)) => {
+                warn!(status = %status, "config-sync returned non-success");
+                false
+            }
+            Err(e) => {
+                warn!(error = %e, "config-sync request failed");
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    timings.end_phase("config_sync");
+
+    // ── Phase 6: Return JSON summary ────────────────────────────────────
+    let timings = timings.finish();
+    info!(
+        committed = committed.len(),
+        errors = errors.len(),
+        config_synced,
+        total_ms = timings.total_ms,
+        slowest_phase = ?timings.slowest_phase,
+        slowest_repo = ?timings.slowest_repo,
+        "Phase 6: done"
+    );
+
+    if !workflow_failures.is_empty() {
+        workflow_sync["failed"] = json!(workflow_failures);
+    }
+    let managed: Vec<&str> = config.repos.iter().map(|r| r.repo.as_str()).collect();
+    let summary = RunSummary {
+        run_id: ctx.run_id.clone(),
+        resumed_from: resumed_from.clone(),
+        plan: stored_plan,
+        applied_plan: applying.as_ref().map(|plan| plan.plan_id.clone()),
+        approval_question,
+        dry_run,
+        offline,
+        config_version: run.snapshot.version.clone(),
+        config_error: run.snapshot.error.clone(),
+        version_freshness: json!(version_freshness),
+        version_lookups: json!(version_lookups),
+        commit_mode: format!("{commit_mode:?}"),
+        commit_granularity: run_granularity,
+        auto_apply,
+        environment: json!(environment),
+        checkouts: json!(checkouts),
+        versions: latest_versions
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), version))
+            .collect(),
+        crate_links,
+        repo_crates: repo_tracked
+            .into_iter()
+            .map(|(repo, crates)| {
+                (
+                    repo.to_string(),
+                    crates.into_iter().map(str::to_string).collect(),
+                )
+            })
+            .collect(),
+        pending_updates: pending_updates.len(),
+        repos: RepoResult::collect(&managed, &committed, &held_for_review, &errors),
+        committed,
+        errors,
+        rejected,
+        unverified_manifests,
+        checksum_failures,
+        audit: audits,
+        osv: osv_report,
+        held_for_review,
+        workspace_conflicts,
+        patch_overrides: patch_states,
+        frozen: frozen_repos,
+        skipped_by_policy,
+        suppressed_by_repo,
+        full_scan,
+        targeted_repos: targeted.then(|| config.repos.iter().map(|r| r.repo.clone()).collect()),
+        unchanged_repos,
+        workflow_patterns,
+        release_bumps,
+        schema_sync,
+        file_sync,
+        file_drift,
+        template_drift,
+        template_prs,
+        workflow_sync,
+        license,
+        license_drift,
+        publish_waits: json!(publish_waits),
+        publish_stuck: json!(publish_stuck),
+        ownership_changes: json!(ownership_changes),
+        rollout_tiers: json!(rollout_tiers),
+        canary: json!(canary_result),
+        fleet_consistent: fleet_divergence.is_empty(),
+        fleet_divergence: json!(fleet_divergence),
+        sboms,
+        github_quota: json!(tokens::TokenPool::global().report()),
+        config_synced,
+        analysis_summary,
+        migration_notes: json!(migration_notes),
+        upstream_changes: json!(upstream_changes),
+        migrations,
+        sandbox: sandboxes,
+        new_dependencies: json!(new_dependencies),
+        risk_assessment: json!(risk_assessment),
+        provenance: json!(provenance_info),
+        lease: json!(lease_status),
+        badges: json!({
+            "repos": badges
+                .iter()
+                .map(|b| json!({ "repo": b.repo, "state": b.state, "message": b.message() }))
+                .collect::<Vec<_>>(),
+            "published": badges_published,
+        }),
+        checks: json!(checks),
+        notifications,
+        retried: json!(retried),
+        retry_queue: json!({ "path": retry_queue.path().display().to_string(), "queued": queued }),
+        fleet_tag: json!(fleet_tag_info),
+        release: json!(release_info),
+        timings: json!(timings),
+        llm_usage: json!(UsageReport {
+            skipped: llm_skipped.map(str::to_string),
+            ..llm.report()
+        }),
+        audit_log: json!({
+            "path": audit_log.path().display().to_string(),
+            "entries": audit_entries.len(),
+            "shipped_to_king": audit_log_shipped,
+        }),
+    };
+    Ok(serde_json::to_value(summary)?)
+}
+
+/// Why the wave is not tagged: it committed nothing directly, or something
+/// failed, was held by a sandbox failure or was not verified.
+fn wave_blocker(
+    committed: &[CommitRecord],
+    errors: &[Value],
+    held: &[HeldUpdate],
+) -> Option<&'static str> {
+    if !errors.is_empty() {
+        Some("errors")
+    } else if held
+        .iter()
+        .any(|h| h.reason == HoldReason::SandboxVerificationFailed)
+        || committed.iter().any(|c| c.verified == Some(false))
+    {
+        Some("verification_failed")
+    } else if !committed.iter().any(|c| c.sha.is_some()) {
+        Some("nothing_committed")
+    } else {
+        None
+    }
+}
+
+/// The last direct commit of every repo in `committed`, in the order the
+/// repos were committed.  Local-git commits are tagged in the checkout.
+fn fleet_tag_targets(
+    config: &UpdateConfig,
+    base_dir: &Path,
+    committed: &[CommitRecord],
+) -> Vec<fleet_tag::Target> {
+    let mut targets: Vec<fleet_tag::Target> = Vec::new();
+    for entry in committed {
+        let (repo, Some(sha)) = (entry.repo.as_str(), entry.sha.as_deref()) else {
+            continue;
+        };
+        let spec = config.repos.iter().find(|spec| spec.repo == repo);
+        let local_base = (entry.strategy.as_deref() == Some("LocalGit"))
+            .then(|| spec.map(|spec| base_dir.join(&spec.local)))
+            .flatten();
+        let target = fleet_tag::Target {
+            repo: repo.to_string(),
+            sha: sha.to_string(),
+            local_base,
+            push: spec.map(RepoConfig::push_target).unwrap_or_default(),
+        };
+        match targets.iter_mut().find(|t| t.repo == repo) {
+            Some(existing) => *existing = target,
+            None => targets.push(target),
+        }
+    }
+    targets
+}
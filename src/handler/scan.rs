@@ -0,0 +1,1648 @@
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{debug, error, info, warn};
+
+use crate::audit::Advisory;
+use crate::backend::RegistryClient;
+use crate::checksum::sha256_hex;
+use crate::commit_message::{CommitTemplates, MessageVars, apply_type_scope};
+use crate::config::{RepoConfig, UpdateConfig};
+use crate::file_sync::{CanonicalFiles, FileSyncCampaign, SyncMode};
+use crate::osv::{OsvFinding, Vulnerability};
+use crate::policy::AutoApply;
+use crate::release::ReleaseBump;
+use crate::repo_config::RepoLocalConfig;
+use crate::scan_state::{RepoFingerprint, ScanState};
+use crate::schemas::SchemaSet;
+use crate::summary::{HeldUpdate, HoldReason};
+use crate::template::Drift;
+use crate::timing::{Stage, Timings};
+use crate::updater::{
+    patch_manifest, patch_workflow_sed, workflow_sed_styles, workflow_sed_version,
+};
+use crate::validate::{Validation, validate_manifest};
+use crate::versions::{VersionReport, current_dep_version, needs_update, requirement_satisfied};
+use crate::workflow_sync::{RenderVars, WorkflowTemplates};
+use crate::{
+    audit, cargo_edit, commit_message, deny, freeze, license, osv, patches, release, repo_config,
+    sbom, template, toolchain, workflow_sync, workspace,
+};
+
+use super::lookup::Versions;
+use super::{Gates, PackageManifest, PendingUpdate, Run, SELF_REPO, repo_cargo_files};
+
+// ─── Phase 2: Scan repos for stale deps ───────────────────────────────────────
+
+/// What Phase 2 found: the updates to deliver, and every finding the run
+/// reports about the repos it scanned.
+#[derive(Debug, Default)]
+pub(super) struct Scan<'a> {
+    pub pending_updates: Vec<PendingUpdate>,
+    /// Every tracked-crate bump of a manifest, for the risk analysis.
+    pub version_reports: Vec<VersionReport>,
+    pub rejected: Vec<Value>,
+    pub unverified_manifests: Vec<Value>,
+    pub audits: Vec<Value>,
+    pub held_for_review: Vec<HeldUpdate>,
+    pub workspace_conflicts: Vec<Value>,
+    pub patch_states: Vec<Value>,
+    pub workflow_patterns: Vec<Value>,
+    /// Tracked crates of every scanned repo.
+    pub repo_tracked: HashMap<&'a str, Vec<&'a str>>,
+    pub osv_report: Vec<Value>,
+    pub skipped_by_policy: Vec<Value>,
+    pub frozen_repos: Vec<Value>,
+    pub suppressed_by_repo: Vec<Value>,
+    pub release_bumps: Vec<Value>,
+    pub schema_sync: Value,
+    pub file_sync: Vec<Value>,
+    pub file_drift: Vec<Value>,
+    pub template_drift: Vec<Value>,
+    /// Repos whose drift from the template is proposed in a PR.
+    pub template_proposals: Vec<(String, Vec<Drift>)>,
+    pub workflow_sync: Value,
+    pub workflow_failures: Vec<Value>,
+    pub license: Value,
+    pub license_drift: Vec<Value>,
+    /// Whether `metadata.full` asked to rescan repos that scanned clean.
+    pub full_scan: bool,
+    pub unchanged_repos: Vec<String>,
+}
+
+/// Phase 2: scans every repo of the run for stale dependencies and drifted
+/// shared files.  Repos that last scanned clean with the same inputs are
+/// skipped unless `metadata.full` asks for a complete rescan.
+pub(super) async fn scan<'a>(
+    run: &Run<'a>,
+    versions: &Versions<'a>,
+    gates: &Gates,
+    timings: &mut Timings,
+) -> Scan<'a> {
+    info!("Phase 2: scanning managed repos for outdated dependencies");
+    if !run.offline && !audit::cargo_audit_available() {
+        warn!("cargo-audit not installed — skipping advisory checks");
+    }
+    if gates.auto_apply == AutoApply::SecurityOnly {
+        info!("security-only policy — holding every update that fixes no advisory");
+    }
+    let mut scan = Scan {
+        full_scan: run.ctx.metadata.get("full").and_then(Value::as_bool) == Some(true),
+        ..Scan::default()
+    };
+    let mut scanner = Scanner::new(run, versions, gates, &mut scan);
+    for spec in &run.config.repos {
+        scanner.repo(spec, &mut scan, timings).await;
+    }
+    if !scan.unchanged_repos.is_empty() {
+        info!(
+            skipped = scan.unchanged_repos.len(),
+            "skipped repos unchanged since their last clean scan"
+        );
+    }
+    if let Err(e) = scanner.scan_state.save() {
+        warn!(error = %e, "failed to persist scan state");
+    }
+    timings.end_phase("scan");
+    scan
+}
+
+/// What the scans of the run's repos share: the canonical files every repo
+/// is compared with, loaded once, the scan state and the OSV answers.
+struct Scanner<'s, 'a> {
+    run: &'s Run<'a>,
+    versions: &'s Versions<'a>,
+    gates: &'s Gates,
+    templates: CommitTemplates,
+    schemas: Option<SchemaSet>,
+    schema_digest: Option<String>,
+    campaigns: Vec<(&'a FileSyncCampaign, CanonicalFiles, String)>,
+    template_root: PathBuf,
+    template_digest: Option<String>,
+    workflow_templates: Option<WorkflowTemplates>,
+    workflow_digest: Option<String>,
+    license_texts: Option<CanonicalFiles>,
+    license_digest: Option<String>,
+    scan_state: ScanState,
+    osv_cache: OsvCache,
+}
+
+impl<'s, 'a> Scanner<'s, 'a> {
+    /// Loads the canonical files of the run's campaigns, recording in `scan`
+    /// where they come from.
+    fn new(
+        run: &'s Run<'a>,
+        versions: &'s Versions<'a>,
+        gates: &'s Gates,
+        scan: &mut Scan<'a>,
+    ) -> Self {
+        let config = run.config;
+        let managed = &*run.snapshot.config;
+        let base_dir = &run.base_dir;
+
+        // ── Canonical schema files, copied into every repo's `schema_dir` ──
+        let schema_source = &config.schemas.source_repo;
+        let schemas: Option<SchemaSet> = if config
+            .repos
+            .iter()
+            .any(|spec| spec.schema_dir.is_some() && spec.repo != *schema_source)
+        {
+            scan.schema_sync = json!({
+                "source_repo": schema_source,
+                "source_dir": config.schemas.source_dir,
+            });
+            let source = source_checkout(managed, base_dir, schema_source);
+            match SchemaSet::load(&source, &config.schemas) {
+                Ok(set) => {
+                    info!(source = %schema_source, files = set.files.len(), "loaded canonical schema files");
+                    scan.schema_sync["files"] = json!(set.files.keys().collect::<Vec<_>>());
+                    scan.schema_sync["synced"] = json!([]);
+                    Some(set)
+                }
+                Err(e) => {
+                    warn!(source = %schema_source, error = %e, "cannot read canonical schema files — not propagating them");
+                    scan.schema_sync["error"] = json!(format!("{e:#}"));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let schema_digest = schemas.as_ref().map(SchemaSet::digest);
+
+        // ── Shared files of the `[[file_sync]]` campaigns ──
+        let campaigns: Vec<(&FileSyncCampaign, CanonicalFiles, String)> = config
+            .file_sync
+            .iter()
+            .filter_map(|campaign| {
+                let mut entry = json!({
+                    "campaign": campaign.name,
+                    "source_repo": campaign.source_repo,
+                    "mode": campaign.mode,
+                });
+                let source =
+                    source_checkout(managed, base_dir, &campaign.source_repo);
+                let loaded = CanonicalFiles::load(&source, campaign);
+                let canonical = match loaded {
+                    Ok(canonical) => {
+                        entry["files"] = json!(canonical.files.keys().collect::<Vec<_>>());
+                        Some(canonical)
+                    }
+                    Err(e) => {
+                        warn!(campaign = %campaign.name, error = %e, "cannot read the campaign's canonical files — skipping it");
+                        entry["error"] = json!(format!("{e:#}"));
+                        None
+                    }
+                };
+                scan.file_sync.push(entry);
+                let canonical = canonical?;
+                let digest = canonical.digest();
+                Some((campaign, canonical, digest))
+            })
+            .collect();
+
+        // ── Template the `from_template` repos are compared with ──
+        let template_root = source_checkout(managed, base_dir, &config.template.repo);
+        let template_digest = config
+            .repos
+            .iter()
+            .any(|spec| spec.from_template)
+            .then(|| template::digest(&template_root, &config.template));
+
+        // ── Canonical CI workflows, rendered into the repos' workflow dir ──
+        let workflow_source = &config.workflow_sync.source_repo;
+        let workflow_templates: Option<WorkflowTemplates> = if config
+            .repos
+            .iter()
+            .any(|spec| !spec.workflow_templates.is_empty() && spec.repo != *workflow_source)
+        {
+            scan.workflow_sync = json!({
+                "source_repo": workflow_source,
+                "source_dir": config.workflow_sync.source_dir,
+            });
+            let source = source_checkout(managed, base_dir, workflow_source);
+            match WorkflowTemplates::load(&source, &config.workflow_sync) {
+                Ok(templates) => {
+                    info!(source = %workflow_source, templates = templates.files.len(), "loaded canonical workflow templates");
+                    scan.workflow_sync["templates"] =
+                        json!(templates.files.keys().collect::<Vec<_>>());
+                    scan.workflow_sync["synced"] = json!([]);
+                    Some(templates)
+                }
+                Err(e) => {
+                    warn!(source = %workflow_source, error = %e, "cannot read canonical workflow templates — not rendering them");
+                    scan.workflow_sync["error"] = json!(format!("{e:#}"));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let workflow_digest = workflow_templates.as_ref().map(WorkflowTemplates::digest);
+
+        // ── License policy: SPDX headers and the canonical license texts ──
+        let license_expression = config.license.expression.as_deref();
+        let license_campaign = config.license.campaign();
+        let license_texts: Option<CanonicalFiles> = license_expression.and_then(|expression| {
+            scan.license = json!({
+                "expression": expression,
+                "source_repo": config.license.source_repo,
+                "mode": config.license.mode,
+            });
+            let source =
+                source_checkout(managed, base_dir, &config.license.source_repo);
+            match CanonicalFiles::load(&source, &license_campaign) {
+                Ok(texts) => {
+                    scan.license["files"] = json!(texts.files.keys().collect::<Vec<_>>());
+                    Some(texts)
+                }
+                Err(e) => {
+                    warn!(source = %config.license.source_repo, error = %e, "cannot read the canonical license texts — checking headers only");
+                    scan.license["error"] = json!(format!("{e:#}"));
+                    None
+                }
+            }
+        });
+        let license_digest = license_expression.map(|expression| {
+            let texts = license_texts.as_ref().map(CanonicalFiles::digest);
+            sha256_hex(format!("{expression}\0{}", texts.unwrap_or_default()).as_bytes())
+        });
+
+        let scan_state_path = ScanState::default_path();
+        let scan_state = ScanState::load(&scan_state_path).unwrap_or_else(|e| {
+            warn!(error = %e, "scan state unreadable — scanning every repo");
+            ScanState::new(&scan_state_path)
+        });
+        Self {
+            run,
+            versions,
+            gates,
+            templates: CommitTemplates::from_env(),
+            schemas,
+            schema_digest,
+            campaigns,
+            template_root,
+            template_digest,
+            workflow_templates,
+            workflow_digest,
+            license_texts,
+            license_digest,
+            scan_state,
+            osv_cache: HashMap::new(),
+        }
+    }
+
+    /// Scans `spec`'s checkout, adding its updates and findings to `scan`.
+    async fn repo(&mut self, spec: &'a RepoConfig, scan: &mut Scan<'a>, timings: &mut Timings) {
+        let Run {
+            ctx,
+            config,
+            offline,
+            ref org,
+            ref base_dir,
+            ..
+        } = *self.run;
+        let registry_client = &self.run.backends.registry;
+        let latest_versions = &self.versions.latest;
+        let Gates {
+            freeze_list,
+            today,
+            crate_policy,
+            auto_apply,
+        } = self.gates;
+        let auto_apply = *auto_apply;
+        let global_templates = &self.templates;
+        let schema_source = &config.schemas.source_repo;
+        let schemas = &self.schemas;
+        let schema_digest = &self.schema_digest;
+        let campaigns = &self.campaigns;
+        let template_root = &self.template_root;
+        let template_digest = &self.template_digest;
+        let workflow_source = &config.workflow_sync.source_repo;
+        let workflow_templates = &self.workflow_templates;
+        let workflow_digest = &self.workflow_digest;
+        let license_expression = config.license.expression.as_deref();
+        let license_texts = &self.license_texts;
+        let license_digest = &self.license_digest;
+        let scan_state = &mut self.scan_state;
+        let osv_cache = &mut self.osv_cache;
+        let Scan {
+            pending_updates,
+            version_reports,
+            rejected,
+            unverified_manifests,
+            audits,
+            held_for_review,
+            workspace_conflicts,
+            patch_states,
+            workflow_patterns,
+            repo_tracked,
+            osv_report,
+            skipped_by_policy,
+            frozen_repos,
+            suppressed_by_repo,
+            release_bumps,
+            schema_sync,
+            file_drift,
+            template_drift,
+            template_proposals,
+            workflow_sync,
+            workflow_failures,
+            license_drift,
+            full_scan,
+            unchanged_repos,
+            ..
+        } = scan;
+
+        let repo_started = Instant::now();
+        let repo_base = base_dir.join(&spec.local);
+        let repo_crates = spec.tracked_crates(&config.tracked_crates);
+        repo_tracked.insert(spec.repo.as_str(), repo_crates.clone());
+        let templates = global_templates.with_overrides(
+            spec.cargo_commit_template.as_deref(),
+            spec.workflow_commit_template.as_deref(),
+        );
+        let repo_local = match RepoLocalConfig::load(&repo_base) {
+            Ok(local) => local,
+            Err(e) => {
+                // Never update past rules the maintainers tried to set.
+                warn!(repo = %spec.repo, error = %e, "invalid repo-local update config — skipping repo");
+                suppressed_by_repo.push(json!({
+                    "repo": spec.repo,
+                    "file": repo_config::FILE_NAME,
+                    "error": format!("{e:#}"),
+                }));
+                return;
+            }
+        };
+        if repo_local.opted_out() {
+            info!(repo = %spec.repo, "repo opted out of automated updates");
+            suppressed_by_repo.push(json!({
+                "repo": spec.repo,
+                "file": repo_config::FILE_NAME,
+                "suppressed_by": repo_config::Suppression::OptOut,
+            }));
+            return;
+        }
+
+        // ── Cargo.toml files (listed ones plus every workspace member,
+        //    unless the repo lists its own) ──
+        let cargo_files = repo_cargo_files(spec, &repo_base, &repo_local);
+        // Rendered workflows are owned by their template: their `sed`
+        // patterns and toolchain pins are not patched in place.
+        let rendered_workflows: Vec<(&str, String)> =
+            if workflow_templates.is_some() && spec.repo != *workflow_source {
+                spec.workflow_templates
+                    .iter()
+                    .map(|name| (name.as_str(), workflow_sync::target_path(name)))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+        let workflow_files: Vec<String> = repo_local
+            .workflow_files
+            .clone()
+            .unwrap_or_else(|| {
+                spec.workflow_files
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .into_iter()
+            .filter(|file| {
+                !rendered_workflows
+                    .iter()
+                    .any(|(_, target)| target == file.trim_matches('/'))
+            })
+            .collect();
+        let package_files: Vec<(PackageManifest, Vec<String>)> = PackageManifest::ALL
+            .into_iter()
+            .map(|kind| {
+                let files = kind
+                    .local_files(&repo_local)
+                    .unwrap_or_else(|| kind.files(spec))
+                    .to_vec();
+                (kind, files)
+            })
+            .collect();
+        let schema_dir = spec
+            .schema_dir
+            .as_deref()
+            .filter(|_| spec.repo != *schema_source);
+        let schema_targets: Vec<String> = schemas
+            .as_ref()
+            .zip(schema_dir)
+            .map(|(set, dir)| set.targets(dir))
+            .unwrap_or_default();
+        let repo_campaigns: Vec<&(&FileSyncCampaign, CanonicalFiles, String)> = campaigns
+            .iter()
+            .filter(|(campaign, ..)| campaign.source_repo != spec.repo)
+            .collect();
+        let file_sync_opt_out: Vec<String> = spec
+            .file_sync_opt_out
+            .iter()
+            .chain(&repo_local.file_sync_opt_out)
+            .cloned()
+            .collect();
+        let from_template = spec.from_template && spec.repo != config.template.repo;
+        let template_files = if from_template {
+            template::compared_files(&config.template)
+        } else {
+            Vec::new()
+        };
+        let license_applies = license_expression.is_some() && !spec.license_exempt;
+        let license_sources: Vec<String> = if license_applies {
+            license::source_files(&repo_base, &config.license).unwrap_or_else(|e| {
+                warn!(repo = %spec.repo, error = %e, "cannot list source files for SPDX headers");
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        let license_files: Option<&CanonicalFiles> = license_texts
+            .as_ref()
+            .filter(|_| license_applies && spec.repo != config.license.source_repo);
+        let scanned_files: Vec<String> = cargo_files
+            .iter()
+            .chain(&workflow_files)
+            .chain(package_files.iter().flat_map(|(_, files)| files))
+            .chain(&schema_targets)
+            .chain(
+                repo_campaigns
+                    .iter()
+                    .flat_map(|(_, canonical, _)| canonical.files.keys()),
+            )
+            .chain(&template_files)
+            .chain(rendered_workflows.iter().map(|(_, target)| target))
+            .chain(&license_sources)
+            .chain(license_files.iter().flat_map(|texts| texts.files.keys()))
+            .cloned()
+            .chain([repo_config::FILE_NAME.to_string()])
+            .collect();
+        let fingerprint = RepoFingerprint::compute(
+            &self.run.snapshot.version,
+            repo_crates
+                .iter()
+                .copied()
+                .chain([toolchain::RUST])
+                .chain(
+                    PackageManifest::ALL
+                        .into_iter()
+                        .flat_map(|kind| kind.packages(config))
+                        .map(String::as_str),
+                )
+                .filter_map(|c| Some((c.to_string(), latest_versions.get(c)?.clone())))
+                .chain(
+                    schema_dir
+                        .and(schema_digest.clone())
+                        .map(|digest| (format!("schemas:{schema_source}"), digest)),
+                )
+                .chain(repo_campaigns.iter().map(|(campaign, _, digest)| {
+                    (format!("file_sync:{}", campaign.name), digest.clone())
+                }))
+                .chain(
+                    template_digest
+                        .clone()
+                        .filter(|_| from_template)
+                        .map(|digest| (format!("template:{}", config.template.repo), digest)),
+                )
+                .chain(
+                    workflow_digest
+                        .clone()
+                        .filter(|_| !rendered_workflows.is_empty())
+                        .map(|digest| (format!("workflows:{workflow_source}"), digest)),
+                )
+                .chain(
+                    license_digest
+                        .clone()
+                        .filter(|_| license_applies)
+                        .map(|digest| ("license".to_string(), digest)),
+                )
+                .collect(),
+            &repo_base,
+            &scanned_files,
+        );
+        if !*full_scan && scan_state.is_settled(&spec.repo, &fingerprint) {
+            debug!(repo = %spec.repo, "files and latest versions unchanged since a clean scan — skipping");
+            unchanged_repos.push(spec.repo.clone());
+            return;
+        }
+        // Findings only ever grow, so their total shows whether the repo
+        // reported anything.
+        let findings_before: usize = held_for_review.len()
+            + [
+                &rejected,
+                &unverified_manifests,
+                &workspace_conflicts,
+                &skipped_by_policy,
+                &suppressed_by_repo,
+                &patch_states,
+                &audits,
+                &frozen_repos,
+                &file_drift,
+                &template_drift,
+                &workflow_failures,
+                &license_drift,
+            ]
+            .iter()
+            .map(|findings| findings.len())
+            .sum::<usize>();
+        let repo_start = pending_updates.len();
+
+        for cargo_file in &cargo_files {
+            let cargo_file = cargo_file.as_str();
+            let path = repo_base.join(cargo_file);
+            let content = match std::fs::read_to_string(&path) {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!(repo = %spec.repo, file = cargo_file, error = %e, "cannot read file — skipping");
+                    continue;
+                }
+            };
+
+            let mut patched = content.clone();
+            let mut file_changed = false;
+            let mut file_versions: Vec<VersionReport> = Vec::new();
+            let mut bumps: Vec<VersionReport> = Vec::new();
+
+            // ── [patch.crates-io] overrides of tracked crates ──
+            // Released patches are dropped; live ones pin the crate, so its
+            // requirement is left alone rather than bumped underneath them.
+            let mut patched_crates: Vec<String> = Vec::new();
+            let manifest_dir = path.parent().unwrap_or(&repo_base);
+            for entry in patches::find_patches(&patched, &repo_crates, manifest_dir) {
+                let Some(latest) = latest_versions.get(entry.crate_name.as_str()) else {
+                    continue;
+                };
+                let status = entry.status(latest);
+                if status == patches::PatchStatus::Stale {
+                    match patches::remove_patch(&patched, &entry.crate_name) {
+                        Ok(content) => {
+                            info!(repo = %spec.repo, file = cargo_file, dep = %entry.crate_name, "removing released [patch.crates-io] override");
+                            patched = content;
+                            file_changed = true;
+                        }
+                        Err(e) => {
+                            warn!(repo = %spec.repo, file = cargo_file, dep = %entry.crate_name, error = %e, "cannot remove stale patch");
+                            patched_crates.push(entry.crate_name.clone());
+                        }
+                    }
+                } else {
+                    info!(repo = %spec.repo, file = cargo_file, dep = %entry.crate_name, status = ?status, "crate is patched — leaving its requirement alone");
+                    patched_crates.push(entry.crate_name.clone());
+                }
+                patch_states.push(json!({
+                    "repo": spec.repo,
+                    "file": cargo_file,
+                    "crate": entry.crate_name,
+                    "source": entry.source,
+                    "patched_version": entry.version,
+                    "latest": latest,
+                    "status": status,
+                }));
+            }
+
+            for &crate_name in &repo_crates {
+                let Some(latest) = latest_versions.get(crate_name) else {
+                    continue;
+                };
+                if patched_crates.iter().any(|c| c == crate_name) {
+                    continue;
+                }
+                let Some(current) = current_dep_version(&patched, crate_name) else {
+                    continue;
+                };
+                // A range that already admits the latest version needs no
+                // new pin; cargo resolves to it on the next lockfile update.
+                if requirement_satisfied(&current, latest) {
+                    debug!(repo = %spec.repo, file = cargo_file, dep = crate_name, requirement = %current, latest = %latest, "requirement already satisfied");
+                    continue;
+                }
+                if needs_update(&current, latest) {
+                    if let Some(block) = crate_policy.check(
+                        crate_name,
+                        &spec.deny_crates,
+                        spec.allow_crates.as_deref(),
+                    ) {
+                        info!(repo = %spec.repo, file = cargo_file, dep = crate_name, policy = ?block, "update skipped by policy");
+                        skipped_by_policy.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "crate": crate_name,
+                            "from": current,
+                            "to": latest,
+                            "policy": block,
+                        }));
+                        continue;
+                    }
+                    if let Some(rule) = repo_local.check(crate_name, &current, latest) {
+                        info!(repo = %spec.repo, file = cargo_file, dep = crate_name, rule = ?rule, "update suppressed by repo-local config");
+                        suppressed_by_repo.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "crate": crate_name,
+                            "from": current,
+                            "to": latest,
+                            "suppressed_by": rule,
+                        }));
+                        continue;
+                    }
+                    info!(
+                        repo = %spec.repo,
+                        file = cargo_file,
+                        dep = crate_name,
+                        current = %current,
+                        latest = %latest,
+                        "update needed"
+                    );
+                    let report = VersionReport::new(crate_name, &current, latest);
+                    version_reports.push(report.clone());
+                    bumps.push(report);
+                }
+            }
+
+            // ── One patch pass over every approved bump ──
+            if !bumps.is_empty() {
+                let updates: Vec<(&str, &str)> = bumps
+                    .iter()
+                    .map(|r| (r.crate_name.as_str(), r.latest.as_str()))
+                    .collect();
+                match patch_manifest(
+                    spec.patcher.unwrap_or_default(),
+                    &path,
+                    sbom::find_lockfile(&repo_base, &path).as_deref(),
+                    &patched,
+                    &updates,
+                    offline,
+                ) {
+                    Ok(outcome) => {
+                        info!(
+                            repo = %spec.repo,
+                            file = cargo_file,
+                            sections = ?outcome.sections_touched,
+                            entries = outcome.changed_entries.len(),
+                            "patched manifest"
+                        );
+                        for entry in &outcome.changed_entries {
+                            debug!(
+                                repo = %spec.repo,
+                                file = cargo_file,
+                                section = %entry.section,
+                                dep = %entry.dep,
+                                from = %entry.from,
+                                to = %entry.to,
+                                "patched version entry"
+                            );
+                        }
+                        for failure in &outcome.failed {
+                            warn!(repo = %spec.repo, file = cargo_file, dep = %failure.dep, error = %failure.error, "patch failed");
+                        }
+                        file_versions.extend(
+                            bumps
+                                .into_iter()
+                                .filter(|r| !outcome.failed.iter().any(|f| f.dep == r.crate_name)),
+                        );
+                        if !file_versions.is_empty() {
+                            patched = outcome.content;
+                            file_changed = true;
+                        }
+                    }
+                    Err(e) => {
+                        warn!(repo = %spec.repo, file = cargo_file, error = %e, "patch failed");
+                    }
+                }
+            }
+
+            if file_changed {
+                match validate_manifest(&patched) {
+                    Ok(Validation::Verified) => {}
+                    Ok(Validation::Unverified(reason)) => {
+                        unverified_manifests.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "reason": reason,
+                        }));
+                    }
+                    Err(e) => {
+                        warn!(repo = %spec.repo, file = cargo_file, error = %e, "patched manifest failed validation — not committing");
+                        rejected.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "error": e.to_string(),
+                        }));
+                        continue;
+                    }
+                }
+            }
+
+            if file_changed
+                && !offline
+                && let Some(config) = deny::find_deny_config(&repo_base, &path)
+            {
+                let lock = sbom::find_lockfile(&repo_base, &path);
+                match deny::deny_check(&config, lock.as_deref(), &patched) {
+                    Ok(Some(report)) if report.is_violation() => {
+                        warn!(
+                            repo = %spec.repo,
+                            file = cargo_file,
+                            checks = ?report.failed_checks,
+                            "cargo deny policy violated — not committing"
+                        );
+                        rejected.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "error": format!("cargo deny failed: {}", report.failed_checks.join(", ")),
+                            "failed_checks": report.failed_checks,
+                            "diagnostics": report.errors,
+                        }));
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!(repo = %spec.repo, file = cargo_file, error = %e, "cargo deny could not run — continuing without it");
+                    }
+                }
+            }
+
+            // Advisories the update resolves; `None` when it was not audited.
+            let mut fixes: Option<Vec<Advisory>> = None;
+            if file_changed && !offline {
+                let updated: Vec<&str> = file_versions
+                    .iter()
+                    .map(|v| v.crate_name.as_str())
+                    .collect();
+                let lock = sbom::find_lockfile(&repo_base, &path);
+                match audit::audit_update(lock.as_deref(), &patched, &updated) {
+                    Ok(Some(report)) => {
+                        fixes = Some(report.fixed.clone());
+                        let hold = report.hold_for_review();
+                        audits.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "hold_for_review": hold,
+                            "report": report,
+                        }));
+                        if hold {
+                            warn!(
+                                repo = %spec.repo,
+                                file = cargo_file,
+                                introduced = report.introduced.len(),
+                                unfixed = report.unfixed.len(),
+                                "cargo audit flagged update — holding for review"
+                            );
+                            held_for_review.push(
+                                HeldUpdate::new(&spec.repo, cargo_file, HoldReason::CargoAudit)
+                                    .with("introduced", &report.introduced)
+                                    .with("unfixed", &report.unfixed),
+                            );
+                            continue;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(repo = %spec.repo, file = cargo_file, error = %e, "cargo audit failed — continuing without it");
+                        audits.push(json!({
+                            "repo": spec.repo,
+                            "file": cargo_file,
+                            "error": e.to_string(),
+                        }));
+                    }
+                }
+            }
+
+            // OSV vulnerabilities of each bump's old and new version.
+            let mut osv_findings: Vec<OsvFinding> = Vec::new();
+            let mut osv_checked = false;
+            if file_changed && !offline && osv::enabled() {
+                let lock = sbom::find_lockfile(&repo_base, &path)
+                    .and_then(|lock| std::fs::read_to_string(lock).ok());
+                osv_checked = true;
+                for report in &file_versions {
+                    let from = lock
+                        .as_deref()
+                        .and_then(|lock| sbom::locked_version(lock, &report.crate_name))
+                        .unwrap_or_else(|| osv::requirement_floor(&report.current));
+                    let before =
+                        osv_lookup(&**registry_client, osv_cache, &report.crate_name, &from).await;
+                    let after = osv_lookup(
+                        &**registry_client,
+                        osv_cache,
+                        &report.crate_name,
+                        &report.latest,
+                    )
+                    .await;
+                    match (before, after) {
+                        (Ok(before), Ok(after)) => {
+                            let finding = OsvFinding::compare(
+                                &report.crate_name,
+                                &from,
+                                &report.latest,
+                                &before,
+                                after,
+                            );
+                            if !finding.is_empty() {
+                                info!(repo = %spec.repo, file = cargo_file, dep = %report.crate_name, fixed = finding.fixed.len(), remaining = finding.remaining.len(), "OSV lists vulnerabilities");
+                                let mut entry = json!(finding);
+                                entry["repo"] = json!(spec.repo);
+                                entry["file"] = json!(cargo_file);
+                                osv_report.push(entry);
+                                osv_findings.push(finding);
+                            }
+                        }
+                        (Err(e), _) | (_, Err(e)) => {
+                            warn!(repo = %spec.repo, file = cargo_file, dep = %report.crate_name, error = %e, "OSV query failed");
+                            osv_checked = false;
+                            osv_report.push(json!({
+                                "repo": spec.repo,
+                                "file": cargo_file,
+                                "crate": report.crate_name,
+                                "error": e,
+                            }));
+                        }
+                    }
+                }
+            }
+
+            if file_changed && auto_apply == AutoApply::SecurityOnly {
+                let rustsec_fixes = fixes.as_ref().map_or(0, Vec::len);
+                let osv_fixes: usize = osv_findings.iter().map(|f| f.fixed.len()).sum();
+                let audited = fixes.is_some() || osv_checked;
+                if rustsec_fixes + osv_fixes > 0 {
+                    info!(repo = %spec.repo, file = cargo_file, rustsec_fixes, osv_fixes, "update fixes advisories — applying under security-only policy");
+                } else {
+                    info!(repo = %spec.repo, file = cargo_file, audited, "update fixes no advisory — holding under security-only policy");
+                    held_for_review.push(
+                        HeldUpdate::new(&spec.repo, cargo_file, HoldReason::SecurityOnly)
+                            .with("bumps", &file_versions)
+                            .with("audited", audited),
+                    );
+                    continue;
+                }
+            }
+
+            if file_changed {
+                let msg = apply_type_scope(
+                    &commit_message::render(
+                        &templates.cargo,
+                        &MessageVars {
+                            repo: &spec.repo,
+                            file: cargo_file,
+                            run_id: &ctx.run_id,
+                            versions: &file_versions,
+                        },
+                    ),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                // The agent's release build is `--locked`: its own bump
+                // ships with the lockfile cargo resolves for it, or not at all.
+                let mut lock_update = None;
+                if spec.repo == SELF_REPO
+                    && let Some(lock) = sbom::find_lockfile(&repo_base, &path)
+                    && let Ok(lock_file) = lock.strip_prefix(&repo_base)
+                {
+                    let lock_file = lock_file.to_string_lossy().into_owned();
+                    let regenerated = std::fs::read_to_string(&lock)
+                        .map_err(|e| anyhow::anyhow!("read {}: {e}", lock.display()))
+                        .and_then(|original| {
+                            let updated =
+                                cargo_edit::regenerate_lockfile(&path, &lock, &patched, offline)?;
+                            Ok((original, updated))
+                        });
+                    match regenerated {
+                        Ok((original, updated)) if original != updated => {
+                            info!(repo = %spec.repo, file = %lock_file, "regenerated own lockfile");
+                            lock_update = Some((lock_file, original, updated));
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!(repo = %spec.repo, file = cargo_file, error = %e, "cannot regenerate own Cargo.lock — not committing");
+                            rejected.push(json!({
+                                "repo": spec.repo,
+                                "file": cargo_file,
+                                "error": format!("regenerate {lock_file}: {e:#}"),
+                            }));
+                            continue;
+                        }
+                    }
+                }
+                pending_updates.push(PendingUpdate {
+                    versions: file_versions,
+                    vulnerabilities: osv_findings,
+                    ..PendingUpdate::new(
+                        spec.repo.clone(),
+                        repo_base.clone(),
+                        cargo_file.to_string(),
+                        content,
+                        patched,
+                        msg.clone(),
+                    )
+                });
+                if let Some((lock_file, original, updated)) = lock_update {
+                    pending_updates.push(PendingUpdate {
+                        part_of: Some(cargo_file.to_string()),
+                        ..PendingUpdate::new(
+                            spec.repo.clone(),
+                            repo_base.clone(),
+                            lock_file,
+                            original,
+                            updated,
+                            msg,
+                        )
+                    });
+                }
+            }
+        }
+
+        // ── Workspace consistency ──
+        // Every manifest must end up on one semver-compatible copy of each
+        // tracked crate; otherwise hold the repo's manifest updates rather
+        // than commit a half-updated workspace.
+        let final_manifests: Vec<(String, String)> = cargo_files
+            .iter()
+            .filter_map(|file| {
+                let pending = pending_updates[repo_start..]
+                    .iter()
+                    .find(|u| &u.file_path == file)
+                    .map(|u| u.patched_content.clone());
+                pending
+                    .or_else(|| std::fs::read_to_string(repo_base.join(file)).ok())
+                    .map(|content| (file.clone(), content))
+            })
+            .collect();
+        let conflicts = workspace::find_conflicts(&final_manifests, &repo_crates);
+        if !conflicts.is_empty() {
+            error!(
+                repo = %spec.repo,
+                conflicts = ?conflicts,
+                "workspace would resolve incompatible copies of a tracked crate — holding manifest updates"
+            );
+            let held: Vec<String> = pending_updates
+                .drain(repo_start..)
+                .map(|u| u.file_path)
+                .collect();
+            workspace_conflicts.push(json!({
+                "repo": spec.repo,
+                "conflicts": conflicts,
+                "held_files": held,
+            }));
+        }
+
+        // ── Workflow files (sed patterns for any tracked crate, pinned
+        //    Rust toolchains) ──
+        let msrv = std::fs::read_to_string(repo_base.join("Cargo.toml"))
+            .ok()
+            .and_then(|manifest| toolchain::msrv(&manifest));
+        for wf_file in &workflow_files {
+            let wf_file = wf_file.as_str();
+            let path = repo_base.join(wf_file);
+            let content = match std::fs::read_to_string(&path) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            let mut patched = content.clone();
+            let mut versions: Vec<VersionReport> = Vec::new();
+            for &crate_name in repo_crates.iter().chain([&toolchain::RUST]) {
+                let Some(latest) = latest_versions.get(crate_name) else {
+                    continue;
+                };
+                let (current, next) = if crate_name == toolchain::RUST {
+                    let Some(current) = toolchain::pinned_version(&patched, msrv.as_deref()) else {
+                        continue;
+                    };
+                    let next = toolchain::patch_pins(&patched, latest, msrv.as_deref());
+                    (current, next)
+                } else {
+                    let Some(current) = workflow_sed_version(&patched, crate_name) else {
+                        continue;
+                    };
+                    workflow_patterns.push(json!({
+                        "repo": spec.repo,
+                        "file": wf_file,
+                        "crate": crate_name,
+                        "patterns": workflow_sed_styles(&patched, crate_name),
+                    }));
+                    let next = patch_workflow_sed(&patched, crate_name, latest);
+                    (current, next)
+                };
+                if next != patched
+                    && let Some(block) = crate_policy.check(
+                        crate_name,
+                        &spec.deny_crates,
+                        spec.allow_crates.as_deref(),
+                    )
+                {
+                    info!(repo = %spec.repo, file = wf_file, dep = crate_name, policy = ?block, "workflow update skipped by policy");
+                    skipped_by_policy.push(json!({
+                        "repo": spec.repo,
+                        "file": wf_file,
+                        "crate": crate_name,
+                        "from": current,
+                        "to": latest,
+                        "policy": block,
+                    }));
+                } else if next != patched
+                    && let Some(rule) = repo_local.check(crate_name, &current, latest)
+                {
+                    info!(repo = %spec.repo, file = wf_file, dep = crate_name, rule = ?rule, "workflow update suppressed by repo-local config");
+                    suppressed_by_repo.push(json!({
+                        "repo": spec.repo,
+                        "file": wf_file,
+                        "crate": crate_name,
+                        "from": current,
+                        "to": latest,
+                        "suppressed_by": rule,
+                    }));
+                } else if next != patched {
+                    info!(repo = %spec.repo, file = wf_file, dep = crate_name, latest = %latest, "workflow sed update needed");
+                    patched = next;
+                    versions.push(VersionReport::new(crate_name, &current, latest));
+                }
+            }
+            // Workflow pins are never advisory fixes.
+            if !versions.is_empty() && auto_apply == AutoApply::SecurityOnly {
+                info!(repo = %spec.repo, file = wf_file, "workflow bump holds under security-only policy");
+                held_for_review.push(
+                    HeldUpdate::new(&spec.repo, wf_file, HoldReason::SecurityOnly)
+                        .with("bumps", &versions)
+                        .with("audited", false),
+                );
+            } else if !versions.is_empty() {
+                let msg = apply_type_scope(
+                    &commit_message::render(
+                        &templates.workflow,
+                        &MessageVars {
+                            repo: &spec.repo,
+                            file: wf_file,
+                            run_id: &ctx.run_id,
+                            versions: &versions,
+                        },
+                    ),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                pending_updates.push(PendingUpdate {
+                    versions,
+                    ..PendingUpdate::new(
+                        spec.repo.clone(),
+                        repo_base.clone(),
+                        wf_file.to_string(),
+                        content,
+                        patched,
+                        msg,
+                    )
+                });
+            }
+        }
+
+        // ── pyproject.toml / package.json files (dependencies on tracked
+        //    packages of other ecosystems) ──
+        for (kind, file) in package_files
+            .iter()
+            .flat_map(|(kind, files)| files.iter().map(move |file| (*kind, file)))
+        {
+            let file = file.as_str();
+            let content = match std::fs::read_to_string(repo_base.join(file)) {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!(repo = %spec.repo, file, error = %e, "cannot read file — skipping");
+                    continue;
+                }
+            };
+            let mut patched = content.clone();
+            let mut versions: Vec<VersionReport> = Vec::new();
+            for package in kind.packages(config).iter().map(String::as_str) {
+                let Some(latest) = latest_versions.get(package) else {
+                    continue;
+                };
+                let Some(current) = kind.current_version(&patched, package) else {
+                    continue;
+                };
+                let next = match kind.patch(&patched, package, latest) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        warn!(repo = %spec.repo, file, error = %e, "cannot patch manifest — skipping");
+                        break;
+                    }
+                };
+                if next != patched
+                    && let Some(block) =
+                        crate_policy.check(package, &spec.deny_crates, spec.allow_crates.as_deref())
+                {
+                    info!(repo = %spec.repo, file, dep = package, policy = ?block, "package update skipped by policy");
+                    skipped_by_policy.push(json!({
+                        "repo": spec.repo,
+                        "file": file,
+                        "crate": package,
+                        "from": current,
+                        "to": latest,
+                        "policy": block,
+                    }));
+                } else if next != patched
+                    && let Some(rule) = repo_local.check(package, &current, latest)
+                {
+                    info!(repo = %spec.repo, file, dep = package, rule = ?rule, "package update suppressed by repo-local config");
+                    suppressed_by_repo.push(json!({
+                        "repo": spec.repo,
+                        "file": file,
+                        "crate": package,
+                        "from": current,
+                        "to": latest,
+                        "suppressed_by": rule,
+                    }));
+                } else if next != patched {
+                    info!(repo = %spec.repo, file, dep = package, latest = %latest, "package update needed");
+                    patched = next;
+                    versions.push(VersionReport::new(package, &current, latest));
+                }
+            }
+            // Packages of other ecosystems are not checked against advisories.
+            if !versions.is_empty() && auto_apply == AutoApply::SecurityOnly {
+                info!(repo = %spec.repo, file, "package bump holds under security-only policy");
+                held_for_review.push(
+                    HeldUpdate::new(&spec.repo, file, HoldReason::SecurityOnly)
+                        .with("bumps", &versions)
+                        .with("audited", false),
+                );
+            } else if !versions.is_empty() {
+                let msg = apply_type_scope(
+                    &commit_message::render(
+                        &templates.cargo,
+                        &MessageVars {
+                            repo: &spec.repo,
+                            file,
+                            run_id: &ctx.run_id,
+                            versions: &versions,
+                        },
+                    ),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                pending_updates.push(PendingUpdate {
+                    versions,
+                    ..PendingUpdate::new(
+                        spec.repo.clone(),
+                        repo_base.clone(),
+                        file.to_string(),
+                        content,
+                        patched,
+                        msg,
+                    )
+                });
+            }
+        }
+
+        // ── Schema files: copies that drifted from the canonical ones ──
+        if let (Some(set), Some(dir)) = (&schemas, schema_dir) {
+            for (file, original, canonical) in set.drift(&repo_base, dir) {
+                info!(repo = %spec.repo, file = %file, source = %schema_source, "schema file differs from the canonical one");
+                let msg = apply_type_scope(
+                    &format!(
+                        "chore: sync {file} from {schema_source} [run_id={}]",
+                        ctx.run_id
+                    ),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                if let Some(synced) = schema_sync["synced"].as_array_mut() {
+                    synced.push(json!({ "repo": spec.repo, "file": file }));
+                }
+                pending_updates.push(PendingUpdate::new(
+                    spec.repo.clone(),
+                    repo_base.clone(),
+                    file,
+                    original,
+                    canonical,
+                    msg,
+                ));
+            }
+        }
+
+        // ── Shared files: copies that drifted from their source of truth ──
+        for (campaign, canonical, _) in &repo_campaigns {
+            let drifted = canonical.drift(&repo_base, |file| {
+                !campaign.opted_out(&file_sync_opt_out, file)
+            });
+            for (file, original, content) in drifted {
+                file_drift.push(json!({
+                    "campaign": campaign.name,
+                    "repo": spec.repo,
+                    "file": file,
+                    "mode": campaign.mode,
+                }));
+                if campaign.mode == SyncMode::Check {
+                    info!(repo = %spec.repo, file = %file, campaign = %campaign.name, "shared file drifted from its source of truth");
+                    continue;
+                }
+                info!(repo = %spec.repo, file = %file, campaign = %campaign.name, "syncing shared file");
+                let msg = apply_type_scope(
+                    &format!(
+                        "chore: sync {file} from {} [run_id={}]",
+                        campaign.source_repo, ctx.run_id
+                    ),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                pending_updates.push(PendingUpdate::new(
+                    spec.repo.clone(),
+                    repo_base.clone(),
+                    file,
+                    original,
+                    content,
+                    msg,
+                ));
+            }
+        }
+
+        // ── CI workflows rendered from the canonical templates ──
+        if let Some(templates) = &workflow_templates {
+            let vars = RenderVars {
+                repo: &spec.repo,
+                org,
+                rust: latest_versions.get(toolchain::RUST).map(String::as_str),
+                msrv: msrv.as_deref(),
+                latest: latest_versions,
+                params: &spec.workflow_params,
+            };
+            for (name, file) in &rendered_workflows {
+                let rendered = templates
+                    .files
+                    .get(*name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("{workflow_source} has no workflow template {name}")
+                    })
+                    .and_then(|template| workflow_sync::render(template, &vars));
+                let rendered = match rendered {
+                    Ok(rendered) => rendered,
+                    Err(e) => {
+                        warn!(repo = %spec.repo, file = %file, error = %e, "cannot render workflow template");
+                        workflow_failures.push(json!({
+                            "repo": spec.repo,
+                            "file": file,
+                            "template": name,
+                            "error": format!("{e:#}"),
+                        }));
+                        continue;
+                    }
+                };
+                let original = std::fs::read_to_string(repo_base.join(file)).unwrap_or_default();
+                if rendered.content == original {
+                    continue;
+                }
+                let blocked: Vec<Value> = rendered
+                    .crates
+                    .iter()
+                    .filter_map(|(crate_name, latest)| {
+                        let block = crate_policy.check(
+                            crate_name,
+                            &spec.deny_crates,
+                            spec.allow_crates.as_deref(),
+                        )?;
+                        Some(json!({
+                            "repo": spec.repo,
+                            "file": file,
+                            "crate": crate_name,
+                            "to": latest,
+                            "policy": block,
+                        }))
+                    })
+                    .collect();
+                if !blocked.is_empty() {
+                    info!(repo = %spec.repo, file = %file, "rendered workflow skipped by policy");
+                    skipped_by_policy.extend(blocked);
+                    continue;
+                }
+                // Like `sed` bumps, embedded crate versions are never advisory fixes.
+                if !rendered.crates.is_empty() && auto_apply == AutoApply::SecurityOnly {
+                    info!(repo = %spec.repo, file = %file, "rendered workflow holds under security-only policy");
+                    held_for_review.push(
+                        HeldUpdate::new(&spec.repo, file, HoldReason::SecurityOnly)
+                            .with("crates", &rendered.crates)
+                            .with("audited", false),
+                    );
+                    continue;
+                }
+                info!(repo = %spec.repo, file = %file, source = %workflow_source, "rendering workflow from its template");
+                let msg = apply_type_scope(
+                    &format!(
+                        "ci: render {file} from {workflow_source} [run_id={}]",
+                        ctx.run_id
+                    ),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                if let Some(synced) = workflow_sync["synced"].as_array_mut() {
+                    synced.push(json!({ "repo": spec.repo, "file": file }));
+                }
+                pending_updates.push(PendingUpdate::new(
+                    spec.repo.clone(),
+                    repo_base.clone(),
+                    file.clone(),
+                    original,
+                    rendered.content,
+                    msg,
+                ));
+            }
+        }
+
+        // ── License policy: SPDX headers and license texts ──
+        if let Some(expression) = license_expression.filter(|_| license_applies) {
+            let sync = config.license.mode == SyncMode::Sync;
+            // (file, original, fixed, commit summary)
+            let mut fixes: Vec<(String, String, String, String)> = Vec::new();
+            for file in &license_sources {
+                let Ok(content) = std::fs::read_to_string(repo_base.join(file)) else {
+                    continue;
+                };
+                let Some(issue) = license::check_header(&content, expression) else {
+                    continue;
+                };
+                info!(repo = %spec.repo, file = %file, issue = ?issue, "source file lacks the SPDX header");
+                let mut entry = json!(issue);
+                entry["repo"] = json!(spec.repo);
+                entry["file"] = json!(file);
+                entry["mode"] = json!(config.license.mode);
+                license_drift.push(entry);
+                // A file already being updated gets the header on top.
+                if sync
+                    && let Some(pending) = pending_updates[repo_start..]
+                        .iter_mut()
+                        .find(|u| u.file_path == *file)
+                {
+                    pending.patched_content =
+                        license::fix_header(file, &pending.patched_content, expression);
+                } else if sync {
+                    let fixed = license::fix_header(file, &content, expression);
+                    let summary = format!("chore: set SPDX header of {file} to {expression}");
+                    fixes.push((file.clone(), content, fixed, summary));
+                }
+            }
+            for (file, original, canonical) in license_files
+                .map(|texts| texts.drift(&repo_base, |_| true))
+                .unwrap_or_default()
+            {
+                info!(repo = %spec.repo, file = %file, "license text differs from the canonical one");
+                license_drift.push(json!({
+                    "issue": "license_file",
+                    "repo": spec.repo,
+                    "file": file,
+                    "mode": config.license.mode,
+                }));
+                if sync {
+                    let summary = format!("chore: sync {file} from {}", config.license.source_repo);
+                    fixes.push((file, original, canonical, summary));
+                }
+            }
+            for (file, original, fixed, summary) in fixes {
+                let msg = apply_type_scope(
+                    &format!("{summary} [run_id={}]", ctx.run_id),
+                    spec.commit_type.as_deref(),
+                    spec.commit_scope.as_deref(),
+                );
+                pending_updates.push(PendingUpdate::new(
+                    spec.repo.clone(),
+                    repo_base.clone(),
+                    file,
+                    original,
+                    fixed,
+                    msg,
+                ));
+            }
+        }
+
+        // ── Template drift: structure of repos generated from the template ──
+        if from_template {
+            match template::compare(template_root, &repo_base, &config.template) {
+                Ok(drift) => {
+                    for d in &drift {
+                        info!(repo = %spec.repo, file = %d.file, kind = ?d.kind, "file drifted from the template");
+                        let mut entry = json!(d);
+                        entry["repo"] = json!(spec.repo);
+                        template_drift.push(entry);
+                    }
+                    if config.template.open_prs && drift.iter().any(|d| d.proposed.is_some()) {
+                        template_proposals.push((spec.repo.clone(), drift));
+                    }
+                }
+                Err(e) => {
+                    warn!(repo = %spec.repo, error = %e, "cannot compare the repo with the template");
+                    template_drift.push(json!({
+                        "repo": spec.repo,
+                        "error": format!("{e:#}"),
+                    }));
+                }
+            }
+        }
+
+        // ── Freeze ──
+        if let Some(freeze) = freeze::freeze_status(
+            &spec.repo,
+            spec.frozen,
+            spec.frozen_until.as_deref(),
+            freeze_list,
+            today,
+        ) {
+            let held: Vec<String> = pending_updates
+                .drain(repo_start..)
+                .map(|u| u.file_path)
+                .collect();
+            info!(
+                repo = %spec.repo,
+                until = ?freeze.until,
+                pending = held.len(),
+                "repo is frozen — not applying updates"
+            );
+            frozen_repos.push(json!({
+                "repo": spec.repo,
+                "until": freeze.until,
+                "pending_updates": held.len(),
+                "files": held,
+            }));
+        }
+
+        // ── Release bump: the repo's own version follows its deps ──
+        if let Some(level) = spec.release_bump
+            && pending_updates.len() > repo_start
+        {
+            match release_bump(
+                spec,
+                level,
+                &repo_base,
+                &ctx.run_id,
+                pending_updates,
+                repo_start,
+            ) {
+                Ok(bump) => release_bumps.push(bump),
+                Err(e) => {
+                    warn!(repo = %spec.repo, error = %e, "cannot bump the repo's own version");
+                    release_bumps.push(json!({
+                        "repo": spec.repo,
+                        "error": format!("{e:#}"),
+                    }));
+                }
+            }
+        }
+
+        // Only a repo with nothing to do or report may be skipped next time.
+        let findings_after: usize = held_for_review.len()
+            + [
+                &rejected,
+                &unverified_manifests,
+                &workspace_conflicts,
+                &skipped_by_policy,
+                &suppressed_by_repo,
+                &patch_states,
+                &audits,
+                &frozen_repos,
+                &file_drift,
+                &template_drift,
+                &workflow_failures,
+                &license_drift,
+            ]
+            .iter()
+            .map(|findings| findings.len())
+            .sum::<usize>();
+        if pending_updates.len() == repo_start && findings_after == findings_before {
+            scan_state.settle(&spec.repo, fingerprint);
+        } else {
+            scan_state.unsettle(&spec.repo);
+        }
+        timings.repo(&spec.repo, Stage::Patch, repo_started.elapsed());
+    }
+}
+
+/// Checkout of `repo` under `base_dir`: its `local` name when it is a
+/// managed repo, its own name otherwise.
+fn source_checkout(config: &UpdateConfig, base_dir: &Path, repo: &str) -> PathBuf {
+    let local = config
+        .repos
+        .iter()
+        .find(|spec| spec.repo == repo)
+        .map_or(repo, |spec| spec.local.as_str());
+    base_dir.join(local)
+}
+
+/// Bumps the version of `spec`'s root package by `level`, alongside the
+/// repo's pending updates (from `repo_start` on).  The root manifest, its
+/// lockfile entry and the release workflow's version references change
+/// together: edits to files already pending are folded into those updates,
+/// new ones join the manifest's commit.  Returns the `release_bumps` entry.
+fn release_bump(
+    spec: &RepoConfig,
+    level: ReleaseBump,
+    repo_base: &Path,
+    run_id: &str,
+    pending_updates: &mut Vec<PendingUpdate>,
+    repo_start: usize,
+) -> anyhow::Result<Value> {
+    const MANIFEST: &str = "Cargo.toml";
+    let manifest_path = repo_base.join(MANIFEST);
+    let current = |file: &str, pending: &[PendingUpdate]| -> anyhow::Result<String> {
+        match pending[repo_start..].iter().find(|u| u.file_path == file) {
+            Some(update) => Ok(update.patched_content.clone()),
+            None => std::fs::read_to_string(repo_base.join(file))
+                .map_err(|e| anyhow::anyhow!("read {file}: {e}")),
+        }
+    };
+    let manifest = current(MANIFEST, pending_updates)?;
+    let (package, from) = release::package_version(&manifest)
+        .ok_or_else(|| anyhow::anyhow!("{MANIFEST} has no package version"))?;
+    let to = level
+        .apply(&from)
+        .ok_or_else(|| anyhow::anyhow!("cannot bump version {from} automatically"))?;
+    let label = package.as_deref().unwrap_or(&spec.repo);
+    let message = apply_type_scope(
+        &format!("chore(release): {label} {to} [run_id={run_id}]"),
+        spec.commit_type.as_deref(),
+        spec.commit_scope.as_deref(),
+    );
+
+    let mut edits = vec![(
+        MANIFEST.to_string(),
+        release::set_package_version(&manifest, &to)?,
+    )];
+    if let Some(lock) = sbom::find_lockfile(repo_base, &manifest_path)
+        && let Ok(lock_file) = lock.strip_prefix(repo_base)
+    {
+        let lock_file = lock_file.to_string_lossy().into_owned();
+        let content = current(&lock_file, pending_updates)?;
+        edits.push((
+            lock_file,
+            release::bump_lockfile(&content, package.as_deref(), &from, &to),
+        ));
+    }
+    if let Some(workflow) = &spec.release_workflow {
+        let content = current(workflow, pending_updates)?;
+        edits.push((
+            workflow.clone(),
+            release::bump_version_refs(&content, &from, &to),
+        ));
+    }
+
+    let mut files = Vec::new();
+    for (file, patched) in edits {
+        match pending_updates[repo_start..]
+            .iter_mut()
+            .find(|u| u.file_path == file)
+        {
+            Some(update) if update.patched_content == patched => {}
+            Some(update) => {
+                update.patched_content = patched;
+                if file == MANIFEST {
+                    update.commit_message =
+                        format!("{}\n\nRelease {label} {to}.", update.commit_message);
+                }
+            }
+            None => {
+                let original = std::fs::read_to_string(repo_base.join(&file))
+                    .map_err(|e| anyhow::anyhow!("read {file}: {e}"))?;
+                if original == patched {
+                    continue;
+                }
+                pending_updates.push(PendingUpdate {
+                    part_of: (file != MANIFEST).then(|| MANIFEST.to_string()),
+                    ..PendingUpdate::new(
+                        spec.repo.clone(),
+                        repo_base.to_path_buf(),
+                        file.clone(),
+                        original,
+                        patched,
+                        message.clone(),
+                    )
+                });
+            }
+        }
+        files.push(file);
+    }
+    info!(repo = %spec.repo, package = label, from = %from, to = %to, "bumping the repo's own version");
+    Ok(json!({
+        "repo": spec.repo,
+        "package": label,
+        "from": from,
+        "to": to,
+        "files": files,
+    }))
+}
+
+/// OSV results by `(crate, version)`, shared by every repo of a run.
+type OsvCache = HashMap<(String, String), Result<Vec<Vulnerability>, String>>;
+
+/// Vulnerabilities of `crate_name@version`, queried once per run.
+async fn osv_lookup(
+    registry: &dyn RegistryClient,
+    cache: &mut OsvCache,
+    crate_name: &str,
+    version: &str,
+) -> Result<Vec<Vulnerability>, String> {
+    let key = (crate_name.to_string(), version.to_string());
+    if let Some(cached) = cache.get(&key) {
+        return cached.clone();
+    }
+    let result = registry
+        .vulnerabilities(crate_name, version)
+        .await
+        .map_err(|e| format!("{e:#}"));
+    cache.insert(key, result.clone());
+    result
+}
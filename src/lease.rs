@@ -0,0 +1,307 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::backend::KingClient;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Name of the lease every replica of this agent competes for.
+const LEASE_NAME: &str = "evo-kernel-agent-update";
+
+/// Lease lifetime when `UPDATE_LEASE_TTL_SECS` is unset.
+const DEFAULT_TTL_SECS: u64 = 300;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Whether the king granted the lease, as reported in the run summary.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LeaseStatus {
+    pub acquired: bool,
+    /// `run_id` of the replica holding the lease when it was refused.
+    #[serde(default)]
+    pub holder: Option<String>,
+    /// Unix seconds when the current holder's lease expires.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Why the lease could not be requested (king unreachable, …).
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The lease was lost while the run was delivering; the rest was held.
+    #[serde(default)]
+    pub lost: bool,
+}
+
+/// Lease settings from the environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaseConfig {
+    pub ttl: Duration,
+}
+
+impl LeaseConfig {
+    /// `Some` when `UPDATE_LEASE` is enabled; the lifetime comes from
+    /// `UPDATE_LEASE_TTL_SECS` (default 300).
+    pub fn from_env() -> Option<Self> {
+        let enabled =
+            std::env::var("UPDATE_LEASE").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        enabled.then(|| Self {
+            ttl: Duration::from_secs(
+                std::env::var("UPDATE_LEASE_TTL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|&secs| secs > 0)
+                    .unwrap_or(DEFAULT_TTL_SECS),
+            ),
+        })
+    }
+}
+
+// ─── Lease ────────────────────────────────────────────────────────────────────
+
+/// A lease held through the king's `/admin/lease/*` endpoints, so only one
+/// replica of the agent commits during any window.
+///
+/// While held, the lease is renewed in the background every quarter of its
+/// lifetime; [`Lease::is_valid`] turns false once another replica takes it
+/// or no renewal succeeded for two thirds of a lifetime, well before the
+/// king can let it expire.  [`Lease::release`] gives it back;
+/// if the agent dies instead, the king lets it expire.
+pub struct Lease {
+    king: Arc<dyn KingClient>,
+    run_id: String,
+    valid: Arc<AtomicBool>,
+    renewal: JoinHandle<()>,
+}
+
+impl Lease {
+    /// Asks the king for the lease on behalf of `run_id`.
+    ///
+    /// Returns the lease (if granted) together with the status to report.
+    /// A king that cannot be reached counts as not granted.
+    pub async fn acquire(
+        king: &Arc<dyn KingClient>,
+        run_id: &str,
+        config: &LeaseConfig,
+    ) -> (Option<Self>, LeaseStatus) {
+        // The king's lease starts after this, so it lasts at least until
+        // `requested + ttl`.
+        let requested = Instant::now();
+        match request(&**king, "acquire", run_id, Some(config.ttl)).await {
+            Ok(status) if status.acquired => {
+                let valid = Arc::new(AtomicBool::new(true));
+                let lease = Self {
+                    king: Arc::clone(king),
+                    run_id: run_id.to_string(),
+                    renewal: spawn_renewal(king, run_id, config.ttl, requested, Arc::clone(&valid)),
+                    valid,
+                };
+                (Some(lease), status)
+            }
+            Ok(status) => (None, status),
+            Err(e) => (
+                None,
+                LeaseStatus {
+                    error: Some(format!("{e:#}")),
+                    ..LeaseStatus::default()
+                },
+            ),
+        }
+    }
+
+    /// Whether the lease is still ours.  Checked before each repo is
+    /// delivered, so a replica that lost it stops committing.
+    pub fn is_valid(&self) -> bool {
+        self.valid.load(Ordering::Acquire)
+    }
+
+    /// Stops renewing and gives the lease back.
+    pub async fn release(self) {
+        self.renewal.abort();
+        if let Err(e) = request(&*self.king, "release", &self.run_id, None).await {
+            warn!(error = %e, "releasing the run lease failed — it will expire on its own");
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+/// Renews the lease every `ttl / 4` until aborted.  Clears `valid` and
+/// stops once the king hands the lease to another replica, or when no
+/// renewal sent after `renewed` succeeded within `2 * ttl / 3`: the lease is
+/// given up a third of its lifetime before the king can expire it, so no
+/// other replica holds it while this one still commits.  A renewal still
+/// in flight at that point does not extend it.
+fn spawn_renewal(
+    king: &Arc<dyn KingClient>,
+    run_id: &str,
+    ttl: Duration,
+    mut renewed: Instant,
+    valid: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let (king, run_id) = (Arc::clone(king), run_id.to_string());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ttl / 4);
+        interval.tick().await;
+        loop {
+            let deadline = renewed + ttl * 2 / 3;
+            let sent = tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {
+                    warn!("no run lease renewal succeeded for two thirds of its lifetime — giving it up");
+                    valid.store(false, Ordering::Release);
+                    return;
+                }
+                sent = interval.tick() => sent,
+            };
+            match tokio::time::timeout_at(deadline, request(&*king, "renew", &run_id, Some(ttl)))
+                .await
+            {
+                Ok(Ok(status)) if status.acquired => {
+                    debug!("run lease renewed");
+                    renewed = sent;
+                }
+                Ok(Ok(status)) => {
+                    warn!(holder = ?status.holder, "run lease lost to another replica");
+                    valid.store(false, Ordering::Release);
+                    return;
+                }
+                Ok(Err(e)) => warn!(error = %e, "renewing the run lease failed"),
+                Err(_) => warn!("renewing the run lease timed out"),
+            }
+        }
+    })
+}
+
+/// POSTs the king's `/admin/lease/{op}`.
+///
+/// The king answers `200` with `{"acquired": true, …}` when the lease is
+/// (still) ours and `409` with the current holder when it is not.
+async fn request(
+    king: &dyn KingClient,
+    op: &str,
+    run_id: &str,
+    ttl: Option<Duration>,
+) -> Result<LeaseStatus> {
+    let path = format!("/admin/lease/{op}");
+    let mut body = json!({ "name": LEASE_NAME, "run_id": run_id });
+    if let Some(ttl) = ttl {
+        body["ttl_secs"] = json!(ttl.as_secs());
+    }
+    let (status, text) = king.post(&path, &body).await?;
+    parse_response(status, &text).with_context(|| format!("{} returned {status}", king.url(&path)))
+}
+
+/// Interprets a lease endpoint response.
+fn parse_response(status: reqwest::StatusCode, body: &str) -> Result<LeaseStatus> {
+    if status == reqwest::StatusCode::CONFLICT {
+        let mut refused: LeaseStatus = serde_json::from_str(body).unwrap_or_default();
+        refused.acquired = false;
+        return Ok(refused);
+    }
+    anyhow::ensure!(status.is_success(), "unexpected status");
+    if body.trim().is_empty() {
+        // Release answers with an empty body.
+        return Ok(LeaseStatus {
+            acquired: true,
+            ..LeaseStatus::default()
+        });
+    }
+    serde_json::from_str(body).context("parse lease response")
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::FakeKing;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_parse_granted_and_refused() {
+        let granted = parse_response(
+            StatusCode::OK,
+            r#"{"acquired": true, "expires_at": 1700000300}"#,
+        )
+        .unwrap();
+        assert!(granted.acquired);
+        assert_eq!(granted.expires_at, Some(1_700_000_300));
+
+        let refused = parse_response(
+            StatusCode::CONFLICT,
+            r#"{"acquired": true, "holder": "run-1", "expires_at": 1700000300}"#,
+        )
+        .unwrap();
+        assert!(!refused.acquired);
+        assert_eq!(refused.holder.as_deref(), Some("run-1"));
+
+        assert!(parse_response(StatusCode::CONFLICT, "busy").is_ok());
+        assert!(parse_response(StatusCode::INTERNAL_SERVER_ERROR, "").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lease_lost_to_another_replica() {
+        let fake = Arc::new(FakeKing::default());
+        fake.respond(
+            "/admin/lease/acquire",
+            StatusCode::OK,
+            r#"{"acquired": true}"#,
+        );
+        let king: Arc<dyn KingClient> = fake.clone();
+        let config = LeaseConfig {
+            ttl: Duration::from_millis(30),
+        };
+        let (lease, status) = Lease::acquire(&king, "run-1", &config).await;
+        let lease = lease.unwrap();
+        assert!(status.acquired);
+        assert!(lease.is_valid());
+
+        fake.respond(
+            "/admin/lease/renew",
+            StatusCode::CONFLICT,
+            r#"{"holder": "run-2"}"#,
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!lease.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_lease_given_up_before_it_can_expire() {
+        let fake = Arc::new(FakeKing::default());
+        fake.respond(
+            "/admin/lease/acquire",
+            StatusCode::OK,
+            r#"{"acquired": true}"#,
+        );
+        fake.respond("/admin/lease/renew", StatusCode::BAD_GATEWAY, "");
+        let king: Arc<dyn KingClient> = fake.clone();
+        let config = LeaseConfig {
+            ttl: Duration::from_millis(900),
+        };
+        let (lease, _) = Lease::acquire(&king, "run-1", &config).await;
+        let lease = lease.unwrap();
+
+        // Renewals keep failing: the lease is given up at 600 ms, while the
+        // king would only let it expire at 900 ms.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(lease.is_valid());
+        tokio::time::sleep(Duration::from_millis(450)).await;
+        assert!(!lease.is_valid());
+        assert!(
+            fake.requests()
+                .iter()
+                .filter(|(path, _)| path == "/admin/lease/renew")
+                .count()
+                >= 2
+        );
+    }
+}
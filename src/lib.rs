@@ -0,0 +1,105 @@
+//! Dependency updates for the evo fleet.
+//!
+//! The `evo-agent-update` binary runs [`UpdateHandler`]; the modules below
+//! are public so other agents can reuse the same building blocks:
+//!
+//! - [`updater`] and [`cargo_edit`] patch `Cargo.toml` files and CI
+//!   workflows, keeping their formatting; [`pyproject`], [`package_json`]
+//!   and [`gomod`] do the same for Python, JS and Go repos.
+//! - [`versions`] looks up the latest published versions and compares them
+//!   with a manifest's requirements.
+//! - [`git`] and [`commit_message`] commit changed files through the `gh`
+//!   CLI, a local checkout or the GitHub REST API.
+//! - [`policy`] evaluates the pin / allow / deny rules of [`config`].
+//! - [`plan`] stores, signs and checks reviewed update plans.
+//! - [`summary`] types the JSON summary a run returns.
+//! - [`backend`] abstracts the registry, VCS and king a run talks to, with
+//!   in-memory fakes for tests; [`fixtures`] records and replays registry
+//!   responses.
+//!
+//! ```
+//! use evo_kernel_agent_update::{updater, versions};
+//!
+//! let manifest = "[dependencies]\nevo-common = \"0.3\"\n";
+//! assert!(versions::needs_update(
+//!     &versions::current_dep_version(manifest, "evo-common").unwrap(),
+//!     "0.4.0",
+//! ));
+//! let outcome = updater::patch_cargo_toml(manifest, "evo-common", "0.4.0").unwrap();
+//! assert!(outcome.content.contains("evo-common = \"0.4.0\""));
+//! ```
+
+mod approval;
+mod audit;
+mod audit_log;
+pub mod backend;
+mod badges;
+pub mod cargo_edit;
+mod changelog;
+mod checkout;
+mod checkpoint;
+mod checks;
+pub mod checksum;
+pub mod commit_message;
+pub mod config;
+mod deny;
+mod diff;
+mod file_sync;
+pub mod fixtures;
+mod fleet_audit;
+mod fleet_tag;
+mod freeze;
+pub mod git;
+mod github;
+pub mod gomod;
+mod handler;
+mod lease;
+mod license;
+pub mod llm;
+mod mac;
+pub mod metadata;
+mod migrate;
+pub mod net;
+mod notify;
+pub mod osv;
+mod outdated;
+pub mod ownership;
+pub mod package_json;
+mod patches;
+pub mod plan;
+pub mod policy;
+mod pr;
+pub mod preflight;
+pub mod provenance;
+mod publish_lag;
+mod publish_wait;
+pub mod pyproject;
+pub mod registry;
+mod release;
+mod repo_config;
+mod retry_queue;
+mod risk;
+mod rollout;
+mod run_lock;
+mod runs;
+mod sandbox;
+mod sbom;
+mod scan_state;
+mod schemas;
+mod state;
+pub mod summary;
+mod template;
+mod timing;
+mod tokens;
+pub mod toolchain;
+pub mod updater;
+mod upstream;
+mod validate;
+pub mod versions;
+mod vet;
+pub mod watcher;
+pub mod webhook;
+mod workflow_sync;
+mod workspace;
+
+pub use handler::{UpdateHandler, builtin_config};
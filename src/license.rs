@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::file_sync::{FileSyncCampaign, SyncMode};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Tag of an SPDX license header line.
+pub const SPDX_TAG: &str = "SPDX-License-Identifier:";
+
+/// Lines at the top of a file searched for an existing header.
+const HEADER_LINES: usize = 5;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[license]` section of the config file: the org's license policy.
+///
+/// ```toml
+/// [license]
+/// expression = "MIT OR Apache-2.0"    # the campaign is off while unset
+/// source_repo = "evo-common"
+/// files = ["LICENSE-MIT", "LICENSE-APACHE"]
+/// extensions = ["rs"]
+/// exclude = ["target", "vendor"]
+/// mode = "sync"                       # "sync" | "check"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LicensePolicy {
+    /// SPDX expression every source file's header must carry.
+    pub expression: Option<String>,
+    /// Repo holding the canonical license texts.
+    pub source_repo: String,
+    /// License texts kept identical in every repo.
+    pub files: Vec<String>,
+    /// Extensions (without the dot) of the source files needing a header.
+    pub extensions: Vec<String>,
+    /// Directories, relative to the repo root, whose files are not checked.
+    pub exclude: Vec<String>,
+    pub mode: SyncMode,
+}
+
+impl Default for LicensePolicy {
+    fn default() -> Self {
+        Self {
+            expression: None,
+            source_repo: "evo-common".to_string(),
+            files: vec!["LICENSE".to_string()],
+            extensions: vec!["rs".to_string()],
+            exclude: vec!["target".to_string(), "vendor".to_string()],
+            mode: SyncMode::Sync,
+        }
+    }
+}
+
+impl LicensePolicy {
+    /// The license texts as a `[[file_sync]]`-style campaign, so they are
+    /// loaded and compared like any shared file.
+    pub fn campaign(&self) -> FileSyncCampaign {
+        FileSyncCampaign {
+            name: "license".to_string(),
+            source_repo: self.source_repo.clone(),
+            files: self.files.clone(),
+            mode: self.mode,
+        }
+    }
+
+    /// Whether `path` (repo-relative) lies under an `exclude` directory.
+    fn excluded(&self, path: &str) -> bool {
+        self.exclude.iter().any(|dir| {
+            let dir = dir.trim_matches('/');
+            path == dir || path.starts_with(&format!("{dir}/"))
+        })
+    }
+}
+
+// ─── Source files ─────────────────────────────────────────────────────────────
+
+/// Line-comment prefix of a source file, by extension.
+pub fn comment_prefix(path: &str) -> Option<&'static str> {
+    let ext = Path::new(path).extension()?.to_str()?;
+    match ext {
+        "rs" | "go" | "js" | "ts" | "c" | "h" | "cc" | "cpp" | "java" | "proto" => Some("//"),
+        "py" | "sh" | "toml" | "yml" | "yaml" | "rb" => Some("#"),
+        _ => None,
+    }
+}
+
+/// Repo-relative paths of the checkout's source files that need a header:
+/// the policy's extensions, outside hidden and excluded directories.
+pub fn source_files(repo_base: &Path, policy: &LicensePolicy) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    collect(repo_base, "", policy, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect(
+    dir: &Path,
+    prefix: &str,
+    policy: &LicensePolicy,
+    files: &mut Vec<String>,
+) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("read {}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let relative = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+        if policy.excluded(&relative) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect(&path, &relative, policy, files)?;
+        } else if comment_prefix(&relative).is_some()
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| policy.extensions.iter().any(|e| e == ext))
+        {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+// ─── Headers ──────────────────────────────────────────────────────────────────
+
+/// What is wrong with a file's SPDX header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum HeaderIssue {
+    /// No `SPDX-License-Identifier:` line near the top.
+    MissingHeader,
+    /// A header naming another expression.
+    WrongHeader { found: String },
+}
+
+/// Index and expression of the SPDX line among the first lines of `content`.
+fn find_header(content: &str) -> Option<(usize, &str)> {
+    content
+        .lines()
+        .take(HEADER_LINES)
+        .enumerate()
+        .find_map(|(idx, line)| {
+            let (_, expression) = line.split_once(SPDX_TAG)?;
+            Some((idx, expression.trim().trim_end_matches("*/").trim()))
+        })
+}
+
+/// Whether `content` carries `expression` in its SPDX header.
+pub fn check_header(content: &str, expression: &str) -> Option<HeaderIssue> {
+    match find_header(content) {
+        None => Some(HeaderIssue::MissingHeader),
+        Some((_, found)) if found != expression => Some(HeaderIssue::WrongHeader {
+            found: found.to_string(),
+        }),
+        Some(_) => None,
+    }
+}
+
+/// `content` with its SPDX header set to `expression`: a wrong header is
+/// rewritten in place, a missing one is added as the first line (after a
+/// `#!` shebang).
+pub fn fix_header(path: &str, content: &str, expression: &str) -> String {
+    let Some(prefix) = comment_prefix(path) else {
+        return content.to_string();
+    };
+    let header = format!("{prefix} {SPDX_TAG} {expression}");
+    if let Some((idx, _)) = find_header(content) {
+        return content
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, line)| match (i == idx, line.ends_with('\n')) {
+                (false, _) => line.to_string(),
+                (true, true) => format!("{header}\n"),
+                (true, false) => header.clone(),
+            })
+            .collect();
+    }
+    if content.starts_with("#!") && !content.starts_with("#![") {
+        return match content.split_once('\n') {
+            Some((shebang, rest)) => format!("{shebang}\n{header}\n{rest}"),
+            None => format!("{content}\n{header}\n"),
+        };
+    }
+    format!("{header}\n{content}")
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPR: &str = "MIT OR Apache-2.0";
+
+    #[test]
+    fn test_headers() {
+        let fixed = fix_header("src/lib.rs", "pub mod a;\n", EXPR);
+        assert_eq!(
+            fixed,
+            "// SPDX-License-Identifier: MIT OR Apache-2.0\npub mod a;\n"
+        );
+        assert_eq!(check_header(&fixed, EXPR), None);
+        assert_eq!(
+            check_header("pub mod a;\n", EXPR),
+            Some(HeaderIssue::MissingHeader)
+        );
+
+        let wrong = "//! Crate docs\n// SPDX-License-Identifier: GPL-3.0\nfn main() {}\n";
+        assert_eq!(
+            check_header(wrong, EXPR),
+            Some(HeaderIssue::WrongHeader {
+                found: "GPL-3.0".into()
+            })
+        );
+        assert_eq!(
+            fix_header("src/main.rs", wrong, EXPR),
+            "//! Crate docs\n// SPDX-License-Identifier: MIT OR Apache-2.0\nfn main() {}\n"
+        );
+
+        let script = "#!/usr/bin/env python3\nprint('hi')\n";
+        assert_eq!(
+            fix_header("tools/run.py", script, "MIT"),
+            "#!/usr/bin/env python3\n# SPDX-License-Identifier: MIT\nprint('hi')\n"
+        );
+        assert_eq!(
+            fix_header("src/lib.rs", "#![deny(missing_docs)]\n", "MIT"),
+            "// SPDX-License-Identifier: MIT\n#![deny(missing_docs)]\n"
+        );
+    }
+
+    #[test]
+    fn test_source_files() {
+        let repo = tempfile::tempdir().unwrap();
+        for dir in ["src/bin", "target/debug", ".cargo", "vendor/x"] {
+            std::fs::create_dir_all(repo.path().join(dir)).unwrap();
+        }
+        for file in [
+            "src/lib.rs",
+            "src/bin/tool.rs",
+            "src/README.md",
+            "build.rs",
+            "target/debug/out.rs",
+            ".cargo/config.rs",
+            "vendor/x/lib.rs",
+        ] {
+            std::fs::write(repo.path().join(file), "").unwrap();
+        }
+        assert_eq!(
+            source_files(repo.path(), &LicensePolicy::default()).unwrap(),
+            vec!["build.rs", "src/bin/tool.rs", "src/lib.rs"]
+        );
+    }
+}
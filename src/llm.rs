@@ -0,0 +1,456 @@
+use anyhow::{Context, Result};
+use evo_agent_sdk::prelude::GatewayClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::warn;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// USD per million `(prompt, completion)` tokens of the models the agent
+/// uses.
+const PRICES: &[(&str, f64, f64)] = &[("gpt-4o", 2.50, 10.00), ("gpt-4o-mini", 0.15, 0.60)];
+
+/// Rough characters per token of English text and code.
+const CHARS_PER_TOKEN: usize = 4;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// What a gateway call is for; each purpose has its own model parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    /// Phase 3 changelog-risk analysis.
+    RiskAnalysis,
+    /// Summary of a bump's upstream commit log.
+    UpstreamSummary,
+    /// Source fixes after a bump breaks the build.
+    Migration,
+}
+
+/// Model parameters of one purpose; unset fields fall through to the next
+/// layer (see [`LlmConfig::resolve`]).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelParams {
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+}
+
+/// `[llm]` section of the config file, also accepted from the soul, the
+/// environment and `metadata.llm`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    /// Template of the risk-analysis prompt; `{updates}` is replaced with
+    /// the bumps, one per line.
+    pub analysis_prompt: Option<String>,
+    pub risk_analysis: ModelParams,
+    pub upstream_summary: ModelParams,
+    pub migration: ModelParams,
+}
+
+/// Parameters of one call.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Settings {
+    pub model: String,
+    pub temperature: f64,
+    pub max_tokens: u32,
+}
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Tokens and estimated cost of the gateway calls made for one purpose.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Usage {
+    pub calls: u32,
+    /// Calls the gateway answered with an error; their tokens are not counted.
+    pub failed: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// `None` when a model without a known price was used.
+    pub estimated_cost_usd: Option<f64>,
+}
+
+/// `llm_usage` of the run summary.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageReport {
+    #[serde(flatten)]
+    pub total: Usage,
+    /// The gateway returns no token counts, so they are estimated from the
+    /// text length.
+    pub tokens_estimated: bool,
+    pub by_purpose: BTreeMap<String, Usage>,
+    pub by_model: BTreeMap<String, Usage>,
+    /// Why the changelog analysis was skipped (`requested`, `patch_only`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+/// The run's gateway, metering every call.
+pub struct Llm<'a> {
+    gateway: &'a GatewayClient,
+    config: LlmConfig,
+    calls: Mutex<Vec<Call>>,
+}
+
+#[derive(Debug, Clone)]
+struct Call {
+    purpose: &'static str,
+    model: String,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    failed: bool,
+}
+
+impl Purpose {
+    pub const ALL: [Purpose; 3] = [
+        Purpose::RiskAnalysis,
+        Purpose::UpstreamSummary,
+        Purpose::Migration,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Purpose::RiskAnalysis => "risk_analysis",
+            Purpose::UpstreamSummary => "upstream_summary",
+            Purpose::Migration => "migration",
+        }
+    }
+
+    /// Built-in parameters.
+    fn defaults(self) -> Settings {
+        let (model, temperature, max_tokens) = match self {
+            Purpose::RiskAnalysis => ("gpt-4o-mini", 0.3, 500),
+            Purpose::UpstreamSummary => ("gpt-4o-mini", 0.2, 400),
+            Purpose::Migration => ("gpt-4o", 0.0, 8_000),
+        };
+        Settings {
+            model: model.to_string(),
+            temperature,
+            max_tokens,
+        }
+    }
+}
+
+impl ModelParams {
+    fn overridden_by(self, over: ModelParams) -> Self {
+        Self {
+            model: over.model.or(self.model),
+            temperature: over.temperature.or(self.temperature),
+            max_tokens: over.max_tokens.or(self.max_tokens),
+        }
+    }
+}
+
+impl LlmConfig {
+    /// The parameters in effect for a run, each field taken from the first
+    /// layer that sets it: `metadata.llm`, the environment, an `[llm]` table
+    /// in the soul, the config file's `[llm]`, the built-in defaults.
+    /// Unreadable layers are skipped with a warning.
+    pub fn resolve(file: &LlmConfig, soul: &str, metadata: &Value) -> Self {
+        let soul = Self::from_soul(soul).unwrap_or_else(|e| {
+            warn!(error = %format!("{e:#}"), "ignoring the soul's [llm] table");
+            None
+        });
+        let metadata = Self::from_metadata(metadata).unwrap_or_else(|e| {
+            warn!(error = %format!("{e:#}"), "ignoring metadata.llm");
+            None
+        });
+        [soul, Some(Self::from_env()), metadata]
+            .into_iter()
+            .flatten()
+            .fold(file.clone(), Self::overridden_by)
+    }
+
+    /// `self` with every field `over` sets replaced.
+    pub fn overridden_by(self, over: LlmConfig) -> Self {
+        Self {
+            analysis_prompt: over.analysis_prompt.or(self.analysis_prompt),
+            risk_analysis: self.risk_analysis.overridden_by(over.risk_analysis),
+            upstream_summary: self.upstream_summary.overridden_by(over.upstream_summary),
+            migration: self.migration.overridden_by(over.migration),
+        }
+    }
+
+    /// The `[llm]` table of a ` ```toml ` block in the soul's behavior.
+    pub fn from_soul(behavior: &str) -> Result<Option<Self>> {
+        #[derive(Deserialize)]
+        struct SoulToml {
+            llm: Option<LlmConfig>,
+        }
+        let mut rest = behavior;
+        while let Some(start) = rest.find("```toml") {
+            let block = &rest[start + "```toml".len()..];
+            let end = block.find("```").unwrap_or(block.len());
+            rest = &block[end..];
+            let toml: SoulToml =
+                toml_edit::de::from_str(&block[..end]).context("parse the soul's toml block")?;
+            if toml.llm.is_some() {
+                return Ok(toml.llm);
+            }
+        }
+        Ok(None)
+    }
+
+    /// `LLM_ANALYSIS_PROMPT` and `LLM_<PURPOSE>_MODEL`, `_TEMPERATURE` and
+    /// `_MAX_TOKENS`; unparsable numbers are ignored.
+    pub fn from_env() -> Self {
+        let mut config = Self {
+            analysis_prompt: env_var("LLM_ANALYSIS_PROMPT"),
+            ..Self::default()
+        };
+        for purpose in Purpose::ALL {
+            let prefix = format!("LLM_{}", purpose.as_str().to_uppercase());
+            *config.params_mut(purpose) = ModelParams {
+                model: env_var(&format!("{prefix}_MODEL")),
+                temperature: env_number(&format!("{prefix}_TEMPERATURE")),
+                max_tokens: env_number(&format!("{prefix}_MAX_TOKENS")),
+            };
+        }
+        config
+    }
+
+    /// `metadata.llm`, in the shape of the `[llm]` section.
+    pub fn from_metadata(metadata: &Value) -> Result<Option<Self>> {
+        metadata
+            .get("llm")
+            .map(|llm| serde_json::from_value(llm.clone()).context("parse metadata.llm"))
+            .transpose()
+    }
+
+    /// The parameters of `purpose`, defaults filled in.
+    pub fn settings(&self, purpose: Purpose) -> Settings {
+        let params = self.params(purpose).clone();
+        let defaults = purpose.defaults();
+        Settings {
+            model: params.model.unwrap_or(defaults.model),
+            temperature: params.temperature.unwrap_or(defaults.temperature),
+            max_tokens: params.max_tokens.unwrap_or(defaults.max_tokens),
+        }
+    }
+
+    fn params(&self, purpose: Purpose) -> &ModelParams {
+        match purpose {
+            Purpose::RiskAnalysis => &self.risk_analysis,
+            Purpose::UpstreamSummary => &self.upstream_summary,
+            Purpose::Migration => &self.migration,
+        }
+    }
+
+    fn params_mut(&mut self, purpose: Purpose) -> &mut ModelParams {
+        match purpose {
+            Purpose::RiskAnalysis => &mut self.risk_analysis,
+            Purpose::UpstreamSummary => &mut self.upstream_summary,
+            Purpose::Migration => &mut self.migration,
+        }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn env_number<T: FromStr>(name: &str) -> Option<T> {
+    let value = env_var(name)?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        warn!(var = name, value = %value, "not a number — ignored");
+    }
+    parsed
+}
+
+// ─── Metering ─────────────────────────────────────────────────────────────────
+
+impl<'a> Llm<'a> {
+    pub fn new(gateway: &'a GatewayClient, config: LlmConfig) -> Self {
+        Self {
+            gateway,
+            config,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The parameters in effect.
+    pub fn config(&self) -> &LlmConfig {
+        &self.config
+    }
+
+    /// `chat_completion` on the gateway with the parameters of `purpose`,
+    /// recorded under it.
+    pub async fn complete(&self, purpose: Purpose, system: &str, prompt: &str) -> Result<String> {
+        let Settings {
+            model,
+            temperature,
+            max_tokens,
+        } = self.config.settings(purpose);
+        let result = self
+            .gateway
+            .chat_completion(&model, system, prompt, Some(temperature), Some(max_tokens))
+            .await;
+        let (prompt_tokens, completion_tokens) = match &result {
+            Ok(response) => (
+                estimate_tokens(system) + estimate_tokens(prompt),
+                estimate_tokens(response),
+            ),
+            Err(_) => (0, 0),
+        };
+        self.calls.lock().unwrap().push(Call {
+            purpose: purpose.as_str(),
+            model,
+            prompt_tokens,
+            completion_tokens,
+            failed: result.is_err(),
+        });
+        result
+    }
+
+    /// Totals of the calls made so far.
+    pub fn report(&self) -> UsageReport {
+        let mut report = UsageReport {
+            tokens_estimated: true,
+            ..Default::default()
+        };
+        for call in self.calls.lock().unwrap().iter() {
+            for usage in [
+                &mut report.total,
+                report
+                    .by_purpose
+                    .entry(call.purpose.to_string())
+                    .or_default(),
+                report.by_model.entry(call.model.clone()).or_default(),
+            ] {
+                usage.add(call);
+            }
+        }
+        report
+    }
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Self {
+            calls: 0,
+            failed: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            estimated_cost_usd: Some(0.0),
+        }
+    }
+}
+
+impl Usage {
+    fn add(&mut self, call: &Call) {
+        self.calls += 1;
+        self.failed += u32::from(call.failed);
+        self.prompt_tokens += call.prompt_tokens;
+        self.completion_tokens += call.completion_tokens;
+        self.estimated_cost_usd = self
+            .estimated_cost_usd
+            .zip(cost_usd(
+                &call.model,
+                call.prompt_tokens,
+                call.completion_tokens,
+            ))
+            .map(|(total, cost)| total + cost);
+    }
+}
+
+/// Estimated USD cost of `prompt` and `completion` tokens of `model`.
+pub fn cost_usd(model: &str, prompt: u64, completion: u64) -> Option<f64> {
+    let (_, input, output) = PRICES.iter().find(|(name, _, _)| *name == model)?;
+    Some((prompt as f64 * input + completion as f64 * output) / 1_000_000.0)
+}
+
+/// Tokens in `text`, at [`CHARS_PER_TOKEN`].
+fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cost_usd() {
+        assert_eq!(cost_usd("gpt-4o", 1_000_000, 0), Some(2.5));
+        assert_eq!(cost_usd("gpt-4o-mini", 0, 1_000_000), Some(0.6));
+        assert_eq!(cost_usd("local-llama", 10, 10), None);
+    }
+
+    #[tokio::test]
+    async fn test_report_totals_calls() {
+        let llm = Llm::new(&GatewayClient, LlmConfig::default());
+        llm.complete(Purpose::RiskAnalysis, "", "12345678")
+            .await
+            .unwrap();
+        llm.complete(Purpose::Migration, "abcd", "efgh")
+            .await
+            .unwrap();
+
+        let report = llm.report();
+        assert!(report.tokens_estimated);
+        assert_eq!(report.total.calls, 2);
+        assert_eq!(report.total.prompt_tokens, 4);
+        assert_eq!(report.by_purpose["risk_analysis"].prompt_tokens, 2);
+        assert_eq!(report.by_model["gpt-4o"].prompt_tokens, 2);
+        let cost = report.total.estimated_cost_usd.unwrap();
+        assert!((cost - (2.0 * 0.15 + 2.0 * 2.50) / 1_000_000.0).abs() < 1e-12);
+
+        let llm = Llm::new(
+            &GatewayClient,
+            LlmConfig {
+                migration: ModelParams {
+                    model: Some("local-llama".to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        llm.complete(Purpose::Migration, "abcd", "efgh")
+            .await
+            .unwrap();
+        llm.complete(Purpose::RiskAnalysis, "", "x").await.unwrap();
+        let report = llm.report();
+        assert_eq!(report.total.estimated_cost_usd, None);
+        assert!(report.by_model["gpt-4o-mini"].estimated_cost_usd.is_some());
+    }
+
+    #[test]
+    fn test_layers_override_field_by_field() {
+        let file = LlmConfig {
+            risk_analysis: ModelParams {
+                model: Some("gpt-4o".to_string()),
+                max_tokens: Some(900),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let soul = "Checks crates.\n\n```toml\n[llm]\nanalysis_prompt = \"Rate:\\n{updates}\"\n\n[llm.risk_analysis]\ntemperature = 0.0\n```\n";
+        let metadata = json!({ "llm": { "risk_analysis": { "max_tokens": 200 } } });
+
+        let config = LlmConfig::resolve(&file, soul, &metadata);
+        assert_eq!(config.analysis_prompt.as_deref(), Some("Rate:\n{updates}"));
+        assert_eq!(
+            config.settings(Purpose::RiskAnalysis),
+            Settings {
+                model: "gpt-4o".to_string(),
+                temperature: 0.0,
+                max_tokens: 200,
+            }
+        );
+        assert_eq!(
+            config.settings(Purpose::UpstreamSummary),
+            Purpose::UpstreamSummary.defaults()
+        );
+
+        assert!(LlmConfig::from_soul("```toml\n[llm]\nmodel = 1\n```").is_err());
+        assert_eq!(LlmConfig::from_soul("no toml here").unwrap(), None);
+        assert!(LlmConfig::from_metadata(&json!({ "llm": { "temp": 1 } })).is_err());
+    }
+}
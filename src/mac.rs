@@ -0,0 +1,66 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// ─── HMAC-SHA256 ──────────────────────────────────────────────────────────────
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Hex encoded HMAC-SHA256 of `message` under `key`.
+pub fn hmac_sha256_hex(key: &[u8], message: &[u8]) -> String {
+    hex::encode(keyed(key, message).finalize().into_bytes())
+}
+
+/// Whether `tag` (hex, either case) is the HMAC-SHA256 of `message` under
+/// `key`.  Compared in constant time.
+pub fn verify_hmac_sha256_hex(key: &[u8], message: &[u8], tag: &str) -> bool {
+    let Ok(tag) = hex::decode(tag.trim()) else {
+        return false;
+    };
+    keyed(key, message).verify_slice(&tag).is_ok()
+}
+
+fn keyed(key: &[u8], message: &[u8]) -> HmacSha256 {
+    // SAFETY: HMAC accepts keys of any length.
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(message);
+    mac
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        // Test case 2.
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Test case 6: a key longer than the block size is hashed first.
+        assert_eq!(
+            hmac_sha256_hex(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_verify_hmac_sha256_hex() {
+        let tag = hmac_sha256_hex(b"s3cret", b"body");
+        assert!(verify_hmac_sha256_hex(b"s3cret", b"body", &tag));
+        assert!(verify_hmac_sha256_hex(
+            b"s3cret",
+            b"body",
+            &tag.to_ascii_uppercase()
+        ));
+        assert!(!verify_hmac_sha256_hex(b"other", b"body", &tag));
+        assert!(!verify_hmac_sha256_hex(b"s3cret", b"tampered", &tag));
+        assert!(!verify_hmac_sha256_hex(b"s3cret", b"body", "not hex"));
+        assert!(!verify_hmac_sha256_hex(b"s3cret", b"body", ""));
+    }
+}
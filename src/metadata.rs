@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::fixtures::RegistryHttp;
+use crate::registry::Registry;
+use crate::state::state_dir;
+use crate::versions::HttpStatus;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Upstream links a crate publishes on its registry page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CrateLinks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<String>,
+}
+
+/// Links of one crate as last fetched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedLinks {
+    #[serde(flatten)]
+    pub links: CrateLinks,
+    /// Unix seconds when the links were fetched.
+    pub fetched_at: u64,
+}
+
+/// On-disk cache of [`CrateLinks`], so each crate's registry page is read
+/// at most once per [`LinkCache::ttl_from_env`] and offline runs still have
+/// links.
+#[derive(Debug, Clone)]
+pub struct LinkCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CachedLinks>,
+}
+
+// ─── crates.io API types ──────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct CratesIoCrate {
+    #[serde(rename = "crate")]
+    krate: CrateLinks,
+}
+
+// ─── Links ────────────────────────────────────────────────────────────────────
+
+impl CrateLinks {
+    /// `owner/name` of the GitHub repo hosting the source: the
+    /// `repository` link, else the `homepage`.
+    pub fn github_repo(&self) -> Option<String> {
+        [&self.repository, &self.homepage]
+            .into_iter()
+            .flatten()
+            .find_map(|url| github_slug(url))
+    }
+
+    /// Where the source lives: the `repository` link, else the `homepage`.
+    pub fn source_url(&self) -> Option<&str> {
+        self.repository.as_deref().or(self.homepage.as_deref())
+    }
+
+    /// The `documentation` link, else docs.rs for `crate_name@version`.
+    pub fn docs_url(&self, crate_name: &str, version: &str) -> String {
+        self.documentation
+            .clone()
+            .unwrap_or_else(|| format!("https://docs.rs/{crate_name}/{version}"))
+    }
+}
+
+/// `owner/name` of `crate_name`'s source repo: its GitHub link when it
+/// has one, else `{org}/{crate_name}`.
+pub fn source_repo(links: Option<&CrateLinks>, org: &str, crate_name: &str) -> String {
+    links
+        .and_then(CrateLinks::github_repo)
+        .unwrap_or_else(|| format!("{org}/{crate_name}"))
+}
+
+/// `owner/name` of a GitHub URL such as `https://github.com/o/r.git` or
+/// `https://github.com/o/r/tree/main/crates/x`.
+pub fn github_slug(url: &str) -> Option<String> {
+    let rest = url
+        .trim()
+        .strip_prefix("https://")
+        .or_else(|| url.trim().strip_prefix("http://"))
+        .or_else(|| url.trim().strip_prefix("git@"))?;
+    let rest = rest
+        .strip_prefix("www.")
+        .unwrap_or(rest)
+        .strip_prefix("github.com")?
+        .trim_start_matches([':', '/']);
+    let mut parts = rest.split('/');
+    let owner = parts.next().filter(|o| !o.is_empty())?;
+    let name = parts.next()?.trim_end_matches(".git");
+    (!name.is_empty()).then(|| format!("{owner}/{name}"))
+}
+
+/// Reads the links of `crate_name` from the API of `registry`.
+pub async fn fetch(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+) -> Result<CrateLinks> {
+    let url = format!("{}/crates/{crate_name}", registry.api.trim_end_matches('/'));
+    let resp = client.get(&url).await?;
+    if !resp.status.is_success() {
+        return Err(anyhow::Error::new(HttpStatus(resp.status))
+            .context(format!("look up crate {crate_name}")));
+    }
+    let data: CratesIoCrate = serde_json::from_slice(&resp.body)
+        .with_context(|| format!("parse registry response for {crate_name}"))?;
+    Ok(data.krate.normalized())
+}
+
+impl CrateLinks {
+    /// Drops empty links.
+    fn normalized(self) -> Self {
+        let keep = |link: Option<String>| link.filter(|l| !l.trim().is_empty());
+        Self {
+            repository: keep(self.repository),
+            homepage: keep(self.homepage),
+            documentation: keep(self.documentation),
+        }
+    }
+}
+
+// ─── Cache ────────────────────────────────────────────────────────────────────
+
+impl LinkCache {
+    /// File name of the cache inside the state dir.
+    pub const FILE_NAME: &'static str = "crate-links.json";
+
+    /// Default TTL of cached links.
+    pub const DEFAULT_TTL_SECS: u64 = 86_400;
+
+    /// How long cached links are used before the registry is asked again
+    /// (`CRATE_LINKS_TTL_SECS`).
+    pub fn ttl_from_env() -> u64 {
+        std::env::var("CRATE_LINKS_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(Self::DEFAULT_TTL_SECS)
+    }
+
+    pub fn default_path() -> PathBuf {
+        state_dir().join(Self::FILE_NAME)
+    }
+
+    /// Loads the cache at `path`; a missing or unreadable file yields an
+    /// empty cache.
+    pub fn load(path: &Path) -> Self {
+        let entries = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| match serde_json::from_str(&text) {
+                Ok(entries) => Some(entries),
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "unreadable crate-link cache — starting empty");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            entries,
+        }
+    }
+
+    pub fn get(&self, crate_name: &str) -> Option<&CachedLinks> {
+        self.entries.get(crate_name)
+    }
+
+    /// Cached links of `crate_name` fetched less than `ttl` seconds before
+    /// `now`.
+    pub fn fresh(&self, crate_name: &str, now: u64, ttl: u64) -> Option<&CachedLinks> {
+        self.get(crate_name)
+            .filter(|cached| now.saturating_sub(cached.fetched_at) < ttl)
+    }
+
+    pub fn insert(&mut self, crate_name: &str, links: CrateLinks, fetched_at: u64) {
+        self.entries
+            .insert(crate_name.to_string(), CachedLinks { links, fetched_at });
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text =
+            serde_json::to_string_pretty(&self.entries).context("serialize crate-link cache")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write crate-link cache {}", self.path.display()))
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_slug() {
+        assert_eq!(
+            github_slug("https://github.com/ai-evo-agents/evo-common").as_deref(),
+            Some("ai-evo-agents/evo-common")
+        );
+        assert_eq!(
+            github_slug("https://github.com/o/r.git").as_deref(),
+            Some("o/r")
+        );
+        assert_eq!(
+            github_slug("https://www.github.com/o/mono/tree/main/crates/x").as_deref(),
+            Some("o/mono")
+        );
+        assert_eq!(
+            github_slug("git@github.com:o/r.git").as_deref(),
+            Some("o/r")
+        );
+        assert_eq!(github_slug("https://gitlab.com/o/r"), None);
+        assert_eq!(github_slug("https://github.com/o"), None);
+    }
+
+    #[test]
+    fn test_links() {
+        let links = CrateLinks {
+            repository: None,
+            homepage: Some("https://github.com/o/r".to_string()),
+            documentation: None,
+        };
+        assert_eq!(links.github_repo().as_deref(), Some("o/r"));
+        assert_eq!(links.source_url(), Some("https://github.com/o/r"));
+        assert_eq!(links.docs_url("r", "1.0.0"), "https://docs.rs/r/1.0.0");
+
+        let parsed: CratesIoCrate = serde_json::from_str(
+            r#"{"crate":{"name":"r","repository":"https://gitlab.com/o/r","homepage":"","documentation":null}}"#,
+        )
+        .unwrap();
+        let links = parsed.krate.normalized();
+        assert_eq!(links.homepage, None);
+        assert_eq!(links.github_repo(), None);
+        assert_eq!(links.source_url(), Some("https://gitlab.com/o/r"));
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(LinkCache::FILE_NAME);
+        let mut cache = LinkCache::load(&path);
+        let links = CrateLinks {
+            repository: Some("https://github.com/o/r".to_string()),
+            ..Default::default()
+        };
+        cache.insert("r", links.clone(), 1_000);
+        cache.save().unwrap();
+
+        let cache = LinkCache::load(&path);
+        assert_eq!(cache.get("r").unwrap().links, links);
+        assert!(cache.fresh("r", 1_500, 600).is_some());
+        assert!(cache.fresh("r", 1_700, 600).is_none());
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use tracing::{info, warn};
+
+use crate::llm::{Llm, Purpose};
+use crate::sandbox::Sandbox;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Migration attempts when `AUTO_MIGRATE_ATTEMPTS` is unset.
+const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// Compiler output sent to the gateway is cut to this many bytes.
+const MAX_ERROR_BYTES: usize = 8_000;
+
+/// At most this many failing source files are sent per attempt.
+const MAX_FILES_PER_ATTEMPT: usize = 4;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Settings for the verification build and the migration loop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrateConfig {
+    /// LLM patch attempts after the first failed `cargo check`.
+    pub max_attempts: u32,
+}
+
+impl MigrateConfig {
+    /// `Some` when `AUTO_MIGRATE` is enabled; attempts come from
+    /// `AUTO_MIGRATE_ATTEMPTS` (default 3).
+    pub fn from_env() -> Option<Self> {
+        let enabled =
+            std::env::var("AUTO_MIGRATE").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        enabled.then(|| Self {
+            max_attempts: std::env::var("AUTO_MIGRATE_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_ATTEMPTS),
+        })
+    }
+}
+
+/// A source file rewritten by the migration loop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigratedFile {
+    pub path: String,
+    pub original: String,
+    pub content: String,
+}
+
+/// Result of verifying one repo's patched manifests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// `cargo check` passed without source changes.
+    Passed,
+    /// `cargo check` passed after `attempts` LLM patches to `files`.
+    Healed {
+        attempts: u32,
+        files: Vec<MigratedFile>,
+    },
+    /// `cargo check` still fails after `attempts` patches.
+    Failed { attempts: u32, errors: String },
+}
+
+/// Source rewrite suggested by the gateway.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct SourcePatch {
+    files: Vec<PatchedSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct PatchedSource {
+    path: String,
+    content: String,
+}
+
+impl SourcePatch {
+    /// Extracts the JSON object from an LLM response, tolerating prose or
+    /// code fences around it.
+    fn parse(response: &str) -> Option<Self> {
+        let start = response.find('{')?;
+        let end = response.rfind('}')?;
+        serde_json::from_str(response.get(start..=end)?).ok()
+    }
+}
+
+// ─── Migration loop ───────────────────────────────────────────────────────────
+
+/// Runs `cargo check` in `sandbox` (which already holds the patched
+/// `manifests`) and on failure asks the gateway to patch the failing source
+/// files, up to `config.max_attempts` times.  Patches are applied in the
+/// sandbox.
+///
+/// Only files named in compiler errors may be rewritten.
+pub async fn verify_and_migrate(
+    llm: &Llm<'_>,
+    system: &str,
+    sandbox: &Sandbox,
+    manifests: &[&str],
+    config: &MigrateConfig,
+) -> Result<Outcome> {
+    let mut errors = match sandbox.cargo_check()? {
+        None => return Ok(Outcome::Passed),
+        Some(errors) => errors,
+    };
+    let mut allowed: Vec<String> = Vec::new();
+    let mut migrated: Vec<MigratedFile> = Vec::new();
+    let mut attempts = 0;
+
+    for attempt in 1..=config.max_attempts {
+        attempts = attempt;
+        let failing: Vec<String> = error_files(&errors)
+            .into_iter()
+            .take(MAX_FILES_PER_ATTEMPT)
+            .collect();
+        if failing.is_empty() {
+            warn!("cargo check failed without pointing at a source file — giving up");
+            break;
+        }
+        for file in &failing {
+            if !allowed.contains(file) {
+                allowed.push(file.clone());
+            }
+        }
+        let mut sources: Vec<(String, String)> = Vec::new();
+        for file in &failing {
+            sources.push((file.clone(), sandbox.read(file)?));
+        }
+
+        let response = llm
+            .complete(
+                Purpose::Migration,
+                system,
+                &prompt(manifests, &errors, &sources),
+            )
+            .await
+            .context("gateway migration request")?;
+        let Some(patch) = SourcePatch::parse(&response) else {
+            warn!(
+                attempt,
+                "migration response is not a source patch — giving up"
+            );
+            break;
+        };
+
+        for file in patch.files {
+            if !allowed.contains(&file.path) {
+                warn!(path = %file.path, "migration touched a file outside the compiler errors — ignored");
+                continue;
+            }
+            let current = sandbox.read(&file.path)?;
+            match migrated.iter_mut().find(|m| m.path == file.path) {
+                Some(existing) => existing.content = file.content.clone(),
+                None => migrated.push(MigratedFile {
+                    path: file.path.clone(),
+                    original: current,
+                    content: file.content.clone(),
+                }),
+            }
+            sandbox.write(&file.path, &file.content)?;
+        }
+
+        match sandbox.cargo_check()? {
+            None => {
+                info!(
+                    attempt,
+                    files = migrated.len(),
+                    "cargo check passes after migration"
+                );
+                migrated.retain(|m| m.original != m.content);
+                return Ok(Outcome::Healed {
+                    attempts: attempt,
+                    files: migrated,
+                });
+            }
+            Some(next) => errors = next,
+        }
+    }
+
+    Ok(Outcome::Failed {
+        attempts,
+        errors: truncate(&errors, MAX_ERROR_BYTES).to_string(),
+    })
+}
+
+/// Repo-relative `.rs` files named in `cargo check --message-format short`
+/// errors, in order of first appearance.
+fn error_files(errors: &str) -> Vec<String> {
+    // SAFETY: the pattern is a literal known to be valid.
+    let re = Regex::new(r"(?m)^([^\s:][^:]*\.rs):\d+:\d+: error").expect("regex is valid");
+    let mut files: Vec<String> = Vec::new();
+    for caps in re.captures_iter(errors) {
+        let file = caps[1].to_string();
+        let inside = Path::new(&file)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if inside && !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Prompt asking the gateway to fix `sources` after the `manifests` bump.
+fn prompt(manifests: &[&str], errors: &str, sources: &[(String, String)]) -> String {
+    let mut out = String::from("A Rust dependency update changed these manifests:\n");
+    for file in manifests {
+        out.push_str(&format!("- {file}\n"));
+    }
+    out.push_str(&format!(
+        "\n`cargo check` now fails:\n```\n{}\n```\n\n",
+        truncate(errors, MAX_ERROR_BYTES)
+    ));
+    for (file, content) in sources {
+        out.push_str(&format!("File `{file}`:\n```rust\n{content}\n```\n\n"));
+    }
+    out.push_str(
+        "Adapt the code to the new dependency APIs with the smallest possible change. \
+         Reply with JSON only, in this shape:\n\
+         {\"files\": [{\"path\": \"src/example.rs\", \"content\": \"full new file content\"}]}",
+    );
+    out
+}
+
+/// `text` cut to at most `max` bytes, on a character boundary.
+fn truncate(text: &str, max: usize) -> &str {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_files() {
+        let errors = "\
+src/main.rs:12:5: error[E0425]: cannot find function `load_config` in crate `evo_common`
+src/main.rs:40:9: error[E0308]: mismatched types
+crates/core/src/lib.rs:3:1: error[E0432]: unresolved import
+src/lib.rs:7:1: warning: unused import
+/home/u/.cargo/registry/src/x.rs:1:1: error: outside
+error: could not compile `evo-king` (bin \"evo-king\") due to 3 previous errors
+";
+        assert_eq!(
+            error_files(errors),
+            vec!["src/main.rs", "crates/core/src/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_parse_source_patch() {
+        let patch = SourcePatch::parse(
+            "```json\n{\"files\": [{\"path\": \"src/main.rs\", \"content\": \"fn main() {}\"}]}\n```",
+        )
+        .unwrap();
+        assert_eq!(patch.files[0].path, "src/main.rs");
+        assert!(SourcePatch::parse("I cannot help.").is_none());
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        assert_eq!(truncate("héllo", 2), "h");
+        assert_eq!(truncate("abc", 10), "abc");
+    }
+}
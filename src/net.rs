@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Common locations of the system CA bundle, probed in order when an extra
+/// bundle has to be merged for spawned tools.
+const SYSTEM_CA_BUNDLES: &[&str] = &[
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/ssl/cert.pem",
+];
+
+// ─── Network settings ─────────────────────────────────────────────────────────
+
+/// Egress proxy and CA settings shared by the HTTP client and every spawned
+/// `git` / `gh` process.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetSettings {
+    /// Proxy for HTTPS traffic (`HTTPS_PROXY` / `https_proxy`).
+    pub https_proxy: Option<String>,
+    /// Proxy for plain HTTP traffic (`HTTP_PROXY` / `http_proxy`).
+    pub http_proxy: Option<String>,
+    /// Hosts that bypass the proxy (`NO_PROXY` / `no_proxy`).
+    pub no_proxy: Option<String>,
+    /// PEM bundle of additional trusted CAs (`EXTRA_CA_BUNDLE`).
+    pub extra_ca_bundle: Option<PathBuf>,
+}
+
+impl NetSettings {
+    /// Reads the settings from the environment (upper- or lower-case names).
+    pub fn from_env() -> Self {
+        let var = |upper: &str, lower: &str| {
+            std::env::var(upper)
+                .or_else(|_| std::env::var(lower))
+                .ok()
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            https_proxy: var("HTTPS_PROXY", "https_proxy"),
+            http_proxy: var("HTTP_PROXY", "http_proxy"),
+            no_proxy: var("NO_PROXY", "no_proxy"),
+            extra_ca_bundle: var("EXTRA_CA_BUNDLE", "extra_ca_bundle").map(PathBuf::from),
+        }
+    }
+
+    /// Process-wide settings, read from the environment on first use.
+    pub fn global() -> &'static NetSettings {
+        static SETTINGS: OnceLock<NetSettings> = OnceLock::new();
+        SETTINGS.get_or_init(NetSettings::from_env)
+    }
+
+    /// Builds a `reqwest` client honouring the proxy and extra CA settings.
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+
+        let no_proxy = self
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string);
+        if let Some(ref url) = self.https_proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::https(url)
+                    .with_context(|| format!("invalid HTTPS_PROXY {url:?}"))?
+                    .no_proxy(no_proxy.clone()),
+            );
+        }
+        if let Some(ref url) = self.http_proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::http(url)
+                    .with_context(|| format!("invalid HTTP_PROXY {url:?}"))?
+                    .no_proxy(no_proxy),
+            );
+        }
+
+        if let Some(ref path) = self.extra_ca_bundle {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("read EXTRA_CA_BUNDLE {}", path.display()))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("parse EXTRA_CA_BUNDLE {}", path.display()))?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        builder.build().context("build HTTP client")
+    }
+
+    /// Exports the proxy and CA settings to a spawned `git` / `gh` process.
+    ///
+    /// Proxies are set under both spellings (curl reads lower-case, Go reads
+    /// either).  The extra CA bundle is merged with the system bundle and
+    /// exposed as `GIT_SSL_CAINFO` (git) and `SSL_CERT_FILE` (gh), since both
+    /// variables *replace* the default trust store.
+    pub fn apply(&self, cmd: &mut Command) {
+        for (upper, lower, value) in [
+            ("HTTPS_PROXY", "https_proxy", &self.https_proxy),
+            ("HTTP_PROXY", "http_proxy", &self.http_proxy),
+            ("NO_PROXY", "no_proxy", &self.no_proxy),
+        ] {
+            if let Some(v) = value {
+                cmd.env(upper, v).env(lower, v);
+            }
+        }
+
+        if let Some(ref extra) = self.extra_ca_bundle {
+            static MERGED: OnceLock<Option<PathBuf>> = OnceLock::new();
+            let merged = MERGED.get_or_init(|| match merge_ca_bundle(extra) {
+                Ok(path) => {
+                    info!(bundle = %path.display(), "merged extra CA bundle for git/gh");
+                    Some(path)
+                }
+                Err(e) => {
+                    warn!(error = %e, "cannot merge EXTRA_CA_BUNDLE — spawned tools use system CAs");
+                    None
+                }
+            });
+            if let Some(path) = merged {
+                cmd.env("GIT_SSL_CAINFO", path).env("SSL_CERT_FILE", path);
+            }
+        }
+    }
+}
+
+/// Shorthand for `NetSettings::global().apply(cmd)`.
+pub fn apply_to_command(cmd: &mut Command) -> &mut Command {
+    NetSettings::global().apply(cmd);
+    cmd
+}
+
+/// Shared HTTP client for crates.io and GitHub traffic.
+pub fn http_client() -> Result<reqwest::Client> {
+    NetSettings::global().build_client()
+}
+
+// ─── CA bundle merging ────────────────────────────────────────────────────────
+
+/// Writes the first existing system CA bundle followed by `extra` to a file in
+/// the temp dir and returns its path.
+fn merge_ca_bundle(extra: &Path) -> Result<PathBuf> {
+    let system = SYSTEM_CA_BUNDLES
+        .iter()
+        .map(Path::new)
+        .find(|p| p.is_file());
+    let out = std::env::temp_dir().join(format!("evo-update-ca-{}.pem", std::process::id()));
+    write_merged_bundle(system, extra, &out)?;
+    Ok(out)
+}
+
+/// Concatenates `system` (if any) and `extra` into `out`.
+fn write_merged_bundle(system: Option<&Path>, extra: &Path, out: &Path) -> Result<()> {
+    let mut merged = match system {
+        Some(path) => std::fs::read(path).with_context(|| format!("read {}", path.display()))?,
+        None => Vec::new(),
+    };
+    if !merged.is_empty() && !merged.ends_with(b"\n") {
+        merged.push(b'\n');
+    }
+    merged.extend(std::fs::read(extra).with_context(|| format!("read {}", extra.display()))?);
+    std::fs::write(out, merged).with_context(|| format!("write {}", out.display()))
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_build_client_with_proxy() {
+        let settings = NetSettings {
+            https_proxy: Some("http://proxy.internal:3128".to_string()),
+            no_proxy: Some("localhost,.internal".to_string()),
+            ..NetSettings::default()
+        };
+        assert!(settings.build_client().is_ok());
+    }
+
+    #[test]
+    fn test_build_client_missing_ca_bundle_errors() {
+        let settings = NetSettings {
+            extra_ca_bundle: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..NetSettings::default()
+        };
+        assert!(settings.build_client().is_err());
+    }
+
+    #[test]
+    fn test_apply_exports_proxy_env() {
+        let settings = NetSettings {
+            https_proxy: Some("http://p:1".to_string()),
+            ..NetSettings::default()
+        };
+        let mut cmd = Command::new("git");
+        settings.apply(&mut cmd);
+        let envs: Vec<_> = cmd.get_envs().collect();
+        assert!(envs.contains(&(
+            std::ffi::OsStr::new("https_proxy"),
+            Some(std::ffi::OsStr::new("http://p:1"))
+        )));
+        assert!(envs.iter().all(|(k, _)| *k != "HTTP_PROXY"));
+    }
+
+    #[test]
+    fn test_write_merged_bundle() {
+        let dir = TempDir::new().unwrap();
+        let system = dir.path().join("system.pem");
+        let extra = dir.path().join("extra.pem");
+        let out = dir.path().join("out.pem");
+        std::fs::write(&system, "SYSTEM").unwrap();
+        std::fs::write(&extra, "EXTRA\n").unwrap();
+
+        write_merged_bundle(Some(&system), &extra, &out).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "SYSTEM\nEXTRA\n");
+
+        write_merged_bundle(None, &extra, &out).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "EXTRA\n");
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::{info, warn};
+
+use crate::diff;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Most files listed in a Discord message, its embed limit.
+const DISCORD_MAX_EMBEDS: usize = 10;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// Where a run's changes are announced.  Each target is an incoming-webhook
+/// URL and is off while unset.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Notifiers {
+    /// `NOTIFY_SLACK_WEBHOOK_URL`.
+    pub slack: Option<String>,
+    /// `NOTIFY_DISCORD_WEBHOOK_URL`.
+    pub discord: Option<String>,
+    /// `NOTIFY_WEBHOOK_URL`: any endpoint taking the generic JSON payload.
+    pub webhook: Option<String>,
+}
+
+impl Notifiers {
+    pub fn from_env() -> Self {
+        let url = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self {
+            slack: url("NOTIFY_SLACK_WEBHOOK_URL"),
+            discord: url("NOTIFY_DISCORD_WEBHOOK_URL"),
+            webhook: url("NOTIFY_WEBHOOK_URL"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slack.is_none() && self.discord.is_none() && self.webhook.is_none()
+    }
+
+    /// POSTs `notification` to every target in its format.  Returns
+    /// `{ "target", "ok" }` or `{ "target", "error" }` per target.
+    pub async fn send(&self, http: &reqwest::Client, notification: &Notification) -> Vec<Value> {
+        let targets = [
+            ("slack", &self.slack, notification.slack_payload()),
+            ("discord", &self.discord, notification.discord_payload()),
+            ("webhook", &self.webhook, notification.webhook_payload()),
+        ];
+        let mut results = Vec::new();
+        for (target, url, payload) in targets {
+            let Some(url) = url else {
+                continue;
+            };
+            match post(http, url, &payload).await {
+                Ok(()) => {
+                    info!(
+                        target,
+                        files = notification.files.len(),
+                        "notification sent"
+                    );
+                    results.push(json!({ "target": target, "ok": true }));
+                }
+                Err(e) => {
+                    warn!(target, error = %e, "notification failed");
+                    results.push(json!({ "target": target, "error": format!("{e:#}") }));
+                }
+            }
+        }
+        results
+    }
+}
+
+async fn post(http: &reqwest::Client, url: &str, payload: &Value) -> Result<()> {
+    let resp = http
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .context("send notification")?;
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        anyhow::bail!("{status}: {text}");
+    }
+    Ok(())
+}
+
+// ─── Notifications ────────────────────────────────────────────────────────────
+
+/// A delivered file, with a snippet of its diff.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NotifiedFile {
+    pub repo: String,
+    pub file: String,
+    /// Commit or PR the file was delivered in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    pub diff: String,
+    /// Whether lines of the diff were left out.
+    pub truncated: bool,
+}
+
+impl NotifiedFile {
+    /// `file` of `repo`, changed from `old` to `new`.
+    pub fn new(repo: &str, file: &str, old: &str, new: &str, link: Option<String>) -> Self {
+        let full = diff::unified_diff(file, old, new);
+        let diff = diff::snippet(&full, diff::SNIPPET_LINES, diff::SNIPPET_LINE_CHARS);
+        Self {
+            repo: repo.to_string(),
+            file: file.to_string(),
+            link,
+            truncated: diff != full,
+            diff,
+        }
+    }
+
+    fn title(&self) -> String {
+        format!("{} · {}", self.repo, self.file)
+    }
+}
+
+/// What a run delivered, as announced to chats and webhooks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub run_id: String,
+    pub files: Vec<NotifiedFile>,
+}
+
+impl Notification {
+    /// "Dependency updates delivered: 3 files in evo-king, evo-runner (run
+    /// abc-123)".
+    pub fn headline(&self) -> String {
+        let mut repos: Vec<&str> = self.files.iter().map(|f| f.repo.as_str()).collect();
+        repos.dedup();
+        let files = match self.files.len() {
+            1 => "1 file".to_string(),
+            n => format!("{n} files"),
+        };
+        format!(
+            "Dependency updates delivered: {files} in {} (run {})",
+            repos.join(", "),
+            self.run_id
+        )
+    }
+
+    /// Slack incoming-webhook message: one attachment per file, whose long
+    /// text Slack folds behind "Show more".
+    pub fn slack_payload(&self) -> Value {
+        let attachments: Vec<Value> = self
+            .files
+            .iter()
+            .map(|file| {
+                let mut attachment = json!({
+                    "color": "#2eb67d",
+                    "title": file.title(),
+                    "text": fenced(&file.diff, ""),
+                    "mrkdwn_in": ["text"],
+                    "fallback": file.title(),
+                });
+                if let Some(link) = &file.link {
+                    attachment["title_link"] = json!(link);
+                }
+                attachment
+            })
+            .collect();
+        json!({ "text": self.headline(), "attachments": attachments })
+    }
+
+    /// Discord webhook message: one embed per file, up to Discord's limit.
+    /// Discord cannot collapse an embed, so the snippets show in full.
+    pub fn discord_payload(&self) -> Value {
+        let embeds: Vec<Value> = self
+            .files
+            .iter()
+            .take(DISCORD_MAX_EMBEDS)
+            .map(|file| {
+                let mut embed = json!({
+                    "title": file.title(),
+                    "description": fenced(&file.diff, "diff"),
+                });
+                if let Some(link) = &file.link {
+                    embed["url"] = json!(link);
+                }
+                embed
+            })
+            .collect();
+        let mut content = self.headline();
+        if self.files.len() > DISCORD_MAX_EMBEDS {
+            content.push_str(&format!(
+                " — {} more files not shown",
+                self.files.len() - DISCORD_MAX_EMBEDS
+            ));
+        }
+        json!({ "content": content, "embeds": embeds })
+    }
+
+    /// Generic webhook payload: the files as data, plus a Markdown rendering
+    /// with each diff in a collapsed `<details>` block.
+    pub fn webhook_payload(&self) -> Value {
+        let markdown: String = self
+            .files
+            .iter()
+            .map(|file| {
+                format!(
+                    "<details>\n<summary>{}</summary>\n\n{}\n</details>\n",
+                    file.title(),
+                    fenced(&file.diff, "diff")
+                )
+            })
+            .collect();
+        json!({
+            "source": "update-agent",
+            "run_id": self.run_id,
+            "text": self.headline(),
+            "files": self.files,
+            "markdown": markdown,
+        })
+    }
+}
+
+/// `text` in a Markdown code block tagged `lang`; backtick runs inside are
+/// broken up so they cannot close the block early.
+fn fenced(text: &str, lang: &str) -> String {
+    let body = text.replace("```", "`\u{200b}``");
+    format!("```{lang}\n{}\n```", body.trim_end_matches('\n'))
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(files: usize) -> Notification {
+        let old = "[dependencies]\nevo-common = \"0.3\"\n";
+        let new = "[dependencies]\nevo-common = \"0.4.0\"\n";
+        Notification {
+            run_id: "run-1".to_string(),
+            files: (0..files)
+                .map(|i| {
+                    NotifiedFile::new(
+                        &format!("evo-{i}"),
+                        "Cargo.toml",
+                        old,
+                        new,
+                        Some(format!("https://github.com/o/evo-{i}/commit/a1")),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_payloads_carry_diff_snippets() {
+        let note = notification(1);
+        assert_eq!(
+            note.headline(),
+            "Dependency updates delivered: 1 file in evo-0 (run run-1)"
+        );
+        assert!(!note.files[0].truncated);
+
+        let slack = note.slack_payload();
+        let text = slack["attachments"][0]["text"].as_str().unwrap();
+        assert!(text.starts_with("```\n--- a/Cargo.toml\n"));
+        assert!(text.contains("+evo-common = \"0.4.0\"\n```"));
+        assert_eq!(
+            slack["attachments"][0]["title_link"],
+            "https://github.com/o/evo-0/commit/a1"
+        );
+
+        let discord = note.discord_payload();
+        assert!(
+            discord["embeds"][0]["description"]
+                .as_str()
+                .unwrap()
+                .starts_with("```diff\n")
+        );
+
+        let webhook = note.webhook_payload();
+        assert_eq!(webhook["files"][0]["repo"], "evo-0");
+        assert!(
+            webhook["markdown"]
+                .as_str()
+                .unwrap()
+                .starts_with("<details>\n<summary>evo-0 · Cargo.toml</summary>\n\n```diff\n")
+        );
+    }
+
+    #[test]
+    fn test_discord_embed_limit_and_fences() {
+        let discord = notification(12).discord_payload();
+        assert_eq!(discord["embeds"].as_array().unwrap().len(), 10);
+        assert!(
+            discord["content"]
+                .as_str()
+                .unwrap()
+                .ends_with("— 2 more files not shown")
+        );
+        assert_eq!(fenced("a```b\n", ""), "```\na`\u{200b}``b\n```");
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::fixtures::RegistryHttp;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Default OSV API root (`OSV_API_URL`).
+pub const DEFAULT_API: &str = "https://api.osv.dev";
+
+/// OSV ecosystem of crates.io packages.
+const ECOSYSTEM: &str = "crates.io";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A known vulnerability of one crate version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Vulnerability {
+    /// OSV id, e.g. `"GHSA-xxxx-xxxx-xxxx"` or `"RUSTSEC-2024-0001"`.
+    pub id: String,
+    /// Other ids of the same vulnerability (CVE, RUSTSEC, GHSA).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    pub summary: String,
+    /// Rating of the advisory database (`critical`, `high`, `moderate`,
+    /// `low`), else the first CVSS vector; `None` when unrated.
+    pub severity: Option<String>,
+}
+
+/// OSV results for one bump.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OsvFinding {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Version queried for the current side: the locked version, else the
+    /// requirement's lowest version.
+    pub from: String,
+    pub to: String,
+    /// Vulnerabilities of `from` that `to` no longer has.
+    pub fixed: Vec<Vulnerability>,
+    /// Vulnerabilities `to` still has (or newly has).
+    pub remaining: Vec<Vulnerability>,
+}
+
+// ─── OSV API types ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize)]
+struct QueryResponse {
+    #[serde(default)]
+    vulns: Vec<OsvVuln>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvVuln {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    database_specific: Option<DatabaseSpecific>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvSeverity {
+    score: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatabaseSpecific {
+    severity: Option<String>,
+}
+
+// ─── Settings ─────────────────────────────────────────────────────────────────
+
+/// Whether bumps are checked against OSV (`OSV_QUERIES`, default on).
+pub fn enabled() -> bool {
+    !matches!(
+        std::env::var("OSV_QUERIES").as_deref().map(str::trim),
+        Ok("0" | "false" | "off")
+    )
+}
+
+/// API root from `OSV_API_URL`, default [`DEFAULT_API`].
+pub fn api_url() -> String {
+    std::env::var("OSV_API_URL").unwrap_or_else(|_| DEFAULT_API.to_string())
+}
+
+// ─── Queries ──────────────────────────────────────────────────────────────────
+
+/// Vulnerabilities OSV lists for `crate_name@version`.
+pub async fn query(
+    client: &RegistryHttp,
+    api: &str,
+    crate_name: &str,
+    version: &str,
+) -> Result<Vec<Vulnerability>> {
+    let url = format!("{}/v1/query", api.trim_end_matches('/'));
+    let body = json!({
+        "package": { "name": crate_name, "ecosystem": ECOSYSTEM },
+        "version": version,
+    });
+    let resp = client.post_json(&url, &body).await?.ensure_success(&url)?;
+    parse_response(resp.text()?)
+        .with_context(|| format!("parse OSV response for {crate_name}@{version}"))
+}
+
+fn parse_response(text: &str) -> Result<Vec<Vulnerability>> {
+    let response: QueryResponse = serde_json::from_str(text)?;
+    Ok(response
+        .vulns
+        .into_iter()
+        .map(|v| Vulnerability {
+            severity: v
+                .database_specific
+                .and_then(|d| d.severity)
+                .map(|s| s.to_lowercase())
+                .or_else(|| v.severity.into_iter().next().map(|s| s.score)),
+            id: v.id,
+            aliases: v.aliases,
+            summary: v.summary,
+        })
+        .collect())
+}
+
+impl Vulnerability {
+    /// Whether `other` is the same vulnerability under any of its ids.
+    fn same_as(&self, other: &Vulnerability) -> bool {
+        let ids = |v: &'_ Vulnerability| -> Vec<String> {
+            std::iter::once(&v.id).chain(&v.aliases).cloned().collect()
+        };
+        let theirs = ids(other);
+        ids(self).iter().any(|id| theirs.contains(id))
+    }
+}
+
+impl OsvFinding {
+    /// Compares the vulnerabilities of `from` (`before`) with those of `to`
+    /// (`after`).
+    pub fn compare(
+        crate_name: &str,
+        from: &str,
+        to: &str,
+        before: &[Vulnerability],
+        after: Vec<Vulnerability>,
+    ) -> Self {
+        Self {
+            crate_name: crate_name.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            fixed: before
+                .iter()
+                .filter(|b| !after.iter().any(|a| a.same_as(b)))
+                .cloned()
+                .collect(),
+            remaining: after,
+        }
+    }
+
+    /// Whether the finding has anything to report.
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.remaining.is_empty()
+    }
+}
+
+/// The concrete version a requirement starts at (`^0.3` → `0.3.0`), for
+/// manifests without a lockfile.
+pub fn requirement_floor(requirement: &str) -> String {
+    let bare = requirement
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .trim_start_matches(['^', '=', '~', '>', ' ']);
+    let mut parts: Vec<&str> = bare
+        .split('.')
+        .take_while(|p| !p.is_empty() && *p != "*")
+        .collect();
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    parts.join(".")
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vuln(id: &str, aliases: &[&str]) -> Vulnerability {
+        Vulnerability {
+            id: id.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            summary: String::new(),
+            severity: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let text = r#"{"vulns":[
+            {"id":"GHSA-1111-2222-3333","aliases":["CVE-2024-1","RUSTSEC-2024-0001"],
+             "summary":"Overflow","severity":[{"type":"CVSS_V3","score":"CVSS:3.1/AV:N"}],
+             "database_specific":{"severity":"HIGH"}},
+            {"id":"RUSTSEC-2024-0002","summary":"Unsound",
+             "severity":[{"type":"CVSS_V3","score":"CVSS:3.1/AV:L"}]},
+            {"id":"RUSTSEC-2024-0003"}
+        ]}"#;
+        let vulns = parse_response(text).unwrap();
+        assert_eq!(vulns[0].severity.as_deref(), Some("high"));
+        assert_eq!(vulns[0].aliases.len(), 2);
+        assert_eq!(vulns[1].severity.as_deref(), Some("CVSS:3.1/AV:L"));
+        assert_eq!(vulns[2].severity, None);
+        assert!(parse_response("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_compare_matches_aliases() {
+        let before = vec![
+            vuln("GHSA-1111-2222-3333", &["RUSTSEC-2024-0001"]),
+            vuln("RUSTSEC-2024-0009", &[]),
+        ];
+        let after = vec![vuln("RUSTSEC-2024-0001", &[])];
+        let finding = OsvFinding::compare("evo-common", "0.3.0", "0.4.0", &before, after);
+        assert_eq!(finding.fixed, vec![vuln("RUSTSEC-2024-0009", &[])]);
+        assert_eq!(finding.remaining.len(), 1);
+        assert!(!finding.is_empty());
+    }
+
+    #[test]
+    fn test_requirement_floor() {
+        assert_eq!(requirement_floor("0.3"), "0.3.0");
+        assert_eq!(requirement_floor("^1.2.3"), "1.2.3");
+        assert_eq!(requirement_floor(">=1.2, <2"), "1.2.0");
+        assert_eq!(requirement_floor("1.*"), "1.0.0");
+    }
+}
@@ -0,0 +1,206 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::repo_config::UpdateType;
+use crate::versions::needs_update;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Dependency tables scanned in a manifest (and under each `[target.*]`).
+const DEP_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A crates.io dependency declared in a manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    /// Package name on crates.io (the `package` key for renamed deps).
+    pub name: String,
+    /// Version requirement as written.
+    pub requirement: String,
+}
+
+/// Where one repo stands on one crate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cell {
+    /// Oldest requirement across the repo's manifests.
+    pub requirement: String,
+    /// Latest stable version, when known.
+    pub latest: Option<String>,
+    pub status: Status,
+    /// Size of the bump to `latest`, when outdated.
+    pub update_type: Option<UpdateType>,
+    /// Manifests declaring the crate.
+    pub files: Vec<String>,
+}
+
+/// Whether a requirement trails the latest release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Current,
+    Outdated,
+    /// No latest version could be resolved.
+    Unknown,
+}
+
+/// `crate → cell` for one repo.
+pub type RepoRow = BTreeMap<String, Cell>;
+
+// ─── Scanning ─────────────────────────────────────────────────────────────────
+
+/// Every crates.io dependency in `manifest`: the regular, dev and build
+/// tables, their `[target.*]` variants and `[workspace.dependencies]`.
+///
+/// Path, git and alternate-registry dependencies and `workspace = true`
+/// entries are skipped, as are unparsable manifests.
+pub fn dependencies(manifest: &str) -> Vec<Dependency> {
+    let Ok(doc) = manifest.parse::<toml_edit::DocumentMut>() else {
+        return Vec::new();
+    };
+    let mut tables: Vec<&toml_edit::Item> = DEP_TABLES.iter().filter_map(|t| doc.get(t)).collect();
+    if let Some(targets) = doc.get("target").and_then(|t| t.as_table_like()) {
+        for (_, target) in targets.iter() {
+            tables.extend(DEP_TABLES.iter().filter_map(|t| target.get(t)));
+        }
+    }
+    if let Some(deps) = doc.get("workspace").and_then(|w| w.get("dependencies")) {
+        tables.push(deps);
+    }
+
+    let mut deps: Vec<Dependency> = Vec::new();
+    for table in tables.into_iter().filter_map(|t| t.as_table_like()) {
+        for (key, item) in table.iter() {
+            let dep = match item.as_str() {
+                Some(requirement) => Some(Dependency {
+                    name: key.to_string(),
+                    requirement: requirement.to_string(),
+                }),
+                None => item.as_table_like().and_then(|entry| {
+                    let registry_dep = ["path", "git", "registry", "workspace"]
+                        .iter()
+                        .all(|k| !entry.contains_key(k));
+                    let requirement = entry.get("version")?.as_str()?;
+                    registry_dep.then(|| Dependency {
+                        name: entry
+                            .get("package")
+                            .and_then(|p| p.as_str())
+                            .unwrap_or(key)
+                            .to_string(),
+                        requirement: requirement.to_string(),
+                    })
+                }),
+            };
+            if let Some(dep) = dep
+                && !deps.contains(&dep)
+            {
+                deps.push(dep);
+            }
+        }
+    }
+    deps
+}
+
+/// Folds the `(file, content)` manifests of one repo into a row, keeping the
+/// oldest requirement per crate.  `latest` is filled in by [`resolve`].
+pub fn scan_repo(manifests: &[(String, String)]) -> RepoRow {
+    let mut row = RepoRow::new();
+    for (file, content) in manifests {
+        for dep in dependencies(content) {
+            let cell = row.entry(dep.name).or_insert_with(|| Cell {
+                requirement: dep.requirement.clone(),
+                latest: None,
+                status: Status::Unknown,
+                update_type: None,
+                files: Vec::new(),
+            });
+            if needs_update(&dep.requirement, &cell.requirement) {
+                cell.requirement = dep.requirement;
+            }
+            if !cell.files.contains(file) {
+                cell.files.push(file.clone());
+            }
+        }
+    }
+    row
+}
+
+/// Compares every cell of `row` with the `latest` versions.
+pub fn resolve(row: &mut RepoRow, latest: &BTreeMap<String, String>) {
+    for (name, cell) in row.iter_mut() {
+        cell.latest = latest.get(name).cloned();
+        (cell.status, cell.update_type) = match &cell.latest {
+            None => (Status::Unknown, None),
+            Some(latest) if needs_update(&cell.requirement, latest) => (
+                Status::Outdated,
+                Some(UpdateType::of(&cell.requirement, latest)),
+            ),
+            Some(_) => (Status::Current, None),
+        };
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "evo-king"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+tokio = "1"
+json = { package = "serde_json", version = "1.0.100" }
+evo-common = { path = "../evo-common", version = "0.3" }
+local = { workspace = true }
+
+[dev-dependencies]
+tempfile = "3.1"
+
+[target.'cfg(unix)'.dependencies]
+nix = "0.26"
+"#;
+
+    #[test]
+    fn test_dependencies_cover_all_tables() {
+        let names: Vec<String> = dependencies(MANIFEST).into_iter().map(|d| d.name).collect();
+        assert_eq!(
+            names,
+            vec!["serde", "tokio", "serde_json", "tempfile", "nix"]
+        );
+    }
+
+    #[test]
+    fn test_scan_and_resolve() {
+        let mut row = scan_repo(&[
+            ("Cargo.toml".to_string(), MANIFEST.to_string()),
+            (
+                "crates/x/Cargo.toml".to_string(),
+                "[dependencies]\nnix = \"0.25\"\n".to_string(),
+            ),
+        ]);
+        let latest: BTreeMap<String, String> = [
+            ("serde", "1.0.200"),
+            ("tokio", "1.40.0"),
+            ("nix", "0.29.0"),
+            ("tempfile", "3.1.0"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        resolve(&mut row, &latest);
+
+        assert_eq!(row["serde"].update_type, Some(UpdateType::Patch));
+        assert_eq!(row["tokio"].update_type, Some(UpdateType::Minor));
+        assert_eq!(row["tempfile"].status, Status::Current);
+        assert_eq!(row["serde_json"].status, Status::Unknown);
+        let nix = &row["nix"];
+        assert_eq!(nix.requirement, "0.25");
+        assert_eq!(nix.status, Status::Outdated);
+        assert_eq!(nix.update_type, Some(UpdateType::Major));
+        assert_eq!(nix.files, vec!["Cargo.toml", "crates/x/Cargo.toml"]);
+    }
+}
@@ -0,0 +1,398 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::backend::KingClient;
+use crate::fixtures::RegistryHttp;
+use crate::registry::Registry;
+use crate::state::state_dir;
+use crate::versions::HttpStatus;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Who controls a crate on its registry: the owners (users and teams) and
+/// every account that published one of its versions.  Both are sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateOwners {
+    pub owners: Vec<String>,
+    pub publishers: Vec<String>,
+}
+
+/// How a crate's owners or publishers differ from the acknowledged ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnershipChange {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub added_owners: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub removed_owners: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub new_publishers: Vec<String>,
+    /// What the registry showed when the change was last seen.
+    pub observed: CrateOwners,
+    /// Unix seconds of the run that first saw the change.
+    pub detected_at: u64,
+    /// Whether the king was alerted about this change.
+    pub notified: bool,
+}
+
+/// The acknowledged owners of one crate and any change awaiting review.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct OwnershipRecord {
+    #[serde(flatten)]
+    known: CrateOwners,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pending: Option<OwnershipChange>,
+}
+
+/// Owners of the tracked crates across runs, in
+/// `$UPDATE_STATE_DIR/crate-owners.json`.
+#[derive(Debug, Clone)]
+pub struct OwnershipState {
+    path: PathBuf,
+    crates: BTreeMap<String, OwnershipRecord>,
+}
+
+// ─── Settings ─────────────────────────────────────────────────────────────────
+
+/// Whether owners are checked (`OWNERSHIP_CHECKS`, default on).
+pub fn enabled() -> bool {
+    !matches!(
+        std::env::var("OWNERSHIP_CHECKS").as_deref().map(str::trim),
+        Ok("0" | "false" | "off")
+    )
+}
+
+// ─── Registry API ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct OwnersResponse {
+    #[serde(default)]
+    users: Vec<Account>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionsResponse {
+    #[serde(default)]
+    versions: Vec<PublishedVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PublishedVersion {
+    #[serde(default)]
+    num: String,
+    #[serde(default)]
+    published_by: Option<Account>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    login: String,
+}
+
+/// Reads the owners and publishers of `crate_name` from the API of
+/// `registry`.
+pub async fn fetch(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+) -> Result<CrateOwners> {
+    let api = registry.api.trim_end_matches('/');
+    let owners = get(client, &format!("{api}/crates/{crate_name}/owners")).await?;
+    let versions = get(client, &format!("{api}/crates/{crate_name}")).await?;
+    parse(&owners, &versions).with_context(|| format!("parse owners of {crate_name}"))
+}
+
+/// Reads the account that published `crate_name@version` from the API of
+/// `registry`; `None` when the registry does not record it.
+pub async fn publisher(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+    version: &str,
+) -> Result<Option<String>> {
+    let api = registry.api.trim_end_matches('/');
+    let versions = get(client, &format!("{api}/crates/{crate_name}")).await?;
+    parse_publisher(&versions, version).with_context(|| format!("parse versions of {crate_name}"))
+}
+
+async fn get(client: &RegistryHttp, url: &str) -> Result<Vec<u8>> {
+    let resp = client.get(url).await?;
+    if !resp.status.is_success() {
+        return Err(anyhow::Error::new(HttpStatus(resp.status)).context(format!("look up {url}")));
+    }
+    Ok(resp.body)
+}
+
+/// Owners from an `/owners` response and publishers from a crate response.
+fn parse(owners: &[u8], versions: &[u8]) -> serde_json::Result<CrateOwners> {
+    let owners: OwnersResponse = serde_json::from_slice(owners)?;
+    let versions: VersionsResponse = serde_json::from_slice(versions)?;
+    Ok(CrateOwners::new(
+        owners.users.into_iter().map(|a| a.login),
+        versions
+            .versions
+            .into_iter()
+            .filter_map(|v| v.published_by.map(|a| a.login)),
+    ))
+}
+
+impl CrateOwners {
+    /// Sorted, deduplicated owners and publishers.
+    pub fn new(
+        owners: impl IntoIterator<Item = String>,
+        publishers: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            owners: sorted(owners),
+            publishers: sorted(publishers),
+        }
+    }
+}
+
+fn sorted(items: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut items: Vec<String> = items.into_iter().collect();
+    items.sort();
+    items.dedup();
+    items
+}
+
+/// Publisher of `version` in a crate response.
+fn parse_publisher(versions: &[u8], version: &str) -> serde_json::Result<Option<String>> {
+    let versions: VersionsResponse = serde_json::from_slice(versions)?;
+    Ok(versions
+        .versions
+        .into_iter()
+        .find(|v| v.num == version)
+        .and_then(|v| v.published_by)
+        .map(|a| a.login))
+}
+
+// ─── State ────────────────────────────────────────────────────────────────────
+
+impl OwnershipState {
+    /// File name of the state inside the state dir.
+    pub const FILE_NAME: &'static str = "crate-owners.json";
+
+    pub fn default_path() -> PathBuf {
+        state_dir().join(Self::FILE_NAME)
+    }
+
+    /// An empty state that will be saved to `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            crates: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the state at `path`; a missing file yields an empty state.
+    pub fn load(path: &Path) -> Result<Self> {
+        let crates = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parse ownership state {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("read ownership state {}", path.display()));
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            crates,
+        })
+    }
+
+    /// Compares `seen` with the acknowledged owners of `crate_name`.  The
+    /// first sighting of a crate becomes its baseline.  A change stays
+    /// pending until acknowledged, even if the owners revert; when the
+    /// difference changes again it is updated and alerted anew.
+    pub fn observe(&mut self, crate_name: &str, seen: CrateOwners, now: u64) {
+        let Some(record) = self.crates.get_mut(crate_name) else {
+            self.crates.insert(
+                crate_name.to_string(),
+                OwnershipRecord {
+                    known: seen,
+                    pending: None,
+                },
+            );
+            return;
+        };
+        let missing = |from: &[String], among: &[String]| -> Vec<String> {
+            from.iter()
+                .filter(|x| !among.contains(x))
+                .cloned()
+                .collect()
+        };
+        let known = &record.known;
+        let added_owners = missing(&seen.owners, &known.owners);
+        let removed_owners = missing(&known.owners, &seen.owners);
+        let new_publishers = missing(&seen.publishers, &known.publishers);
+        if added_owners.is_empty() && removed_owners.is_empty() && new_publishers.is_empty() {
+            return;
+        }
+        let previous = record.pending.take();
+        let unchanged = previous.as_ref().is_some_and(|p| {
+            p.added_owners == added_owners
+                && p.removed_owners == removed_owners
+                && p.new_publishers == new_publishers
+        });
+        record.pending = Some(OwnershipChange {
+            crate_name: crate_name.to_string(),
+            added_owners,
+            removed_owners,
+            new_publishers,
+            observed: seen,
+            detected_at: previous.as_ref().map_or(now, |p| p.detected_at),
+            notified: unchanged && previous.is_some_and(|p| p.notified),
+        });
+    }
+
+    /// Changes awaiting acknowledgement.
+    pub fn pending(&self) -> Vec<&OwnershipChange> {
+        self.crates
+            .values()
+            .filter_map(|r| r.pending.as_ref())
+            .collect()
+    }
+
+    /// Marks the pending change of `crate_name` as alerted.
+    pub fn mark_notified(&mut self, crate_name: &str) {
+        if let Some(pending) = self
+            .crates
+            .get_mut(crate_name)
+            .and_then(|r| r.pending.as_mut())
+        {
+            pending.notified = true;
+        }
+    }
+
+    /// Accepts the pending change of `crate_name` as its new baseline;
+    /// returns whether there was one.
+    pub fn acknowledge(&mut self, crate_name: &str) -> bool {
+        let Some(record) = self.crates.get_mut(crate_name) else {
+            return false;
+        };
+        match record.pending.take() {
+            Some(change) => {
+                record.known = change.observed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text =
+            serde_json::to_string_pretty(&self.crates).context("serialize ownership state")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write ownership state {}", self.path.display()))
+    }
+}
+
+// ─── Alerts ───────────────────────────────────────────────────────────────────
+
+/// POSTs a high-severity `ownership_change` alert to the king's
+/// `/admin/alerts` endpoint.
+pub async fn alert_king(
+    king: &dyn KingClient,
+    run_id: &str,
+    changes: &[OwnershipChange],
+) -> Result<()> {
+    king.post_ok(
+        "/admin/alerts",
+        &json!({
+            "kind": "ownership_change",
+            "severity": "high",
+            "source": "update-agent",
+            "run_id": run_id,
+            "message": "the owners or publishers of a tracked crate changed — its updates are held until acknowledged",
+            "details": changes,
+        }),
+    )
+    .await
+    .map(drop)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owners(owners: &[&str], publishers: &[&str]) -> CrateOwners {
+        CrateOwners::new(
+            owners.iter().map(|o| o.to_string()),
+            publishers.iter().map(|p| p.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_change_stays_pending_until_acknowledged() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(OwnershipState::FILE_NAME);
+        let mut state = OwnershipState::load(&path).unwrap();
+
+        state.observe("evo-common", owners(&["alice", "bob"], &["alice"]), 100);
+        assert!(state.pending().is_empty());
+
+        state.observe("evo-common", owners(&["alice", "mallory"], &["alice"]), 200);
+        let change = state.pending()[0].clone();
+        assert_eq!(change.added_owners, vec!["mallory"]);
+        assert_eq!(change.removed_owners, vec!["bob"]);
+        assert!(change.new_publishers.is_empty());
+        assert_eq!(change.detected_at, 200);
+        state.mark_notified("evo-common");
+        state.save().unwrap();
+
+        // Reverting the owners does not lift the hold.
+        let mut state = OwnershipState::load(&path).unwrap();
+        state.observe("evo-common", owners(&["alice", "bob"], &["alice"]), 300);
+        assert!(state.pending()[0].notified);
+
+        // A new publisher widens the change and is alerted again.
+        state.observe(
+            "evo-common",
+            owners(&["alice", "mallory"], &["alice", "mallory"]),
+            400,
+        );
+        let change = state.pending()[0];
+        assert_eq!(change.new_publishers, vec!["mallory"]);
+        assert_eq!(change.detected_at, 200);
+        assert!(!change.notified);
+
+        assert!(state.acknowledge("evo-common"));
+        assert!(!state.acknowledge("evo-common"));
+        assert!(state.pending().is_empty());
+        state.observe(
+            "evo-common",
+            owners(&["alice", "mallory"], &["mallory", "alice"]),
+            500,
+        );
+        assert!(state.pending().is_empty());
+    }
+
+    #[test]
+    fn test_parse_registry_responses() {
+        let parsed = parse(
+            br#"{"users":[{"id":1,"login":"github:ai-evo-agents:core","kind":"team"},{"id":2,"login":"alice","kind":"user"}]}"#,
+            br#"{"crate":{},"versions":[{"num":"0.2.0","published_by":{"login":"alice"}},{"num":"0.1.0","published_by":null}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.owners, vec!["alice", "github:ai-evo-agents:core"]);
+        assert_eq!(parsed.publishers, vec!["alice"]);
+
+        let versions = br#"{"versions":[{"num":"0.2.0","published_by":{"login":"alice"}},{"num":"0.1.0","published_by":null}]}"#;
+        assert_eq!(
+            parse_publisher(versions, "0.2.0").unwrap().as_deref(),
+            Some("alice")
+        );
+        assert_eq!(parse_publisher(versions, "0.1.0").unwrap(), None);
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::fixtures::RegistryHttp;
+use crate::versions::{is_prerelease, needs_update, requirement_satisfied};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Default npm registry root.
+const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org";
+
+/// `package.json` objects whose entries are dependency ranges.
+const SECTIONS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+// ─── npm registry ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct Packument {
+    #[serde(default, rename = "dist-tags")]
+    dist_tags: BTreeMap<String, String>,
+    #[serde(default)]
+    versions: BTreeMap<String, PackageVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageVersion {
+    #[serde(default)]
+    deprecated: Option<serde_json::Value>,
+}
+
+/// Registry root from `NPM_REGISTRY_URL`, default [`DEFAULT_REGISTRY`].
+pub fn registry_url() -> String {
+    std::env::var("NPM_REGISTRY_URL").unwrap_or_else(|_| DEFAULT_REGISTRY.to_string())
+}
+
+/// Latest stable version of `package` (e.g. `@evo/agent-client`) on the
+/// registry at `registry`.
+pub async fn latest_version(
+    client: &RegistryHttp,
+    registry: &str,
+    package: &str,
+) -> Result<String> {
+    let url = format!(
+        "{}/{}",
+        registry.trim_end_matches('/'),
+        package.replace('/', "%2F")
+    );
+    let resp = client.get(&url).await?.ensure_success(&url)?;
+    let packument: Packument = serde_json::from_slice(&resp.body)
+        .with_context(|| format!("parse npm registry response for {package}"))?;
+    resolve_latest(&packument)
+        .with_context(|| format!("{package} has no stable release on the npm registry"))
+}
+
+/// The `latest` dist-tag when it is a stable release, else the highest
+/// stable version that is not deprecated.
+fn resolve_latest(packument: &Packument) -> Option<String> {
+    if let Some(latest) = packument.dist_tags.get("latest")
+        && !is_prerelease(latest)
+    {
+        return Some(latest.clone());
+    }
+    packument
+        .versions
+        .iter()
+        .filter(|(version, meta)| !is_prerelease(version) && meta.deprecated.is_none())
+        .map(|(version, _)| version.as_str())
+        .fold(None, |best: Option<&str>, v| match best {
+            Some(b) if !needs_update(b, v) => Some(b),
+            _ => Some(v),
+        })
+        .map(str::to_string)
+}
+
+// ─── Ranges ───────────────────────────────────────────────────────────────────
+
+/// Splits a single-comparator range (`^1.2.3`, `~1.2.3`, `=1.2.3`,
+/// `>=1.2.3` or a bare `1.2.3`) into its operator and version.  Unions,
+/// intervals, wildcards, tags and protocols (`workspace:`, `npm:`, URLs)
+/// yield `None`.
+fn split_range(range: &str) -> Option<(&str, &str)> {
+    let range = range.trim();
+    let op_len = ["^", "~", ">=", "="]
+        .iter()
+        .find(|op| range.starts_with(**op))
+        .map_or(0, |op| op.len());
+    let (op, version) = range.split_at(op_len);
+    let version = version.trim_start().trim_start_matches('v');
+    let release = version.split(['-', '+']).next()?;
+    let parts: Vec<&str> = release.split('.').collect();
+    (parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit())))
+    .then_some((op, version))
+}
+
+/// The version a bumpable range names, e.g. `1.2.3` of `^1.2.3`.
+pub fn range_version(range: &str) -> Option<String> {
+    split_range(range).map(|(_, version)| version.to_string())
+}
+
+/// Whether `version` satisfies the npm `range`.  Handles `||` unions,
+/// space-separated comparators, `^`, `~`, bare (exact) versions and
+/// `x` / `*` wildcards; tags and protocols admit nothing.
+pub fn admits(range: &str, version: &str) -> bool {
+    range.split("||").any(|alternative| {
+        let comparators: Vec<String> = alternative
+            .split_whitespace()
+            .map(|c| match c.chars().next() {
+                Some(first) if first.is_ascii_digit() => format!("={c}"),
+                _ => c.to_string(),
+            })
+            .collect();
+        let requirement = if comparators.is_empty() {
+            "*".to_string()
+        } else {
+            comparators.join(",")
+        };
+        requirement_satisfied(&requirement, version)
+    })
+}
+
+// ─── package.json ─────────────────────────────────────────────────────────────
+
+/// A cursor over JSON text that records where values are, so single
+/// strings can be replaced without re-serializing the document.
+struct Scanner<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// A string literal; returns the byte range between its quotes.
+    fn string(&mut self) -> Option<Range<usize>> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match *self.text.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(start..self.pos - 1);
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skips any value.
+    fn value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => self.string().map(drop),
+            b'{' => self.object(|scanner, _| scanner.value()),
+            b'[' => {
+                self.pos += 1;
+                if self.peek()? == b']' {
+                    self.pos += 1;
+                    return Some(());
+                }
+                loop {
+                    self.value()?;
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        b']' => {
+                            self.pos += 1;
+                            return Some(());
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .text
+                    .get(self.pos)
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                (self.pos > start).then_some(())
+            }
+        }
+    }
+
+    /// An object; `member` is called with each key's range and must
+    /// consume the member's value.
+    fn object(
+        &mut self,
+        mut member: impl FnMut(&mut Self, Range<usize>) -> Option<()>,
+    ) -> Option<()> {
+        self.expect(b'{')?;
+        if self.peek()? == b'}' {
+            self.pos += 1;
+            return Some(());
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            member(self, key)?;
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b'}' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Byte ranges of the range strings `package` has in the dependency
+/// sections of `content`, in document order.
+fn dependency_ranges(content: &str, package: &str) -> Option<Vec<Range<usize>>> {
+    let text = content.as_bytes();
+    let mut found = Vec::new();
+    let mut scanner = Scanner::new(content);
+    scanner.object(|scanner, key| {
+        let section = &content[key];
+        if !SECTIONS.contains(&section) || scanner.peek()? != b'{' {
+            return scanner.value();
+        }
+        scanner.object(|scanner, key| {
+            if &text[key] == package.as_bytes() && scanner.peek()? == b'"' {
+                found.push(scanner.string()?);
+                Some(())
+            } else {
+                scanner.value()
+            }
+        })
+    })?;
+    Some(found)
+}
+
+/// Range of the first entry for `package` in `dependencies`,
+/// `devDependencies`, `peerDependencies` or `optionalDependencies`.
+pub fn current_range(content: &str, package: &str) -> Option<String> {
+    let ranges = dependency_ranges(content, package)?;
+    ranges
+        .first()
+        .map(|range| content[range.clone()].to_string())
+}
+
+/// Moves every entry for `package` whose range excludes `latest` to the
+/// same operator on `latest` (`^0.3.1` → `^0.5.2`, `1.2.3` → `2.0.0`),
+/// changing nothing else in the file.  Ranges [`range_version`] cannot
+/// read are left alone.
+pub fn patch_dependency(content: &str, package: &str, latest: &str) -> Result<String> {
+    serde_json::from_str::<serde_json::Value>(content).context("parse package.json")?;
+    let ranges = dependency_ranges(content, package).context("scan package.json")?;
+    let mut patched = content.to_string();
+    for span in ranges.into_iter().rev() {
+        let range = &content[span.clone()];
+        if admits(range, latest) {
+            continue;
+        }
+        let Some((op, _)) = split_range(range) else {
+            continue;
+        };
+        patched.replace_range(span, &format!("{op}{latest}"));
+    }
+    Ok(patched)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE_JSON: &str = r#"{
+  "name": "evo-dashboard",
+  "version": "1.4.0",
+  "scripts": { "build": "vite build", "note": "\"@evo/agent-client\": \"^0.1.0\"" },
+  "dependencies": {
+    "@evo/agent-client": "^0.3.1",
+    "react": "^18.2.0"
+  },
+  "devDependencies": {
+    "@evo/agent-client-mock": "0.3.1",
+    "@evo/agent-client":   "~0.3.1"
+  },
+  "peerDependencies": { "@evo/agent-client": ">=0.3.0 <0.4.0" }
+}
+"#;
+
+    #[test]
+    fn test_ranges() {
+        assert!(admits("^0.3.1", "0.3.9"));
+        assert!(!admits("^0.3.1", "0.4.0"));
+        assert!(admits("~1.2.0", "1.2.7"));
+        assert!(!admits("1.2.3", "1.2.4"));
+        assert!(admits("1.x", "1.9.0"));
+        assert!(admits(">=0.3.0 <0.4.0", "0.3.5"));
+        assert!(admits("^1.0.0 || ^2.0.0", "2.1.0"));
+        assert!(admits("*", "3.0.0"));
+        assert!(!admits("workspace:*", "3.0.0"));
+
+        assert_eq!(range_version("^0.3.1").as_deref(), Some("0.3.1"));
+        assert_eq!(range_version("1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(
+            range_version("^1.0.0-beta.1").as_deref(),
+            Some("1.0.0-beta.1")
+        );
+        assert_eq!(range_version(">=0.3.0 <0.4.0"), None);
+        assert_eq!(range_version("1.x"), None);
+        assert_eq!(range_version("npm:other@^1.0.0"), None);
+    }
+
+    #[test]
+    fn test_resolve_latest() {
+        let packument: Packument = serde_json::from_str(
+            r#"{"dist-tags":{"latest":"0.5.0","next":"0.6.0-rc.1"},"versions":{"0.5.0":{}}}"#,
+        )
+        .unwrap();
+        assert_eq!(resolve_latest(&packument).as_deref(), Some("0.5.0"));
+
+        let packument: Packument = serde_json::from_str(
+            r#"{"dist-tags":{"latest":"1.0.0-rc.1"},"versions":{
+                "0.9.0":{},"0.10.0":{},"0.11.0":{"deprecated":"broken"},"1.0.0-rc.1":{}}}"#,
+        )
+        .unwrap();
+        assert_eq!(resolve_latest(&packument).as_deref(), Some("0.10.0"));
+    }
+
+    #[test]
+    fn test_patch_dependency() {
+        assert_eq!(
+            current_range(PACKAGE_JSON, "@evo/agent-client").as_deref(),
+            Some("^0.3.1")
+        );
+        assert_eq!(current_range(PACKAGE_JSON, "missing"), None);
+
+        let patched = patch_dependency(PACKAGE_JSON, "@evo/agent-client", "0.5.2").unwrap();
+        let expected = PACKAGE_JSON
+            .replace(
+                r#""@evo/agent-client": "^0.3.1""#,
+                r#""@evo/agent-client": "^0.5.2""#,
+            )
+            .replace(
+                r#""@evo/agent-client":   "~0.3.1""#,
+                r#""@evo/agent-client":   "~0.5.2""#,
+            );
+        assert_eq!(patched, expected);
+
+        assert_eq!(
+            patch_dependency(PACKAGE_JSON, "react", "18.3.1").unwrap(),
+            PACKAGE_JSON
+        );
+        assert!(patch_dependency("{\"dependencies\": {", "react", "18.3.1").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use toml_edit::DocumentMut;
+
+use crate::versions::needs_update;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// What the agent decided about a `[patch.crates-io]` override.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchStatus {
+    /// The patch targets a version newer than the latest release — kept, and
+    /// the crate's requirement is left alone in this manifest.
+    Active,
+    /// The patched version has been released — the patch is removed.
+    Stale,
+    /// The patched version cannot be determined (e.g. a git branch) — kept,
+    /// and the crate's requirement is left alone.
+    Unknown,
+}
+
+/// A `[patch.crates-io]` override of a tracked crate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatchEntry {
+    /// Patched crate, e.g. `"evo-common"`.
+    pub crate_name: String,
+    /// Where the override points: `path:…` or `git:…[#tag]`.
+    pub source: String,
+    /// Version the patch provides, when known.
+    pub version: Option<String>,
+}
+
+impl PatchEntry {
+    /// Classifies the patch against the latest released version.
+    pub fn status(&self, latest: &str) -> PatchStatus {
+        match self.version {
+            Some(ref v) if needs_update(latest, v) => PatchStatus::Active,
+            Some(_) => PatchStatus::Stale,
+            None => PatchStatus::Unknown,
+        }
+    }
+}
+
+// ─── Detection ────────────────────────────────────────────────────────────────
+
+/// Lists the `[patch.crates-io]` overrides of `crates` in a manifest.
+///
+/// `manifest_dir` resolves `path` patches, whose version is read from the
+/// patched crate's own `Cargo.toml`; git patches only have a version when
+/// pinned to a `vX.Y.Z` tag.
+pub fn find_patches(content: &str, crates: &[&str], manifest_dir: &Path) -> Vec<PatchEntry> {
+    let Ok(doc) = content.parse::<DocumentMut>() else {
+        return Vec::new();
+    };
+    let Some(table) = doc
+        .get("patch")
+        .and_then(|p| p.get("crates-io"))
+        .and_then(|t| t.as_table_like())
+    else {
+        return Vec::new();
+    };
+
+    let mut entries: Vec<PatchEntry> = Vec::new();
+    for &crate_name in crates {
+        let Some(item) = table.get(crate_name) else {
+            continue;
+        };
+        let field = |key: &str| item.get(key).and_then(|v| v.as_str());
+        let (source, version) = if let Some(path) = field("path") {
+            let version = std::fs::read_to_string(manifest_dir.join(path).join("Cargo.toml"))
+                .ok()
+                .and_then(|c| package_version(&c));
+            (format!("path:{path}"), version)
+        } else if let Some(git) = field("git") {
+            let tag = field("tag");
+            let version = tag.and_then(tag_version);
+            let source = match tag {
+                Some(t) => format!("git:{git}#{t}"),
+                None => format!("git:{git}"),
+            };
+            (source, version)
+        } else {
+            ("unknown".to_string(), None)
+        };
+        entries.push(PatchEntry {
+            crate_name: crate_name.to_string(),
+            source,
+            version,
+        });
+    }
+    entries
+}
+
+/// Removes the `[patch.crates-io]` override of `crate_name`, dropping the
+/// table (and `[patch]`) when it becomes empty.  Formatting elsewhere is kept.
+pub fn remove_patch(content: &str, crate_name: &str) -> Result<String> {
+    let mut doc: DocumentMut = content
+        .parse()
+        .with_context(|| format!("parse Cargo.toml to remove patch for {crate_name}"))?;
+    let patch = doc
+        .get_mut("patch")
+        .and_then(|p| p.as_table_like_mut())
+        .context("no [patch] section")?;
+    let crates_io = patch
+        .get_mut("crates-io")
+        .and_then(|t| t.as_table_like_mut())
+        .context("no [patch.crates-io] section")?;
+    crates_io
+        .remove(crate_name)
+        .with_context(|| format!("{crate_name} is not patched"))?;
+
+    if crates_io.is_empty() {
+        patch.remove("crates-io");
+    }
+    if patch.is_empty() {
+        doc.remove("patch");
+    }
+    Ok(doc.to_string())
+}
+
+// ─── Helpers ──────────────────────────────────────────────────────────────────
+
+/// `[package].version` of a manifest.
+fn package_version(manifest: &str) -> Option<String> {
+    let doc: DocumentMut = manifest.parse().ok()?;
+    doc.get("package")?
+        .get("version")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Version encoded in a release tag such as `v0.4.0` or `evo-common-0.4.0`.
+fn tag_version(tag: &str) -> Option<String> {
+    let version = tag.rsplit(['-', 'v']).next()?;
+    let numeric = version.split('.').count() >= 2
+        && version
+            .split('.')
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+    numeric.then(|| version.to_string())
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const MANIFEST: &str = r#"[package]
+name = "evo-king"
+version = "0.1.0"
+
+[dependencies]
+evo-common = "0.3"
+
+[patch.crates-io]
+evo-common = { path = "../evo-common" }
+evo-agent-sdk = { git = "https://github.com/ai-evo-agents/evo-agents", tag = "v0.3.1" }
+"#;
+
+    #[test]
+    fn test_find_patches_reads_path_and_tag_versions() {
+        let dir = TempDir::new().unwrap();
+        let common = dir.path().join("evo-common");
+        std::fs::create_dir(&common).unwrap();
+        std::fs::write(
+            common.join("Cargo.toml"),
+            "[package]\nname = \"evo-common\"\nversion = \"0.4.0\"\n",
+        )
+        .unwrap();
+        let manifest_dir = dir.path().join("evo-king");
+        std::fs::create_dir(&manifest_dir).unwrap();
+
+        let patches = find_patches(MANIFEST, &["evo-common", "evo-agent-sdk"], &manifest_dir);
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].source, "path:../evo-common");
+        assert_eq!(patches[0].version.as_deref(), Some("0.4.0"));
+        assert_eq!(
+            patches[1].source,
+            "git:https://github.com/ai-evo-agents/evo-agents#v0.3.1"
+        );
+        assert_eq!(patches[1].version.as_deref(), Some("0.3.1"));
+    }
+
+    #[test]
+    fn test_patch_status() {
+        let entry = |v: Option<&str>| PatchEntry {
+            crate_name: "evo-common".to_string(),
+            source: "path:x".to_string(),
+            version: v.map(str::to_string),
+        };
+        assert_eq!(entry(Some("0.5.0")).status("0.4.0"), PatchStatus::Active);
+        assert_eq!(entry(Some("0.4.0")).status("0.4.0"), PatchStatus::Stale);
+        assert_eq!(entry(None).status("0.4.0"), PatchStatus::Unknown);
+    }
+
+    #[test]
+    fn test_remove_patch() {
+        let once = remove_patch(MANIFEST, "evo-common").unwrap();
+        assert!(once.contains("[patch.crates-io]"));
+        assert!(!once.contains("path = \"../evo-common\""));
+        assert!(once.contains("evo-common = \"0.3\""));
+
+        let twice = remove_patch(&once, "evo-agent-sdk").unwrap();
+        assert!(!twice.contains("patch"));
+        assert!(remove_patch(&twice, "evo-common").is_err());
+    }
+
+    #[test]
+    fn test_tag_version() {
+        assert_eq!(tag_version("v0.4.0").as_deref(), Some("0.4.0"));
+        assert_eq!(tag_version("evo-common-0.4.1").as_deref(), Some("0.4.1"));
+        assert_eq!(tag_version("main"), None);
+    }
+}
@@ -0,0 +1,369 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::changelog::MigrationNote;
+use crate::checksum::sha256_hex;
+use crate::mac::{hmac_sha256_hex, verify_hmac_sha256_hex};
+use crate::state::state_dir;
+use crate::versions::VersionReport;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Algorithm recorded in plan signatures.
+const SIGNATURE_ALG: &str = "hmac-sha256";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// One file change of a plan, with everything Phase 4 needs to deliver it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedChange {
+    pub repo: String,
+    pub file: String,
+    /// sha256 of the content the change applies to.
+    pub base_sha256: String,
+    /// sha256 of `content`.
+    pub sha256: String,
+    /// Unified diff from `original` to `content`, for review.
+    pub diff: String,
+    pub original: String,
+    pub content: String,
+    pub commit_message: String,
+    pub versions: Vec<VersionReport>,
+    pub migration_notes: Vec<MigrationNote>,
+    pub requires_pr: bool,
+    pub verified_sha256: Option<String>,
+    pub part_of: Option<String>,
+}
+
+/// The changes a `plan` run would deliver, stored for a later `apply`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdatePlan {
+    /// Id of the run that made the plan.
+    pub plan_id: String,
+    pub created_at: u64,
+    /// `config_version` the plan was made with.
+    pub config_version: String,
+    /// Metadata of the plan run (without `action`); `apply` runs with it.
+    pub metadata: Value,
+    /// Latest version of each tracked crate when planned.
+    pub versions: BTreeMap<String, String>,
+    pub changes: Vec<PlannedChange>,
+    /// Signature over the rest of the plan; `None` when no key was set.
+    #[serde(default)]
+    pub signature: Option<PlanSignature>,
+}
+
+/// HMAC over a plan's serialized form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanSignature {
+    pub alg: String,
+    pub key_id: String,
+    /// Hex encoded MAC.
+    pub value: String,
+}
+
+/// The shared secret plans are signed and verified with.
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    pub id: String,
+    secret: String,
+}
+
+/// A planned file whose content changed since the plan was made.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Drift {
+    pub repo: String,
+    pub file: String,
+    /// `changed`, or `unreadable` with the error.
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ─── Signing ──────────────────────────────────────────────────────────────────
+
+impl SigningKey {
+    /// The key from `PLAN_SIGNING_KEY` (and `PLAN_SIGNING_KEY_ID`, default
+    /// `default`); `None` when unset.
+    pub fn from_env() -> Option<Self> {
+        let secret = std::env::var("PLAN_SIGNING_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())?;
+        let id = std::env::var("PLAN_SIGNING_KEY_ID").unwrap_or_else(|_| "default".to_string());
+        Some(Self { id, secret })
+    }
+
+    fn mac(&self, plan: &UpdatePlan) -> Result<String> {
+        Ok(hmac_sha256_hex(
+            self.secret.as_bytes(),
+            &unsigned_bytes(plan)?,
+        ))
+    }
+
+    /// Whether `value` is this key's MAC of `plan`, compared in constant time.
+    fn verifies(&self, plan: &UpdatePlan, value: &str) -> Result<bool> {
+        Ok(verify_hmac_sha256_hex(
+            self.secret.as_bytes(),
+            &unsigned_bytes(plan)?,
+            value,
+        ))
+    }
+}
+
+/// Whether `ALLOW_UNSIGNED_PLANS` opts in to applying unsigned plans.
+pub fn unsigned_allowed_from_env() -> bool {
+    std::env::var("ALLOW_UNSIGNED_PLANS").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// The serialized form of `plan` without its signature, which is signed.
+fn unsigned_bytes(plan: &UpdatePlan) -> Result<Vec<u8>> {
+    let unsigned = UpdatePlan {
+        signature: None,
+        ..plan.clone()
+    };
+    serde_json::to_vec(&unsigned).context("serialize plan")
+}
+
+impl UpdatePlan {
+    /// Signs the plan with `key`, replacing any earlier signature.
+    pub fn sign(&mut self, key: &SigningKey) -> Result<()> {
+        self.signature = Some(PlanSignature {
+            alg: SIGNATURE_ALG.to_string(),
+            key_id: key.id.clone(),
+            value: key.mac(self)?,
+        });
+        Ok(())
+    }
+
+    /// Checks the plan is signed by `key`.  An unsigned plan only passes
+    /// with `allow_unsigned`, and a signed one never passes without a key,
+    /// since a signature nobody can check proves nothing.
+    pub fn verify(&self, key: Option<&SigningKey>, allow_unsigned: bool) -> Result<()> {
+        let id = &self.plan_id;
+        match (key, &self.signature) {
+            (_, None) if allow_unsigned => Ok(()),
+            (None, None) => anyhow::bail!(
+                "PLAN_SIGNING_KEY is not set — refusing to apply plan {id} \
+                 (set ALLOW_UNSIGNED_PLANS=1 to apply unsigned plans)"
+            ),
+            (None, Some(_)) => {
+                anyhow::bail!("plan {id} is signed but PLAN_SIGNING_KEY is not set")
+            }
+            (Some(_), None) => anyhow::bail!("plan {id} is not signed"),
+            (Some(key), Some(signature)) => {
+                anyhow::ensure!(
+                    signature.alg == SIGNATURE_ALG,
+                    "plan {id} is signed with unsupported {}",
+                    signature.alg
+                );
+                anyhow::ensure!(
+                    signature.key_id == key.id,
+                    "plan {id} is signed with key {}, not {}",
+                    signature.key_id,
+                    key.id
+                );
+                anyhow::ensure!(
+                    key.verifies(self, &signature.value)?,
+                    "plan {id} does not match its signature"
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+// ─── Plan store ───────────────────────────────────────────────────────────────
+
+/// Plans kept as `<plan_id>.json` in a directory (default
+/// `$UPDATE_STATE_DIR/plans`).
+#[derive(Debug, Clone)]
+pub struct PlanStore {
+    dir: PathBuf,
+}
+
+impl PlanStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The store inside [`state_dir`].
+    pub fn default_location() -> Self {
+        Self::new(state_dir().join("plans"))
+    }
+
+    /// Writes `plan` and returns its path.
+    pub fn save(&self, plan: &UpdatePlan) -> Result<PathBuf> {
+        let path = self.path(&plan.plan_id)?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create plan dir {}", self.dir.display()))?;
+        let text = serde_json::to_string_pretty(plan).context("serialize plan")?;
+        std::fs::write(&path, text).with_context(|| format!("write plan {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Reads plan `plan_id`.
+    pub fn load(&self, plan_id: &str) -> Result<UpdatePlan> {
+        let path = self.path(plan_id)?;
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("no plan {plan_id} at {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("parse plan {}", path.display()))
+    }
+
+    /// Path of plan `plan_id`; ids are run ids, never paths.
+    fn path(&self, plan_id: &str) -> Result<PathBuf> {
+        anyhow::ensure!(
+            !plan_id.is_empty()
+                && plan_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                && !plan_id.starts_with('.'),
+            "invalid plan id {plan_id:?}"
+        );
+        Ok(self.dir.join(format!("{plan_id}.json")))
+    }
+}
+
+// ─── Drift ────────────────────────────────────────────────────────────────────
+
+/// Planned files whose `current` content no longer hashes to the content
+/// the plan was computed from.
+pub async fn drift<'p, F>(
+    plan: &'p UpdatePlan,
+    current: impl Fn(&'p str, &'p str) -> F,
+) -> Vec<Drift>
+where
+    F: Future<Output = Result<String>>,
+{
+    let mut drifted = Vec::new();
+    for change in &plan.changes {
+        let (reason, error) = match current(&change.repo, &change.file).await {
+            Ok(text) if sha256_hex(text.as_bytes()) == change.base_sha256 => continue,
+            Ok(_) => ("changed", None),
+            Err(e) => ("unreadable", Some(format!("{e:#}"))),
+        };
+        drifted.push(Drift {
+            repo: change.repo.clone(),
+            file: change.file.clone(),
+            reason: reason.to_string(),
+            error,
+        });
+    }
+    drifted
+}
+
+/// `{ "plan_id", "path", "changes", "repos" }` for a run summary.
+pub fn summary(plan: &UpdatePlan, path: &std::path::Path) -> Value {
+    let mut repos: Vec<&str> = plan.changes.iter().map(|c| c.repo.as_str()).collect();
+    repos.dedup();
+    json!({
+        "plan_id": plan.plan_id,
+        "path": path.display().to_string(),
+        "changes": plan.changes.len(),
+        "repos": repos,
+    })
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn plan() -> UpdatePlan {
+        let original = "[dependencies]\nevo-common = \"0.3\"\n";
+        let content = "[dependencies]\nevo-common = \"0.4.0\"\n";
+        UpdatePlan {
+            plan_id: "run-42".to_string(),
+            created_at: 1,
+            config_version: "builtin".to_string(),
+            metadata: json!({ "commit_mode": "pr" }),
+            versions: BTreeMap::from([("evo-common".to_string(), "0.4.0".to_string())]),
+            changes: vec![PlannedChange {
+                repo: "evo-king".to_string(),
+                file: "Cargo.toml".to_string(),
+                base_sha256: sha256_hex(original.as_bytes()),
+                sha256: sha256_hex(content.as_bytes()),
+                diff: String::new(),
+                original: original.to_string(),
+                content: content.to_string(),
+                commit_message: "chore(deps): bump evo-common to 0.4.0".to_string(),
+                versions: Vec::new(),
+                migration_notes: Vec::new(),
+                requires_pr: false,
+                verified_sha256: None,
+                part_of: None,
+            }],
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_store_round_trip_and_rejects_paths() {
+        let dir = TempDir::new().unwrap();
+        let store = PlanStore::new(dir.path().join("plans"));
+        let path = store.save(&plan()).unwrap();
+        assert!(path.ends_with("plans/run-42.json"));
+        assert_eq!(store.load("run-42").unwrap(), plan());
+        assert!(store.load("run-43").is_err());
+        assert!(store.load("../checkpoint").is_err());
+        assert!(store.load("").is_err());
+    }
+
+    fn signing_key(id: &str, secret: &str) -> SigningKey {
+        SigningKey {
+            id: id.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = signing_key("ops-2026", "s3cret");
+        let mut signed = plan();
+        signed.sign(&key).unwrap();
+        signed.verify(Some(&key), false).unwrap();
+        assert!(signed.verify(None, false).is_err());
+        assert!(signed.verify(None, true).is_err());
+        assert!(plan().verify(Some(&key), false).is_err());
+        let err = plan().verify(None, false).unwrap_err();
+        assert!(err.to_string().contains("PLAN_SIGNING_KEY is not set"));
+        plan().verify(None, true).unwrap();
+        plan().verify(Some(&key), true).unwrap();
+        assert!(
+            signed
+                .verify(Some(&signing_key("ops-2026", "other")), false)
+                .is_err()
+        );
+        assert!(
+            signed
+                .verify(Some(&signing_key("old", "s3cret")), false)
+                .is_err()
+        );
+
+        let mut tampered = signed.clone();
+        tampered.changes[0].content = "[dependencies]\nevil = \"1\"\n".to_string();
+        let err = tampered.verify(Some(&key), false).unwrap_err();
+        assert!(err.to_string().contains("does not match its signature"));
+    }
+
+    #[tokio::test]
+    async fn test_drift() {
+        let plan = plan();
+        let original = plan.changes[0].original.clone();
+        assert!(
+            drift(&plan, |_, _| async { Ok(original.clone()) })
+                .await
+                .is_empty()
+        );
+        let drifted = drift(&plan, |_, _| async { Ok("[dependencies]\n".to_string()) }).await;
+        assert_eq!(drifted.len(), 1);
+        assert_eq!(drifted[0].reason, "changed");
+        let unreadable = drift(&plan, |_, _| async { anyhow::bail!("404 Not Found") }).await;
+        assert_eq!(unreadable[0].reason, "unreadable");
+        assert_eq!(unreadable[0].error.as_deref(), Some("404 Not Found"));
+    }
+}
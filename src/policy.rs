@@ -0,0 +1,219 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::PolicyConfig;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Which policy kept an update from being applied.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyBlock {
+    /// The crate's owners or publishers changed and nobody acknowledged it.
+    OwnershipChange,
+    /// Listed in the global deny list.
+    GlobalDeny,
+    /// Listed in the repo's `deny_crates`.
+    RepoDeny,
+    /// The global allow list is set and does not list the crate.
+    NotGloballyAllowed,
+    /// The repo's `allow_crates` is set and does not list the crate.
+    NotAllowedForRepo,
+}
+
+/// Global crate allow/deny lists.
+///
+/// A deny list names crates that are never updated automatically; an allow
+/// list, when set, restricts updates to the crates it names.  Deny wins over
+/// allow, and global lists are checked before per-repo ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CratePolicy {
+    deny: Vec<String>,
+    allow: Option<Vec<String>>,
+    /// Crates held for an unacknowledged ownership change.
+    held: Vec<String>,
+}
+
+impl CratePolicy {
+    /// Builds a policy from explicit lists.
+    pub fn new(deny: Vec<String>, allow: Option<Vec<String>>) -> Self {
+        Self {
+            deny,
+            allow,
+            held: Vec::new(),
+        }
+    }
+
+    /// Also blocks `held`, the crates with an unacknowledged ownership
+    /// change.
+    pub fn with_held(mut self, held: Vec<String>) -> Self {
+        self.held = held;
+        self
+    }
+
+    /// Global lists from the config file, each overridden by
+    /// `UPDATE_DENY_CRATES` / `UPDATE_ALLOW_CRATES` (comma-separated) when set.
+    pub fn from_env_or(config: &PolicyConfig) -> Self {
+        let list = |name: &str| std::env::var(name).ok().map(|v| parse_list(&v));
+        Self::new(
+            list("UPDATE_DENY_CRATES").unwrap_or_else(|| config.deny_crates.clone()),
+            match list("UPDATE_ALLOW_CRATES") {
+                Some(allow) => Some(allow).filter(|l| !l.is_empty()),
+                None => config.allow_crates.clone(),
+            },
+        )
+    }
+
+    /// Checks `crate_name` against the global lists and then the repo's own
+    /// `repo_deny` / `repo_allow`.  Returns the first policy that blocks it.
+    pub fn check(
+        &self,
+        crate_name: &str,
+        repo_deny: &[String],
+        repo_allow: Option<&[String]>,
+    ) -> Option<PolicyBlock> {
+        if self.held.iter().any(|c| c == crate_name) {
+            Some(PolicyBlock::OwnershipChange)
+        } else if self.deny.iter().any(|c| c == crate_name) {
+            Some(PolicyBlock::GlobalDeny)
+        } else if repo_deny.iter().any(|c| c == crate_name) {
+            Some(PolicyBlock::RepoDeny)
+        } else if self
+            .allow
+            .as_ref()
+            .is_some_and(|allow| !allow.iter().any(|c| c == crate_name))
+        {
+            Some(PolicyBlock::NotGloballyAllowed)
+        } else if repo_allow.is_some_and(|allow| !allow.iter().any(|c| c == crate_name)) {
+            Some(PolicyBlock::NotAllowedForRepo)
+        } else {
+            None
+        }
+    }
+}
+
+// ─── Auto-apply level ─────────────────────────────────────────────────────────
+
+/// Which updates are applied without review.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoApply {
+    /// Every update that passes the other checks.
+    #[default]
+    All,
+    /// Only manifest updates that fix a known advisory; every other bump is
+    /// held for review.
+    SecurityOnly,
+}
+
+impl AutoApply {
+    /// `[policy].auto_apply`, overridden by `UPDATE_AUTO_APPLY` (`all` or
+    /// `security_only`) when set.
+    pub fn from_env_or(config: &PolicyConfig) -> Self {
+        match std::env::var("UPDATE_AUTO_APPLY") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                warn!(value = %value, "unknown UPDATE_AUTO_APPLY — using [policy].auto_apply");
+                config.auto_apply
+            }),
+            Err(_) => config.auto_apply,
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().replace('-', "_").as_str() {
+            "all" => Some(AutoApply::All),
+            "security_only" => Some(AutoApply::SecurityOnly),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a comma-separated crate list, dropping blanks.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_auto_apply() {
+        assert_eq!(AutoApply::parse("all"), Some(AutoApply::All));
+        assert_eq!(
+            AutoApply::parse(" security-only "),
+            Some(AutoApply::SecurityOnly)
+        );
+        assert_eq!(
+            AutoApply::parse("security_only"),
+            Some(AutoApply::SecurityOnly)
+        );
+        assert_eq!(AutoApply::parse("some"), None);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(parse_list(" a, ,b "), vec!["a", "b"]);
+        assert!(parse_list("").is_empty());
+    }
+
+    #[test]
+    fn test_default_policy_allows_everything() {
+        assert_eq!(CratePolicy::default().check("evo-common", &[], None), None);
+    }
+
+    #[test]
+    fn test_deny_lists() {
+        let policy = CratePolicy::new(vec!["evo-common".into()], None);
+        assert_eq!(
+            policy.check("evo-common", &[], None),
+            Some(PolicyBlock::GlobalDeny)
+        );
+        assert_eq!(
+            policy.check("evo-agent-sdk", &["evo-agent-sdk".to_string()], None),
+            Some(PolicyBlock::RepoDeny)
+        );
+    }
+
+    #[test]
+    fn test_held_crates_come_first() {
+        let policy =
+            CratePolicy::new(vec!["evo-common".into()], None).with_held(vec!["evo-common".into()]);
+        assert_eq!(
+            policy.check("evo-common", &[], None),
+            Some(PolicyBlock::OwnershipChange)
+        );
+        assert_eq!(policy.check("evo-agent-sdk", &[], None), None);
+    }
+
+    #[test]
+    fn test_allow_lists() {
+        let policy = CratePolicy::new(vec![], Some(vec!["evo-agent-sdk".into()]));
+        assert_eq!(
+            policy.check("evo-common", &[], None),
+            Some(PolicyBlock::NotGloballyAllowed)
+        );
+        assert_eq!(policy.check("evo-agent-sdk", &[], None), None);
+        assert_eq!(
+            policy.check("evo-agent-sdk", &[], Some(&["evo-common".to_string()])),
+            Some(PolicyBlock::NotAllowedForRepo)
+        );
+    }
+
+    #[test]
+    fn test_deny_wins_over_allow() {
+        let policy = CratePolicy::new(vec!["evo-common".into()], Some(vec!["evo-common".into()]));
+        assert_eq!(
+            policy.check("evo-common", &[], None),
+            Some(PolicyBlock::GlobalDeny)
+        );
+    }
+}
@@ -0,0 +1,543 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+use crate::changelog::{MigrationNote, render_markdown};
+use crate::diff::{added_lines, unified_diff};
+use crate::git::{commit_tree_via_gh, commit_via_gh_cli, gh_api, gh_api_with_body};
+use crate::metadata::{CrateLinks, source_repo};
+use crate::osv::OsvFinding;
+use crate::upstream::{self, UpstreamChanges};
+use crate::versions::VersionReport;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A single file included in a grouped pull request.
+#[derive(Debug, Clone)]
+pub struct PrFile {
+    /// Path of the file inside the repo, e.g. `"Cargo.toml"`.
+    pub path: String,
+    /// Content before patching (used to render the diff).
+    pub original: String,
+    /// Patched content to commit on the PR branch.
+    pub content: String,
+    /// Commit message for this file's commit on the PR branch.
+    pub commit_message: String,
+}
+
+/// Outcome of opening a grouped pull request.
+#[derive(Debug, Clone)]
+pub struct PrResult {
+    /// PR number on GitHub.
+    pub number: u64,
+    /// Browser URL of the PR.
+    pub url: String,
+    /// Head branch the updates were committed to.
+    pub branch: String,
+}
+
+/// A review comment on one line of a changed file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineComment {
+    pub path: String,
+    /// 1-based line number in the patched file.
+    pub line: usize,
+    pub body: String,
+}
+
+// ─── Branch / body rendering ──────────────────────────────────────────────────
+
+/// Head branch name used for the grouped update PR of a run.
+pub fn branch_name(run_id: &str) -> String {
+    format!("evo-update/{run_id}")
+}
+
+/// Renders the Markdown body of a grouped update PR.
+///
+/// Sections, mirroring dependabot grouped updates:
+/// - a versions table (`crate | from | to | bump`) with crates.io / docs.rs
+///   links,
+/// - the changelog's BREAKING / Migration passages, verbatim,
+/// - what changed upstream in each crate, summarized from its commit log,
+/// - the OSV vulnerabilities each bump fixes or leaves in place,
+/// - the LLM changelog-risk assessment,
+/// - a collapsible unified diff per changed file.
+pub fn render_pr_body(
+    versions: &[VersionReport],
+    notes: &[MigrationNote],
+    upstream: &[UpstreamChanges],
+    vulnerabilities: &[OsvFinding],
+    files: &[PrFile],
+    analysis: &str,
+    links: &BTreeMap<String, CrateLinks>,
+) -> String {
+    let mut body = String::from("Automated dependency update from `evo-kernel-agent-update`.\n\n");
+
+    if !versions.is_empty() {
+        body.push_str(
+            "## Versions\n\n| Crate | From | To | Bump | Release |\n|-------|------|----|------|---------|\n",
+        );
+        let mut seen: Vec<(&str, &str, &str)> = Vec::new();
+        for r in versions {
+            // The same bump can come from both a Cargo.toml and a workflow file.
+            let key = (r.crate_name.as_str(), r.current.as_str(), r.latest.as_str());
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            let crate_links = links.get(&r.crate_name).cloned().unwrap_or_default();
+            let source = crate_links
+                .source_url()
+                .map(|url| format!(" · [source]({url})"))
+                .unwrap_or_default();
+            body.push_str(&format!(
+                "| `{name}` | `{from}` | `{to}` | {kind} | [crates.io](https://crates.io/crates/{name}/{to}) · [docs]({docs}){source} |\n",
+                name = r.crate_name,
+                from = r.current,
+                to = r.latest,
+                kind = r.bump_kind,
+                docs = crate_links.docs_url(&r.crate_name, &r.latest),
+            ));
+        }
+        body.push('\n');
+    }
+
+    body.push_str(&render_markdown(notes));
+    body.push_str(&upstream::render_markdown(upstream));
+    body.push_str(&render_vulnerabilities(vulnerabilities));
+    body.push_str("## Risk assessment\n\n");
+    body.push_str(analysis.trim());
+    body.push_str("\n\n## Changes\n\n");
+
+    for file in files {
+        let diff = unified_diff(&file.path, &file.original, &file.content);
+        body.push_str(&format!(
+            "<details>\n<summary><code>{}</code></summary>\n\n```diff\n{diff}```\n\n</details>\n\n",
+            file.path
+        ));
+    }
+
+    body
+}
+
+/// The `## Vulnerabilities` table of a PR body; empty without findings.
+fn render_vulnerabilities(findings: &[OsvFinding]) -> String {
+    let mut rows: Vec<String> = Vec::new();
+    for finding in findings {
+        let fixed = finding.fixed.iter().map(|v| (v, "fixed"));
+        let remaining = finding.remaining.iter().map(|v| (v, "still present"));
+        for (v, status) in fixed.chain(remaining) {
+            let row = format!(
+                "| `{name}` | [{id}](https://osv.dev/vulnerability/{id}) | {severity} | {status} | {summary} |\n",
+                name = finding.crate_name,
+                id = v.id,
+                severity = v.severity.as_deref().unwrap_or("unrated"),
+                summary = v.summary.replace('|', "\\|"),
+            );
+            if !rows.contains(&row) {
+                rows.push(row);
+            }
+        }
+    }
+    if rows.is_empty() {
+        return String::new();
+    }
+    format!(
+        "## Vulnerabilities (OSV)\n\n| Crate | ID | Severity | After update | Summary |\n|-------|----|----------|--------------|---------|\n{}\n",
+        rows.concat()
+    )
+}
+
+/// Review comments on the changed version lines of `files`.
+///
+/// An added line belongs to a bumped crate when it names the crate, or sits
+/// in the crate's own table (`[dependencies.<crate>]`).  Each comment gives
+/// the old → new versions, the semver class and links to the crate's
+/// changelog in its source repo (from `links`, else in `org`) and to
+/// crates.io.
+pub fn version_comments(
+    org: &str,
+    links: &BTreeMap<String, CrateLinks>,
+    files: &[PrFile],
+    versions: &[VersionReport],
+) -> Vec<LineComment> {
+    let mut comments = Vec::new();
+    for file in files {
+        let lines: Vec<&str> = file.content.lines().collect();
+        for (line, text) in added_lines(&file.original, &file.content) {
+            let table = lines[..line - 1]
+                .iter()
+                .rev()
+                .find(|l| l.trim_start().starts_with('['))
+                .copied()
+                .unwrap_or_default();
+            let mut seen: Vec<&str> = Vec::new();
+            let bumps: Vec<String> = versions
+                .iter()
+                .filter(|r| text.contains(&r.crate_name) || table.contains(&r.crate_name))
+                .filter(|r| {
+                    let first = !seen.contains(&r.crate_name.as_str());
+                    seen.push(&r.crate_name);
+                    first
+                })
+                .map(|r| {
+                    format!(
+                        "`{name}` `{from}` → `{to}` ({kind}) · [changelog](https://github.com/{repo}/blob/HEAD/CHANGELOG.md) · [crates.io](https://crates.io/crates/{name}/{to})",
+                        name = r.crate_name,
+                        from = r.current,
+                        to = r.latest,
+                        kind = r.bump_kind,
+                        repo = source_repo(links.get(&r.crate_name), org, &r.crate_name),
+                    )
+                })
+                .collect();
+            if !bumps.is_empty() {
+                comments.push(LineComment {
+                    path: file.path.clone(),
+                    line,
+                    body: bumps.join("\n"),
+                });
+            }
+        }
+    }
+    comments
+}
+
+/// Posts `comments` on PR `number` of `org/repo` as one `COMMENT` review.
+pub fn post_review(org: &str, repo: &str, number: u64, comments: &[LineComment]) -> Result<()> {
+    let comments: Vec<_> = comments
+        .iter()
+        .map(|c| serde_json::json!({ "path": c.path, "line": c.line, "side": "RIGHT", "body": c.body }))
+        .collect();
+    gh_api_with_body(
+        &[
+            "--method",
+            "POST",
+            &format!("repos/{org}/{repo}/pulls/{number}/reviews"),
+        ],
+        &serde_json::json!({
+            "event": "COMMENT",
+            "body": "Version changes, line by line.",
+            "comments": comments,
+        }),
+    )
+    .with_context(|| format!("review PR #{number} in {org}/{repo}"))?;
+    Ok(())
+}
+
+// ─── PR creation ──────────────────────────────────────────────────────────────
+
+/// Whether `branch` exists in `{org}/{repo}`.
+pub fn branch_exists(org: &str, repo: &str, branch: &str) -> bool {
+    gh_api(&[
+        &format!("repos/{org}/{repo}/git/ref/heads/{branch}"),
+        "--jq",
+        ".object.sha",
+    ])
+    .is_ok()
+}
+
+/// Opens a single pull request against `{org}/{repo}` containing every file
+/// in `files`.
+///
+/// Steps (all via `gh api`):
+/// 1. Resolve the default branch and its head SHA.
+/// 2. Create `branch` from that SHA, or force-reset it there when it is
+///    left from an earlier attempt (a re-run or resumed run).
+/// 3. Commit each file onto `branch` through the contents API, or all of
+///    them as one commit with the `single_commit` message when given.
+/// 4. Open the PR with `title` and `body`, as a draft when `draft` is set,
+///    or update the title and body of the PR already open from `branch`.
+#[allow(clippy::too_many_arguments)]
+pub fn open_grouped_pr(
+    org: &str,
+    repo: &str,
+    branch: &str,
+    title: &str,
+    body: &str,
+    files: &[PrFile],
+    draft: bool,
+    single_commit: Option<&str>,
+) -> Result<PrResult> {
+    let slug = format!("{org}/{repo}");
+
+    let base = gh_api(&[&format!("repos/{slug}"), "--jq", ".default_branch"])
+        .with_context(|| format!("resolve default branch of {slug}"))?;
+    let base_sha = gh_api(&[
+        &format!("repos/{slug}/git/ref/heads/{base}"),
+        "--jq",
+        ".object.sha",
+    ])
+    .with_context(|| format!("resolve head of {slug}@{base}"))?;
+
+    if branch_exists(org, repo, branch) {
+        gh_api(&[
+            "--method",
+            "PATCH",
+            &format!("repos/{slug}/git/refs/heads/{branch}"),
+            "--field",
+            &format!("sha={base_sha}"),
+            "--field",
+            "force=true",
+        ])
+        .with_context(|| format!("reset branch {branch} in {slug}"))?;
+        debug!(repo = %slug, branch, "reset existing update branch");
+    } else {
+        gh_api(&[
+            "--method",
+            "POST",
+            &format!("repos/{slug}/git/refs"),
+            "--field",
+            &format!("ref=refs/heads/{branch}"),
+            "--field",
+            &format!("sha={base_sha}"),
+        ])
+        .with_context(|| format!("create branch {branch} in {slug}"))?;
+    }
+
+    if let Some(message) = single_commit {
+        let contents: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.content.as_bytes()))
+            .collect();
+        commit_tree_via_gh(&slug, &contents, message, Some(branch))
+            .with_context(|| format!("commit {} files to {slug}@{branch}", files.len()))?;
+    }
+    for file in files.iter().filter(|_| single_commit.is_none()) {
+        commit_via_gh_cli(
+            &slug,
+            &file.path,
+            file.content.as_bytes(),
+            &file.commit_message,
+            Some(branch),
+        )
+        .with_context(|| format!("commit {} to {slug}@{branch}", file.path))?;
+    }
+
+    let open = gh_api(&[
+        &format!("repos/{slug}/pulls?head={org}:{branch}&state=open"),
+        "--jq",
+        ".[] | [.number, .html_url] | @tsv",
+    ])
+    .with_context(|| format!("look up open pull requests of {slug}@{branch}"))?;
+    if let Some(line) = open.lines().next() {
+        let (number, url) = parse_pr_line(line)?;
+        gh_api(&[
+            "--method",
+            "PATCH",
+            &format!("repos/{slug}/pulls/{number}"),
+            "--raw-field",
+            &format!("title={title}"),
+            "--raw-field",
+            &format!("body={body}"),
+        ])
+        .with_context(|| format!("update pull request #{number} in {slug}"))?;
+        info!(repo = %slug, number, url, "updated existing grouped update PR");
+        return Ok(PrResult {
+            number,
+            url,
+            branch: branch.to_string(),
+        });
+    }
+
+    let created = gh_api(&[
+        "--method",
+        "POST",
+        &format!("repos/{slug}/pulls"),
+        "--raw-field",
+        &format!("title={title}"),
+        "--raw-field",
+        &format!("head={branch}"),
+        "--raw-field",
+        &format!("base={base}"),
+        "--raw-field",
+        &format!("body={body}"),
+        "--field",
+        &format!("draft={draft}"),
+        "--jq",
+        "[.number, .html_url] | @tsv",
+    ])
+    .with_context(|| format!("open pull request in {slug}"))?;
+    let (number, url) = parse_pr_line(&created)?;
+
+    info!(repo = %slug, number, url, draft, "opened grouped update PR");
+    Ok(PrResult {
+        number,
+        url,
+        branch: branch.to_string(),
+    })
+}
+
+/// `(number, url)` from a `number<TAB>html_url` line of the pulls API.
+fn parse_pr_line(line: &str) -> Result<(u64, String)> {
+    let (number, url) = line
+        .split_once('\t')
+        .with_context(|| format!("unexpected pulls API output: {line}"))?;
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("parse PR number from {number:?}"))?;
+    Ok((number, url.trim().to_string()))
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(name: &str, from: &str, to: &str) -> VersionReport {
+        VersionReport::new(name, from, to)
+    }
+
+    #[test]
+    fn test_branch_name() {
+        assert_eq!(branch_name("abc-123"), "evo-update/abc-123");
+    }
+
+    #[test]
+    fn test_parse_pr_line() {
+        assert_eq!(
+            parse_pr_line("42\thttps://github.com/o/r/pull/42\n").unwrap(),
+            (42, "https://github.com/o/r/pull/42".to_string())
+        );
+        assert!(parse_pr_line("").is_err());
+        assert!(parse_pr_line("x\turl").is_err());
+    }
+
+    #[test]
+    fn test_render_pr_body_sections() {
+        let files = vec![PrFile {
+            path: "Cargo.toml".to_string(),
+            original: "evo-common = \"0.2\"\n".to_string(),
+            content: "evo-common = \"0.3\"\n".to_string(),
+            commit_message: "chore(deps): bump".to_string(),
+        }];
+        let body = render_pr_body(
+            &[report("evo-common", "0.2", "0.3")],
+            &[],
+            &[UpstreamChanges {
+                crate_name: "evo-common".to_string(),
+                from: "0.2.0".to_string(),
+                to: "0.3".to_string(),
+                range: "v0.2.0..v0.3".to_string(),
+                commits: 2,
+                summary: "- Frames can be split.".to_string(),
+                summarized: true,
+            }],
+            &[],
+            &files,
+            "Low risk.",
+            &BTreeMap::from([(
+                "evo-common".to_string(),
+                CrateLinks {
+                    repository: Some("https://github.com/o/evo-common".to_string()),
+                    documentation: Some("https://docs.evo.dev/common".to_string()),
+                    ..Default::default()
+                },
+            )]),
+        );
+        assert!(body.contains("## Versions"));
+        assert!(body.contains("## What changed upstream"));
+        assert!(body.contains("- Frames can be split."));
+        assert!(!body.contains("## Vulnerabilities"));
+        assert!(body.contains("| `evo-common` | `0.2` | `0.3` |"));
+        assert!(body.contains("https://crates.io/crates/evo-common/0.3"));
+        assert!(body.contains(
+            "[docs](https://docs.evo.dev/common) · [source](https://github.com/o/evo-common)"
+        ));
+        assert!(body.contains("## Risk assessment\n\nLow risk."));
+        assert!(body.contains("<code>Cargo.toml</code>"));
+        assert!(body.contains("-evo-common = \"0.2\"\n+evo-common = \"0.3\"\n"));
+    }
+
+    #[test]
+    fn test_render_vulnerabilities() {
+        let vuln = |id: &str, severity: Option<&str>| crate::osv::Vulnerability {
+            id: id.to_string(),
+            aliases: Vec::new(),
+            summary: "Overflow | panic".to_string(),
+            severity: severity.map(str::to_string),
+        };
+        let finding = OsvFinding {
+            crate_name: "evo-common".to_string(),
+            from: "0.3.0".to_string(),
+            to: "0.4.0".to_string(),
+            fixed: vec![vuln("RUSTSEC-2024-0001", Some("high"))],
+            remaining: vec![vuln("GHSA-aaaa-bbbb-cccc", None)],
+        };
+        let body = render_pr_body(
+            &[],
+            &[],
+            &[],
+            &[finding.clone(), finding],
+            &[],
+            "ok",
+            &BTreeMap::new(),
+        );
+        assert!(body.contains("## Vulnerabilities (OSV)"));
+        assert!(body.contains(
+            "| `evo-common` | [RUSTSEC-2024-0001](https://osv.dev/vulnerability/RUSTSEC-2024-0001) | high | fixed | Overflow \\| panic |"
+        ));
+        assert!(body.contains("| unrated | still present |"));
+        assert_eq!(body.matches("RUSTSEC-2024-0001]").count(), 1);
+    }
+
+    #[test]
+    fn test_version_comments_on_changed_lines() {
+        let files = vec![PrFile {
+            path: "Cargo.toml".to_string(),
+            original: "[dependencies]\nevo-common = \"0.3\"\nserde = \"1\"\n\n[dependencies.evo-agent-sdk]\nversion = \"0.1\"\n".to_string(),
+            content: "[dependencies]\nevo-common = \"0.4.0\"\nserde = \"1\"\n\n[dependencies.evo-agent-sdk]\nversion = \"0.1.5\"\n".to_string(),
+            commit_message: "bump".to_string(),
+        }];
+        let versions = [
+            report("evo-common", "0.3", "0.4.0"),
+            report("evo-agent-sdk", "0.1", "0.1.5"),
+        ];
+        let links = BTreeMap::from([(
+            "evo-agent-sdk".to_string(),
+            CrateLinks {
+                repository: Some("https://github.com/ai-evo-agents/evo-agents".to_string()),
+                ..Default::default()
+            },
+        )]);
+        let comments = version_comments("ai-evo-agents", &links, &files, &versions);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(
+            (comments[0].path.as_str(), comments[0].line),
+            ("Cargo.toml", 2)
+        );
+        assert!(
+            comments[0]
+                .body
+                .starts_with("`evo-common` `0.3` → `0.4.0` (major)")
+        );
+        assert!(
+            comments[0]
+                .body
+                .contains("https://github.com/ai-evo-agents/evo-common/blob/HEAD/CHANGELOG.md")
+        );
+        assert_eq!(comments[1].line, 6);
+        assert!(comments[1].body.contains("(patch)"));
+        assert!(
+            comments[1]
+                .body
+                .contains("https://github.com/ai-evo-agents/evo-agents/blob/HEAD/CHANGELOG.md")
+        );
+    }
+
+    #[test]
+    fn test_render_pr_body_dedupes_versions() {
+        let r = report("evo-agent-sdk", "0.1", "0.2");
+        let body = render_pr_body(&[r.clone(), r], &[], &[], &[], &[], "ok", &BTreeMap::new());
+        assert_eq!(body.matches("| `evo-agent-sdk` |").count(), 1);
+    }
+
+    #[test]
+    fn test_render_pr_body_without_versions() {
+        let body = render_pr_body(&[], &[], &[], &[], &[], "n/a", &BTreeMap::new());
+        assert!(!body.contains("## Versions"));
+        assert!(!body.contains("## Migration notes"));
+        assert!(body.contains("## Risk assessment"));
+    }
+}
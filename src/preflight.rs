@@ -0,0 +1,730 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::{ConfigSnapshot, RepoCommitStrategy, RepoConfig};
+use crate::git::gh_available;
+use crate::github::DEFAULT_API_URL;
+use crate::net::apply_to_command;
+use crate::tokens::TokenPool;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// How long the king reachability probe may take.
+const KING_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Free space below which a run is not started (`PREFLIGHT_MIN_FREE_MB`).
+const DEFAULT_MIN_FREE_MB: u64 = 200;
+
+/// Oldest gh CLI whose `api` subcommand behaves as the agent expects.
+const MIN_GH_VERSION: (u64, u64) = (2, 0);
+
+/// Oldest git honouring `GIT_CONFIG_COUNT`, which token-authenticated local
+/// pushes rely on.
+const MIN_GIT_VERSION: (u64, u64) = (2, 31);
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// How serious a preflight finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Degrades the run (a fallback kicks in or a side effect is lost).
+    Warning,
+    /// Part of the configuration cannot work as written.
+    Error,
+}
+
+/// One problem found while validating the configuration and environment.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Which check produced it: `config`, `repo`, `file`, `auth`, `king`,
+    /// `gh`, `git`, `token`, `base_dir` or `disk`.
+    pub check: &'static str,
+    /// What it is about — a repo, `repo/file`, a variable or a URL.
+    pub subject: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(
+        severity: Severity,
+        check: &'static str,
+        subject: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            check,
+            subject: subject.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Environment the checks run against.
+#[derive(Debug, Clone)]
+pub struct PreflightEnv {
+    pub org: String,
+    pub base_dir: PathBuf,
+    pub king_addr: String,
+    /// Offline runs commit through local checkouts only, so a missing
+    /// checkout is an error and GitHub / king checks are skipped.
+    pub offline: bool,
+    /// GitHub REST API root, for the token scope probe.
+    pub api_url: String,
+    /// Free space (MiB) required on the `base_dir` filesystem.
+    pub min_free_mb: u64,
+}
+
+impl PreflightEnv {
+    /// Reads the same variables (and defaults) as a pipeline run.
+    pub fn from_env() -> Self {
+        Self {
+            org: std::env::var("GITHUB_ORG").unwrap_or_else(|_| "ai-evo-agents".to_string()),
+            base_dir: std::env::var("KERNEL_AGENTS_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("..")),
+            king_addr: std::env::var("KING_ADDRESS")
+                .unwrap_or_else(|_| "http://localhost:3000".to_string()),
+            offline: std::env::var("OFFLINE")
+                .is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true")),
+            api_url: std::env::var("GITHUB_API_URL")
+                .unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            min_free_mb: std::env::var("PREFLIGHT_MIN_FREE_MB")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MIN_FREE_MB),
+        }
+    }
+}
+
+/// What Phase 0 found out about the host, reported as `environment` in the
+/// run summary.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Environment {
+    /// `gh --version`, when the binary runs.
+    pub gh_version: Option<String>,
+    /// `git --version`, when the binary runs.
+    pub git_version: Option<String>,
+    /// OAuth scopes of the current GitHub token; `None` when there is no
+    /// token, the run is offline, or the token type does not report scopes
+    /// (fine-grained and app tokens).
+    pub token_scopes: Option<Vec<String>>,
+    pub base_dir_exists: bool,
+    /// Space available on the `base_dir` filesystem.
+    pub free_disk_mb: Option<u64>,
+    /// Problems found, errors first.
+    pub findings: Vec<Finding>,
+}
+
+impl Environment {
+    /// Whether a critical check failed and the run must not start.
+    pub fn is_fatal(&self) -> bool {
+        self.findings.iter().any(|f| f.severity == Severity::Error)
+    }
+
+    /// One line naming every failed critical check.
+    pub fn diagnosis(&self) -> String {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .map(|f| format!("{} ({}): {}", f.check, f.subject, f.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+// ─── Checks ───────────────────────────────────────────────────────────────────
+
+/// Validates the configuration and environment, returning every finding
+/// ordered errors first.
+pub async fn run(
+    snapshot: &ConfigSnapshot,
+    env: &PreflightEnv,
+    http: &reqwest::Client,
+) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    if let Some(ref error) = snapshot.error {
+        findings.push(Finding::new(
+            Severity::Error,
+            "config",
+            snapshot.version.clone(),
+            format!("config file rejected, previous configuration in effect: {error}"),
+        ));
+    }
+    findings.extend(check_environment(env, http).await.findings);
+    for spec in &snapshot.config.repos {
+        findings.extend(check_repo(spec, env));
+    }
+    if !env.offline {
+        findings.extend(check_auth());
+        findings.extend(check_king(http, &env.king_addr).await);
+    }
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    findings
+}
+
+/// Logs every finding and returns how many are errors.
+pub fn log_findings(findings: &[Finding]) -> usize {
+    for f in findings {
+        warn!(severity = ?f.severity, check = f.check, subject = %f.subject, "preflight: {}", f.message);
+    }
+    findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count()
+}
+
+/// Phase 0: probes the tools, token, base directory and disk space a run
+/// depends on.
+///
+/// Missing git, a missing `base_dir`, too little disk space and a token
+/// GitHub rejects are errors; outdated tools and a token without the `repo`
+/// scope are warnings.
+pub async fn check_environment(env: &PreflightEnv, http: &reqwest::Client) -> Environment {
+    let token = TokenPool::global().current().map(|(_, token)| token);
+    let probe = match (&token, env.offline) {
+        (Some(token), false) => Some(token_scopes(http, &env.api_url, token).await),
+        _ => None,
+    };
+    let mut environment = Environment {
+        gh_version: tool_version("gh"),
+        git_version: tool_version("git"),
+        token_scopes: None,
+        base_dir_exists: env.base_dir.is_dir(),
+        free_disk_mb: free_disk_mb(&env.base_dir),
+        findings: Vec::new(),
+    };
+    evaluate(&mut environment, env, probe);
+    environment
+}
+
+/// Fills in `findings` from the gathered facts and the token `probe`
+/// (`Err` holding the HTTP status when GitHub rejected the token).
+fn evaluate(
+    environment: &mut Environment,
+    env: &PreflightEnv,
+    probe: Option<Result<Option<Vec<String>>, u16>>,
+) {
+    let mut findings: Vec<Finding> = Vec::new();
+    match environment.git_version.as_deref() {
+        None => findings.push(Finding::new(
+            Severity::Error,
+            "git",
+            "git",
+            "git is not installed — nothing can be scanned or committed",
+        )),
+        Some(version) if !version_at_least(version, MIN_GIT_VERSION) => {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "git",
+                version,
+                format!(
+                    "git older than {}.{} ignores GIT_CONFIG_COUNT — local pushes fall back to cached credentials",
+                    MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+                ),
+            ))
+        }
+        Some(_) => {}
+    }
+    if let Some(version) = environment.gh_version.as_deref()
+        && !version_at_least(version, MIN_GH_VERSION)
+    {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "gh",
+            version,
+            format!(
+                "gh older than {}.{} — gh CLI commits may fail and fall back",
+                MIN_GH_VERSION.0, MIN_GH_VERSION.1
+            ),
+        ));
+    }
+    match probe {
+        Some(Err(status)) => findings.push(Finding::new(
+            Severity::Error,
+            "token",
+            "GITHUB_TOKEN",
+            format!("GitHub rejected the token (HTTP {status})"),
+        )),
+        Some(Ok(Some(scopes))) => {
+            if !scopes.iter().any(|s| s == "repo" || s == "public_repo") {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    "token",
+                    "GITHUB_TOKEN",
+                    format!(
+                        "token scopes [{}] lack `repo` — API commits and PRs will be refused",
+                        scopes.join(", ")
+                    ),
+                ));
+            }
+            environment.token_scopes = Some(scopes);
+        }
+        Some(Ok(None)) | None => {}
+    }
+    if !environment.base_dir_exists {
+        findings.push(Finding::new(
+            Severity::Error,
+            "base_dir",
+            env.base_dir.display().to_string(),
+            "KERNEL_AGENTS_DIR does not exist — no repo checkout can be found",
+        ));
+    }
+    if let Some(free) = environment.free_disk_mb
+        && free < env.min_free_mb
+    {
+        findings.push(Finding::new(
+            Severity::Error,
+            "disk",
+            env.base_dir.display().to_string(),
+            format!(
+                "only {free} MiB free, {} MiB required (PREFLIGHT_MIN_FREE_MB)",
+                env.min_free_mb
+            ),
+        ));
+    }
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    environment.findings = findings;
+}
+
+/// The checkout must exist (or, online, be cloneable) and contain every
+/// listed Cargo.toml and workflow file.
+fn check_repo(spec: &RepoConfig, env: &PreflightEnv) -> Vec<Finding> {
+    let repo_base = env.base_dir.join(&spec.local);
+    if !repo_base.is_dir() {
+        let path = repo_base.display();
+        let finding = if env.offline {
+            Finding::new(
+                Severity::Error,
+                "repo",
+                &spec.repo,
+                format!("no checkout at {path} and offline runs cannot reach GitHub"),
+            )
+        } else if remote_reachable(&format!("https://github.com/{}/{}.git", env.org, spec.repo)) {
+            Finding::new(
+                Severity::Warning,
+                "repo",
+                &spec.repo,
+                format!("no checkout at {path} — it will be cloned at the start of the next run"),
+            )
+        } else {
+            Finding::new(
+                Severity::Error,
+                "repo",
+                &spec.repo,
+                format!(
+                    "no checkout at {path} and {}/{} is not cloneable",
+                    env.org, spec.repo
+                ),
+            )
+        };
+        return vec![finding];
+    }
+
+    let mut findings: Vec<Finding> = spec
+        .cargo_files
+        .iter()
+        .chain(&spec.workflow_files)
+        .chain(&spec.python_files)
+        .chain(&spec.npm_files)
+        .chain(&spec.go_files)
+        .filter(|file| !repo_base.join(file).is_file())
+        .map(|file| {
+            Finding::new(
+                Severity::Error,
+                "file",
+                format!("{}/{file}", spec.repo),
+                "listed in the configuration but missing from the checkout",
+            )
+        })
+        .collect();
+    // A URL is pushed to as-is; a name must be a remote of the checkout.
+    if let Some(remote) = &spec.push_remote
+        && !remote.contains([':', '/'])
+        && !has_remote(&repo_base, remote)
+    {
+        findings.push(Finding::new(
+            Severity::Error,
+            "repo",
+            &spec.repo,
+            format!("push_remote {remote:?} is not a remote of the checkout"),
+        ));
+    }
+    if let Some(var) = &spec.deploy_key_env {
+        if std::env::var(var).is_ok_and(|k| !k.trim().is_empty()) {
+            if spec.commit_strategy != Some(RepoCommitStrategy::LocalGit) {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    "auth",
+                    &spec.repo,
+                    "the deploy key only authenticates local git pushes — set \
+                     commit_strategy = \"local_git\" to keep the repo off the GitHub token",
+                ));
+            }
+        } else {
+            findings.push(Finding::new(
+                Severity::Error,
+                "auth",
+                var,
+                format!("deploy key for {} is not set", spec.repo),
+            ));
+        }
+    }
+    if spec.sparse_checkout && spec.verify.is_some() {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "repo",
+            &spec.repo,
+            "sparse checkouts hold only manifests and workflows — `verify` commands needing the source will fail",
+        ));
+    }
+    findings
+}
+
+/// Remote commits need gh CLI or a GitHub token; without either only local
+/// git pushes remain and PR mode cannot work.
+fn check_auth() -> Option<Finding> {
+    if !TokenPool::global().is_empty() {
+        return None;
+    }
+    let message = if !gh_available() {
+        "gh CLI not installed and GITHUB_TOKEN / GITHUB_TOKENS unset — only local git commits are possible"
+    } else if !gh_authenticated() {
+        "gh CLI is not authenticated and GITHUB_TOKEN / GITHUB_TOKENS unset — only local git commits are possible"
+    } else {
+        return None;
+    };
+    Some(Finding::new(
+        Severity::Warning,
+        "auth",
+        "GITHUB_TOKEN",
+        message,
+    ))
+}
+
+/// Any HTTP response from the king counts as reachable; config-sync is the
+/// only thing that depends on it.
+async fn check_king(http: &reqwest::Client, king_addr: &str) -> Option<Finding> {
+    match http.get(king_addr).timeout(KING_PROBE_TIMEOUT).send().await {
+        Ok(_) => None,
+        Err(e) => Some(Finding::new(
+            Severity::Warning,
+            "king",
+            king_addr,
+            format!("king unreachable, config-sync will fail: {e}"),
+        )),
+    }
+}
+
+// ─── Helpers ──────────────────────────────────────────────────────────────────
+
+/// `{tool} --version` reduced to its version number, e.g. `2.43.0`.
+fn tool_version(tool: &str) -> Option<String> {
+    let output = apply_to_command(&mut Command::new(tool))
+        .arg("--version")
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// First dotted number in `text` (`gh version 2.40.1 (2023-12-13)`,
+/// `git version 2.43.0`).
+fn parse_version(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .find(|word| {
+            word.contains('.')
+                && word
+                    .split('.')
+                    .next()
+                    .is_some_and(|major| major.parse::<u64>().is_ok())
+        })
+        .map(str::to_string)
+}
+
+/// Whether dotted `version` is at least `(major, minor)`.
+fn version_at_least(version: &str, (major, minor): (u64, u64)) -> bool {
+    let mut parts = version.split('.').map(|p| p.parse::<u64>().unwrap_or(0));
+    let found = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+    found >= (major, minor)
+}
+
+/// Space available to the agent on the filesystem holding `dir`, in MiB.
+fn free_disk_mb(dir: &Path) -> Option<u64> {
+    let output = apply_to_command(&mut Command::new("df"))
+        .args(["-Pk"])
+        .arg(dir)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Available MiB from POSIX `df -Pk` output (fourth column, KiB).
+fn parse_df(output: &str) -> Option<u64> {
+    let kib: u64 = output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(kib / 1024)
+}
+
+/// Scopes GitHub reports for `token` (`X-OAuth-Scopes`), `Ok(None)` when
+/// the probe is inconclusive, `Err(status)` when the token is rejected.
+async fn token_scopes(
+    http: &reqwest::Client,
+    api_url: &str,
+    token: &str,
+) -> Result<Option<Vec<String>>, u16> {
+    // `rate_limit` does not count against the quota.
+    let Ok(resp) = http
+        .get(format!("{api_url}/rate_limit"))
+        .bearer_auth(token)
+        .timeout(KING_PROBE_TIMEOUT)
+        .send()
+        .await
+    else {
+        return Ok(None);
+    };
+    if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(resp.status().as_u16());
+    }
+    Ok(resp
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        }))
+}
+
+/// `git ls-remote` succeeds against `url` (non-interactively).
+fn remote_reachable(url: &str) -> bool {
+    apply_to_command(&mut Command::new("git"))
+        .args(["ls-remote", "--exit-code", url, "HEAD"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// `git remote get-url` knows `remote` in the checkout at `base`.
+fn has_remote(base: &Path, remote: &str) -> bool {
+    apply_to_command(&mut Command::new("git"))
+        .args(["remote", "get-url", remote])
+        .current_dir(base)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// `gh auth status` reports a logged-in account.
+fn gh_authenticated() -> bool {
+    apply_to_command(&mut Command::new("gh"))
+        .args(["auth", "status"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn spec() -> RepoConfig {
+        RepoConfig {
+            repo: "evo-king".into(),
+            local: "evo-king".into(),
+            cargo_files: vec!["Cargo.toml".into()],
+            workflow_files: vec![".github/workflows/release.yml".into()],
+            ..RepoConfig::default()
+        }
+    }
+
+    fn env(base_dir: &Path) -> PreflightEnv {
+        PreflightEnv {
+            org: "ai-evo-agents".into(),
+            base_dir: base_dir.to_path_buf(),
+            king_addr: "http://localhost:3000".into(),
+            offline: true,
+            api_url: "https://api.github.com".into(),
+            min_free_mb: 200,
+        }
+    }
+
+    fn facts() -> Environment {
+        Environment {
+            gh_version: Some("2.40.1".into()),
+            git_version: Some("2.43.0".into()),
+            token_scopes: None,
+            base_dir_exists: true,
+            free_disk_mb: Some(10_000),
+            findings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_tool_output() {
+        assert_eq!(
+            parse_version("gh version 2.40.1 (2023-12-13)\nhttps://github.com/cli/cli\n"),
+            Some("2.40.1".to_string())
+        );
+        assert_eq!(
+            parse_version("git version 2.39.3 (Apple Git-146)"),
+            Some("2.39.3".to_string())
+        );
+        assert!(version_at_least("2.31.0", (2, 31)));
+        assert!(!version_at_least("2.30.9", (2, 31)));
+        assert!(version_at_least("10.0", (2, 31)));
+        assert_eq!(
+            parse_df(
+                "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                 /dev/sda1 100000000 50000000 2048000 50% /\n"
+            ),
+            Some(2000)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_healthy_environment() {
+        let dir = TempDir::new().unwrap();
+        let mut environment = facts();
+        evaluate(
+            &mut environment,
+            &env(dir.path()),
+            Some(Ok(Some(vec!["repo".into(), "workflow".into()]))),
+        );
+        assert!(environment.findings.is_empty());
+        assert!(!environment.is_fatal());
+        assert_eq!(
+            environment.token_scopes,
+            Some(vec!["repo".to_string(), "workflow".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_evaluate_critical_failures() {
+        let dir = TempDir::new().unwrap();
+        let mut environment = Environment {
+            git_version: None,
+            base_dir_exists: false,
+            free_disk_mb: Some(50),
+            ..facts()
+        };
+        evaluate(&mut environment, &env(dir.path()), Some(Err(401)));
+        assert!(environment.is_fatal());
+        let checks: Vec<&str> = environment.findings.iter().map(|f| f.check).collect();
+        assert_eq!(checks, vec!["git", "token", "base_dir", "disk"]);
+        assert!(environment.diagnosis().contains("HTTP 401"));
+
+        let mut outdated = Environment {
+            gh_version: Some("1.14.0".into()),
+            git_version: Some("2.25.1".into()),
+            ..facts()
+        };
+        evaluate(
+            &mut outdated,
+            &env(dir.path()),
+            Some(Ok(Some(vec!["read:org".into()]))),
+        );
+        assert!(!outdated.is_fatal());
+        assert_eq!(outdated.findings.len(), 3);
+    }
+
+    #[test]
+    fn test_check_repo_reports_missing_files() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("evo-king")).unwrap();
+        std::fs::write(dir.path().join("evo-king/Cargo.toml"), "").unwrap();
+
+        let findings = check_repo(&spec(), &env(dir.path()));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].check, "file");
+        assert_eq!(
+            findings[0].subject,
+            "evo-king/.github/workflows/release.yml"
+        );
+    }
+
+    #[test]
+    fn test_check_repo_unknown_push_remote() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("evo-king");
+        std::fs::create_dir(&repo).unwrap();
+        std::fs::write(repo.join("Cargo.toml"), "").unwrap();
+        let git = |args: &[&str]| {
+            assert!(
+                Command::new("git")
+                    .args(args)
+                    .current_dir(&repo)
+                    .status()
+                    .unwrap()
+                    .success()
+            );
+        };
+        git(&["init", "--quiet"]);
+        git(&["remote", "add", "fork", "https://example.com/fork.git"]);
+
+        let mut spec = RepoConfig {
+            workflow_files: Vec::new(),
+            push_remote: Some("fork".into()),
+            ..spec()
+        };
+        assert!(check_repo(&spec, &env(dir.path())).is_empty());
+        spec.push_remote = Some("upstream".into());
+        let findings = check_repo(&spec, &env(dir.path()));
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].message.contains("\"upstream\""));
+        spec.push_remote = Some("git@github.com:me/evo-king.git".into());
+        assert!(check_repo(&spec, &env(dir.path())).is_empty());
+    }
+
+    #[test]
+    fn test_check_repo_unset_deploy_key() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("evo-king")).unwrap();
+        std::fs::write(dir.path().join("evo-king/Cargo.toml"), "").unwrap();
+        let spec = RepoConfig {
+            workflow_files: Vec::new(),
+            deploy_key_env: Some("EVO_TEST_DEPLOY_KEY_THAT_IS_NOT_SET".into()),
+            ..spec()
+        };
+        let findings = check_repo(&spec, &env(dir.path()));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].subject, "EVO_TEST_DEPLOY_KEY_THAT_IS_NOT_SET");
+    }
+
+    #[test]
+    fn test_check_repo_missing_checkout_offline_is_error() {
+        let dir = TempDir::new().unwrap();
+        let findings = check_repo(&spec(), &env(dir.path()));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].check, "repo");
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::backend::KingClient;
+use crate::checksum::sha256_hex;
+use crate::git::gh_api_with_body;
+use crate::state::state_dir;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// in-toto statement type of the provenance document.
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+
+/// DSSE payload type of an in-toto statement.
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Predicate type identifying this agent's update provenance.
+const PREDICATE_TYPE: &str =
+    "https://github.com/ai-evo-agents/evo-kernel-agent-update/provenance/v1";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A file changed by the update wave, with where the change landed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub repo: String,
+    pub file: String,
+    pub pre_sha256: String,
+    pub post_sha256: String,
+    /// Commit SHA for direct commits.
+    pub commit: Option<String>,
+    /// PR URL for changes delivered as a pull request.
+    pub pr_url: Option<String>,
+}
+
+/// Where a signed statement is published besides the local state dir.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PublishTarget {
+    /// POST to the king's `/admin/provenance` endpoint.
+    King,
+    /// A secret gist created with `gh`.
+    Gist,
+}
+
+impl PublishTarget {
+    /// Parses `PROVENANCE_PUBLISH` (comma-separated `king`, `gist`);
+    /// unknown entries are ignored.
+    pub fn from_env() -> Vec<Self> {
+        Self::from_env_var("PROVENANCE_PUBLISH")
+    }
+
+    /// Parses the target list in the environment variable `name`.
+    pub fn from_env_var(name: &str) -> Vec<Self> {
+        std::env::var(name)
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|t| match t.trim() {
+                "king" => Some(PublishTarget::King),
+                "gist" => Some(PublishTarget::Gist),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// ─── Statement ────────────────────────────────────────────────────────────────
+
+/// Builds the in-toto provenance statement for one update wave.
+///
+/// Subjects are the changed files at their new content; the predicate
+/// records the run, the agent version, the crate versions the run resolved
+/// and a digest over every file diff (`diffs_sha256`).
+pub fn statement(
+    run_id: &str,
+    org: &str,
+    input_versions: &BTreeMap<String, String>,
+    files: &[ChangedFile],
+) -> Value {
+    let subjects: Vec<Value> = files
+        .iter()
+        .map(|f| {
+            json!({
+                "name": format!("{org}/{}/{}", f.repo, f.file),
+                "digest": { "sha256": f.post_sha256 },
+            })
+        })
+        .collect();
+    json!({
+        "_type": STATEMENT_TYPE,
+        "subject": subjects,
+        "predicateType": PREDICATE_TYPE,
+        "predicate": {
+            "builder": {
+                "id": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "run_id": run_id,
+            "input_versions": input_versions,
+            "diffs_sha256": diffs_digest(files),
+            "changes": files,
+        },
+    })
+}
+
+/// sha256 over `repo/file pre post` lines, sorted, so the digest does not
+/// depend on the order changes were applied in.
+fn diffs_digest(files: &[ChangedFile]) -> String {
+    let mut lines: Vec<String> = files
+        .iter()
+        .map(|f| format!("{}/{} {} {}\n", f.repo, f.file, f.pre_sha256, f.post_sha256))
+        .collect();
+    lines.sort();
+    sha256_hex(lines.concat().as_bytes())
+}
+
+// ─── Signing ──────────────────────────────────────────────────────────────────
+
+/// The ed25519 key provenance statements are signed with.
+pub struct ProvenanceKey {
+    pub id: String,
+    key: SigningKey,
+}
+
+impl ProvenanceKey {
+    /// The key from `PROVENANCE_KEY` (the base64 32-byte ed25519 seed) and
+    /// `PROVENANCE_KEY_ID` (default `default`); `None` when unset.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(seed) = std::env::var("PROVENANCE_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())
+        else {
+            return Ok(None);
+        };
+        let id = std::env::var("PROVENANCE_KEY_ID").unwrap_or_else(|_| "default".to_string());
+        Self::from_seed(id, seed.trim()).map(Some)
+    }
+
+    /// A key from its base64 32-byte seed.
+    pub fn from_seed(id: String, seed: &str) -> Result<Self> {
+        let seed: [u8; 32] = BASE64
+            .decode(seed)
+            .context("PROVENANCE_KEY is not base64")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("PROVENANCE_KEY is not a 32-byte ed25519 seed"))?;
+        Ok(Self {
+            id,
+            key: SigningKey::from_bytes(&seed),
+        })
+    }
+
+    /// The base64 public key consumers verify statements with.
+    pub fn public_key(&self) -> String {
+        BASE64.encode(self.key.verifying_key().as_bytes())
+    }
+}
+
+/// DSSE pre-authentication encoding of `payload`: what is actually signed,
+/// so the signature covers the exact payload bytes and their type.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+/// Wraps `statement` in a DSSE envelope signed with `key`.
+///
+/// The payload is the statement's serialized bytes, base64 encoded, so
+/// verifiers check exactly what was signed instead of re-serializing it.
+/// Without a key the envelope has no signatures.
+pub fn envelope(statement: &Value, key: Option<&ProvenanceKey>) -> Value {
+    let payload = statement.to_string();
+    let signatures: Vec<Value> = key
+        .map(|key| {
+            let sig = key.key.sign(&pae(PAYLOAD_TYPE, payload.as_bytes()));
+            json!({ "keyid": key.id, "sig": BASE64.encode(sig.to_bytes()) })
+        })
+        .into_iter()
+        .collect();
+    json!({
+        "payloadType": PAYLOAD_TYPE,
+        "payload": BASE64.encode(payload),
+        "signatures": signatures,
+    })
+}
+
+/// Checks that `envelope` carries a valid signature by `key_id` under the
+/// base64 `public_key`, and returns its statement.
+pub fn verify(envelope: &Value, key_id: &str, public_key: &str) -> Result<Value> {
+    anyhow::ensure!(
+        envelope["payloadType"] == PAYLOAD_TYPE,
+        "unexpected payload type {}",
+        envelope["payloadType"]
+    );
+    let public_key: [u8; 32] = BASE64
+        .decode(public_key)
+        .context("public key is not base64")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("public key is not 32 bytes"))?;
+    let public_key = VerifyingKey::from_bytes(&public_key).context("invalid ed25519 key")?;
+    let payload = BASE64
+        .decode(envelope["payload"].as_str().unwrap_or_default())
+        .context("payload is not base64")?;
+    let signature = envelope["signatures"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|s| s["keyid"] == key_id)
+        .with_context(|| format!("no signature by key {key_id}"))?;
+    let sig = BASE64
+        .decode(signature["sig"].as_str().unwrap_or_default())
+        .context("signature is not base64")?;
+    let sig = Signature::from_slice(&sig).context("malformed signature")?;
+    public_key
+        .verify(&pae(PAYLOAD_TYPE, &payload), &sig)
+        .context("signature does not match the payload")?;
+    serde_json::from_slice(&payload).context("payload is not a JSON statement")
+}
+
+// ─── Publishing ───────────────────────────────────────────────────────────────
+
+/// Writes the envelope to `state_dir/provenance/{run_id}.json`.
+pub fn store(run_id: &str, envelope: &Value) -> Result<PathBuf> {
+    let dir = state_dir().join("provenance");
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("create provenance dir {}", dir.display()))?;
+    let path = dir.join(format!("{run_id}.json"));
+    let text = serde_json::to_string_pretty(envelope).context("serialize provenance")?;
+    std::fs::write(&path, text).with_context(|| format!("write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Writes `key`'s public key to `state_dir/provenance/{key_id}.pub`, next to
+/// the statements it verifies.
+pub fn store_public_key(key: &ProvenanceKey) -> Result<PathBuf> {
+    let dir = state_dir().join("provenance");
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("create provenance dir {}", dir.display()))?;
+    let path = dir.join(format!("{}.pub", key.id));
+    std::fs::write(&path, format!("{}\n", key.public_key()))
+        .with_context(|| format!("write {}", path.display()))?;
+    Ok(path)
+}
+
+/// POSTs the envelope to the king's `/admin/provenance` endpoint.
+pub async fn publish_to_king(king: &dyn KingClient, envelope: &Value) -> Result<()> {
+    king.post_ok("/admin/provenance", envelope).await.map(drop)
+}
+
+/// Publishes the envelope as a secret gist and returns its URL.
+pub fn publish_gist(run_id: &str, envelope: &Value) -> Result<String> {
+    let body = json!({
+        "description": format!("evo-kernel-agent-update provenance for run {run_id}"),
+        "public": false,
+        "files": {
+            format!("provenance-{run_id}.json"): {
+                "content": serde_json::to_string_pretty(envelope)
+                    .context("serialize provenance")?,
+            },
+        },
+    });
+    gh_api_with_body(&["--method", "POST", "gists", "--jq", ".html_url"], &body)
+        .context("create provenance gist")
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> ChangedFile {
+        ChangedFile {
+            repo: "evo-king".into(),
+            file: name.into(),
+            pre_sha256: "aa".into(),
+            post_sha256: "bb".into(),
+            commit: Some("a1b2c3d".into()),
+            pr_url: None,
+        }
+    }
+
+    #[test]
+    fn test_statement_subjects_and_digest() {
+        let versions = BTreeMap::from([("evo-common".to_string(), "0.4.0".to_string())]);
+        let a = statement(
+            "run-1",
+            "org",
+            &versions,
+            &[file("Cargo.toml"), file("ci.yml")],
+        );
+        let b = statement(
+            "run-1",
+            "org",
+            &versions,
+            &[file("ci.yml"), file("Cargo.toml")],
+        );
+        assert_eq!(a["subject"][0]["name"], "org/evo-king/Cargo.toml");
+        assert_eq!(a["subject"][0]["digest"]["sha256"], "bb");
+        assert_eq!(
+            a["predicate"]["diffs_sha256"],
+            b["predicate"]["diffs_sha256"]
+        );
+        assert_eq!(a["predicate"]["input_versions"]["evo-common"], "0.4.0");
+    }
+
+    #[test]
+    fn test_envelope_signs_and_verifies() {
+        let seed = BASE64.encode([7u8; 32]);
+        let key = ProvenanceKey::from_seed("ci".into(), &seed).unwrap();
+        let public_key = key.public_key();
+        let statement = statement("run-1", "org", &BTreeMap::new(), &[file("Cargo.toml")]);
+
+        let signed = envelope(&statement, Some(&key));
+        assert_eq!(signed["payloadType"], PAYLOAD_TYPE);
+        assert_eq!(signed["signatures"][0]["keyid"], "ci");
+        assert_eq!(verify(&signed, "ci", &public_key).unwrap(), statement);
+        assert!(verify(&signed, "other", &public_key).is_err());
+
+        let mut tampered = signed.clone();
+        let forged = json!({ "_type": STATEMENT_TYPE, "subject": [] });
+        tampered["payload"] = json!(BASE64.encode(forged.to_string()));
+        assert!(verify(&tampered, "ci", &public_key).is_err());
+
+        let other = ProvenanceKey::from_seed("ci".into(), &BASE64.encode([8u8; 32])).unwrap();
+        assert!(verify(&signed, "ci", &other.public_key()).is_err());
+
+        let unsigned = envelope(&statement, None);
+        assert_eq!(unsigned["signatures"], json!([]));
+        assert!(verify(&unsigned, "ci", &public_key).is_err());
+        assert!(ProvenanceKey::from_seed("ci".into(), "c2hvcnQ=").is_err());
+    }
+}
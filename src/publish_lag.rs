@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::backend::KingClient;
+use crate::git::gh_api;
+use crate::state::{state_dir, unix_from_rfc3339};
+use crate::versions::needs_update;
+use crate::webhook::parse_tag;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// Default `PUBLISH_LAG_THRESHOLD_SECS`.
+pub const DEFAULT_THRESHOLD_SECS: u64 = 3_600;
+
+/// How long a tagged version may be missing from the registry before its
+/// publish counts as stuck (`PUBLISH_LAG_THRESHOLD_SECS`); `None` when `0`.
+pub fn threshold_from_env() -> Option<u64> {
+    let secs = std::env::var("PUBLISH_LAG_THRESHOLD_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_THRESHOLD_SECS);
+    (secs > 0).then_some(secs)
+}
+
+// ─── Detection ────────────────────────────────────────────────────────────────
+
+/// A tagged release the registry has not served for longer than the
+/// threshold.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StuckPublish {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub repo: String,
+    pub tag: String,
+    pub tagged_version: String,
+    pub registry_version: String,
+    /// Seconds since the tagged commit.
+    pub lag_secs: u64,
+    /// The crate's registry `repository` (or `homepage`) link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// Whether the king was alerted (once per tagged version).
+    pub notified: bool,
+}
+
+/// The highest stable version of `crate_name` among `tags` of `repo`, with
+/// the tag naming it.
+pub fn latest_tagged(tags: &[String], repo: &str, crate_name: &str) -> Option<(String, String)> {
+    tags.iter()
+        .filter_map(|tag| {
+            let (_, version) = parse_tag(tag, repo, &[crate_name])?;
+            Some((tag.clone(), version))
+        })
+        .reduce(|best, next| {
+            if needs_update(&best.1, &next.1) {
+                next
+            } else {
+                best
+            }
+        })
+}
+
+/// Checks `org/repo`'s tags for a release of `crate_name` newer than
+/// `registry_version`; `Some` when it has been tagged for longer than
+/// `threshold` seconds at `now`.
+pub fn check(
+    org: &str,
+    repo: &str,
+    crate_name: &str,
+    registry_version: &str,
+    threshold: u64,
+    now: u64,
+) -> Result<Option<StuckPublish>> {
+    let tags: Vec<String> = gh_api(&[
+        &format!("repos/{org}/{repo}/tags?per_page=100"),
+        "--jq",
+        ".[].name",
+    ])
+    .with_context(|| format!("list tags of {org}/{repo}"))?
+    .lines()
+    .map(str::to_string)
+    .collect();
+    let Some((tag, tagged_version)) = latest_tagged(&tags, repo, crate_name) else {
+        return Ok(None);
+    };
+    if !needs_update(registry_version, &tagged_version) {
+        return Ok(None);
+    }
+    let date = gh_api(&[
+        &format!("repos/{org}/{repo}/commits/{tag}"),
+        "--jq",
+        ".commit.committer.date",
+    ])
+    .with_context(|| format!("look up the commit of {tag} in {org}/{repo}"))?;
+    let tagged_at = unix_from_rfc3339(&date)
+        .with_context(|| format!("unexpected commit date {date:?} of {tag}"))?;
+    let lag_secs = now.saturating_sub(tagged_at);
+    Ok((lag_secs > threshold).then(|| StuckPublish {
+        crate_name: crate_name.to_string(),
+        repo: repo.to_string(),
+        tag,
+        tagged_version,
+        registry_version: registry_version.to_string(),
+        lag_secs,
+        source_url: None,
+        notified: false,
+    }))
+}
+
+// ─── Alerts ───────────────────────────────────────────────────────────────────
+
+/// Tagged versions already reported as stuck, so each is alerted once; kept
+/// as one JSON file under [`state_dir`].
+#[derive(Debug, Clone)]
+pub struct AlertedVersions {
+    path: PathBuf,
+    versions: BTreeMap<String, String>,
+}
+
+impl AlertedVersions {
+    /// File name of the state inside the state dir.
+    pub const FILE_NAME: &'static str = "publish-lag.json";
+
+    /// Default location inside [`state_dir`].
+    pub fn default_path() -> PathBuf {
+        state_dir().join(Self::FILE_NAME)
+    }
+
+    /// Loads the state at `path`; a missing or unreadable file yields an
+    /// empty state (at worst an alert is repeated).
+    pub fn load(path: &Path) -> Self {
+        let versions = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            versions,
+        }
+    }
+
+    /// Records `stuck` and returns whether it is new, i.e. needs an alert.
+    pub fn record(&mut self, stuck: &StuckPublish) -> bool {
+        self.versions
+            .insert(stuck.crate_name.clone(), stuck.tagged_version.clone())
+            .as_ref()
+            != Some(&stuck.tagged_version)
+    }
+
+    /// Forgets `crate_name` once its publish is no longer stuck.
+    pub fn clear(&mut self, crate_name: &str) {
+        self.versions.remove(crate_name);
+    }
+
+    /// Writes the state back to disk, creating the state dir if needed.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text =
+            serde_json::to_string_pretty(&self.versions).context("serialize publish-lag state")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write publish-lag state {}", self.path.display()))
+    }
+}
+
+/// POSTs a `publish_stuck` alert to the king's `/admin/alerts` endpoint.
+pub async fn alert_king(king: &dyn KingClient, run_id: &str, stuck: &[StuckPublish]) -> Result<()> {
+    king.post_ok(
+        "/admin/alerts",
+        &json!({
+            "kind": "publish_stuck",
+            "source": "update-agent",
+            "run_id": run_id,
+            "message": "a tagged release is missing from the registry — the upstream release workflow may be broken",
+            "details": stuck,
+        }),
+    )
+    .await
+    .map(drop)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_latest_tagged() {
+        let tags: Vec<String> = [
+            "evo-agent-sdk-v0.3.1",
+            "evo-agent-sdk-v0.3.10",
+            "evo-agent-sdk-v0.4.0-rc.1",
+            "evo-common-v9.0.0",
+            "v1.0.0",
+        ]
+        .map(str::to_string)
+        .to_vec();
+        assert_eq!(
+            latest_tagged(&tags, "evo-agents", "evo-agent-sdk"),
+            Some(("evo-agent-sdk-v0.3.10".to_string(), "0.3.10".to_string()))
+        );
+        assert_eq!(
+            latest_tagged(&tags[4..], "evo-common", "evo-common"),
+            Some(("v1.0.0".to_string(), "1.0.0".to_string()))
+        );
+        assert_eq!(latest_tagged(&tags, "evo-agents", "evo-runner"), None);
+    }
+
+    #[test]
+    fn test_alerts_once_per_version() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("publish-lag.json");
+        let stuck = StuckPublish {
+            crate_name: "evo-agent-sdk".to_string(),
+            repo: "evo-agents".to_string(),
+            tag: "evo-agent-sdk-v0.3.2".to_string(),
+            tagged_version: "0.3.2".to_string(),
+            registry_version: "0.3.1".to_string(),
+            lag_secs: 7_200,
+            source_url: None,
+            notified: false,
+        };
+        let mut alerted = AlertedVersions::load(&path);
+        assert!(alerted.record(&stuck));
+        alerted.save().unwrap();
+        let mut alerted = AlertedVersions::load(&path);
+        assert!(!alerted.record(&stuck));
+        alerted.clear("evo-agent-sdk");
+        assert!(alerted.record(&stuck));
+    }
+}
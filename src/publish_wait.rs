@@ -0,0 +1,173 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::backend::RegistryClient;
+use crate::fixtures::RegistryHttp;
+use crate::registry::{IndexEntry, Registry, fetch_index};
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// How long a run waits for a version to become resolvable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PublishWait {
+    /// Give up after this long.
+    pub timeout: Duration,
+    /// Delay between two polls.
+    pub interval: Duration,
+}
+
+impl PublishWait {
+    /// Default `PUBLISH_WAIT_TIMEOUT_SECS`.
+    pub const DEFAULT_TIMEOUT_SECS: u64 = 900;
+    /// Default `PUBLISH_WAIT_INTERVAL_SECS`.
+    pub const DEFAULT_INTERVAL_SECS: u64 = 15;
+
+    /// Settings from `PUBLISH_WAIT_TIMEOUT_SECS` and
+    /// `PUBLISH_WAIT_INTERVAL_SECS`; `None` when the timeout is `0`.
+    pub fn from_env() -> Option<Self> {
+        let secs = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        let timeout = secs("PUBLISH_WAIT_TIMEOUT_SECS", Self::DEFAULT_TIMEOUT_SECS);
+        (timeout > 0).then(|| Self {
+            timeout: Duration::from_secs(timeout),
+            interval: Duration::from_secs(
+                secs("PUBLISH_WAIT_INTERVAL_SECS", Self::DEFAULT_INTERVAL_SECS).max(1),
+            ),
+        })
+    }
+}
+
+// ─── Index checks ─────────────────────────────────────────────────────────────
+
+/// Whether a crate's index entries list `version`, not yanked.
+pub fn index_serves(index: &[IndexEntry], version: &str) -> bool {
+    index.iter().any(|e| e.vers == version && !e.yanked)
+}
+
+/// Versions a run was started for (`metadata.released`, as sent by the
+/// release watcher, the webhook receiver and release rollouts).
+pub fn released_versions(metadata: &Value) -> BTreeMap<String, String> {
+    metadata["released"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| {
+            Some((
+                r["crate"].as_str()?.to_string(),
+                r["version"].as_str()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+// ─── Waiting ──────────────────────────────────────────────────────────────────
+
+/// How long a version took to become resolvable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Waited {
+    /// Polls made, including the one that found the version.
+    pub polls: u32,
+    pub elapsed: Duration,
+}
+
+/// Polls `registry` until cargo can resolve `crate_name@version`: the
+/// sparse index lists it and its `.crate` downloads.  The registry API can
+/// report a version before its index file and download have propagated
+/// through the CDN, so both are checked, with `Cache-Control: no-cache`.
+///
+/// Polling failures count as "not yet"; after `wait.timeout` the last
+/// reason is returned as the error.
+pub async fn wait_for_version(
+    client: &dyn RegistryClient,
+    registry: &Registry,
+    crate_name: &str,
+    version: &str,
+    wait: &PublishWait,
+) -> Result<Waited> {
+    let started = Instant::now();
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        let reason = match client.resolvable(registry, crate_name, version).await {
+            Ok(()) => {
+                let elapsed = started.elapsed();
+                if polls > 1 {
+                    info!(
+                        crate = crate_name,
+                        version,
+                        polls,
+                        elapsed_secs = elapsed.as_secs(),
+                        "version is now resolvable"
+                    );
+                }
+                return Ok(Waited { polls, elapsed });
+            }
+            Err(reason) => reason,
+        };
+        if started.elapsed() + wait.interval > wait.timeout {
+            anyhow::bail!(
+                "{crate_name}@{version} not resolvable after {}s ({polls} polls): {reason:#}",
+                started.elapsed().as_secs()
+            );
+        }
+        debug!(crate = crate_name, version, polls, reason = %reason, "waiting for the version to propagate");
+        tokio::time::sleep(wait.interval).await;
+    }
+}
+
+/// `Ok` once the index lists `crate_name@version` and its download answers.
+pub async fn resolvable(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+    version: &str,
+) -> Result<()> {
+    let index = fetch_index(client, registry, crate_name, true).await?;
+    anyhow::ensure!(index_serves(&index, version), "not in the sparse index yet");
+
+    let download = registry.download_url(crate_name, version);
+    client.head(&download).await?.ensure_success(&download)?;
+    Ok(())
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::parse_index;
+    use serde_json::json;
+
+    #[test]
+    fn test_index_serves() {
+        let index = [
+            r#"{"name":"evo-agent-sdk","vers":"0.3.0","cksum":"a"}"#,
+            r#"{"name":"evo-agent-sdk","vers":"0.3.1","cksum":"b","yanked":true}"#,
+        ]
+        .join("\n");
+        let index = parse_index(&index);
+        assert!(index_serves(&index, "0.3.0"));
+        assert!(!index_serves(&index, "0.3.1"));
+        assert!(!index_serves(&index, "0.3.2"));
+    }
+
+    #[test]
+    fn test_released_versions() {
+        let metadata = json!({ "released": [
+            { "crate": "evo-agent-sdk", "version": "0.3.1" },
+            { "crate": "evo-common" },
+        ] });
+        assert_eq!(
+            released_versions(&metadata),
+            BTreeMap::from([("evo-agent-sdk".to_string(), "0.3.1".to_string())])
+        );
+        assert!(released_versions(&json!({})).is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::fixtures::RegistryHttp;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Default root of PyPI's JSON API.
+const DEFAULT_INDEX: &str = "https://pypi.org/pypi";
+
+/// Specifier operators, longest first so `===` is not read as `==`.
+const OPERATORS: &[&str] = &["===", "==", "~=", "!=", "<=", ">=", "<", ">"];
+
+// ─── PyPI ─────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct ProjectResponse {
+    info: ProjectInfo,
+    #[serde(default)]
+    releases: BTreeMap<String, Vec<ReleaseFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectInfo {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseFile {
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// JSON API root from `PYPI_INDEX_URL`, default [`DEFAULT_INDEX`].
+pub fn index_url() -> String {
+    std::env::var("PYPI_INDEX_URL").unwrap_or_else(|_| DEFAULT_INDEX.to_string())
+}
+
+/// Latest stable version of `package` on the index at `index`.
+pub async fn latest_version(client: &RegistryHttp, index: &str, package: &str) -> Result<String> {
+    let url = format!(
+        "{}/{}/json",
+        index.trim_end_matches('/'),
+        normalize(package)
+    );
+    let resp = client.get(&url).await?.ensure_success(&url)?;
+    let project: ProjectResponse = serde_json::from_slice(&resp.body)
+        .with_context(|| format!("parse PyPI response for {package}"))?;
+    resolve_latest(&project).with_context(|| format!("{package} has no stable release on PyPI"))
+}
+
+/// The highest stable release with at least one file that is not yanked;
+/// `info.version` when the response lists no releases.
+fn resolve_latest(project: &ProjectResponse) -> Option<String> {
+    if project.releases.is_empty() {
+        return (!is_prerelease(&project.info.version)).then(|| project.info.version.clone());
+    }
+    project
+        .releases
+        .iter()
+        .filter(|(version, files)| !is_prerelease(version) && files.iter().any(|f| !f.yanked))
+        .map(|(version, _)| version)
+        .max_by(|a, b| compare(a, b))
+        .cloned()
+}
+
+// ─── Versions ─────────────────────────────────────────────────────────────────
+
+/// A PEP 503 normalized project name: lowercase, with runs of `-`, `_` and
+/// `.` folded into one `-`.
+pub fn normalize(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !out.ends_with('-') {
+                out.push('-');
+            }
+        } else {
+            out.push(c.to_ascii_lowercase());
+        }
+    }
+    out
+}
+
+/// Whether `version` is a PEP 440 pre- or dev-release (`1.0rc1`,
+/// `2.0.0b2`, `1.1.dev3`); post-releases are stable.
+pub fn is_prerelease(version: &str) -> bool {
+    let rest = version[release_len(version)..].to_ascii_lowercase();
+    let rest = rest.trim_start_matches(['.', '-', '_']);
+    rest.contains("dev")
+        || ["a", "b", "c", "rc", "pre"]
+            .iter()
+            .any(|tag| rest.starts_with(tag))
+}
+
+/// Length of the leading release segment (`1.2.3` of `1.2.3rc1`).
+fn release_len(version: &str) -> usize {
+    let digits = version.trim_start_matches(['v', 'V']);
+    version.len() - digits.len()
+        + digits
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(digits.len())
+}
+
+/// The numeric release segment of `version`; pre-, post- and dev-release
+/// parts are ignored.
+fn release(version: &str) -> Vec<u64> {
+    version[..release_len(version)]
+        .trim_start_matches(['v', 'V'])
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Compares two release segments, padding the shorter with zeros.
+fn compare(a: &str, b: &str) -> Ordering {
+    let (a, b) = (release(a), release(b));
+    let len = a.len().max(b.len());
+    let pad = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| pad(&a, i).cmp(&pad(&b, i)))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn join(release: &[u64]) -> String {
+    release
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// ─── Specifiers ───────────────────────────────────────────────────────────────
+
+/// One clause of a specifier set, e.g. `>=` `0.3.1`.
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    op: &'static str,
+    version: String,
+}
+
+impl Clause {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let op = OPERATORS.iter().find(|op| text.starts_with(**op))?;
+        let version = text[op.len()..].trim();
+        (!version.is_empty()).then(|| Self {
+            op,
+            version: version.to_string(),
+        })
+    }
+
+    /// The version this clause sets as the lowest one it accepts.
+    fn floor(&self) -> Option<&str> {
+        matches!(self.op, "==" | "===" | "~=" | ">=" | ">")
+            .then(|| self.version.trim_end_matches(".*"))
+    }
+
+    fn matches(&self, version: &str) -> bool {
+        if let Some(prefix) = self.version.strip_suffix(".*") {
+            let (prefix, version) = (release(prefix), release(version));
+            let equal = version.len() >= prefix.len() && version[..prefix.len()] == prefix[..];
+            return if self.op == "!=" { !equal } else { equal };
+        }
+        let ord = compare(version, &self.version);
+        match self.op {
+            "===" => version == self.version,
+            "==" => ord.is_eq(),
+            "!=" => ord.is_ne(),
+            "<=" => ord.is_le(),
+            ">=" => ord.is_ge(),
+            "<" => ord.is_lt(),
+            ">" => ord.is_gt(),
+            // `~=1.4.2` means `>=1.4.2, ==1.4.*`.
+            _ => {
+                let floor = release(&self.version);
+                let prefix = &floor[..floor.len().saturating_sub(1).max(1)];
+                let version = release(version);
+                ord.is_ge() && version.len() >= prefix.len() && version[..prefix.len()] == *prefix
+            }
+        }
+    }
+}
+
+/// The lowest version a specifier set such as `>=0.3.1,<0.4` names: the
+/// version of its first `==`, `===`, `~=`, `>=` or `>` clause.
+pub fn floor(specifier: &str) -> Option<String> {
+    specifier
+        .split(',')
+        .filter_map(Clause::parse)
+        .find_map(|c| c.floor().map(str::to_string))
+}
+
+/// Whether `version` satisfies every clause of `specifier`.  Versions are
+/// compared by their release segment; an unparseable specifier admits
+/// nothing.
+pub fn admits(specifier: &str, version: &str) -> bool {
+    let Some(clauses) = specifier
+        .split(',')
+        .map(Clause::parse)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    clauses.iter().all(|c| c.matches(version))
+}
+
+/// `specifier` moved to admit `latest`: its floor becomes `latest` and an
+/// upper bound excluding `latest` moves up by the width of the original
+/// range (`>=0.3.1,<0.4` → `>=0.5.2,<0.6`).  `None` without a floor.
+pub fn bump_specifier(specifier: &str, latest: &str) -> Option<String> {
+    let clauses = specifier
+        .split(',')
+        .map(Clause::parse)
+        .collect::<Option<Vec<_>>>()?;
+    let floor = clauses.iter().find_map(Clause::floor).map(release)?;
+    let latest_release = release(latest);
+    let bumped: Vec<String> = clauses
+        .iter()
+        .map(|c| match c.op {
+            "==" | "===" if c.version.ends_with(".*") => {
+                let width = release(&c.version).len();
+                let mut prefix = latest_release.clone();
+                prefix.resize(width, 0);
+                format!("{}{}.*", c.op, join(&prefix))
+            }
+            "==" | "===" => format!("{}{latest}", c.op),
+            "~=" => {
+                let mut next = latest_release.clone();
+                next.resize(release(&c.version).len().max(2), 0);
+                format!("~={}", join(&next))
+            }
+            ">=" | ">" => format!(">={latest}"),
+            "<" | "<=" if !c.matches(latest) => {
+                let upper = release(&c.version);
+                let pad = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+                let step = (0..upper.len())
+                    .find(|&i| pad(&upper, i) != pad(&floor, i))
+                    .unwrap_or(upper.len() - 1);
+                let mut next: Vec<u64> =
+                    (0..upper.len()).map(|i| pad(&latest_release, i)).collect();
+                next[step] += 1;
+                next[step + 1..].fill(0);
+                format!("{}{}", c.op, join(&next))
+            }
+            op => format!("{op}{}", c.version),
+        })
+        .collect();
+    Some(bumped.join(","))
+}
+
+// ─── pyproject.toml ───────────────────────────────────────────────────────────
+
+/// A PEP 508 requirement string split around its specifier set.
+#[derive(Debug)]
+struct Requirement<'a> {
+    name: &'a str,
+    /// Byte range of the specifier set inside the string.
+    specifier: std::ops::Range<usize>,
+}
+
+impl<'a> Requirement<'a> {
+    /// Parses `text`; direct references (`name @ url`) yield `None`.
+    fn parse(text: &'a str) -> Option<Self> {
+        let name_end = text
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(text.len());
+        let name = &text[..name_end];
+        if name.is_empty() {
+            return None;
+        }
+        let mut start = name_end + (text[name_end..].len() - text[name_end..].trim_start().len());
+        if text[start..].starts_with('[') {
+            start += text[start..].find(']')? + 1;
+        }
+        start += text[start..].len() - text[start..].trim_start().len();
+        if text[start..].starts_with('(') {
+            start += 1;
+        }
+        let end = start + text[start..].find([';', ')']).unwrap_or(text.len() - start);
+        let end = start + text[start..end].trim_end().len();
+        if text[start..end].contains('@') {
+            return None;
+        }
+        Some(Self {
+            name,
+            specifier: start..end,
+        })
+    }
+}
+
+/// The `[project]` dependency arrays of a pyproject document:
+/// `dependencies` and every `optional-dependencies` group.
+fn dependency_arrays(doc: &mut toml_edit::DocumentMut) -> Vec<&mut toml_edit::Array> {
+    let Some(project) = doc.get_mut("project").and_then(|p| p.as_table_like_mut()) else {
+        return Vec::new();
+    };
+    let mut arrays = Vec::new();
+    for (key, item) in project.iter_mut() {
+        match key.get() {
+            "dependencies" => arrays.extend(item.as_array_mut()),
+            "optional-dependencies" => {
+                if let Some(groups) = item.as_table_like_mut() {
+                    arrays.extend(groups.iter_mut().filter_map(|(_, g)| g.as_array_mut()));
+                }
+            }
+            _ => {}
+        }
+    }
+    arrays
+}
+
+/// Specifier set of the first `[project]` dependency on `package`
+/// (`dependencies`, then `optional-dependencies`), e.g. `>=0.3.1,<0.4`.
+pub fn current_requirement(content: &str, package: &str) -> Option<String> {
+    let mut doc: toml_edit::DocumentMut = content.parse().ok()?;
+    let wanted = normalize(package);
+    dependency_arrays(&mut doc)
+        .into_iter()
+        .flat_map(|array| array.iter())
+        .filter_map(|value| value.as_str())
+        .find_map(|text| {
+            let req = Requirement::parse(text)?;
+            (normalize(req.name) == wanted).then(|| text[req.specifier].to_string())
+        })
+}
+
+/// Moves every `[project]` dependency on `package` whose specifier set
+/// excludes `latest` to one admitting it (see [`bump_specifier`]), keeping
+/// the file's formatting.  Unpinned and direct-reference requirements are
+/// left alone.
+pub fn patch_dependency(content: &str, package: &str, latest: &str) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = content.parse().context("parse pyproject.toml")?;
+    let wanted = normalize(package);
+    let mut changed = false;
+    for array in dependency_arrays(&mut doc) {
+        for value in array.iter_mut() {
+            let Some(text) = value.as_str() else {
+                continue;
+            };
+            let Some(req) = Requirement::parse(text) else {
+                continue;
+            };
+            let specifier = &text[req.specifier.clone()];
+            if normalize(req.name) != wanted || specifier.is_empty() || admits(specifier, latest) {
+                continue;
+            }
+            let Some(next) = bump_specifier(specifier, latest) else {
+                continue;
+            };
+            let rewritten = format!(
+                "{}{next}{}",
+                &text[..req.specifier.start],
+                &text[req.specifier.end..]
+            );
+            let decor = value.decor().clone();
+            *value = toml_edit::Value::from(rewritten);
+            *value.decor_mut() = decor;
+            changed = true;
+        }
+    }
+    Ok(if changed {
+        doc.to_string()
+    } else {
+        content.to_string()
+    })
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PYPROJECT: &str = r#"[project]
+name = "weather-agent"
+version = "0.1.0"
+dependencies = [
+    "httpx>=0.27",
+    "Evo_Agent.SDK-py[async] >= 0.3.1, < 0.4 ; python_version >= '3.11'",  # the SDK
+]
+
+[project.optional-dependencies]
+test = ["evo-agent-sdk-py[testing]==0.3.1", "pytest"]
+"#;
+
+    #[test]
+    fn test_specifiers() {
+        assert_eq!(floor(">=0.3.1,<0.4").as_deref(), Some("0.3.1"));
+        assert_eq!(floor("<0.4"), None);
+        assert!(admits(">=0.3.1,<0.4", "0.3.9"));
+        assert!(!admits(">=0.3.1,<0.4", "0.4.0"));
+        assert!(admits("~=0.3.1", "0.3.7"));
+        assert!(!admits("~=0.3.1", "0.4.0"));
+        assert!(admits("~=1.4", "1.9"));
+        assert!(admits("==0.3.*", "0.3.2"));
+        assert!(!admits("==0.3.1", "0.3.2"));
+        assert!(!admits("bogus", "0.3.2"));
+
+        assert_eq!(
+            bump_specifier(">=0.3.1,<0.4", "0.5.2").as_deref(),
+            Some(">=0.5.2,<0.6")
+        );
+        assert_eq!(
+            bump_specifier(">=1.2,<2,!=1.5.0", "2.1.0").as_deref(),
+            Some(">=2.1.0,<3,!=1.5.0")
+        );
+        assert_eq!(
+            bump_specifier("~=0.3.1", "0.5.2").as_deref(),
+            Some("~=0.5.2")
+        );
+        assert_eq!(bump_specifier("~=0.3", "0.5.2").as_deref(), Some("~=0.5"));
+        assert_eq!(
+            bump_specifier("==0.3.*", "0.5.2").as_deref(),
+            Some("==0.5.*")
+        );
+        assert_eq!(bump_specifier("<0.4", "0.5.2"), None);
+    }
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(normalize("Evo_Agent.SDK--py"), "evo-agent-sdk-py");
+        assert!(is_prerelease("1.0rc1"));
+        assert!(is_prerelease("2.0.0b2"));
+        assert!(is_prerelease("1.1.dev3"));
+        assert!(!is_prerelease("1.0.post1"));
+        assert!(!is_prerelease("1.0.0"));
+        assert_eq!(compare("0.10", "0.9.9"), Ordering::Greater);
+        assert_eq!(compare("1.0", "1.0.0"), Ordering::Equal);
+
+        let project: ProjectResponse = serde_json::from_str(
+            r#"{"info":{"version":"0.5.0"},"releases":{
+                "0.4.0":[{"yanked":false}],
+                "0.5.0":[{"yanked":true}],
+                "0.4.1":[{"yanked":false}],
+                "0.6.0rc1":[{"yanked":false}],
+                "0.3.0":[]}}"#,
+        )
+        .unwrap();
+        assert_eq!(resolve_latest(&project).as_deref(), Some("0.4.1"));
+    }
+
+    #[test]
+    fn test_patch_dependency() {
+        assert_eq!(
+            current_requirement(PYPROJECT, "evo-agent-sdk-py").as_deref(),
+            Some(">= 0.3.1, < 0.4")
+        );
+        assert_eq!(current_requirement(PYPROJECT, "missing"), None);
+
+        let patched = patch_dependency(PYPROJECT, "evo-agent-sdk-py", "0.4.2").unwrap();
+        assert!(patched.contains(
+            r#""Evo_Agent.SDK-py[async] >=0.4.2,<0.5 ; python_version >= '3.11'",  # the SDK"#
+        ));
+        assert!(patched.contains(r#"test = ["evo-agent-sdk-py[testing]==0.4.2", "pytest"]"#));
+        assert!(patched.contains(r#""httpx>=0.27","#));
+        assert_eq!(
+            current_requirement(&patched, "evo-agent-sdk-py").as_deref(),
+            Some(">=0.4.2,<0.5")
+        );
+
+        // Already admitted, unpinned or direct references: untouched.
+        assert_eq!(
+            patch_dependency(PYPROJECT, "httpx", "0.28.1").unwrap(),
+            PYPROJECT
+        );
+        let direct = "[project]\ndependencies = [\"evo-agent-sdk-py @ git+https://github.com/o/r\", \"pytest\"]\n";
+        assert_eq!(
+            patch_dependency(direct, "evo-agent-sdk-py", "0.4.2").unwrap(),
+            direct
+        );
+        assert_eq!(patch_dependency(direct, "pytest", "8.0.0").unwrap(), direct);
+    }
+}
@@ -0,0 +1,239 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::fixtures::RegistryHttp;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Name of the built-in crates.io registry in fallback lists.
+pub const CRATES_IO: &str = "crates-io";
+
+/// Key of the fallback list used by crates without one of their own.
+const EVERY_CRATE: &str = "*";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Endpoints of a crates.io-compatible registry or mirror.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Registry {
+    /// Web API root serving `{api}/crates/{crate}`.
+    pub api: String,
+    /// Sparse index root.
+    pub index: String,
+    /// `.crate` download URL with `{crate}` and `{version}` markers.
+    pub download: String,
+}
+
+impl Registry {
+    /// The crates.io endpoints.
+    pub fn crates_io() -> Self {
+        Self {
+            api: "https://crates.io/api/v1".to_string(),
+            index: "https://index.crates.io".to_string(),
+            download: "https://static.crates.io/crates/{crate}/{crate}-{version}.crate".to_string(),
+        }
+    }
+
+    /// URL of `crate_name`'s sparse-index file.
+    pub fn index_url(&self, crate_name: &str) -> String {
+        format!(
+            "{}/{}",
+            self.index.trim_end_matches('/'),
+            index_path(crate_name)
+        )
+    }
+
+    /// Download URL of `crate_name@version`.
+    pub fn download_url(&self, crate_name: &str, version: &str) -> String {
+        self.download
+            .replace("{crate}", crate_name)
+            .replace("{version}", version)
+    }
+}
+
+/// Named registries and the order in which each tracked crate tries them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Registries {
+    /// `[registries.<name>]` sections; `crates-io` is always known.
+    pub named: BTreeMap<String, Registry>,
+    /// `[crate_registries]`: crate → registry names, `*` for every crate
+    /// without its own list.
+    pub order: BTreeMap<String, Vec<String>>,
+}
+
+impl Registries {
+    /// Registry names referenced in a fallback list but never defined.
+    pub fn undefined(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .order
+            .values()
+            .flatten()
+            .filter(|name| *name != CRATES_IO && !self.named.contains_key(*name))
+            .cloned()
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// `(name, registry)` pairs `crate_name` is looked up in, in order:
+    /// its own list, else the `*` list, else crates.io alone.
+    pub fn for_crate(&self, crate_name: &str) -> Vec<(String, Registry)> {
+        let names = self
+            .order
+            .get(crate_name)
+            .or_else(|| self.order.get(EVERY_CRATE))
+            .cloned()
+            .unwrap_or_else(|| vec![CRATES_IO.to_string()]);
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let registry = match self.named.get(&name) {
+                    Some(registry) => registry.clone(),
+                    None if name == CRATES_IO => Registry::crates_io(),
+                    None => return None,
+                };
+                Some((name, registry))
+            })
+            .collect()
+    }
+}
+
+// ─── Sparse index ─────────────────────────────────────────────────────────────
+
+/// One line of a sparse-index file (only the fields we need).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IndexEntry {
+    pub vers: String,
+    #[serde(default)]
+    pub cksum: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+/// Path of a crate's file inside the sparse index (cargo's layout rules).
+pub fn index_path(crate_name: &str) -> String {
+    let name = crate_name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    }
+}
+
+/// Entries of a newline-delimited index file; lines that do not parse are
+/// skipped.
+pub fn parse_index(index: &str) -> Vec<IndexEntry> {
+    index
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Fetches and parses `crate_name`'s index file from `registry`, past any
+/// cache when `fresh` is set.
+pub async fn fetch_index(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+    fresh: bool,
+) -> Result<Vec<IndexEntry>> {
+    let url = registry.index_url(crate_name);
+    let response = if fresh {
+        client.get_uncached(&url).await?
+    } else {
+        client.get(&url).await?
+    };
+    Ok(parse_index(response.ensure_success(&url)?.text()?))
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror() -> Registry {
+        Registry {
+            api: "https://mirror.example/api/v1".to_string(),
+            index: "https://mirror.example/index".to_string(),
+            download: "https://mirror.example/dl/{crate}/{version}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fallback_order() {
+        let registries = Registries {
+            named: BTreeMap::from([("mirror".to_string(), mirror())]),
+            order: BTreeMap::from([
+                (
+                    "evo-agent-sdk".to_string(),
+                    vec!["crates-io".to_string(), "mirror".to_string()],
+                ),
+                ("*".to_string(), vec!["mirror".to_string()]),
+            ]),
+        };
+        let names = |c: &str| -> Vec<String> {
+            registries
+                .for_crate(c)
+                .into_iter()
+                .map(|(n, _)| n)
+                .collect()
+        };
+        assert_eq!(names("evo-agent-sdk"), vec!["crates-io", "mirror"]);
+        assert_eq!(names("evo-common"), vec!["mirror"]);
+        assert_eq!(
+            Registries::default().for_crate("evo-common")[0].1,
+            Registry::crates_io()
+        );
+        assert!(registries.undefined().is_empty());
+    }
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("evo-common"), "ev/o-/evo-common");
+        assert_eq!(index_path("Serde"), "se/rd/serde");
+        assert_eq!(
+            mirror().index_url("evo-common"),
+            "https://mirror.example/index/ev/o-/evo-common"
+        );
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let index = concat!(
+            r#"{"name":"evo-common","vers":"0.3.0","deps":[],"cksum":"aaa","features":{},"yanked":false}"#,
+            "\n",
+            "not json\n",
+            r#"{"name":"evo-common","vers":"0.4.0","cksum":"bbb","yanked":true}"#,
+        );
+        let entries = parse_index(index);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].cksum, "aaa");
+        assert!(!entries[0].yanked);
+        assert!(entries[1].yanked);
+    }
+
+    #[test]
+    fn test_download_url_and_undefined_names() {
+        assert_eq!(
+            Registry::crates_io().download_url("evo-common", "0.4.0"),
+            "https://static.crates.io/crates/evo-common/evo-common-0.4.0.crate"
+        );
+        assert_eq!(
+            mirror().download_url("evo-common", "0.4.0"),
+            "https://mirror.example/dl/evo-common/0.4.0"
+        );
+        let registries = Registries {
+            named: BTreeMap::new(),
+            order: BTreeMap::from([("*".to_string(), vec!["corp".to_string()])]),
+        };
+        assert_eq!(registries.undefined(), vec!["corp"]);
+    }
+}
@@ -0,0 +1,492 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+use crate::git::{gh_api, gh_api_with_body};
+use crate::sbom::NewDependency;
+use crate::summary::{CommitRecord, HeldUpdate};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// GitHub rejects release bodies longer than this.
+const MAX_BODY_CHARS: usize = 125_000;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[release]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReleaseConfig {
+    /// Repo the wave's release is published in; off while unset
+    /// (`RELEASE_REPO` takes precedence).
+    pub repo: Option<String>,
+    /// Tag name template of the release, with the fleet tag placeholders.
+    pub tag: String,
+    /// Publish the release as a draft.
+    pub draft: bool,
+}
+
+impl Default for ReleaseConfig {
+    fn default() -> Self {
+        Self {
+            repo: None,
+            tag: "evo-update-{run_id}".to_string(),
+            draft: false,
+        }
+    }
+}
+
+impl ReleaseConfig {
+    /// The release repo in effect, if releases are on.
+    pub fn repo(&self) -> Option<String> {
+        std::env::var("RELEASE_REPO")
+            .ok()
+            .or_else(|| self.repo.clone())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+}
+
+// ─── Run report ───────────────────────────────────────────────────────────────
+
+/// What one update wave did, as recorded in the run summary.
+#[derive(Debug, Clone, Copy)]
+pub struct Wave<'a> {
+    pub org: &'a str,
+    pub run_id: &'a str,
+    /// Latest version of each tracked crate.
+    pub latest: &'a BTreeMap<String, String>,
+    /// `committed` entries: direct commits and grouped PRs.
+    pub committed: &'a [CommitRecord],
+    pub held_for_review: &'a [HeldUpdate],
+    pub errors: &'a [Value],
+    /// Crates the wave's lockfiles pull in for the first time.
+    pub new_dependencies: &'a [NewDependency],
+    /// Name of the fleet tag created for the wave, if any.
+    pub fleet_tag: Option<&'a str>,
+}
+
+/// Renders the Markdown report of `wave`: the versions rolled out, a table
+/// of direct commits, the PRs opened, new transitive dependencies and what
+/// was held or failed.  Empty sections are left out.
+pub fn render_report(wave: &Wave<'_>) -> String {
+    let mut body = format!(
+        "Fleet update wave `{}` from `evo-kernel-agent-update`.\n\n",
+        wave.run_id
+    );
+    if let Some(tag) = wave.fleet_tag {
+        body.push_str(&format!("Fleet tag: `{tag}`\n\n"));
+    }
+
+    if !wave.latest.is_empty() {
+        body.push_str("## Versions\n\n| Crate | Version |\n|-------|---------|\n");
+        for (crate_name, version) in wave.latest {
+            body.push_str(&format!("| `{crate_name}` | `{version}` |\n"));
+        }
+        body.push('\n');
+    }
+
+    let commits: Vec<(&CommitRecord, &str)> = wave
+        .committed
+        .iter()
+        .filter_map(|c| Some((c, c.sha.as_deref()?)))
+        .collect();
+    if !commits.is_empty() {
+        body.push_str(
+            "## Commits\n\n| Repo | File | Commit | Verified |\n|------|------|--------|----------|\n",
+        );
+        for (commit, sha) in commits {
+            let repo = &commit.repo;
+            let verified = match commit.verified {
+                Some(true) => "yes",
+                Some(false) => "no",
+                None => "—",
+            };
+            body.push_str(&format!(
+                "| {repo} | `{file}` | [`{short}`](https://github.com/{org}/{repo}/commit/{sha}) | {verified} |\n",
+                file = commit.file.as_deref().unwrap_or_default(),
+                short = &sha[..sha.len().min(7)],
+                org = wave.org,
+            ));
+        }
+        body.push('\n');
+    }
+
+    let prs: Vec<(&CommitRecord, &str)> = wave
+        .committed
+        .iter()
+        .filter_map(|c| Some((c, c.pr_url.as_deref()?)))
+        .collect();
+    if !prs.is_empty() {
+        body.push_str("## Pull requests\n\n");
+        for (pr, url) in prs {
+            body.push_str(&format!(
+                "- {repo}: [#{number}]({url}){draft} — {files}\n",
+                repo = pr.repo,
+                number = pr.pr_number.unwrap_or_default(),
+                draft = if pr.draft == Some(true) {
+                    " (draft)"
+                } else {
+                    ""
+                },
+                files = code_list(&pr.files),
+            ));
+        }
+        body.push('\n');
+    }
+
+    if !wave.new_dependencies.is_empty() {
+        body.push_str("## New transitive dependencies\n\n");
+        for dep in wave.new_dependencies {
+            body.push_str(&format!(
+                "- {repo} `{lockfile}`: `{name}` {version}, published by {publisher}\n",
+                repo = dep.repo,
+                lockfile = dep.lockfile,
+                name = dep.crate_name,
+                version = dep.version,
+                publisher = dep.publisher.as_deref().unwrap_or("unknown"),
+            ));
+        }
+        body.push('\n');
+    }
+
+    let held: Vec<(&str, String, String)> = wave
+        .held_for_review
+        .iter()
+        .map(|h| {
+            let reason = json!(h.reason);
+            (
+                h.repo.as_str(),
+                format!("`{}`", h.file),
+                text(&reason).to_string(),
+            )
+        })
+        .collect();
+    let errors: Vec<(&str, String, String)> = wave
+        .errors
+        .iter()
+        .map(|e| (text(&e["repo"]), files(e), text(&e["error"]).to_string()))
+        .collect();
+    for (title, entries) in [("Held for review", held), ("Errors", errors)] {
+        if entries.is_empty() {
+            continue;
+        }
+        body.push_str(&format!("## {title}\n\n"));
+        for (repo, files, detail) in entries {
+            body.push_str(&format!(
+                "- {repo} {files}: {}\n",
+                detail.lines().next().unwrap_or_default()
+            ));
+        }
+        body.push('\n');
+    }
+    body
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+/// The entry's `file`, or its `files` list, as inline code.
+fn files(entry: &Value) -> String {
+    match entry["files"].as_array() {
+        Some(files) => code_list(
+            &files
+                .iter()
+                .map(|f| text(f).to_string())
+                .collect::<Vec<_>>(),
+        ),
+        None => format!("`{}`", text(&entry["file"])),
+    }
+}
+
+/// `files` as a comma-separated list of inline code.
+fn code_list(files: &[String]) -> String {
+    files
+        .iter()
+        .map(|f| format!("`{f}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// ─── Publishing ───────────────────────────────────────────────────────────────
+
+/// Publishes a release of `org/repo` at `tag` with `body`; GitHub creates
+/// the tag on the default branch's head when it does not exist yet.
+/// Returns the release URL.
+pub fn publish(org: &str, repo: &str, tag: &str, body: &str, draft: bool) -> Result<String> {
+    let body = match body.char_indices().nth(MAX_BODY_CHARS) {
+        Some((cut, _)) => format!("{}\n\n… (truncated)\n", &body[..cut]),
+        None => body.to_string(),
+    };
+    gh_api_with_body(
+        &[
+            "--method",
+            "POST",
+            &format!("repos/{org}/{repo}/releases"),
+            "--jq",
+            ".html_url",
+        ],
+        &json!({
+            "tag_name": tag,
+            "name": format!("Fleet update {tag}"),
+            "body": body,
+            "draft": draft,
+        }),
+    )
+    .with_context(|| format!("create release {tag} in {org}/{repo}"))
+}
+
+// ─── Package releases ─────────────────────────────────────────────────────────
+
+/// Which part of a repo's own version is bumped after its dependencies are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseBump {
+    /// `0.3.1` → `0.3.2`.
+    Patch,
+    /// `0.3.1` → `0.4.0`.
+    Minor,
+}
+
+impl ReleaseBump {
+    /// `version` bumped by this level; `None` for pre-release or malformed
+    /// versions, which need a human to pick the next one.
+    pub fn apply(self, version: &str) -> Option<String> {
+        let parts: Vec<u64> = version
+            .split('.')
+            .map(|p| p.parse().ok())
+            .collect::<Option<_>>()?;
+        let [major, minor, patch] = parts[..] else {
+            return None;
+        };
+        Some(match self {
+            ReleaseBump::Patch => format!("{major}.{minor}.{}", patch + 1),
+            ReleaseBump::Minor => format!("{major}.{}.0", minor + 1),
+        })
+    }
+}
+
+/// `(name, version)` of the manifest's own package; the name is `None`
+/// when the version comes from `[workspace.package]`.
+pub fn package_version(manifest: &str) -> Option<(Option<String>, String)> {
+    let doc: toml_edit::DocumentMut = manifest.parse().ok()?;
+    if let Some(package) = doc.get("package")
+        && let Some(version) = package.get("version").and_then(|v| v.as_str())
+    {
+        let name = package.get("name").and_then(|n| n.as_str());
+        return Some((name.map(str::to_string), version.to_string()));
+    }
+    let version = doc.get("workspace")?.get("package")?.get("version")?;
+    Some((None, version.as_str()?.to_string()))
+}
+
+/// `manifest` with its own package version (as found by
+/// [`package_version`]) set to `version`, formatting kept.
+pub fn set_package_version(manifest: &str, version: &str) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = manifest.parse().context("parse manifest")?;
+    let table = if doc
+        .get("package")
+        .and_then(|p| p.get("version"))
+        .is_some_and(|v| v.is_str())
+    {
+        &mut doc["package"]
+    } else {
+        &mut doc["workspace"]["package"]
+    };
+    let value = table
+        .get_mut("version")
+        .and_then(|v| v.as_value_mut())
+        .filter(|v| v.is_str())
+        .context("manifest has no package version")?;
+    let decor = value.decor().clone();
+    *value = version.into();
+    *value.decor_mut() = decor;
+    Ok(doc.to_string())
+}
+
+/// `lock` with the local package `package` (every local package at `from`
+/// when `None`) moved from `from` to `to`.  Registry and git packages, which
+/// carry a `source`, are never touched.
+pub fn bump_lockfile(lock: &str, package: Option<&str>, from: &str, to: &str) -> String {
+    let from_line = format!("version = \"{from}\"");
+    let mut out = String::with_capacity(lock.len());
+    for block in lock.split_inclusive("[[package]]") {
+        let local = !block.lines().any(|l| l.starts_with("source = "));
+        let named =
+            package.is_none_or(|name| block.lines().any(|l| l == format!("name = \"{name}\"")));
+        if local && named && block.lines().any(|l| l == from_line) {
+            out.push_str(&block.replacen(&from_line, &format!("version = \"{to}\""), 1));
+        } else {
+            out.push_str(block);
+        }
+    }
+    out
+}
+
+/// `content` with every `from` on a line mentioning a version (`version =
+/// "0.3.1"`, `VERSION: 0.3.1`, `--version 0.3.1`) replaced by `to`.  Longer
+/// versions containing `from` (`10.3.1`, `0.3.12`) are left alone.
+pub fn bump_version_refs(content: &str, from: &str, to: &str) -> String {
+    let version_char = |c: char| c.is_ascii_alphanumeric() || c == '.';
+    content
+        .split_inclusive('\n')
+        .map(|line| {
+            if !line.to_ascii_lowercase().contains("version") {
+                return line.to_string();
+            }
+            let mut out = String::with_capacity(line.len());
+            let mut rest = line;
+            while let Some(at) = rest.find(from) {
+                let before = rest[..at].chars().next_back().or(out.chars().next_back());
+                let after = rest[at + from.len()..].chars().next();
+                out.push_str(&rest[..at]);
+                if before.is_some_and(version_char) || after.is_some_and(version_char) {
+                    out.push_str(from);
+                } else {
+                    out.push_str(to);
+                }
+                rest = &rest[at + from.len()..];
+            }
+            out.push_str(rest);
+            out
+        })
+        .collect()
+}
+
+/// Starts `workflow` (a `.github/workflows/` path or file name) of
+/// `org/repo` on the repo's default branch.  The workflow must accept
+/// `workflow_dispatch`.
+pub fn dispatch_workflow(org: &str, repo: &str, workflow: &str) -> Result<String> {
+    let branch = gh_api(&[&format!("repos/{org}/{repo}"), "--jq", ".default_branch"])
+        .with_context(|| format!("look up the default branch of {org}/{repo}"))?;
+    let file = workflow.rsplit('/').next().unwrap_or(workflow);
+    gh_api_with_body(
+        &[
+            "--method",
+            "POST",
+            &format!("repos/{org}/{repo}/actions/workflows/{file}/dispatches"),
+        ],
+        &json!({ "ref": branch }),
+    )
+    .with_context(|| format!("dispatch {file} in {org}/{repo}"))?;
+    Ok(branch)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_sections() {
+        let latest = BTreeMap::from([("evo-common".to_string(), "0.4.0".to_string())]);
+        let committed = vec![
+            CommitRecord {
+                repo: "evo-king".to_string(),
+                file: Some("Cargo.toml".to_string()),
+                sha: Some("abc1234def".to_string()),
+                verified: Some(true),
+                ..Default::default()
+            },
+            CommitRecord {
+                repo: "evo-runner".to_string(),
+                files: vec!["Cargo.toml".to_string(), "ci.yml".to_string()],
+                pr_number: Some(7),
+                pr_url: Some("https://github.com/o/evo-runner/pull/7".to_string()),
+                draft: Some(true),
+                ..Default::default()
+            },
+        ];
+        let held = vec![HeldUpdate::new(
+            "evo-x",
+            "Cargo.toml",
+            crate::summary::HoldReason::LlmRisk,
+        )];
+        let report = render_report(&Wave {
+            org: "o",
+            run_id: "r1",
+            latest: &latest,
+            committed: &committed,
+            held_for_review: &held,
+            errors: &[],
+            new_dependencies: &[NewDependency {
+                repo: "evo-king".to_string(),
+                lockfile: "Cargo.lock".to_string(),
+                crate_name: "tinyframe".to_string(),
+                version: "0.1.2".to_string(),
+                publisher: Some("mallory".to_string()),
+            }],
+            fleet_tag: Some("sdk-0.3-rollout"),
+        });
+        assert!(report.contains("Fleet tag: `sdk-0.3-rollout`"));
+        assert!(report.contains("| `evo-common` | `0.4.0` |"));
+        assert!(
+            report.contains("- evo-king `Cargo.lock`: `tinyframe` 0.1.2, published by mallory")
+        );
+        assert!(report.contains(
+            "| evo-king | `Cargo.toml` | [`abc1234`](https://github.com/o/evo-king/commit/abc1234def) | yes |"
+        ));
+        assert!(report.contains(
+            "- evo-runner: [#7](https://github.com/o/evo-runner/pull/7) (draft) — `Cargo.toml`, `ci.yml`"
+        ));
+        assert!(report.contains("## Held for review\n\n- evo-x `Cargo.toml`: llm_risk\n"));
+        assert!(!report.contains("## Errors"));
+    }
+
+    #[test]
+    fn test_release_bump() {
+        assert_eq!(ReleaseBump::Patch.apply("0.3.1").as_deref(), Some("0.3.2"));
+        assert_eq!(ReleaseBump::Minor.apply("0.3.1").as_deref(), Some("0.4.0"));
+        assert_eq!(ReleaseBump::Patch.apply("0.4.0-rc.1"), None);
+        assert_eq!(ReleaseBump::Patch.apply("1.0"), None);
+    }
+
+    #[test]
+    fn test_package_version_edits() {
+        let manifest = "[package]\nname = \"evo-king\"\nversion = \"0.3.1\" # released\n\n[dependencies]\nevo-common = \"0.4\"\n";
+        assert_eq!(
+            package_version(manifest),
+            Some((Some("evo-king".to_string()), "0.3.1".to_string()))
+        );
+        assert_eq!(
+            set_package_version(manifest, "0.3.2").unwrap(),
+            manifest.replace("\"0.3.1\"", "\"0.3.2\"")
+        );
+        let workspace =
+            "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nversion = \"1.2.0\"\n";
+        assert_eq!(
+            package_version(workspace),
+            Some((None, "1.2.0".to_string()))
+        );
+        assert!(
+            set_package_version(workspace, "1.3.0")
+                .unwrap()
+                .contains("version = \"1.3.0\"")
+        );
+        assert!(set_package_version("[dependencies]\n", "1.0.0").is_err());
+    }
+
+    #[test]
+    fn test_bump_lockfile_and_version_refs() {
+        let lock = "version = 4\n\n[[package]]\nname = \"evo-common\"\nversion = \"0.3.1\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"evo-king\"\nversion = \"0.3.1\"\n";
+        let bumped = bump_lockfile(lock, Some("evo-king"), "0.3.1", "0.3.2");
+        assert_eq!(
+            bumped,
+            lock.replacen(
+                "name = \"evo-king\"\nversion = \"0.3.1\"",
+                "name = \"evo-king\"\nversion = \"0.3.2\"",
+                1
+            )
+        );
+        assert_eq!(bump_lockfile(lock, None, "0.3.1", "0.3.2"), bumped);
+
+        let workflow = "env:\n  VERSION: 0.3.1\n  OTHER: 0.3.1\nrun: sed 's/version = \"0.3.1\"/x/' && echo 10.3.1 version 0.3.12\n";
+        assert_eq!(
+            bump_version_refs(workflow, "0.3.1", "0.3.2"),
+            "env:\n  VERSION: 0.3.2\n  OTHER: 0.3.1\nrun: sed 's/version = \"0.3.2\"/x/' && echo 10.3.1 version 0.3.12\n"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::versions::BumpKind;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// File at the root of a managed repo holding the repo's own update rules.
+pub const FILE_NAME: &str = ".evo-update.toml";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Size of a version bump.
+///
+/// `major` follows cargo's caret rules, so `0.3 → 0.4` is major; `minor`
+/// is a compatible bump of the second component and `patch` anything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateType {
+    #[serde(alias = "version-update:semver-major")]
+    Major,
+    #[serde(alias = "version-update:semver-minor")]
+    Minor,
+    #[serde(alias = "version-update:semver-patch")]
+    Patch,
+}
+
+impl UpdateType {
+    /// Classifies the bump from requirement `from` to version `to`.
+    pub fn of(from: &str, to: &str) -> Self {
+        match BumpKind::size(from, to) {
+            BumpKind::Major => UpdateType::Major,
+            BumpKind::Minor => UpdateType::Minor,
+            _ => UpdateType::Patch,
+        }
+    }
+}
+
+/// A dependabot-style ignore rule.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreRule {
+    /// Crate the rule applies to; every tracked crate when omitted.
+    #[serde(rename = "crate", alias = "dependency-name", default)]
+    pub crate_name: Option<String>,
+    /// Bump sizes to ignore; every bump when empty.
+    #[serde(alias = "update-types", default)]
+    pub update_types: Vec<UpdateType>,
+}
+
+/// Why a repo's own config suppressed an update.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Suppression {
+    /// The target is above the version the repo is pinned to.
+    Pin { max: String },
+    /// An `[[ignore]]` rule matched.
+    Ignore { update_type: UpdateType },
+    /// The repo opted out of automated updates (`enabled = false`).
+    OptOut,
+}
+
+/// Update rules a repo keeps in its own [`FILE_NAME`]:
+///
+/// ```toml
+/// enabled = true                      # false opts the repo out
+/// cargo_files = ["Cargo.toml"]        # replaces the central lists
+/// workflow_files = [".github/workflows/ci.yml"]
+/// python_files = ["pyproject.toml"]
+/// npm_files = ["package.json"]
+/// go_files = ["go.mod"]
+/// file_sync_opt_out = ["deny.toml"]  # campaigns or files not synced here
+///
+/// [pins]
+/// evo-common = "0.3"        # never bump past 0.3.x
+///
+/// [[ignore]]
+/// crate = "evo-agent-sdk"   # omit to match every crate
+/// update_types = ["major"]  # omit to ignore every bump
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoLocalConfig {
+    /// `false` opts the repo out of automated updates.
+    pub enabled: Option<bool>,
+    /// Manifests the agent may touch, replacing the central `cargo_files`
+    /// and workspace-member discovery.
+    pub cargo_files: Option<Vec<String>>,
+    /// Workflow files the agent may touch, replacing the central list.
+    pub workflow_files: Option<Vec<String>>,
+    /// `pyproject.toml` files the agent may touch, replacing the central list.
+    pub python_files: Option<Vec<String>>,
+    /// `package.json` files the agent may touch, replacing the central list.
+    pub npm_files: Option<Vec<String>>,
+    /// `go.mod` files the agent may touch, replacing the central list.
+    pub go_files: Option<Vec<String>>,
+    /// `[[file_sync]]` campaigns or files the repo keeps its own copy of,
+    /// on top of the central `file_sync_opt_out`.
+    pub file_sync_opt_out: Vec<String>,
+    /// Highest version each crate may be bumped to.  Missing components
+    /// are wildcards, so `"0.3"` allows any `0.3.x`.
+    pub pins: BTreeMap<String, String>,
+    pub ignore: Vec<IgnoreRule>,
+}
+
+impl RepoLocalConfig {
+    /// Reads `repo_base/.evo-update.toml`; a repo without one has no rules.
+    pub fn load(repo_base: &Path) -> Result<Self> {
+        let path = repo_base.join(FILE_NAME);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        let config: Self =
+            toml_edit::de::from_str(&text).with_context(|| format!("parse {}", path.display()))?;
+        for file in config
+            .cargo_files
+            .iter()
+            .chain(&config.workflow_files)
+            .chain(&config.python_files)
+            .chain(&config.npm_files)
+            .chain(&config.go_files)
+            .flatten()
+        {
+            anyhow::ensure!(
+                is_repo_relative(file),
+                "{}: {file:?} is not a path inside the repo",
+                path.display()
+            );
+        }
+        Ok(config)
+    }
+
+    /// Whether the repo opted out of automated updates.
+    pub fn opted_out(&self) -> bool {
+        self.enabled == Some(false)
+    }
+
+    /// The rule that suppresses bumping `crate_name` from `from` to `to`,
+    /// if any.  Pins are checked before ignore rules.
+    pub fn check(&self, crate_name: &str, from: &str, to: &str) -> Option<Suppression> {
+        if let Some(max) = self.pins.get(crate_name)
+            && exceeds(to, max)
+        {
+            return Some(Suppression::Pin { max: max.clone() });
+        }
+        let update_type = UpdateType::of(from, to);
+        self.ignore
+            .iter()
+            .any(|rule| {
+                rule.crate_name.as_deref().is_none_or(|c| c == crate_name)
+                    && (rule.update_types.is_empty() || rule.update_types.contains(&update_type))
+            })
+            .then_some(Suppression::Ignore { update_type })
+    }
+}
+
+/// Whether `path` is relative and stays inside the repo.
+pub(crate) fn is_repo_relative(path: &str) -> bool {
+    let path = Path::new(path);
+    !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Whether `version` is above `max`, comparing only the components `max`
+/// spells out.
+fn exceeds(version: &str, max: &str) -> bool {
+    let max = components(max);
+    let version = components(version);
+    version.iter().take(max.len()).cmp(max.iter()) == std::cmp::Ordering::Greater
+}
+
+/// Numeric `major.minor.patch` components, as many as are given.
+fn components(v: &str) -> Vec<u64> {
+    v.trim_start_matches(['^', '=', '~', ' '])
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .take(3)
+        .map(|p| p.parse().unwrap_or(0))
+        .collect()
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[pins]
+evo-common = "0.3"
+
+[[ignore]]
+dependency-name = "evo-agent-sdk"
+update-types = ["version-update:semver-major"]
+"#;
+
+    #[test]
+    fn test_update_type() {
+        assert_eq!(UpdateType::of("0.3", "0.4.0"), UpdateType::Major);
+        assert_eq!(UpdateType::of("1.2", "1.3.0"), UpdateType::Minor);
+        assert_eq!(UpdateType::of("1.2.0", "1.2.5"), UpdateType::Patch);
+        assert_eq!(UpdateType::of("0.3.1", "0.3.2"), UpdateType::Patch);
+    }
+
+    #[test]
+    fn test_pins_cap_versions() {
+        let config: RepoLocalConfig = toml_edit::de::from_str(CONFIG).unwrap();
+        assert_eq!(config.check("evo-common", "0.3.0", "0.3.9"), None);
+        assert_eq!(
+            config.check("evo-common", "0.3", "0.4.0"),
+            Some(Suppression::Pin { max: "0.3".into() })
+        );
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let config: RepoLocalConfig = toml_edit::de::from_str(CONFIG).unwrap();
+        assert_eq!(
+            config.check("evo-agent-sdk", "0.3", "0.4.0"),
+            Some(Suppression::Ignore {
+                update_type: UpdateType::Major
+            })
+        );
+        assert_eq!(config.check("evo-agent-sdk", "0.3.0", "0.3.1"), None);
+
+        let all: RepoLocalConfig = toml_edit::de::from_str("[[ignore]]\n").unwrap();
+        assert!(all.check("anything", "1.0.0", "1.0.1").is_some());
+    }
+
+    #[test]
+    fn test_path_overrides_stay_inside_repo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let write = |text: &str| std::fs::write(dir.path().join(FILE_NAME), text).unwrap();
+
+        write("enabled = false\ncargo_files = [\"crates/core/Cargo.toml\"]\n");
+        let config = RepoLocalConfig::load(dir.path()).unwrap();
+        assert!(config.opted_out());
+        assert_eq!(
+            config.cargo_files.as_deref(),
+            Some(&["crates/core/Cargo.toml".to_string()][..])
+        );
+        assert_eq!(config.workflow_files, None);
+
+        write("workflow_files = [\"../evo-king/.github/workflows/ci.yml\"]\n");
+        assert!(RepoLocalConfig::load(dir.path()).is_err());
+        write("cargo_files = [\"/etc/Cargo.toml\"]\n");
+        assert!(RepoLocalConfig::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_missing_file_has_no_rules() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            RepoLocalConfig::load(dir.path()).unwrap(),
+            RepoLocalConfig::default()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::state::state_dir;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Attempts (the original one included) after which a commit is given up.
+pub const MAX_ATTEMPTS: u32 = 5;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// One file of a queued commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedFile {
+    pub path: String,
+    /// Content the change was computed from.
+    pub original: String,
+    /// Content to commit.
+    pub content: String,
+}
+
+/// A direct commit that failed, kept for the next run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedCommit {
+    pub repo: String,
+    pub files: Vec<QueuedFile>,
+    pub message: String,
+    /// Run whose commit failed first.
+    pub run_id: String,
+    /// Attempts so far, the original one included.
+    pub attempts: u32,
+    pub last_error: String,
+    /// Unix seconds when the commit was first queued.
+    pub queued_at: u64,
+    /// Tracked crates the commit bumps, checked against the crate policy
+    /// again before every retry.
+    #[serde(default)]
+    pub crates: Vec<String>,
+    /// Whether the commit fixes a known advisory, so the security-only
+    /// policy lets it through.
+    #[serde(default)]
+    pub security_fix: bool,
+}
+
+/// Where a queued commit stands against the repo's checkout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Every file still has the content the change was computed from.
+    Pending,
+    /// Every file already has the queued content.
+    Applied,
+    /// Some file changed otherwise; the next scan recomputes the update.
+    Stale,
+}
+
+impl QueuedCommit {
+    /// Compares the queued files with their current content (`None` when a
+    /// file cannot be read).
+    pub fn freshness(&self, current: impl Fn(&str) -> Option<String>) -> Freshness {
+        let contents: Vec<Option<String>> = self.files.iter().map(|f| current(&f.path)).collect();
+        let all = |pick: fn(&QueuedFile) -> &str| {
+            self.files
+                .iter()
+                .zip(&contents)
+                .all(|(file, now)| now.as_deref() == Some(pick(file)))
+        };
+        if all(|f| &f.content) {
+            Freshness::Applied
+        } else if all(|f| &f.original) {
+            Freshness::Pending
+        } else {
+            Freshness::Stale
+        }
+    }
+}
+
+// ─── Storage ──────────────────────────────────────────────────────────────────
+
+/// The queue, kept as one JSON file under [`state_dir`].
+#[derive(Debug, Clone)]
+pub struct RetryQueue {
+    path: PathBuf,
+}
+
+impl RetryQueue {
+    /// A queue stored at `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The queue at `state_dir/retry-queue.json`.
+    pub fn default_location() -> Self {
+        Self::new(&state_dir().join("retry-queue.json"))
+    }
+
+    /// Queued commits, oldest first; empty when there is no queue yet.
+    pub fn load(&self) -> Result<Vec<QueuedCommit>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parse retry queue {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).with_context(|| format!("read retry queue {}", self.path.display())),
+        }
+    }
+
+    /// Replaces the queue with `commits`; an empty queue removes the file.
+    pub fn save(&self, commits: &[QueuedCommit]) -> Result<()> {
+        if commits.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("remove retry queue {}", self.path.display()))
+                }
+                _ => Ok(()),
+            };
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create {}", parent.display()))?;
+        }
+        let text = serde_json::to_string_pretty(commits).context("serialize retry queue")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write retry queue {}", self.path.display()))
+    }
+
+    /// Location of the queue file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued() -> QueuedCommit {
+        QueuedCommit {
+            repo: "evo-king".to_string(),
+            files: vec![QueuedFile {
+                path: "Cargo.toml".to_string(),
+                original: "v = 1".to_string(),
+                content: "v = 2".to_string(),
+            }],
+            message: "bump".to_string(),
+            run_id: "r1".to_string(),
+            attempts: 1,
+            last_error: "rate limited".to_string(),
+            queued_at: 1,
+            crates: vec!["evo-common".to_string()],
+            security_fix: false,
+        }
+    }
+
+    #[test]
+    fn test_freshness() {
+        let commit = queued();
+        let now = |text: &'static str| move |_: &str| Some(text.to_string());
+        assert_eq!(commit.freshness(now("v = 1")), Freshness::Pending);
+        assert_eq!(commit.freshness(now("v = 2")), Freshness::Applied);
+        assert_eq!(commit.freshness(now("v = 3")), Freshness::Stale);
+        assert_eq!(commit.freshness(|_| None), Freshness::Stale);
+    }
+
+    #[test]
+    fn test_save_load_and_clear() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let queue = RetryQueue::new(&dir.path().join("state/retry-queue.json"));
+        assert!(queue.load().unwrap().is_empty());
+        queue.save(&[queued()]).unwrap();
+        assert_eq!(queue.load().unwrap(), vec![queued()]);
+        queue.save(&[]).unwrap();
+        assert!(!queue.path().exists());
+        queue.save(&[]).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+use crate::versions::{BumpKind, VersionReport};
+
+// ─── Risk assessment ──────────────────────────────────────────────────────────
+
+/// Risk the changelog analysis assigns to a bump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// The analysis verdict for one crate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateRisk {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub risk: RiskLevel,
+    #[serde(default)]
+    pub hold_for_review: bool,
+    /// Migration notes, if any.
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// Structured output of the Phase 3 changelog analysis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskAssessment {
+    /// 2–3 sentence overview, used as the PR "Risk assessment" section.
+    pub summary: String,
+    #[serde(default)]
+    pub crates: Vec<CrateRisk>,
+}
+
+impl RiskAssessment {
+    /// Extracts the JSON object from an LLM response, tolerating prose or
+    /// code fences around it.
+    pub fn parse(response: &str) -> Option<Self> {
+        let start = response.find('{')?;
+        let end = response.rfind('}')?;
+        serde_json::from_str(response.get(start..=end)?).ok()
+    }
+
+    /// The verdict for `crate_name`, if the analysis gave one.
+    pub fn for_crate(&self, crate_name: &str) -> Option<&CrateRisk> {
+        self.crates.iter().find(|c| c.crate_name == crate_name)
+    }
+}
+
+/// Built-in template of the analysis prompt; `{updates}` is replaced with
+/// the bumps.
+pub const DEFAULT_PROMPT: &str = "The following Rust crate dependencies are being updated:\n{updates}\n\n\
+     Assess the risk of applying these updates automatically.  Reply with \
+     JSON only, in this shape:\n\
+     {\"summary\": \"2-3 sentence risk assessment with any migration notes\", \
+     \"crates\": [{\"crate\": \"name\", \"risk\": \"low|medium|high|critical\", \
+     \"hold_for_review\": false, \"notes\": \"\"}]}\n\
+     Set hold_for_review when an update should not be applied without a human \
+     review (e.g. likely breaking changes).";
+
+/// Prompt asking for a [`RiskAssessment`] of the listed bumps
+/// (`crate: from → to`, one per line), from `template` or
+/// [`DEFAULT_PROMPT`].
+pub fn prompt(template: Option<&str>, update_list: &[String]) -> String {
+    template
+        .unwrap_or(DEFAULT_PROMPT)
+        .replace("{updates}", &update_list.join("\n"))
+}
+
+// ─── Major-bump gate ──────────────────────────────────────────────────────────
+
+/// What Phase 4 does with a major bump the analysis flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateAction {
+    /// Apply it like any other update.
+    Off,
+    /// Deliver it as a draft PR instead of committing it.
+    #[default]
+    DraftPr,
+    /// Do not apply it; report it under `held_for_review`.
+    Hold,
+}
+
+/// Policy for semver-incompatible bumps the analysis considers risky.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MajorBumpGate {
+    pub action: GateAction,
+    /// Lowest risk that triggers the gate (`hold_for_review` always does).
+    pub min_risk: RiskLevel,
+}
+
+impl Default for MajorBumpGate {
+    fn default() -> Self {
+        Self {
+            action: GateAction::default(),
+            min_risk: RiskLevel::High,
+        }
+    }
+}
+
+impl MajorBumpGate {
+    /// The semver-incompatible bumps in `versions` that the gate stops.
+    pub fn flagged<'a>(
+        &self,
+        versions: &'a [VersionReport],
+        assessment: &RiskAssessment,
+    ) -> Vec<&'a VersionReport> {
+        if self.action == GateAction::Off {
+            return Vec::new();
+        }
+        versions
+            .iter()
+            .filter(|v| v.bump_kind == BumpKind::Major)
+            .filter(|v| {
+                assessment
+                    .for_crate(&v.crate_name)
+                    .is_some_and(|r| r.hold_for_review || r.risk >= self.min_risk)
+            })
+            .collect()
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bump(name: &str, from: &str, to: &str) -> VersionReport {
+        VersionReport::new(name, from, to)
+    }
+
+    const RESPONSE: &str = r#"Here you go:
+```json
+{"summary": "evo-common 0.4 renames the config API.",
+ "crates": [{"crate": "evo-common", "risk": "high"},
+            {"crate": "evo-agent-sdk", "risk": "low", "hold_for_review": true}]}
+```"#;
+
+    #[test]
+    fn test_prompt_template() {
+        let updates = vec!["evo-common: 0.3.0 → 0.4.0 (minor)".to_string()];
+        assert!(prompt(None, &updates).contains("updated:\nevo-common: 0.3.0 → 0.4.0 (minor)\n"));
+        assert!(prompt(None, &updates).contains("{\"summary\": "));
+        assert_eq!(
+            prompt(Some("Rate these:\n{updates}"), &updates),
+            "Rate these:\nevo-common: 0.3.0 → 0.4.0 (minor)"
+        );
+    }
+
+    #[test]
+    fn test_parse_assessment() {
+        let assessment = RiskAssessment::parse(RESPONSE).unwrap();
+        assert_eq!(assessment.summary, "evo-common 0.4 renames the config API.");
+        assert_eq!(
+            assessment.for_crate("evo-common").map(|r| r.risk),
+            Some(RiskLevel::High)
+        );
+        assert!(RiskAssessment::parse("No JSON here.").is_none());
+    }
+
+    #[test]
+    fn test_gate_flags_risky_major_bumps_only() {
+        let assessment = RiskAssessment::parse(RESPONSE).unwrap();
+        let versions = vec![
+            bump("evo-common", "0.3", "0.4.0"),
+            bump("evo-agent-sdk", "0.3", "0.3.2"),
+        ];
+        let gate = MajorBumpGate::default();
+        let flagged = gate.flagged(&versions, &assessment);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].crate_name, "evo-common");
+
+        let strict = MajorBumpGate {
+            min_risk: RiskLevel::Critical,
+            ..MajorBumpGate::default()
+        };
+        assert!(strict.flagged(&versions, &assessment).is_empty());
+
+        let off = MajorBumpGate {
+            action: GateAction::Off,
+            ..MajorBumpGate::default()
+        };
+        assert!(off.flagged(&versions, &assessment).is_empty());
+    }
+
+    #[test]
+    fn test_gate_honours_hold_for_review() {
+        let assessment = RiskAssessment::parse(RESPONSE).unwrap();
+        let versions = vec![bump("evo-agent-sdk", "0.3", "0.4.0")];
+        assert_eq!(
+            MajorBumpGate::default()
+                .flagged(&versions, &assessment)
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::checks::CHECK_NAME;
+use crate::config::RepoConfig;
+use crate::git::gh_api;
+use crate::summary::CommitRecord;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[rollout]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RolloutConfig {
+    /// What must hold for a tier before the next one is updated.
+    pub tier_gate: TierGate,
+    /// How long the `checks` gate waits for a tier's CI.
+    pub timeout_secs: u64,
+    /// Delay between two polls of a tier's CI.
+    pub interval_secs: u64,
+    /// Repo updated and verified by CI before every other repo.
+    pub canary: Option<String>,
+}
+
+impl Default for RolloutConfig {
+    fn default() -> Self {
+        Self {
+            tier_gate: TierGate::Errors,
+            timeout_secs: 1_800,
+            interval_secs: 30,
+            canary: None,
+        }
+    }
+}
+
+/// Step between two tiers of the repo dependency graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TierGate {
+    /// Update every tier, whatever happened upstream.
+    None,
+    /// Hold the remaining tiers when a repo of the tier failed to update.
+    #[default]
+    Errors,
+    /// Also wait for CI on the tier's last commits to pass.
+    Checks,
+}
+
+// ─── Dependency graph ─────────────────────────────────────────────────────────
+
+/// Sorts `repos` into tiers: each repo comes after every repo in its
+/// `depends_on`, in the earliest tier possible.  Repos keep their config
+/// order within a tier.  Unknown dependencies and cycles are errors.
+pub fn tiers(repos: &[RepoConfig]) -> Result<Vec<Vec<String>>> {
+    for repo in repos {
+        for dep in &repo.depends_on {
+            anyhow::ensure!(
+                *dep != repo.repo && repos.iter().any(|r| r.repo == *dep),
+                "repo {} depends on unknown repo {dep}",
+                repo.repo
+            );
+        }
+    }
+    let mut placed: Vec<&str> = Vec::new();
+    let mut remaining: Vec<&RepoConfig> = repos.iter().collect();
+    let mut tiers = Vec::new();
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<&RepoConfig>, Vec<&RepoConfig>) = remaining
+            .into_iter()
+            .partition(|r| r.depends_on.iter().all(|d| placed.contains(&d.as_str())));
+        anyhow::ensure!(
+            !ready.is_empty(),
+            "repo dependencies form a cycle through {}",
+            blocked
+                .iter()
+                .map(|r| r.repo.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        placed.extend(ready.iter().map(|r| r.repo.as_str()));
+        tiers.push(ready.iter().map(|r| r.repo.clone()).collect());
+        remaining = blocked;
+    }
+    Ok(tiers)
+}
+
+// ─── Tier gate ────────────────────────────────────────────────────────────────
+
+/// Outcome of the gate after one tier.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TierResult {
+    pub tier: usize,
+    /// Repos of the tier the run had updates for.
+    pub repos: Vec<String>,
+    pub gate: TierGate,
+    pub passed: bool,
+    /// Why the remaining tiers are held.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub waited_secs: u64,
+}
+
+/// Runs `gate` for `tier`, given the `errors` and `committed` entries its
+/// repos produced.
+pub async fn gate(
+    config: &RolloutConfig,
+    gate: TierGate,
+    org: &str,
+    tier: usize,
+    repos: Vec<String>,
+    errors: &[Value],
+    committed: &[CommitRecord],
+) -> TierResult {
+    let started = Instant::now();
+    let failed: Vec<&str> = errors.iter().filter_map(|e| e["repo"].as_str()).collect();
+    let reason = if gate == TierGate::None {
+        None
+    } else if !failed.is_empty() {
+        Some(format!("update failed in {}", dedup(failed).join(", ")))
+    } else if gate == TierGate::Checks {
+        wait_for_ci(config, org, &last_commits(committed))
+            .await
+            .reason()
+    } else {
+        None
+    };
+    let result = TierResult {
+        tier,
+        repos,
+        gate,
+        passed: reason.is_none(),
+        reason,
+        waited_secs: started.elapsed().as_secs(),
+    };
+    match &result.reason {
+        None => info!(tier, "tier gate passed"),
+        Some(reason) => {
+            warn!(tier, reason = %reason, "tier gate failed — holding the remaining tiers")
+        }
+    }
+    result
+}
+
+/// The last direct commit of each repo in `committed`.
+fn last_commits(committed: &[CommitRecord]) -> Vec<(String, String)> {
+    let mut commits: Vec<(String, String)> = Vec::new();
+    for entry in committed {
+        let Some(sha) = &entry.sha else {
+            continue;
+        };
+        commits.retain(|(r, _)| *r != entry.repo);
+        commits.push((entry.repo.clone(), sha.clone()));
+    }
+    commits
+}
+
+fn dedup(mut repos: Vec<&str>) -> Vec<&str> {
+    repos.sort_unstable();
+    repos.dedup();
+    repos
+}
+
+// ─── Canary ───────────────────────────────────────────────────────────────────
+
+/// How the canary stage ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanaryStatus {
+    /// CI passed, or there was nothing to verify: the other repos follow.
+    Passed,
+    /// The update or its CI failed: the canary is reverted, the rest held.
+    Failed,
+    /// CI was still running at the timeout: the rest waits for a follow-up
+    /// run.
+    Pending,
+}
+
+/// Outcome of the canary stage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanaryResult {
+    pub repo: String,
+    pub status: CanaryStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Canary commits under verification, oldest first.
+    pub commits: Vec<String>,
+    pub waited_secs: u64,
+    /// Revert of `commits` after a failure: `{ "sha" }` or `{ "error" }`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverted: Option<Value>,
+    /// Follow-up run request while pending: `"requested"` or `{ "error" }`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_up: Option<Value>,
+}
+
+/// Verifies the canary `repo` given the `errors` of its update and its
+/// `commits`: with `gate` [`TierGate::Checks`], CI of the last commit must
+/// pass.  A `follow_up` run does not hand over again: CI still running
+/// counts as failed.
+pub async fn canary_gate(
+    config: &RolloutConfig,
+    gate: TierGate,
+    org: &str,
+    repo: &str,
+    errors: &[Value],
+    commits: Vec<String>,
+    follow_up: bool,
+) -> CanaryResult {
+    let started = Instant::now();
+    let (status, reason) = if errors.iter().any(|e| e["repo"] == repo) {
+        (
+            CanaryStatus::Failed,
+            Some("the canary update failed".to_string()),
+        )
+    } else if gate == TierGate::Checks
+        && let Some(last) = commits.last()
+    {
+        match wait_for_ci(config, org, &[(repo.to_string(), last.clone())]).await {
+            CiOutcome::Passed => (CanaryStatus::Passed, None),
+            CiOutcome::Running(reason) if !follow_up => (CanaryStatus::Pending, Some(reason)),
+            CiOutcome::Failed(reason) | CiOutcome::Running(reason) => {
+                (CanaryStatus::Failed, Some(reason))
+            }
+        }
+    } else {
+        (CanaryStatus::Passed, None)
+    };
+    match &reason {
+        None => info!(repo, "canary passed"),
+        Some(reason) => {
+            warn!(repo, status = ?status, reason = %reason, "canary did not pass — holding the other repos")
+        }
+    }
+    CanaryResult {
+        repo: repo.to_string(),
+        status,
+        reason,
+        commits,
+        waited_secs: started.elapsed().as_secs(),
+        reverted: None,
+        follow_up: None,
+    }
+}
+
+/// Metadata of the follow-up run that finishes verifying `canary`'s
+/// `commits`.
+pub fn follow_up_metadata(canary: &str, commits: &[String]) -> Value {
+    json!({ "canary": { "repo": canary, "commits": commits } })
+}
+
+/// Canary commits handed over by the run that requested this one
+/// (`metadata.canary`), when they are `canary`'s.
+pub fn handed_over(metadata: &Value, canary: &str) -> Vec<String> {
+    if metadata["canary"]["repo"] != canary {
+        return Vec::new();
+    }
+    metadata["canary"]["commits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|sha| sha.as_str().map(str::to_string))
+        .collect()
+}
+
+// ─── CI polling ───────────────────────────────────────────────────────────────
+
+/// CI status of one commit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CiState {
+    /// No check-runs registered (yet).
+    NoChecks,
+    /// Some check-runs still running.
+    Pending,
+    Passed,
+    /// Names of the failed check-runs.
+    Failed(Vec<String>),
+}
+
+/// CI status from a `commits/{sha}/check-runs` response, ignoring the
+/// agent's own verification check.
+pub fn ci_state(response: &Value) -> CiState {
+    let runs: Vec<&Value> = response["check_runs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|run| run["name"] != CHECK_NAME)
+        .collect();
+    let failed: Vec<String> = runs
+        .iter()
+        .filter(|run| {
+            run["status"] == "completed"
+                && !matches!(
+                    run["conclusion"].as_str(),
+                    Some("success" | "neutral" | "skipped")
+                )
+        })
+        .filter_map(|run| run["name"].as_str().map(str::to_string))
+        .collect();
+    if !failed.is_empty() {
+        CiState::Failed(failed)
+    } else if runs.is_empty() {
+        CiState::NoChecks
+    } else if runs.iter().any(|run| run["status"] != "completed") {
+        CiState::Pending
+    } else {
+        CiState::Passed
+    }
+}
+
+/// Result of waiting for CI.
+#[derive(Debug, Clone, PartialEq)]
+enum CiOutcome {
+    Passed,
+    Failed(String),
+    /// Still running at the timeout.
+    Running(String),
+}
+
+impl CiOutcome {
+    fn reason(self) -> Option<String> {
+        match self {
+            Self::Passed => None,
+            Self::Failed(reason) | Self::Running(reason) => Some(reason),
+        }
+    }
+}
+
+/// Polls CI of every `(repo, sha)` in `org` until all pass.  A commit
+/// still without check-runs at the timeout belongs to a repo without CI and
+/// passes.
+async fn wait_for_ci(config: &RolloutConfig, org: &str, commits: &[(String, String)]) -> CiOutcome {
+    let started = Instant::now();
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let interval = Duration::from_secs(config.interval_secs.max(1));
+    let mut waiting: Vec<(&str, &str, CiState)> = commits
+        .iter()
+        .map(|(repo, sha)| (repo.as_str(), sha.as_str(), CiState::NoChecks))
+        .collect();
+    loop {
+        for (repo, sha, state) in &mut waiting {
+            match commit_ci(org, repo, sha) {
+                Ok(CiState::Failed(names)) => {
+                    return CiOutcome::Failed(format!(
+                        "CI failed on {repo}@{sha}: {}",
+                        names.join(", ")
+                    ));
+                }
+                Ok(current) => *state = current,
+                Err(e) => {
+                    debug!(repo, sha, error = %e, "CI lookup failed — retrying");
+                    *state = CiState::Pending;
+                }
+            }
+        }
+        waiting.retain(|(_, _, state)| *state != CiState::Passed);
+        if waiting
+            .iter()
+            .all(|(_, _, state)| *state == CiState::NoChecks)
+            && (waiting.is_empty() || started.elapsed() + interval > timeout)
+        {
+            return CiOutcome::Passed;
+        }
+        if started.elapsed() + interval > timeout {
+            let running: Vec<&str> = waiting
+                .iter()
+                .filter(|(_, _, state)| *state == CiState::Pending)
+                .map(|(repo, _, _)| *repo)
+                .collect();
+            return CiOutcome::Running(format!(
+                "CI still running after {}s on {}",
+                started.elapsed().as_secs(),
+                running.join(", ")
+            ));
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+fn commit_ci(org: &str, repo: &str, sha: &str) -> Result<CiState> {
+    let text = gh_api(&[&format!("repos/{org}/{repo}/commits/{sha}/check-runs")])
+        .with_context(|| format!("list check-runs of {org}/{repo}@{sha}"))?;
+    let response: Value = serde_json::from_str(&text).context("parse check-runs response")?;
+    Ok(ci_state(&response))
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn repo(name: &str, depends_on: &[&str]) -> RepoConfig {
+        RepoConfig {
+            repo: name.to_string(),
+            depends_on: depends_on.iter().map(ToString::to_string).collect(),
+            ..RepoConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_tiers_follow_dependencies() {
+        let repos = [
+            repo("evo-kernel-agent-update", &["evo-king"]),
+            repo("evo-king", &["evo-agents"]),
+            repo("evo-agents", &[]),
+            repo("evo-user-agent-template", &["evo-agents"]),
+        ];
+        assert_eq!(
+            tiers(&repos).unwrap(),
+            vec![
+                vec!["evo-agents".to_string()],
+                vec![
+                    "evo-king".to_string(),
+                    "evo-user-agent-template".to_string()
+                ],
+                vec!["evo-kernel-agent-update".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tiers_reject_cycles_and_unknown_repos() {
+        let cycle = [repo("a", &["b"]), repo("b", &["a"]), repo("c", &[])];
+        let err = tiers(&cycle).unwrap_err().to_string();
+        assert!(err.contains("cycle through a, b"), "{err}");
+        assert!(tiers(&[repo("a", &["missing"])]).is_err());
+        assert!(tiers(&[repo("a", &["a"])]).is_err());
+    }
+
+    #[test]
+    fn test_ci_state() {
+        let run = |name: &str, status: &str, conclusion: Value| json!({ "name": name, "status": status, "conclusion": conclusion });
+        let response = |runs: Vec<Value>| json!({ "total_count": runs.len(), "check_runs": runs });
+        assert_eq!(ci_state(&response(vec![])), CiState::NoChecks);
+        assert_eq!(
+            ci_state(&response(vec![
+                run("test", "completed", json!("success")),
+                run("lint", "in_progress", Value::Null),
+            ])),
+            CiState::Pending
+        );
+        assert_eq!(
+            ci_state(&response(vec![
+                run("test", "completed", json!("success")),
+                run("docs", "completed", json!("skipped")),
+                run(CHECK_NAME, "completed", json!("failure")),
+            ])),
+            CiState::Passed
+        );
+        assert_eq!(
+            ci_state(&response(vec![
+                run("test", "completed", json!("failure")),
+                run("lint", "queued", Value::Null),
+            ])),
+            CiState::Failed(vec!["test".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_canary_hand_over() {
+        let commits = vec!["a1".to_string(), "b2".to_string()];
+        let metadata = follow_up_metadata("evo-king", &commits);
+        assert_eq!(handed_over(&metadata, "evo-king"), commits);
+        assert!(handed_over(&metadata, "evo-agents").is_empty());
+        assert!(handed_over(&json!({}), "evo-king").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_canary_gate_without_ci_wait() {
+        let config = RolloutConfig::default();
+        let errors = [json!({ "repo": "evo-king", "error": "push rejected" })];
+        let failed = canary_gate(
+            &config,
+            TierGate::Checks,
+            "o",
+            "evo-king",
+            &errors,
+            Vec::new(),
+            false,
+        )
+        .await;
+        assert_eq!(failed.status, CanaryStatus::Failed);
+        let passed = canary_gate(
+            &config,
+            TierGate::Errors,
+            "o",
+            "evo-king",
+            &[],
+            vec!["a1".to_string()],
+            false,
+        )
+        .await;
+        assert_eq!(passed.status, CanaryStatus::Passed);
+        assert_eq!(passed.reason, None);
+    }
+
+    #[test]
+    fn test_last_commits() {
+        let commit = |repo: &str, sha: Option<&str>| CommitRecord {
+            repo: repo.to_string(),
+            sha: sha.map(str::to_string),
+            pr_url: sha
+                .is_none()
+                .then(|| format!("https://github.com/o/{repo}/pull/1")),
+            ..Default::default()
+        };
+        let committed = [
+            commit("evo-king", Some("a1")),
+            commit("evo-agents", None),
+            commit("evo-king", Some("b2")),
+        ];
+        assert_eq!(
+            last_commits(&committed),
+            vec![("evo-king".to_string(), "b2".to_string())]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use std::fs::{File, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::state::state_dir;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// How often a waiting run retries the lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// ─── Run lock ─────────────────────────────────────────────────────────────────
+
+/// Exclusive lock (`flock`) on a state file, held for the duration of a run
+/// so two pipeline events never patch and push at the same time.
+///
+/// The lock is released when the value is dropped, or by the kernel if the
+/// process dies.
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// The lock file at `UPDATE_RUN_LOCK` (default `$UPDATE_STATE_DIR/run.lock`).
+    pub fn path_from_env() -> PathBuf {
+        std::env::var("UPDATE_RUN_LOCK")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| state_dir().join("run.lock"))
+    }
+
+    /// How long a run waits for the lock (`RUN_LOCK_WAIT_SECS`, default 0:
+    /// give up immediately).
+    pub fn wait_from_env() -> Duration {
+        std::env::var("RUN_LOCK_WAIT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Takes the lock at `path`, retrying for up to `wait`.
+    ///
+    /// Returns `Ok(None)` when another run still holds it after `wait`.
+    pub async fn acquire(path: &Path, run_id: &str, wait: Duration) -> Result<Option<Self>> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create run lock dir {}", parent.display()))?;
+        }
+        let mut file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("open run lock {}", path.display()))?;
+
+        let deadline = Instant::now() + wait;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(TryLockError::WouldBlock) => return Ok(None),
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("lock {}", path.display()));
+                }
+            }
+        }
+
+        // Record the holder for whoever inspects a stuck lock.
+        file.set_len(0)
+            .and_then(|()| writeln!(file, "{run_id}"))
+            .with_context(|| format!("write run lock {}", path.display()))?;
+        Ok(Some(Self { _file: file }))
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_second_run_is_refused_until_release() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state/run.lock");
+
+        let first = RunLock::acquire(&path, "run-1", Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(first.is_some());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "run-1\n");
+        assert!(
+            RunLock::acquire(&path, "run-2", Duration::ZERO)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        drop(first);
+        assert!(
+            RunLock::acquire(&path, "run-2", Duration::ZERO)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::state::state_dir;
+use crate::versions::needs_update;
+
+// ─── Run records ──────────────────────────────────────────────────────────────
+
+/// `repo → crate → version requirement` across the fleet.
+pub type VersionMatrix = BTreeMap<String, BTreeMap<String, String>>;
+
+/// What one run saw and left behind, kept for run-to-run comparisons.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    /// Unix seconds when the run finished.
+    pub timestamp: u64,
+    pub dry_run: bool,
+    /// Latest released version of each tracked crate.
+    pub latest: BTreeMap<String, String>,
+    /// Requirements in each repo after the run's direct commits.
+    pub matrix: VersionMatrix,
+}
+
+/// One [`RunRecord`] per file under `state_dir/runs`.
+#[derive(Debug, Clone)]
+pub struct RunHistory {
+    dir: PathBuf,
+}
+
+impl RunHistory {
+    /// A history stored in `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The history under [`state_dir`].
+    pub fn default_location() -> Self {
+        Self::new(&state_dir().join("runs"))
+    }
+
+    /// Stores `record` as `{run_id}.json`.
+    pub fn save(&self, record: &RunRecord) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create run history dir {}", self.dir.display()))?;
+        let path = self.dir.join(format!("{}.json", record.run_id));
+        let text = serde_json::to_string_pretty(record).context("serialize run record")?;
+        std::fs::write(&path, text).with_context(|| format!("write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Every stored record, oldest first.  Unreadable files are skipped.
+    pub fn list(&self) -> Vec<RunRecord> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut records: Vec<RunRecord> = entries
+            .filter_map(Result::ok)
+            .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+            .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+            .filter_map(|text| serde_json::from_str(&text).ok())
+            .collect();
+        records.sort_by(|a, b| (a.timestamp, &a.run_id).cmp(&(b.timestamp, &b.run_id)));
+        records
+    }
+}
+
+// ─── Run-to-run delta ─────────────────────────────────────────────────────────
+
+/// A repo/crate requirement that changed between two runs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Move {
+    pub repo: String,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// A repo/crate requirement still behind the latest release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Pending {
+    pub repo: String,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub current: String,
+    pub latest: String,
+}
+
+/// Fleet changes between a base run and a later run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunDelta {
+    pub base_run_id: String,
+    pub run_id: String,
+    /// Requirements that moved forward.
+    pub moved: Vec<Move>,
+    /// Requirements that went backwards (e.g. a revert or manual downgrade).
+    pub regressed: Vec<Move>,
+    /// Requirements still behind the later run's latest versions.
+    pub pending: Vec<Pending>,
+}
+
+/// Compares the fleet matrix of `base` with that of `run`.
+pub fn diff(base: &RunRecord, run: &RunRecord) -> RunDelta {
+    let mut moved: Vec<Move> = Vec::new();
+    let mut regressed: Vec<Move> = Vec::new();
+    let mut pending: Vec<Pending> = Vec::new();
+
+    for (repo, crates) in &run.matrix {
+        for (crate_name, to) in crates {
+            if let Some(from) = base.matrix.get(repo).and_then(|c| c.get(crate_name))
+                && from != to
+            {
+                let change = Move {
+                    repo: repo.clone(),
+                    crate_name: crate_name.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                };
+                if needs_update(to, from) {
+                    regressed.push(change);
+                } else {
+                    moved.push(change);
+                }
+            }
+            if let Some(latest) = run.latest.get(crate_name)
+                && needs_update(to, latest)
+            {
+                pending.push(Pending {
+                    repo: repo.clone(),
+                    crate_name: crate_name.clone(),
+                    current: to.clone(),
+                    latest: latest.clone(),
+                });
+            }
+        }
+    }
+
+    RunDelta {
+        base_run_id: base.run_id.clone(),
+        run_id: run.run_id.clone(),
+        moved,
+        regressed,
+        pending,
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(run_id: &str, timestamp: u64, rows: &[(&str, &str, &str)]) -> RunRecord {
+        let mut matrix = VersionMatrix::new();
+        for (repo, krate, version) in rows {
+            matrix
+                .entry(repo.to_string())
+                .or_default()
+                .insert(krate.to_string(), version.to_string());
+        }
+        RunRecord {
+            run_id: run_id.to_string(),
+            timestamp,
+            dry_run: false,
+            latest: BTreeMap::from([("evo-common".to_string(), "0.4.0".to_string())]),
+            matrix,
+        }
+    }
+
+    #[test]
+    fn test_diff_moved_regressed_pending() {
+        let base = record(
+            "r1",
+            1,
+            &[
+                ("evo-king", "evo-common", "0.3"),
+                ("evo-agents", "evo-common", "0.4.0"),
+                ("evo-learning", "evo-common", "0.3"),
+            ],
+        );
+        let run = record(
+            "r2",
+            2,
+            &[
+                ("evo-king", "evo-common", "0.4.0"),
+                ("evo-agents", "evo-common", "0.3"),
+                ("evo-learning", "evo-common", "0.3"),
+            ],
+        );
+        let delta = diff(&base, &run);
+        assert_eq!(delta.moved.len(), 1);
+        assert_eq!(delta.moved[0].repo, "evo-king");
+        assert_eq!(delta.regressed.len(), 1);
+        assert_eq!(delta.regressed[0].repo, "evo-agents");
+        let pending: Vec<&str> = delta.pending.iter().map(|p| p.repo.as_str()).collect();
+        assert_eq!(pending, vec!["evo-agents", "evo-learning"]);
+    }
+
+    #[test]
+    fn test_history_lists_oldest_first() {
+        let dir = TempDir::new().unwrap();
+        let history = RunHistory::new(dir.path());
+        history.save(&record("late", 20, &[])).unwrap();
+        history.save(&record("early", 10, &[])).unwrap();
+        let ids: Vec<String> = history.list().into_iter().map(|r| r.run_id).collect();
+        assert_eq!(ids, vec!["early", "late"]);
+    }
+}
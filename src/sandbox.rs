@@ -0,0 +1,296 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+use tracing::{info, warn};
+
+use crate::git::{CommandError, resolve_inside};
+use crate::net::apply_to_command;
+use crate::tokens::redact;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Only the tail of a command's output is kept in the summary.
+pub(crate) const MAX_OUTPUT_BYTES: usize = 4_000;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[sandbox]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Apply and verify every repo's updates in a clean clone before
+    /// committing (also enabled by `UPDATE_SANDBOX=1`).
+    pub enabled: bool,
+    /// Verification commands, run in order with `sh -c` at the clone's
+    /// root; a repo's `verify` list replaces these.
+    pub verify: Vec<String>,
+    /// Attach the verification result to every commit and PR the run
+    /// creates, as an `evo-update verification` check-run.
+    pub publish_checks: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            verify: vec!["cargo check --workspace --all-targets".to_string()],
+            publish_checks: true,
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// Whether the sandbox stage runs, from the config file or
+    /// `UPDATE_SANDBOX`.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+            || std::env::var("UPDATE_SANDBOX")
+                .is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+    }
+}
+
+/// Outcome of one verification command.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandResult {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    /// Tail of the combined stdout / stderr.
+    pub output: String,
+}
+
+// ─── Sandbox ──────────────────────────────────────────────────────────────────
+
+/// A throwaway clone of a repo checkout in which patches are applied and
+/// verified before anything is committed.
+///
+/// The content committed afterwards is read back from the sandbox, so what
+/// was verified and what is pushed are the same bytes.
+#[derive(Debug)]
+pub struct Sandbox {
+    _dir: TempDir,
+    root: PathBuf,
+}
+
+impl Sandbox {
+    /// Clones the committed state of the checkout at `local_base`.
+    pub fn clone_from(local_base: &Path) -> Result<Self> {
+        let dir = tempfile::tempdir().context("create sandbox dir")?;
+        let root = dir.path().join("repo");
+        let output = apply_to_command(&mut Command::new("git"))
+            .args(["clone", "--quiet", "--no-hardlinks"])
+            .arg(local_base)
+            .arg(&root)
+            .output()
+            .context("spawn git clone")?;
+        if !output.status.success() {
+            return Err(CommandError::from_output(
+                format!("git clone {}", local_base.display()),
+                &output,
+            )
+            .into());
+        }
+        Ok(Self { _dir: dir, root })
+    }
+
+    /// Root of the clone.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Reads `file` (repo-relative) from the clone.
+    pub fn read(&self, file: &str) -> Result<String> {
+        let path = self.path_of(file)?;
+        std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))
+    }
+
+    /// Writes `content` to `file` (repo-relative) in the clone.
+    pub fn write(&self, file: &str, content: &str) -> Result<()> {
+        let path = self.path_of(file)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create {}", parent.display()))?;
+        }
+        std::fs::write(&path, content).with_context(|| format!("write {}", path.display()))
+    }
+
+    /// Runs `cargo check`; `None` when it passes, the compiler output otherwise.
+    pub fn cargo_check(&self) -> Result<Option<String>> {
+        let output = apply_to_command(&mut Command::new("cargo"))
+            .args([
+                "check",
+                "--workspace",
+                "--all-targets",
+                "--quiet",
+                "--message-format",
+                "short",
+            ])
+            .current_dir(&self.root)
+            .output()
+            .context("run cargo check")?;
+        Ok(
+            (!output.status.success())
+                .then(|| String::from_utf8_lossy(&output.stderr).into_owned()),
+        )
+    }
+
+    /// Runs `commands` in order, stopping at the first failure.
+    pub fn verify(&self, commands: &[String]) -> Result<Vec<CommandResult>> {
+        let mut results: Vec<CommandResult> = Vec::new();
+        for command in commands {
+            let result = self.run(command, &self.root)?;
+            let success = result.success;
+            results.push(result);
+            if !success {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Runs `go mod tidy` next to the `go.mod` at `go_mod` (repo-relative),
+    /// updating its `go.sum`.
+    pub fn go_mod_tidy(&self, go_mod: &str) -> Result<CommandResult> {
+        let path = self.path_of(go_mod)?;
+        let dir = path.parent().unwrap_or(&self.root);
+        let mut result = self.run("go mod tidy", dir)?;
+        if dir != self.root {
+            result.command = format!("go mod tidy ({go_mod})");
+        }
+        Ok(result)
+    }
+
+    /// Runs `command` with `sh -c` in `dir`.
+    fn run(&self, command: &str, dir: &Path) -> Result<CommandResult> {
+        info!(command = %command, "running sandbox verification");
+        let output = apply_to_command(&mut Command::new("sh"))
+            .args(["-c", command])
+            .current_dir(dir)
+            .output()
+            .with_context(|| format!("spawn `{command}`"))?;
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        let success = output.status.success();
+        if !success {
+            warn!(command = %command, status = %output.status, "sandbox verification failed");
+        }
+        Ok(CommandResult {
+            command: command.to_string(),
+            success,
+            exit_code: output.status.code(),
+            output: redact(tail(&text, MAX_OUTPUT_BYTES)),
+        })
+    }
+
+    /// `root/file`, refusing paths that leave the clone — lexically or
+    /// through a symlink committed in the repo (see [`resolve_inside`]).
+    fn path_of(&self, file: &str) -> Result<PathBuf> {
+        anyhow::ensure!(!file.is_empty(), "{file:?} is not a path inside the repo");
+        Ok(resolve_inside(&self.root, file)?)
+    }
+}
+
+/// The last `max` bytes of `text`, on a character boundary.
+pub(crate) fn tail(text: &str, max: usize) -> &str {
+    let mut start = text.len().saturating_sub(max);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A local git repo with one committed `Cargo.toml`.
+    fn make_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "Test"]);
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        dir
+    }
+
+    #[test]
+    fn test_apply_and_verify_in_clone() {
+        let repo = make_repo();
+        let sandbox = Sandbox::clone_from(repo.path()).unwrap();
+        sandbox
+            .write("Cargo.toml", "[package]\nname = \"x\"\n")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("Cargo.toml")).unwrap(),
+            "[package]\n"
+        );
+
+        let results = sandbox
+            .verify(&[
+                "grep -q 'name = \"x\"' Cargo.toml".to_string(),
+                "echo broken >&2; exit 3".to_string(),
+                "echo never".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].success);
+        assert_eq!(results[1].exit_code, Some(3));
+        assert_eq!(results[1].output, "broken\n");
+    }
+
+    #[test]
+    fn test_paths_stay_inside_clone() {
+        let repo = make_repo();
+        let sandbox = Sandbox::clone_from(repo.path()).unwrap();
+        assert!(sandbox.write("../escape.toml", "").is_err());
+        assert!(sandbox.read("/etc/passwd").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_dir_cannot_leave_clone() {
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.toml"), "secret").unwrap();
+        let repo = make_repo();
+        std::os::unix::fs::symlink(outside.path(), repo.path().join("schemas")).unwrap();
+        let status = Command::new("git")
+            .args(["add", "schemas"])
+            .current_dir(repo.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = Command::new("git")
+            .args(["commit", "--quiet", "-m", "link"])
+            .current_dir(repo.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let sandbox = Sandbox::clone_from(repo.path()).unwrap();
+        assert!(sandbox.root().join("schemas").is_symlink());
+        assert!(sandbox.read("schemas/secret.toml").is_err());
+        assert!(sandbox.write("schemas/new.toml", "x").is_err());
+        assert!(!outside.path().join("new.toml").exists());
+        sandbox.write("Cargo.toml", "[package]\n").unwrap();
+    }
+
+    #[test]
+    fn test_tail_on_char_boundary() {
+        assert_eq!(tail("héllo", 4), "llo");
+        assert_eq!(tail("abc", 10), "abc");
+    }
+}
@@ -0,0 +1,377 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+use crate::state::state_dir;
+use crate::versions::needs_update;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// CycloneDX spec version emitted.
+const SPEC_VERSION: &str = "1.5";
+
+/// Sub-directory of the state dir holding one SBOM per managed repo.
+const SBOM_DIR: &str = "sbom";
+
+// ─── Lockfile model ───────────────────────────────────────────────────────────
+
+/// One `[[package]]` entry of a `Cargo.lock`.
+#[derive(Debug, Clone, PartialEq)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// Registry / git source; `None` for path and workspace members.
+    source: Option<String>,
+    /// sha256 of the `.crate` file (registry packages only).
+    checksum: Option<String>,
+    /// Raw dependency specs: `"name"`, `"name version"` or `"name version (source)"`.
+    dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    fn purl(&self) -> String {
+        purl(&self.name, &self.version)
+    }
+}
+
+/// Parses the `[[package]]` entries of a `Cargo.lock`.
+fn parse_lockfile(content: &str) -> Result<Vec<LockedPackage>> {
+    let doc: DocumentMut = content.parse().context("parse Cargo.lock")?;
+    let Some(packages) = doc.get("package").and_then(|p| p.as_array_of_tables()) else {
+        return Ok(Vec::new());
+    };
+    let str_field =
+        |t: &toml_edit::Table, key: &str| t.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    Ok(packages
+        .iter()
+        .filter_map(|t| {
+            Some(LockedPackage {
+                name: str_field(t, "name")?,
+                version: str_field(t, "version")?,
+                source: str_field(t, "source"),
+                checksum: str_field(t, "checksum"),
+                dependencies: t
+                    .get("dependencies")
+                    .and_then(|d| d.as_array())
+                    .map(|a| {
+                        a.iter()
+                            .filter_map(|v| v.as_str().map(str::to_string))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/// Highest registry version of `crate_name` locked in `lockfile`.
+pub fn locked_version(lockfile: &str, crate_name: &str) -> Option<String> {
+    parse_lockfile(lockfile)
+        .ok()?
+        .into_iter()
+        .filter(|p| p.name == crate_name && p.source.is_some())
+        .map(|p| p.version)
+        .reduce(|best, v| if needs_update(&best, &v) { v } else { best })
+}
+
+/// Whether `lockfile` locks `crate_name` at `version`.
+pub fn is_locked(lockfile: &str, crate_name: &str, version: &str) -> bool {
+    parse_lockfile(lockfile).is_ok_and(|packages| {
+        packages
+            .iter()
+            .any(|p| p.name == crate_name && p.version == version)
+    })
+}
+
+/// Registry and git packages of `after` whose name does not occur in
+/// `before`, as `(name, version)` pairs in lockfile order.
+pub fn new_packages(before: &str, after: &str) -> Result<Vec<(String, String)>> {
+    let before = parse_lockfile(before)?;
+    Ok(parse_lockfile(after)?
+        .into_iter()
+        .filter(|p| p.source.is_some() && !before.iter().any(|b| b.name == p.name))
+        .map(|p| (p.name, p.version))
+        .collect())
+}
+
+/// A crate a repo's resolved lockfile pulls in for the first time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewDependency {
+    pub repo: String,
+    /// Repo-relative path of the lockfile.
+    pub lockfile: String,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub version: String,
+    /// Account that published the version, when the registry says.
+    pub publisher: Option<String>,
+}
+
+// ─── SBOM generation ──────────────────────────────────────────────────────────
+
+/// Builds a CycloneDX JSON SBOM for `repo`.
+///
+/// With a `Cargo.lock` every locked package becomes a component (with its
+/// sha256 when known) and the lock's dependency edges are recorded.  Without
+/// one, only the manifest's direct dependencies are listed, using their
+/// version requirements.
+pub fn generate(repo: &str, manifest: &str, lockfile: Option<&str>, run_id: &str) -> Result<Value> {
+    let doc: DocumentMut = manifest.parse().context("parse Cargo.toml")?;
+    let root_name = doc
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|v| v.as_str())
+        .unwrap_or(repo)
+        .to_string();
+    let root_version = doc
+        .get("package")
+        .and_then(|p| p.get("version"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("0.0.0")
+        .to_string();
+    let root_ref = purl(&root_name, &root_version);
+
+    let mut components: Vec<Value> = Vec::new();
+    let mut dependencies: Vec<Value> = Vec::new();
+
+    match lockfile {
+        Some(lock) => {
+            let packages = parse_lockfile(lock)?;
+            for pkg in &packages {
+                let depends_on: Vec<String> = pkg
+                    .dependencies
+                    .iter()
+                    .filter_map(|spec| resolve_dep(&packages, spec))
+                    .map(LockedPackage::purl)
+                    .collect();
+                dependencies.push(json!({ "ref": pkg.purl(), "dependsOn": depends_on }));
+                if pkg.purl() == root_ref {
+                    continue;
+                }
+                let mut component = json!({
+                    "type": "library",
+                    "bom-ref": pkg.purl(),
+                    "name": pkg.name,
+                    "version": pkg.version,
+                    "purl": pkg.purl(),
+                });
+                if let Some(ref sum) = pkg.checksum {
+                    component["hashes"] = json!([{ "alg": "SHA-256", "content": sum }]);
+                }
+                if let Some(ref source) = pkg.source {
+                    component["properties"] = json!([{ "name": "cargo:source", "value": source }]);
+                }
+                components.push(component);
+            }
+        }
+        None => {
+            for (name, req) in direct_dependencies(&doc) {
+                components.push(json!({
+                    "type": "library",
+                    "bom-ref": format!("pkg:cargo/{name}"),
+                    "name": name,
+                    "version": req,
+                    "purl": format!("pkg:cargo/{name}"),
+                    "properties": [{ "name": "cargo:version_req", "value": req }],
+                }));
+            }
+        }
+    }
+
+    Ok(json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": SPEC_VERSION,
+        "version": 1,
+        "metadata": {
+            "tools": [{ "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") }],
+            "component": {
+                "type": "application",
+                "bom-ref": root_ref,
+                "name": root_name,
+                "version": root_version,
+            },
+            "properties": [
+                { "name": "evo:repo", "value": repo },
+                { "name": "evo:run_id", "value": run_id },
+                { "name": "evo:lockfile", "value": lockfile.is_some().to_string() },
+            ],
+        },
+        "components": components,
+        "dependencies": dependencies,
+    }))
+}
+
+/// Writes `sbom` to `$UPDATE_STATE_DIR/sbom/{repo}.cdx.json` and returns the path.
+pub fn store(repo: &str, sbom: &Value) -> Result<PathBuf> {
+    let dir = state_dir().join(SBOM_DIR);
+    std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+    let path = dir.join(format!("{repo}.cdx.json"));
+    let text = serde_json::to_string_pretty(sbom).context("serialize SBOM")?;
+    std::fs::write(&path, text).with_context(|| format!("write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Finds the `Cargo.lock` governing `manifest_path`: next to the manifest,
+/// otherwise at the repo root (workspace lockfile).
+pub fn find_lockfile(repo_base: &Path, manifest_path: &Path) -> Option<PathBuf> {
+    manifest_path
+        .parent()
+        .map(|dir| dir.join("Cargo.lock"))
+        .into_iter()
+        .chain(std::iter::once(repo_base.join("Cargo.lock")))
+        .find(|p| p.is_file())
+}
+
+// ─── Helpers ──────────────────────────────────────────────────────────────────
+
+/// Package URL for a crates.io package.
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:cargo/{name}@{version}")
+}
+
+/// Resolves a lockfile dependency spec to the package it names.
+fn resolve_dep<'a>(packages: &'a [LockedPackage], spec: &str) -> Option<&'a LockedPackage> {
+    let mut parts = spec.split_whitespace();
+    let name = parts.next()?;
+    match parts.next() {
+        Some(version) => packages
+            .iter()
+            .find(|p| p.name == name && p.version == version),
+        None => packages.iter().find(|p| p.name == name),
+    }
+}
+
+/// Direct dependencies (all dependency tables) with their version requirement.
+fn direct_dependencies(doc: &DocumentMut) -> Vec<(String, String)> {
+    let mut deps: Vec<(String, String)> = Vec::new();
+    for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let Some(table) = doc.get(section).and_then(|t| t.as_table_like()) else {
+            continue;
+        };
+        for (name, item) in table.iter() {
+            let req = item
+                .as_str()
+                .or_else(|| item.get("version").and_then(|v| v.as_str()))
+                .unwrap_or("*");
+            if !deps.iter().any(|(n, _)| n == name) {
+                deps.push((name.to_string(), req.to_string()));
+            }
+        }
+    }
+    deps
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "evo-king"
+version = "0.2.0"
+
+[dependencies]
+evo-common = "0.4"
+serde = { version = "1", features = ["derive"] }
+"#;
+
+    const LOCK: &str = r#"
+version = 3
+
+[[package]]
+name = "evo-common"
+version = "0.4.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "abc123"
+dependencies = [
+ "serde",
+]
+
+[[package]]
+name = "evo-king"
+version = "0.2.0"
+dependencies = [
+ "evo-common",
+ "serde 1.0.200",
+]
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "def456"
+"#;
+
+    #[test]
+    fn test_parse_lockfile() {
+        let packages = parse_lockfile(LOCK).unwrap();
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0].checksum.as_deref(), Some("abc123"));
+        assert_eq!(packages[1].source, None);
+        assert_eq!(
+            packages[1].dependencies,
+            vec!["evo-common", "serde 1.0.200"]
+        );
+    }
+
+    #[test]
+    fn test_locked_version() {
+        assert_eq!(locked_version(LOCK, "serde").as_deref(), Some("1.0.200"));
+        // Workspace members are not registry packages.
+        assert_eq!(locked_version(LOCK, "evo-king"), None);
+        assert_eq!(locked_version(LOCK, "tokio"), None);
+    }
+
+    #[test]
+    fn test_new_packages() {
+        let after = format!(
+            "{LOCK}\n[[package]]\nname = \"serde\"\nversion = \"0.9.15\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"tokio\"\nversion = \"1.40.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"evo-tools\"\nversion = \"0.1.0\"\n"
+        );
+        assert_eq!(
+            new_packages(LOCK, &after).unwrap(),
+            vec![("tokio".to_string(), "1.40.0".to_string())]
+        );
+        assert!(new_packages(LOCK, LOCK).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_generate_from_lockfile() {
+        let sbom = generate("evo-king", MANIFEST, Some(LOCK), "run-1").unwrap();
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        assert_eq!(
+            sbom["metadata"]["component"]["bom-ref"],
+            "pkg:cargo/evo-king@0.2.0"
+        );
+
+        let components = sbom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2, "root package is not a component");
+        assert_eq!(components[0]["purl"], "pkg:cargo/evo-common@0.4.0");
+        assert_eq!(components[0]["hashes"][0]["content"], "abc123");
+
+        let root_deps = sbom["dependencies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["ref"] == "pkg:cargo/evo-king@0.2.0")
+            .unwrap();
+        assert_eq!(
+            root_deps["dependsOn"],
+            json!(["pkg:cargo/evo-common@0.4.0", "pkg:cargo/serde@1.0.200"])
+        );
+    }
+
+    #[test]
+    fn test_generate_without_lockfile_lists_direct_deps() {
+        let sbom = generate("evo-king", MANIFEST, None, "run-1").unwrap();
+        let components = sbom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0]["name"], "evo-common");
+        assert_eq!(components[0]["version"], "0.4");
+        assert_eq!(components[1]["version"], "1");
+        assert!(sbom["dependencies"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::checksum::sha256_hex;
+use crate::state::state_dir;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Everything a repo's Phase 2 scan depends on.  A repo whose fingerprint
+/// matches the one recorded when it last scanned clean needs no rescan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoFingerprint {
+    /// `config_version` of the run.
+    pub config_version: String,
+    /// Latest version of each crate tracked for the repo.
+    pub latest: BTreeMap<String, String>,
+    /// sha256 of each scanned file; missing files are left out.
+    pub files: BTreeMap<String, String>,
+}
+
+impl RepoFingerprint {
+    /// Fingerprints `files` under `repo_base` against `latest`.
+    pub fn compute(
+        config_version: &str,
+        latest: BTreeMap<String, String>,
+        repo_base: &Path,
+        files: &[String],
+    ) -> Self {
+        let files = files
+            .iter()
+            .filter_map(|file| {
+                let bytes = std::fs::read(repo_base.join(file)).ok()?;
+                Some((file.clone(), sha256_hex(&bytes)))
+            })
+            .collect();
+        Self {
+            config_version: config_version.to_string(),
+            latest,
+            files,
+        }
+    }
+}
+
+// ─── Storage ──────────────────────────────────────────────────────────────────
+
+/// Fingerprints of the repos whose last scan found nothing to do, kept as
+/// one JSON file under [`state_dir`].
+#[derive(Debug, Clone)]
+pub struct ScanState {
+    path: PathBuf,
+    repos: BTreeMap<String, RepoFingerprint>,
+}
+
+impl ScanState {
+    /// File name of the state inside the state dir.
+    pub const FILE_NAME: &'static str = "scan-state.json";
+
+    /// Default location inside [`state_dir`].
+    pub fn default_path() -> PathBuf {
+        state_dir().join(Self::FILE_NAME)
+    }
+
+    /// Loads the state at `path`; a missing file yields an empty state.
+    pub fn load(path: &Path) -> Result<Self> {
+        let repos = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parse scan state {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("read scan state {}", path.display()));
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            repos,
+        })
+    }
+
+    /// An empty state that will be saved to `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            repos: BTreeMap::new(),
+        }
+    }
+
+    /// Whether `repo` last scanned clean with exactly `fingerprint`.
+    pub fn is_settled(&self, repo: &str, fingerprint: &RepoFingerprint) -> bool {
+        self.repos.get(repo) == Some(fingerprint)
+    }
+
+    /// Records that `repo` scanned clean with `fingerprint`.
+    pub fn settle(&mut self, repo: &str, fingerprint: RepoFingerprint) {
+        self.repos.insert(repo.to_string(), fingerprint);
+    }
+
+    /// Forgets `repo`, so its next scan is a full one.
+    pub fn unsettle(&mut self, repo: &str) {
+        self.repos.remove(repo);
+    }
+
+    /// Writes the state back to disk, creating the state dir if needed.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text = serde_json::to_string_pretty(&self.repos).context("serialize scan state")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write scan state {}", self.path.display()))
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fingerprint_tracks_files_versions_and_config() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+        let files = vec!["Cargo.toml".to_string(), "missing.yml".to_string()];
+        let latest = BTreeMap::from([("evo-common".to_string(), "0.4.0".to_string())]);
+        let fingerprint = RepoFingerprint::compute("builtin", latest.clone(), dir.path(), &files);
+        assert_eq!(fingerprint.files.len(), 1);
+
+        let path = dir.path().join("state/scan-state.json");
+        let mut state = ScanState::load(&path).unwrap();
+        state.settle("evo-king", fingerprint.clone());
+        state.save().unwrap();
+        let state = ScanState::load(&path).unwrap();
+        assert!(state.is_settled("evo-king", &fingerprint));
+        assert!(!state.is_settled("evo-runner", &fingerprint));
+
+        let bumped = BTreeMap::from([("evo-common".to_string(), "0.5.0".to_string())]);
+        let newer = RepoFingerprint::compute("builtin", bumped, dir.path(), &files);
+        assert!(!state.is_settled("evo-king", &newer));
+        let reconfigured = RepoFingerprint::compute("sha256:1", latest.clone(), dir.path(), &files);
+        assert!(!state.is_settled("evo-king", &reconfigured));
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        let edited = RepoFingerprint::compute("builtin", latest, dir.path(), &files);
+        assert!(!state.is_settled("evo-king", &edited));
+    }
+}
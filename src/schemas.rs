@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::checksum::sha256_hex;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[schemas]` section of the config file: where the canonical protobuf /
+/// JSON Schema files live.  Repos receive them under their `schema_dir`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchemaConfig {
+    /// Repo publishing the canonical files.
+    pub source_repo: String,
+    /// Directory of the files inside the source repo's checkout.
+    pub source_dir: String,
+    /// Extensions (without the dot) of the files that are propagated.
+    pub extensions: Vec<String>,
+}
+
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        Self {
+            source_repo: "evo-common".to_string(),
+            source_dir: "schemas".to_string(),
+            extensions: vec!["proto".to_string(), "json".to_string()],
+        }
+    }
+}
+
+impl SchemaConfig {
+    /// Whether `path` has one of the propagated extensions.
+    fn is_schema(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|e| e == ext))
+    }
+}
+
+// ─── Canonical files ──────────────────────────────────────────────────────────
+
+/// The canonical schema files of one run, keyed by their path relative to
+/// the source directory (`/`-separated).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaSet {
+    pub files: BTreeMap<String, String>,
+}
+
+impl SchemaSet {
+    /// Reads every schema file under `source_root/source_dir`, recursively;
+    /// hidden files and directories are skipped.
+    pub fn load(source_root: &Path, config: &SchemaConfig) -> Result<Self> {
+        let dir = source_root.join(&config.source_dir);
+        anyhow::ensure!(
+            dir.is_dir(),
+            "{} has no schema directory {}",
+            config.source_repo,
+            config.source_dir
+        );
+        let mut files = BTreeMap::new();
+        collect(&dir, "", config, &mut files)?;
+        Ok(Self { files })
+    }
+
+    /// sha256 over every path and content, so a repo's scan fingerprint
+    /// changes whenever the canonical set does.
+    pub fn digest(&self) -> String {
+        let mut all = String::new();
+        for (path, content) in &self.files {
+            all.push_str(path);
+            all.push('\0');
+            all.push_str(&sha256_hex(content.as_bytes()));
+            all.push('\n');
+        }
+        sha256_hex(all.as_bytes())
+    }
+
+    /// Repo-relative path of each canonical file under `schema_dir`.
+    pub fn targets(&self, schema_dir: &str) -> Vec<String> {
+        self.files
+            .keys()
+            .map(|path| target_path(schema_dir, path))
+            .collect()
+    }
+
+    /// The copies under `schema_dir` in the checkout at `repo_base` that
+    /// differ from the canonical files, as `(path, current, canonical)`;
+    /// a missing copy has empty current content.
+    pub fn drift(&self, repo_base: &Path, schema_dir: &str) -> Vec<(String, String, String)> {
+        self.files
+            .iter()
+            .filter_map(|(path, canonical)| {
+                let target = target_path(schema_dir, path);
+                let current = std::fs::read_to_string(repo_base.join(&target)).unwrap_or_default();
+                (current != *canonical).then(|| (target, current, canonical.clone()))
+            })
+            .collect()
+    }
+}
+
+/// `schema_dir/path`, without doubled or leading slashes.
+fn target_path(schema_dir: &str, path: &str) -> String {
+    let dir = schema_dir.trim_matches('/');
+    if dir.is_empty() || dir == "." {
+        path.to_string()
+    } else {
+        format!("{dir}/{path}")
+    }
+}
+
+fn collect(
+    dir: &Path,
+    prefix: &str,
+    config: &SchemaConfig,
+    files: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("read {}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let relative = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+        if path.is_dir() {
+            collect(&path, &relative, config, files)?;
+        } else if config.is_schema(&path) {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("read {}", path.display()))?;
+            files.insert(relative, content);
+        }
+    }
+    Ok(())
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_drift() {
+        let source = tempfile::tempdir().unwrap();
+        let schemas = source.path().join("schemas");
+        std::fs::create_dir_all(schemas.join("events/.cache")).unwrap();
+        std::fs::write(schemas.join("agent.proto"), "syntax = \"proto3\";\n").unwrap();
+        std::fs::write(schemas.join("events/run.json"), "{}\n").unwrap();
+        std::fs::write(schemas.join("events/.cache/x.json"), "{}\n").unwrap();
+        std::fs::write(schemas.join("README.md"), "# schemas\n").unwrap();
+
+        let set = SchemaSet::load(source.path(), &SchemaConfig::default()).unwrap();
+        assert_eq!(
+            set.files.keys().collect::<Vec<_>>(),
+            vec!["agent.proto", "events/run.json"]
+        );
+        assert_eq!(
+            set.targets("/proto/"),
+            vec!["proto/agent.proto", "proto/events/run.json"]
+        );
+
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("proto")).unwrap();
+        std::fs::write(
+            repo.path().join("proto/agent.proto"),
+            "syntax = \"proto3\";\n",
+        )
+        .unwrap();
+        assert_eq!(
+            set.drift(repo.path(), "proto"),
+            vec![(
+                "proto/events/run.json".to_string(),
+                String::new(),
+                "{}\n".to_string()
+            )]
+        );
+
+        let mut changed = set.clone();
+        changed
+            .files
+            .insert("agent.proto".into(), "syntax = \"proto2\";\n".into());
+        assert_ne!(changed.digest(), set.digest());
+    }
+
+    #[test]
+    fn test_missing_source_dir() {
+        let source = tempfile::tempdir().unwrap();
+        let err = SchemaSet::load(source.path(), &SchemaConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("no schema directory schemas"));
+    }
+}
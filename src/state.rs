@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ─── State directory ──────────────────────────────────────────────────────────
+
+/// Directory holding the agent's persistent state (`UPDATE_STATE_DIR`,
+/// default `.evo-update` in the working directory).
+pub fn state_dir() -> PathBuf {
+    std::env::var("UPDATE_STATE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(".evo-update"))
+}
+
+/// Current time as unix seconds.
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+pub fn today_utc() -> String {
+    date_from_unix(now_secs())
+}
+
+/// UTC calendar date (`YYYY-MM-DD`) of a unix timestamp.
+pub fn date_from_unix(secs: u64) -> String {
+    // Howard Hinnant's civil-from-days algorithm.
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Unix timestamp of a UTC RFC 3339 time such as GitHub's
+/// `2024-06-10T14:03:00Z`; fractional seconds are dropped.
+pub fn unix_from_rfc3339(time: &str) -> Option<u64> {
+    let time = time.strip_suffix('Z')?;
+    let (date, clock) = time.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let clock = clock.split('.').next()?;
+    let mut clock = clock.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+    // Howard Hinnant's days-from-civil algorithm.
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400 + hour * 3_600 + minute * 60 + second).ok()
+}
+
+// ─── Version cache ────────────────────────────────────────────────────────────
+
+/// A crate version as last resolved from the registry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedVersion {
+    /// Latest stable version at `fetched_at`.
+    pub version: String,
+    /// Unix seconds when the version was fetched.
+    pub fetched_at: u64,
+}
+
+/// On-disk cache of the latest versions seen on crates.io.
+///
+/// Written after every successful online lookup so offline runs
+/// (`OFFLINE=1`) can resolve versions without network access, and so
+/// actions started within [`VersionCache::ttl_from_env`] of each other
+/// act on the same versions.
+#[derive(Debug, Clone)]
+pub struct VersionCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CachedVersion>,
+}
+
+impl VersionCache {
+    /// File name of the cache inside the state dir.
+    pub const FILE_NAME: &'static str = "versions.json";
+
+    /// An empty cache that will be saved to `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Default TTL of a cached version for online runs.
+    pub const DEFAULT_TTL_SECS: u64 = 300;
+
+    /// How long online runs reuse a cached version instead of querying the
+    /// registry (`VERSION_CACHE_TTL_SECS`, `0` disables reuse).
+    pub fn ttl_from_env() -> u64 {
+        std::env::var("VERSION_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(Self::DEFAULT_TTL_SECS)
+    }
+
+    /// Default cache location inside [`state_dir`].
+    pub fn default_path() -> PathBuf {
+        state_dir().join(Self::FILE_NAME)
+    }
+
+    /// Loads the cache at `path`; a missing file yields an empty cache.
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("parse version cache {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("read version cache {}", path.display()));
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Cached entry for `crate_name`, if any.
+    pub fn get(&self, crate_name: &str) -> Option<&CachedVersion> {
+        self.entries.get(crate_name)
+    }
+
+    /// Cached entry for `crate_name` fetched less than `ttl` seconds
+    /// before `now`.
+    pub fn fresh(&self, crate_name: &str, now: u64, ttl: u64) -> Option<&CachedVersion> {
+        self.get(crate_name)
+            .filter(|cached| now.saturating_sub(cached.fetched_at) < ttl)
+    }
+
+    /// Records `version` for `crate_name` as fetched at `fetched_at`.
+    pub fn insert(&mut self, crate_name: &str, version: &str, fetched_at: u64) {
+        self.entries.insert(
+            crate_name.to_string(),
+            CachedVersion {
+                version: version.to_string(),
+                fetched_at,
+            },
+        );
+    }
+
+    /// Writes the cache back to disk, creating the state dir if needed.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create state dir {}", parent.display()))?;
+        }
+        let text =
+            serde_json::to_string_pretty(&self.entries).context("serialize version cache")?;
+        std::fs::write(&self.path, text)
+            .with_context(|| format!("write version cache {}", self.path.display()))
+    }
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unix_from_rfc3339() {
+        assert_eq!(unix_from_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            unix_from_rfc3339("2024-06-10T14:03:00Z"),
+            Some(1_718_028_180)
+        );
+        assert_eq!(
+            unix_from_rfc3339("2024-02-29T00:00:01.500Z"),
+            Some(1_709_164_801)
+        );
+        assert_eq!(
+            date_from_unix(unix_from_rfc3339("2000-03-01T12:00:00Z").unwrap()),
+            "2000-03-01"
+        );
+        assert_eq!(unix_from_rfc3339("2024-06-10 14:03:00"), None);
+    }
+
+    #[test]
+    fn test_date_from_unix() {
+        assert_eq!(date_from_unix(0), "1970-01-01");
+        assert_eq!(date_from_unix(1_700_000_000), "2023-11-14");
+        assert_eq!(date_from_unix(951_782_400), "2000-02-29");
+    }
+
+    #[test]
+    fn test_version_cache_missing_file_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let cache = VersionCache::load(&dir.path().join("versions.json")).unwrap();
+        assert!(cache.get("evo-common").is_none());
+    }
+
+    #[test]
+    fn test_version_cache_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/versions.json");
+        let mut cache = VersionCache::load(&path).unwrap();
+        cache.insert("evo-common", "0.4.0", 1_700_000_000);
+        cache.save().unwrap();
+
+        let reloaded = VersionCache::load(&path).unwrap();
+        assert_eq!(
+            reloaded.get("evo-common"),
+            Some(&CachedVersion {
+                version: "0.4.0".to_string(),
+                fetched_at: 1_700_000_000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_version_cache_fresh_within_ttl() {
+        let dir = TempDir::new().unwrap();
+        let mut cache = VersionCache::new(&dir.path().join("versions.json"));
+        cache.insert("evo-common", "0.4.0", 1_000);
+        assert!(cache.fresh("evo-common", 1_299, 300).is_some());
+        assert!(cache.fresh("evo-common", 1_300, 300).is_none());
+        assert!(cache.fresh("evo-common", 1_000, 0).is_none());
+        assert!(cache.fresh("evo-agent-sdk", 1_000, 300).is_none());
+    }
+
+    #[test]
+    fn test_version_cache_corrupt_file_errors() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("versions.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(VersionCache::load(&path).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use schemars::{JsonSchema, Schema, schema_for};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+use crate::config::CommitGranularity;
+use crate::diff::DiffStats;
+use crate::metadata::CrateLinks;
+use crate::policy::AutoApply;
+use crate::versions::VersionReport;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A direct commit or grouped PR of a run; in a dry run, one that would
+/// have been made.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct CommitRecord {
+    pub repo: String,
+    /// File of a direct commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Files of a grouped PR.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// `GhCli`, `LocalGit`, `RestApi` or `PullRequest`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pr_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pr_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft: Option<bool>,
+    /// Review comments posted on the PR, or `{ "error" }`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff_stats: Option<CommitDiffStats>,
+    /// Version bumps carried, each with its `bump_kind`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bumps: Vec<VersionReport>,
+    /// Whether the sandbox verified exactly the delivered content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    /// Custom push target of a local-git commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pushed_to: Option<String>,
+    /// Reverted after its canary failed.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub reverted: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Delivery a dry run would have used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_granularity: Option<CommitGranularity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<String>,
+}
+
+/// Diff statistics of a direct commit, or of every file of a PR.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum CommitDiffStats {
+    File(DiffStats),
+    Files(BTreeMap<String, DiffStats>),
+}
+
+/// Why an update was held instead of delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldReason {
+    CargoAudit,
+    SandboxVerificationFailed,
+    CargoCheckFailed,
+    UpstreamTierFailed,
+    CanaryFailed,
+    CanaryPending,
+    LlmRisk,
+    LlmMigrationNeedsPr,
+    /// The update fixes no advisory and the run applies security fixes only.
+    SecurityOnly,
+    /// `cargo vet` found crates the update introduces without an audit.
+    UnvettedCrates,
+    /// The run lease was lost before the repo was delivered.
+    LeaseLost,
+}
+
+/// An update held for review.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct HeldUpdate {
+    pub repo: String,
+    pub file: String,
+    pub reason: HoldReason,
+    /// Reason-specific details (`bumps`, `tier`, `detail`, `introduced`, …).
+    #[serde(flatten)]
+    pub details: Map<String, Value>,
+}
+
+/// Outcome of one repo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoStatus {
+    /// Everything proposed was delivered.
+    Updated,
+    /// Some updates were delivered, others held or failed.
+    Partial,
+    /// Nothing was delivered and updates were held.
+    Held,
+    /// Nothing was delivered and updates failed.
+    Failed,
+    /// The run had nothing to deliver.
+    Unchanged,
+}
+
+/// What the run did to one managed repo.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct RepoResult {
+    pub repo: String,
+    pub status: RepoStatus,
+    /// Files committed directly or included in a PR.
+    pub delivered: usize,
+    pub commits: Vec<String>,
+    pub pull_requests: Vec<String>,
+    pub held: usize,
+    pub errors: usize,
+}
+
+/// The JSON summary a run returns.  Sections listed as free-form carry the
+/// shapes documented in `docs/update-agent.md`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub resumed_from: Option<String>,
+    pub plan: Option<Value>,
+    pub applied_plan: Option<String>,
+    pub approval_question: Option<Value>,
+    pub dry_run: bool,
+    pub offline: bool,
+    pub config_version: String,
+    pub config_error: Option<String>,
+    pub version_freshness: Value,
+    pub version_lookups: Value,
+    pub commit_mode: String,
+    pub commit_granularity: CommitGranularity,
+    pub auto_apply: AutoApply,
+    pub environment: Value,
+    pub checkouts: Value,
+    /// Latest version of each tracked crate.
+    pub versions: BTreeMap<String, String>,
+    /// Repository, homepage and documentation links of each tracked crate.
+    pub crate_links: BTreeMap<String, CrateLinks>,
+    /// Tracked crates of each repo.
+    pub repo_crates: BTreeMap<String, Vec<String>>,
+    pub pending_updates: usize,
+    pub repos: Vec<RepoResult>,
+    pub committed: Vec<CommitRecord>,
+    pub errors: Vec<Value>,
+    pub rejected: Vec<Value>,
+    /// Patched manifests cargo could not check, with the `reason`.
+    pub unverified_manifests: Vec<Value>,
+    pub checksum_failures: Vec<Value>,
+    pub audit: Vec<Value>,
+    /// OSV findings of each bump, or the query `error`.
+    pub osv: Vec<Value>,
+    pub held_for_review: Vec<HeldUpdate>,
+    pub workspace_conflicts: Vec<Value>,
+    pub patch_overrides: Vec<Value>,
+    pub frozen: Vec<Value>,
+    pub skipped_by_policy: Vec<Value>,
+    pub suppressed_by_repo: Vec<Value>,
+    pub full_scan: bool,
+    pub targeted_repos: Option<Vec<String>>,
+    pub unchanged_repos: Vec<String>,
+    pub workflow_patterns: Vec<Value>,
+    pub release_bumps: Vec<Value>,
+    /// Canonical schema files and the copies that were synced from them.
+    pub schema_sync: Value,
+    /// `[[file_sync]]` campaigns with their canonical files, or the `error`.
+    pub file_sync: Vec<Value>,
+    /// Copies that differ from their campaign's canonical files.
+    pub file_drift: Vec<Value>,
+    /// Files of `from_template` repos that drifted from the template.
+    pub template_drift: Vec<Value>,
+    /// PRs applying the template to drifted repos.
+    pub template_prs: Vec<Value>,
+    /// Canonical workflow templates, the workflows rendered from them and
+    /// any that `failed` to render.
+    pub workflow_sync: Value,
+    /// The `[license]` policy in effect and its canonical license texts.
+    pub license: Value,
+    /// Source files without the required SPDX header and license texts
+    /// that differ from the canonical ones.
+    pub license_drift: Vec<Value>,
+    pub publish_waits: Value,
+    pub publish_stuck: Value,
+    /// Unacknowledged owner / publisher changes; their crates are held.
+    pub ownership_changes: Value,
+    pub rollout_tiers: Value,
+    pub canary: Value,
+    pub fleet_consistent: bool,
+    pub fleet_divergence: Value,
+    pub sboms: Vec<Value>,
+    pub github_quota: Value,
+    pub config_synced: bool,
+    pub analysis_summary: String,
+    pub migration_notes: Value,
+    /// Commit-log summary of each bumped crate.
+    pub upstream_changes: Value,
+    pub migrations: Vec<Value>,
+    pub sandbox: Vec<Value>,
+    /// Crates the verified lockfiles pull in for the first time.
+    pub new_dependencies: Value,
+    pub risk_assessment: Value,
+    pub provenance: Value,
+    pub lease: Value,
+    pub badges: Value,
+    pub checks: Value,
+    /// Slack, Discord and webhook notifications of the delivered changes,
+    /// `{ "target", "ok" }` or `{ "target", "error" }` each.
+    pub notifications: Vec<Value>,
+    pub retried: Value,
+    pub retry_queue: Value,
+    pub fleet_tag: Value,
+    pub release: Value,
+    pub timings: Value,
+    /// Estimated tokens and cost of the run's gateway calls.
+    pub llm_usage: Value,
+    pub audit_log: Value,
+}
+
+// ─── Held updates ─────────────────────────────────────────────────────────────
+
+impl HeldUpdate {
+    pub fn new(repo: &str, file: &str, reason: HoldReason) -> Self {
+        Self {
+            repo: repo.to_string(),
+            file: file.to_string(),
+            reason,
+            details: Map::new(),
+        }
+    }
+
+    /// Adds the detail `key`.
+    pub fn with(mut self, key: &str, value: impl Serialize) -> Self {
+        self.details.insert(key.to_string(), json!(value));
+        self
+    }
+}
+
+// ─── Per-repo results ─────────────────────────────────────────────────────────
+
+impl RepoResult {
+    /// The result of each of `repos` from the run's `committed`, `held`
+    /// and `errors` entries.
+    pub fn collect(
+        repos: &[&str],
+        committed: &[CommitRecord],
+        held: &[HeldUpdate],
+        errors: &[Value],
+    ) -> Vec<Self> {
+        repos
+            .iter()
+            .map(|&repo| {
+                let entries: Vec<&CommitRecord> = committed
+                    .iter()
+                    .filter(|c| c.repo == repo && !c.reverted)
+                    .collect();
+                let delivered = entries
+                    .iter()
+                    .map(|c| c.files.len() + usize::from(c.file.is_some()))
+                    .sum();
+                let held = held.iter().filter(|h| h.repo == repo).count();
+                let errors = errors.iter().filter(|e| e["repo"] == repo).count();
+                let status = match (delivered > 0, held > 0 || errors > 0) {
+                    (true, false) => RepoStatus::Updated,
+                    (true, true) => RepoStatus::Partial,
+                    (false, _) if errors > 0 => RepoStatus::Failed,
+                    (false, _) if held > 0 => RepoStatus::Held,
+                    (false, _) => RepoStatus::Unchanged,
+                };
+                Self {
+                    repo: repo.to_string(),
+                    status,
+                    delivered,
+                    commits: entries.iter().filter_map(|c| c.sha.clone()).collect(),
+                    pull_requests: entries.iter().filter_map(|c| c.pr_url.clone()).collect(),
+                    held,
+                    errors,
+                }
+            })
+            .collect()
+    }
+}
+
+// ─── Schema ───────────────────────────────────────────────────────────────────
+
+/// JSON Schema of [`RunSummary`], as kept in `docs/run-summary.schema.json`.
+pub fn schema() -> Schema {
+    schema_for!(RunSummary)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_results() {
+        let committed = vec![
+            CommitRecord {
+                repo: "evo-king".to_string(),
+                file: Some("Cargo.toml".to_string()),
+                sha: Some("a1".to_string()),
+                ..Default::default()
+            },
+            CommitRecord {
+                repo: "evo-runner".to_string(),
+                files: vec!["Cargo.toml".to_string(), "Cargo.lock".to_string()],
+                pr_url: Some("https://github.com/o/evo-runner/pull/3".to_string()),
+                ..Default::default()
+            },
+            CommitRecord {
+                repo: "evo-agents".to_string(),
+                file: Some("Cargo.toml".to_string()),
+                sha: Some("b2".to_string()),
+                reverted: true,
+                ..Default::default()
+            },
+        ];
+        let held = vec![HeldUpdate::new(
+            "evo-runner",
+            ".github/workflows/ci.yml",
+            HoldReason::LlmRisk,
+        )];
+        let errors = vec![json!({ "repo": "evo-agents", "error": "push rejected" })];
+        let results = RepoResult::collect(
+            &["evo-king", "evo-runner", "evo-agents", "evo-gateway"],
+            &committed,
+            &held,
+            &errors,
+        );
+        let statuses: Vec<RepoStatus> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                RepoStatus::Updated,
+                RepoStatus::Partial,
+                RepoStatus::Failed,
+                RepoStatus::Unchanged
+            ]
+        );
+        assert_eq!(results[0].commits, vec!["a1"]);
+        assert_eq!(results[1].delivered, 2);
+        assert_eq!(results[2].delivered, 0);
+    }
+
+    #[test]
+    fn test_held_update_flattens_details() {
+        let held = HeldUpdate::new("evo-king", "Cargo.toml", HoldReason::UpstreamTierFailed)
+            .with("tier", 1);
+        assert_eq!(
+            serde_json::to_value(&held).unwrap(),
+            json!({ "repo": "evo-king", "file": "Cargo.toml", "reason": "upstream_tier_failed", "tier": 1 })
+        );
+    }
+
+    #[test]
+    fn test_exported_schema_is_current() {
+        let exported = include_str!("../docs/run-summary.schema.json");
+        let current = serde_json::to_string_pretty(&schema()).unwrap();
+        assert_eq!(
+            exported.trim_end(),
+            current,
+            "docs/run-summary.schema.json is stale — regenerate it with `action: \"schema\"`"
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::checksum::sha256_hex;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[template]` section of the config file: the repo agents are generated
+/// from and what of it their repos (`from_template = true`) must keep.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// The template repo.
+    pub repo: String,
+    /// Structural files compared whole (workflows and the like).
+    pub files: Vec<String>,
+    /// Manifest whose skeleton (edition, profiles, lints, …) is compared;
+    /// dependencies and the package's identity are the repo's own.
+    pub manifest: String,
+    /// Source file whose scaffolding marker lines must all be kept.
+    pub scaffold: String,
+    /// Text marking a scaffolding line in `scaffold`.
+    pub marker: String,
+    /// Open a PR in each drifted repo applying the template's files and
+    /// manifest skeleton; drift is only reported otherwise.
+    pub open_prs: bool,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            repo: "evo-user-agent-template".to_string(),
+            files: vec![
+                ".github/workflows/ci.yml".to_string(),
+                ".github/workflows/release.yml".to_string(),
+            ],
+            manifest: "Cargo.toml".to_string(),
+            scaffold: "src/main.rs".to_string(),
+            marker: "evo:scaffold".to_string(),
+            open_prs: false,
+        }
+    }
+}
+
+/// Tables of a manifest that are the repo's own, not skeleton.
+const OWN_TABLES: &[&str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "build-dependencies",
+    "target",
+    "features",
+    "bin",
+    "lib",
+    "example",
+    "test",
+    "bench",
+    "patch",
+];
+
+/// `[package]` keys identifying the repo's own crate.
+const OWN_PACKAGE_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "description",
+    "authors",
+    "repository",
+    "homepage",
+    "documentation",
+    "readme",
+    "keywords",
+    "categories",
+    "default-run",
+    "metadata",
+];
+
+// ─── Drift ────────────────────────────────────────────────────────────────────
+
+/// How a repo's file drifted from the template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftKind {
+    /// A structural file differs or is missing.
+    File,
+    /// Skeleton keys of the manifest differ or are missing.
+    Manifest,
+    /// Scaffolding marker lines are missing.
+    Scaffold,
+}
+
+/// One drifted file of a repo.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Drift {
+    pub file: String,
+    pub kind: DriftKind,
+    /// Differing skeleton keys (`key: template → repo`) or missing markers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    /// The repo's content of the file (empty when missing).
+    #[serde(skip)]
+    pub original: String,
+    /// The file with the template applied, when that can be done
+    /// mechanically (never for scaffolding).
+    #[serde(skip)]
+    pub proposed: Option<String>,
+}
+
+/// Paths, in both the template and the repo, that drift detection reads.
+pub fn compared_files(config: &TemplateConfig) -> Vec<String> {
+    config
+        .files
+        .iter()
+        .chain([&config.manifest, &config.scaffold])
+        .cloned()
+        .collect()
+}
+
+/// sha256 over the template's compared files, so a repo's scan
+/// fingerprint changes whenever the template does.
+pub fn digest(template_root: &Path, config: &TemplateConfig) -> String {
+    let mut all = String::new();
+    for file in compared_files(config) {
+        let content = std::fs::read(template_root.join(&file)).unwrap_or_default();
+        all.push_str(&file);
+        all.push('\0');
+        all.push_str(&sha256_hex(&content));
+        all.push('\n');
+    }
+    sha256_hex(all.as_bytes())
+}
+
+/// Compares the checkout at `repo_base` with the template checkout at
+/// `template_root`.  Files the template lacks are not compared.
+pub fn compare(
+    template_root: &Path,
+    repo_base: &Path,
+    config: &TemplateConfig,
+) -> Result<Vec<Drift>> {
+    anyhow::ensure!(
+        template_root.is_dir(),
+        "no checkout of {} at {}",
+        config.repo,
+        template_root.display()
+    );
+    let read = |root: &Path, file: &str| std::fs::read_to_string(root.join(file)).ok();
+    let mut drift = Vec::new();
+
+    for file in &config.files {
+        let Some(template) = read(template_root, file) else {
+            continue;
+        };
+        let current = read(repo_base, file).unwrap_or_default();
+        if current != template {
+            drift.push(Drift {
+                file: file.clone(),
+                kind: DriftKind::File,
+                details: Vec::new(),
+                original: current,
+                proposed: Some(template),
+            });
+        }
+    }
+
+    if let Some(template) = read(template_root, &config.manifest) {
+        let current = read(repo_base, &config.manifest).unwrap_or_default();
+        let (details, proposed) = manifest_drift(&template, &current)
+            .with_context(|| format!("compare {} with the template's", config.manifest))?;
+        if !details.is_empty() {
+            drift.push(Drift {
+                file: config.manifest.clone(),
+                kind: DriftKind::Manifest,
+                details,
+                original: current,
+                proposed: Some(proposed),
+            });
+        }
+    }
+
+    if let Some(template) = read(template_root, &config.scaffold) {
+        let current = read(repo_base, &config.scaffold).unwrap_or_default();
+        let kept: Vec<&str> = current.lines().map(str::trim).collect();
+        let missing: Vec<String> = template
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.contains(&config.marker) && !kept.contains(line))
+            .map(str::to_string)
+            .collect();
+        if !missing.is_empty() {
+            drift.push(Drift {
+                file: config.scaffold.clone(),
+                kind: DriftKind::Scaffold,
+                details: missing,
+                original: current,
+                proposed: None,
+            });
+        }
+    }
+    Ok(drift)
+}
+
+/// The skeleton keys `current` lacks or sets differently from `template`,
+/// and `current` with the template's values applied.
+fn manifest_drift(template: &str, current: &str) -> Result<(Vec<String>, String)> {
+    let template: DocumentMut = template.parse().context("parse template manifest")?;
+    let mut doc: DocumentMut = current.parse().context("parse manifest")?;
+    let mut expected = BTreeMap::new();
+    skeleton(template.as_table(), "", &mut expected);
+    let mut found = BTreeMap::new();
+    skeleton(doc.as_table(), "", &mut found);
+
+    let mut details = Vec::new();
+    for (key, (value, item)) in &expected {
+        let have = found.get(key).map(|(v, _)| v.as_str());
+        if have == Some(value.as_str()) {
+            continue;
+        }
+        details.push(format!("{key}: {value} → {}", have.unwrap_or("(missing)")));
+        set(doc.as_table_mut(), key, item.clone());
+    }
+    Ok((details, doc.to_string()))
+}
+
+/// Every skeleton leaf below `table` as `dotted.key → (value, item)`.
+fn skeleton(table: &Table, prefix: &str, out: &mut BTreeMap<String, (String, Item)>) {
+    for (key, item) in table.iter() {
+        if prefix.is_empty() && OWN_TABLES.contains(&key) {
+            continue;
+        }
+        if prefix == "package" && OWN_PACKAGE_KEYS.contains(&key) {
+            continue;
+        }
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match item {
+            Item::Table(inner) => skeleton(inner, &path, out),
+            Item::Value(value) => {
+                let mut value = value.clone();
+                value.decor_mut().clear();
+                out.insert(path, (value.to_string(), Item::Value(value)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sets the dotted `key` below `table`, creating missing tables.
+fn set(table: &mut Table, key: &str, item: Item) {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let Some(leaf) = parts.pop() else {
+        return;
+    };
+    let mut table = table;
+    for part in parts {
+        let entry = table
+            .entry(part)
+            .or_insert_with(|| Item::Table(Table::new()));
+        let Some(inner) = entry.as_table_mut() else {
+            return;
+        };
+        table = inner;
+    }
+    table.insert(leaf, item);
+}
+
+// ─── Pull requests ────────────────────────────────────────────────────────────
+
+/// Head branch of the PR applying `drift`: named after the proposed
+/// content, so an unchanged proposal is never opened twice.
+pub fn branch_name(drift: &[Drift]) -> String {
+    let mut all = String::new();
+    for d in drift {
+        all.push_str(&d.file);
+        all.push('\0');
+        all.push_str(d.proposed.as_deref().unwrap_or_default());
+    }
+    format!("evo-template/{}", &sha256_hex(all.as_bytes())[..12])
+}
+
+/// Markdown body of the PR applying `drift`, listing what it changes and
+/// the scaffolding it cannot restore.
+pub fn pr_body(template_repo: &str, drift: &[Drift]) -> String {
+    let mut body = format!(
+        "Structural files of this repo drifted from `{template_repo}`.  This PR applies \
+         the template's current version.\n\n"
+    );
+    for d in drift.iter().filter(|d| d.proposed.is_some()) {
+        body.push_str(&format!("- `{}`\n", d.file));
+        for detail in &d.details {
+            body.push_str(&format!("  - `{detail}`\n"));
+        }
+    }
+    let scaffold: Vec<&Drift> = drift.iter().filter(|d| d.proposed.is_none()).collect();
+    if !scaffold.is_empty() {
+        body.push_str("\nScaffolding to restore by hand:\n\n");
+        for d in scaffold {
+            for detail in &d.details {
+                body.push_str(&format!("- `{}`: `{detail}`\n", d.file));
+            }
+        }
+    }
+    body
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE_MANIFEST: &str = r#"[package]
+name = "evo-user-agent"
+version = "0.1.0"
+edition = "2024"
+rust-version = "1.85"
+
+[dependencies]
+evo-agent-sdk = "0.3"
+
+[profile.release]
+lto = true
+strip = true
+"#;
+
+    #[test]
+    fn test_manifest_skeleton() {
+        let current = r#"[package]
+name = "evo-weather-agent"
+version = "1.4.0"
+edition = "2021"
+rust-version = "1.85"
+
+[dependencies]
+evo-agent-sdk = "0.2"
+reqwest = "0.12"
+
+[profile.release]
+lto = true
+"#;
+        let (details, proposed) = manifest_drift(TEMPLATE_MANIFEST, current).unwrap();
+        assert_eq!(
+            details,
+            vec![
+                "package.edition: \"2024\" → \"2021\"",
+                "profile.release.strip: true → (missing)",
+            ]
+        );
+        assert_eq!(
+            proposed,
+            current
+                .replace("edition = \"2021\"", "edition = \"2024\"")
+                .replace("lto = true\n", "lto = true\nstrip = true\n")
+        );
+        let (details, _) = manifest_drift(TEMPLATE_MANIFEST, &proposed).unwrap();
+        assert!(details.is_empty());
+    }
+
+    #[test]
+    fn test_compare() {
+        let template = tempfile::tempdir().unwrap();
+        let repo = tempfile::tempdir().unwrap();
+        for root in [template.path(), repo.path()] {
+            std::fs::create_dir_all(root.join("src")).unwrap();
+            std::fs::create_dir_all(root.join(".github/workflows")).unwrap();
+            std::fs::write(root.join("Cargo.toml"), TEMPLATE_MANIFEST).unwrap();
+        }
+        std::fs::write(
+            template.path().join(".github/workflows/ci.yml"),
+            "on: push\n",
+        )
+        .unwrap();
+        std::fs::write(
+            template.path().join("src/main.rs"),
+            "fn main() {\n    // evo:scaffold register-handlers\n    run();\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            repo.path().join("src/main.rs"),
+            "fn main() {\n    run();\n}\n",
+        )
+        .unwrap();
+
+        let drift = compare(template.path(), repo.path(), &TemplateConfig::default()).unwrap();
+        assert_eq!(drift.len(), 2);
+        assert_eq!(drift[0].file, ".github/workflows/ci.yml");
+        assert_eq!(drift[0].proposed.as_deref(), Some("on: push\n"));
+        assert_eq!(drift[1].kind, DriftKind::Scaffold);
+        assert_eq!(drift[1].details, vec!["// evo:scaffold register-handlers"]);
+        assert_eq!(drift[1].proposed, None);
+
+        let missing = repo.path().join("nowhere");
+        assert!(compare(&missing, repo.path(), &TemplateConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// Per-repo stage a duration is charged to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Phase 2: reading and patching the repo's files.
+    Patch,
+    /// Sandbox clone, verification commands and migration loop.
+    Verify,
+    /// Phase 4: commits, pushes and PRs.
+    Commit,
+}
+
+/// Milliseconds spent on one repo, by stage.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RepoTiming {
+    pub patch_ms: u64,
+    pub verify_ms: u64,
+    pub commit_ms: u64,
+    pub total_ms: u64,
+}
+
+/// One finished phase.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PhaseTiming {
+    pub phase: &'static str,
+    pub ms: u64,
+}
+
+/// Wall-clock durations of one run, reported as `timings` in the summary.
+#[derive(Debug, Clone, Serialize)]
+pub struct Timings {
+    #[serde(skip)]
+    started: Instant,
+    /// Start of the phase currently running.
+    #[serde(skip)]
+    mark: Instant,
+    pub total_ms: u64,
+    /// Phases in run order.
+    pub phases: Vec<PhaseTiming>,
+    /// Latency of the crates.io version lookup per crate.
+    pub crates_io: BTreeMap<String, u64>,
+    pub repos: BTreeMap<String, RepoTiming>,
+    pub slowest_phase: Option<&'static str>,
+    pub slowest_repo: Option<String>,
+}
+
+impl Timings {
+    /// Starts the run clock and the first phase.
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            mark: now,
+            total_ms: 0,
+            phases: Vec::new(),
+            crates_io: BTreeMap::new(),
+            repos: BTreeMap::new(),
+            slowest_phase: None,
+            slowest_repo: None,
+        }
+    }
+
+    /// Records the phase running since the last call as `phase` and starts
+    /// the next one.
+    pub fn end_phase(&mut self, phase: &'static str) {
+        let took = self.mark.elapsed();
+        self.mark = Instant::now();
+        self.phases.push(PhaseTiming {
+            phase,
+            ms: millis(took),
+        });
+    }
+
+    /// Records the crates.io lookup of `crate_name`.
+    pub fn crates_io(&mut self, crate_name: &str, took: Duration) {
+        self.crates_io.insert(crate_name.to_string(), millis(took));
+    }
+
+    /// Adds `took` to `repo`'s `stage`.
+    pub fn repo(&mut self, repo: &str, stage: Stage, took: Duration) {
+        let ms = millis(took);
+        let timing = self.repos.entry(repo.to_string()).or_default();
+        match stage {
+            Stage::Patch => timing.patch_ms += ms,
+            Stage::Verify => timing.verify_ms += ms,
+            Stage::Commit => timing.commit_ms += ms,
+        }
+        timing.total_ms += ms;
+    }
+
+    /// Stops the run clock and picks the slowest phase and repo.
+    pub fn finish(mut self) -> Self {
+        self.total_ms = millis(self.started.elapsed());
+        self.slowest_phase = self.phases.iter().max_by_key(|p| p.ms).map(|p| p.phase);
+        self.slowest_repo = self
+            .repos
+            .iter()
+            .max_by_key(|(_, t)| t.total_ms)
+            .map(|(repo, _)| repo.clone());
+        self
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    u64::try_from(d.as_millis()).unwrap_or(u64::MAX)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_stages_accumulate() {
+        let mut timings = Timings::start();
+        timings.repo("evo-king", Stage::Patch, Duration::from_millis(5));
+        timings.repo("evo-king", Stage::Commit, Duration::from_millis(300));
+        timings.repo("evo-king", Stage::Commit, Duration::from_millis(200));
+        timings.repo("evo-runner", Stage::Verify, Duration::from_millis(40));
+        timings.crates_io("evo-common", Duration::from_millis(120));
+        timings.end_phase("check_versions");
+        timings.end_phase("scan");
+        let timings = timings.finish();
+
+        assert_eq!(
+            timings.repos["evo-king"],
+            RepoTiming {
+                patch_ms: 5,
+                verify_ms: 0,
+                commit_ms: 500,
+                total_ms: 505,
+            }
+        );
+        assert_eq!(timings.slowest_repo.as_deref(), Some("evo-king"));
+        assert_eq!(timings.crates_io["evo-common"], 120);
+        let phases: Vec<&str> = timings.phases.iter().map(|p| p.phase).collect();
+        assert_eq!(phases, vec!["check_versions", "scan"]);
+        assert!(timings.slowest_phase.is_some());
+    }
+}
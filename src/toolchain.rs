@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::fixtures::RegistryHttp;
+use crate::versions::needs_update;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Name the Rust toolchain goes by wherever crates are named: policy lists,
+/// repo-local rules, the version cache and version reports.
+pub const RUST: &str = "rust";
+
+/// Manifest of the current stable release.
+const STABLE_CHANNEL_URL: &str = "https://static.rust-lang.org/dist/channel-rust-stable.toml";
+
+// ─── Stable release ───────────────────────────────────────────────────────────
+
+/// Version of the current stable Rust release.
+pub async fn latest_stable(client: &RegistryHttp) -> Result<String> {
+    let resp = client
+        .get(STABLE_CHANNEL_URL)
+        .await?
+        .ensure_success(STABLE_CHANNEL_URL)?;
+    let manifest = resp.text().context("read stable channel manifest")?;
+    channel_version(manifest).context("stable channel manifest has no [pkg.rust] version")
+}
+
+/// The `[pkg.rust]` version of a channel manifest, e.g. `1.79.0` from
+/// `version = "1.79.0 (129f3b996 2024-06-10)"`.
+pub fn channel_version(manifest: &str) -> Option<String> {
+    let mut in_rust = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_rust = line == "[pkg.rust]";
+        } else if in_rust
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "version"
+        {
+            return value
+                .trim()
+                .trim_matches('"')
+                .split_whitespace()
+                .next()
+                .map(str::to_string);
+        }
+    }
+    None
+}
+
+// ─── Workflow pins ────────────────────────────────────────────────────────────
+
+/// `rust-version` of a manifest's `[package]` or `[workspace.package]`.
+pub fn msrv(manifest: &str) -> Option<String> {
+    let doc: toml_edit::DocumentMut = manifest.parse().ok()?;
+    [
+        doc.get("package"),
+        doc.get("workspace").and_then(|w| w.get("package")),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|table| table.get("rust-version")?.as_str().map(str::to_string))
+}
+
+/// The lowest toolchain pin in `content` that [`patch_pins`] would move,
+/// ignoring pins to the repo's `msrv`.
+pub fn pinned_version(content: &str, msrv: Option<&str>) -> Option<String> {
+    pin_regex()
+        .captures_iter(content)
+        .map(|caps| caps["version"].to_string())
+        .filter(|version| !is_msrv(version, msrv))
+        .reduce(|low, v| if needs_update(&v, &low) { v } else { low })
+}
+
+/// Moves every pin older than `latest` to `latest`, keeping each pin's
+/// precision (`1.79` → `1.80`, `1.79.0` → `1.80.1`).
+///
+/// Pins to the repo's `msrv` test the minimum supported version and stay,
+/// as do lists of several versions (compatibility matrices).
+pub fn patch_pins(content: &str, latest: &str, msrv: Option<&str>) -> String {
+    pin_regex()
+        .replace_all(content, |caps: &regex::Captures| {
+            let current = &caps["version"];
+            let next = if current.matches('.').count() == 1 {
+                latest.splitn(3, '.').take(2).collect::<Vec<_>>().join(".")
+            } else {
+                latest.to_string()
+            };
+            if is_msrv(current, msrv) || !needs_update(current, &next) {
+                caps[0].to_string()
+            } else {
+                format!("{}{next}{}", &caps["prefix"], &caps["suffix"])
+            }
+        })
+        .into_owned()
+}
+
+/// A single pinned version under a `rust:` or `toolchain:` key, bare or as a
+/// one-entry flow list: `rust: [1.79.0]`, `toolchain: "1.79"`.
+fn pin_regex() -> Regex {
+    Regex::new(
+        r#"(?m)^(?P<prefix>[ \t]*(?:-[ \t]+)?(?:rust|toolchain):[ \t]*(?:\[[ \t]*)?["']?)(?P<version>1\.\d+(?:\.\d+)?)(?P<suffix>["']?[ \t]*\]?[ \t]*(?:#.*)?)$"#,
+    )
+    .expect("toolchain pin regex is valid")
+}
+
+/// Whether `version` names the same release as `msrv` (`1.70` = `1.70.0`).
+fn is_msrv(version: &str, msrv: Option<&str>) -> bool {
+    msrv.is_some_and(|msrv| !needs_update(version, msrv) && !needs_update(msrv, version))
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = "\
+jobs:
+  test:
+    strategy:
+      matrix:
+        rust: [1.79.0]
+    steps:
+      - uses: dtolnay/rust-toolchain@master
+        with:
+          toolchain: \"1.79\" # pinned
+  msrv:
+    steps:
+      - uses: dtolnay/rust-toolchain@master
+        with:
+          toolchain: 1.70.0
+  compat:
+    strategy:
+      matrix:
+        rust: [1.70.0, 1.75.0]
+  nightly:
+    steps:
+      - uses: dtolnay/rust-toolchain@master
+        with:
+          toolchain: stable
+";
+
+    #[test]
+    fn test_channel_version() {
+        let manifest = "manifest-version = \"2\"\n\n[pkg.cargo]\nversion = \"0.80.0 (abc 2024-06-10)\"\n\n[pkg.rust]\nversion = \"1.80.1 (3f5fd8dd4 2024-08-06)\"\n";
+        assert_eq!(channel_version(manifest).as_deref(), Some("1.80.1"));
+        assert_eq!(channel_version("[pkg.cargo]\nversion = \"0.80.0\"\n"), None);
+    }
+
+    #[test]
+    fn test_patch_pins_keeps_precision_msrv_and_matrices() {
+        let msrv = Some("1.70");
+        assert_eq!(pinned_version(WORKFLOW, msrv).as_deref(), Some("1.79.0"));
+        let patched = patch_pins(WORKFLOW, "1.80.1", msrv);
+        assert!(patched.contains("rust: [1.80.1]\n"));
+        assert!(patched.contains("toolchain: \"1.80\" # pinned\n"));
+        assert!(patched.contains("toolchain: 1.70.0\n"));
+        assert!(patched.contains("rust: [1.70.0, 1.75.0]\n"));
+        assert!(patched.contains("toolchain: stable\n"));
+        assert_eq!(patch_pins(&patched, "1.80.1", msrv), patched);
+        assert_eq!(patch_pins(WORKFLOW, "1.79.0", msrv), WORKFLOW);
+    }
+
+    #[test]
+    fn test_msrv() {
+        assert_eq!(
+            msrv("[package]\nname = \"x\"\nrust-version = \"1.70\"\n").as_deref(),
+            Some("1.70")
+        );
+        assert_eq!(
+            msrv("[workspace.package]\nrust-version = \"1.75.0\"\n").as_deref(),
+            Some("1.75.0")
+        );
+        assert_eq!(msrv("[package]\nname = \"x\"\n"), None);
+    }
+}
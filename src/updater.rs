@@ -1,47 +1,302 @@
 use anyhow::{Context, Result};
 use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::cargo_edit::patch_with_cargo_add;
+use crate::versions::DEP_SECTIONS;
 
 // ─── Cargo.toml patching ─────────────────────────────────────────────────────
 
+/// A single dependency version rewritten by [`patch_cargo_toml`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedEntry {
+    /// Table the dependency lives in, e.g. `"dependencies"`.
+    pub section: String,
+    /// Dependency name.
+    pub dep: String,
+    /// Version requirement before the patch.
+    pub from: String,
+    /// Version requirement after the patch.
+    pub to: String,
+}
+
+/// Result of patching a Cargo.toml.
+#[derive(Debug, Clone)]
+pub struct PatchOutcome {
+    /// Full patched file content.
+    pub content: String,
+    /// Every version entry that was rewritten.
+    pub changed_entries: Vec<ChangedEntry>,
+    /// Tables that had at least one entry rewritten.
+    pub sections_touched: Vec<String>,
+    /// Requested updates that could not be applied; the rest of the
+    /// manifest is still patched.
+    pub failed: Vec<FailedEntry>,
+}
+
+/// A requested update [`patch_cargo_toml_multi`] could not apply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedEntry {
+    pub dep: String,
+    pub error: String,
+}
+
+/// Implementation that rewrites a repo's manifests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchBackend {
+    /// In-process `toml_edit` rewrite of the version tokens
+    /// ([`patch_cargo_toml_multi`]).
+    #[default]
+    TomlEdit,
+    /// `cargo add <crate>@<version>` in the local checkout, for manifests
+    /// using constructs the `toml_edit` patcher does not handle.
+    CargoEdit,
+}
+
+/// Applies every `(dep, version)` update to `content` (the manifest at
+/// `manifest_path`) with the chosen `backend`.
+pub fn patch_manifest(
+    backend: PatchBackend,
+    manifest_path: &Path,
+    lockfile: Option<&Path>,
+    content: &str,
+    updates: &[(&str, &str)],
+    offline: bool,
+) -> Result<PatchOutcome> {
+    if backend == PatchBackend::TomlEdit {
+        return patch_cargo_toml_multi(content, updates);
+    }
+    let mut outcome = PatchOutcome {
+        content: content.to_string(),
+        changed_entries: Vec::new(),
+        sections_touched: Vec::new(),
+        failed: Vec::new(),
+    };
+    for &(dep_name, new_version) in updates {
+        match patch_with_cargo_add(
+            manifest_path,
+            lockfile,
+            &outcome.content,
+            dep_name,
+            new_version,
+            offline,
+        ) {
+            Ok(step) => {
+                outcome.content = step.content;
+                outcome.changed_entries.extend(step.changed_entries);
+            }
+            Err(e) => outcome.failed.push(FailedEntry {
+                dep: dep_name.to_string(),
+                error: format!("{e:#}"),
+            }),
+        }
+    }
+    outcome.sections_touched = sections_of(&outcome.changed_entries);
+    Ok(outcome)
+}
+
 /// Updates the version of `dep_name` in a Cargo.toml string using `toml_edit`,
 /// preserving existing formatting and comments.
 ///
-/// Handles both:
-/// - `dep_name = "X.Y.Z"` (simple string form)
-/// - `dep_name = { version = "X.Y.Z", ... }` (inline table form)
-pub fn patch_cargo_toml(content: &str, dep_name: &str, new_version: &str) -> Result<String> {
-    let mut doc: toml_edit::DocumentMut = content
-        .parse()
-        .with_context(|| format!("parse Cargo.toml to patch {dep_name}"))?;
-
-    let deps = doc
-        .get_mut("dependencies")
-        .with_context(|| "no [dependencies] section found")?;
-
-    let dep = deps
-        .get_mut(dep_name)
-        .with_context(|| format!("dependency {dep_name} not found in [dependencies]"))?;
+/// Single-crate form of [`patch_cargo_toml_multi`]; a crate that cannot be
+/// patched is an error here.
+pub fn patch_cargo_toml(content: &str, dep_name: &str, new_version: &str) -> Result<PatchOutcome> {
+    let outcome = patch_cargo_toml_multi(content, &[(dep_name, new_version)])?;
+    if let Some(failed) = outcome.failed.first() {
+        anyhow::bail!("{}", failed.error);
+    }
+    Ok(outcome)
+}
+
+/// Updates every `(dep, version)` in `updates` in one parse of `content`,
+/// preserving existing formatting and comments.
+///
+/// Each crate is patched wherever it appears with a version: the regular,
+/// dev and build tables, their `[target.*]` variants and
+/// `[workspace.dependencies]`, in the simple (`dep = "1.2"`), inline-table
+/// and block-table forms.  Entries without a version (`path`-only,
+/// `workspace = true`) are left alone.  A crate found nowhere, or only in
+/// an unexpected shape, is reported under `failed`.
+///
+/// The output is checked against the input before returning: any difference
+/// other than the rewritten version tokens is reported as an error rather
+/// than risking a corrupted manifest.
+pub fn patch_cargo_toml_multi(content: &str, updates: &[(&str, &str)]) -> Result<PatchOutcome> {
+    let mut doc: toml_edit::DocumentMut = content.parse().context("parse Cargo.toml to patch")?;
 
+    let mut sections: Vec<(String, &mut toml_edit::Item)> = Vec::new();
+    for (key, item) in doc.as_table_mut().iter_mut() {
+        match key.get() {
+            name if DEP_SECTIONS.contains(&name) => sections.push((name.to_string(), item)),
+            "target" => {
+                let Some(targets) = item.as_table_like_mut() else {
+                    continue;
+                };
+                for (cfg, target) in targets.iter_mut() {
+                    let cfg = cfg.get().to_string();
+                    let Some(target) = target.as_table_like_mut() else {
+                        continue;
+                    };
+                    for (kind, deps) in target.iter_mut() {
+                        if DEP_SECTIONS.contains(&kind.get()) {
+                            sections.push((format!("target.{cfg}.{}", kind.get()), deps));
+                        }
+                    }
+                }
+            }
+            "workspace" => {
+                if let Some(deps) = item
+                    .as_table_like_mut()
+                    .and_then(|ws| ws.get_mut("dependencies"))
+                {
+                    sections.push(("workspace.dependencies".to_string(), deps));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut changed_entries: Vec<ChangedEntry> = Vec::new();
+    let mut failed: Vec<FailedEntry> = Vec::new();
+    for &(dep_name, new_version) in updates {
+        let mut found = false;
+        let mut error: Option<String> = None;
+        for (section, deps) in sections.iter_mut() {
+            let Some(dep) = deps
+                .as_table_like_mut()
+                .and_then(|table| table.get_mut(dep_name))
+            else {
+                continue;
+            };
+            match set_dep_version(dep, new_version) {
+                Ok(Some(from)) => {
+                    found = true;
+                    if from != new_version {
+                        changed_entries.push(ChangedEntry {
+                            section: section.clone(),
+                            dep: dep_name.to_string(),
+                            from,
+                            to: new_version.to_string(),
+                        });
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => error = Some(format!("[{section}]: {e}")),
+            }
+        }
+        match (found, error) {
+            (_, Some(error)) => failed.push(FailedEntry {
+                dep: dep_name.to_string(),
+                error,
+            }),
+            (false, None) => failed.push(FailedEntry {
+                dep: dep_name.to_string(),
+                error: format!("dependency {dep_name} not found with a version"),
+            }),
+            (true, None) => {}
+        }
+    }
+
+    let patched = doc.to_string();
+    guard_only_versions_changed(content, &patched, &changed_entries)?;
+
+    Ok(PatchOutcome {
+        content: patched,
+        sections_touched: sections_of(&changed_entries),
+        changed_entries,
+        failed,
+    })
+}
+
+/// Sets the version of one dependency entry.  Returns the previous version,
+/// or `None` when the entry carries no version.
+fn set_dep_version(dep: &mut toml_edit::Item, new_version: &str) -> Result<Option<String>> {
     if dep.is_str() {
         // Simple form: `dep = "1.2"`
-        *dep = toml_edit::value(new_version);
+        Ok(dep
+            .as_value_mut()
+            .and_then(|v| set_version_preserving_decor(v, new_version)))
     } else if let Some(table) = dep.as_inline_table_mut() {
         // Inline table: `dep = { version = "1.2", ... }`
-        if let Some(v) = table.get_mut("version") {
-            *v = toml_edit::Value::from(new_version);
-        }
+        Ok(table
+            .get_mut("version")
+            .and_then(|v| set_version_preserving_decor(v, new_version)))
     } else if let Some(table) = dep.as_table_mut() {
         // Block table:
         // [dependencies.dep]
         // version = "1.2"
-        if let Some(v) = table.get_mut("version") {
-            *v = toml_edit::value(new_version);
-        }
+        Ok(table
+            .get_mut("version")
+            .and_then(|v| v.as_value_mut())
+            .and_then(|v| set_version_preserving_decor(v, new_version)))
     } else {
-        anyhow::bail!("unexpected TOML shape for dependency {dep_name} — cannot patch version");
+        anyhow::bail!("unexpected TOML shape — cannot patch version")
     }
+}
+
+/// Distinct sections of `entries`, in order.
+fn sections_of(entries: &[ChangedEntry]) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for entry in entries {
+        if !sections.contains(&entry.section) {
+            sections.push(entry.section.clone());
+        }
+    }
+    sections
+}
 
-    Ok(doc.to_string())
+/// Replaces a string value with `new_version`, keeping its surrounding
+/// whitespace and trailing comment.  Returns the previous version, or `None`
+/// if the value is not a string.
+fn set_version_preserving_decor(value: &mut toml_edit::Value, new_version: &str) -> Option<String> {
+    let old = value.as_str()?.to_string();
+    let decor = value.decor().clone();
+    *value = toml_edit::Value::from(new_version);
+    *value.decor_mut() = decor;
+    Some(old)
+}
+
+/// Diff guard: every line that differs between `before` and `after` must be
+/// explained by one of `entries` (its version token swapped), and nothing
+/// else — line count and trailing newline included — may change.
+fn guard_only_versions_changed(before: &str, after: &str, entries: &[ChangedEntry]) -> Result<()> {
+    let before_lines: Vec<&str> = before.lines().collect();
+    let after_lines: Vec<&str> = after.lines().collect();
+
+    if before_lines.len() != after_lines.len() || before.ends_with('\n') != after.ends_with('\n') {
+        anyhow::bail!("patch guard: line structure changed while patching versions");
+    }
+
+    for (idx, (old, new)) in before_lines.iter().zip(&after_lines).enumerate() {
+        if old == new {
+            continue;
+        }
+        let explained = entries.iter().any(|e| {
+            matches!(
+                (mask_version(old, &e.from), mask_version(new, &e.to)),
+                (Some(a), Some(b)) if a == b
+            )
+        });
+        if !explained {
+            anyhow::bail!(
+                "patch guard: unexpected change on line {}: {old:?} → {new:?}",
+                idx + 1
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the quoted `version` token in `line` with a placeholder, accepting
+/// either basic (`"…"`) or literal (`'…'`) quoting.
+fn mask_version(line: &str, version: &str) -> Option<String> {
+    ["\"", "'"].iter().find_map(|q| {
+        let token = format!("{q}{version}{q}");
+        line.contains(&token)
+            .then(|| line.replacen(&token, "\"\u{0}\"", 1))
+    })
 }
 
 // ─── Workflow YAML patching ───────────────────────────────────────────────────
@@ -52,30 +307,94 @@ pub fn patch_cargo_toml(content: &str, dep_name: &str, new_version: &str) -> Res
 /// ```yaml
 /// run: |
 ///   sed -i.bak 's|evo-agent-sdk = { path = "[^"]*" }|evo-agent-sdk = "0.2"|' Cargo.toml
+///   sed -i.bak "s#evo-common = { path = \"[^\"]*\" }#evo-common = \"0.4\"#" Cargo.toml
+///   sed --expression="s@evo-common = .*@evo-common = '0.4'@" Cargo.toml
 /// ```
 ///
 /// The function rewrites *only* the literal crates.io version on the right-hand
-/// side of that `|dep_name = "VERSION"|` sed replacement target, leaving
-/// everything else in the file unchanged.
+/// side of the `dep_name = "VERSION"` sed replacement target, leaving
+/// everything else in the file unchanged.  Supported variants:
+///
+/// | Variant | Example target |
+/// |---------|----------------|
+/// | delimiter `\|`, `#`, `/`, `@` or `,` | `\|dep = "0.2"\|`, `#dep = "0.2"#` |
+/// | plain double quotes (single-quoted sed script) | `dep = "0.2"` |
+/// | escaped double quotes (double-quoted sed script) | `dep = \"0.2\"` |
+/// | single quotes (TOML literal string) | `dep = '0.2'` |
 ///
 /// Returns the (possibly unchanged) content — never errors so the caller can
 /// decide whether the absence of a match is a problem.
 pub fn patch_workflow_sed(content: &str, dep_name: &str, new_version: &str) -> String {
-    // Match: dep_name = "OLD_VERSION" at the end of a sed replacement block.
+    workflow_sed_regex(dep_name)
+        .replace_all(content, |caps: &regex::Captures| {
+            let target = SedTarget::from(caps);
+            format!(
+                "{}{quote}{new_version}{quote}",
+                &caps["prefix"],
+                quote = target.quote
+            )
+        })
+        .into_owned()
+}
+
+/// Returns the first crates.io version found in a workflow `sed` replacement
+/// target for `dep_name` (the value [`patch_workflow_sed`] would rewrite).
+pub fn workflow_sed_version(content: &str, dep_name: &str) -> Option<String> {
+    workflow_sed_regex(dep_name)
+        .captures(content)
+        .map(|caps| SedTarget::from(&caps).version.to_string())
+}
+
+/// Describes the distinct sed pattern variants matched for `dep_name`, e.g.
+/// `"'|' delimiter, plain quotes"`, for the run report.
+pub fn workflow_sed_styles(content: &str, dep_name: &str) -> Vec<String> {
+    let mut styles: Vec<String> = Vec::new();
+    for caps in workflow_sed_regex(dep_name).captures_iter(content) {
+        let quotes = match SedTarget::from(&caps).quote {
+            "\"" => "plain quotes",
+            "'" => "single quotes",
+            _ => "escaped quotes",
+        };
+        let style = format!("'{}' delimiter, {quotes}", &caps["delim"]);
+        if !styles.contains(&style) {
+            styles.push(style);
+        }
+    }
+    styles
+}
+
+/// The quoted version of one sed replacement target.
+struct SedTarget<'a> {
+    /// `\"`, `"` or `'`, the same on both sides of the version.
+    quote: &'a str,
+    version: &'a str,
+}
+
+impl<'a> From<&regex::Captures<'a>> for SedTarget<'a> {
+    fn from(caps: &regex::Captures<'a>) -> Self {
+        let (quote, version) = [("\\\"", "escaped"), ("\"", "plain"), ("'", "single")]
+            .into_iter()
+            .find_map(|(quote, name)| Some((quote, caps.name(name)?.as_str())))
+            .unwrap_or_default();
+        Self { quote, version }
+    }
+}
+
+/// Builds the regex matching `<delim>dep_name = "VERSION"` in a sed replacement.
+fn workflow_sed_regex(dep_name: &str) -> Regex {
+    // Match: dep_name = "OLD_VERSION" right after a sed delimiter.
     // The sed line looks like:  …|dep_name = "OLD"|' …
-    // We specifically target the escaped-quote pattern used in shell sed args.
+    // The quote may be escaped (\"…\") inside a double-quoted sed script, or
+    // a TOML single quote.  The regex crate has no backreferences, so each
+    // quote style is its own alternative and mismatched quotes never match.
+    let dep = regex::escape(dep_name);
+    let version = r#"\d[^"'\\]*"#;
     let pattern = format!(
-        r#"(\|{dep_name} = ")([\d][^"]*)(")"#,
-        dep_name = regex::escape(dep_name)
+        r#"(?P<prefix>(?P<delim>[|#/@,]){dep} = )(?:\\"(?P<escaped>{version})\\"|"(?P<plain>{version})"|'(?P<single>{version})')"#
     );
 
     // SAFETY: the pattern is constructed from known-safe components.
-    let re = Regex::new(&pattern).expect("patch_workflow_sed regex is valid");
-
-    re.replace_all(content, |caps: &regex::Captures| {
-        format!("{}{}{}", &caps[1], new_version, &caps[3])
-    })
-    .into_owned()
+    Regex::new(&pattern).expect("workflow sed regex is valid")
 }
 
 // ─── Tests ───────────────────────────────────────────────────────────────────
@@ -93,7 +412,7 @@ mod tests {
 evo-common = "0.2"
 tokio = { version = "1", features = ["full"] }
 "#;
-        let patched = patch_cargo_toml(toml, "evo-common", "0.3").unwrap();
+        let patched = patch_cargo_toml(toml, "evo-common", "0.3").unwrap().content;
         assert!(patched.contains("evo-common = \"0.3\""));
         // Other deps should be untouched
         assert!(patched.contains("tokio"));
@@ -105,7 +424,9 @@ tokio = { version = "1", features = ["full"] }
 [dependencies]
 evo-agent-sdk = { version = "0.1", features = ["full"] }
 "#;
-        let patched = patch_cargo_toml(toml, "evo-agent-sdk", "0.2").unwrap();
+        let patched = patch_cargo_toml(toml, "evo-agent-sdk", "0.2")
+            .unwrap()
+            .content;
         assert!(patched.contains("\"0.2\""));
         // features should remain
         assert!(patched.contains("features"));
@@ -129,13 +450,113 @@ version = "1.0.0"
 serde = "1"
 evo-common = "0.2"
 "#;
-        let patched = patch_cargo_toml(toml, "evo-common", "0.3").unwrap();
+        let patched = patch_cargo_toml(toml, "evo-common", "0.3").unwrap().content;
         assert!(patched.contains("[package]"));
         assert!(patched.contains("name = \"my-crate\""));
         assert!(patched.contains("serde = \"1\""));
         assert!(patched.contains("evo-common = \"0.3\""));
     }
 
+    #[test]
+    fn test_patch_outcome_reports_changes() {
+        let toml = "[dependencies]\nevo-common = \"0.2\" # pinned by hand\nserde = \"1\"\n";
+        let outcome = patch_cargo_toml(toml, "evo-common", "0.3").unwrap();
+        assert_eq!(
+            outcome.content,
+            "[dependencies]\nevo-common = \"0.3\" # pinned by hand\nserde = \"1\"\n"
+        );
+        assert_eq!(
+            outcome.changed_entries,
+            vec![ChangedEntry {
+                section: "dependencies".to_string(),
+                dep: "evo-common".to_string(),
+                from: "0.2".to_string(),
+                to: "0.3".to_string(),
+            }]
+        );
+        assert_eq!(outcome.sections_touched, vec!["dependencies".to_string()]);
+    }
+
+    #[test]
+    fn test_patch_block_table_and_literal_quotes() {
+        let toml = "[dependencies.evo-common]\nversion = '0.2'\nfeatures = [\"x\"]\n";
+        let outcome = patch_cargo_toml(toml, "evo-common", "0.3").unwrap();
+        assert!(outcome.content.contains("version = \"0.3\""));
+        assert_eq!(outcome.changed_entries.len(), 1);
+    }
+
+    #[test]
+    fn test_patch_same_version_is_noop() {
+        let toml = "[dependencies]\nevo-common = \"0.3\"\n";
+        let outcome = patch_cargo_toml(toml, "evo-common", "0.3").unwrap();
+        assert_eq!(outcome.content, toml);
+        assert!(outcome.changed_entries.is_empty());
+        assert!(outcome.sections_touched.is_empty());
+    }
+
+    #[test]
+    fn test_guard_rejects_unrelated_change() {
+        let entries = vec![ChangedEntry {
+            section: "dependencies".to_string(),
+            dep: "a".to_string(),
+            from: "1".to_string(),
+            to: "2".to_string(),
+        }];
+        assert!(guard_only_versions_changed("a = \"1\"\n", "a = \"2\"\n", &entries).is_ok());
+        assert!(guard_only_versions_changed("b = \"1\"\n", "b = \"3\"\n", &entries).is_err());
+        assert!(guard_only_versions_changed("a = \"1\"\n", "a = \"2\"\n\n", &entries).is_err());
+    }
+
+    #[test]
+    fn test_patch_multi_all_sections() {
+        let toml = r#"[dependencies]
+evo-common = "0.2"
+evo-agent-sdk = { path = "../sdk" }
+
+[dev-dependencies.evo-common]
+version = "0.2"
+
+[target.'cfg(unix)'.dependencies]
+evo-agent-sdk = { version = "0.1", features = ["x"] }
+
+[workspace.dependencies]
+evo-common = { version = "0.3", default-features = false }
+"#;
+        let outcome = patch_cargo_toml_multi(
+            toml,
+            &[
+                ("evo-common", "0.3"),
+                ("evo-agent-sdk", "0.2"),
+                ("missing", "1.0"),
+            ],
+        )
+        .unwrap();
+        assert!(outcome.content.contains("evo-common = \"0.3\"\n"));
+        assert!(outcome.content.contains("version = \"0.3\"\n"));
+        assert!(outcome.content.contains("{ version = \"0.2\", features"));
+        assert!(outcome.content.contains("{ path = \"../sdk\" }"));
+        let sections: Vec<&str> = outcome
+            .changed_entries
+            .iter()
+            .map(|e| e.section.as_str())
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                "dependencies",
+                "dev-dependencies",
+                "target.cfg(unix).dependencies"
+            ]
+        );
+        assert_eq!(
+            outcome.failed,
+            vec![FailedEntry {
+                dep: "missing".to_string(),
+                error: "dependency missing not found with a version".to_string(),
+            }]
+        );
+    }
+
     // ── Workflow sed patching ──
 
     #[test]
@@ -171,4 +592,61 @@ evo-common = "0.2"
         assert!(patched.contains("cargo test"));
         assert!(patched.contains("\"0.2\""));
     }
+
+    #[test]
+    fn test_workflow_sed_version() {
+        let yaml = r#"sed -i.bak 's|evo-agent-sdk = { path = "[^"]*" }|evo-agent-sdk = "0.1.4"|' Cargo.toml"#;
+        assert_eq!(
+            workflow_sed_version(yaml, "evo-agent-sdk"),
+            Some("0.1.4".to_string())
+        );
+        assert_eq!(workflow_sed_version(yaml, "evo-common"), None);
+    }
+
+    #[test]
+    fn test_patch_workflow_sed_per_crate() {
+        let yaml = r#"
+          sed -i.bak 's|evo-agent-sdk = { path = "[^"]*" }|evo-agent-sdk = "0.1"|' Cargo.toml
+          sed -i.bak 's|evo-common = { path = "[^"]*" }|evo-common = "0.3"|' Cargo.toml
+"#;
+        let patched = patch_workflow_sed(yaml, "evo-common", "0.4.0");
+        assert!(patched.contains("evo-common = \"0.4.0\""));
+        assert!(patched.contains("evo-agent-sdk = \"0.1\""));
+    }
+
+    #[test]
+    fn test_patch_workflow_sed_variants() {
+        let yaml = r##"
+          sed -i "s#evo-common = { path = \"[^\"]*\" }#evo-common = \"0.3\"#" Cargo.toml
+          sed --expression="s@evo-common = .*@evo-common = '0.3'@" Cargo.toml
+          sed -e 's|evo-common = { path = "[^"]*" }|evo-common = "0.3"|' Cargo.toml
+"##;
+        let patched = patch_workflow_sed(yaml, "evo-common", "0.4.0");
+        assert!(patched.contains(r##"#evo-common = \"0.4.0\"#"##));
+        assert!(patched.contains("@evo-common = '0.4.0'@"));
+        assert!(patched.contains(r#"|evo-common = "0.4.0"|"#));
+        assert_eq!(
+            workflow_sed_version(yaml, "evo-common").as_deref(),
+            Some("0.3")
+        );
+        assert_eq!(
+            workflow_sed_styles(yaml, "evo-common"),
+            vec![
+                "'#' delimiter, escaped quotes",
+                "'@' delimiter, single quotes",
+                "'|' delimiter, plain quotes",
+            ]
+        );
+    }
+    #[test]
+    fn test_patch_workflow_sed_mismatched_quotes_unchanged() {
+        let yaml = r#"
+          sed -e 's|evo-common = .*|evo-common = "0.3'"'"'|' Cargo.toml
+          sed -e "s|evo-common = .*|evo-common = "0.3\"|" Cargo.toml
+          sed -e "s|evo-common = .*|evo-common = '0.3\"|" Cargo.toml
+"#;
+        assert_eq!(patch_workflow_sed(yaml, "evo-common", "0.4.0"), yaml);
+        assert_eq!(workflow_sed_version(yaml, "evo-common"), None);
+        assert!(workflow_sed_styles(yaml, "evo-common").is_empty());
+    }
 }
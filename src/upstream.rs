@@ -0,0 +1,422 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tracing::{debug, warn};
+
+use crate::git::gh_api;
+use crate::llm::{Llm, Purpose};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// At most this many commit subjects are sent to the gateway per crate.
+const MAX_PROMPT_COMMITS: usize = 200;
+
+/// Subjects listed when the gateway gives no summary.
+const MAX_LISTED_COMMITS: usize = 20;
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// One commit of a tracked crate's source repo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamCommit {
+    /// Abbreviated SHA.
+    pub sha: String,
+    /// First line of the commit message.
+    pub subject: String,
+}
+
+/// What changed in a tracked crate between two releases.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpstreamChanges {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub from: String,
+    pub to: String,
+    /// Tags the log was taken between, e.g. `v0.3.0..v0.4.0`.
+    pub range: String,
+    /// Commits in the range.
+    pub commits: usize,
+    /// Markdown "what actually changed" summary.
+    pub summary: String,
+    /// Whether `summary` was written by the LLM rather than listing the
+    /// commit subjects.
+    pub summarized: bool,
+}
+
+// ─── Settings ─────────────────────────────────────────────────────────────────
+
+/// Whether bumps get an upstream commit-log summary (`UPSTREAM_LOGS`,
+/// default on).
+pub fn enabled() -> bool {
+    !matches!(
+        std::env::var("UPSTREAM_LOGS").as_deref().map(str::trim),
+        Ok("0" | "false" | "off")
+    )
+}
+
+// ─── Fetching ─────────────────────────────────────────────────────────────────
+
+/// Tag names a release of `version` may carry.
+fn tag_candidates(version: &str) -> [String; 2] {
+    [format!("v{version}"), version.to_string()]
+}
+
+/// Commits of `crate_name` between the `from` and `to` release tags, newest
+/// first, with the range they were taken from.
+///
+/// The log comes from the local checkout at `base_dir/{crate_name}` when it
+/// has both tags, else, when online, from GitHub's compare API on the source
+/// `repo` (`owner/name`).  Tags are `v{version}` or `{version}`.
+pub fn fetch(
+    base_dir: &Path,
+    repo: &str,
+    crate_name: &str,
+    from: &str,
+    to: &str,
+    offline: bool,
+) -> Option<(String, Vec<UpstreamCommit>)> {
+    let checkout = base_dir.join(crate_name);
+    if checkout.join(".git").exists() {
+        for (from_tag, to_tag) in tag_pairs(from, to) {
+            let range = format!("{from_tag}..{to_tag}");
+            match local_log(&checkout, &range) {
+                Ok(commits) => return Some((range, commits)),
+                Err(e) => debug!(crate = crate_name, %range, error = %e, "no local log"),
+            }
+        }
+    }
+    if offline {
+        return None;
+    }
+    for (from_tag, to_tag) in tag_pairs(from, to) {
+        let endpoint = format!("repos/{repo}/compare/{from_tag}...{to_tag}");
+        match gh_api(&[&endpoint]).and_then(|text| parse_compare(&text)) {
+            Ok(commits) => return Some((format!("{from_tag}..{to_tag}"), commits)),
+            Err(e) => debug!(crate = crate_name, %endpoint, error = %e, "no upstream log"),
+        }
+    }
+    None
+}
+
+/// `(from, to)` tag pairs, prefixed first.
+fn tag_pairs(from: &str, to: &str) -> Vec<(String, String)> {
+    let [from_v, from_bare] = tag_candidates(from);
+    let [to_v, to_bare] = tag_candidates(to);
+    vec![(from_v, to_v), (from_bare, to_bare)]
+}
+
+fn local_log(checkout: &Path, range: &str) -> Result<Vec<UpstreamCommit>> {
+    let output = Command::new("git")
+        .args(["log", "--no-merges", "--format=%h%x09%s", range, "--"])
+        .current_dir(checkout)
+        .output()
+        .context("run git log")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "git log {range}: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (sha, subject) = line.split_once('\t')?;
+            Some(UpstreamCommit {
+                sha: sha.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct Compare {
+    #[serde(default)]
+    commits: Vec<CompareCommit>,
+}
+
+#[derive(Deserialize)]
+struct CompareCommit {
+    sha: String,
+    commit: CompareMessage,
+    #[serde(default)]
+    parents: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct CompareMessage {
+    message: String,
+}
+
+/// Commits of a GitHub compare response, newest first and without merges
+/// (the API lists them oldest first).
+fn parse_compare(text: &str) -> Result<Vec<UpstreamCommit>> {
+    let compare: Compare = serde_json::from_str(text).context("parse compare response")?;
+    Ok(compare
+        .commits
+        .into_iter()
+        .rev()
+        .filter(|c| c.parents.len() < 2)
+        .map(|c| UpstreamCommit {
+            sha: c.sha.chars().take(7).collect(),
+            subject: c
+                .commit
+                .message
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        })
+        .collect())
+}
+
+// ─── Summaries ────────────────────────────────────────────────────────────────
+
+/// Prompt asking for a short Markdown summary of `commits`.
+fn prompt(crate_name: &str, from: &str, to: &str, commits: &[UpstreamCommit]) -> String {
+    let mut log: Vec<String> = commits
+        .iter()
+        .take(MAX_PROMPT_COMMITS)
+        .map(|c| format!("{} {}", c.sha, c.subject))
+        .collect();
+    if commits.len() > MAX_PROMPT_COMMITS {
+        log.push(format!(
+            "… {} older commits omitted",
+            commits.len() - MAX_PROMPT_COMMITS
+        ));
+    }
+    format!(
+        "These are the commits of the Rust crate `{crate_name}` between releases \
+         {from} and {to}, newest first:\n{}\n\n\
+         Summarize what actually changed for a downstream user in at most 6 \
+         Markdown bullets: new features, behaviour changes, fixes and anything \
+         that may break callers.  Skip chores (CI, formatting, version bumps).  \
+         Reply with the bullets only.",
+        log.join("\n")
+    )
+}
+
+/// Plain list of the newest commit subjects.
+fn commit_list(commits: &[UpstreamCommit]) -> String {
+    let mut lines: Vec<String> = commits
+        .iter()
+        .take(MAX_LISTED_COMMITS)
+        .map(|c| format!("- {} ({})", c.subject, c.sha))
+        .collect();
+    if commits.len() > MAX_LISTED_COMMITS {
+        lines.push(format!(
+            "- … and {} more",
+            commits.len() - MAX_LISTED_COMMITS
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Summarizes `commits` through `llm`; falls back to listing their subjects
+/// without one, or when the gateway fails or answers nothing.
+pub async fn summarize(
+    llm: Option<&Llm<'_>>,
+    system: &str,
+    crate_name: &str,
+    from: &str,
+    to: &str,
+    range: String,
+    commits: &[UpstreamCommit],
+) -> UpstreamChanges {
+    let mut changes = UpstreamChanges {
+        crate_name: crate_name.to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+        range,
+        commits: commits.len(),
+        summary: commit_list(commits),
+        summarized: false,
+    };
+    let Some(llm) = llm.filter(|_| !commits.is_empty()) else {
+        return changes;
+    };
+    match llm
+        .complete(
+            Purpose::UpstreamSummary,
+            system,
+            &prompt(crate_name, from, to, commits),
+        )
+        .await
+    {
+        Ok(response) if !response.trim().is_empty() => {
+            changes.summary = response.trim().to_string();
+            changes.summarized = true;
+        }
+        Ok(_) => debug!(
+            crate = crate_name,
+            "empty upstream summary — listing commits"
+        ),
+        Err(e) => {
+            warn!(crate = crate_name, error = %e, "upstream summary failed — listing commits")
+        }
+    }
+    changes
+}
+
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+/// Markdown `## What changed upstream` section for a PR description; empty
+/// without changes.
+pub fn render_markdown(changes: &[UpstreamChanges]) -> String {
+    if changes.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("## What changed upstream\n\n");
+    for c in changes {
+        out.push_str(&format!(
+            "### `{}` {} → {} ({} commit{}, `{}`)\n\n{}\n\n",
+            c.crate_name,
+            c.from,
+            c.to,
+            c.commits,
+            if c.commits == 1 { "" } else { "s" },
+            c.range,
+            if c.summary.is_empty() {
+                "No commits between the release tags."
+            } else {
+                &c.summary
+            }
+        ));
+    }
+    out
+}
+
+/// Plain-text commit-message body with the summaries; empty without
+/// changes.
+pub fn render_commit_body(changes: &[UpstreamChanges]) -> String {
+    changes
+        .iter()
+        .filter(|c| !c.summary.is_empty())
+        .map(|c| {
+            format!(
+                "{} {} -> {} upstream:\n{}",
+                c.crate_name, c.from, c.to, c.summary
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evo_agent_sdk::prelude::GatewayClient;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?}");
+    }
+
+    #[test]
+    fn test_fetch_local_log_between_tags() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let checkout = dir.path().join("evo-common");
+        std::fs::create_dir_all(&checkout).unwrap();
+        git(&checkout, &["init", "-q"]);
+        for (subject, tag) in [
+            ("release 0.3.0", Some("v0.3.0")),
+            ("feat: add Frame::split", None),
+            ("fix: reject oversized frames", None),
+            ("release 0.4.0", Some("v0.4.0")),
+            ("wip", None),
+        ] {
+            git(&checkout, &["commit", "-q", "--allow-empty", "-m", subject]);
+            if let Some(tag) = tag {
+                git(&checkout, &["tag", tag]);
+            }
+        }
+
+        let (range, commits) = fetch(
+            dir.path(),
+            "o/evo-common",
+            "evo-common",
+            "0.3.0",
+            "0.4.0",
+            true,
+        )
+        .unwrap();
+        assert_eq!(range, "v0.3.0..v0.4.0");
+        let subjects: Vec<&str> = commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(
+            subjects,
+            vec![
+                "release 0.4.0",
+                "fix: reject oversized frames",
+                "feat: add Frame::split"
+            ]
+        );
+        assert!(
+            fetch(
+                dir.path(),
+                "o/evo-common",
+                "evo-common",
+                "0.3.0",
+                "0.9.0",
+                true
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_compare_skips_merges() {
+        let text = r#"{"commits":[
+            {"sha":"aaaaaaaaaa","commit":{"message":"feat: one\n\nbody"},"parents":[{}]},
+            {"sha":"bbbbbbbbbb","commit":{"message":"Merge pull request #2"},"parents":[{},{}]},
+            {"sha":"cccccccccc","commit":{"message":"fix: two"},"parents":[{}]}
+        ]}"#;
+        let commits = parse_compare(text).unwrap();
+        assert_eq!(
+            commits,
+            vec![
+                UpstreamCommit {
+                    sha: "ccccccc".to_string(),
+                    subject: "fix: two".to_string()
+                },
+                UpstreamCommit {
+                    sha: "aaaaaaa".to_string(),
+                    subject: "feat: one".to_string()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summarize_falls_back_to_commit_list() {
+        let commits: Vec<UpstreamCommit> = (0..25)
+            .map(|i| UpstreamCommit {
+                sha: format!("{i:07}"),
+                subject: format!("change {i}"),
+            })
+            .collect();
+        let changes = summarize(
+            Some(&Llm::new(&GatewayClient, Default::default())),
+            "",
+            "evo-common",
+            "0.3.0",
+            "0.4.0",
+            "v0.3.0..v0.4.0".to_string(),
+            &commits,
+        )
+        .await;
+        assert!(!changes.summarized);
+        assert_eq!(changes.commits, 25);
+        assert!(changes.summary.starts_with("- change 0 (0000000)"));
+        assert!(changes.summary.ends_with("- … and 5 more"));
+
+        let markdown = render_markdown(&[changes]);
+        assert!(markdown.contains("### `evo-common` 0.3.0 → 0.4.0 (25 commits, `v0.3.0..v0.4.0`)"));
+    }
+}
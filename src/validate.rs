@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+use tracing::{debug, warn};
+
+// ─── Manifest validation gate ─────────────────────────────────────────────────
+
+/// How far a patched manifest could be validated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validation {
+    /// Parsed and accepted by `cargo metadata`.
+    Verified,
+    /// Parsed only; cargo could not check it, for the given reason.
+    Unverified(String),
+}
+
+/// Validates a patched Cargo.toml before it is allowed to be committed.
+///
+/// 1. Re-parses `content` with `toml_edit` — a patcher bug that produces
+///    invalid TOML is rejected outright.
+/// 2. Writes it to a scratch dir (with a stub `src/lib.rs`) and runs
+///    `cargo metadata --no-deps` so cargo itself confirms the manifest is
+///    still well-formed.
+///
+/// Step 2 is skipped, and the manifest reported [`Validation::Unverified`],
+/// when the manifest inherits fields from a workspace (`*.workspace = true`)
+/// or is a workspace root listing `members`, since neither can be resolved
+/// outside the original checkout, or when `cargo` is not installed.
+pub fn validate_manifest(content: &str) -> Result<Validation> {
+    let doc = content
+        .parse::<toml_edit::DocumentMut>()
+        .context("patched manifest no longer parses as TOML")?;
+
+    if uses_workspace_inheritance(content) {
+        debug!("manifest uses workspace inheritance — parse-only validation");
+        return Ok(Validation::Unverified(
+            "inherits from its workspace".to_string(),
+        ));
+    }
+    if has_workspace_members(&doc) {
+        debug!("manifest is a workspace root with members — parse-only validation");
+        return Ok(Validation::Unverified(
+            "workspace root with members".to_string(),
+        ));
+    }
+
+    let dir = scratch_manifest_dir(content, None)?;
+    let manifest = dir.path().join("Cargo.toml");
+
+    let output = match Command::new("cargo")
+        .args([
+            "metadata",
+            "--no-deps",
+            "--format-version",
+            "1",
+            "--offline",
+        ])
+        .arg("--manifest-path")
+        .arg(&manifest)
+        .output()
+    {
+        Ok(o) => o,
+        Err(e) => {
+            warn!(error = %e, "cargo not available — parse-only manifest validation");
+            return Ok(Validation::Unverified(format!("cargo not available: {e}")));
+        }
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "cargo metadata rejected patched manifest: {}",
+            stderr.trim()
+        );
+    }
+    Ok(Validation::Verified)
+}
+
+/// Whether the manifest's `[workspace]` lists any `members`, which cargo
+/// would try to load next to the scratch copy.
+fn has_workspace_members(doc: &toml_edit::DocumentMut) -> bool {
+    doc.get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(|members| members.as_array())
+        .is_some_and(|members| !members.is_empty())
+}
+
+/// Writes `content` as `Cargo.toml` into a fresh temp dir, with a stub
+/// `src/lib.rs` and, when given, a copy of `lockfile` as `Cargo.lock`.
+///
+/// Shared sandbox for the post-patch checks (validation, audit, deny).
+pub fn scratch_manifest_dir(content: &str, lockfile: Option<&Path>) -> Result<TempDir> {
+    let dir = tempfile::tempdir().context("create manifest sandbox")?;
+    // cargo refuses manifests without any target, so give it an empty lib.
+    std::fs::create_dir(dir.path().join("src")).context("create sandbox src dir")?;
+    std::fs::write(dir.path().join("src/lib.rs"), "").context("write sandbox lib.rs")?;
+    let manifest = dir.path().join("Cargo.toml");
+    std::fs::write(&manifest, content).with_context(|| format!("write {}", manifest.display()))?;
+    if let Some(lock) = lockfile {
+        std::fs::copy(lock, dir.path().join("Cargo.lock"))
+            .with_context(|| format!("copy {}", lock.display()))?;
+    }
+    Ok(dir)
+}
+
+/// Returns `true` if the manifest inherits any key from its workspace.
+pub fn uses_workspace_inheritance(content: &str) -> bool {
+    // SAFETY: the pattern is a literal known to be valid.
+    let re = Regex::new(r"(?m)(\.workspace|\bworkspace)\s*=\s*true").expect("regex is valid");
+    re.is_match(content)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_manifest_accepts_valid() {
+        let toml = "[package]\nname = \"x\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nevo-common = \"0.3\"\n";
+        assert_eq!(validate_manifest(toml).unwrap(), Validation::Verified);
+    }
+
+    #[test]
+    fn test_validate_manifest_workspace_root_is_unverified() {
+        let toml = "[workspace]\nmembers = [\"crates/a\", \"crates/b\"]\n\n[workspace.dependencies]\nevo-common = \"0.3\"\n";
+        assert_eq!(
+            validate_manifest(toml).unwrap(),
+            Validation::Unverified("workspace root with members".to_string())
+        );
+        let inherits = "[package]\nname = \"x\"\nversion.workspace = true\n";
+        assert!(matches!(
+            validate_manifest(inherits).unwrap(),
+            Validation::Unverified(_)
+        ));
+    }
+
+    #[test]
+    fn test_validate_manifest_rejects_invalid_toml() {
+        let toml = "[package]\nname = = \"x\"\n";
+        assert!(validate_manifest(toml).is_err());
+    }
+
+    #[test]
+    fn test_validate_manifest_rejects_bad_dependency_shape() {
+        // Valid TOML, but cargo refuses a dependency given as an integer.
+        let toml = "[package]\nname = \"x\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nevo-common = 3\n";
+        assert!(validate_manifest(toml).is_err());
+    }
+
+    #[test]
+    fn test_uses_workspace_inheritance() {
+        assert!(uses_workspace_inheritance("version.workspace = true\n"));
+        assert!(uses_workspace_inheritance("serde = { workspace = true }\n"));
+        assert!(!uses_workspace_inheritance(
+            "[dependencies]\nserde = \"1\"\n"
+        ));
+    }
+}
@@ -1,5 +1,24 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+use crate::fixtures::RegistryHttp;
+use crate::registry::Registry;
+
+/// `User-Agent` sent to crates.io, as required by its crawler policy.
+pub const USER_AGENT: &str = "evo-kernel-agent-update/0.1.0 (github.com/ai-evo-agents)";
+
+/// Attempts of one crates.io version lookup.
+pub const LOOKUP_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled for each later one.
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+
+/// Upper bound of any single retry delay.
+const BACKOFF_CAP: Duration = Duration::from_secs(30);
 
 // ─── crates.io API types ──────────────────────────────────────────────────────
 
@@ -7,95 +26,522 @@ use serde::{Deserialize, Serialize};
 struct CratesIoCrate {
     #[serde(rename = "crate")]
     krate: CratesIoInfo,
+    /// Every published version; absent from some mirrors.
+    #[serde(default)]
+    versions: Vec<CratesIoVersion>,
 }
 
 #[derive(Debug, Deserialize)]
 struct CratesIoInfo {
-    max_stable_version: String,
+    max_stable_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CratesIoVersion {
+    num: String,
+    #[serde(default)]
+    yanked: bool,
 }
 
 // ─── Public types ─────────────────────────────────────────────────────────────
 
+/// A non-success HTTP status from a registry API.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HttpStatus(pub reqwest::StatusCode);
+
+impl fmt::Display for HttpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "registry returned {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpStatus {}
+
+/// Outcome of a version lookup made with [`latest_crate_version_with_retry`].
+#[derive(Debug)]
+pub struct Lookup {
+    pub result: Result<String>,
+    /// Requests made, at most [`LOOKUP_ATTEMPTS`].
+    pub attempts: u32,
+}
+
+/// Kind of change from a requirement to a newer (or older) version.
+///
+/// `major` follows cargo's caret rules, so `0.3 → 0.4` is major.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BumpKind {
+    Major,
+    Minor,
+    Patch,
+    /// The new version is a pre-release (`1.0.0-rc.1`).
+    Prerelease,
+    /// The new version is older than the requirement.
+    Downgrade,
+}
+
 /// Result of a version comparison for a single crate.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct VersionReport {
     pub crate_name: String,
     pub current: String,
     pub latest: String,
     pub needs_update: bool,
+    pub bump_kind: BumpKind,
 }
 
 // ─── Version detection ───────────────────────────────────────────────────────
 
-/// Calls the crates.io API and returns the latest stable version string for a crate.
+/// Calls the API of `registry` and returns the latest stable version string
+/// for a crate.
 ///
 /// Uses the `User-Agent` header required by crates.io policy.
-pub async fn latest_crate_version(client: &reqwest::Client, crate_name: &str) -> Result<String> {
-    let url = format!("https://crates.io/api/v1/crates/{crate_name}");
-    let resp = client
-        .get(&url)
-        .header(
-            "User-Agent",
-            "evo-kernel-agent-update/0.1.0 (github.com/ai-evo-agents)",
-        )
-        .send()
-        .await
-        .with_context(|| format!("HTTP request to crates.io for {crate_name}"))?;
-
-    if !resp.status().is_success() {
-        anyhow::bail!(
-            "crates.io returned {} for crate {crate_name}",
-            resp.status()
-        );
+pub async fn latest_crate_version(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+) -> Result<String> {
+    let url = format!("{}/crates/{crate_name}", registry.api.trim_end_matches('/'));
+    let resp = client.get(&url).await?;
+
+    if !resp.status.is_success() {
+        return Err(anyhow::Error::new(HttpStatus(resp.status))
+            .context(format!("look up crate {crate_name}")));
     }
 
-    let data: CratesIoCrate = resp
-        .json()
-        .await
-        .with_context(|| format!("parse crates.io response for {crate_name}"))?;
+    let data: CratesIoCrate = serde_json::from_slice(&resp.body)
+        .with_context(|| format!("parse registry response for {crate_name}"))?;
 
-    Ok(data.krate.max_stable_version)
+    resolve_latest(&data).with_context(|| format!("{crate_name} has no stable release"))
 }
 
-/// Reads the current simple version of a dependency from a Cargo.toml string.
+/// The highest published version that is neither yanked nor a pre-release;
+/// the registry's `max_stable_version` when the response lists no versions.
+fn resolve_latest(data: &CratesIoCrate) -> Option<String> {
+    if data.versions.is_empty() {
+        return data.krate.max_stable_version.clone();
+    }
+    data.versions
+        .iter()
+        .filter(|v| !v.yanked && !is_prerelease(&v.num))
+        .map(|v| v.num.as_str())
+        .fold(None, |best: Option<&str>, v| match best {
+            Some(b) if !needs_update(b, v) => Some(b),
+            _ => Some(v),
+        })
+        .map(str::to_string)
+}
+
+/// Whether `version` carries a pre-release suffix (`1.0.0-beta.1`); build
+/// metadata (`1.0.0+build-5`) does not count.
+pub fn is_prerelease(version: &str) -> bool {
+    version.split('+').next().is_some_and(|v| v.contains('-'))
+}
+
+/// [`latest_crate_version`] with up to [`LOOKUP_ATTEMPTS`] attempts.
+///
+/// Transport errors, `429` and `5xx` responses are retried after a jittered,
+/// exponentially growing delay capped at 30 s; other statuses (e.g. `404`
+/// for an unknown crate) fail at once.
+pub async fn latest_crate_version_with_retry(
+    client: &RegistryHttp,
+    registry: &Registry,
+    crate_name: &str,
+) -> Lookup {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let result = latest_crate_version(client, registry, crate_name).await;
+        match &result {
+            Err(e) if attempts < LOOKUP_ATTEMPTS && is_transient(e) => {
+                let delay = backoff(attempts, jitter());
+                warn!(crate = crate_name, attempt = attempts, delay_ms = delay.as_millis() as u64, error = %e, "registry lookup failed — retrying");
+                tokio::time::sleep(delay).await;
+            }
+            _ => return Lookup { result, attempts },
+        }
+    }
+}
+
+/// Whether a failed lookup may succeed when repeated.
+fn is_transient(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<HttpStatus>() {
+        Some(HttpStatus(status)) => {
+            status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        }
+        None => true,
+    }
+}
+
+/// Delay before retry number `attempt` (1-based): between half and all of
+/// `BACKOFF_BASE · 2^(attempt-1)`, by `jitter` in `[0, 1)`, at most
+/// [`BACKOFF_CAP`].
+fn backoff(attempt: u32, jitter: f64) -> Duration {
+    let full = BACKOFF_BASE
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(BACKOFF_CAP);
+    full.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+/// A value in `[0, 1)` from the clock's sub-second nanoseconds.
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    f64::from(nanos) / 1e9
+}
+
+/// Dependency tables of a manifest, at the top level and under each
+/// `[target.*]`.
+pub const DEP_SECTIONS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The dependency tables of a parsed manifest in document order, named like
+/// `dev-dependencies`, `target.<cfg>.dependencies` or
+/// `workspace.dependencies` — the sections the patchers rewrite.
+pub fn dependency_sections(doc: &toml_edit::DocumentMut) -> Vec<(String, &toml_edit::Item)> {
+    let mut sections = Vec::new();
+    for (key, item) in doc.as_table().iter() {
+        match key {
+            name if DEP_SECTIONS.contains(&name) => sections.push((name.to_string(), item)),
+            "target" => {
+                let Some(targets) = item.as_table_like() else {
+                    continue;
+                };
+                for (cfg, target) in targets.iter() {
+                    let Some(target) = target.as_table_like() else {
+                        continue;
+                    };
+                    for (kind, deps) in target.iter() {
+                        if DEP_SECTIONS.contains(&kind) {
+                            sections.push((format!("target.{cfg}.{kind}"), deps));
+                        }
+                    }
+                }
+            }
+            "workspace" => {
+                if let Some(deps) = item.as_table_like().and_then(|ws| ws.get("dependencies")) {
+                    sections.push(("workspace.dependencies".to_string(), deps));
+                }
+            }
+            _ => {}
+        }
+    }
+    sections
+}
+
+/// Every version requirement of a dependency in a Cargo.toml string, as
+/// `(section, requirement)` pairs in document order.
 ///
 /// Handles both:
 /// - `dep_name = "X.Y.Z"` (simple string)
-/// - `dep_name = { version = "X.Y.Z", ... }` (table form)
+/// - `dep_name = { version = "X.Y.Z", ... }` (inline or block table)
+///
+/// Path dependencies and `workspace = true` entries carry no requirement.
+pub fn dep_requirements(cargo_toml: &str, dep_name: &str) -> Vec<(String, String)> {
+    let Ok(doc) = cargo_toml.parse::<toml_edit::DocumentMut>() else {
+        return Vec::new();
+    };
+    dependency_sections(&doc)
+        .into_iter()
+        .filter_map(|(section, deps)| {
+            let dep = deps.as_table_like()?.get(dep_name)?;
+            if let Some(ver_str) = dep.as_str() {
+                return Some((section, ver_str.to_string()));
+            }
+            let table = dep.as_table_like()?;
+            if table.get("path").is_some() {
+                // Path deps have no semver to compare.
+                return None;
+            }
+            let version = table.get("version")?.as_str()?;
+            Some((section, version.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the current version requirement of a dependency from a Cargo.toml
+/// string: the oldest one among the sections of [`dep_requirements`], as it
+/// is what still needs updating.
 pub fn current_dep_version(cargo_toml: &str, dep_name: &str) -> Option<String> {
-    let doc: toml_edit::DocumentMut = cargo_toml.parse().ok()?;
-    let deps = doc.get("dependencies")?;
+    dep_requirements(cargo_toml, dep_name)
+        .into_iter()
+        .map(|(_, requirement)| requirement)
+        .reduce(|oldest, requirement| {
+            if needs_update(&requirement, &oldest) {
+                requirement
+            } else {
+                oldest
+            }
+        })
+}
 
-    let dep = deps.get(dep_name)?;
+// ─── Bump classification ─────────────────────────────────────────────────────
 
-    if let Some(ver_str) = dep.as_str() {
-        // Simple: `dep = "1.2"`
-        return Some(ver_str.to_string());
+impl BumpKind {
+    /// Classifies the change from requirement `current` to version `latest`.
+    pub fn of(current: &str, latest: &str) -> Self {
+        if needs_update(latest, bare(current)) {
+            BumpKind::Downgrade
+        } else if is_prerelease(latest) {
+            BumpKind::Prerelease
+        } else {
+            Self::size(current, latest)
+        }
     }
 
-    // Inline table: `dep = { version = "1.2", features = [...] }`
-    if let Some(table) = dep.as_inline_table() {
-        if table.get("path").is_some() {
-            return None;
+    /// `Major`, `Minor` or `Patch` from the numeric components alone.
+    pub fn size(current: &str, latest: &str) -> Self {
+        let (from, to) = (parse_semver(bare(current)), parse_semver(bare(latest)));
+        if !semver_compatible(current, latest) {
+            BumpKind::Major
+        } else if (from.0, from.1) != (to.0, to.1) {
+            BumpKind::Minor
+        } else {
+            BumpKind::Patch
         }
-        if let Some(v) = table.get("version") {
-            return v.as_str().map(|s| s.to_string());
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BumpKind::Major => "major",
+            BumpKind::Minor => "minor",
+            BumpKind::Patch => "patch",
+            BumpKind::Prerelease => "prerelease",
+            BumpKind::Downgrade => "downgrade",
         }
     }
+}
 
-    // Block table:
-    // [dependencies.dep]
-    // version = "1.2"
-    if let Some(table) = dep.as_table() {
-        if table.get("path").is_some() {
+impl fmt::Display for BumpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl VersionReport {
+    /// The comparison of requirement `current` with version `latest`.
+    pub fn new(crate_name: &str, current: &str, latest: &str) -> Self {
+        Self {
+            crate_name: crate_name.to_string(),
+            current: current.to_string(),
+            latest: latest.to_string(),
+            needs_update: needs_update(current, latest),
+            bump_kind: BumpKind::of(current, latest),
+        }
+    }
+}
+
+/// `v` without a requirement operator (`^`, `=`, `~`).
+fn bare(v: &str) -> &str {
+    v.trim_start_matches(['^', '=', '~', ' '])
+}
+
+// ─── Requirements ─────────────────────────────────────────────────────────────
+
+/// A version with its pre-release identifiers; build metadata is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Semver {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<String>,
+}
+
+impl Semver {
+    fn parse(v: &str) -> Option<Self> {
+        let v = v.trim().split('+').next()?;
+        let (numbers, pre) = match v.split_once('-') {
+            Some((numbers, pre)) => (numbers, pre.split('.').map(str::to_string).collect()),
+            None => (v, Vec::new()),
+        };
+        let mut parts = numbers.split('.').map(|p| p.parse::<u64>().ok());
+        let version = Self {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+            pre,
+        };
+        parts.next().is_none().then_some(version)
+    }
+
+    fn triple(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl Ord for Semver {
+    /// Semver precedence: a release ranks above its pre-releases, whose
+    /// numeric identifiers rank below alphanumeric ones.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        self.triple().cmp(&other.triple()).then_with(|| {
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (a, b) in self.pre.iter().zip(&other.pre) {
+                        let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+                            (Ok(a), Ok(b)) => a.cmp(&b),
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => a.cmp(b),
+                        };
+                        if order != Ordering::Equal {
+                            return order;
+                        }
+                    }
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            }
+        })
+    }
+}
+
+impl PartialOrd for Semver {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// One comparator of a requirement, e.g. `>=1.2`, `~0.3.1` or `1.*`.
+#[derive(Debug, Clone, PartialEq)]
+struct Comparator {
+    op: &'static str,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<String>,
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let op = ["<=", ">=", "=", ">", "<", "~", "^"]
+            .into_iter()
+            .find(|op| text.starts_with(op))
+            .unwrap_or("^");
+        let rest = text.trim_start_matches(op).trim();
+        let rest = rest.split('+').next()?;
+        let (numbers, pre) = match rest.split_once('-') {
+            Some((numbers, pre)) => (numbers, pre.split('.').map(str::to_string).collect()),
+            None => (rest, Vec::new()),
+        };
+        let mut parts = numbers.split('.');
+        // `*`, `1.*` and `1.2.*` leave the remaining components open.
+        let mut component = || match parts.next() {
+            None | Some("*" | "x" | "X") => Ok(None),
+            Some(p) => p.parse::<u64>().map(Some).map_err(|_| ()),
+        };
+        let major = component().ok()?;
+        let minor = component().ok()?;
+        let patch = component().ok()?;
+        if parts.next().is_some() {
             return None;
         }
-        if let Some(v) = table.get("version") {
-            return v.as_str().map(|s| s.to_string());
+        let Some(major) = major else {
+            // A bare wildcard matches every release.
+            return Some(Self {
+                op: "*",
+                major: 0,
+                minor: None,
+                patch: None,
+                pre,
+            });
+        };
+        let op = if numbers.contains(['*', 'x', 'X']) && op == "^" {
+            "="
+        } else {
+            op
+        };
+        Some(Self {
+            op,
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    /// The lowest version the comparator names, missing components as 0.
+    fn floor(&self) -> Semver {
+        Semver {
+            major: self.major,
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            pre: self.pre.clone(),
+        }
+    }
+
+    /// The first version past a partial comparator's last component
+    /// (`1` → `2.0.0`, `1.2` → `1.3.0`).
+    fn ceiling(&self) -> Semver {
+        let (major, minor) = match self.minor {
+            None => (self.major + 1, 0),
+            Some(minor) => (self.major, minor + 1),
+        };
+        Semver {
+            major,
+            minor,
+            patch: 0,
+            pre: Vec::new(),
         }
     }
 
-    None
+    fn matches(&self, v: &Semver) -> bool {
+        let floor = self.floor();
+        match self.op {
+            "*" => true,
+            "=" if self.patch.is_some() => *v == floor,
+            "=" => *v >= floor && *v < self.ceiling(),
+            ">" if self.patch.is_some() => *v > floor,
+            ">" => *v >= self.ceiling(),
+            ">=" => *v >= floor,
+            "<" => *v < floor,
+            "<=" if self.patch.is_some() => *v <= floor,
+            "<=" => *v < self.ceiling(),
+            "~" => *v >= floor && *v < self.ceiling(),
+            _ => {
+                // Caret: the leftmost non-zero component may not change.
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => (0, 0, patch + 1),
+                    (0, Some(minor), _) => (0, minor + 1, 0),
+                    (0, None, _) => (1, 0, 0),
+                    (major, _, _) => (major + 1, 0, 0),
+                };
+                *v >= floor && v.triple() < upper
+            }
+        }
+    }
+}
+
+/// Whether `candidate` satisfies the cargo requirement `current_req`.
+///
+/// Handles bare versions (caret by default, with cargo's pre-1.0 rules),
+/// `^`, `~`, `=`, `<`, `<=`, `>`, `>=`, wildcards (`*`, `1.*`, `1.2.*`) and
+/// comma-separated comparators.  A pre-release only satisfies a requirement
+/// with a pre-release comparator on the same `major.minor.patch`.  An
+/// unparseable requirement or version satisfies nothing.
+pub fn requirement_satisfied(current_req: &str, candidate: &str) -> bool {
+    let Some(version) = Semver::parse(candidate) else {
+        return false;
+    };
+    let Some(comparators) = current_req
+        .split(',')
+        .map(Comparator::parse)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    if !version.pre.is_empty()
+        && !comparators
+            .iter()
+            .any(|c| !c.pre.is_empty() && c.floor().triple() == version.triple())
+    {
+        return false;
+    }
+    comparators.iter().all(|c| c.matches(&version))
 }
 
 /// Compares two semver strings. Returns `true` if `latest` is strictly newer than `current`.
@@ -105,6 +551,23 @@ pub fn needs_update(current: &str, latest: &str) -> bool {
     parse_semver(latest) > parse_semver(current)
 }
 
+/// Whether two version requirements select the same semver-compatible
+/// series under cargo's caret rules (`1.x`, `0.3.x`, `0.0.7`).
+///
+/// A workspace whose members disagree here resolves two copies of the crate.
+pub fn semver_compatible(a: &str, b: &str) -> bool {
+    compat_series(a) == compat_series(b)
+}
+
+/// The leftmost non-zero component (and everything before it).
+fn compat_series(v: &str) -> (u64, u64, u64) {
+    match parse_semver(bare(v)) {
+        (0, 0, patch) => (0, 0, patch),
+        (0, minor, _) => (0, minor, 0),
+        (major, _, _) => (major, 0, 0),
+    }
+}
+
 fn parse_semver(v: &str) -> (u64, u64, u64) {
     let parts: Vec<u64> = v
         .split(['.', '-', '+'])
@@ -124,6 +587,27 @@ fn parse_semver(v: &str) -> (u64, u64, u64) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_backoff_grows_with_jitter_and_cap() {
+        assert_eq!(backoff(1, 0.0), Duration::from_millis(500));
+        assert_eq!(backoff(1, 1.0), Duration::from_secs(1));
+        assert_eq!(backoff(3, 1.0), Duration::from_secs(4));
+        assert_eq!(backoff(10, 1.0), BACKOFF_CAP);
+        assert_eq!(backoff(40, 0.0), BACKOFF_CAP / 2);
+    }
+
+    #[test]
+    fn test_transient_failures() {
+        let status = |code: u16| {
+            anyhow::Error::new(HttpStatus(reqwest::StatusCode::from_u16(code).unwrap()))
+                .context("look up crate x")
+        };
+        assert!(is_transient(&status(503)));
+        assert!(is_transient(&status(429)));
+        assert!(!is_transient(&status(404)));
+        assert!(is_transient(&anyhow::anyhow!("connection reset")));
+    }
+
     #[test]
     fn test_needs_update_newer() {
         assert!(needs_update("0.2.0", "0.3.0"));
@@ -137,6 +621,129 @@ mod tests {
         assert!(!needs_update("0.3.0", "0.2.0"));
     }
 
+    #[test]
+    fn test_requirement_satisfied() {
+        // Caret by default, with pre-1.0 semantics.
+        assert!(requirement_satisfied("1.2", "1.9.0"));
+        assert!(requirement_satisfied("1.2.3", "1.2.4"));
+        assert!(!requirement_satisfied("1.2.3", "1.2.2"));
+        assert!(!requirement_satisfied("1.2", "2.0.0"));
+        assert!(requirement_satisfied("^0.3", "0.3.7"));
+        assert!(!requirement_satisfied("0.3", "0.4.0"));
+        assert!(requirement_satisfied("0.0.7", "0.0.7"));
+        assert!(!requirement_satisfied("0.0.7", "0.0.8"));
+        assert!(requirement_satisfied("0", "0.9.1"));
+        assert!(!requirement_satisfied("0", "1.0.0"));
+        // Tilde.
+        assert!(requirement_satisfied("~1.2.3", "1.2.9"));
+        assert!(!requirement_satisfied("~1.2.3", "1.3.0"));
+        assert!(requirement_satisfied("~1", "1.8.0"));
+        // Exact and wildcards.
+        assert!(requirement_satisfied("=1.2.3", "1.2.3"));
+        assert!(!requirement_satisfied("=1.2.3", "1.2.4"));
+        assert!(requirement_satisfied("=1.2", "1.2.9"));
+        assert!(requirement_satisfied("1.*", "1.7.0"));
+        assert!(!requirement_satisfied("1.2.*", "1.3.0"));
+        assert!(requirement_satisfied("*", "4.0.0"));
+        // Ranges.
+        assert!(requirement_satisfied(">=1.2, <2", "1.9.9"));
+        assert!(!requirement_satisfied(">=1.2, <2", "2.0.0"));
+        assert!(requirement_satisfied(">1.2", "1.3.0"));
+        assert!(!requirement_satisfied(">1.2", "1.2.5"));
+        assert!(requirement_satisfied("<=1.2", "1.2.9"));
+        // Pre-releases need an opt-in on the same version.
+        assert!(!requirement_satisfied("1.0", "1.1.0-beta.1"));
+        assert!(requirement_satisfied("1.0.0-beta.1", "1.0.0-beta.2"));
+        assert!(!requirement_satisfied("1.0.0-beta.2", "1.0.0-beta.1"));
+        assert!(requirement_satisfied("1.0.0-rc.1", "1.0.0"));
+        // Build metadata is ignored; garbage satisfies nothing.
+        assert!(requirement_satisfied("1.2", "1.2.0+build.5"));
+        assert!(!requirement_satisfied("git", "1.0.0"));
+        assert!(!requirement_satisfied("1.2", "latest"));
+    }
+
+    #[test]
+    fn test_semver_compatible() {
+        assert!(semver_compatible("0.3", "0.3.5"));
+        assert!(semver_compatible("1.2", "^1.9.0"));
+        assert!(!semver_compatible("0.3.0", "0.4.0"));
+        assert!(!semver_compatible("0.0.1", "0.0.2"));
+        assert!(!semver_compatible("1.0", "2.0"));
+    }
+
+    #[test]
+    fn test_resolve_latest_skips_yanked_and_prereleases() {
+        let data: CratesIoCrate = serde_json::from_value(serde_json::json!({
+            "crate": { "max_stable_version": "0.4.1" },
+            "versions": [
+                { "num": "1.0.0-rc.1", "yanked": false },
+                { "num": "0.4.1", "yanked": true },
+                { "num": "0.4.0+build-5", "yanked": false },
+                { "num": "0.3.9", "yanked": false },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(resolve_latest(&data), Some("0.4.0+build-5".to_string()));
+
+        let only_prereleases: CratesIoCrate = serde_json::from_value(serde_json::json!({
+            "crate": { "max_stable_version": null },
+            "versions": [{ "num": "0.1.0-alpha.1" }],
+        }))
+        .unwrap();
+        assert_eq!(resolve_latest(&only_prereleases), None);
+
+        let mirror: CratesIoCrate = serde_json::from_value(
+            serde_json::json!({ "crate": { "max_stable_version": "2.1.0" } }),
+        )
+        .unwrap();
+        assert_eq!(resolve_latest(&mirror), Some("2.1.0".to_string()));
+    }
+
+    #[test]
+    fn test_bump_kind() {
+        assert_eq!(BumpKind::of("0.3", "0.4.0"), BumpKind::Major);
+        assert_eq!(BumpKind::of("1.2", "2.0.0"), BumpKind::Major);
+        assert_eq!(BumpKind::of("1.2", "1.3.0"), BumpKind::Minor);
+        assert_eq!(BumpKind::of("^1.2.0", "1.2.5"), BumpKind::Patch);
+        assert_eq!(BumpKind::of("0.3.1", "0.3.2"), BumpKind::Patch);
+        assert_eq!(BumpKind::of("0.9", "1.0.0-rc.1"), BumpKind::Prerelease);
+        assert_eq!(BumpKind::of("0.4.1", "0.4.0"), BumpKind::Downgrade);
+        assert_eq!(BumpKind::size("0.9", "1.0.0-rc.1"), BumpKind::Major);
+
+        let report = VersionReport::new("evo-common", "0.3", "0.4.0");
+        assert!(report.needs_update);
+        assert_eq!(serde_json::to_value(&report).unwrap()["bump_kind"], "major");
+    }
+
+    #[test]
+    fn test_is_prerelease() {
+        assert!(is_prerelease("1.0.0-beta.1"));
+        assert!(!is_prerelease("1.0.0"));
+        assert!(!is_prerelease("1.0.0+build-5"));
+    }
+
+    #[tokio::test]
+    async fn test_latest_version_from_replayed_fixtures() {
+        let fixtures = crate::fixtures::Fixtures::replay(std::path::Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/registry"
+        )));
+        let client = RegistryHttp::new(reqwest::Client::new(), Some(fixtures));
+        let registry = Registry::crates_io();
+
+        // 0.5.0-beta.1 is a pre-release and 0.4.1 is yanked.
+        let latest = latest_crate_version(&client, &registry, "evo-common").await;
+        assert_eq!(latest.unwrap(), "0.4.0");
+
+        let missing = latest_crate_version_with_retry(&client, &registry, "evo-missing").await;
+        assert_eq!(missing.attempts, 1);
+        let err = missing.result.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<HttpStatus>(),
+            Some(&HttpStatus(reqwest::StatusCode::NOT_FOUND))
+        );
+    }
+
     #[test]
     fn test_current_dep_version_simple() {
         let toml = r#"
@@ -161,6 +768,44 @@ evo-agent-sdk = { path = "../evo-agents/evo-agent-sdk" }
         assert_eq!(current_dep_version(toml, "evo-agent-sdk"), None);
     }
 
+    #[test]
+    fn test_current_dep_version_other_sections() {
+        let toml = r#"
+[workspace]
+members = ["crates/*"]
+
+[workspace.dependencies]
+evo-common = "0.3"
+
+[dev-dependencies]
+evo-agent-sdk = { version = "0.2", features = ["test"] }
+
+[target.'cfg(unix)'.build-dependencies.evo-agent-sdk]
+version = "0.1.4"
+"#;
+        assert_eq!(
+            current_dep_version(toml, "evo-common"),
+            Some("0.3".to_string())
+        );
+        assert_eq!(
+            dep_requirements(toml, "evo-agent-sdk"),
+            vec![
+                ("dev-dependencies".to_string(), "0.2".to_string()),
+                (
+                    "target.cfg(unix).build-dependencies".to_string(),
+                    "0.1.4".to_string()
+                ),
+            ]
+        );
+        // The oldest requirement is the one to report.
+        assert_eq!(
+            current_dep_version(toml, "evo-agent-sdk"),
+            Some("0.1.4".to_string())
+        );
+        let member = "[dependencies]\nevo-common = { workspace = true }\n";
+        assert_eq!(current_dep_version(member, "evo-common"), None);
+    }
+
     #[test]
     fn test_current_dep_version_missing() {
         let toml = "[dependencies]\n";
@@ -0,0 +1,179 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::net::apply_to_command;
+use crate::sandbox::{MAX_OUTPUT_BYTES, tail};
+use crate::sbom;
+use crate::tokens::redact;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// cargo-vet's config, relative to the workspace root.
+const CONFIG_FILE: &str = "supply-chain/config.toml";
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+/// A locked crate no audit or exemption covers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnvettedCrate {
+    #[serde(rename(serialize = "crate"))]
+    pub name: String,
+    pub version: String,
+    /// Criteria the crate still needs (e.g. `["safe-to-deploy"]`).
+    #[serde(default)]
+    pub missing_criteria: Vec<String>,
+}
+
+/// Outcome of `cargo vet` on a patched checkout.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct VetReport {
+    /// cargo-vet's conclusion, e.g. `"success"` or `"fail (vetting)"`.
+    pub conclusion: String,
+    /// Unvetted crates the update introduced; crates already locked at the
+    /// same version before the update are left out.
+    pub unvetted: Vec<UnvettedCrate>,
+    /// Tail of cargo-vet's output when it did not succeed.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub output: String,
+}
+
+impl VetReport {
+    /// `true` when the update must be held: it introduces unvetted crates,
+    /// or cargo-vet failed for another reason (violations, errors).
+    pub fn is_violation(&self) -> bool {
+        !self.unvetted.is_empty()
+            || (self.conclusion != "success" && self.conclusion != "fail (vetting)")
+    }
+
+    /// Drops the unvetted crates `previous_lock` already locked at the same
+    /// version.
+    fn introduced_since(mut self, previous_lock: &str) -> Self {
+        self.unvetted
+            .retain(|c| !sbom::is_locked(previous_lock, &c.name, &c.version));
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonReport {
+    conclusion: String,
+    #[serde(default)]
+    failures: Vec<UnvettedCrate>,
+}
+
+// ─── Vetting ──────────────────────────────────────────────────────────────────
+
+/// Whether the `cargo vet` subcommand is installed (checked once).
+pub fn cargo_vet_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("cargo")
+            .args(["vet", "--version"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Whether the workspace at `root` is set up for cargo-vet.
+pub fn has_config(root: &Path) -> bool {
+    root.join(CONFIG_FILE).is_file()
+}
+
+/// Runs `cargo vet --locked` at `root`.  `previous_lock` is the lockfile
+/// before the update; failures it already contained are not blamed on the
+/// update.
+pub fn check(root: &Path, previous_lock: Option<&str>) -> Result<VetReport> {
+    let output = apply_to_command(&mut Command::new("cargo"))
+        .args(["vet", "--locked", "--output-format", "json"])
+        .current_dir(root)
+        .output()
+        .context("run cargo vet")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut report = parse_vet_output(&stdout).with_context(|| {
+        format!(
+            "cargo vet produced no report: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    })?;
+    if let Some(lock) = previous_lock {
+        report = report.introduced_since(lock);
+    }
+    if !output.status.success() {
+        let mut text = stdout.into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        report.output = redact(tail(&text, MAX_OUTPUT_BYTES));
+    }
+    Ok(report)
+}
+
+/// Parses the JSON report cargo-vet prints on stdout.
+fn parse_vet_output(stdout: &str) -> serde_json::Result<VetReport> {
+    let json: JsonReport = serde_json::from_str(stdout.trim())?;
+    Ok(VetReport {
+        conclusion: json.conclusion,
+        unvetted: json.failures,
+        output: String::new(),
+    })
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vet_output() {
+        let report = parse_vet_output(
+            r#"{"conclusion":"fail (vetting)","failures":[{"name":"tinyframe","version":"0.1.2","missing_criteria":["safe-to-deploy"]}],"suggest":null}"#,
+        )
+        .unwrap();
+        assert_eq!(report.unvetted[0].name, "tinyframe");
+        assert_eq!(report.unvetted[0].missing_criteria, vec!["safe-to-deploy"]);
+        assert!(report.is_violation());
+
+        let report = parse_vet_output(
+            r#"{"conclusion":"success","vetted_fully":[],"vetted_partially":[],"vetted_with_exemptions":[]}"#,
+        )
+        .unwrap();
+        assert!(!report.is_violation());
+
+        let report =
+            parse_vet_output(r#"{"conclusion":"fail (violation)","violations":{}}"#).unwrap();
+        assert!(report.is_violation());
+        assert!(parse_vet_output("error: no supply-chain").is_err());
+    }
+
+    #[test]
+    fn test_pre_existing_failures_are_not_blamed() {
+        let report = parse_vet_output(
+            r#"{"conclusion":"fail (vetting)","failures":[{"name":"tinyframe","version":"0.1.2"},{"name":"oldframe","version":"1.0.0"}]}"#,
+        )
+        .unwrap()
+        .introduced_since(
+            "version = 3\n\n[[package]]\nname = \"oldframe\"\nversion = \"1.0.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+        assert_eq!(report.unvetted.len(), 1);
+        assert_eq!(report.unvetted[0].name, "tinyframe");
+
+        let report = VetReport {
+            conclusion: "fail (vetting)".to_string(),
+            unvetted: Vec::new(),
+            output: String::new(),
+        };
+        assert!(!report.is_violation());
+    }
+
+    #[test]
+    fn test_has_config() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!has_config(dir.path()));
+        std::fs::create_dir_all(dir.path().join("supply-chain")).unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE), "").unwrap();
+        assert!(has_config(dir.path()));
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::backend::KingClient;
+use crate::config::ConfigStore;
+use crate::registry::{IndexEntry, Registry, parse_index};
+use crate::state::VersionCache;
+use crate::versions::{USER_AGENT, needs_update};
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// Release-watcher settings from the environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchConfig {
+    /// Delay between two polls of the index.
+    pub interval: Duration,
+    /// Crates whose releases start a run; every tracked crate when `None`.
+    pub crates: Option<Vec<String>>,
+}
+
+impl WatchConfig {
+    /// `Some` when `RELEASE_WATCH_INTERVAL_SECS` is a positive number; the
+    /// watched crates come from `RELEASE_WATCH_CRATES` (comma-separated).
+    pub fn from_env() -> Option<Self> {
+        let secs: u64 = std::env::var("RELEASE_WATCH_INTERVAL_SECS")
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&secs| secs > 0)?;
+        let crates = std::env::var("RELEASE_WATCH_CRATES").ok().map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|c| !c.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        });
+        Some(Self {
+            interval: Duration::from_secs(secs),
+            crates,
+        })
+    }
+}
+
+// ─── Index parsing ────────────────────────────────────────────────────────────
+
+/// Highest stable, non-yanked version among a crate's index entries.
+pub fn latest_stable(index: &[IndexEntry]) -> Option<String> {
+    index
+        .iter()
+        .filter(|e| !e.yanked && !e.vers.contains(['-', '+']))
+        .map(|e| e.vers.clone())
+        .reduce(|best, v| if needs_update(&best, &v) { v } else { best })
+}
+
+/// Crates whose `polled` version is newer than the one in `seen`.  Crates
+/// seen for the first time count as new only when `baseline` has an older
+/// version for them (a release published while the agent was down).
+pub fn new_releases(
+    seen: &HashMap<String, String>,
+    baseline: &HashMap<String, String>,
+    polled: &BTreeMap<String, String>,
+) -> Vec<(String, String)> {
+    polled
+        .iter()
+        .filter(|(crate_name, version)| {
+            match seen.get(*crate_name).or_else(|| baseline.get(*crate_name)) {
+                Some(known) => needs_update(known, version),
+                None => false,
+            }
+        })
+        .map(|(c, v)| (c.clone(), v.clone()))
+        .collect()
+}
+
+// ─── Watcher ──────────────────────────────────────────────────────────────────
+
+/// Polls the index every `watch.interval` and asks the `king` to start an
+/// update run as soon as a watched crate publishes.
+pub fn spawn(
+    http: reqwest::Client,
+    config: Arc<ConfigStore>,
+    watch: WatchConfig,
+    king: Arc<dyn KingClient>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        info!(interval_secs = watch.interval.as_secs(), crates = ?watch.crates, "release watcher started");
+        let mut etags: HashMap<String, (String, String)> = HashMap::new();
+        let mut seen: HashMap<String, String> = HashMap::new();
+        let mut interval = tokio::time::interval(watch.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let snapshot = config.current();
+            let crates: Vec<String> = match &watch.crates {
+                Some(crates) => crates.clone(),
+                None => snapshot
+                    .config
+                    .all_tracked_crates()
+                    .into_iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            };
+            let mut polled = BTreeMap::new();
+            for crate_name in &crates {
+                for (name, registry) in snapshot.config.registries.for_crate(crate_name) {
+                    match poll(&http, &registry, crate_name, &mut etags).await {
+                        Ok(Some(version)) => {
+                            polled.insert(crate_name.clone(), version);
+                            break;
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            debug!(crate = %crate_name, registry = %name, error = %e, "index poll failed");
+                        }
+                    }
+                }
+            }
+
+            let baseline = cached_versions(&crates);
+            let released = new_releases(&seen, &baseline, &polled);
+            if !released.is_empty() {
+                info!(released = ?released, "new release published — requesting an update run");
+                let metadata = json!({
+                    "refresh_versions": true,
+                    "released": released_metadata(&released),
+                });
+                if let Err(e) = request_run(&*king, "release_watch", metadata).await {
+                    // Left unseen, so the next poll asks again.
+                    warn!(error = %e, "requesting an update run failed");
+                    continue;
+                }
+            }
+            seen.extend(polled);
+        }
+    })
+}
+
+/// Latest stable version of `crate_name` in `registry`'s sparse index;
+/// `None` when the crate has no stable release.  Unchanged files (`304`)
+/// reuse the version parsed last time.
+async fn poll(
+    http: &reqwest::Client,
+    registry: &Registry,
+    crate_name: &str,
+    etags: &mut HashMap<String, (String, String)>,
+) -> Result<Option<String>> {
+    let url = registry.index_url(crate_name);
+    let mut request = http.get(&url).header("User-Agent", USER_AGENT);
+    if let Some((etag, _)) = etags.get(&url) {
+        request = request.header("If-None-Match", etag);
+    }
+    let resp = request.send().await.with_context(|| format!("GET {url}"))?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED
+        && let Some((_, version)) = etags.get(&url)
+    {
+        return Ok(Some(version.clone()));
+    }
+    anyhow::ensure!(
+        resp.status().is_success(),
+        "{url} returned {}",
+        resp.status()
+    );
+    let etag = resp
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string);
+    let Some(version) = latest_stable(&parse_index(&resp.text().await?)) else {
+        return Ok(None);
+    };
+    if let Some(etag) = etag {
+        etags.insert(url, (etag, version.clone()));
+    }
+    Ok(Some(version))
+}
+
+/// Versions the last runs resolved, from the version cache.
+fn cached_versions(crates: &[String]) -> HashMap<String, String> {
+    let Ok(cache) = VersionCache::load(&VersionCache::default_path()) else {
+        return HashMap::new();
+    };
+    crates
+        .iter()
+        .filter_map(|c| Some((c.clone(), cache.get(c)?.version.clone())))
+        .collect()
+}
+
+/// The `released` metadata entries of a run started for `released`.
+pub fn released_metadata(released: &[(String, String)]) -> Vec<Value> {
+    released
+        .iter()
+        .map(|(crate_name, version)| json!({ "crate": crate_name, "version": version }))
+        .collect()
+}
+
+/// Asks the king to start an update run with `trigger` and `metadata`.
+pub async fn request_run(king: &dyn KingClient, trigger: &str, metadata: Value) -> Result<()> {
+    king.post_ok(
+        "/pipeline/start",
+        &json!({ "trigger": trigger, "metadata": metadata }),
+    )
+    .await
+    .map(drop)
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_stable_skips_yanked_and_prereleases() {
+        let index = [
+            r#"{"name":"evo-agent-sdk","vers":"0.2.9","cksum":"a"}"#,
+            r#"{"name":"evo-agent-sdk","vers":"0.3.0","cksum":"b","yanked":false}"#,
+            r#"{"name":"evo-agent-sdk","vers":"0.3.1","cksum":"c","yanked":true}"#,
+            r#"{"name":"evo-agent-sdk","vers":"0.4.0-rc.1","cksum":"d","yanked":false}"#,
+        ]
+        .join("\n");
+        assert_eq!(
+            latest_stable(&parse_index(&index)).as_deref(),
+            Some("0.3.0")
+        );
+        assert_eq!(latest_stable(&[]), None);
+    }
+
+    #[test]
+    fn test_new_releases() {
+        let polled = BTreeMap::from([
+            ("evo-agent-sdk".to_string(), "0.3.1".to_string()),
+            ("evo-common".to_string(), "0.4.0".to_string()),
+            ("evo-new".to_string(), "1.0.0".to_string()),
+        ]);
+        let seen = HashMap::from([("evo-agent-sdk".to_string(), "0.3.0".to_string())]);
+        let baseline = HashMap::from([
+            ("evo-agent-sdk".to_string(), "0.3.1".to_string()),
+            ("evo-common".to_string(), "0.3.9".to_string()),
+        ]);
+        assert_eq!(
+            new_releases(&seen, &baseline, &polled),
+            vec![
+                ("evo-agent-sdk".to_string(), "0.3.1".to_string()),
+                ("evo-common".to_string(), "0.4.0".to_string()),
+            ]
+        );
+        let seen: HashMap<String, String> = polled.clone().into_iter().collect();
+        assert!(new_releases(&seen, &baseline, &polled).is_empty());
+    }
+}
@@ -0,0 +1,663 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::backend::KingClient;
+use crate::checksum::sha256_hex;
+use crate::commit_message::is_self_update;
+use crate::config::{ConfigStore, UpdateConfig};
+use crate::mac::verify_hmac_sha256_hex;
+use crate::watcher::{released_metadata, request_run};
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Path the receiver accepts deliveries on.
+pub const WEBHOOK_PATH: &str = "/webhook";
+
+/// Largest request head (request line and headers) read.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// Largest payload accepted; GitHub push payloads stay well below.
+const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+
+/// Time a client gets to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delivery bodies remembered (by digest) to reject replays.
+pub const RECENT_DELIVERIES: usize = 1024;
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// Webhook receiver settings from the environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookConfig {
+    /// Address to listen on, e.g. `0.0.0.0:8787`.
+    pub listen: String,
+    /// Shared secret deliveries are signed with.
+    pub secret: String,
+}
+
+impl WebhookConfig {
+    /// `Some` when `WEBHOOK_LISTEN` is set.  `WEBHOOK_SECRET` is then
+    /// required: unsigned deliveries are never accepted.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(listen) = std::env::var("WEBHOOK_LISTEN")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+        else {
+            return Ok(None);
+        };
+        let secret = std::env::var("WEBHOOK_SECRET")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .context("WEBHOOK_LISTEN is set but WEBHOOK_SECRET is not")?;
+        Ok(Some(Self { listen, secret }))
+    }
+}
+
+// ─── Signatures ───────────────────────────────────────────────────────────────
+
+/// Whether `header` (`sha256=<hex>`, as in `X-Hub-Signature-256`) signs
+/// `body` with `secret`.  Compared in constant time.
+pub fn verify_signature(secret: &str, body: &[u8], header: &str) -> bool {
+    header
+        .trim()
+        .strip_prefix("sha256=")
+        .is_some_and(|tag| verify_hmac_sha256_hex(secret.as_bytes(), body, tag))
+}
+
+// ─── Replays ──────────────────────────────────────────────────────────────────
+
+/// SHA-256 digests of the most recent signed bodies, oldest first, so a
+/// captured delivery cannot be replayed to start runs again.  Keyed on the
+/// body rather than `X-GitHub-Delivery`: the signature does not cover that
+/// header, so a replay could simply change or drop it.
+#[derive(Debug, Default)]
+pub struct RecentDeliveries {
+    digests: VecDeque<String>,
+}
+
+impl RecentDeliveries {
+    /// Records `body`; `false` when the same body was already seen.  Past
+    /// [`RECENT_DELIVERIES`] bodies the oldest is forgotten.
+    pub fn first_seen(&mut self, body: &[u8]) -> bool {
+        let digest = sha256_hex(body);
+        if self.digests.iter().any(|seen| *seen == digest) {
+            return false;
+        }
+        if self.digests.len() == RECENT_DELIVERIES {
+            self.digests.pop_front();
+        }
+        self.digests.push_back(digest);
+        true
+    }
+}
+
+// ─── Events ───────────────────────────────────────────────────────────────────
+
+/// What to do with one delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    /// Answer a GitHub `ping`.
+    Pong,
+    /// Start a run for `released` limited to `repos`.
+    Run {
+        released: Vec<(String, String)>,
+        repos: Vec<String>,
+    },
+    /// Nothing to do, and why.
+    Ignore(String),
+}
+
+/// The event a payload's shape belongs to, among those [`decide`] acts on:
+/// GitHub pings carry `zen`, releases a `release` object, pushes a `ref`
+/// (create and delete events add `ref_type`), and the king's
+/// `crate_published` a `crate` and `version`.
+pub fn payload_event(payload: &Value) -> Option<&'static str> {
+    if payload.get("zen").is_some() {
+        Some("ping")
+    } else if payload["release"].is_object() {
+        Some("release")
+    } else if payload["crate"].is_string() && payload["version"].is_string() {
+        Some("crate_published")
+    } else if payload["ref"].is_string() && payload.get("ref_type").is_none() {
+        Some("push")
+    } else {
+        None
+    }
+}
+
+/// Whether the unsigned event header agrees with the signed payload.  An
+/// event [`decide`] acts on must match the payload's shape, so a signed body
+/// cannot be reinterpreted as another event; a payload shaped like a handled
+/// event must not hide behind an unhandled header either.
+pub fn event_matches(event: &str, payload: &Value) -> bool {
+    match payload_event(payload) {
+        Some(kind) => kind == event,
+        None => !HANDLED_EVENTS.contains(&event),
+    }
+}
+
+/// Events [`decide`] does more than ignore.
+const HANDLED_EVENTS: [&str; 4] = ["ping", "release", "push", "crate_published"];
+
+/// Maps a delivery to a run.
+///
+/// `event` is `X-GitHub-Event` (`release`, `push`, `ping`) or the king's
+/// `X-Evo-Event` (`crate_published` with `{"crate", "version"}`).  Releases
+/// and pushed tags name a crate and version through their tag (see
+/// [`parse_tag`]); only tracked crates start a run, limited to the repos
+/// that track them.
+pub fn decide(event: &str, payload: &Value, config: &UpdateConfig) -> Decision {
+    let tracked = config.all_tracked_crates();
+    let repo = payload["repository"]["name"].as_str().unwrap_or_default();
+    let released = match event {
+        "ping" => return Decision::Pong,
+        "release" => {
+            let action = payload["action"].as_str().unwrap_or_default();
+            if !matches!(action, "published" | "released") {
+                return Decision::Ignore(format!("release action {action:?}"));
+            }
+            if payload["release"]["prerelease"] == true || payload["release"]["draft"] == true {
+                return Decision::Ignore("pre-release".to_string());
+            }
+            parse_tag(
+                payload["release"]["tag_name"].as_str().unwrap_or_default(),
+                repo,
+                &tracked,
+            )
+        }
+        "push" => {
+            let Some(tag) = payload["ref"]
+                .as_str()
+                .and_then(|r| r.strip_prefix("refs/tags/"))
+            else {
+                return Decision::Ignore("branch push".to_string());
+            };
+            if payload["deleted"] == true {
+                return Decision::Ignore("tag deleted".to_string());
+            }
+            if is_self_update(
+                payload["head_commit"]["message"]
+                    .as_str()
+                    .unwrap_or_default(),
+            ) {
+                return Decision::Ignore("the agent's own update".to_string());
+            }
+            parse_tag(tag, repo, &tracked)
+        }
+        "crate_published" => match (payload["crate"].as_str(), payload["version"].as_str()) {
+            (Some(crate_name), Some(version)) if tracked.contains(&crate_name) => {
+                Some((crate_name.to_string(), version.to_string()))
+            }
+            _ => None,
+        },
+        other => return Decision::Ignore(format!("event {other:?}")),
+    };
+    let Some((crate_name, version)) = released else {
+        return Decision::Ignore("no tracked crate release".to_string());
+    };
+    let repos: Vec<String> = config
+        .repos
+        .iter()
+        .filter(|spec| {
+            spec.tracked_crates(&config.tracked_crates)
+                .contains(&crate_name.as_str())
+        })
+        .map(|spec| spec.repo.clone())
+        .collect();
+    if repos.is_empty() {
+        return Decision::Ignore(format!("no managed repo tracks {crate_name}"));
+    }
+    Decision::Run {
+        released: vec![(crate_name, version)],
+        repos,
+    }
+}
+
+/// Crate and version a release tag names: `<crate>-v<version>`,
+/// `<crate>@<version>`, `<crate>/v<version>`, or a bare `v<version>` in the
+/// repo named like the crate.  Pre-release versions and untracked crates
+/// yield `None`.
+pub fn parse_tag(tag: &str, repo: &str, tracked: &[&str]) -> Option<(String, String)> {
+    let (crate_name, version) = tracked
+        .iter()
+        .find_map(|&c| {
+            let rest = tag.strip_prefix(c)?;
+            let version = rest
+                .strip_prefix("-v")
+                .or_else(|| rest.strip_prefix('@'))
+                .or_else(|| rest.strip_prefix("/v"))?;
+            Some((c, version))
+        })
+        .or_else(|| {
+            let &crate_name = tracked.iter().find(|&&c| c == repo)?;
+            Some((crate_name, tag.strip_prefix('v').unwrap_or(tag)))
+        })?;
+    let stable = version.starts_with(|c: char| c.is_ascii_digit())
+        && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+    stable.then(|| (crate_name.to_string(), version.to_string()))
+}
+
+// ─── Server ───────────────────────────────────────────────────────────────────
+
+/// Listens on `webhook.listen` and turns signed deliveries into runs
+/// requested from the `king`.
+pub async fn spawn(
+    config: Arc<ConfigStore>,
+    webhook: WebhookConfig,
+    king: Arc<dyn KingClient>,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(&webhook.listen)
+        .await
+        .with_context(|| format!("listen for webhooks on {}", webhook.listen))?;
+    info!(listen = %webhook.listen, path = WEBHOOK_PATH, "webhook receiver started");
+    let shared = Arc::new((
+        config,
+        webhook,
+        king,
+        Mutex::new(RecentDeliveries::default()),
+    ));
+    Ok(tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "accepting a webhook connection failed");
+                    continue;
+                }
+            };
+            let shared = Arc::clone(&shared);
+            tokio::spawn(async move {
+                let (config, webhook, king, recent) = &*shared;
+                if let Err(e) = handle(stream, config, &webhook.secret, &**king, recent).await {
+                    debug!(%peer, error = %e, "webhook connection failed");
+                }
+            });
+        }
+    }))
+}
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    config: &ConfigStore,
+    secret: &str,
+    king: &dyn KingClient,
+    recent: &Mutex<RecentDeliveries>,
+) -> Result<()> {
+    let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => {
+            return respond(&mut stream, 400, &json!({ "error": format!("{e:#}") })).await;
+        }
+        Err(_) => return respond(&mut stream, 408, &json!({ "error": "timeout" })).await,
+    };
+    let (status, body) = route(&request, config, secret, king, recent).await;
+    respond(&mut stream, status, &body).await
+}
+
+async fn route(
+    request: &Request,
+    config: &ConfigStore,
+    secret: &str,
+    king: &dyn KingClient,
+    recent: &Mutex<RecentDeliveries>,
+) -> (u16, Value) {
+    if request.path != WEBHOOK_PATH {
+        return (404, json!({ "error": "not found" }));
+    }
+    if request.method != "POST" {
+        return (405, json!({ "error": "method not allowed" }));
+    }
+    let signature = request.header("X-Hub-Signature-256").unwrap_or_default();
+    if !verify_signature(secret, &request.body, signature) {
+        warn!("webhook delivery with a missing or invalid signature — rejected");
+        return (401, json!({ "error": "invalid signature" }));
+    }
+    let delivery = request.header("X-GitHub-Delivery").unwrap_or("-");
+    let first = recent
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .first_seen(&request.body);
+    if !first {
+        warn!(delivery, "webhook body seen before — rejected as a replay");
+        return (409, json!({ "error": "duplicate delivery" }));
+    }
+    let Some(event) = request
+        .header("X-Evo-Event")
+        .or_else(|| request.header("X-GitHub-Event"))
+    else {
+        return (400, json!({ "error": "no event header" }));
+    };
+    let payload: Value = match serde_json::from_slice(&request.body) {
+        Ok(payload) => payload,
+        Err(e) => return (400, json!({ "error": format!("invalid JSON: {e}") })),
+    };
+    if !event_matches(event, &payload) {
+        warn!(
+            event,
+            delivery, "webhook event header does not match the payload — rejected"
+        );
+        return (
+            400,
+            json!({ "error": format!("event {event} does not match the payload") }),
+        );
+    }
+    let snapshot = config.current();
+    match decide(event, &payload, &snapshot.config) {
+        Decision::Pong => (200, json!({ "pong": true })),
+        Decision::Ignore(reason) => {
+            debug!(event, reason = %reason, "webhook delivery ignored");
+            (200, json!({ "ignored": reason }))
+        }
+        Decision::Run { released, repos } => {
+            info!(event, released = ?released, repos = ?repos, "webhook release — requesting a targeted update run");
+            let metadata = json!({
+                "refresh_versions": true,
+                "released": released_metadata(&released),
+                "repos": repos,
+            });
+            match request_run(king, "webhook", metadata).await {
+                Ok(()) => (
+                    202,
+                    json!({ "accepted": true, "released": released_metadata(&released), "repos": repos }),
+                ),
+                Err(e) => {
+                    error!(error = %e, "requesting a webhook run failed");
+                    (502, json!({ "error": format!("{e:#}") }))
+                }
+            }
+        }
+    }
+}
+
+/// Reads one HTTP/1.1 request with a `Content-Length` body.
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut buf: Vec<u8> = Vec::new();
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        anyhow::ensure!(buf.len() <= MAX_HEAD_BYTES, "request head too large");
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await.context("read request")?;
+        anyhow::ensure!(n > 0, "connection closed mid-request");
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = std::str::from_utf8(&buf[..head_end]).context("request head is not UTF-8")?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect();
+    let length: usize = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("Content-Length"))
+        .map(|(_, v)| v.parse().context("invalid Content-Length"))
+        .transpose()?
+        .unwrap_or(0);
+    anyhow::ensure!(length <= MAX_BODY_BYTES, "payload too large");
+    let mut body = buf[head_end + 4..].to_vec();
+    while body.len() < length {
+        let mut chunk = vec![0u8; (length - body.len()).min(64 * 1024)];
+        let n = stream.read(&mut chunk).await.context("read request body")?;
+        anyhow::ensure!(n > 0, "connection closed mid-body");
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(length);
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        _ => "Bad Gateway",
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .context("write response")?;
+    stream.shutdown().await.ok();
+    Ok(())
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepoConfig;
+    use crate::mac::hmac_sha256_hex;
+
+    fn config() -> UpdateConfig {
+        UpdateConfig {
+            tracked_crates: vec!["evo-agent-sdk".into(), "evo-common".into()],
+            repos: vec![
+                RepoConfig {
+                    repo: "evo-king".into(),
+                    ..RepoConfig::default()
+                },
+                RepoConfig {
+                    repo: "evo-runner".into(),
+                    excluded_crates: vec!["evo-agent-sdk".into()],
+                    ..RepoConfig::default()
+                },
+            ],
+            ..UpdateConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = br#"{"zen":"Keep it logically awesome."}"#;
+        let signature = format!("sha256={}", hmac_sha256_hex(b"s3cret", body));
+        assert!(verify_signature("s3cret", body, &signature));
+        assert!(verify_signature(
+            "s3cret",
+            body,
+            &signature.to_uppercase().replace("SHA256", "sha256")
+        ));
+        assert!(!verify_signature("other", body, &signature));
+        assert!(!verify_signature("s3cret", b"tampered", &signature));
+        assert!(!verify_signature("s3cret", body, ""));
+    }
+
+    #[test]
+    fn test_recent_deliveries_reject_replays() {
+        let mut recent = RecentDeliveries::default();
+        assert!(recent.first_seen(b"body-0"));
+        assert!(!recent.first_seen(b"body-0"));
+        for i in 1..=RECENT_DELIVERIES {
+            assert!(recent.first_seen(format!("body-{i}").as_bytes()));
+        }
+        // The oldest body was forgotten once the set was full.
+        assert!(recent.first_seen(b"body-0"));
+        assert!(!recent.first_seen(format!("body-{RECENT_DELIVERIES}").as_bytes()));
+    }
+
+    fn signed(event: &str, delivery: Option<&str>, body: &Value) -> Request {
+        let body = serde_json::to_vec(body).unwrap();
+        let mut headers = vec![
+            (
+                "x-hub-signature-256".to_string(),
+                format!("sha256={}", hmac_sha256_hex(b"s3cret", &body)),
+            ),
+            ("x-github-event".to_string(), event.to_string()),
+        ];
+        if let Some(delivery) = delivery {
+            headers.push(("x-github-delivery".to_string(), delivery.to_string()));
+        }
+        Request {
+            method: "POST".into(),
+            path: WEBHOOK_PATH.into(),
+            headers,
+            body,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_rejects_replays_and_mismatched_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ConfigStore::new(&dir.path().join("update-agent.toml"), config());
+        let king = crate::backend::FakeKing::default();
+        let recent = Mutex::new(RecentDeliveries::default());
+        let release = json!({
+            "action": "published",
+            "release": { "tag_name": "evo-common-v0.4.0", "draft": false, "prerelease": false },
+            "repository": { "name": "evo-common" },
+        });
+        let route = |request: Request| {
+            let (store, king, recent) = (&store, &king, &recent);
+            async move { route(&request, store, "s3cret", king, recent).await.0 }
+        };
+
+        assert_eq!(route(signed("release", Some("d-1"), &release)).await, 202);
+        // Neither a fresh delivery ID nor dropping it makes the body new.
+        assert_eq!(route(signed("release", Some("d-2"), &release)).await, 409);
+        assert_eq!(route(signed("release", None, &release)).await, 409);
+        // The unsigned event header cannot reinterpret a signed body.
+        let king_event = json!({ "crate": "evo-common", "version": "0.5.0" });
+        assert_eq!(route(signed("push", None, &king_event)).await, 400);
+        assert_eq!(king.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_event_must_match_payload() {
+        let release = json!({ "action": "published", "release": { "tag_name": "v1.0.0" } });
+        let push = json!({ "ref": "refs/tags/v1.0.0", "pusher": { "name": "ci" } });
+        let king = json!({ "crate": "evo-common", "version": "0.4.0" });
+        let ping = json!({ "zen": "Keep it simple.", "hook_id": 1 });
+        assert!(event_matches("release", &release));
+        assert!(event_matches("push", &push));
+        assert!(event_matches("crate_published", &king));
+        assert!(event_matches("ping", &ping));
+        assert!(!event_matches("push", &release));
+        assert!(!event_matches("crate_published", &push));
+        assert!(!event_matches("release", &king));
+        assert!(!event_matches("issues", &king));
+        assert!(!event_matches("ping", &json!({})));
+        let create = json!({ "ref": "v1.0.0", "ref_type": "tag" });
+        assert!(event_matches("create", &create));
+        assert!(!event_matches("push", &create));
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        let tracked = ["evo-agent-sdk", "evo-common"];
+        let parsed = |tag, repo| parse_tag(tag, repo, &tracked);
+        assert_eq!(
+            parsed("evo-agent-sdk-v0.3.1", "evo-agents"),
+            Some(("evo-agent-sdk".to_string(), "0.3.1".to_string()))
+        );
+        assert_eq!(
+            parsed("evo-common@0.4.0", "evo-agents"),
+            Some(("evo-common".to_string(), "0.4.0".to_string()))
+        );
+        assert_eq!(
+            parsed("v0.4.1", "evo-common"),
+            Some(("evo-common".to_string(), "0.4.1".to_string()))
+        );
+        assert_eq!(parsed("v0.4.1", "evo-agents"), None);
+        assert_eq!(parsed("evo-agent-sdk-v0.4.0-rc.1", "evo-agents"), None);
+        assert_eq!(parsed("evo-other-v1.0.0", "evo-agents"), None);
+    }
+
+    #[test]
+    fn test_decide_targets_tracking_repos() {
+        let release = json!({
+            "action": "published",
+            "release": { "tag_name": "evo-agent-sdk-v0.3.1", "prerelease": false, "draft": false },
+            "repository": { "name": "evo-agents" },
+        });
+        assert_eq!(
+            decide("release", &release, &config()),
+            Decision::Run {
+                released: vec![("evo-agent-sdk".to_string(), "0.3.1".to_string())],
+                repos: vec!["evo-king".to_string()],
+            }
+        );
+        let king = json!({ "crate": "evo-common", "version": "0.4.0" });
+        assert!(matches!(
+            decide("crate_published", &king, &config()),
+            Decision::Run { repos, .. } if repos.len() == 2
+        ));
+        let tag_push =
+            json!({ "ref": "refs/tags/evo-common-v0.4.0", "repository": { "name": "evo-agents" } });
+        assert!(matches!(
+            decide("push", &tag_push, &config()),
+            Decision::Run { .. }
+        ));
+        let own_tag_push = json!({
+            "ref": "refs/tags/evo-common-v0.4.0",
+            "repository": { "name": "evo-agents" },
+            "head_commit": { "message": "chore(deps): bump evo-common\n\nEvo-Update-Run: r1\n" },
+        });
+        assert!(matches!(
+            decide("push", &own_tag_push, &config()),
+            Decision::Ignore(_)
+        ));
+        let branch_push =
+            json!({ "ref": "refs/heads/main", "repository": { "name": "evo-agents" } });
+        assert!(matches!(
+            decide("push", &branch_push, &config()),
+            Decision::Ignore(_)
+        ));
+        assert_eq!(decide("ping", &json!({}), &config()), Decision::Pong);
+        assert!(matches!(
+            decide("issues", &json!({}), &config()),
+            Decision::Ignore(_)
+        ));
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::checksum::sha256_hex;
+use crate::toolchain;
+
+// ─── Constants ────────────────────────────────────────────────────────────────
+
+/// Directory of a repo's GitHub Actions workflows.
+pub const WORKFLOW_DIR: &str = ".github/workflows";
+
+// ─── Configuration ────────────────────────────────────────────────────────────
+
+/// `[workflow_sync]` section of the config file: where the canonical CI
+/// workflow templates live.  Repos list the ones they use in
+/// `workflow_templates`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkflowSyncConfig {
+    /// Repo publishing the templates.
+    pub source_repo: String,
+    /// Directory of the templates inside the source repo's checkout.
+    pub source_dir: String,
+}
+
+impl Default for WorkflowSyncConfig {
+    fn default() -> Self {
+        Self {
+            source_repo: "evo-common".to_string(),
+            source_dir: "workflow-templates".to_string(),
+        }
+    }
+}
+
+/// Repo-relative path a template named `name` is rendered to.
+pub fn target_path(name: &str) -> String {
+    format!("{WORKFLOW_DIR}/{}", name.trim_matches('/'))
+}
+
+// ─── Templates ────────────────────────────────────────────────────────────────
+
+/// The canonical workflow templates of one run, keyed by file name
+/// (`ci.yml`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkflowTemplates {
+    pub files: BTreeMap<String, String>,
+}
+
+impl WorkflowTemplates {
+    /// Reads every `.yml` / `.yaml` file directly under
+    /// `source_root/source_dir`.
+    pub fn load(source_root: &Path, config: &WorkflowSyncConfig) -> Result<Self> {
+        let dir = source_root.join(&config.source_dir);
+        anyhow::ensure!(
+            dir.is_dir(),
+            "{} has no workflow template directory {}",
+            config.source_repo,
+            config.source_dir
+        );
+        let mut files = BTreeMap::new();
+        let entries = std::fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry.with_context(|| format!("read {}", dir.display()))?;
+            let path = entry.path();
+            let is_yaml = path
+                .extension()
+                .is_some_and(|ext| ext == "yml" || ext == "yaml");
+            if path.is_file() && is_yaml {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("read {}", path.display()))?;
+                files.insert(entry.file_name().to_string_lossy().into_owned(), content);
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// sha256 over every name and content, so a repo's scan fingerprint
+    /// changes whenever a template does.
+    pub fn digest(&self) -> String {
+        let mut all = String::new();
+        for (name, content) in &self.files {
+            all.push_str(name);
+            all.push('\0');
+            all.push_str(&sha256_hex(content.as_bytes()));
+            all.push('\n');
+        }
+        sha256_hex(all.as_bytes())
+    }
+}
+
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+/// Values a template's `{{evo.<key>}}` placeholders are filled from.
+pub struct RenderVars<'a> {
+    pub repo: &'a str,
+    pub org: &'a str,
+    /// Latest stable Rust.
+    pub rust: Option<&'a str>,
+    /// The repo's `rust-version`.
+    pub msrv: Option<&'a str>,
+    /// Latest version of each tracked crate.
+    pub latest: &'a HashMap<&'a str, String>,
+    /// The repo's `workflow_params`.
+    pub params: &'a BTreeMap<String, String>,
+}
+
+/// A rendered workflow and the tracked crates (and `rust`) whose versions
+/// it embeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendered {
+    pub content: String,
+    /// `(crate, version)` of each `{{evo.crate.<name>}}` placeholder, and
+    /// `("rust", version)` for `{{evo.rust}}`.
+    pub crates: Vec<(String, String)>,
+}
+
+/// Fills the `{{evo.<key>}}` placeholders of `template`.
+///
+/// Keys: `repo`, `org`, `rust`, `msrv`, `crate.<name>` (the crate's latest
+/// version, e.g. `0.3.2`), `crate.<name>:minor` (its `major.minor`, e.g.
+/// `0.3`) and, for any other key, the repo's `workflow_params`.  Other
+/// `{{ … }}` text, such as GitHub's `${{ github.ref }}` expressions, is left
+/// alone.  Unknown keys are errors.
+pub fn render(template: &str, vars: &RenderVars<'_>) -> Result<Rendered> {
+    let mut content = String::with_capacity(template.len());
+    let mut crates: Vec<(String, String)> = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        let inner = &rest[open + 2..];
+        if !inner.trim_start().starts_with("evo.") {
+            content.push_str(&rest[..open + 2]);
+            rest = inner;
+            continue;
+        }
+        let close = inner.find("}}").context("unclosed {{evo.…}} placeholder")?;
+        let key = &inner[..close].trim()["evo.".len()..];
+        let value = match key {
+            "repo" => Some(vars.repo.to_string()),
+            "org" => Some(vars.org.to_string()),
+            "rust" => vars.rust.map(|version| {
+                if !crates.iter().any(|(c, _)| c == toolchain::RUST) {
+                    crates.push((toolchain::RUST.to_string(), version.to_string()));
+                }
+                version.to_string()
+            }),
+            "msrv" => vars.msrv.map(str::to_string),
+            _ => match key.strip_prefix("crate.") {
+                Some(crate_key) => {
+                    let (crate_name, minor) = match crate_key.strip_suffix(":minor") {
+                        Some(crate_name) => (crate_name, true),
+                        None => (crate_key, false),
+                    };
+                    vars.latest.get(crate_name).map(|version| {
+                        if !crates.iter().any(|(c, _)| c == crate_name) {
+                            crates.push((crate_name.to_string(), version.clone()));
+                        }
+                        if minor {
+                            version.split('.').take(2).collect::<Vec<_>>().join(".")
+                        } else {
+                            version.clone()
+                        }
+                    })
+                }
+                None => vars.params.get(key).cloned(),
+            },
+        };
+        let value = value.with_context(|| format!("no value for {{{{evo.{key}}}}}"))?;
+        content.push_str(&rest[..open]);
+        content.push_str(&value);
+        rest = &inner[close + 2..];
+    }
+    content.push_str(rest);
+    Ok(Rendered { content, crates })
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CI: &str = "name: CI
+on: push
+jobs:
+  test:
+    runs-on: ${{ matrix.os }}
+    steps:
+      - uses: dtolnay/rust-toolchain@{{ evo.msrv }}
+      - run: cargo test -p {{evo.repo}} --features {{ evo.features }}
+      - run: echo \"sdk {{evo.crate.evo-agent-sdk}} in {{evo.org}}/{{evo.repo}}\"
+      - run: echo \"pin {{evo.crate.evo-agent-sdk:minor}}\"
+";
+
+    #[test]
+    fn test_render() {
+        let latest = HashMap::from([("evo-agent-sdk", "0.3.2".to_string())]);
+        let params = BTreeMap::from([("features".to_string(), "full".to_string())]);
+        let vars = RenderVars {
+            repo: "evo-king",
+            org: "ai-evo-agents",
+            rust: Some("1.86.0"),
+            msrv: Some("1.80"),
+            latest: &latest,
+            params: &params,
+        };
+        let rendered = render(CI, &vars).unwrap();
+        assert!(rendered.content.contains("runs-on: ${{ matrix.os }}\n"));
+        assert!(rendered.content.contains("rust-toolchain@1.80\n"));
+        assert!(
+            rendered
+                .content
+                .contains("cargo test -p evo-king --features full\n")
+        );
+        assert!(
+            rendered
+                .content
+                .contains("sdk 0.3.2 in ai-evo-agents/evo-king")
+        );
+        assert!(rendered.content.contains("pin 0.3\"\n"));
+        assert_eq!(
+            rendered.crates,
+            vec![("evo-agent-sdk".to_string(), "0.3.2".to_string())]
+        );
+
+        let err = render("x: {{evo.nope}}\n", &vars).unwrap_err();
+        assert!(err.to_string().contains("no value for {{evo.nope}}"));
+        let no_msrv = RenderVars { msrv: None, ..vars };
+        assert!(render(CI, &no_msrv).is_err());
+    }
+
+    #[test]
+    fn test_load_and_digest() {
+        let source = tempfile::tempdir().unwrap();
+        let dir = source.path().join("workflow-templates");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("ci.yml"), "name: CI\n").unwrap();
+        std::fs::write(dir.join("release.yaml"), "name: Release\n").unwrap();
+        std::fs::write(dir.join("README.md"), "# templates\n").unwrap();
+
+        let templates =
+            WorkflowTemplates::load(source.path(), &WorkflowSyncConfig::default()).unwrap();
+        assert_eq!(
+            templates.files.keys().collect::<Vec<_>>(),
+            vec!["ci.yml", "release.yaml"]
+        );
+        assert_eq!(target_path("ci.yml"), ".github/workflows/ci.yml");
+
+        let mut changed = templates.clone();
+        changed
+            .files
+            .insert("ci.yml".into(), "name: Tests\n".into());
+        assert_ne!(changed.digest(), templates.digest());
+
+        let empty = tempfile::tempdir().unwrap();
+        let err =
+            WorkflowTemplates::load(empty.path(), &WorkflowSyncConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("no workflow template directory"));
+    }
+}
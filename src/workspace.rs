@@ -0,0 +1,291 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::versions::{dep_requirements, semver_compatible};
+
+// ─── Workspace members ────────────────────────────────────────────────────────
+
+/// Manifest paths (relative to the repo root) of every member of the
+/// workspace declared in `manifest`, the content of the repo-relative
+/// `manifest_path` (the repo's root `Cargo.toml` or any nested workspace
+/// root).
+///
+/// Members are resolved relative to the manifest's directory, like cargo
+/// does.  `*` and `?` wildcards may appear in any path segment; a member is
+/// dropped when it is, or lies under, an `exclude` entry.  Returns an empty
+/// list when the manifest is not a workspace.
+pub fn member_manifests(repo_base: &Path, manifest_path: &str, manifest: &str) -> Vec<String> {
+    let Ok(doc) = manifest.parse::<toml_edit::DocumentMut>() else {
+        return Vec::new();
+    };
+    let Some(workspace) = doc.get("workspace") else {
+        return Vec::new();
+    };
+    let list = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.trim_end_matches('/').to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let exclude = list("exclude");
+    let excluded = |dir: &str| {
+        exclude
+            .iter()
+            .any(|e| Path::new(dir).starts_with(Path::new(e)))
+    };
+
+    // Directory of the workspace root, relative to the repo.
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut manifests: Vec<String> = Vec::new();
+    for member in list("members") {
+        for dir in expand(&repo_base.join(&root_dir), &member) {
+            if excluded(&dir) {
+                continue;
+            }
+            let rel = Path::new(&root_dir).join(&dir).join("Cargo.toml");
+            let rel = rel
+                .components()
+                .filter(|c| !matches!(c, std::path::Component::CurDir))
+                .collect::<std::path::PathBuf>()
+                .to_string_lossy()
+                .into_owned();
+            if repo_base.join(&rel).is_file() && !manifests.contains(&rel) {
+                manifests.push(rel);
+            }
+        }
+    }
+    manifests
+}
+
+/// Directories under `base` matching the member `pattern`, relative to
+/// `base` and sorted per wildcard segment.
+fn expand(base: &Path, pattern: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = vec![String::new()];
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        let mut next: Vec<String> = Vec::new();
+        for dir in &dirs {
+            let join = |name: &str| {
+                if dir.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{dir}/{name}")
+                }
+            };
+            if !segment.contains(['*', '?']) {
+                next.push(join(segment));
+                continue;
+            }
+            let Ok(entries) = std::fs::read_dir(base.join(dir)) else {
+                continue;
+            };
+            let mut found: Vec<String> = entries
+                .filter_map(Result::ok)
+                .filter(|e| e.path().is_dir())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|name| !name.starts_with('.') && wildcard_match(segment, name))
+                .map(|name| join(&name))
+                .collect();
+            found.sort();
+            next.extend(found);
+        }
+        dirs = next;
+    }
+    dirs.into_iter()
+        .map(|d| if d.is_empty() { ".".to_string() } else { d })
+        .collect()
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+/// and `?` exactly one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // Last `*` seen in the pattern, and the name position it was tried at.
+    let (mut pi, mut ni, mut star): (usize, usize, Option<(usize, usize)>) = (0, 0, None);
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+// ─── Duplicate-version detection ──────────────────────────────────────────────
+
+/// A tracked crate required at semver-incompatible versions within one repo.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VersionConflict {
+    /// The tracked crate, e.g. `"evo-common"`.
+    pub crate_name: String,
+    /// `(manifest path, version requirement)` for every distinct requirement
+    /// in any of a manifest's dependency tables.
+    pub requirements: Vec<(String, String)>,
+}
+
+/// Finds tracked crates that `manifests` (`(path, content)` pairs) would
+/// resolve to more than one semver-incompatible copy.
+pub fn find_conflicts(manifests: &[(String, String)], crates: &[&str]) -> Vec<VersionConflict> {
+    let mut conflicts: Vec<VersionConflict> = Vec::new();
+    for &crate_name in crates {
+        let mut requirements: Vec<(String, String)> = Vec::new();
+        for (path, content) in manifests {
+            for (_, req) in dep_requirements(content, crate_name) {
+                let entry = (path.clone(), req);
+                if !requirements.contains(&entry) {
+                    requirements.push(entry);
+                }
+            }
+        }
+        if requirements.is_empty() {
+            continue;
+        }
+        let split = requirements
+            .iter()
+            .any(|(_, req)| !semver_compatible(req, &requirements[0].1));
+        if split {
+            conflicts.push(VersionConflict {
+                crate_name: crate_name.to_string(),
+                requirements,
+            });
+        }
+    }
+    conflicts
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn manifest(dep: &str) -> String {
+        format!(
+            "[package]\nname = \"x\"\nversion = \"0.1.0\"\n\n[dependencies]\nevo-common = \"{dep}\"\n"
+        )
+    }
+
+    #[test]
+    fn test_member_manifests_expands_globs_and_excludes() {
+        let dir = TempDir::new().unwrap();
+        for member in ["crates/a", "crates/b", "crates/skip", "tool"] {
+            std::fs::create_dir_all(dir.path().join(member)).unwrap();
+            std::fs::write(dir.path().join(member).join("Cargo.toml"), "").unwrap();
+        }
+        let root = r#"
+[workspace]
+members = ["crates/*", "tool", "missing"]
+exclude = ["crates/skip"]
+"#;
+        assert_eq!(
+            member_manifests(dir.path(), "Cargo.toml", root),
+            vec![
+                "crates/a/Cargo.toml",
+                "crates/b/Cargo.toml",
+                "tool/Cargo.toml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_member_manifests_nested_root_and_wildcards() {
+        let dir = TempDir::new().unwrap();
+        for member in [
+            "rust/agents/evo-a-agent",
+            "rust/agents/evo-b-agent",
+            "rust/agents/evo-c-tool",
+            "rust/agents/old/evo-d-agent",
+        ] {
+            std::fs::create_dir_all(dir.path().join(member)).unwrap();
+            std::fs::write(dir.path().join(member).join("Cargo.toml"), "").unwrap();
+        }
+        let root = r#"
+[workspace]
+members = ["agents/evo-?-agent", "agents/*/evo-*", "."]
+exclude = ["agents/old"]
+"#;
+        std::fs::write(dir.path().join("rust/Cargo.toml"), root).unwrap();
+        assert_eq!(
+            member_manifests(dir.path(), "rust/Cargo.toml", root),
+            vec![
+                "rust/agents/evo-a-agent/Cargo.toml",
+                "rust/agents/evo-b-agent/Cargo.toml",
+                "rust/Cargo.toml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("evo-*-agent", "evo-king-agent"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("a?c", "abc"));
+        assert!(!wildcard_match("evo-*-agent", "evo-king"));
+        assert!(!wildcard_match("a?c", "ac"));
+    }
+
+    #[test]
+    fn test_member_manifests_non_workspace() {
+        let dir = TempDir::new().unwrap();
+        assert!(member_manifests(dir.path(), "Cargo.toml", &manifest("0.3")).is_empty());
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let manifests = vec![
+            ("a/Cargo.toml".to_string(), manifest("0.4.0")),
+            ("b/Cargo.toml".to_string(), manifest("0.3")),
+            ("c/Cargo.toml".to_string(), "[dependencies]\n".to_string()),
+        ];
+        let conflicts = find_conflicts(&manifests, &["evo-common", "evo-agent-sdk"]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].crate_name, "evo-common");
+        assert_eq!(conflicts[0].requirements.len(), 2);
+
+        let aligned = vec![
+            ("a/Cargo.toml".to_string(), manifest("0.4.0")),
+            ("b/Cargo.toml".to_string(), manifest("0.4")),
+        ];
+        assert!(find_conflicts(&aligned, &["evo-common"]).is_empty());
+
+        // Workspace-level and dev requirements count too.
+        let root =
+            "[workspace]\nmembers = [\"a\"]\n\n[workspace.dependencies]\nevo-common = \"0.3\"\n";
+        let dev = "[dev-dependencies]\nevo-common = { version = \"0.4\" }\n";
+        let hidden = vec![
+            ("Cargo.toml".to_string(), root.to_string()),
+            ("a/Cargo.toml".to_string(), manifest("0.4")),
+        ];
+        let conflicts = find_conflicts(&hidden, &["evo-common"]);
+        assert_eq!(
+            conflicts[0].requirements,
+            vec![
+                ("Cargo.toml".to_string(), "0.3".to_string()),
+                ("a/Cargo.toml".to_string(), "0.4".to_string()),
+            ]
+        );
+        let mixed = vec![(
+            "a/Cargo.toml".to_string(),
+            format!("{}\n{dev}", manifest("0.3")),
+        )];
+        assert_eq!(find_conflicts(&mixed, &["evo-common"]).len(), 1);
+    }
+}
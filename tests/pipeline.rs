@@ -0,0 +1,1034 @@
+//! End-to-end runs of the update handler against temp git repos, with an
+//! in-memory (or fixture-replaying) registry, VCS and king.
+
+use evo_agent_sdk::prelude::*;
+use evo_kernel_agent_update::backend::{
+    Backends, FakeKing, FakeRegistry, FakeVcs, HttpRegistry, RegistryClient,
+};
+use evo_kernel_agent_update::config::ConfigStore;
+use evo_kernel_agent_update::fixtures::Fixtures;
+use evo_kernel_agent_update::osv::Vulnerability;
+use evo_kernel_agent_update::ownership::CrateOwners;
+use evo_kernel_agent_update::{UpdateHandler, builtin_config};
+use serde_json::{Value, json};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+
+/// Runs read their directories from the environment, so they take turns.
+static RUNS: Mutex<()> = Mutex::const_new(());
+
+// ─── Harness ──────────────────────────────────────────────────────────────────
+
+/// A fleet of temp repos and the fakes a run talks to.
+struct Harness {
+    dir: TempDir,
+    registry: Arc<dyn RegistryClient>,
+    vcs: Arc<FakeVcs>,
+    king: Arc<FakeKing>,
+    /// Environment variables set for the duration of each run.
+    env: Vec<(&'static str, String)>,
+}
+
+impl Harness {
+    /// One git repo per `(name, Cargo.toml)`, managed through a config file
+    /// tracking `evo-common`.
+    fn new(repos: &[(&str, &str)], registry: impl RegistryClient + 'static) -> Self {
+        let dir = TempDir::new().unwrap();
+        let mut config = String::from("tracked_crates = [\"evo-common\"]\n");
+        for (name, manifest) in repos {
+            let repo = dir.path().join("repos").join(name);
+            std::fs::create_dir_all(&repo).unwrap();
+            std::fs::write(repo.join("Cargo.toml"), manifest).unwrap();
+            git(&repo, &["init", "-q"]);
+            git(&repo, &["add", "-A"]);
+            git(&repo, &["commit", "-qm", "init"]);
+            config.push_str(&format!(
+                "\n[[repos]]\nrepo = \"{name}\"\ncargo_files = [\"Cargo.toml\"]\nworkflow_files = []\n"
+            ));
+        }
+        std::fs::write(dir.path().join("update-agent.toml"), config).unwrap();
+        Self {
+            dir,
+            registry: Arc::new(registry),
+            vcs: Arc::new(FakeVcs::default()),
+            king: Arc::new(FakeKing::default()),
+            env: Vec::new(),
+        }
+    }
+
+    /// Sets `name` to `value` while the handler runs.
+    fn with_env(mut self, name: &'static str, value: &str) -> Self {
+        self.env.push((name, value.to_string()));
+        self
+    }
+
+    /// Appends `toml` to the config file.
+    fn with_config(self, toml: &str) -> Self {
+        let path = self.dir.path().join("update-agent.toml");
+        let config = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, format!("{config}\n{toml}")).unwrap();
+        self
+    }
+
+    /// Runs the handler once with `metadata`.
+    async fn run(&self, metadata: Value) -> Value {
+        self.try_run(metadata).await.unwrap()
+    }
+
+    /// Runs the handler once with `metadata`, returning its error.
+    async fn try_run(&self, metadata: Value) -> anyhow::Result<Value> {
+        let _turn = RUNS.lock().await;
+        // SAFETY: runs hold `RUNS`, so no other test reads the environment.
+        unsafe {
+            std::env::set_var("KERNEL_AGENTS_DIR", self.dir.path().join("repos"));
+            std::env::set_var("UPDATE_STATE_DIR", self.dir.path().join("state"));
+            for (name, value) in &self.env {
+                std::env::set_var(name, value);
+            }
+        }
+        let config = ConfigStore::new(&self.dir.path().join("update-agent.toml"), builtin_config());
+        let handler = UpdateHandler::new(
+            Arc::new(config),
+            Backends {
+                registry: self.registry.clone(),
+                vcs: self.vcs.clone(),
+                king: self.king.clone(),
+            },
+        );
+        let soul = Soul {
+            role: "update".to_string(),
+            behavior: String::new(),
+        };
+        let ctx = PipelineContext {
+            run_id: "run-1".to_string(),
+            stage: "update".to_string(),
+            artifact_id: "artifact-1".to_string(),
+            metadata,
+            soul: &soul,
+            gateway: &GatewayClient,
+        };
+        let result = handler.on_pipeline(ctx).await;
+        // SAFETY: as above.
+        unsafe {
+            for (name, _) in &self.env {
+                std::env::remove_var(name);
+            }
+        }
+        result
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+// ─── Tests ───────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_full_run_commits_outdated_repos() {
+    let harness = Harness::new(
+        &[
+            (
+                "evo-king",
+                "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+            ),
+            (
+                "evo-runner",
+                "[package]\nname = \"evo-runner\"\n\n[dependencies]\nevo-common = \"0.4.0\"\n",
+            ),
+            (
+                "evo-gateway",
+                "[package]\nname = \"evo-gateway\"\n\n[dependencies]\nevo-common = \">=0.3, <0.5\"\n",
+            ),
+        ],
+        FakeRegistry::default()
+            .with_version("evo-common", "0.4.0")
+            .with_toolchain("1.95.0"),
+    );
+
+    let summary = harness.run(json!({})).await;
+
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].repo, "evo-king");
+    assert!(commits[0].files[0].1.contains("evo-common = \"0.4.0\""));
+    assert_eq!(summary["versions"]["evo-common"], "0.4.0");
+    assert_eq!(
+        summary["llm_usage"]["by_purpose"]["risk_analysis"]["calls"],
+        1
+    );
+    assert_eq!(summary["llm_usage"]["tokens_estimated"], true);
+    assert_eq!(summary["committed"][0]["bumps"][0]["bump_kind"], "major");
+    assert_eq!(summary["repos"][0]["status"], "updated");
+    assert_eq!(summary["repos"][1]["status"], "unchanged");
+    // The range already admits 0.4.0.
+    assert_eq!(summary["repos"][2]["status"], "unchanged");
+    assert_eq!(summary["config_synced"], true);
+    let paths: Vec<String> = harness
+        .king
+        .requests()
+        .into_iter()
+        .map(|(p, _)| p)
+        .collect();
+    assert!(paths.contains(&"/admin/config-sync".to_string()));
+}
+
+#[tokio::test]
+async fn test_dry_run_changes_nothing() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    );
+
+    let summary = harness.run(json!({ "dry_run": true })).await;
+
+    assert!(harness.vcs.commits().is_empty());
+    assert_eq!(summary["committed"][0]["dry_run"], true);
+    assert_eq!(summary["config_synced"], false);
+    assert!(harness.king.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_run_replays_registry_fixtures() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        HttpRegistry::new(
+            reqwest::Client::new(),
+            Some(Fixtures::replay(
+                &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/registry"),
+            )),
+        ),
+    );
+
+    let summary = harness.run(json!({})).await;
+
+    // The recorded index lists a yanked 0.4.1 and a 0.5.0 pre-release.
+    assert_eq!(summary["versions"]["evo-common"], "0.4.0");
+    assert_eq!(summary["checksum_failures"], json!([]));
+    assert_eq!(
+        summary["crate_links"]["evo-common"],
+        json!({
+            "repository": "https://github.com/ai-evo-agents/evo-common",
+            "documentation": "https://docs.rs/evo-common",
+        })
+    );
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert!(commits[0].files[0].1.contains("evo-common = \"0.4.0\""));
+    // The recorded OSV answer for 0.3.0 lists one advisory; 0.4.0 has none.
+    let osv = &summary["osv"][0];
+    assert_eq!(osv["fixed"][0]["id"], "GHSA-4x2q-7v6r-9m3c");
+    assert_eq!(osv["remaining"], json!([]));
+}
+
+#[tokio::test]
+async fn test_security_only_holds_plain_bumps() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    )
+    .with_config("[policy]\nauto_apply = \"security_only\"\n");
+
+    let summary = harness.run(json!({})).await;
+
+    assert!(harness.vcs.commits().is_empty());
+    assert_eq!(summary["auto_apply"], "security_only");
+    let held = &summary["held_for_review"][0];
+    assert_eq!(held["reason"], "security_only");
+    assert_eq!(held["bumps"][0]["latest"], "0.4.0");
+    assert_eq!(summary["repos"][0]["status"], "held");
+}
+
+#[tokio::test]
+async fn test_security_only_applies_osv_fixes() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default()
+            .with_version("evo-common", "0.4.0")
+            .with_vulnerability(
+                "evo-common",
+                "0.3.0",
+                Vulnerability {
+                    id: "RUSTSEC-2026-0042".to_string(),
+                    aliases: Vec::new(),
+                    summary: "Unbounded allocation".to_string(),
+                    severity: Some("high".to_string()),
+                },
+            ),
+    )
+    .with_config("[policy]\nauto_apply = \"security_only\"\n");
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(harness.vcs.commits().len(), 1);
+    assert_eq!(summary["held_for_review"], json!([]));
+    assert_eq!(summary["osv"][0]["fixed"][0]["id"], "RUSTSEC-2026-0042");
+    assert_eq!(summary["osv"][0]["from"], "0.3.0");
+}
+
+#[tokio::test]
+async fn test_commit_carries_upstream_log() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    );
+    let upstream = harness.dir.path().join("repos/evo-common");
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q"]);
+    for (subject, tag) in [
+        ("release 0.3.0", Some("v0.3.0")),
+        ("fix: reject oversized frames", None),
+        ("release 0.4.0", Some("v0.4.0")),
+    ] {
+        git(&upstream, &["commit", "-q", "--allow-empty", "-m", subject]);
+        if let Some(tag) = tag {
+            git(&upstream, &["tag", tag]);
+        }
+    }
+
+    let summary = harness.run(json!({})).await;
+
+    let changes = &summary["upstream_changes"][0];
+    assert_eq!(changes["range"], "v0.3.0..v0.4.0");
+    assert_eq!(changes["commits"], 2);
+    // The test gateway answers nothing, so the subjects are listed.
+    assert_eq!(changes["summarized"], false);
+    let message = &harness.vcs.commits()[0].message;
+    assert!(message.contains("evo-common 0.3.0 -> 0.4.0 upstream:"));
+    assert!(message.contains("- fix: reject oversized frames"));
+}
+
+#[tokio::test]
+async fn test_ownership_change_holds_crate_until_acknowledged() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default()
+            .with_version("evo-common", "0.4.0")
+            .with_owners(
+                "evo-common",
+                CrateOwners::new(["alice".to_string(), "mallory".to_string()], []),
+            ),
+    );
+    // An earlier run saw alice as the only owner.
+    let state = harness.dir.path().join("state");
+    std::fs::create_dir_all(&state).unwrap();
+    std::fs::write(
+        state.join("crate-owners.json"),
+        r#"{ "evo-common": { "owners": ["alice"], "publishers": [] } }"#,
+    )
+    .unwrap();
+
+    let summary = harness.run(json!({})).await;
+
+    assert!(harness.vcs.commits().is_empty());
+    assert_eq!(
+        summary["ownership_changes"][0]["added_owners"],
+        json!(["mallory"])
+    );
+    assert_eq!(summary["ownership_changes"][0]["notified"], true);
+    assert_eq!(
+        summary["skipped_by_policy"][0]["policy"],
+        "ownership_change"
+    );
+    let alerts: Vec<Value> = harness
+        .king
+        .requests()
+        .into_iter()
+        .filter(|(path, _)| path == "/admin/alerts")
+        .map(|(_, body)| body)
+        .collect();
+    assert_eq!(alerts[0]["kind"], "ownership_change");
+    assert_eq!(alerts[0]["severity"], "high");
+
+    let acknowledged = harness
+        .run(json!({ "action": "acknowledge_owners", "crate": "evo-common" }))
+        .await;
+    assert_eq!(acknowledged["acknowledged"], json!(["evo-common"]));
+
+    let summary = harness.run(json!({})).await;
+    assert_eq!(summary["ownership_changes"], json!([]));
+    assert_eq!(harness.vcs.commits().len(), 1);
+}
+
+#[tokio::test]
+async fn test_resolved_lockfile_flags_new_transitive_dependencies() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default()
+            .with_version("evo-common", "0.4.0")
+            .with_publisher("tinyframe", "0.1.2", "mallory"),
+    )
+    // Stands in for cargo resolving a new crate into the lockfile.
+    .with_config(
+        "[sandbox]\nenabled = true\npublish_checks = false\nverify = [\"printf '\\\\n[[package]]\\\\nname = \\\"tinyframe\\\"\\\\nversion = \\\"0.1.2\\\"\\\\nsource = \\\"registry+https://github.com/rust-lang/crates.io-index\\\"\\\\n' >> Cargo.lock\"]\n",
+    );
+    let repo = harness.dir.path().join("repos/evo-king");
+    std::fs::write(
+        repo.join("Cargo.lock"),
+        "version = 3\n\n[[package]]\nname = \"evo-common\"\nversion = \"0.3.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+    )
+    .unwrap();
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "lock"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(summary["sandbox"][0]["verified"], true);
+    assert_eq!(
+        summary["new_dependencies"],
+        json!([{
+            "repo": "evo-king",
+            "lockfile": "Cargo.lock",
+            "crate": "tinyframe",
+            "version": "0.1.2",
+            "publisher": "mallory",
+        }])
+    );
+    assert_eq!(harness.vcs.commits().len(), 1);
+}
+
+#[tokio::test]
+async fn test_patch_only_runs_skip_the_llm() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"=0.4.0\"\n",
+        )],
+        FakeRegistry::default().with_version("evo-common", "0.4.1"),
+    );
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(harness.vcs.commits().len(), 1);
+    assert_eq!(summary["llm_usage"]["skipped"], "patch_only");
+    assert_eq!(summary["llm_usage"]["calls"], 0);
+
+    let forced = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"=0.4.0\"\n",
+        )],
+        FakeRegistry::default().with_version("evo-common", "0.4.1"),
+    );
+    let summary = forced.run(json!({ "skip_llm": false })).await;
+    assert_eq!(summary["llm_usage"].get("skipped"), None);
+    assert_eq!(
+        summary["llm_usage"]["by_purpose"]["risk_analysis"]["calls"],
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_skip_llm_bypasses_the_analysis() {
+    let harness = Harness::new(
+        &[(
+            "evo-king",
+            "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n",
+        )],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    );
+
+    let summary = harness.run(json!({ "skip_llm": true })).await;
+
+    assert_eq!(harness.vcs.commits().len(), 1);
+    assert_eq!(summary["llm_usage"]["skipped"], "requested");
+    assert_eq!(summary["llm_usage"]["calls"], 0);
+    assert_eq!(
+        summary["analysis_summary"],
+        "LLM analysis skipped (requested)."
+    );
+}
+
+#[tokio::test]
+async fn test_python_agents_follow_pypi() {
+    let harness = Harness::new(
+        &[],
+        FakeRegistry::default().with_pypi_version("evo-agent-sdk-py", "0.4.2"),
+    )
+    .with_config("[[repos]]\nrepo = \"weather-agent\"\npython_files = [\"pyproject.toml\"]\n");
+    let repo = harness.dir.path().join("repos/weather-agent");
+    std::fs::create_dir_all(&repo).unwrap();
+    std::fs::write(
+        repo.join("pyproject.toml"),
+        "[project]\nname = \"weather-agent\"\ndependencies = [\n    \"evo-agent-sdk-py>=0.3.1,<0.4\",\n    \"httpx>=0.27\",\n]\n",
+    )
+    .unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(summary["versions"]["evo-agent-sdk-py"], "0.4.2");
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].repo, "weather-agent");
+    assert_eq!(
+        commits[0].files,
+        vec![(
+            "pyproject.toml".to_string(),
+            "[project]\nname = \"weather-agent\"\ndependencies = [\n    \"evo-agent-sdk-py>=0.4.2,<0.5\",\n    \"httpx>=0.27\",\n]\n".to_string(),
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_package_json_follows_npm() {
+    let harness = Harness::new(
+        &[],
+        FakeRegistry::default().with_npm_version("@evo/agent-client", "0.5.2"),
+    )
+    .with_config("[[repos]]\nrepo = \"evo-dashboard\"\nnpm_files = [\"package.json\"]\n");
+    let repo = harness.dir.path().join("repos/evo-dashboard");
+    std::fs::create_dir_all(&repo).unwrap();
+    let manifest = "{\n  \"name\": \"evo-dashboard\",\n  \"dependencies\": {\n    \"@evo/agent-client\": \"^0.3.1\",\n    \"react\": \"^18.2.0\"\n  }\n}\n";
+    std::fs::write(repo.join("package.json"), manifest).unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(summary["versions"]["@evo/agent-client"], "0.5.2");
+    assert_eq!(
+        summary["version_freshness"]["@evo/agent-client"]["source"],
+        "registry.npmjs.org"
+    );
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(
+        commits[0].files,
+        vec![(
+            "package.json".to_string(),
+            manifest.replace("^0.3.1", "^0.5.2"),
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_go_mod_follows_the_module_proxy() {
+    let module = "github.com/ai-evo-agents/evo-agent-sdk-go";
+    let harness = Harness::new(&[], FakeRegistry::default().with_go_version(module, "0.5.0"))
+        .with_config(&format!(
+            "tracked_go_modules = [\"{module}\"]\n\n[[repos]]\nrepo = \"evo-go-agent\"\ngo_files = [\"go.mod\"]\n"
+        ));
+    let repo = harness.dir.path().join("repos/evo-go-agent");
+    std::fs::create_dir_all(&repo).unwrap();
+    let go_mod = format!(
+        "module github.com/ai-evo-agents/evo-go-agent\n\ngo 1.22\n\nrequire (\n\t{module} v0.3.1\n\tgolang.org/x/sync v0.6.0 // indirect\n)\n"
+    );
+    std::fs::write(repo.join("go.mod"), &go_mod).unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(summary["versions"][module], "0.5.0");
+    assert_eq!(
+        summary["version_freshness"][module]["source"],
+        "proxy.golang.org"
+    );
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(
+        commits[0].files,
+        vec![("go.mod".to_string(), go_mod.replace("v0.3.1", "v0.5.0"))]
+    );
+}
+
+#[tokio::test]
+async fn test_schema_files_follow_evo_common() {
+    let harness = Harness::new(&[], FakeRegistry::default())
+        .with_config("[[repos]]\nrepo = \"evo-runner\"\nschema_dir = \"proto\"\n");
+    let source = harness.dir.path().join("repos/evo-common/schemas");
+    std::fs::create_dir_all(source.join("events")).unwrap();
+    let agent_proto = "syntax = \"proto3\";\nmessage Agent { string id = 1; }\n";
+    std::fs::write(source.join("agent.proto"), agent_proto).unwrap();
+    std::fs::write(source.join("events/run.json"), "{\"type\": \"object\"}\n").unwrap();
+    let repo = harness.dir.path().join("repos/evo-runner");
+    std::fs::create_dir_all(repo.join("proto")).unwrap();
+    std::fs::write(
+        repo.join("Cargo.toml"),
+        "[package]\nname = \"evo-runner\"\n",
+    )
+    .unwrap();
+    std::fs::write(repo.join("proto/agent.proto"), "syntax = \"proto3\";\n").unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(
+        summary["schema_sync"]["files"],
+        json!(["agent.proto", "events/run.json"])
+    );
+    assert_eq!(
+        summary["schema_sync"]["synced"],
+        json!([
+            { "repo": "evo-runner", "file": "proto/agent.proto" },
+            { "repo": "evo-runner", "file": "proto/events/run.json" },
+        ])
+    );
+    let mut files: Vec<(String, String)> = harness
+        .vcs
+        .commits()
+        .into_iter()
+        .flat_map(|c| c.files)
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            ("proto/agent.proto".to_string(), agent_proto.to_string()),
+            (
+                "proto/events/run.json".to_string(),
+                "{\"type\": \"object\"}\n".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_file_sync_campaigns() {
+    let harness = Harness::new(&[], FakeRegistry::default()).with_config(
+        "[[file_sync]]\nname = \"lint-config\"\nfiles = [\"rustfmt.toml\", \".editorconfig\"]\n\n\
+         [[file_sync]]\nname = \"deny\"\nfiles = [\"deny.toml\"]\nmode = \"check\"\n\n\
+         [[repos]]\nrepo = \"evo-runner\"\n",
+    );
+    let source = harness.dir.path().join("repos/evo-common");
+    std::fs::create_dir_all(&source).unwrap();
+    std::fs::write(source.join("rustfmt.toml"), "edition = \"2024\"\n").unwrap();
+    std::fs::write(source.join(".editorconfig"), "root = true\n").unwrap();
+    std::fs::write(
+        source.join("deny.toml"),
+        "[bans]\nmultiple-versions = \"deny\"\n",
+    )
+    .unwrap();
+    let repo = harness.dir.path().join("repos/evo-runner");
+    std::fs::create_dir_all(&repo).unwrap();
+    std::fs::write(repo.join("rustfmt.toml"), "edition = \"2021\"\n").unwrap();
+    std::fs::write(
+        repo.join(".evo-update.toml"),
+        "file_sync_opt_out = [\".editorconfig\"]\n",
+    )
+    .unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(
+        summary["file_sync"][0]["files"],
+        json!([".editorconfig", "rustfmt.toml"])
+    );
+    assert_eq!(
+        summary["file_drift"],
+        json!([
+            { "campaign": "lint-config", "repo": "evo-runner", "file": "rustfmt.toml", "mode": "sync" },
+            { "campaign": "deny", "repo": "evo-runner", "file": "deny.toml", "mode": "check" },
+        ])
+    );
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(
+        commits[0].files,
+        vec![(
+            "rustfmt.toml".to_string(),
+            "edition = \"2024\"\n".to_string()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_template_drift_is_reported() {
+    let harness = Harness::new(&[], FakeRegistry::default()).with_config(
+        "[template]\nopen_prs = true\n\n[[repos]]\nrepo = \"evo-weather-agent\"\nfrom_template = true\n",
+    );
+    let template = harness.dir.path().join("repos/evo-user-agent-template");
+    std::fs::create_dir_all(template.join(".github/workflows")).unwrap();
+    std::fs::create_dir_all(template.join("src")).unwrap();
+    std::fs::write(
+        template.join(".github/workflows/ci.yml"),
+        "on: [push, pull_request]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        template.join("Cargo.toml"),
+        "[package]\nname = \"evo-user-agent\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        template.join("src/main.rs"),
+        "fn main() {\n    // evo:scaffold health-check\n}\n",
+    )
+    .unwrap();
+    let repo = harness.dir.path().join("repos/evo-weather-agent");
+    std::fs::create_dir_all(repo.join(".github/workflows")).unwrap();
+    std::fs::create_dir_all(repo.join("src")).unwrap();
+    std::fs::write(repo.join(".github/workflows/ci.yml"), "on: [push]\n").unwrap();
+    std::fs::write(
+        repo.join("Cargo.toml"),
+        "[package]\nname = \"evo-weather-agent\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(repo.join("src/main.rs"), "fn main() {}\n").unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({ "dry_run": true })).await;
+
+    assert_eq!(
+        summary["template_drift"],
+        json!([
+            { "repo": "evo-weather-agent", "file": ".github/workflows/ci.yml", "kind": "file" },
+            { "repo": "evo-weather-agent", "file": "src/main.rs", "kind": "scaffold",
+              "details": ["// evo:scaffold health-check"] },
+        ])
+    );
+    assert_eq!(
+        summary["template_prs"][0]["files"],
+        json!([".github/workflows/ci.yml"])
+    );
+    assert_eq!(summary["template_prs"][0]["pr"], "dry_run");
+    assert!(harness.vcs.commits().is_empty());
+}
+
+#[tokio::test]
+async fn test_workflows_are_rendered_from_templates() {
+    let harness = Harness::new(
+        &[],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    )
+    .with_config(
+        "[[repos]]\nrepo = \"evo-runner\"\n\
+         workflow_files = [\".github/workflows/ci.yml\"]\n\
+         workflow_templates = [\"ci.yml\", \"release.yml\"]\n\
+         workflow_params = { features = \"full\" }\n",
+    );
+    let templates = harness
+        .dir
+        .path()
+        .join("repos/evo-common/workflow-templates");
+    std::fs::create_dir_all(&templates).unwrap();
+    std::fs::write(
+        templates.join("ci.yml"),
+        "on: push\njobs:\n  test:\n    if: ${{ github.ref == 'refs/heads/main' }}\n    steps:\n      \
+         - run: cargo test -p {{ evo.repo }} --features {{ evo.features }}\n      \
+         - run: sed -i 's|evo-common = .*|evo-common = \"{{evo.crate.evo-common:minor}}\"|' Cargo.toml\n",
+    )
+    .unwrap();
+    let repo = harness.dir.path().join("repos/evo-runner");
+    std::fs::create_dir_all(repo.join(".github/workflows")).unwrap();
+    std::fs::write(
+        repo.join(".github/workflows/ci.yml"),
+        "on: push\njobs:\n  test:\n    steps:\n      \
+         - run: sed -i 's|evo-common = .*|evo-common = \"0.3\"|' Cargo.toml\n",
+    )
+    .unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "-A"]);
+    git(&repo, &["commit", "-qm", "init"]);
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(summary["workflow_sync"]["templates"], json!(["ci.yml"]));
+    assert_eq!(
+        summary["workflow_sync"]["synced"],
+        json!([{ "repo": "evo-runner", "file": ".github/workflows/ci.yml" }])
+    );
+    assert_eq!(
+        summary["workflow_sync"]["failed"][0]["file"],
+        ".github/workflows/release.yml"
+    );
+    // The rendered file is not also bumped through its `sed` pattern.
+    assert_eq!(summary["workflow_patterns"], json!([]));
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    let (file, content) = &commits[0].files[0];
+    assert_eq!(file, ".github/workflows/ci.yml");
+    assert!(content.contains("if: ${{ github.ref == 'refs/heads/main' }}\n"));
+    assert!(content.contains("cargo test -p evo-runner --features full\n"));
+    assert!(content.contains("evo-common = \"0.4\""));
+}
+
+#[tokio::test]
+async fn test_license_policy_fixes_headers_and_texts() {
+    let harness = Harness::new(&[], FakeRegistry::default()).with_config(
+        "[license]\nexpression = \"MIT OR Apache-2.0\"\nfiles = [\"LICENSE\"]\n\n\
+         [[repos]]\nrepo = \"evo-runner\"\n\n[[repos]]\nrepo = \"evo-legacy\"\nlicense_exempt = true\n",
+    );
+    let source = harness.dir.path().join("repos/evo-common");
+    std::fs::create_dir_all(&source).unwrap();
+    std::fs::write(source.join("LICENSE"), "MIT OR Apache-2.0\n").unwrap();
+    for name in ["evo-runner", "evo-legacy"] {
+        let repo = harness.dir.path().join("repos").join(name);
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::create_dir_all(repo.join("target")).unwrap();
+        std::fs::write(repo.join("LICENSE"), "MIT\n").unwrap();
+        std::fs::write(repo.join("src/lib.rs"), "pub mod run;\n").unwrap();
+        std::fs::write(
+            repo.join("src/run.rs"),
+            "// SPDX-License-Identifier: MIT OR Apache-2.0\npub fn run() {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            repo.join("src/main.rs"),
+            "// SPDX-License-Identifier: GPL-3.0\nfn main() {}\n",
+        )
+        .unwrap();
+        std::fs::write(repo.join("target/out.rs"), "").unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-qm", "init"]);
+    }
+
+    let summary = harness.run(json!({})).await;
+
+    assert_eq!(summary["license"]["files"], json!(["LICENSE"]));
+    assert_eq!(
+        summary["license_drift"],
+        json!([
+            { "issue": "missing_header", "repo": "evo-runner", "file": "src/lib.rs", "mode": "sync" },
+            { "issue": "wrong_header", "found": "GPL-3.0", "repo": "evo-runner", "file": "src/main.rs", "mode": "sync" },
+            { "issue": "license_file", "repo": "evo-runner", "file": "LICENSE", "mode": "sync" },
+        ])
+    );
+    let mut files: Vec<(String, String)> = harness
+        .vcs
+        .commits()
+        .into_iter()
+        .flat_map(|commit| commit.files)
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![
+            ("LICENSE".to_string(), "MIT OR Apache-2.0\n".to_string()),
+            (
+                "src/lib.rs".to_string(),
+                "// SPDX-License-Identifier: MIT OR Apache-2.0\npub mod run;\n".to_string()
+            ),
+            (
+                "src/main.rs".to_string(),
+                "// SPDX-License-Identifier: MIT OR Apache-2.0\nfn main() {}\n".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_retry_sends_queued_commits_through_the_gates() {
+    let manifest = "[package]\nname = \"x\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n";
+    let bumped = manifest.replace("0.3.0", "0.4.0");
+    let harness = Harness::new(
+        &[("evo-king", manifest), ("evo-frozen", manifest)],
+        FakeRegistry::default(),
+    )
+    .with_config(
+        "frozen = true\n\n[[repos]]\nrepo = \"evo-remote\"\ncargo_files = [\"Cargo.toml\"]\nworkflow_files = []\n\n[policy]\ndeny_crates = [\"openssl\"]\n",
+    );
+    // `evo-remote` has no checkout: its file is read from the default branch.
+    harness.vcs.set_file("evo-remote", "Cargo.toml", manifest);
+    let queued = |repo: &str, file: &str, crates: &[&str]| {
+        json!({
+            "repo": repo,
+            "files": [{ "path": file, "original": manifest, "content": bumped }],
+            "message": format!("chore(deps): bump {repo}"),
+            "run_id": "run-0",
+            "attempts": 1,
+            "last_error": "rate limited",
+            "queued_at": 1,
+            "crates": crates,
+        })
+    };
+    let state = harness.dir.path().join("state");
+    std::fs::create_dir_all(&state).unwrap();
+    std::fs::write(
+        state.join("retry-queue.json"),
+        json!([
+            queued("evo-king", "Cargo.toml", &["evo-common"]),
+            queued("evo-frozen", "Cargo.toml", &["evo-common"]),
+            queued("evo-king", "sub/Cargo.toml", &["openssl"]),
+            queued("evo-remote", "Cargo.toml", &["evo-common"]),
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let summary = harness.run(json!({ "action": "retry" })).await;
+
+    let outcome = |repo: &str, file: &str| {
+        summary["retried"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["repo"] == repo && e["files"][0] == file)
+            .map(|e| (e["outcome"].clone(), e["reason"].clone()))
+            .unwrap()
+    };
+    assert_eq!(outcome("evo-king", "Cargo.toml").0, "committed");
+    assert_eq!(outcome("evo-remote", "Cargo.toml").0, "committed");
+    assert_eq!(
+        outcome("evo-frozen", "Cargo.toml"),
+        (json!("held"), json!("frozen"))
+    );
+    assert_eq!(outcome("evo-king", "sub/Cargo.toml").0, "skipped_by_policy");
+    let repos: Vec<String> = harness.vcs.commits().into_iter().map(|c| c.repo).collect();
+    assert_eq!(repos, vec!["evo-king", "evo-remote"]);
+    // Only the frozen repo's commit waits for a later run.
+    assert_eq!(summary["queued"], 1);
+}
+
+#[tokio::test]
+async fn test_apply_delivers_the_plan_without_rescanning() {
+    let manifest = "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n";
+    let harness = Harness::new(
+        &[("evo-king", manifest)],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    )
+    .with_env("PLAN_SIGNING_KEY", "s3cret");
+
+    let planned = harness.run(json!({ "action": "plan" })).await;
+    let plan_id = planned["plan"]["plan_id"].as_str().unwrap().to_string();
+    assert!(harness.vcs.commits().is_empty());
+
+    // The drift check reads the default branch through the VCS backend.
+    harness.vcs.set_file("evo-king", "Cargo.toml", manifest);
+    let summary = harness
+        .run(json!({ "action": "apply", "plan_id": plan_id }))
+        .await;
+
+    let commits = harness.vcs.commits();
+    assert_eq!(commits.len(), 1);
+    assert!(commits[0].files[0].1.contains("evo-common = \"0.4.0\""));
+    assert_eq!(summary["applied_plan"], plan_id);
+    assert_eq!(summary["versions"]["evo-common"], "0.4.0");
+    let phases: Vec<&str> = summary["timings"]["phases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["phase"].as_str().unwrap())
+        .collect();
+    for skipped in ["check_versions", "scan", "changelogs", "verification"] {
+        assert!(!phases.contains(&skipped), "{skipped} ran: {phases:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_apply_refuses_unsigned_plans_without_opt_in() {
+    let manifest = "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n";
+    let harness = Harness::new(
+        &[("evo-king", manifest)],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    );
+    let planned = harness.run(json!({ "action": "plan" })).await;
+    let plan_id = planned["plan"]["plan_id"].as_str().unwrap().to_string();
+    harness.vcs.set_file("evo-king", "Cargo.toml", manifest);
+
+    let err = harness
+        .try_run(json!({ "action": "apply", "plan_id": plan_id }))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("PLAN_SIGNING_KEY is not set"));
+    assert!(harness.vcs.commits().is_empty());
+
+    let opted_in = Harness {
+        env: vec![("ALLOW_UNSIGNED_PLANS", "1".to_string())],
+        ..harness
+    };
+    let summary = opted_in
+        .run(json!({ "action": "apply", "plan_id": plan_id }))
+        .await;
+    assert_eq!(summary["applied_plan"], plan_id);
+    assert_eq!(opted_in.vcs.commits().len(), 1);
+}
+
+#[tokio::test]
+async fn test_answer_stays_open_when_delivery_fails() {
+    let manifest = "[package]\nname = \"evo-king\"\n\n[dependencies]\nevo-common = \"0.3.0\"\n";
+    let harness = Harness::new(
+        &[("evo-king", manifest)],
+        FakeRegistry::default().with_version("evo-common", "0.4.0"),
+    )
+    .with_env("PLAN_SIGNING_KEY", "s3cret");
+    let planned = harness.run(json!({ "action": "plan" })).await;
+    let plan_id = planned["plan"]["plan_id"].as_str().unwrap().to_string();
+    harness.vcs.set_file("evo-king", "Cargo.toml", manifest);
+    let approvals = harness.dir.path().join("state").join("approvals.json");
+    std::fs::write(
+        &approvals,
+        json!({ "open": [[plan_id, []]], "rejected": [] }).to_string(),
+    )
+    .unwrap();
+    let answer = json!({ "action": "answer", "question_id": plan_id, "decision": "approve" });
+    let is_open = || {
+        let store: Value =
+            serde_json::from_str(&std::fs::read_to_string(&approvals).unwrap()).unwrap();
+        store["open"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|q| q[0] == plan_id)
+    };
+
+    // Another replica holds the run lease: nothing is delivered.
+    harness.king.respond(
+        "/admin/lease/acquire",
+        reqwest::StatusCode::CONFLICT,
+        r#"{"holder": "replica-2"}"#,
+    );
+    let leased = Harness {
+        env: vec![
+            ("PLAN_SIGNING_KEY", "s3cret".to_string()),
+            ("UPDATE_LEASE", "1".to_string()),
+        ],
+        ..harness
+    };
+    leased.run(answer.clone()).await;
+    assert!(leased.vcs.commits().is_empty());
+    assert!(is_open(), "a failed delivery closed the question");
+
+    // Answered again once the lease is free, the plan lands and the
+    // question closes.
+    let harness = Harness {
+        env: vec![("PLAN_SIGNING_KEY", "s3cret".to_string())],
+        ..leased
+    };
+    harness.run(answer).await;
+    assert_eq!(harness.vcs.commits().len(), 1);
+    assert!(!is_open());
+}